@@ -0,0 +1,81 @@
+// Creates the signed release manifest (latest.json + latest.json.sig) read by the in-app updater.
+//
+//   node scripts/update-manifest.mjs keygen update-key.pem
+//   node scripts/update-manifest.mjs sign --key update-key.pem --version 1.2.0 \
+//     --base-url https://github.com/<owner>/<repo>/releases/download/v1.2.0 \
+//     [--notes-file notes.md] [--out dist-update] windows-x86_64=path/to/setup.msi ...
+//
+// The private key can also come from the APEX_UPDATE_PRIVATE_KEY environment variable (PEM text).
+// Build the app with APEX_UPDATE_PUBLIC_KEY set to the value printed by keygen.
+import { createHash, createPrivateKey, createPublicKey, generateKeyPairSync, sign } from 'node:crypto';
+import { createReadStream, mkdirSync, readFileSync, statSync, writeFileSync } from 'node:fs';
+import { basename, join } from 'node:path';
+
+const fail = (text) => {
+    console.error(text);
+    process.exit(1);
+};
+
+// The updater expects the raw 32-byte key, base64 encoded
+const rawPublicKey = (key) => Buffer.from(createPublicKey(key).export({ format: 'jwk' }).x, 'base64url').toString('base64');
+
+const sha256 = (file) => new Promise((resolve, reject) => {
+    const hash = createHash('sha256');
+    createReadStream(file).on('data', (chunk) => hash.update(chunk)).on('end', () => resolve(hash.digest('hex'))).on('error', reject);
+});
+
+const parseArgs = (args) => {
+    const options = {};
+    const assets = [];
+    for (let i = 0; i < args.length; i++) {
+        if (args[i].startsWith('--')) options[args[i].slice(2)] = args[++i];
+        else assets.push(args[i]);
+    }
+    return { options, assets };
+};
+
+const keygen = (target) => {
+    if (!target) fail('Usage: update-manifest.mjs keygen <private-key.pem>');
+    const { privateKey } = generateKeyPairSync('ed25519');
+    writeFileSync(target, privateKey.export({ format: 'pem', type: 'pkcs8' }), { mode: 0o600 });
+    console.log(`Private key written to ${target}. Keep it out of the repository.`);
+    console.log(`APEX_UPDATE_PUBLIC_KEY=${rawPublicKey(privateKey)}`);
+};
+
+const signManifest = async (args) => {
+    const { options, assets } = parseArgs(args);
+    const pem = options.key ? readFileSync(options.key, 'utf8') : process.env.APEX_UPDATE_PRIVATE_KEY;
+    if (!pem) fail('Pass --key or set APEX_UPDATE_PRIVATE_KEY');
+    if (!options.version || !options['base-url'] || assets.length === 0) {
+        fail('Usage: update-manifest.mjs sign --key <pem> --version <x.y.z> --base-url <url> <platform>=<installer> ...');
+    }
+    const privateKey = createPrivateKey(pem);
+    const baseUrl = options['base-url'].replace(/\/+$/, '');
+
+    const platforms = {};
+    for (const asset of assets) {
+        const [platform, file] = asset.split('=');
+        if (!platform || !file) fail(`Expected <platform>=<installer>, got ${asset}`);
+        // Release assets are served under URL-safe names; match how GitHub renames spaces
+        const name = basename(file).replace(/ /g, '.');
+        platforms[platform] = { url: `${baseUrl}/${encodeURIComponent(name)}`, name, size: statSync(file).size, sha256: await sha256(file) };
+    }
+
+    const manifest = {
+        version: options.version.replace(/^v/, ''),
+        notes: options['notes-file'] ? readFileSync(options['notes-file'], 'utf8') : '',
+        pubDate: new Date().toISOString(),
+        platforms,
+    };
+    const bytes = Buffer.from(JSON.stringify(manifest, null, 2));
+    const outDir = options.out || '.';
+    mkdirSync(outDir, { recursive: true });
+    writeFileSync(join(outDir, 'latest.json'), bytes);
+    writeFileSync(join(outDir, 'latest.json.sig'), sign(null, bytes, privateKey).toString('base64'));
+    console.log(`Signed manifest for ${manifest.version} written to ${outDir} (public key ${rawPublicKey(privateKey)})`);
+};
+
+const [command, ...rest] = process.argv.slice(2);
+if (command === 'keygen') keygen(rest[0]);
+else if (command === 'sign') await signManifest(rest);
+else fail('Usage: update-manifest.mjs keygen|sign ...');
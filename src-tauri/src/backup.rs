@@ -0,0 +1,359 @@
+use crate::{db, device};
+use chrono::{Datelike, Local, NaiveDateTime};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Setting holding the backup folder chosen on the settings page.
+pub const BACKUP_PATH_SETTING: &str = "backup_path";
+const SNAPSHOT_PREFIX: &str = "invoices-";
+/// Plain database snapshot.
+pub const SNAPSHOT_SUFFIX: &str = ".db";
+/// Backup bundle produced by bundle.rs; shares the naming and retention of plain snapshots.
+pub const BUNDLE_SUFFIX: &str = ".zip";
+/// Backup bundle encrypted with the backup passphrase (see crypto.rs).
+pub const ENCRYPTED_BUNDLE_SUFFIX: &str = ".zip.enc";
+const SNAPSHOT_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+const SNAPSHOT_TIME_LEN: usize = "20240703-101500".len();
+const DEVICE_TAG_LEN: usize = 8;
+
+/// Grandfather-father-son retention: keep the newest snapshot of each of the last
+/// `daily` days, `weekly` ISO weeks and `monthly` months. All zeros keeps everything.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    pub daily: u32,
+    pub weekly: u32,
+    pub monthly: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        RetentionPolicy { daily: 7, weekly: 4, monthly: 12 }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupSnapshot {
+    pub path: String,
+    pub created_at: String,
+    pub pruned: Vec<String>,
+}
+
+/// Copies a live database through SQLite's online backup API, so pages written by
+/// other connections mid-copy are picked up instead of producing a torn file.
+pub fn snapshot_database(source: &Path, target: &Path) -> Result<(), String> {
+    let src = db::open_path(source)?;
+
+    // Write next to the target first so a failed backup never replaces a good file
+    let partial = target.with_extension("partial");
+    if partial.exists() {
+        fs::remove_file(&partial).map_err(|e| e.to_string())?;
+    }
+    {
+        let mut dst = Connection::open(&partial).map_err(|e| e.to_string())?;
+        let backup = Backup::new(&src, &mut dst).map_err(|e| e.to_string())?;
+        backup
+            .run_to_completion(256, Duration::from_millis(25), None)
+            .map_err(|e| e.to_string())?;
+        drop(backup);
+        // The live database runs in WAL mode; a snapshot should be a single self-contained file
+        dst.pragma_update(None, "journal_mode", "DELETE").map_err(|e| e.to_string())?;
+    }
+    fs::rename(&partial, target).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Short form of a device ID put into snapshot names, so each device can tell its own backups
+/// apart in a folder other devices write to as well.
+pub fn device_tag(device_id: &str) -> &str {
+    device_id.get(..DEVICE_TAG_LEN).unwrap_or(device_id)
+}
+
+/// `invoices-20240703-101500-1a2b3c4d.zip`; without a device the tag is left out.
+pub fn snapshot_file_name(time: NaiveDateTime, device_id: Option<&str>, suffix: &str) -> String {
+    let stamp = time.format(SNAPSHOT_TIME_FORMAT);
+    match device_id {
+        Some(id) => format!("{}{}-{}{}", SNAPSHOT_PREFIX, stamp, device_tag(id), suffix),
+        None => format!("{}{}{}", SNAPSHOT_PREFIX, stamp, suffix),
+    }
+}
+
+/// Parses the timestamp and device tag back out of a snapshot file name; `None` for anything
+/// else in the folder. Snapshots taken before names carried a device have no tag.
+pub fn parse_snapshot(name: &str) -> Option<(NaiveDateTime, Option<&str>)> {
+    let stem = name.strip_prefix(SNAPSHOT_PREFIX)?;
+    let stem = [SNAPSHOT_SUFFIX, BUNDLE_SUFFIX, ENCRYPTED_BUNDLE_SUFFIX]
+        .iter()
+        .find_map(|suffix| stem.strip_suffix(suffix))?;
+    let stamp = stem.get(..SNAPSHOT_TIME_LEN)?;
+    let tag = match &stem[SNAPSHOT_TIME_LEN..] {
+        "" => None,
+        rest => Some(rest.strip_prefix('-').filter(|tag| !tag.is_empty() && tag.bytes().all(|b| b.is_ascii_hexdigit()))?),
+    };
+    let time = NaiveDateTime::parse_from_str(stamp, SNAPSHOT_TIME_FORMAT).ok()?;
+    Some((time, tag))
+}
+
+pub fn parse_snapshot_name(name: &str) -> Option<NaiveDateTime> {
+    parse_snapshot(name).map(|(time, _)| time)
+}
+
+/// Whether a snapshot was taken by this device. Untagged ones could have come from any device.
+pub fn is_own_snapshot(name: &str, device_id: &str) -> bool {
+    parse_snapshot(name).and_then(|(_, tag)| tag) == Some(device_tag(device_id))
+}
+
+/// Whether a file name is a database backup: a timestamped snapshot or the legacy single `invoices.db`.
+pub fn is_backup_file_name(name: &str) -> bool {
+    name == db::DB_FILE_NAME || parse_snapshot_name(name).is_some()
+}
+
+/// Picks the most recent backup among file names; a legacy `invoices.db` loses to any snapshot.
+pub fn latest_backup_name<'a, I: IntoIterator<Item = &'a str>>(names: I) -> Option<&'a str> {
+    names
+        .into_iter()
+        .filter(|name| is_backup_file_name(name))
+        .max_by_key(|name| parse_snapshot_name(name))
+}
+
+/// Lists the snapshots in a folder, newest first.
+pub fn list_snapshots(dir: &Path) -> Result<Vec<(NaiveDateTime, PathBuf)>, String> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let time = path.file_name().and_then(|n| n.to_str()).and_then(parse_snapshot_name);
+        if let Some(time) = time {
+            snapshots.push((time, path));
+        }
+    }
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.0));
+    Ok(snapshots)
+}
+
+/// The backup folder chosen in the settings, if any.
+pub fn configured_dir(conn: &Connection) -> Result<Option<PathBuf>, String> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [BACKUP_PATH_SETTING], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .flatten();
+    Ok(value.filter(|dir| !dir.is_empty()).map(PathBuf::from))
+}
+
+/// Checks that a path handed over by the frontend is a backup directly inside `backup_dir`
+/// before it is read and sent anywhere, and returns it resolved.
+pub fn backup_in_dir(path: &Path, backup_dir: &Path) -> Result<PathBuf, String> {
+    let name = path.file_name().and_then(|n| n.to_str()).ok_or("Invalid backup path")?;
+    if !is_backup_file_name(name) {
+        return Err("Not an invoice backup file".to_string());
+    }
+    let dir = fs::canonicalize(backup_dir).map_err(|e| format!("Backup folder not found: {}", e))?;
+    let path = fs::canonicalize(path).map_err(|e| format!("Backup not found: {}", e))?;
+    if path.parent() != Some(dir.as_path()) || !path.is_file() {
+        return Err("The backup is not in the backup folder".to_string());
+    }
+    Ok(path)
+}
+
+/// [`backup_in_dir`] against the backup folder in the app's settings.
+pub fn configured_backup(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
+    let dir = configured_dir(&db::open(app_handle)?)?.ok_or("Choose a backup folder first.")?;
+    backup_in_dir(Path::new(path), &dir)
+}
+
+/// Picks which snapshots to keep under a policy. Input must be sorted newest first.
+pub fn snapshots_to_keep(snapshots: &[(NaiveDateTime, PathBuf)], policy: &RetentionPolicy) -> HashSet<PathBuf> {
+    let mut keep = HashSet::new();
+    if policy.daily == 0 && policy.weekly == 0 && policy.monthly == 0 {
+        keep.extend(snapshots.iter().map(|(_, p)| p.clone()));
+        return keep;
+    }
+    // The latest snapshot always survives, whatever the policy says
+    if let Some((_, newest)) = snapshots.first() {
+        keep.insert(newest.clone());
+    }
+
+    let mut keep_per_bucket = |limit: u32, bucket: &dyn Fn(&NaiveDateTime) -> (i32, u32)| {
+        let mut seen = HashSet::new();
+        for (time, path) in snapshots {
+            if seen.len() >= limit as usize {
+                break;
+            }
+            if seen.insert(bucket(time)) {
+                keep.insert(path.clone());
+            }
+        }
+    };
+    keep_per_bucket(policy.daily, &|t| (t.year(), t.ordinal()));
+    keep_per_bucket(policy.weekly, &|t| (t.iso_week().year(), t.iso_week().week()));
+    keep_per_bucket(policy.monthly, &|t| (t.year(), t.month()));
+    keep
+}
+
+/// Deletes this device's snapshots and bundles in `dir` that fall outside the retention policy.
+/// The folder may be synced with other devices; their backups, and untagged ones from before
+/// names carried a device, are left alone.
+pub fn prune(dir: &Path, policy: &RetentionPolicy, device_id: &str) -> Result<Vec<String>, String> {
+    let snapshots: Vec<_> = list_snapshots(dir)?
+        .into_iter()
+        .filter(|(_, path)| path.file_name().and_then(|n| n.to_str()).is_some_and(|name| is_own_snapshot(name, device_id)))
+        .collect();
+    let keep = snapshots_to_keep(&snapshots, policy);
+    let mut pruned = Vec::new();
+    for (_, path) in snapshots.iter().filter(|(_, p)| !keep.contains(p)) {
+        fs::remove_file(path).map_err(|e| e.to_string())?;
+        pruned.push(path.to_string_lossy().to_string());
+    }
+    Ok(pruned)
+}
+
+/// Takes a timestamped snapshot into `target_dir` and prunes older ones per the policy.
+pub fn create_snapshot(source: &Path, target_dir: &Path, policy: &RetentionPolicy, device_id: &str) -> Result<BackupSnapshot, String> {
+    fs::create_dir_all(target_dir).map_err(|e| e.to_string())?;
+    let now = Local::now().naive_local();
+    let target = target_dir.join(snapshot_file_name(now, Some(device_id), SNAPSHOT_SUFFIX));
+    snapshot_database(source, &target)?;
+    let pruned = prune(target_dir, policy, device_id)?;
+
+    Ok(BackupSnapshot {
+        path: target.to_string_lossy().to_string(),
+        created_at: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        pruned,
+    })
+}
+
+#[tauri::command]
+pub async fn create_backup_snapshot(
+    app_handle: tauri::AppHandle,
+    target_dir: String,
+    retention: Option<RetentionPolicy>,
+) -> Result<BackupSnapshot, String> {
+    let source = db::db_path(&app_handle)?;
+    if !source.exists() {
+        return Err("Database file not found".to_string());
+    }
+    // Note: target_dir comes from a system dialog (Safe), same as export_database
+    let device = device::this_device(&app_handle)?;
+    create_snapshot(&source, Path::new(&target_dir), &retention.unwrap_or_default(), &device.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(stamp: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    /// Snapshots named after their timestamps, newest first, and the stamps `policy` keeps.
+    fn kept(stamps: &[&str], policy: RetentionPolicy) -> Vec<String> {
+        let mut snapshots: Vec<_> = stamps.iter().map(|s| (at(s), PathBuf::from(*s))).collect();
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.0));
+        let keep = snapshots_to_keep(&snapshots, &policy);
+        snapshots.into_iter().filter(|(_, p)| keep.contains(p)).map(|(_, p)| p.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn daily_keeps_the_newest_of_each_day() {
+        let stamps = ["2024-07-03 23:59", "2024-07-03 08:00", "2024-07-02 00:00", "2024-07-01 12:00"];
+        assert_eq!(kept(&stamps, RetentionPolicy { daily: 2, weekly: 0, monthly: 0 }), ["2024-07-03 23:59", "2024-07-02 00:00"]);
+    }
+
+    #[test]
+    fn weekly_buckets_are_iso_weeks() {
+        // Monday 1 July starts ISO week 27; the Sunday before is still week 26
+        let stamps = ["2024-07-01 10:00", "2024-06-30 22:00", "2024-06-29 09:00", "2024-06-23 09:00"];
+        assert_eq!(kept(&stamps, RetentionPolicy { daily: 0, weekly: 2, monthly: 0 }), ["2024-07-01 10:00", "2024-06-30 22:00"]);
+        // 30 December 2024 already belongs to week 1 of 2025
+        let stamps = ["2025-01-01 10:00", "2024-12-30 10:00", "2024-12-29 10:00"];
+        assert_eq!(kept(&stamps, RetentionPolicy { daily: 0, weekly: 2, monthly: 0 }), ["2025-01-01 10:00", "2024-12-29 10:00"]);
+    }
+
+    #[test]
+    fn monthly_keeps_the_newest_of_each_month() {
+        let stamps = ["2024-08-01 00:00", "2024-07-31 23:59", "2024-07-01 00:00", "2024-06-30 23:59"];
+        assert_eq!(kept(&stamps, RetentionPolicy { daily: 0, weekly: 0, monthly: 2 }), ["2024-08-01 00:00", "2024-07-31 23:59"]);
+    }
+
+    #[test]
+    fn policies_add_up_and_zeros_keep_everything() {
+        let stamps = ["2024-07-03 10:00", "2024-07-02 10:00", "2024-06-20 10:00", "2024-05-10 10:00", "2024-05-02 10:00"];
+        assert_eq!(
+            kept(&stamps, RetentionPolicy { daily: 1, weekly: 2, monthly: 3 }),
+            ["2024-07-03 10:00", "2024-06-20 10:00", "2024-05-10 10:00"]
+        );
+        assert_eq!(kept(&stamps, RetentionPolicy { daily: 0, weekly: 0, monthly: 0 }).len(), stamps.len());
+    }
+
+    #[test]
+    fn snapshot_names_round_trip_with_and_without_a_device() {
+        let time = at("2024-07-03 10:15");
+        let tagged = snapshot_file_name(time, Some("1a2b3c4d5e6f7a8b"), BUNDLE_SUFFIX);
+        assert_eq!(tagged, "invoices-20240703-101500-1a2b3c4d.zip");
+        assert_eq!(parse_snapshot(&tagged), Some((time, Some("1a2b3c4d"))));
+        assert_eq!(parse_snapshot("invoices-20240703-101500.zip.enc"), Some((time, None)));
+        assert!(is_own_snapshot(&tagged, "1a2b3c4d5e6f7a8b"));
+        assert!(!is_own_snapshot(&tagged, "ffffffff00000000"));
+        assert!(!is_own_snapshot("invoices-20240703-101500.db", "1a2b3c4d5e6f7a8b"));
+        for other in ["invoices-20240703-101500-.zip", "invoices-20240703-101500-xyz.zip", "invoices-20240703.zip", "invoices-20240703-101500.txt"] {
+            assert_eq!(parse_snapshot(other), None, "{}", other);
+        }
+    }
+
+    #[test]
+    fn prune_leaves_other_devices_backups_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let own = "1a2b3c4d5e6f7a8b";
+        let names = [
+            snapshot_file_name(at("2024-07-03 10:00"), Some(own), BUNDLE_SUFFIX),
+            snapshot_file_name(at("2024-07-02 10:00"), Some(own), BUNDLE_SUFFIX),
+            snapshot_file_name(at("2024-07-01 10:00"), Some(own), ENCRYPTED_BUNDLE_SUFFIX),
+            snapshot_file_name(at("2024-07-02 11:00"), Some("99887766aabbccdd"), BUNDLE_SUFFIX),
+            snapshot_file_name(at("2024-06-01 11:00"), Some("99887766aabbccdd"), BUNDLE_SUFFIX),
+            snapshot_file_name(at("2024-06-01 09:00"), None, SNAPSHOT_SUFFIX),
+        ];
+        for name in &names {
+            fs::write(dir.path().join(name), b"backup").unwrap();
+        }
+        let pruned = prune(dir.path(), &RetentionPolicy { daily: 1, weekly: 0, monthly: 0 }, own).unwrap();
+        assert_eq!(pruned.len(), 2);
+        let left: HashSet<_> = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name().into_string().unwrap()).collect();
+        assert!(left.contains(&names[0]));
+        assert!(!left.contains(&names[1]) && !left.contains(&names[2]));
+        assert!(names[3..].iter().all(|name| left.contains(name)));
+    }
+
+    #[test]
+    fn only_backups_inside_the_backup_folder_are_accepted() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("backups");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let name = "invoices-20240703-101500-1a2b3c4d.zip";
+        for path in [dir.join(name), dir.join("notes.txt"), dir.join("sub").join(name), root.path().join(name)] {
+            fs::write(path, b"backup").unwrap();
+        }
+
+        assert_eq!(backup_in_dir(&dir.join(name), &dir).unwrap(), fs::canonicalize(dir.join(name)).unwrap());
+        assert_eq!(backup_in_dir(&dir.join("notes.txt"), &dir).unwrap_err(), "Not an invoice backup file");
+        for outside in [dir.join("sub").join(name), root.path().join(name), dir.join("sub").join("..").join("..").join(name)] {
+            assert_eq!(backup_in_dir(&outside, &dir).unwrap_err(), "The backup is not in the backup folder", "{:?}", outside);
+        }
+        assert!(backup_in_dir(&dir.join("invoices-20240704-101500.zip"), &dir).unwrap_err().starts_with("Backup not found"));
+    }
+
+    #[test]
+    fn configured_dir_reads_the_backup_path_setting() {
+        let conn = crate::test_support::migrated_db();
+        assert_eq!(configured_dir(&conn).unwrap(), None);
+        conn.execute("INSERT INTO settings(key, value) VALUES (?1, '')", [BACKUP_PATH_SETTING]).unwrap();
+        assert_eq!(configured_dir(&conn).unwrap(), None);
+        conn.execute("UPDATE settings SET value = '/srv/backups' WHERE key = ?1", [BACKUP_PATH_SETTING]).unwrap();
+        assert_eq!(configured_dir(&conn).unwrap(), Some(PathBuf::from("/srv/backups")));
+    }
+}
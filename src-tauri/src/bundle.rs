@@ -0,0 +1,535 @@
+use crate::backup::{self, RetentionPolicy};
+use crate::conflict::{self, SYNC_CONFLICT};
+use crate::device::{self, Device};
+use crate::{crypto, db, migrations, vault};
+use base64::Engine;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const BUNDLE_FORMAT: &str = "apex-invoice-backup";
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_PATH: &str = "manifest.json";
+/// Archive path of the database snapshot inside a bundle.
+pub const DATABASE_PATH: &str = "invoices.db";
+const SETTINGS_PATH: &str = "settings.json";
+const GENERATED_DIR: &str = "generated/";
+/// The encrypted vault snapshot and its salt; they only open with the vault password.
+const VAULT_DIR: &str = "vault/";
+/// Settings holding images as data URLs; they are stored as files under `assets/` instead.
+const IMAGE_SETTINGS: [&str; 2] = ["company_logo", "stamp_signature"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    pub format: String,
+    pub format_version: u32,
+    pub app_version: String,
+    pub schema_version: i64,
+    pub created_at: String,
+    pub entries: Vec<BundleEntry>,
+    /// Device that made the backup; bundles from before multi-device support have none of these.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
+    /// When the database was last written before the backup was taken.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// [`conflict::data_hash`] of the database snapshot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleResult {
+    pub path: String,
+    pub manifest: BundleManifest,
+    pub pruned: Vec<String>,
+}
+
+enum Source {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+impl Source {
+    fn reader(&self) -> Result<Box<dyn Read + '_>, String> {
+        match self {
+            Source::File(path) => Ok(Box::new(fs::File::open(path).map_err(|e| e.to_string())?)),
+            Source::Bytes(bytes) => Ok(Box::new(bytes.as_slice())),
+        }
+    }
+}
+
+/// Streams a reader through SHA-256, returning the hex digest and byte count.
+pub fn sha256_hex(mut reader: impl Read) -> Result<(String, u64), String> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = reader.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    let digest = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((digest, size))
+}
+
+/// Splits `data:image/png;base64,...` into bytes and a file extension.
+fn decode_data_url(data_url: &str) -> Option<(Vec<u8>, &'static str)> {
+    let (header, data) = data_url.split_once(',').unwrap_or(("", data_url));
+    let extension = if header.contains("image/jpeg") || header.contains("image/jpg") { "jpg" } else { "png" };
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim()).ok()?;
+    Some((bytes, extension))
+}
+
+/// Settings rows, minus the images which travel as separate archive entries.
+type SettingsMap = BTreeMap<String, Option<String>>;
+/// Archive path and contents of an image pulled out of settings.
+type Asset = (String, Vec<u8>);
+
+/// Reads settings out of the snapshot rather than the live file, so the bundle is self-consistent.
+fn collect_settings(snapshot: &Path) -> Result<(SettingsMap, Vec<Asset>), String> {
+    let conn = db::open_path(snapshot)?;
+    let mut stmt = conn.prepare("SELECT key, value FROM settings").map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))
+        .map_err(|e| e.to_string())?;
+
+    let mut settings = BTreeMap::new();
+    let mut assets = Vec::new();
+    for row in rows {
+        let (key, value) = row.map_err(|e| e.to_string())?;
+        if IMAGE_SETTINGS.contains(&key.as_str()) {
+            if let Some((bytes, extension)) = value.as_deref().and_then(decode_data_url) {
+                assets.push((format!("assets/{}.{}", key, extension), bytes));
+            }
+            continue;
+        }
+        settings.insert(key, value);
+    }
+    Ok((settings, assets))
+}
+
+/// Writes a bundle with the database snapshot, images, generated PDFs, settings and the vault
+/// files into `target_dir`. With a passphrase the whole archive is encrypted (see crypto.rs).
+pub fn create_bundle(
+    db_path: &Path,
+    generated_dir: &Path,
+    vault_files: &[PathBuf],
+    target_dir: &Path,
+    policy: &RetentionPolicy,
+    passphrase: Option<&str>,
+    device: &Device,
+) -> Result<BundleResult, String> {
+    fs::create_dir_all(target_dir).map_err(|e| e.to_string())?;
+    let now = Local::now();
+    let suffix = if passphrase.is_some() { backup::ENCRYPTED_BUNDLE_SUFFIX } else { backup::BUNDLE_SUFFIX };
+    let target = target_dir.join(backup::snapshot_file_name(now.naive_local(), Some(&device.id), suffix));
+
+    let temp = std::env::temp_dir().join(format!("invoices-bundle-{}-{}", std::process::id(), now.timestamp_millis()));
+    let snapshot = temp.with_extension("db");
+    let plain = temp.with_extension("zip");
+    let result = backup::snapshot_database(db_path, &snapshot).and_then(|_| {
+        let origin = Origin { device, created_at: now.to_rfc3339(), last_modified: conflict::last_modified(db_path) };
+        let manifest = write_bundle(&snapshot, generated_dir, vault_files, &plain, origin)?;
+        match passphrase {
+            Some(passphrase) => {
+                // Encrypt next to the target first so a failure never leaves a half-written backup
+                let partial = target.with_extension("partial");
+                crypto::encrypt_file(&plain, &partial, passphrase)?;
+                fs::rename(&partial, &target).map_err(|e| e.to_string())?;
+            }
+            None => move_file(&plain, &target)?,
+        }
+        Ok(manifest)
+    });
+    let _ = fs::remove_file(&snapshot);
+    let _ = fs::remove_file(&plain);
+    let manifest = result?;
+
+    let pruned = backup::prune(target_dir, policy, &device.id)?;
+    Ok(BundleResult { path: target.to_string_lossy().to_string(), manifest, pruned })
+}
+
+/// Renames, falling back to copy when the temp dir and the backup folder are on different volumes.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    let partial = to.with_extension("partial");
+    fs::copy(from, &partial).map_err(|e| e.to_string())?;
+    fs::rename(&partial, to).map_err(|e| e.to_string())
+}
+
+/// Who made a bundle and when, recorded in its manifest.
+struct Origin<'a> {
+    device: &'a Device,
+    created_at: String,
+    last_modified: Option<String>,
+}
+
+fn write_bundle(
+    snapshot: &Path,
+    generated_dir: &Path,
+    vault_files: &[PathBuf],
+    target: &Path,
+    origin: Origin,
+) -> Result<BundleManifest, String> {
+    let conn = db::open_path(snapshot)?;
+    let schema_version = db::schema_version(&conn)?;
+    let data_hash = conflict::data_hash(&conn)?;
+    drop(conn);
+    let (settings, assets) = collect_settings(snapshot)?;
+
+    let mut sources: Vec<(String, Source)> = vec![(DATABASE_PATH.to_string(), Source::File(snapshot.to_path_buf()))];
+    for (path, bytes) in assets {
+        sources.push((path, Source::Bytes(bytes)));
+    }
+    sources.push((
+        SETTINGS_PATH.to_string(),
+        Source::Bytes(serde_json::to_vec_pretty(&settings).map_err(|e| e.to_string())?),
+    ));
+    if generated_dir.is_dir() {
+        let mut pdfs: Vec<PathBuf> = fs::read_dir(generated_dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("pdf")))
+            .collect();
+        pdfs.sort();
+        for pdf in pdfs {
+            let name = pdf.file_name().unwrap().to_string_lossy().to_string();
+            sources.push((format!("{}{}", GENERATED_DIR, name), Source::File(pdf)));
+        }
+    }
+    for file in vault_files {
+        let name = file.file_name().unwrap().to_string_lossy().to_string();
+        sources.push((format!("{}{}", VAULT_DIR, name), Source::File(file.clone())));
+    }
+
+    let mut entries = Vec::new();
+    for (path, source) in &sources {
+        let (sha256, size) = sha256_hex(source.reader()?)?;
+        entries.push(BundleEntry { path: path.clone(), size, sha256 });
+    }
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT.to_string(),
+        format_version: BUNDLE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        created_at: origin.created_at,
+        entries,
+        device_id: Some(origin.device.id.clone()),
+        device_name: Some(origin.device.name.clone()),
+        last_modified: origin.last_modified,
+        data_hash: Some(data_hash),
+    };
+
+    {
+        let file = fs::File::create(target).map_err(|e| e.to_string())?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+        // Manifest goes first so readers can inspect a bundle without scanning it
+        zip.start_file(MANIFEST_PATH, options).map_err(|e| e.to_string())?;
+        zip.write_all(&serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+        for (path, source) in &sources {
+            zip.start_file(path.as_str(), options).map_err(|e| e.to_string())?;
+            std::io::copy(&mut source.reader()?, &mut zip).map_err(|e| e.to_string())?;
+        }
+        zip.finish().map_err(|e| e.to_string())?;
+    }
+    Ok(manifest)
+}
+
+/// Whether a file is a zip archive (a bundle) rather than a bare database.
+pub fn is_bundle(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| magic == *b"PK\x03\x04")
+        .unwrap_or(false)
+}
+
+fn open_archive(path: &Path) -> Result<zip::ZipArchive<fs::File>, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    zip::ZipArchive::new(file).map_err(|e| format!("Not a valid backup bundle: {}", e))
+}
+
+fn manifest_of(archive: &mut zip::ZipArchive<fs::File>) -> Result<BundleManifest, String> {
+    let entry = archive
+        .by_name(MANIFEST_PATH)
+        .map_err(|_| "Backup bundle has no manifest".to_string())?;
+    serde_json::from_reader(entry).map_err(|e| format!("Backup manifest is unreadable: {}", e))
+}
+
+/// Reads the manifest without checking any entries.
+pub fn read_manifest(path: &Path) -> Result<BundleManifest, String> {
+    manifest_of(&mut open_archive(path)?)
+}
+
+/// Checks the manifest and every entry's size and SHA-256. Nothing is extracted.
+pub fn verify_bundle(path: &Path) -> Result<BundleManifest, String> {
+    let mut archive = open_archive(path)?;
+    let manifest = manifest_of(&mut archive)?;
+
+    if manifest.format != BUNDLE_FORMAT {
+        return Err(format!("Unknown backup format '{}'", manifest.format));
+    }
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err("This backup was made by a newer version of the app. Please update first.".to_string());
+    }
+    if manifest.schema_version > migrations::latest_version() {
+        return Err(format!(
+            "This backup uses database schema v{}, newer than this app supports (v{}). Please update first.",
+            manifest.schema_version,
+            migrations::latest_version()
+        ));
+    }
+    if !manifest.entries.iter().any(|e| e.path == DATABASE_PATH) {
+        return Err("Backup bundle does not contain a database".to_string());
+    }
+
+    let listed: HashSet<&str> = manifest.entries.iter().map(|e| e.path.as_str()).collect();
+    let unlisted: Vec<String> = archive
+        .file_names()
+        .filter(|name| *name != MANIFEST_PATH && !listed.contains(name))
+        .map(|name| name.to_string())
+        .collect();
+    if !unlisted.is_empty() {
+        return Err(format!("Backup bundle contains files not in its manifest: {}", unlisted.join(", ")));
+    }
+
+    for entry in &manifest.entries {
+        let file = archive
+            .by_name(&entry.path)
+            .map_err(|_| format!("Backup bundle is missing {}", entry.path))?;
+        let (sha256, size) = sha256_hex(file)?;
+        if size != entry.size || sha256 != entry.sha256 {
+            return Err(format!("Checksum mismatch for {}. The backup is corrupted.", entry.path));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Extracts one verified entry to `dest`.
+pub fn extract_entry(bundle: &Path, entry_path: &str, dest: &Path) -> Result<(), String> {
+    let mut archive = open_archive(bundle)?;
+    let mut entry = archive.by_name(entry_path).map_err(|e| e.to_string())?;
+    let mut out = fs::File::create(dest).map_err(|e| e.to_string())?;
+    std::io::copy(&mut entry, &mut out).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Restores the generated PDFs from a verified bundle. Returns how many were written.
+fn extract_folder(bundle: &Path, manifest: &BundleManifest, prefix: &str, target_dir: &Path) -> Result<usize, String> {
+    fs::create_dir_all(target_dir).map_err(|e| e.to_string())?;
+    let mut count = 0;
+    for entry in manifest.entries.iter().filter(|e| e.path.starts_with(prefix)) {
+        let name = &entry.path[prefix.len()..];
+        // Entry names come from the archive; refuse anything that could escape the folder
+        if name.is_empty() || name.contains('/') || name.contains('\\') || name.contains("..") {
+            return Err(format!("Refusing to extract suspicious entry {}", entry.path));
+        }
+        extract_entry(bundle, &entry.path, &target_dir.join(name))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+pub fn extract_generated(bundle: &Path, manifest: &BundleManifest, generated_dir: &Path) -> Result<usize, String> {
+    extract_folder(bundle, manifest, GENERATED_DIR, generated_dir)
+}
+
+/// Unpacks the vault files, if the bundle has any. They stay encrypted.
+pub fn extract_vault(bundle: &Path, manifest: &BundleManifest, vault_dir: &Path) -> Result<usize, String> {
+    extract_folder(bundle, manifest, VAULT_DIR, vault_dir)
+}
+
+pub fn generated_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app_handle.path_resolver().app_data_dir().ok_or("Failed to resolve app data dir")?;
+    Ok(data_dir.join("generated"))
+}
+
+/// A PDF in the app's `generated` folder. Only a bare file name is accepted.
+pub fn generated_file(app_handle: &tauri::AppHandle, file_name: &str) -> Result<PathBuf, String> {
+    if file_name.is_empty() || file_name.contains(['/', '\\']) || file_name.contains("..") {
+        return Err("Access denied: Path is outside of allowed scope".to_string());
+    }
+    let path = generated_dir(app_handle)?.join(file_name);
+    if !path.exists() {
+        return Err(format!("{} has not been generated yet", file_name));
+    }
+    Ok(path)
+}
+
+/// Backs up into `target_dir`. When the newest backup there holds changes from another device
+/// that this device has not seen, fails with [`SYNC_CONFLICT`] unless `force` is set.
+#[tauri::command]
+pub async fn create_backup_bundle(
+    app_handle: tauri::AppHandle,
+    target_dir: String,
+    retention: Option<RetentionPolicy>,
+    force: Option<bool>,
+    vault: tauri::State<'_, vault::Vault>,
+) -> Result<BundleResult, String> {
+    let db_path = db::db_path(&app_handle)?;
+    if !db_path.exists() {
+        return Err("Database file not found".to_string());
+    }
+    // Note: target_dir comes from a system dialog (Safe), same as export_database
+    let target_dir = Path::new(&target_dir);
+    let config_dir = device::config_dir(&app_handle)?;
+    let passphrase = crypto::stored_passphrase(&config_dir, &vault)?;
+    if !force.unwrap_or(false) && conflict::check(&db_path, target_dir, &config_dir, passphrase.as_deref())?.status.is_conflict() {
+        return Err(SYNC_CONFLICT.to_string());
+    }
+
+    let result = create_bundle(
+        &db_path,
+        &generated_dir(&app_handle)?,
+        &vault::files(&config_dir),
+        target_dir,
+        &retention.unwrap_or_default(),
+        passphrase.as_deref(),
+        &device::load_or_create(&config_dir)?,
+    )?;
+    if let Some(hash) = &result.manifest.data_hash {
+        conflict::record_sync(&config_dir, hash, Some(Path::new(&result.path)))?;
+    }
+    log::info!("Backup written to {}, {} old backup(s) pruned", result.path, result.pruned.len());
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn verify_backup_bundle(path: String) -> Result<BundleManifest, String> {
+    verify_bundle(Path::new(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_customer, migrated_db_at};
+
+    struct Fixture {
+        dir: tempfile::TempDir,
+        bundle: PathBuf,
+    }
+
+    fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("invoices.db");
+        insert_customer(&migrated_db_at(&db_path), "c1", "Acme Pvt Ltd");
+        let generated = dir.path().join("generated");
+        fs::create_dir_all(&generated).unwrap();
+        fs::write(generated.join("Invoice_001.pdf"), b"%PDF-1.4 invoice").unwrap();
+        let device = Device { id: "1a2b3c4d5e6f7a8b".to_string(), name: "Office".to_string() };
+        let policy = RetentionPolicy { daily: 0, weekly: 0, monthly: 0 };
+        let result = create_bundle(&db_path, &generated, &[], &dir.path().join("backups"), &policy, None, &device).unwrap();
+        Fixture { bundle: PathBuf::from(result.path), dir }
+    }
+
+    /// Writes a copy of `bundle` whose entries have gone through `edit`.
+    fn rewrite(bundle: &Path, target: &Path, edit: impl FnOnce(&mut Vec<(String, Vec<u8>)>)) {
+        let mut archive = open_archive(bundle).unwrap();
+        let mut entries = Vec::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).unwrap();
+            let mut data = Vec::new();
+            file.read_to_end(&mut data).unwrap();
+            entries.push((file.name().to_string(), data));
+        }
+        edit(&mut entries);
+        let mut zip = zip::ZipWriter::new(fs::File::create(target).unwrap());
+        for (name, data) in entries {
+            zip.start_file(name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(&data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn edit_manifest(entries: &mut [(String, Vec<u8>)], edit: impl FnOnce(&mut BundleManifest)) {
+        let (_, data) = entries.iter_mut().find(|(name, _)| name == MANIFEST_PATH).unwrap();
+        let mut manifest: BundleManifest = serde_json::from_slice(data).unwrap();
+        edit(&mut manifest);
+        *data = serde_json::to_vec(&manifest).unwrap();
+    }
+
+    #[test]
+    fn fresh_bundle_verifies() {
+        let f = fixture();
+        let manifest = verify_bundle(&f.bundle).unwrap();
+        assert_eq!(manifest.schema_version, migrations::latest_version());
+        assert_eq!(manifest.device_id.as_deref(), Some("1a2b3c4d5e6f7a8b"));
+        let paths: Vec<_> = manifest.entries.iter().map(|e| e.path.as_str()).collect();
+        assert!(paths.contains(&DATABASE_PATH) && paths.contains(&"generated/Invoice_001.pdf"), "{:?}", paths);
+    }
+
+    #[test]
+    fn tampered_entry_is_rejected() {
+        let f = fixture();
+        let target = f.dir.path().join("tampered.zip");
+        rewrite(&f.bundle, &target, |entries| {
+            let (_, data) = entries.iter_mut().find(|(name, _)| name == "generated/Invoice_001.pdf").unwrap();
+            data[0] ^= 1;
+        });
+        let error = verify_bundle(&target).unwrap_err();
+        assert!(error.contains("Checksum mismatch for generated/Invoice_001.pdf"), "{}", error);
+    }
+
+    #[test]
+    fn unlisted_entry_is_rejected() {
+        let f = fixture();
+        let target = f.dir.path().join("unlisted.zip");
+        rewrite(&f.bundle, &target, |entries| entries.push(("generated/extra.pdf".to_string(), b"%PDF".to_vec())));
+        let error = verify_bundle(&target).unwrap_err();
+        assert!(error.contains("not in its manifest: generated/extra.pdf"), "{}", error);
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let f = fixture();
+        let target = f.dir.path().join("newer.zip");
+        rewrite(&f.bundle, &target, |entries| edit_manifest(entries, |m| m.schema_version = migrations::latest_version() + 1));
+        let error = verify_bundle(&target).unwrap_err();
+        assert!(error.contains("newer than this app supports"), "{}", error);
+    }
+
+    #[test]
+    fn entries_cannot_escape_the_target_folder() {
+        let f = fixture();
+        for evil in ["generated/../evil.pdf", "generated/sub/evil.pdf", "generated/..\\evil.pdf", "generated/"] {
+            let target = f.dir.path().join("evil.zip");
+            let data = b"%PDF evil".to_vec();
+            let (sha256, size) = sha256_hex(data.as_slice()).unwrap();
+            rewrite(&f.bundle, &target, |entries| {
+                entries.push((evil.to_string(), data));
+                edit_manifest(entries, |m| m.entries.push(BundleEntry { path: evil.to_string(), size, sha256 }));
+            });
+            // The checksums all match, so only the extraction guard stands in the way
+            let manifest = verify_bundle(&target).unwrap();
+            let out = f.dir.path().join("restore").join("generated");
+            let error = extract_generated(&target, &manifest, &out).unwrap_err();
+            assert!(error.contains("suspicious entry"), "{}: {}", evil, error);
+            assert!(!f.dir.path().join("restore").join("evil.pdf").exists());
+        }
+    }
+}
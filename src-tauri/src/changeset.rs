@@ -0,0 +1,647 @@
+use crate::device::{self, Device};
+use crate::{db, migrations};
+use base64::Engine;
+use chrono::Local;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as Json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const CHANGESET_FORMAT: &str = "apex-invoice-changeset";
+const CHANGESET_FORMAT_VERSION: u32 = 1;
+const CHANGESET_PREFIX: &str = "changes-";
+
+/// A table whose rows travel in changesets. `key_sql` builds the same row key as the
+/// `*_version_*` triggers in migration 4.
+struct SyncedTable {
+    name: &'static str,
+    key_sql: &'static str,
+}
+
+/// In the order inserts and updates are applied, so foreign keys and the invoice lock are
+/// satisfied: customers before their invoices, revisions before the invoice that moves to them.
+/// Deletes run in reverse.
+const SYNCED_TABLES: [SyncedTable; 4] = [
+    SyncedTable { name: "customers", key_sql: "id" },
+    SyncedTable { name: "invoice_revisions", key_sql: "invoice_number || '/' || revision" },
+    SyncedTable { name: "invoices", key_sql: "invoice_number" },
+    SyncedTable { name: "invoice_items", key_sql: "id" },
+];
+
+type Row = Map<String, Json>;
+
+/// One row as of the export. `row` is `None` when the row was deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub table: String,
+    pub key: String,
+    pub version: i64,
+    /// Installation the row's current contents were written on, and its version there. Lets a
+    /// device recognise its own changes coming back through a peer.
+    pub origin_device: String,
+    pub origin_version: i64,
+    #[serde(default)]
+    pub row: Option<Row>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Changeset {
+    pub format: String,
+    pub format_version: u32,
+    pub device_id: String,
+    pub device_name: String,
+    pub schema_version: i64,
+    /// Rows changed after this version of the exporting database...
+    pub from_version: i64,
+    /// ...up to and including this one.
+    pub to_version: i64,
+    pub created_at: String,
+    /// Highest version imported from each other device. Tells a device which of its own rows
+    /// the exporter had already seen.
+    #[serde(default)]
+    pub imported_versions: BTreeMap<String, i64>,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangesetExport {
+    /// `None` when nothing changed since the last export.
+    pub path: Option<String>,
+    pub changes: usize,
+    pub from_version: i64,
+    pub to_version: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeConflict {
+    pub table: String,
+    pub key: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangesetReport {
+    pub device_name: String,
+    pub inserted: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+    /// Changes already imported from an earlier changeset of the same device.
+    pub already_imported: usize,
+    /// Rows left as they are on this device.
+    pub conflicts: Vec<ChangeConflict>,
+}
+
+fn json_value(value: ValueRef) -> Json {
+    match value {
+        ValueRef::Null => Json::Null,
+        ValueRef::Integer(v) => Json::from(v),
+        ValueRef::Real(v) => serde_json::Number::from_f64(v).map(Json::Number).unwrap_or(Json::Null),
+        ValueRef::Text(t) => Json::String(String::from_utf8_lossy(t).into_owned()),
+        // The synced tables have no blob columns; this only keeps odd data from failing the export
+        ValueRef::Blob(b) => Json::String(base64::engine::general_purpose::STANDARD.encode(b)),
+    }
+}
+
+fn sql_value(value: &Json) -> Value {
+    match value {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Integer(*b as i64),
+        Json::Number(n) => n.as_i64().map(Value::Integer).unwrap_or_else(|| Value::Real(n.as_f64().unwrap_or_default())),
+        Json::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+fn table(name: &str) -> Result<&'static SyncedTable, String> {
+    SYNCED_TABLES.iter().find(|t| t.name == name).ok_or_else(|| format!("Changeset contains unknown table '{}'", name))
+}
+
+fn columns(conn: &Connection, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table)).map_err(|e| e.to_string())?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string());
+    columns
+}
+
+/// Reads every column of a statement row, starting at `offset`, into a JSON object.
+fn read_row(row: &rusqlite::Row, names: &[String], offset: usize) -> rusqlite::Result<Row> {
+    let mut map = Map::new();
+    for (i, name) in names.iter().enumerate().skip(offset) {
+        map.insert(name.clone(), json_value(row.get_ref(i)?));
+    }
+    Ok(map)
+}
+
+fn local_row(conn: &Connection, table: &SyncedTable, key: &str) -> Result<Option<Row>, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM {} WHERE ({}) = ?1", table.name, table.key_sql))
+        .map_err(|e| e.to_string())?;
+    let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+    stmt.query_row([key], |row| read_row(row, &names, 0)).optional().map_err(|e| e.to_string())
+}
+
+/// This database's version of a row, including deleted ones.
+fn local_version(conn: &Connection, table: &str, key: &str) -> Result<Option<i64>, String> {
+    conn.query_row("SELECT version FROM row_versions WHERE table_name = ?1 AND row_key = ?2", params![table, key], |row| row.get(0))
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn clock(conn: &Connection) -> Result<(i64, i64), String> {
+    conn.query_row("SELECT version, exported_version FROM sync_clock WHERE id = 1", [], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())
+}
+
+/// Collects every synced row changed after `since`, including deletions.
+pub fn export(conn: &Connection, device: &Device, since: i64) -> Result<Changeset, String> {
+    let (to_version, _) = clock(conn)?;
+    let mut changes = Vec::new();
+    for table in &SYNCED_TABLES {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT v.row_key, v.version, COALESCE(v.origin_device, ?3), COALESCE(v.origin_version, v.version), t.* \
+                 FROM {} t JOIN row_versions v ON v.table_name = ?1 AND v.row_key = ({}) \
+                 WHERE v.deleted = 0 AND v.version > ?2 ORDER BY v.version",
+                table.name, table.key_sql
+            ))
+            .map_err(|e| e.to_string())?;
+        let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+        let rows = stmt
+            .query_map(params![table.name, since, device.id], |row| {
+                Ok(Change {
+                    table: table.name.to_string(),
+                    key: row.get(0)?,
+                    version: row.get(1)?,
+                    origin_device: row.get(2)?,
+                    origin_version: row.get(3)?,
+                    row: Some(read_row(row, &names, 4)?),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        for change in rows {
+            changes.push(change.map_err(|e| e.to_string())?);
+        }
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT row_key, version, COALESCE(origin_device, ?3), COALESCE(origin_version, version) FROM row_versions \
+                 WHERE table_name = ?1 AND deleted = 1 AND version > ?2 ORDER BY version",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![table.name, since, device.id], |row| {
+                Ok(Change {
+                    table: table.name.to_string(),
+                    key: row.get(0)?,
+                    version: row.get(1)?,
+                    origin_device: row.get(2)?,
+                    origin_version: row.get(3)?,
+                    row: None,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        for change in rows {
+            changes.push(change.map_err(|e| e.to_string())?);
+        }
+    }
+
+    let mut stmt = conn.prepare("SELECT device_id, last_version FROM sync_peers").map_err(|e| e.to_string())?;
+    let imported_versions = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<BTreeMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(Changeset {
+        format: CHANGESET_FORMAT.to_string(),
+        format_version: CHANGESET_FORMAT_VERSION,
+        device_id: device.id.clone(),
+        device_name: device.name.clone(),
+        schema_version: db::schema_version(conn)?,
+        from_version: since,
+        to_version,
+        created_at: Local::now().to_rfc3339(),
+        imported_versions,
+        changes,
+    })
+}
+
+/// What happened to a single change.
+enum Outcome {
+    Inserted,
+    Updated,
+    Deleted,
+    Unchanged,
+}
+
+struct Applier<'a> {
+    conn: &'a Connection,
+    this_device_id: &'a str,
+    peer_name: &'a str,
+    /// The newer of this database's clock right after the previous import from the same device
+    /// and the version that device had imported from here; rows with a higher version were
+    /// changed here since the peer last saw them.
+    synced_local_version: i64,
+    prefer_remote: bool,
+    /// Invoices written unlocked so their items could follow, to be locked again at the end.
+    relock: Vec<String>,
+}
+
+impl Applier<'_> {
+    fn changed_here(&self, version: Option<i64>) -> bool {
+        version.is_some_and(|v| v > self.synced_local_version)
+    }
+
+    fn upsert(&mut self, table: &SyncedTable, key: &str, remote: &Row) -> Result<Outcome, String> {
+        let local_columns = columns(self.conn, table.name)?;
+        // Columns this schema does not have (a newer peer) are dropped; missing ones keep their defaults
+        let mut row: Row = remote.iter().filter(|(c, _)| local_columns.contains(c)).map(|(c, v)| (c.clone(), v.clone())).collect();
+        let local = local_row(self.conn, table, key)?;
+        let changed_here = self.changed_here(local_version(self.conn, table.name, key)?);
+
+        if let Some(local) = &local {
+            if row.iter().all(|(c, v)| local.get(c) == Some(v)) {
+                return Ok(Outcome::Unchanged);
+            }
+        }
+        if changed_here && !self.prefer_remote {
+            return Err(match local {
+                Some(_) => format!("Changed on this device and on {}", self.peer_name),
+                None => format!("Deleted on this device, changed on {}", self.peer_name),
+            });
+        }
+
+        let mut relock = false;
+        if table.name == "invoices" {
+            let remote_locked = row.get("locked").and_then(Json::as_i64) == Some(1);
+            let local_lock = local.as_ref().map(|l| (l.get("locked").and_then(Json::as_i64) == Some(1), l.get("revision").and_then(Json::as_i64)));
+            let remote_revision = row.get("revision").and_then(Json::as_i64);
+            match local_lock {
+                // Issued on both sides: only a newer revision (an amendment) or a cancellation can be applied
+                Some((true, local_revision)) if remote_revision < local_revision => {
+                    return Err("This device has a newer revision of the invoice".to_string());
+                }
+                Some((true, local_revision)) if remote_revision == local_revision => {}
+                // Written unlocked so the items can follow, then locked again at the end
+                _ if remote_locked => {
+                    row.insert("locked".to_string(), Json::from(0));
+                    relock = true;
+                }
+                _ => {}
+            }
+        }
+
+        let names: Vec<&String> = row.keys().collect();
+        let values: Vec<Value> = row.values().map(sql_value).collect();
+        if local.is_some() {
+            let assignments: Vec<String> = names.iter().enumerate().map(|(i, c)| format!("{} = ?{}", c, i + 1)).collect();
+            let sql = format!("UPDATE {} SET {} WHERE ({}) = ?{}", table.name, assignments.join(", "), table.key_sql, names.len() + 1);
+            let mut params: Vec<Value> = values;
+            params.push(Value::Text(key.to_string()));
+            self.conn.execute(&sql, rusqlite::params_from_iter(params)).map_err(|e| e.to_string())?;
+        } else {
+            let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+            let column_list: Vec<&str> = names.iter().map(|c| c.as_str()).collect();
+            let sql = format!("INSERT INTO {} ({}) VALUES ({})", table.name, column_list.join(", "), placeholders.join(", "));
+            self.conn.execute(&sql, rusqlite::params_from_iter(values)).map_err(|e| e.to_string())?;
+        }
+        if relock {
+            self.relock.push(key.to_string());
+        }
+        Ok(if local.is_some() { Outcome::Updated } else { Outcome::Inserted })
+    }
+
+    fn delete(&mut self, table: &SyncedTable, key: &str) -> Result<Outcome, String> {
+        if local_row(self.conn, table, key)?.is_none() {
+            return Ok(Outcome::Unchanged);
+        }
+        if self.changed_here(local_version(self.conn, table.name, key)?) && !self.prefer_remote {
+            return Err(format!("Changed on this device, deleted on {}", self.peer_name));
+        }
+        if table.name == "customers" {
+            // Customers are only purged from the trash; the peer trashed it first
+            self.conn
+                .execute("UPDATE customers SET deleted_at = COALESCE(deleted_at, CURRENT_TIMESTAMP) WHERE id = ?1", [key])
+                .map_err(|e| e.to_string())?;
+        }
+        self.conn
+            .execute(&format!("DELETE FROM {} WHERE ({}) = ?1", table.name, table.key_sql), [key])
+            .map_err(|e| e.to_string())?;
+        Ok(Outcome::Deleted)
+    }
+
+    /// Applies one change in a savepoint, so a row the triggers refuse leaves nothing half-written.
+    fn apply(&mut self, change: &Change, report: &mut ChangesetReport) -> Result<(), String> {
+        let table = table(&change.table)?;
+        // One of this device's own changes, passed back by the peer
+        if change.origin_device == self.this_device_id {
+            report.unchanged += 1;
+            return Ok(());
+        }
+        self.conn.execute_batch("SAVEPOINT change").map_err(|e| e.to_string())?;
+        let result = match &change.row {
+            Some(row) => self.upsert(table, &change.key, row),
+            None => self.delete(table, &change.key),
+        };
+        match result {
+            Ok(outcome) => {
+                if !matches!(outcome, Outcome::Unchanged) {
+                    self.conn
+                        .execute(
+                            "UPDATE row_versions SET origin_device = ?3, origin_version = ?4 WHERE table_name = ?1 AND row_key = ?2",
+                            params![change.table, change.key, change.origin_device, change.origin_version],
+                        )
+                        .map_err(|e| e.to_string())?;
+                }
+                self.conn.execute_batch("RELEASE change").map_err(|e| e.to_string())?;
+                match outcome {
+                    Outcome::Inserted => report.inserted += 1,
+                    Outcome::Updated => report.updated += 1,
+                    Outcome::Deleted => report.deleted += 1,
+                    Outcome::Unchanged => report.unchanged += 1,
+                }
+            }
+            Err(reason) => {
+                self.conn.execute_batch("ROLLBACK TO change; RELEASE change").map_err(|e| e.to_string())?;
+                report.conflicts.push(ChangeConflict { table: change.table.clone(), key: change.key.clone(), reason });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merges a changeset from another installation. Rows changed only on the other side are taken
+/// over; rows changed on both sides since the last import from that device are left alone and
+/// reported, unless `prefer_remote` is set. Runs inside the caller's transaction.
+pub fn apply(conn: &Connection, changeset: &Changeset, this_device: &Device, prefer_remote: bool) -> Result<ChangesetReport, String> {
+    if changeset.format != CHANGESET_FORMAT {
+        return Err(format!("Unknown changeset format '{}'", changeset.format));
+    }
+    if changeset.format_version > CHANGESET_FORMAT_VERSION {
+        return Err("This changeset was made by a newer version of the app. Please update first.".to_string());
+    }
+    if changeset.schema_version > migrations::latest_version() {
+        return Err(format!(
+            "This changeset uses database schema v{}, newer than this app supports (v{}). Please update first.",
+            changeset.schema_version,
+            migrations::latest_version()
+        ));
+    }
+    if changeset.device_id == this_device.id {
+        return Err("This changeset was exported from this device.".to_string());
+    }
+    for change in &changeset.changes {
+        table(&change.table)?;
+    }
+
+    let (last_version, synced_local_version): (i64, i64) = conn
+        .query_row("SELECT last_version, local_version FROM sync_peers WHERE device_id = ?1", [&changeset.device_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .optional()
+        .map_err(|e| e.to_string())?
+        .unwrap_or((0, 0));
+    let seen_by_peer = changeset.imported_versions.get(&this_device.id).copied().unwrap_or(0);
+    let synced_local_version = synced_local_version.max(seen_by_peer);
+
+    let mut report = ChangesetReport { device_name: changeset.device_name.clone(), ..Default::default() };
+    let mut applier = Applier { conn, this_device_id: &this_device.id, peer_name: &changeset.device_name, synced_local_version, prefer_remote, relock: Vec::new() };
+    let pending: Vec<&Change> = changeset.changes.iter().filter(|c| c.version > last_version).collect();
+    report.already_imported = changeset.changes.len() - pending.len();
+
+    for table in SYNCED_TABLES.iter() {
+        for change in pending.iter().filter(|c| c.table == table.name && c.row.is_some()) {
+            applier.apply(change, &mut report)?;
+        }
+    }
+    for table in SYNCED_TABLES.iter().rev() {
+        for change in pending.iter().filter(|c| c.table == table.name && c.row.is_none()) {
+            applier.apply(change, &mut report)?;
+        }
+    }
+    for invoice_number in &applier.relock {
+        conn.execute("UPDATE invoices SET locked = 1 WHERE invoice_number = ?1", [invoice_number])
+            .map_err(|e| e.to_string())?;
+    }
+
+    let (local_version, _) = clock(conn)?;
+    conn.execute(
+        "INSERT INTO sync_peers(device_id, device_name, last_version, local_version, synced_at)
+         VALUES(?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)
+         ON CONFLICT(device_id) DO UPDATE SET device_name = excluded.device_name,
+             last_version = MAX(last_version, excluded.last_version),
+             local_version = excluded.local_version, synced_at = excluded.synced_at",
+        params![changeset.device_id, changeset.device_name, changeset.to_version, local_version],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+/// Whether a file is a changeset, judged by its `format` field alone.
+pub fn is_changeset_file(path: &Path) -> bool {
+    #[derive(Deserialize)]
+    struct Header {
+        format: String,
+    }
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Header>(&content).ok())
+        .is_some_and(|header| header.format == CHANGESET_FORMAT)
+}
+
+fn read_changeset(path: &Path) -> Result<Changeset, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Not a valid changeset file: {}", e))
+}
+
+fn parse_prefer(prefer: Option<&str>) -> Result<bool, String> {
+    match prefer.unwrap_or("local") {
+        "local" => Ok(false),
+        "remote" => Ok(true),
+        other => Err(format!("Unknown conflict preference '{}'", other)),
+    }
+}
+
+fn import(app_handle: &tauri::AppHandle, path: &str, prefer: Option<&str>, commit: bool) -> Result<ChangesetReport, String> {
+    let changeset = read_changeset(Path::new(path))?;
+    let device = device::this_device(app_handle)?;
+    let mut conn = db::open(app_handle)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let report = apply(&tx, &changeset, &device, parse_prefer(prefer)?)?;
+    if commit {
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+    Ok(report)
+}
+
+/// Writes the rows changed since the last export (or all rows with `full`) to a file in
+/// `target_dir` for another installation to import.
+#[tauri::command]
+pub async fn export_changeset(app_handle: tauri::AppHandle, target_dir: String, full: Option<bool>) -> Result<ChangesetExport, String> {
+    let device = device::this_device(&app_handle)?;
+    let conn = db::open(&app_handle)?;
+    let (_, exported_version) = clock(&conn)?;
+    let since = if full.unwrap_or(false) { 0 } else { exported_version };
+    let changeset = export(&conn, &device, since)?;
+    let mut result = ChangesetExport { path: None, changes: changeset.changes.len(), from_version: since, to_version: changeset.to_version };
+    if changeset.changes.is_empty() {
+        return Ok(result);
+    }
+
+    let target_dir = Path::new(&target_dir);
+    fs::create_dir_all(target_dir).map_err(|e| e.to_string())?;
+    let name = format!("{}{}-{}.json", CHANGESET_PREFIX, &device.id[..8], Local::now().format("%Y%m%d-%H%M%S"));
+    let target = target_dir.join(name);
+    let partial = target.with_extension("partial");
+    fs::write(&partial, serde_json::to_vec_pretty(&changeset).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    fs::rename(&partial, &target).map_err(|e| e.to_string())?;
+
+    conn.execute("UPDATE sync_clock SET exported_version = MAX(exported_version, ?1) WHERE id = 1", [changeset.to_version])
+        .map_err(|e| e.to_string())?;
+    result.path = Some(target.to_string_lossy().into_owned());
+    Ok(result)
+}
+
+/// Dry run of [`import_changeset`]: the same merge inside a transaction that is rolled back.
+#[tauri::command]
+pub async fn preview_changeset(app_handle: tauri::AppHandle, path: String, prefer: Option<String>) -> Result<ChangesetReport, String> {
+    import(&app_handle, &path, prefer.as_deref(), false)
+}
+
+/// Merges a changeset in one transaction. `prefer` is "local" (default) or "remote" and decides
+/// rows changed on both devices since they last synced.
+#[tauri::command]
+pub async fn import_changeset(app_handle: tauri::AppHandle, path: String, prefer: Option<String>) -> Result<ChangesetReport, String> {
+    import(&app_handle, &path, prefer.as_deref(), true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoices;
+    use crate::test_support::{insert_customer, migrated_db, sample_invoice};
+
+    fn device(id: &str, name: &str) -> Device {
+        Device { id: id.to_string(), name: name.to_string() }
+    }
+
+    /// Exports everything `from` has and merges it into `to`, the way an import commits it.
+    fn sync(from: &Connection, from_device: &Device, to: &Connection, to_device: &Device, prefer_remote: bool) -> ChangesetReport {
+        let changeset = export(from, from_device, 0).unwrap();
+        let tx = to.unchecked_transaction().unwrap();
+        let report = apply(&tx, &changeset, to_device, prefer_remote).unwrap();
+        tx.commit().unwrap();
+        report
+    }
+
+    fn customers(conn: &Connection) -> Vec<(String, Option<String>)> {
+        let mut stmt = conn.prepare("SELECT id, city FROM customers ORDER BY id").unwrap();
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        rows.collect::<Result<_, _>>().unwrap()
+    }
+
+    fn purge(conn: &Connection, id: &str) {
+        conn.execute("UPDATE customers SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?1", [id]).unwrap();
+        conn.execute("DELETE FROM customers WHERE id = ?1", [id]).unwrap();
+    }
+
+    #[test]
+    fn inserts_updates_and_deletes_travel_both_ways() {
+        let (office, laptop) = (device("1a2b3c4d5e6f7a8b", "Office"), device("9f8e7d6c5b4a3f2e", "Laptop"));
+        let (a, b) = (migrated_db(), migrated_db());
+        insert_customer(&a, "c1", "Acme Pvt Ltd");
+        insert_customer(&a, "c2", "Bharat Solar");
+
+        let report = sync(&a, &office, &b, &laptop, false);
+        assert_eq!((report.inserted, report.updated, report.deleted), (2, 0, 0));
+        assert_eq!(report.device_name, "Office");
+
+        b.execute("UPDATE customers SET city = 'Howrah' WHERE id = 'c1'", []).unwrap();
+        insert_customer(&b, "c3", "Coastal Traders");
+        purge(&b, "c2");
+        let report = sync(&b, &laptop, &a, &office, false);
+        assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+        assert_eq!((report.inserted, report.updated, report.deleted), (1, 1, 1));
+
+        a.execute("UPDATE customers SET city = 'Durgapur' WHERE id = 'c3'", []).unwrap();
+        purge(&a, "c1");
+        let report = sync(&a, &office, &b, &laptop, false);
+        assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+        assert_eq!((report.inserted, report.updated, report.deleted), (0, 1, 1));
+        // The laptop's own deletion of c2, passed back
+        assert_eq!(report.unchanged, 1);
+
+        let expected = vec![("c3".to_string(), Some("Durgapur".to_string()))];
+        assert_eq!(customers(&a), expected);
+        assert_eq!(customers(&b), expected);
+        // Nothing new comes back from the side that just imported
+        let report = sync(&b, &laptop, &a, &office, false);
+        assert_eq!((report.inserted, report.updated, report.deleted), (0, 0, 0));
+    }
+
+    #[test]
+    fn rows_changed_on_both_sides_keep_the_preferred_copy() {
+        let (office, laptop) = (device("1a2b3c4d5e6f7a8b", "Office"), device("9f8e7d6c5b4a3f2e", "Laptop"));
+        let (a, b) = (migrated_db(), migrated_db());
+        insert_customer(&a, "c1", "Acme Pvt Ltd");
+        sync(&a, &office, &b, &laptop, false);
+        a.execute("UPDATE customers SET city = 'Howrah' WHERE id = 'c1'", []).unwrap();
+        b.execute("UPDATE customers SET city = 'Siliguri' WHERE id = 'c1'", []).unwrap();
+        let changeset = export(&b, &laptop, 0).unwrap();
+
+        let tx = a.unchecked_transaction().unwrap();
+        let report = apply(&tx, &changeset, &office, false).unwrap();
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].key, "c1");
+        assert_eq!(report.conflicts[0].reason, "Changed on this device and on Laptop");
+        assert_eq!(customers(&tx)[0].1.as_deref(), Some("Howrah"));
+        drop(tx);
+
+        let tx = a.unchecked_transaction().unwrap();
+        let report = apply(&tx, &changeset, &office, true).unwrap();
+        assert!(report.conflicts.is_empty());
+        assert_eq!(report.updated, 1);
+        assert_eq!(customers(&tx)[0].1.as_deref(), Some("Siliguri"));
+    }
+
+    #[test]
+    fn issued_invoices_stay_locked_on_the_other_device() {
+        let (office, laptop) = (device("1a2b3c4d5e6f7a8b", "Office"), device("9f8e7d6c5b4a3f2e", "Laptop"));
+        let (mut a, b) = (migrated_db(), migrated_db());
+        insert_customer(&a, "c1", "Acme Pvt Ltd");
+        invoices::issue(&mut a, sample_invoice("001", "c1")).unwrap();
+        let lock = |conn: &Connection| -> (i64, i64) {
+            conn.query_row("SELECT locked, revision FROM invoices WHERE invoice_number = '001'", [], |r| Ok((r.get(0)?, r.get(1)?)))
+                .unwrap()
+        };
+
+        let report = sync(&a, &office, &b, &laptop, false);
+        assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+        assert_eq!(lock(&b), (1, 0));
+        let error = b.execute("UPDATE invoice_items SET amount = 1 WHERE invoice_number = '001'", []).unwrap_err();
+        assert!(error.to_string().contains("locked"), "{}", error);
+
+        let mut amended = sample_invoice("001", "c1");
+        amended.line_items[0].quantity = 6.0;
+        invoices::amend(&mut a, amended, "Quantity corrected").unwrap();
+        let report = sync(&a, &office, &b, &laptop, false);
+        assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+        assert_eq!(lock(&b), (1, 1));
+        let quantity: f64 = b.query_row("SELECT quantity FROM invoice_items WHERE invoice_number = '001'", [], |r| r.get(0)).unwrap();
+        assert_eq!(quantity, 6.0);
+        let error = b.execute("UPDATE invoices SET grand_total = 1 WHERE invoice_number = '001'", []).unwrap_err();
+        assert!(error.to_string().contains("locked"), "{}", error);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::backup;
+use crate::bundle::{self, BundleManifest};
+use crate::device::{self, Device};
+use crate::vault::Vault;
+use crate::{crypto, db};
+use chrono::{DateTime, Local};
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Error returned when a backup would overwrite changes made on another device.
+/// The frontend matches on it to show the conflict resolution dialog.
+pub const SYNC_CONFLICT: &str = "SYNC_CONFLICT";
+/// File in the app config dir remembering what the data looked like at the last backup or restore.
+const STATE_FILE: &str = "sync-state.json";
+/// Tables that change without the data changing: sync bookkeeping and the log of sent emails.
+const UNHASHED_TABLES: [&str; 3] = ["row_versions", "sync_clock", "invoice_emails"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncState {
+    /// [`data_hash`] of the database right after the last backup or restore on this device.
+    pub last_synced_hash: Option<String>,
+    pub last_synced_at: Option<String>,
+    pub last_synced_backup: Option<String>,
+}
+
+/// Where the newest backup in the shared folder came from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupOrigin {
+    pub path: String,
+    pub device_id: String,
+    pub device_name: String,
+    pub created_at: String,
+    pub last_modified: Option<String>,
+    pub data_hash: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncStatus {
+    /// No backup in the folder says which device made it.
+    NoRemote,
+    InSync,
+    /// Only this device changed since the last sync; backing up is safe.
+    LocalAhead,
+    /// Only the other device changed; backing up would throw its changes away.
+    RemoteAhead,
+    /// Both sides changed since the last sync.
+    Diverged,
+}
+
+impl SyncStatus {
+    pub fn is_conflict(self) -> bool {
+        matches!(self, SyncStatus::RemoteAhead | SyncStatus::Diverged)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCheck {
+    pub status: SyncStatus,
+    pub device: Device,
+    pub local_hash: String,
+    pub local_last_modified: Option<String>,
+    pub remote: Option<BackupOrigin>,
+    pub last_synced_at: Option<String>,
+}
+
+/// Fingerprint of everything stored in a database: every row of every table, in rowid order.
+/// Two copies hash the same only when they hold the same data, whatever their page layout or
+/// journal mode. The migration bookkeeping is left out since it records when each device ran it,
+/// and so are [`UNHASHED_TABLES`].
+pub fn data_hash(conn: &Connection) -> Result<String, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master WHERE type = 'table' \
+             AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\' AND name NOT LIKE '\\_sqlx\\_%' ESCAPE '\\' ORDER BY name",
+        )
+        .map_err(|e| e.to_string())?;
+    let tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut hasher = Sha256::new();
+    for table in tables.into_iter().filter(|t| !UNHASHED_TABLES.contains(&t.as_str())) {
+        hasher.update(table.as_bytes());
+        hasher.update([0]);
+        let mut stmt = conn
+            .prepare(&format!("SELECT * FROM \"{}\" ORDER BY rowid", table.replace('"', "\"\"")))
+            .map_err(|e| e.to_string())?;
+        let column_count = stmt.column_count();
+        let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            // Tag each value with its type and length so different rows can never run together
+            for i in 0..column_count {
+                match row.get_ref(i).map_err(|e| e.to_string())? {
+                    ValueRef::Null => hasher.update([0]),
+                    ValueRef::Integer(v) => {
+                        hasher.update([1]);
+                        hasher.update(v.to_le_bytes());
+                    }
+                    ValueRef::Real(v) => {
+                        hasher.update([2]);
+                        hasher.update(v.to_le_bytes());
+                    }
+                    ValueRef::Text(bytes) => {
+                        hasher.update([3]);
+                        hasher.update((bytes.len() as u64).to_le_bytes());
+                        hasher.update(bytes);
+                    }
+                    ValueRef::Blob(bytes) => {
+                        hasher.update([4]);
+                        hasher.update((bytes.len() as u64).to_le_bytes());
+                        hasher.update(bytes);
+                    }
+                }
+            }
+        }
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// When the database file (or its write-ahead log) was last written.
+pub fn last_modified(db_path: &Path) -> Option<String> {
+    let wal = PathBuf::from(format!("{}-wal", db_path.to_string_lossy()));
+    [db_path, wal.as_path()]
+        .iter()
+        .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+        .map(|time| DateTime::<Local>::from(time).to_rfc3339())
+}
+
+/// Reads the manifest of a bundle, decrypting it to a temp file first when needed.
+fn read_bundle_manifest(path: &Path, passphrase: Option<&str>) -> Option<BundleManifest> {
+    if !crypto::is_encrypted(path) {
+        return bundle::read_manifest(path).ok();
+    }
+    let temp = std::env::temp_dir().join(format!("invoices-origin-{}.zip", std::process::id()));
+    let manifest = crypto::decrypt_file(path, &temp, passphrase?).ok().and_then(|_| bundle::read_manifest(&temp).ok());
+    let _ = fs::remove_file(&temp);
+    manifest
+}
+
+/// The origin of the newest backup in `backup_dir`. `None` when there is no backup yet, or when
+/// the newest one predates device metadata or cannot be opened with this device's passphrase.
+pub fn latest_origin(backup_dir: &Path, passphrase: Option<&str>) -> Result<Option<BackupOrigin>, String> {
+    if !backup_dir.is_dir() {
+        return Ok(None);
+    }
+    let Some((_, path)) = backup::list_snapshots(backup_dir)?.into_iter().next() else {
+        return Ok(None);
+    };
+    Ok(origin(&path, passphrase))
+}
+
+/// Which device made a backup and from what data; `None` when it predates device metadata or
+/// cannot be opened with this device's passphrase.
+pub fn origin(path: &Path, passphrase: Option<&str>) -> Option<BackupOrigin> {
+    let manifest = read_bundle_manifest(path, passphrase)?;
+    match (manifest.device_id, manifest.device_name, manifest.data_hash) {
+        (Some(device_id), Some(device_name), Some(data_hash)) => Some(BackupOrigin {
+            path: path.to_string_lossy().into_owned(),
+            device_id,
+            device_name,
+            created_at: manifest.created_at,
+            last_modified: manifest.last_modified,
+            data_hash,
+        }),
+        _ => None,
+    }
+}
+
+/// The newest backup at a remote, if another device made it. The ones this device made cannot
+/// hold changes it has not seen, so they need not be downloaded to compare with.
+pub fn newest_foreign_backup<'a, I: IntoIterator<Item = &'a str>>(names: I, device_id: &str) -> Option<&'a str> {
+    backup::latest_backup_name(names).filter(|name| !backup::is_own_snapshot(name, device_id))
+}
+
+/// Compares the local data and the newest backup against what both looked like at the last sync.
+pub fn compare(local_hash: &str, last_synced_hash: Option<&str>, device_id: &str, remote: Option<&BackupOrigin>) -> SyncStatus {
+    let Some(remote) = remote else {
+        return SyncStatus::NoRemote;
+    };
+    if remote.data_hash == local_hash {
+        SyncStatus::InSync
+    } else if remote.device_id == device_id || Some(remote.data_hash.as_str()) == last_synced_hash {
+        SyncStatus::LocalAhead
+    } else if Some(local_hash) == last_synced_hash {
+        SyncStatus::RemoteAhead
+    } else {
+        SyncStatus::Diverged
+    }
+}
+
+fn state_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(STATE_FILE)
+}
+
+pub fn load_state(config_dir: &Path) -> Result<SyncState, String> {
+    let path = state_path(config_dir);
+    if !path.exists() {
+        return Ok(SyncState::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+/// Remembers the data as of a backup or restore, as the base for the next comparison.
+pub fn record_sync(config_dir: &Path, data_hash: &str, backup_path: Option<&Path>) -> Result<(), String> {
+    let state = SyncState {
+        last_synced_hash: Some(data_hash.to_string()),
+        last_synced_at: Some(Local::now().to_rfc3339()),
+        last_synced_backup: backup_path.and_then(|p| p.file_name()).map(|n| n.to_string_lossy().into_owned()),
+    };
+    fs::create_dir_all(config_dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(&state).map_err(|e| e.to_string())?;
+    fs::write(state_path(config_dir), content).map_err(|e| e.to_string())
+}
+
+pub fn check(db_path: &Path, backup_dir: &Path, config_dir: &Path, passphrase: Option<&str>) -> Result<SyncCheck, String> {
+    check_against(db_path, config_dir, latest_origin(backup_dir, passphrase)?)
+}
+
+/// Compares this device's data with a backup found somewhere other than the backup folder.
+pub fn check_against(db_path: &Path, config_dir: &Path, remote: Option<BackupOrigin>) -> Result<SyncCheck, String> {
+    let device = device::load_or_create(config_dir)?;
+    let local_hash = data_hash(&db::open_path(db_path)?)?;
+    let state = load_state(config_dir)?;
+    Ok(SyncCheck {
+        status: compare(&local_hash, state.last_synced_hash.as_deref(), &device.id, remote.as_ref()),
+        device,
+        local_hash,
+        local_last_modified: last_modified(db_path),
+        remote,
+        last_synced_at: state.last_synced_at,
+    })
+}
+
+/// Compares this device's data with the newest backup in the (synced) backup folder. While the
+/// vault is locked, an encrypted newest backup cannot be opened and counts as no remote.
+#[tauri::command]
+pub async fn check_sync_status(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    target_dir: String,
+) -> Result<SyncCheck, String> {
+    let db_path = db::db_path(&app_handle)?;
+    if !db_path.exists() {
+        return Err("Database file not found".to_string());
+    }
+    let config_dir = device::config_dir(&app_handle)?;
+    let passphrase = crypto::passphrase_if_unlocked(&config_dir, &vault)?;
+    check(&db_path, Path::new(&target_dir), &config_dir, passphrase.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_customer, migrated_db};
+
+    #[test]
+    fn bookkeeping_tables_do_not_change_the_hash() {
+        let conn = migrated_db();
+        insert_customer(&conn, "c1", "Acme Pvt Ltd");
+        let hash = data_hash(&conn).unwrap();
+
+        conn.execute_batch(
+            "UPDATE sync_clock SET version = version + 5, exported_version = 3;
+             UPDATE row_versions SET origin_device = 'other-device', origin_version = 9;
+             INSERT INTO invoice_emails(invoice_number, recipients, subject, attachment, status)
+             VALUES ('AE/24-25/001', 'accounts@acme.example', 'Invoice', 'AE_24-25_001.pdf', 'sent');",
+        )
+        .unwrap();
+        assert_eq!(data_hash(&conn).unwrap(), hash);
+
+        conn.execute("UPDATE customers SET city = 'Howrah' WHERE id = 'c1'", []).unwrap();
+        assert_ne!(data_hash(&conn).unwrap(), hash);
+    }
+
+    #[test]
+    fn only_another_devices_newest_backup_is_worth_downloading() {
+        let device_id = "1a2b3c4d5e6f7a8b";
+        let names = [
+            "invoices-20240702-101500-9f8e7d6c.zip",
+            "invoices-20240703-101500-1a2b3c4d.zip.enc",
+            "notes.txt",
+        ];
+        assert_eq!(newest_foreign_backup(names, device_id), None);
+        assert_eq!(newest_foreign_backup(names, "9f8e7d6c5b4a3f2e"), Some("invoices-20240703-101500-1a2b3c4d.zip.enc"));
+        assert_eq!(newest_foreign_backup(["invoices-20240701-090000.zip"], device_id), Some("invoices-20240701-090000.zip"));
+        assert_eq!(newest_foreign_backup(["notes.txt"], device_id), None);
+    }
+}
@@ -0,0 +1,378 @@
+use crate::device;
+use crate::vault::{self, Vault};
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, Payload};
+use chacha20poly1305::XChaCha20Poly1305;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Error returned when an encrypted backup is restored without a passphrase.
+/// The frontend matches on it to prompt the user.
+pub const PASSPHRASE_REQUIRED: &str = "PASSPHRASE_REQUIRED";
+/// Empty file in the app config dir marking that backups are encrypted, so that is known while
+/// the vault is locked. The passphrase itself is kept in the vault, outside the database, so it
+/// never ends up inside the backups it protects.
+const ENCRYPTION_MARKER: &str = "backup.encrypted";
+/// Where the passphrase used to be kept in plain text; moved into the vault on unlock.
+const LEGACY_PASSPHRASE_FILE: &str = "backup.passphrase";
+
+const MAGIC: &[u8; 8] = b"APEXENC1";
+const SALT_LENGTH: usize = 32;
+/// XChaCha20 nonce minus the 5 bytes the STREAM construction uses for the counter and last-chunk flag.
+const NONCE_PREFIX_LENGTH: usize = 19;
+const KEY_LENGTH: u32 = 32;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+const HEADER_LENGTH: usize = MAGIC.len() + 12 + SALT_LENGTH + NONCE_PREFIX_LENGTH;
+
+/// Argon2id cost parameters; stored in each file header so they can be raised later
+/// without breaking older backups.
+#[derive(Debug, Clone, Copy)]
+struct KdfParams {
+    mem_cost: u32,
+    time_cost: u32,
+    lanes: u32,
+}
+
+const KDF_PARAMS: KdfParams = KdfParams { mem_cost: 19 * 1024, time_cost: 2, lanes: 1 };
+
+fn derive_key(passphrase: &str, salt: &[u8], params: KdfParams) -> Result<Vec<u8>, String> {
+    let config = argon2::Config {
+        variant: argon2::Variant::Argon2id,
+        mem_cost: params.mem_cost,
+        time_cost: params.time_cost,
+        lanes: params.lanes,
+        hash_length: KEY_LENGTH,
+        ..Default::default()
+    };
+    argon2::hash_raw(passphrase.as_bytes(), salt, &config).map_err(|e| e.to_string())
+}
+
+/// Reads until `buf` is full or the reader is exhausted.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize, String> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]).map_err(|e| e.to_string())? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Whether a file starts with the encrypted backup header.
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut magic = [0u8; 8];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| &magic == MAGIC)
+        .unwrap_or(false)
+}
+
+/// Encrypts `source` into `target` with a key derived from the passphrase.
+/// The file is split into chunks that are each authenticated, with the header as associated data.
+pub fn encrypt_file(source: &Path, target: &Path, passphrase: &str) -> Result<(), String> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_PREFIX_LENGTH];
+    rng.fill_bytes(&mut salt);
+    rng.fill_bytes(&mut nonce);
+
+    let mut header = Vec::with_capacity(HEADER_LENGTH);
+    header.extend_from_slice(MAGIC);
+    for value in [KDF_PARAMS.mem_cost, KDF_PARAMS.time_cost, KDF_PARAMS.lanes] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    header.extend_from_slice(&salt);
+    header.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt, KDF_PARAMS)?;
+    let cipher = XChaCha20Poly1305::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let mut encryptor = EncryptorBE32::from_aead(cipher, nonce.as_ref().into());
+
+    let mut input = fs::File::open(source).map_err(|e| e.to_string())?;
+    let mut output = fs::File::create(target).map_err(|e| e.to_string())?;
+    output.write_all(&header).map_err(|e| e.to_string())?;
+
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let mut next = vec![0u8; CHUNK_SIZE];
+    let mut len = read_full(&mut input, &mut chunk)?;
+    loop {
+        // Look one chunk ahead so the final chunk can be flagged, which makes truncation detectable
+        let next_len = if len == CHUNK_SIZE { read_full(&mut input, &mut next)? } else { 0 };
+        let payload = Payload { msg: &chunk[..len], aad: &header };
+        if next_len == 0 {
+            let sealed = encryptor.encrypt_last(payload).map_err(|_| "Encryption failed".to_string())?;
+            output.write_all(&sealed).map_err(|e| e.to_string())?;
+            break;
+        }
+        let sealed = encryptor.encrypt_next(payload).map_err(|_| "Encryption failed".to_string())?;
+        output.write_all(&sealed).map_err(|e| e.to_string())?;
+        std::mem::swap(&mut chunk, &mut next);
+        len = next_len;
+    }
+    output.sync_all().map_err(|e| e.to_string())
+}
+
+/// Decrypts a file written by [`encrypt_file`]. A wrong passphrase, a modified byte or a
+/// truncated file all fail authentication; `target` is removed in that case.
+pub fn decrypt_file(source: &Path, target: &Path, passphrase: &str) -> Result<(), String> {
+    let result = decrypt_into(source, target, passphrase);
+    if result.is_err() {
+        let _ = fs::remove_file(target);
+    }
+    result
+}
+
+fn decrypt_into(source: &Path, target: &Path, passphrase: &str) -> Result<(), String> {
+    let mut input = fs::File::open(source).map_err(|e| e.to_string())?;
+    let mut header = [0u8; HEADER_LENGTH];
+    if read_full(&mut input, &mut header)? != HEADER_LENGTH || &header[..MAGIC.len()] != MAGIC {
+        return Err("Not an encrypted backup".to_string());
+    }
+    let field = |i: usize| {
+        let start = MAGIC.len() + i * 4;
+        u32::from_le_bytes(header[start..start + 4].try_into().unwrap())
+    };
+    let params = KdfParams { mem_cost: field(0), time_cost: field(1), lanes: field(2) };
+    // The header is untrusted until the first chunk authenticates; refuse costs that would stall the app
+    if params.mem_cost > 1024 * 1024 || params.time_cost > 16 || params.lanes > 16 {
+        return Err("Encrypted backup has invalid key derivation parameters".to_string());
+    }
+    let salt_start = MAGIC.len() + 12;
+    let salt = &header[salt_start..salt_start + SALT_LENGTH];
+    let nonce = &header[salt_start + SALT_LENGTH..];
+
+    let key = derive_key(passphrase, salt, params)?;
+    let cipher = XChaCha20Poly1305::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let mut decryptor = DecryptorBE32::from_aead(cipher, nonce.into());
+    let failed = || "Incorrect passphrase, or the backup is corrupted.".to_string();
+
+    let mut output = fs::File::create(target).map_err(|e| e.to_string())?;
+    let mut chunk = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+    let mut next = vec![0u8; CHUNK_SIZE + TAG_SIZE];
+    let mut len = read_full(&mut input, &mut chunk)?;
+    loop {
+        let next_len = if len == chunk.len() { read_full(&mut input, &mut next)? } else { 0 };
+        let payload = Payload { msg: &chunk[..len], aad: &header };
+        if next_len == 0 {
+            let plain = decryptor.decrypt_last(payload).map_err(|_| failed())?;
+            output.write_all(&plain).map_err(|e| e.to_string())?;
+            break;
+        }
+        let plain = decryptor.decrypt_next(payload).map_err(|_| failed())?;
+        output.write_all(&plain).map_err(|e| e.to_string())?;
+        std::mem::swap(&mut chunk, &mut next);
+        len = next_len;
+    }
+    Ok(())
+}
+
+/// Writes a file only the current user can read, for passphrases and credentials.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, contents).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Whether backups are encrypted. Does not need the vault to be unlocked.
+pub fn encryption_enabled(config_dir: &Path) -> bool {
+    config_dir.join(ENCRYPTION_MARKER).exists() || config_dir.join(LEGACY_PASSPHRASE_FILE).exists()
+}
+
+/// The passphrase backups are encrypted with, `None` when encryption is off. Fails with
+/// [`vault::VAULT_LOCKED`] while the vault is locked. Installs that have not set up a vault yet
+/// still read the old plain-text file.
+pub fn stored_passphrase(config_dir: &Path, vault: &Vault) -> Result<Option<String>, String> {
+    if !encryption_enabled(config_dir) {
+        return Ok(None);
+    }
+    let legacy = config_dir.join(LEGACY_PASSPHRASE_FILE);
+    if !vault::exists(config_dir) && legacy.exists() {
+        let passphrase = fs::read_to_string(legacy).map_err(|e| e.to_string())?;
+        return Ok(Some(passphrase).filter(|p| !p.is_empty()));
+    }
+    match vault.get(vault::BACKUP_PASSPHRASE)? {
+        Some(passphrase) => String::from_utf8(passphrase).map(Some).map_err(|e| e.to_string()),
+        None => Err("The backup passphrase is missing from the vault. Set it again under Settings > Backup.".to_string()),
+    }
+}
+
+/// [`stored_passphrase`] for checks that can go ahead without it: a locked vault gives `None`,
+/// so encrypted backups are skipped instead of asking for the vault password.
+pub fn passphrase_if_unlocked(config_dir: &Path, vault: &Vault) -> Result<Option<String>, String> {
+    match stored_passphrase(config_dir, vault) {
+        Err(e) if e == vault::VAULT_LOCKED => Ok(None),
+        result => result,
+    }
+}
+
+/// Moves a passphrase still kept in plain text into the vault.
+pub fn migrate_passphrase(config_dir: &Path, vault: &Vault) -> Result<bool, String> {
+    let legacy = config_dir.join(LEGACY_PASSPHRASE_FILE);
+    if !legacy.exists() {
+        return Ok(false);
+    }
+    let passphrase = fs::read_to_string(&legacy).map_err(|e| e.to_string())?;
+    if !passphrase.is_empty() {
+        if vault.get(vault::BACKUP_PASSPHRASE)?.is_none() {
+            vault.put(vault::BACKUP_PASSPHRASE, passphrase.into_bytes())?;
+        }
+        write_private_file(&config_dir.join(ENCRYPTION_MARKER), b"")?;
+    }
+    fs::remove_file(&legacy).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn set_backup_passphrase(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    let config_dir = device::config_dir(&app_handle)?;
+    let marker = config_dir.join(ENCRYPTION_MARKER);
+    let legacy = config_dir.join(LEGACY_PASSPHRASE_FILE);
+    match passphrase.filter(|p| !p.is_empty()) {
+        Some(passphrase) => {
+            if !vault::exists(&config_dir) {
+                return Err("Set up the vault before turning on backup encryption.".to_string());
+            }
+            vault.put(vault::BACKUP_PASSPHRASE, passphrase.into_bytes())?;
+            write_private_file(&marker, b"")?;
+        }
+        None => {
+            if vault.is_unlocked() {
+                vault.remove(vault::BACKUP_PASSPHRASE)?;
+            }
+            if marker.exists() {
+                fs::remove_file(&marker).map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    if legacy.exists() {
+        fs::remove_file(&legacy).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn is_backup_encryption_enabled(app_handle: tauri::AppHandle) -> Result<bool, String> {
+    Ok(encryption_enabled(&device::config_dir(&app_handle)?))
+}
+
+#[tauri::command]
+pub async fn is_backup_encrypted(path: String) -> Result<bool, String> {
+    Ok(is_encrypted(Path::new(&path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    struct Files {
+        dir: tempfile::TempDir,
+        plain: Vec<u8>,
+    }
+
+    impl Files {
+        fn path(&self, name: &str) -> std::path::PathBuf {
+            self.dir.path().join(name)
+        }
+
+        fn decrypt(&self, passphrase: &str) -> Result<Vec<u8>, String> {
+            decrypt_file(&self.path("backup.enc"), &self.path("out.zip"), passphrase)?;
+            Ok(fs::read(self.path("out.zip")).unwrap())
+        }
+
+        /// Rewrites the encrypted file through `edit` and expects decryption to fail.
+        fn assert_rejected(&self, edit: impl FnOnce(&mut Vec<u8>)) -> String {
+            let path = self.path("backup.enc");
+            let mut data = fs::read(&path).unwrap();
+            edit(&mut data);
+            fs::write(&path, data).unwrap();
+            let error = self.decrypt(PASSPHRASE).unwrap_err();
+            assert!(!self.path("out.zip").exists(), "a failed decryption must not leave output behind");
+            error
+        }
+    }
+
+    /// Encrypts `len` bytes of patterned data.
+    fn encrypted(len: usize) -> Files {
+        let dir = tempfile::tempdir().unwrap();
+        let plain: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        fs::write(dir.path().join("backup.zip"), &plain).unwrap();
+        encrypt_file(&dir.path().join("backup.zip"), &dir.path().join("backup.enc"), PASSPHRASE).unwrap();
+        Files { dir, plain }
+    }
+
+    #[test]
+    fn round_trips_across_chunk_boundaries() {
+        for len in [0, 1, CHUNK_SIZE, 2 * CHUNK_SIZE + 17] {
+            let files = encrypted(len);
+            assert!(is_encrypted(&files.path("backup.enc")));
+            assert_eq!(files.decrypt(PASSPHRASE).unwrap(), files.plain, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn wrong_passphrase_fails() {
+        let files = encrypted(1000);
+        let error = files.decrypt("not the passphrase").unwrap_err();
+        assert!(error.contains("Incorrect passphrase"), "{}", error);
+        assert!(!files.path("out.zip").exists());
+    }
+
+    #[test]
+    fn flipped_ciphertext_byte_fails() {
+        let files = encrypted(CHUNK_SIZE + 100);
+        let error = files.assert_rejected(|data| data[HEADER_LENGTH + CHUNK_SIZE + 50] ^= 1);
+        assert!(error.contains("Incorrect passphrase, or the backup is corrupted"), "{}", error);
+    }
+
+    #[test]
+    fn flipped_header_byte_fails() {
+        // The nonce prefix is not part of the key derivation, so only the header's use as
+        // associated data and as the nonce catches this
+        let files = encrypted(1000);
+        files.assert_rejected(|data| data[HEADER_LENGTH - 1] ^= 1);
+        let files = encrypted(1000);
+        files.assert_rejected(|data| data[MAGIC.len() + 12] ^= 1);
+    }
+
+    #[test]
+    fn truncation_fails() {
+        // Cut at a chunk boundary, so what is left is a whole, valid chunk that is not flagged last
+        let files = encrypted(2 * CHUNK_SIZE + 17);
+        files.assert_rejected(|data| data.truncate(HEADER_LENGTH + CHUNK_SIZE + TAG_SIZE));
+        let files = encrypted(1000);
+        files.assert_rejected(|data| data.truncate(data.len() - 1));
+        let files = encrypted(1000);
+        let error = files.assert_rejected(|data| data.truncate(HEADER_LENGTH - 1));
+        assert_eq!(error, "Not an encrypted backup");
+    }
+
+    #[test]
+    fn oversized_kdf_params_are_rejected() {
+        for (field, value) in [(0, 4 * 1024 * 1024u32), (1, 1000), (2, 64)] {
+            let files = encrypted(10);
+            let error = files.assert_rejected(|data| {
+                let start = MAGIC.len() + field * 4;
+                data[start..start + 4].copy_from_slice(&value.to_le_bytes());
+            });
+            assert!(error.contains("invalid key derivation parameters"), "{}", error);
+        }
+    }
+}
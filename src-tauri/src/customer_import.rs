@@ -0,0 +1,568 @@
+use crate::db;
+use crate::models::Customer;
+use crate::spreadsheet;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Rows shown in the mapping step before the user picks columns.
+const SAMPLE_ROWS: usize = 5;
+
+/// Which column of the file feeds each customer field, by index. Unmapped fields stay empty.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnMapping {
+    pub company_name: Option<usize>,
+    pub gst_number: Option<usize>,
+    pub pan_number: Option<usize>,
+    pub address_line1: Option<usize>,
+    pub address_line2: Option<usize>,
+    pub address_line3: Option<usize>,
+    pub city: Option<usize>,
+    pub state: Option<usize>,
+    pub pincode: Option<usize>,
+    pub email: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportFile {
+    pub headers: Vec<String>,
+    pub sample_rows: Vec<Vec<String>>,
+    pub total_rows: usize,
+    /// Guessed from the header names; the user confirms or changes it.
+    pub suggested_mapping: ColumnMapping,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateMatch {
+    pub id: String,
+    pub company_name: String,
+    pub matched_by: String, // "gstin" | "name"
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportRow {
+    /// Row in the file, counting the header as row 1; for a Tally file, the line of the ledger.
+    pub line: usize,
+    pub customer: Customer,
+    pub action: String, // "create" | "update" | "skip" | "invalid"
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    pub duplicate_of: Option<DuplicateMatch>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub rows: Vec<ImportRow>,
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub invalid: usize,
+    /// False for a dry run.
+    pub committed: bool,
+}
+
+fn header_key(header: &str) -> String {
+    header.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+/// Picks a column for each field by its header, e.g. "GSTIN/UIN" or "Party Name".
+pub fn suggest_mapping(headers: &[String]) -> ColumnMapping {
+    let find = |names: &[&str]| headers.iter().position(|h| names.contains(&header_key(h).as_str()));
+    ColumnMapping {
+        company_name: find(&["companyname", "name", "customer", "customername", "party", "partyname", "company", "ledgername", "businessname"]),
+        gst_number: find(&["gstin", "gst", "gstno", "gstnumber", "gstinuin", "gstregistrationnumber"]),
+        pan_number: find(&["pan", "panno", "pannumber", "panit", "incometaxnumber"]),
+        address_line1: find(&["addressline1", "address1", "address", "addr1", "street"]),
+        address_line2: find(&["addressline2", "address2", "addr2"]),
+        address_line3: find(&["addressline3", "address3", "addr3"]),
+        city: find(&["city", "town", "district"]),
+        state: find(&["state", "statename"]),
+        pincode: find(&["pincode", "pin", "pinno", "postalcode", "postcode", "zip", "zipcode"]),
+        email: find(&["email", "emailid", "emailaddress", "mail", "mailid"]),
+    }
+}
+
+fn gstin_value(c: char) -> Option<u32> {
+    c.to_digit(36)
+}
+
+/// Checks the structure and the check digit of a GSTIN.
+pub fn validate_gstin(gstin: &str) -> Result<(), String> {
+    let pattern = Regex::new(r"^[0-9]{2}[A-Z]{5}[0-9]{4}[A-Z][0-9A-Z]Z[0-9A-Z]$").map_err(|e| e.to_string())?;
+    if !pattern.is_match(gstin) {
+        return Err(format!("GSTIN {} is not in the 15-character format (e.g. 19AFZPT2526E1ZV)", gstin));
+    }
+    let state: u32 = gstin[..2].parse().unwrap_or(0);
+    if !(1..=38).contains(&state) && state != 97 && state != 99 {
+        return Err(format!("GSTIN {} starts with an unknown state code", gstin));
+    }
+    let chars: Vec<char> = gstin.chars().collect();
+    let mut sum = 0;
+    for (i, c) in chars[..14].iter().enumerate() {
+        let product = gstin_value(*c).unwrap_or(0) * if i % 2 == 0 { 1 } else { 2 };
+        sum += product / 36 + product % 36;
+    }
+    let check = (36 - sum % 36) % 36;
+    if gstin_value(chars[14]) != Some(check) {
+        return Err(format!("GSTIN {} has a wrong check digit; it may have been mistyped", gstin));
+    }
+    Ok(())
+}
+
+pub fn validate_pan(pan: &str) -> Result<(), String> {
+    let pattern = Regex::new(r"^[A-Z]{5}[0-9]{4}[A-Z]$").map_err(|e| e.to_string())?;
+    if pattern.is_match(pan) {
+        Ok(())
+    } else {
+        Err(format!("PAN {} is not in the 10-character format (e.g. AFZPT2526E)", pan))
+    }
+}
+
+pub fn validate_pincode(pincode: &str) -> Result<(), String> {
+    if pincode.len() == 6 && pincode.chars().all(|c| c.is_ascii_digit()) && !pincode.starts_with('0') {
+        Ok(())
+    } else {
+        Err(format!("Pincode {} should be 6 digits", pincode))
+    }
+}
+
+/// Checks each address of a comma-separated list.
+pub fn validate_emails(emails: &str) -> Result<(), String> {
+    for address in emails.split([',', ';']).map(str::trim).filter(|a| !a.is_empty()) {
+        if address.parse::<lettre::Address>().is_err() {
+            return Err(format!("Email {} is not a valid address", address));
+        }
+    }
+    Ok(())
+}
+
+/// Lowercase letters and digits only, with the usual spellings of company suffixes unified, so
+/// "M/s. Acme Pvt. Ltd." and "ACME PRIVATE LIMITED" compare equal. "and" is dropped, as "&" is.
+pub fn normalise_name(name: &str) -> String {
+    let lower = name.to_lowercase();
+    let lower = lower.trim_start();
+    let lower = lower.strip_prefix("m/s").unwrap_or(lower);
+    lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| match word {
+            "private" => "pvt",
+            "limited" => "ltd",
+            "company" => "co",
+            "and" => "",
+            other => other,
+        })
+        .collect()
+}
+
+fn cell(row: &[String], column: Option<usize>) -> Option<String> {
+    column.and_then(|i| row.get(i)).map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+pub fn compact_upper(value: Option<String>) -> Option<String> {
+    value.map(|v| v.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase())
+}
+
+fn customer_from_row(row: &[String], mapping: &ColumnMapping) -> Customer {
+    Customer {
+        id: None,
+        company_name: cell(row, mapping.company_name).unwrap_or_default(),
+        address_line1: cell(row, mapping.address_line1).unwrap_or_default(),
+        address_line2: cell(row, mapping.address_line2),
+        address_line3: cell(row, mapping.address_line3),
+        city: cell(row, mapping.city),
+        state: cell(row, mapping.state),
+        // Spreadsheets often hold "700 152" or a number formatted as "700152.0"
+        pincode: cell(row, mapping.pincode).map(|p| p.trim_end_matches(".0").chars().filter(|c| !c.is_whitespace()).collect()),
+        gst_number: compact_upper(cell(row, mapping.gst_number)),
+        pan_number: compact_upper(cell(row, mapping.pan_number)),
+        email: cell(row, mapping.email),
+    }
+}
+
+/// Validates a customer in place: errors make the row invalid, warnings are informational.
+fn check_customer(customer: &mut Customer) -> (Vec<String>, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    if customer.company_name.is_empty() {
+        errors.push("Company name is missing".to_string());
+    }
+    if customer.address_line1.is_empty() {
+        warnings.push("No address; add one before using this customer on an invoice".to_string());
+    }
+    let gstin_ok = match &customer.gst_number {
+        Some(gstin) => validate_gstin(gstin).map_err(|e| errors.push(e)).is_ok(),
+        None => false,
+    };
+    match (&customer.pan_number, &customer.gst_number) {
+        (Some(pan), gstin) => {
+            if let Err(e) = validate_pan(pan) {
+                errors.push(e);
+            } else if let Some(gstin) = gstin.as_ref().filter(|_| gstin_ok) {
+                if &gstin[2..12] != pan.as_str() {
+                    errors.push(format!("PAN {} does not match the PAN inside GSTIN {}", pan, gstin));
+                }
+            }
+        }
+        (None, Some(gstin)) if gstin_ok => {
+            customer.pan_number = Some(gstin[2..12].to_string());
+            warnings.push("PAN taken from the GSTIN".to_string());
+        }
+        _ => {}
+    }
+    if let Some(pincode) = &customer.pincode {
+        if let Err(e) = validate_pincode(pincode) {
+            errors.push(e);
+        }
+    }
+    if let Some(email) = &customer.email {
+        if let Err(e) = validate_emails(email) {
+            errors.push(e);
+        }
+    }
+    (errors, warnings)
+}
+
+/// A customer already in the database, for duplicate detection.
+struct Existing {
+    id: String,
+    company_name: String,
+    gst_number: Option<String>,
+}
+
+fn existing_customers(conn: &Connection) -> Result<Vec<Existing>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, company_name, gst_number FROM customers WHERE deleted_at IS NULL")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(Existing {
+                id: row.get(0)?,
+                company_name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                gst_number: compact_upper(row.get::<_, Option<String>>(2)?.filter(|g| !g.trim().is_empty())),
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Finds the customer this one duplicates: same GSTIN, or the same name when at most one of
+/// them has a GSTIN. Two GSTINs that differ are separate registrations even under one name.
+fn find_duplicate<'a>(
+    customer: &Customer,
+    by_gstin: &HashMap<String, &'a Existing>,
+    by_name: &HashMap<String, Vec<&'a Existing>>,
+) -> Option<DuplicateMatch> {
+    let matched = |existing: &Existing, by: &str| DuplicateMatch {
+        id: existing.id.clone(),
+        company_name: existing.company_name.clone(),
+        matched_by: by.to_string(),
+    };
+    if let Some(existing) = customer.gst_number.as_ref().and_then(|g| by_gstin.get(g)) {
+        return Some(matched(existing, "gstin"));
+    }
+    by_name
+        .get(&normalise_name(&customer.company_name))?
+        .iter()
+        .find(|existing| existing.gst_number.is_none() || customer.gst_number.is_none())
+        .map(|existing| matched(existing, "name"))
+}
+
+/// Works out what importing `rows` would do without writing anything. `on_duplicate` is
+/// "skip" or "update" and decides what happens to rows matching an existing customer.
+pub fn plan(conn: &Connection, rows: &[Vec<String>], mapping: &ColumnMapping, on_duplicate: &str) -> Result<ImportReport, String> {
+    if mapping.company_name.is_none() {
+        return Err("Choose the column that holds the company name".to_string());
+    }
+    let customers = rows
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, row)| row.iter().any(|field| !field.trim().is_empty()))
+        .map(|(index, row)| (index + 1, customer_from_row(row, mapping)))
+        .collect();
+    plan_customers(conn, customers, on_duplicate)
+}
+
+/// Validates and deduplicates customers read from any source, each with the line it came
+/// from, against the database and against each other.
+pub fn plan_customers(conn: &Connection, customers: Vec<(usize, Customer)>, on_duplicate: &str) -> Result<ImportReport, String> {
+    if !matches!(on_duplicate, "skip" | "update") {
+        return Err(format!("Unknown duplicate handling: {}", on_duplicate));
+    }
+    let existing = existing_customers(conn)?;
+    let by_gstin: HashMap<String, &Existing> =
+        existing.iter().filter_map(|e| e.gst_number.clone().map(|g| (g, e))).collect();
+    let mut by_name: HashMap<String, Vec<&Existing>> = HashMap::new();
+    for e in &existing {
+        by_name.entry(normalise_name(&e.company_name)).or_default().push(e);
+    }
+    // Earlier rows of the same file, keyed the same way
+    let mut seen_gstin: HashMap<String, usize> = HashMap::new();
+    let mut seen_name: HashMap<String, (usize, bool)> = HashMap::new();
+
+    let mut report = ImportReport::default();
+    for (line, mut customer) in customers {
+        let (errors, mut warnings) = check_customer(&mut customer);
+        let name_key = normalise_name(&customer.company_name);
+        let duplicate_of = find_duplicate(&customer, &by_gstin, &by_name);
+
+        let earlier = match &customer.gst_number {
+            Some(gstin) => seen_gstin.get(gstin).copied(),
+            None => None,
+        }
+        .or_else(|| {
+            seen_name
+                .get(&name_key)
+                .filter(|(_, had_gstin)| !had_gstin || customer.gst_number.is_none())
+                .map(|(line, _)| *line)
+        });
+
+        let action = if !errors.is_empty() {
+            "invalid"
+        } else if let Some(earlier) = earlier {
+            warnings.push(format!("Same customer as row {}", earlier));
+            "skip"
+        } else if duplicate_of.is_some() {
+            on_duplicate
+        } else {
+            "create"
+        };
+        if action != "invalid" && earlier.is_none() {
+            if let Some(gstin) = &customer.gst_number {
+                seen_gstin.insert(gstin.clone(), line);
+            }
+            seen_name.entry(name_key).or_insert((line, customer.gst_number.is_some()));
+        }
+        match action {
+            "create" => report.created += 1,
+            "update" => report.updated += 1,
+            "skip" => report.skipped += 1,
+            _ => report.invalid += 1,
+        }
+        report.rows.push(ImportRow { line, customer, action: action.to_string(), errors, warnings, duplicate_of });
+    }
+    Ok(report)
+}
+
+pub fn new_id() -> String {
+    let mut id = [0u8; 8];
+    ChaCha20Rng::from_entropy().fill_bytes(&mut id);
+    hex::encode(id)
+}
+
+/// Applies a plan in one transaction: either every row goes in or none does.
+pub fn commit(conn: &mut Connection, report: &mut ImportReport) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    write_rows(&tx, report)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    report.committed = true;
+    Ok(())
+}
+
+/// Inserts and updates the planned customers on a connection the caller has already opened a
+/// transaction on. Updates only overwrite the fields the file has a value for.
+pub fn write_rows(tx: &Connection, report: &mut ImportReport) -> Result<(), String> {
+    for row in report.rows.iter_mut() {
+        let c = &mut row.customer;
+        match row.action.as_str() {
+            "create" => {
+                let id = new_id();
+                tx.execute(
+                    "INSERT INTO customers(id, company_name, gst_number, pan_number, address_line1, address_line2, address_line3, city, state, pincode, email)
+                     VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    params![
+                        id,
+                        c.company_name,
+                        c.gst_number.clone().unwrap_or_default(),
+                        c.pan_number.clone().unwrap_or_default(),
+                        c.address_line1,
+                        c.address_line2.clone().unwrap_or_default(),
+                        c.address_line3.clone().unwrap_or_default(),
+                        c.city.clone().unwrap_or_default(),
+                        c.state.clone().unwrap_or_default(),
+                        c.pincode.clone().unwrap_or_default(),
+                        c.email
+                    ],
+                )
+                .map_err(|e| format!("Row {}: {}", row.line, e))?;
+                c.id = Some(id);
+            }
+            "update" => {
+                let id = row.duplicate_of.as_ref().map(|d| d.id.clone()).ok_or("Update without a matching customer")?;
+                tx.execute(
+                    "UPDATE customers SET
+                        company_name = ?2,
+                        gst_number = COALESCE(?3, gst_number), pan_number = COALESCE(?4, pan_number),
+                        address_line1 = COALESCE(NULLIF(?5, ''), address_line1), address_line2 = COALESCE(?6, address_line2),
+                        address_line3 = COALESCE(?7, address_line3), city = COALESCE(?8, city), state = COALESCE(?9, state),
+                        pincode = COALESCE(?10, pincode), email = COALESCE(?11, email)
+                     WHERE id = ?1",
+                    params![id, c.company_name, c.gst_number, c.pan_number, c.address_line1, c.address_line2, c.address_line3, c.city, c.state, c.pincode, c.email],
+                )
+                .map_err(|e| format!("Row {}: {}", row.line, e))?;
+                c.id = Some(id);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Reads the header and a few rows so the user can map the columns.
+#[tauri::command]
+pub async fn read_customer_import_file(path: String) -> Result<ImportFile, String> {
+    let rows = spreadsheet::read_table(Path::new(&path))?;
+    let headers = rows.first().cloned().ok_or("The file is empty")?;
+    let data: Vec<&Vec<String>> = rows.iter().skip(1).filter(|row| row.iter().any(|f| !f.trim().is_empty())).collect();
+    Ok(ImportFile {
+        suggested_mapping: suggest_mapping(&headers),
+        sample_rows: data.iter().take(SAMPLE_ROWS).map(|row| row.to_vec()).collect(),
+        total_rows: data.len(),
+        headers,
+    })
+}
+
+/// Validates and deduplicates the file against the customer list. With `dry_run` nothing is
+/// written; otherwise the report's rows are applied in a single transaction.
+#[tauri::command]
+pub async fn import_customers(
+    app_handle: tauri::AppHandle,
+    path: String,
+    mapping: ColumnMapping,
+    on_duplicate: String,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let rows = spreadsheet::read_table(Path::new(&path))?;
+    let mut conn = db::open(&app_handle)?;
+    let mut report = plan(&conn, &rows, &mapping, &on_duplicate)?;
+    if !dry_run {
+        commit(&mut conn, &mut report)?;
+        log::info!("Imported customers from {}: {} created, {} updated", path, report.created, report.updated);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_customer, migrated_db};
+
+    fn table(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        let mut table = vec![vec!["Party Name".to_string(), "GSTIN/UIN".to_string(), "City".to_string()]];
+        table.extend(rows.iter().map(|row| row.iter().map(|field| field.to_string()).collect()));
+        table
+    }
+
+    fn mapping() -> ColumnMapping {
+        suggest_mapping(&table(&[])[0])
+    }
+
+    fn customer_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM customers", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn the_gstin_check_digit_catches_a_typo() {
+        assert_eq!(validate_gstin("19AFZPT2526E1ZV"), Ok(()));
+        let error = validate_gstin("19AFZPT2536E1ZV").unwrap_err();
+        assert!(error.contains("wrong check digit"), "{}", error);
+        assert!(validate_gstin("19AFZPT2526E1ZW").unwrap_err().contains("wrong check digit"));
+        assert!(validate_gstin("19AFZPT2526E1Z").unwrap_err().contains("15-character format"));
+    }
+
+    #[test]
+    fn the_special_state_codes_are_accepted() {
+        // 97 for other territories, 99 for the centre's jurisdiction
+        assert_eq!(validate_gstin("97AAACA1234A1ZD"), Ok(()));
+        assert_eq!(validate_gstin("99AAACA1234A1Z9"), Ok(()));
+        assert!(validate_gstin("98AAACA1234A1Z9").unwrap_err().contains("unknown state code"));
+        assert!(validate_gstin("00AAACA1234A1Z9").unwrap_err().contains("unknown state code"));
+    }
+
+    #[test]
+    fn company_suffixes_are_spelled_one_way() {
+        assert_eq!(normalise_name("M/s. Acme Pvt. Ltd."), normalise_name("ACME PRIVATE LIMITED"));
+        assert_eq!(normalise_name("Sharma and Company"), normalise_name("SHARMA & CO."));
+        assert_ne!(normalise_name("Acme Pvt Ltd"), normalise_name("Acme Solar Pvt Ltd"));
+    }
+
+    #[test]
+    fn the_same_name_under_another_gstin_is_a_separate_customer() {
+        let conn = migrated_db();
+        insert_customer(&conn, "c1", "Acme Pvt Ltd");
+        conn.execute("UPDATE customers SET gst_number = '19AAACA1234A1ZH' WHERE id = 'c1'", []).unwrap();
+
+        let rows = table(&[&["ACME PRIVATE LIMITED", "27AAACA1234A1ZK", "Mumbai"], &["Acme Private Ltd", "19 aaaca1234a1zh", ""]]);
+        let report = plan(&conn, &rows, &mapping(), "update").unwrap();
+        let actions: Vec<&str> = report.rows.iter().map(|row| row.action.as_str()).collect();
+        assert_eq!(actions, vec!["create", "update"]);
+        assert!(report.rows[0].duplicate_of.is_none());
+        assert_eq!(report.rows[0].customer.pan_number.as_deref(), Some("AAACA1234A"));
+        assert_eq!(report.rows[1].duplicate_of.as_ref().unwrap().matched_by, "gstin");
+
+        // Without a GSTIN of its own, the name alone is enough
+        let report = plan(&conn, &table(&[&["M/s. Acme Pvt. Ltd.", "", "Kolkata"]]), &mapping(), "update").unwrap();
+        let duplicate = report.rows[0].duplicate_of.as_ref().unwrap();
+        assert_eq!((duplicate.id.as_str(), duplicate.matched_by.as_str()), ("c1", "name"));
+    }
+
+    #[test]
+    fn repeats_within_the_file_point_at_the_earlier_row() {
+        let conn = migrated_db();
+        let rows = table(&[
+            &["Sunrise Traders", "19AFZPT2526E1ZV", "Howrah"],
+            &["Green Leaf Co", "", "Siliguri"],
+            &["SUNRISE TRADERS", "19AFZPT2526E1ZV", "Howrah"],
+            &["Green Leaf Company", "", ""],
+            &["Sunrise Traders", "", ""],
+        ]);
+        let report = plan(&conn, &rows, &mapping(), "skip").unwrap();
+        let outcome: Vec<(usize, &str, Vec<String>)> = report
+            .rows
+            .iter()
+            .map(|row| (row.line, row.action.as_str(), row.warnings.iter().filter(|w| w.starts_with("Same")).cloned().collect()))
+            .collect();
+        assert_eq!(
+            outcome,
+            vec![
+                (2, "create", vec![]),
+                (3, "create", vec![]),
+                (4, "skip", vec!["Same customer as row 2".to_string()]),
+                (5, "skip", vec!["Same customer as row 3".to_string()]),
+                (6, "skip", vec!["Same customer as row 2".to_string()]),
+            ]
+        );
+        assert_eq!((report.created, report.skipped), (2, 3));
+    }
+
+    #[test]
+    fn a_failing_row_leaves_nothing_written() {
+        let mut conn = migrated_db();
+        conn.execute_batch(
+            "CREATE TEMP TRIGGER refuse BEFORE INSERT ON customers WHEN NEW.company_name = 'Green Leaf Co'
+             BEGIN SELECT RAISE(ABORT, 'disk is full'); END;",
+        )
+        .unwrap();
+        let rows = table(&[&["Sunrise Traders", "", "Howrah"], &["Green Leaf Co", "", "Siliguri"]]);
+        let mut report = plan(&conn, &rows, &mapping(), "skip").unwrap();
+        assert_eq!(report.created, 2);
+
+        assert_eq!(commit(&mut conn, &mut report).unwrap_err(), "Row 3: disk is full");
+        assert!(!report.committed);
+        assert_eq!(customer_count(&conn), 0);
+
+        conn.execute_batch("DROP TRIGGER refuse").unwrap();
+        commit(&mut conn, &mut report).unwrap();
+        assert_eq!(customer_count(&conn), 2);
+    }
+}
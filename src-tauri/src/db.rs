@@ -0,0 +1,55 @@
+use rusqlite::Connection;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Name of the database file loaded by the frontend through `sqlite:invoices.db`.
+pub const DB_FILE_NAME: &str = "invoices.db";
+
+/// Resolves the live database file. The sql plugin keeps it in the app config dir,
+/// older installs may still have it in the app data dir.
+pub fn db_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Failed to resolve app config dir")?;
+    let data_dir = app_handle.path_resolver().app_data_dir().ok_or("Failed to resolve app data dir")?;
+
+    let db_path_config = config_dir.join(DB_FILE_NAME);
+    let db_path_data = data_dir.join(DB_FILE_NAME);
+
+    if !db_path_config.exists() && db_path_data.exists() {
+        return Ok(db_path_data);
+    }
+    Ok(db_path_config)
+}
+
+/// Opens a connection to the live database alongside the sql plugin's pool.
+pub fn open(app_handle: &tauri::AppHandle) -> Result<Connection, String> {
+    let path = db_path(app_handle)?;
+    if !path.exists() {
+        return Err("Database file not found".to_string());
+    }
+    open_path(&path)
+}
+
+/// Opens a connection to an arbitrary database file with the pragmas the app relies on.
+pub fn open_path(path: &std::path::Path) -> Result<Connection, String> {
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    // The webview may be holding a write lock through the plugin pool
+    conn.busy_timeout(Duration::from_secs(10)).map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "foreign_keys", "ON").map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// Highest migration applied to a database, 0 for files that predate migrations.
+pub fn schema_version(conn: &Connection) -> Result<i64, String> {
+    let has_table: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !has_table {
+        return Ok(0);
+    }
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success = 1", [], |row| row.get(0))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,76 @@
+use crate::crypto;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File in the app config dir identifying this installation. It is never part of a backup,
+/// so a restored database keeps the identity of the machine it was restored on.
+const DEVICE_FILE: &str = "device.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Device {
+    pub id: String,
+    /// Shown to the other devices when their copy conflicts with this one.
+    pub name: String,
+}
+
+fn default_name() -> String {
+    ["COMPUTERNAME", "HOSTNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "This computer".to_string())
+}
+
+fn device_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(DEVICE_FILE)
+}
+
+fn save(config_dir: &Path, device: &Device) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(device).map_err(|e| e.to_string())?;
+    crypto::write_private_file(&device_path(config_dir), content.as_bytes())
+}
+
+/// Reads this device's identity, creating one with a random ID on first use.
+pub fn load_or_create(config_dir: &Path) -> Result<Device, String> {
+    let path = device_path(config_dir);
+    if path.exists() {
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        return serde_json::from_str(&content).map_err(|e| e.to_string());
+    }
+    let mut id = [0u8; 16];
+    ChaCha20Rng::from_entropy().fill_bytes(&mut id);
+    let device = Device { id: hex::encode(id), name: default_name() };
+    save(config_dir, &device)?;
+    Ok(device)
+}
+
+pub fn config_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle.path_resolver().app_config_dir().ok_or_else(|| "Failed to resolve app config dir".to_string())
+}
+
+pub fn this_device(app_handle: &tauri::AppHandle) -> Result<Device, String> {
+    load_or_create(&config_dir(app_handle)?)
+}
+
+#[tauri::command]
+pub async fn get_device_info(app_handle: tauri::AppHandle) -> Result<Device, String> {
+    this_device(&app_handle)
+}
+
+#[tauri::command]
+pub async fn set_device_name(app_handle: tauri::AppHandle, name: String) -> Result<Device, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Enter a name for this device".to_string());
+    }
+    let config_dir = config_dir(&app_handle)?;
+    let device = Device { name: name.to_string(), ..load_or_create(&config_dir)? };
+    save(&config_dir, &device)?;
+    Ok(device)
+}
@@ -0,0 +1,303 @@
+use crate::{crypto, db, migrations, updater};
+use chrono::Local;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Base name of the log files: `apex-invoice.log`, rotated to `apex-invoice_<timestamp>.log`.
+pub const LOG_NAME: &str = "apex-invoice";
+/// The log plugin rotates the file at startup once it has grown past this.
+pub const MAX_LOG_FILE_SIZE: u128 = 2 * 1024 * 1024;
+/// Rotated log files kept next to the current one.
+const KEEP_ROTATED_LOGS: usize = 5;
+/// Config files that go into a bundle, with their secrets redacted.
+const CONFIG_FILES: [&str; 4] = ["sync.json", "gdrive.json", "sync-state.json", "device.json"];
+/// Key fragments whose values never leave the machine: credentials, bank and tax identifiers, contact details.
+const SENSITIVE_KEYS: [&str; 14] = [
+    "password", "secret", "token", "passphrase", "key", "username", "account", "ifsc", "gstin", "email", "address", "phone",
+    "mobile", "upi",
+];
+const REDACTED: &str = "[redacted]";
+
+fn is_log_file(name: &str) -> bool {
+    name == format!("{}.log", LOG_NAME)
+        || (name.starts_with(&format!("{}_", LOG_NAME)) && (name.ends_with(".log") || name.ends_with(".log.bak")))
+}
+
+/// The current log followed by the rotated ones, newest first.
+pub fn log_files(log_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !log_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(log_dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.file_name().and_then(|n| n.to_str()).is_some_and(is_log_file))
+        .collect();
+    // Rotated names embed a sortable timestamp; the current file goes first
+    let current = format!("{}.log", LOG_NAME);
+    files.sort_by_key(|path| {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        (name != current, std::cmp::Reverse(name))
+    });
+    Ok(files)
+}
+
+/// Deletes rotated logs beyond [`KEEP_ROTATED_LOGS`]; the plugin itself keeps every one.
+pub fn prune_logs(log_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut pruned = Vec::new();
+    for path in log_files(log_dir)?.into_iter().skip(1 + KEEP_ROTATED_LOGS) {
+        fs::remove_file(&path).map_err(|e| e.to_string())?;
+        pruned.push(path);
+    }
+    Ok(pruned)
+}
+
+fn is_sensitive(key: &str) -> bool {
+    let key = key.to_lowercase().replace(['_', '-'], "");
+    // "pan" on its own would also match "company"
+    SENSITIVE_KEYS.iter().any(|s| key.contains(s)) || key == "pan" || key.starts_with("pan") || key.ends_with("pan")
+}
+
+fn redact_string(key: &str, value: &str) -> Value {
+    if value.starts_with("data:") {
+        return Value::String(format!("[image, {} bytes]", value.len()));
+    }
+    if is_sensitive(key) && !value.is_empty() {
+        return Value::String(REDACTED.to_string());
+    }
+    // Settings often hold a JSON document as text, e.g. the company settings
+    if let Ok(mut parsed @ (Value::Object(_) | Value::Array(_))) = serde_json::from_str::<Value>(value) {
+        redact(&mut parsed);
+        return parsed;
+    }
+    Value::String(value.to_string())
+}
+
+/// Replaces the values of sensitive keys, keeping whether they were set so support can tell
+/// a missing setting from a wrong one.
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(s) => *value = redact_string(key, s),
+                    Value::Null | Value::Bool(_) => {}
+                    _ if is_sensitive(key) => *value = Value::String(REDACTED.to_string()),
+                    other => redact(other),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Settings rows of the database, redacted.
+fn database_settings(db_path: &Path) -> Result<Value, String> {
+    let conn = db::open_path(db_path)?;
+    let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key").map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))
+        .map_err(|e| e.to_string())?;
+    let mut settings = Map::new();
+    for row in rows {
+        let (key, value) = row.map_err(|e| e.to_string())?;
+        let value = value.map(|v| redact_string(&key, &v)).unwrap_or(Value::Null);
+        settings.insert(key, value);
+    }
+    Ok(Value::Object(settings))
+}
+
+/// Size, schema and row counts of a database, plus SQLite's own consistency check.
+pub fn database_stats(db_path: &Path) -> Result<Value, String> {
+    let size = fs::metadata(db_path).map_err(|e| e.to_string())?.len();
+    let conn = db::open_path(db_path)?;
+    let pragma = |name: &str| -> Result<Value, String> {
+        conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, rusqlite::types::Value>(0))
+            .map(|v| match v {
+                rusqlite::types::Value::Integer(i) => json!(i),
+                rusqlite::types::Value::Text(s) => json!(s),
+                _ => Value::Null,
+            })
+            .map_err(|e| e.to_string())
+    };
+
+    let mut stmt = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")
+        .map_err(|e| e.to_string())?;
+    let names: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut tables = BTreeMap::new();
+    for name in names {
+        let count: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\"")), [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        tables.insert(name, count);
+    }
+
+    Ok(json!({
+        "path": db_path.to_string_lossy(),
+        "sizeBytes": size,
+        "schemaVersion": db::schema_version(&conn)?,
+        "latestSchemaVersion": migrations::latest_version(),
+        "journalMode": pragma("journal_mode")?,
+        "pageSize": pragma("page_size")?,
+        "pageCount": pragma("page_count")?,
+        "freelistCount": pragma("freelist_count")?,
+        "quickCheck": pragma("quick_check")?,
+        "tables": tables,
+    }))
+}
+
+/// Where a bundle gathers its contents from.
+pub struct Sources<'a> {
+    pub log_dir: &'a Path,
+    pub config_dir: &'a Path,
+    pub db_path: Option<&'a Path>,
+    /// Written as `app.json`.
+    pub app_info: Value,
+}
+
+/// Writes `apex-diagnostics-<timestamp>.zip` to `target_dir`. Parts that cannot be read are
+/// recorded as an error in their place, so a broken install still produces a bundle.
+pub fn write_bundle(target_dir: &Path, sources: &Sources) -> Result<PathBuf, String> {
+    fs::create_dir_all(target_dir).map_err(|e| e.to_string())?;
+    let target = target_dir.join(format!("apex-diagnostics-{}.zip", Local::now().format("%Y%m%d-%H%M%S")));
+    let or_error = |result: Result<Value, String>| result.unwrap_or_else(|e| json!({ "error": e }));
+
+    let db_path = sources.db_path.filter(|p| p.exists());
+    let database = or_error(db_path.ok_or_else(|| "Database file not found".to_string()).and_then(database_stats));
+    let mut settings = Map::new();
+    settings.insert(
+        "database".to_string(),
+        or_error(db_path.ok_or_else(|| "Database file not found".to_string()).and_then(database_settings)),
+    );
+    for name in CONFIG_FILES {
+        let path = sources.config_dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| serde_json::from_str::<Value>(&text).map_err(|e| e.to_string()))
+            .map(|mut value| {
+                redact(&mut value);
+                value
+            });
+        settings.insert(name.to_string(), or_error(parsed));
+    }
+
+    let file = fs::File::create(&target).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let documents = [("app.json", &sources.app_info), ("database.json", &database), ("settings.json", &Value::Object(settings))];
+    for (name, value) in documents {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(&serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    }
+    for path in log_files(sources.log_dir)? {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        zip.start_file(format!("logs/{}", name), options).map_err(|e| e.to_string())?;
+        std::io::copy(&mut fs::File::open(&path).map_err(|e| e.to_string())?, &mut zip).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(target)
+}
+
+/// Zips the logs, versions, database stats and redacted settings into `target_dir` for support.
+#[tauri::command]
+pub async fn create_diagnostics_bundle(app_handle: tauri::AppHandle, target_dir: String) -> Result<String, String> {
+    let resolver = app_handle.path_resolver();
+    let log_dir = resolver.app_log_dir().ok_or("Failed to resolve app log dir")?;
+    let config_dir = resolver.app_config_dir().ok_or("Failed to resolve app config dir")?;
+    let db_path = db::db_path(&app_handle).ok();
+    let app_info = json!({
+        "appVersion": app_handle.package_info().version.to_string(),
+        "latestSchemaVersion": migrations::latest_version(),
+        "platform": updater::platform_key(),
+        "osFamily": std::env::consts::FAMILY,
+        "backupEncryption": crypto::encryption_enabled(&config_dir),
+        "createdAt": Local::now().to_rfc3339(),
+    });
+
+    let sources = Sources { log_dir: &log_dir, config_dir: &config_dir, db_path: db_path.as_deref(), app_info };
+    let path = write_bundle(Path::new(&target_dir), &sources)?;
+    log::info!("Diagnostics bundle written to {}", path.display());
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_db_at;
+    use std::io::Read;
+
+    fn bundle_text(bundle: &Path) -> String {
+        let mut zip = zip::ZipArchive::new(fs::File::open(bundle).unwrap()).unwrap();
+        let mut text = String::new();
+        for i in 0..zip.len() {
+            zip.by_index(i).unwrap().read_to_string(&mut text).unwrap();
+        }
+        text
+    }
+
+    #[test]
+    fn a_bundle_leaves_out_credentials_and_bank_details() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("invoices.db");
+        let conn = migrated_db_at(&db_path);
+        let company = json!({ "companyName": "Apex Solar", "accountNumber": "123456782674", "ifscCode": "SBIN0001234", "upiId": "apex@sbi" });
+        let smtp = json!({ "host": "smtp.example.com", "port": 587, "username": "billing@apexsolar.in", "fromAddress": "accounts@apexsolar.in", "password": "smtp-hunter2" });
+        for (key, value) in [("company_settings", company), ("smtp_settings", smtp)] {
+            conn.execute("INSERT OR REPLACE INTO settings(key, value) VALUES (?1, ?2)", rusqlite::params![key, value.to_string()])
+                .unwrap();
+        }
+        drop(conn);
+
+        let webdav = json!({ "kind": "webdav", "url": "https://cloud.example.com/dav/", "username": "apex-dav", "password": "dav-hunter2" });
+        let s3 = json!({ "kind": "s3", "endpoint": "https://s3.example.com", "region": "ap-south-1", "bucket": "apex", "accessKeyId": "AKIAAPEXEXAMPLE", "secretAccessKey": "s3-hunter2" });
+        let secrets = [
+            "123456782674", "SBIN0001234", "apex@sbi", "billing@apexsolar.in", "accounts@apexsolar.in", "smtp-hunter2",
+            "apex-dav", "dav-hunter2", "AKIAAPEXEXAMPLE", "s3-hunter2",
+        ];
+        for sync in [webdav, s3] {
+            fs::write(dir.path().join("sync.json"), sync.to_string()).unwrap();
+            let sources = Sources { log_dir: &dir.path().join("logs"), config_dir: dir.path(), db_path: Some(&db_path), app_info: json!({}) };
+            let bundle = write_bundle(&dir.path().join("out"), &sources).unwrap();
+            let text = bundle_text(&bundle);
+            fs::remove_file(bundle).unwrap();
+
+            for secret in secrets {
+                assert!(!text.contains(secret), "{} is in the bundle", secret);
+            }
+            // Whether a value was set and the harmless ones still come through
+            assert!(text.contains(REDACTED) && text.contains("Apex Solar") && text.contains("smtp.example.com"), "{}", text);
+        }
+    }
+
+    #[test]
+    fn pruning_keeps_the_current_log_and_the_newest_rotated_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let rotated: Vec<String> = (1..=8).map(|day| format!("{}_2024-07-0{}_10-00-00.log", LOG_NAME, day)).collect();
+        for name in rotated.iter().map(String::as_str).chain([&*format!("{}.log", LOG_NAME), "other.log"]) {
+            fs::write(dir.path().join(name), "log").unwrap();
+        }
+
+        let pruned = prune_logs(dir.path()).unwrap();
+        let names = |paths: Vec<PathBuf>| -> Vec<String> {
+            paths.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect()
+        };
+        assert_eq!(names(pruned), vec![rotated[2].clone(), rotated[1].clone(), rotated[0].clone()]);
+        let mut kept = vec![format!("{}.log", LOG_NAME)];
+        kept.extend(rotated[3..].iter().rev().cloned());
+        assert_eq!(names(log_files(dir.path()).unwrap()), kept);
+        assert!(dir.path().join("other.log").exists());
+        assert!(prune_logs(dir.path()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,755 @@
+use crate::{backup, crypto};
+use regex::Regex;
+use reqwest::header::{CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE, RANGE};
+use reqwest::{Client, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+/// Emitted with a [`ProgressEvent`] while a shared-link download is running.
+pub const PROGRESS_EVENT: &str = "gdrive-download-progress";
+const DRIVE_BASE_URL: &str = "https://drive.google.com";
+/// Attempts per download before giving up; every retry resumes where the last one stopped.
+const MAX_ATTEMPTS: u32 = 5;
+/// A connection that delivers nothing for this long is dropped and resumed.
+const STALL_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often the cancellation flag is checked while waiting for data.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadErrorKind {
+    /// The text is not a Drive file or folder link.
+    InvalidLink,
+    NotFound,
+    /// The file or folder is not shared with "Anyone with the link".
+    AccessDenied,
+    /// Drive's download limit for the file was reached; it lifts after a while.
+    QuotaExceeded,
+    NoBackupInFolder,
+    /// Drive sent something other than a backup, usually a web page.
+    NotABackup,
+    /// The connection failed or dropped. The partial file is kept and resumed next time.
+    Network,
+    /// Stopped by the user. The partial file is discarded.
+    Cancelled,
+    Io,
+}
+
+/// Why a link could not be downloaded. Serialized as `{ kind, message }` so the frontend can
+/// show the message as is and still react to the kind.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadError {
+    pub kind: DownloadErrorKind,
+    pub message: String,
+}
+
+impl DownloadError {
+    fn new(kind: DownloadErrorKind, message: impl Into<String>) -> Self {
+        DownloadError { kind, message: message.into() }
+    }
+
+    fn io(e: impl fmt::Display) -> Self {
+        Self::new(DownloadErrorKind::Io, e.to_string())
+    }
+
+    fn network(e: impl fmt::Display) -> Self {
+        Self::new(DownloadErrorKind::Network, format!("Could not download from Google Drive: {}", e))
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub downloaded: u64,
+    /// Unknown when Drive does not send a length.
+    pub total: Option<u64>,
+    /// Bytes kept from an earlier, interrupted attempt.
+    pub resumed_from: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent {
+    pub download_id: String,
+    #[serde(flatten)]
+    pub progress: Progress,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriveLink {
+    pub id: String,
+    pub is_folder: bool,
+}
+
+/// Accepts folder links, file links (`/d/<id>`, `?id=<id>`) and bare IDs.
+pub fn parse_link(link: &str) -> Result<DriveLink, DownloadError> {
+    let link = link.trim();
+    let patterns = [r"/folders/([a-zA-Z0-9_-]+)", r"/d/([a-zA-Z0-9_-]+)", r"id=([a-zA-Z0-9_-]+)", r"^([a-zA-Z0-9_-]{10,50})$"];
+    patterns
+        .iter()
+        .find_map(|pattern| Regex::new(pattern).unwrap().captures(link).map(|caps| caps[1].to_string()))
+        .map(|id| DriveLink { id, is_folder: link.contains("/folders/") })
+        .ok_or_else(|| DownloadError::new(DownloadErrorKind::InvalidLink, "This is not a Google Drive file or folder link."))
+}
+
+fn is_html(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/html"))
+}
+
+/// The error for an error status or a web page served in place of the file: a quota, sign-in or
+/// missing-file page, or anything else as not a backup.
+fn classify_page(status: StatusCode, final_url: &Url, page: &str) -> DownloadError {
+    if page.contains("Quota exceeded") || page.contains("Too many users have viewed or downloaded this file") || status == StatusCode::TOO_MANY_REQUESTS {
+        DownloadError::new(
+            DownloadErrorKind::QuotaExceeded,
+            "Google Drive's download limit for this file was reached. Try again in a few hours, or download it while signed in.",
+        )
+    } else if status == StatusCode::NOT_FOUND || page.contains("the file you have requested does not exist") {
+        DownloadError::new(DownloadErrorKind::NotFound, "Google Drive could not find this file or folder. Check that the link is complete.")
+    } else if status == StatusCode::FORBIDDEN
+        || status == StatusCode::UNAUTHORIZED
+        || final_url.host_str() == Some("accounts.google.com")
+        || page.contains("ServiceLogin")
+    {
+        DownloadError::new(
+            DownloadErrorKind::AccessDenied,
+            "Google Drive asked for a sign-in. Share the file or folder with \"Anyone with the link\" and try again.",
+        )
+    } else {
+        DownloadError::new(
+            DownloadErrorKind::NotABackup,
+            "Google Drive returned a web page instead of a backup. Check that the link points to a backup file or folder.",
+        )
+    }
+}
+
+fn check_status(response: Response) -> Result<Response, DownloadError> {
+    let status = response.status();
+    if status.is_success() || status == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(response);
+    }
+    Err(match status {
+        StatusCode::NOT_FOUND | StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED | StatusCode::TOO_MANY_REQUESTS => {
+            classify_page(status, response.url(), "")
+        }
+        _ => DownloadError::network(format!("Google Drive responded with {}", status)),
+    })
+}
+
+/// The large-file warning page ("can't scan this file for viruses") links to the real download,
+/// either through a form or through a `confirm=` parameter.
+fn confirm_url(download_url: &str, page: &str) -> Option<String> {
+    let form = Regex::new(r#"(?s)<form[^>]*action="([^"]+)"[^>]*>(.*?)</form>"#).unwrap();
+    if let Some(caps) = form.captures(page) {
+        let input = Regex::new(r#"<input[^>]*type="hidden"[^>]*name="([^"]+)"[^>]*value="([^"]*)""#).unwrap();
+        let params: Vec<(String, String)> = input
+            .captures_iter(&caps[2])
+            .map(|c| (c[1].to_string(), c[2].replace("&amp;", "&")))
+            .collect();
+        if let Ok(url) = Url::parse_with_params(&caps[1].replace("&amp;", "&"), &params) {
+            return Some(url.to_string());
+        }
+    }
+    let code = Regex::new(r"confirm=([a-zA-Z0-9_-]+)").unwrap().captures(page)?;
+    Some(format!("{}&confirm={}", download_url, &code[1]))
+}
+
+async fn request(client: &Client, url: &str, offset: u64, etag: Option<&str>) -> Result<Response, DownloadError> {
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+        // Without a match Drive sends the whole (changed) file instead of the rest of the old one
+        if let Some(etag) = etag {
+            request = request.header(IF_RANGE, etag);
+        }
+    }
+    request.send().await.map_err(DownloadError::network)
+}
+
+/// Requests a file from `offset` on, getting past the virus scan warning for large files.
+async fn open_download(client: &Client, base: &str, file_id: &str, offset: u64, etag: Option<&str>) -> Result<Response, DownloadError> {
+    let download_url = format!("{}/uc?id={}&export=download", base, file_id);
+    let response = request(client, &download_url, offset, etag).await?;
+    if !is_html(&response) {
+        return check_status(response);
+    }
+    let (status, url) = (response.status(), response.url().clone());
+    let page = response.text().await.map_err(DownloadError::network)?;
+    let confirmed = confirm_url(&download_url, &page).ok_or_else(|| classify_page(status, &url, &page))?;
+
+    let response = request(client, &confirmed, offset, etag).await?;
+    if is_html(&response) {
+        let (status, url) = (response.status(), response.url().clone());
+        let page = response.text().await.map_err(DownloadError::network)?;
+        return Err(classify_page(status, &url, &page));
+    }
+    check_status(response)
+}
+
+/// `(file id, name)` of every backup a shared folder's page lists. Names are taken as they come
+/// and checked with [`backup::is_backup_file_name`], so the name format is only defined there.
+fn backups_in_folder_page(page: &str) -> Vec<(String, String)> {
+    let re_json = Regex::new(r#"\["([a-zA-Z0-9_-]+)","([^"\\]+)""#).unwrap();
+    let mut entries: Vec<(String, String)> =
+        re_json.captures_iter(page).map(|caps| (caps[1].to_string(), caps[2].to_string())).collect();
+    if !entries.iter().any(|(_, name)| backup::is_backup_file_name(name)) {
+        // The plain HTML view: each file links to /d/<id>/view and the first text after it is its name
+        let re_html = Regex::new(r#"(?s)/d/([a-zA-Z0-9_-]+)/view.*?>\s*([^<>\s][^<>]*?)\s*<"#).unwrap();
+        entries = re_html.captures_iter(page).map(|caps| (caps[1].to_string(), caps[2].to_string())).collect();
+    }
+    entries.retain(|(_, name)| backup::is_backup_file_name(name));
+    entries
+}
+
+/// The newest backup listed in a shared folder, if any.
+async fn latest_in_folder(client: &Client, base: &str, folder_id: &str) -> Result<Option<String>, DownloadError> {
+    let folder_url = format!("{}/embeddedfolderview?id={}", base, folder_id);
+    let response = client.get(&folder_url).send().await.map_err(DownloadError::network)?;
+    let (status, url) = (response.status(), response.url().clone());
+    let page = response.text().await.map_err(DownloadError::network)?;
+    if !status.is_success() || url.host_str() == Some("accounts.google.com") || page.contains("ServiceLogin") {
+        return Err(classify_page(status, &url, &page));
+    }
+
+    let candidates = backups_in_folder_page(&page);
+    Ok(backup::latest_backup_name(candidates.iter().map(|(_, name)| name.as_str()))
+        .and_then(|latest| candidates.iter().find(|(_, name)| name == latest))
+        .map(|(id, _)| id.clone()))
+}
+
+/// Remembers which file a `.part` download belongs to, so it is only ever resumed with the same one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartialDownload {
+    file_id: String,
+    etag: Option<String>,
+    total: Option<u64>,
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", path.to_string_lossy(), suffix))
+}
+
+fn load_partial(state_path: &Path) -> Option<PartialDownload> {
+    serde_json::from_str(&fs::read_to_string(state_path).ok()?).ok()
+}
+
+fn save_partial(state_path: &Path, state: &PartialDownload) -> Result<(), DownloadError> {
+    let content = serde_json::to_string(state).map_err(DownloadError::io)?;
+    fs::write(state_path, content).map_err(DownloadError::io)
+}
+
+fn discard_partial(part: &Path, state_path: &Path) {
+    let _ = fs::remove_file(part);
+    let _ = fs::remove_file(state_path);
+}
+
+/// `(first byte, total length)` from a `Content-Range: bytes first-last/total` header.
+fn content_range(response: &Response) -> Option<(u64, Option<u64>)> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let first = range.split_once('-')?.0.parse().ok()?;
+    Some((first, total.parse().ok()))
+}
+
+fn cancelled() -> DownloadError {
+    DownloadError::new(DownloadErrorKind::Cancelled, "The download was cancelled.")
+}
+
+/// One attempt: appends to `part` from its current length, or starts over when Drive cannot resume.
+async fn fetch_into<F: FnMut(Progress)>(
+    client: &Client,
+    base: &str,
+    state: &mut PartialDownload,
+    part: &Path,
+    state_path: &Path,
+    cancel: &AtomicBool,
+    on_progress: &mut F,
+) -> Result<(), DownloadError> {
+    let offset = fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+    let mut response = open_download(client, base, &state.file_id, offset, state.etag.as_deref()).await?;
+
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        if offset > 0 && state.total == Some(offset) {
+            return Ok(());
+        }
+        discard_partial(part, state_path);
+        return Err(DownloadError::network("the partial download could not be resumed"));
+    }
+
+    let resumed = match (response.status(), content_range(&response)) {
+        (StatusCode::PARTIAL_CONTENT, Some((first, total))) if first == offset => Some(total),
+        _ => None,
+    };
+    let (start, total) = match resumed {
+        Some(total) => (offset, total),
+        None => (0, response.content_length()),
+    };
+    let mut file = if resumed.is_some() {
+        OpenOptions::new().append(true).open(part)
+    } else {
+        fs::File::create(part)
+    }
+    .map_err(DownloadError::io)?;
+    if resumed.is_none() {
+        state.etag = response.headers().get(ETAG).and_then(|v| v.to_str().ok()).map(str::to_string);
+    }
+    state.total = total;
+    save_partial(state_path, state)?;
+
+    let mut progress = Progress { downloaded: start, total, resumed_from: start };
+    on_progress(progress);
+    let mut last_data = Instant::now();
+    let mut last_report = Instant::now();
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(cancelled());
+        }
+        // Wait in short slices so a cancel or a stalled connection is noticed without new data
+        let chunk = match tokio::time::timeout(POLL_INTERVAL, response.chunk()).await {
+            Err(_) if last_data.elapsed() >= STALL_TIMEOUT => return Err(DownloadError::network("the connection stalled")),
+            Err(_) => continue,
+            Ok(chunk) => chunk.map_err(DownloadError::network)?,
+        };
+        let Some(chunk) = chunk else { break };
+        file.write_all(&chunk).map_err(DownloadError::io)?;
+        progress.downloaded += chunk.len() as u64;
+        last_data = Instant::now();
+        if last_report.elapsed() >= PROGRESS_INTERVAL {
+            on_progress(progress);
+            last_report = Instant::now();
+        }
+    }
+    file.sync_all().map_err(DownloadError::io)?;
+    on_progress(progress);
+
+    match total {
+        Some(total) if progress.downloaded < total => Err(DownloadError::network(format!(
+            "the connection closed after {} of {} bytes",
+            progress.downloaded, total
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Backups are plain SQLite snapshots, zip bundles or encrypted bundles; anything else is not one.
+fn looks_like_backup(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    let read = fs::File::open(path).and_then(|mut f| f.read(&mut header)).unwrap_or(0);
+    header[..read].starts_with(SQLITE_MAGIC) || header[..read].starts_with(ZIP_MAGIC) || crypto::is_encrypted(path)
+}
+
+async fn wait_before_retry(attempt: u32, cancel: &AtomicBool) -> Result<(), DownloadError> {
+    let until = Instant::now() + Duration::from_secs(1 << attempt.min(4));
+    while Instant::now() < until {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(cancelled());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    Ok(())
+}
+
+/// Downloads one Drive file to `output` through `<output>.part`, resuming an earlier partial
+/// download of the same file and retrying dropped connections. A cancelled download leaves
+/// nothing behind. Returns the file size.
+pub async fn download_file<F: FnMut(Progress)>(
+    client: &Client,
+    base: &str,
+    file_id: &str,
+    output: &Path,
+    cancel: &AtomicBool,
+    mut on_progress: F,
+) -> Result<u64, DownloadError> {
+    let part = with_suffix(output, ".part");
+    let state_path = with_suffix(output, ".part.json");
+    let mut state = match load_partial(&state_path) {
+        Some(state) if state.file_id == file_id && part.exists() => state,
+        _ => {
+            discard_partial(&part, &state_path);
+            PartialDownload { file_id: file_id.to_string(), etag: None, total: None }
+        }
+    };
+
+    let mut attempt = 1;
+    loop {
+        match fetch_into(client, base, &mut state, &part, &state_path, cancel, &mut on_progress).await {
+            Ok(()) => break,
+            Err(e) if e.kind == DownloadErrorKind::Network && attempt < MAX_ATTEMPTS => {
+                log::warn!("Drive download interrupted (attempt {}/{}): {}", attempt, MAX_ATTEMPTS, e.message);
+                if let Err(e) = wait_before_retry(attempt, cancel).await {
+                    discard_partial(&part, &state_path);
+                    return Err(e);
+                }
+                attempt += 1;
+            }
+            Err(e) => {
+                if matches!(e.kind, DownloadErrorKind::NotFound | DownloadErrorKind::NotABackup | DownloadErrorKind::Cancelled) {
+                    discard_partial(&part, &state_path);
+                }
+                return Err(e);
+            }
+        }
+    }
+
+    if !looks_like_backup(&part) {
+        discard_partial(&part, &state_path);
+        return Err(DownloadError::new(
+            DownloadErrorKind::NotABackup,
+            "The downloaded file is not an invoice backup. Check that the link points to a backup file or folder.",
+        ));
+    }
+    let size = fs::metadata(&part).map_err(DownloadError::io)?.len();
+    fs::rename(&part, output).map_err(DownloadError::io)?;
+    let _ = fs::remove_file(&state_path);
+    Ok(size)
+}
+
+/// Pulls the newest backup out of a folder that Drive served as a zip archive.
+fn extract_latest_backup(archive_path: &Path, output: &Path) -> Result<(), DownloadError> {
+    let file = fs::File::open(archive_path).map_err(DownloadError::io)?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| DownloadError::new(DownloadErrorKind::NotABackup, format!("The folder download is not a valid zip: {}", e)))?;
+    let names: Vec<String> = archive.file_names().map(|n| n.to_string()).collect();
+    let base_name = |n: &str| n.rsplit('/').next().unwrap_or(n).to_string();
+    let latest = backup::latest_backup_name(names.iter().map(|n| n.rsplit('/').next().unwrap_or(n)))
+        .map(str::to_string)
+        .ok_or_else(|| DownloadError::new(DownloadErrorKind::NoBackupInFolder, "The shared folder does not contain any invoice backups."))?;
+    let entry = names.iter().find(|n| base_name(n) == latest).cloned().unwrap_or(latest);
+    let mut source = archive.by_name(&entry).map_err(DownloadError::io)?;
+    let mut target = fs::File::create(output).map_err(DownloadError::io)?;
+    std::io::copy(&mut source, &mut target).map_err(DownloadError::io)?;
+    Ok(())
+}
+
+/// Downloads the file behind a shared link, or the newest backup in a shared folder, to `output`.
+pub async fn download_link<F: FnMut(Progress)>(
+    client: &Client,
+    base: &str,
+    link: &str,
+    output: &Path,
+    cancel: &AtomicBool,
+    on_progress: F,
+) -> Result<(), DownloadError> {
+    let link = parse_link(link)?;
+    if !link.is_folder {
+        return download_file(client, base, &link.id, output, cancel, on_progress).await.map(|_| ());
+    }
+    if let Some(file_id) = latest_in_folder(client, base, &link.id).await? {
+        return download_file(client, base, &file_id, output, cancel, on_progress).await.map(|_| ());
+    }
+
+    // The folder view listed nothing we recognise; Drive can still serve the whole folder as a zip
+    let archive_path = output.with_extension("zip");
+    download_file(client, base, &link.id, &archive_path, cancel, on_progress)
+        .await
+        .map_err(|e| match e.kind {
+            DownloadErrorKind::NotABackup | DownloadErrorKind::NotFound => DownloadError::new(
+                DownloadErrorKind::NoBackupInFolder,
+                "No invoice backups were found in the shared folder. Check that it contains backups and is shared with \"Anyone with the link\".",
+            ),
+            _ => e,
+        })?;
+    let extracted = extract_latest_backup(&archive_path, output);
+    let _ = fs::remove_file(&archive_path);
+    extracted
+}
+
+pub fn http_client() -> Result<Client, DownloadError> {
+    // Drive's download warning relies on a cookie, and it serves plain pages to unknown agents
+    Client::builder()
+        .cookie_store(true)
+        .user_agent("Mozilla/5.0")
+        .connect_timeout(Duration::from_secs(30))
+        .build()
+        .map_err(DownloadError::network)
+}
+
+/// `APEX_GDRIVE_LINK_BASE_URL` points shared-link downloads at a mock server instead of Drive.
+fn base_url() -> String {
+    match std::env::var("APEX_GDRIVE_LINK_BASE_URL") {
+        Ok(base) if !base.is_empty() => base.trim_end_matches('/').to_string(),
+        _ => DRIVE_BASE_URL.to_string(),
+    }
+}
+
+/// Cancellation flags of the downloads in progress, by download ID.
+#[derive(Default)]
+pub struct Downloads(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl Downloads {
+    fn register(&self, id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.0.lock().unwrap().insert(id.to_string(), flag.clone());
+        flag
+    }
+
+    fn finish(&self, id: &str) {
+        self.0.lock().unwrap().remove(id);
+    }
+
+    fn cancel(&self, id: &str) -> bool {
+        match self.0.lock().unwrap().get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Downloads a shared Drive file, or the newest backup in a shared folder, to `output_path`,
+/// emitting [`PROGRESS_EVENT`]s tagged with `download_id`.
+#[tauri::command]
+pub async fn download_gdrive_file(
+    app_handle: tauri::AppHandle,
+    downloads: tauri::State<'_, Downloads>,
+    url: String,
+    output_path: String,
+    download_id: Option<String>,
+) -> Result<(), DownloadError> {
+    let download_id = download_id.unwrap_or_else(|| output_path.clone());
+    let cancel = downloads.register(&download_id);
+    let client = http_client()?;
+    let result = download_link(&client, &base_url(), &url, Path::new(&output_path), &cancel, |progress| {
+        let _ = app_handle.emit_all(PROGRESS_EVENT, ProgressEvent { download_id: download_id.clone(), progress });
+    })
+    .await;
+    downloads.finish(&download_id);
+    result
+}
+
+/// Stops a running download and deletes what it had downloaded so far.
+#[tauri::command]
+pub async fn cancel_gdrive_download(downloads: tauri::State<'_, Downloads>, download_id: String) -> Result<bool, String> {
+    Ok(downloads.cancel(&download_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockRequest, MockResponse, MockServer};
+    use std::net::TcpListener;
+
+    const TAGGED_FOLDER_JSON: &str = r#"window.viewerData = [["1AbCdEfGhIjK","invoices-20240702-101500-1a2b3c4d.zip.enc",null],["2BcDeFgHiJkL","invoices-20240703-093000-9f8e7d6c.zip",null],["3CdEfGhIjKlM","notes.txt",null],["4DeFgHiJkLmN","invoices-20240701-080000.db",null]]"#;
+    const TAGGED_FOLDER_HTML: &str = r#"<div class="flip-entry"><a href="https://drive.google.com/file/d/1AbCdEfGhIjK/view?usp=drive_web" target="_blank"><div class="flip-entry-thumb"></div><div class="flip-entry-title">invoices-20240702-101500-1a2b3c4d.zip.enc</div></a></div>
+<div class="flip-entry"><a href="https://drive.google.com/file/d/2BcDeFgHiJkL/view?usp=drive_web" target="_blank"><div class="flip-entry-title">
+  invoices-20240703-093000-9f8e7d6c.zip </div></a></div>
+<div class="flip-entry"><a href="https://drive.google.com/file/d/3CdEfGhIjKlM/view?usp=drive_web"><div class="flip-entry-title">notes.txt</div></a></div>"#;
+
+    #[test]
+    fn tagged_snapshots_are_found_in_folder_pages() {
+        for page in [TAGGED_FOLDER_JSON, TAGGED_FOLDER_HTML] {
+            let backups = backups_in_folder_page(page);
+            assert!(backups.contains(&("1AbCdEfGhIjK".to_string(), "invoices-20240702-101500-1a2b3c4d.zip.enc".to_string())), "{:?}", backups);
+            assert!(backups.contains(&("2BcDeFgHiJkL".to_string(), "invoices-20240703-093000-9f8e7d6c.zip".to_string())), "{:?}", backups);
+            assert!(backups.iter().all(|(_, name)| name != "notes.txt"));
+        }
+    }
+
+    #[tokio::test]
+    async fn the_newest_tagged_snapshot_in_a_folder_is_picked() {
+        let server = MockServer::start(|_| MockResponse::new(200, TAGGED_FOLDER_JSON).header("Content-Type", "text/html"));
+        let client = http_client().unwrap();
+        let latest = latest_in_folder(&client, &server.url, "folder1").await.unwrap();
+        assert_eq!(latest.as_deref(), Some("2BcDeFgHiJkL"));
+        assert_eq!(server.requests()[0].target, "/embeddedfolderview?id=folder1");
+    }
+
+    /// Enough of a SQLite file to pass as a backup.
+    fn snapshot(len: usize) -> Vec<u8> {
+        let mut data = SQLITE_MAGIC.to_vec();
+        data.extend((0..len - SQLITE_MAGIC.len()).map(|i| (i % 251) as u8));
+        data
+    }
+
+    fn file(body: &[u8]) -> MockResponse {
+        MockResponse::new(200, body).header("Content-Type", "application/octet-stream")
+    }
+
+    fn page(status: u16, html: &str) -> MockResponse {
+        MockResponse::new(status, html).header("Content-Type", "text/html; charset=utf-8")
+    }
+
+    /// Leaves `<output>.part` holding `data` as an interrupted download of `file_id` would.
+    fn interrupted(output: &Path, file_id: &str, data: &[u8], etag: &str, total: usize) {
+        fs::write(with_suffix(output, ".part"), data).unwrap();
+        let state = PartialDownload { file_id: file_id.to_string(), etag: Some(etag.to_string()), total: Some(total as u64) };
+        save_partial(&with_suffix(output, ".part.json"), &state).unwrap();
+    }
+
+    async fn download(server: &MockServer, output: &Path) -> (Result<u64, DownloadError>, Vec<Progress>) {
+        let mut progress = Vec::new();
+        let result = download_file(&http_client().unwrap(), &server.url, "file1", output, &AtomicBool::new(false), |p| progress.push(p)).await;
+        (result, progress)
+    }
+
+    #[tokio::test]
+    async fn an_interrupted_download_resumes_from_its_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("restored.db");
+        let data = snapshot(4096);
+        interrupted(&output, "file1", &data[..1000], "\"v1\"", data.len());
+        let rest = data[1000..].to_vec();
+        let server = MockServer::start(move |req| {
+            assert_eq!(req.header("Range"), Some("bytes=1000-"));
+            assert_eq!(req.header("If-Range"), Some("\"v1\""));
+            MockResponse::new(206, rest.clone())
+                .header("Content-Type", "application/octet-stream")
+                .header("Content-Range", "bytes 1000-4095/4096")
+        });
+
+        let (result, progress) = download(&server, &output).await;
+        assert_eq!(result.unwrap(), 4096);
+        assert_eq!(fs::read(&output).unwrap(), data);
+        assert_eq!(progress[0].resumed_from, 1000);
+        assert_eq!(progress.last().unwrap().downloaded, 4096);
+        assert!(!with_suffix(&output, ".part").exists() && !with_suffix(&output, ".part.json").exists());
+        assert_eq!(server.requests()[0].target, "/uc?id=file1&export=download");
+    }
+
+    #[tokio::test]
+    async fn a_changed_file_is_downloaded_again_from_the_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("restored.db");
+        interrupted(&output, "file1", &snapshot(4096)[..1000], "\"v1\"", 4096);
+        // Drive ignores the range when If-Range no longer matches and sends the new file whole
+        let changed = snapshot(3000);
+        let body = changed.clone();
+        let server = MockServer::start(move |_| file(&body).header("ETag", "\"v2\""));
+
+        let (result, progress) = download(&server, &output).await;
+        assert_eq!(result.unwrap(), 3000);
+        assert_eq!(fs::read(&output).unwrap(), changed);
+        assert_eq!(progress[0].resumed_from, 0);
+        assert_eq!(server.requests()[0].header("If-Range"), Some("\"v1\""));
+    }
+
+    #[tokio::test]
+    async fn the_virus_scan_warning_form_is_confirmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("restored.db");
+        let data = snapshot(2048);
+        let body = data.clone();
+        let server = MockServer::start(move |req: &MockRequest| match req.path() {
+            "/uc" => page(200, &format!(
+                r#"<p>Google Drive can't scan this file for viruses.</p>
+                <form id="download-form" action="http://{}/download" method="get">
+                <input type="hidden" name="id" value="file1"><input type="hidden" name="export" value="download">
+                <input type="hidden" name="confirm" value="t"><input type="hidden" name="uuid" value="0a1b&amp;2c">
+                <input type="submit" value="Download anyway"></form>"#,
+                req.header("Host").unwrap()
+            )),
+            "/download" if req.query("confirm").as_deref() == Some("t") => file(&body),
+            _ => page(404, "<p>Sorry, the file you have requested does not exist.</p>"),
+        });
+
+        let (result, _) = download(&server, &output).await;
+        assert_eq!(result.unwrap(), 2048);
+        assert_eq!(fs::read(&output).unwrap(), data);
+        let confirmed = &server.requests()[1];
+        assert_eq!((confirmed.query("id").as_deref(), confirmed.query("uuid").as_deref()), (Some("file1"), Some("0a1b&2c")));
+    }
+
+    #[tokio::test]
+    async fn a_confirm_code_in_the_warning_page_is_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("restored.db");
+        let data = snapshot(2048);
+        let body = data.clone();
+        let server = MockServer::start(move |req| match req.query("confirm") {
+            Some(code) if code == "AbC_1" => file(&body),
+            _ => page(200, r#"<a id="uc-download-link" href="/uc?export=download&amp;confirm=AbC_1&amp;id=file1">Download anyway</a>"#),
+        });
+
+        let (result, _) = download(&server, &output).await;
+        assert_eq!(result.unwrap(), 2048);
+        assert_eq!(server.requests()[1].target, "/uc?id=file1&export=download&confirm=AbC_1");
+    }
+
+    #[tokio::test]
+    async fn drive_errors_are_told_apart() {
+        let cases = [
+            (MockResponse::new(403, ""), DownloadErrorKind::AccessDenied),
+            (MockResponse::new(404, ""), DownloadErrorKind::NotFound),
+            (MockResponse::new(429, ""), DownloadErrorKind::QuotaExceeded),
+            (page(200, "<p>Too many users have viewed or downloaded this file recently.</p>"), DownloadErrorKind::QuotaExceeded),
+            (page(200, "<title>Google Drive - Quota exceeded</title>"), DownloadErrorKind::QuotaExceeded),
+            (page(200, r#"<a href="https://accounts.google.com/ServiceLogin">Sign in</a>"#), DownloadErrorKind::AccessDenied),
+            (page(200, "<p>Sorry, the file you have requested does not exist.</p>"), DownloadErrorKind::NotFound),
+            (page(200, "<p>Some other page</p>"), DownloadErrorKind::NotABackup),
+            (file(b"just some text"), DownloadErrorKind::NotABackup),
+        ];
+        for (response, kind) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let output = dir.path().join("restored.db");
+            let response = std::sync::Mutex::new(Some(response));
+            let server = MockServer::start(move |_| response.lock().unwrap().take().unwrap_or_else(|| MockResponse::new(500, "")));
+
+            let error = download(&server, &output).await.0.unwrap_err();
+            assert_eq!(error.kind, kind, "{}", error);
+            assert!(!output.exists());
+            if kind == DownloadErrorKind::NotABackup {
+                assert!(!with_suffix(&output, ".part").exists());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_download_leaves_no_partial_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("restored.db");
+        let part = with_suffix(&output, ".part");
+        let data = snapshot(8192);
+
+        // Sends half the file, then holds the connection open as a slow download would
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n", data.len());
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(&data[..4096]).unwrap();
+            stream.flush().unwrap();
+            std::thread::sleep(Duration::from_secs(5));
+        });
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancel);
+        let watched = part.clone();
+        std::thread::spawn(move || {
+            while fs::metadata(&watched).map(|m| m.len()).unwrap_or(0) < 4096 {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        let error = download_file(&http_client().unwrap(), &base, "file1", &output, &cancel, |_| {}).await.unwrap_err();
+        assert_eq!(error.kind, DownloadErrorKind::Cancelled);
+        assert!(!part.exists() && !with_suffix(&output, ".part.json").exists() && !output.exists());
+    }
+}
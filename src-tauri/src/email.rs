@@ -0,0 +1,431 @@
+use crate::models::Invoice;
+use crate::vault::{self, Vault};
+use crate::{bundle, db, device};
+use chrono::NaiveDate;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+
+/// Setting holding the SMTP settings as JSON. The password is kept in the vault.
+pub const SMTP_SETTINGS: &str = "smtp_settings";
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub security: String, // "starttls" | "tls" | "none"
+    /// Empty for servers that accept mail without logging in, such as a local catcher.
+    pub username: String,
+    pub from_address: String,
+    pub from_name: String,
+    /// Copied on every invoice email, e.g. the accounts mailbox. Comma separated.
+    pub cc: String,
+    pub subject_template: String,
+    pub body_template: String,
+}
+
+impl Default for SmtpSettings {
+    fn default() -> Self {
+        SmtpSettings {
+            host: String::new(),
+            port: 587,
+            security: "starttls".to_string(),
+            username: String::new(),
+            from_address: String::new(),
+            from_name: String::new(),
+            cc: String::new(),
+            subject_template: "Invoice {invoiceNumber} dated {invoiceDate}".to_string(),
+            body_template: "Dear {customerName},\n\nPlease find attached invoice {invoiceNumber} dated {invoiceDate} for Rs. {grandTotal}.\n\nRegards,\n{senderName}".to_string(),
+        }
+    }
+}
+
+/// An invoice email as it will be sent; address lists are comma separated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutgoingEmail {
+    pub to: String,
+    pub cc: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailRequest {
+    pub invoice_number: String,
+    /// File name of the PDF in the app's `generated` folder.
+    pub pdf_file: String,
+    /// Left out to use the customer's addresses, the settings' CC and the templates.
+    #[serde(default)]
+    pub email: Option<OutgoingEmail>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailLogEntry {
+    pub id: i64,
+    pub invoice_number: String,
+    pub recipients: String,
+    pub cc: Option<String>,
+    pub subject: String,
+    pub attachment: String,
+    pub status: String, // "sent" | "failed"
+    pub error: Option<String>,
+    pub sent_at: Option<String>,
+}
+
+pub fn load_settings(conn: &Connection) -> Result<SmtpSettings, String> {
+    let value: Option<Option<String>> = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [SMTP_SETTINGS], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let settings: SmtpSettings = match value.flatten() {
+        Some(json) => serde_json::from_str(&json).map_err(|e| e.to_string())?,
+        None => SmtpSettings::default(),
+    };
+    if settings.host.trim().is_empty() || settings.from_address.trim().is_empty() {
+        return Err("Set up the mail server and sender address under Settings > Email first.".to_string());
+    }
+    Ok(settings)
+}
+
+/// Amounts the way they are written on invoices, e.g. 1,23,456.50.
+fn indian_amount(value: f64) -> String {
+    let fixed = format!("{:.2}", value.abs());
+    let (whole, paise) = fixed.split_once('.').unwrap_or((&fixed, "00"));
+    // The last three digits, then groups of two
+    let (mut rest, last) = whole.split_at(whole.len().saturating_sub(3));
+    let mut groups = vec![last];
+    while !rest.is_empty() {
+        let (head, group) = rest.split_at(rest.len().saturating_sub(2));
+        groups.insert(0, group);
+        rest = head;
+    }
+    let grouped = groups.join(",");
+    let sign = if value < 0.0 && fixed != "0.00" { "-" } else { "" };
+    format!("{}{}.{}", sign, grouped, paise)
+}
+
+/// Fills `{invoiceNumber}`, `{invoiceDate}`, `{financialYear}`, `{customerName}`,
+/// `{grandTotal}` and `{senderName}` into a subject or body template.
+pub fn render(template: &str, invoice: &Invoice, settings: &SmtpSettings) -> String {
+    let date = NaiveDate::parse_from_str(&invoice.invoice_date, "%Y-%m-%d")
+        .map(|d| d.format("%d-%m-%Y").to_string())
+        .unwrap_or_else(|_| invoice.invoice_date.clone());
+    [
+        ("{invoiceNumber}", invoice.invoice_number.clone()),
+        ("{invoiceDate}", date),
+        ("{financialYear}", invoice.financial_year.clone()),
+        ("{customerName}", invoice.customer.company_name.clone()),
+        ("{grandTotal}", indian_amount(invoice.grand_total)),
+        ("{senderName}", settings.from_name.clone()),
+    ]
+    .iter()
+    .fold(template.to_string(), |text, (placeholder, value)| text.replace(placeholder, value))
+}
+
+fn addresses(list: &str) -> impl Iterator<Item = &str> {
+    list.split([',', ';']).map(str::trim).filter(|a| !a.is_empty())
+}
+
+fn mailboxes(list: &str) -> Result<Vec<Mailbox>, String> {
+    addresses(list)
+        .map(|address| address.parse::<Mailbox>().map_err(|_| format!("{} is not a valid email address", address)))
+        .collect()
+}
+
+/// The stored invoice and the current email addresses of its customer.
+fn load_invoice(conn: &Connection, invoice_number: &str) -> Result<(Invoice, Option<String>), String> {
+    let (json, customer_id): (Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT json_data, customer_id FROM invoices WHERE invoice_number = ?1",
+            [invoice_number],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Invoice {} not found", invoice_number))?;
+    let invoice: Invoice = serde_json::from_str(&json.ok_or("The invoice has no saved data")?).map_err(|e| e.to_string())?;
+    let email: Option<String> = match customer_id {
+        Some(id) => conn
+            .query_row("SELECT email FROM customers WHERE id = ?1", [id], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?
+            .flatten(),
+        None => None,
+    };
+    let email = email.or_else(|| invoice.customer.email.clone()).filter(|e| !e.trim().is_empty());
+    Ok((invoice, email))
+}
+
+/// The email an invoice gets by default: to the customer, CC from the settings, with the
+/// templates filled in.
+pub fn default_email(conn: &Connection, settings: &SmtpSettings, invoice_number: &str) -> Result<OutgoingEmail, String> {
+    let (invoice, customer_email) = load_invoice(conn, invoice_number)?;
+    Ok(OutgoingEmail {
+        to: customer_email.unwrap_or_default(),
+        cc: settings.cc.clone(),
+        subject: render(&settings.subject_template, &invoice, settings),
+        body: render(&settings.body_template, &invoice, settings),
+    })
+}
+
+pub fn build_message(settings: &SmtpSettings, email: &OutgoingEmail, attachment: Option<(&str, Vec<u8>)>) -> Result<Message, String> {
+    let from_address = settings.from_address.trim().parse().map_err(|_| format!("{} is not a valid sender address", settings.from_address))?;
+    let from_name = Some(settings.from_name.trim().to_string()).filter(|n| !n.is_empty());
+    let to = mailboxes(&email.to)?;
+    if to.is_empty() {
+        return Err("Add at least one recipient. The customer has no email address on file.".to_string());
+    }
+    let mut builder = Message::builder().from(Mailbox::new(from_name, from_address)).subject(email.subject.trim());
+    for mailbox in to {
+        builder = builder.to(mailbox);
+    }
+    for mailbox in mailboxes(&email.cc)? {
+        builder = builder.cc(mailbox);
+    }
+    let text = SinglePart::plain(email.body.clone());
+    let message = match attachment {
+        Some((name, pdf)) => {
+            let pdf_type = ContentType::parse("application/pdf").map_err(|e| e.to_string())?;
+            builder.multipart(MultiPart::mixed().singlepart(text).singlepart(Attachment::new(name.to_string()).body(pdf, pdf_type)))
+        }
+        None => builder.singlepart(text),
+    };
+    message.map_err(|e| e.to_string())
+}
+
+/// Hands the message to the configured server. `"none"` sends in plain text and is meant for
+/// local test servers.
+pub async fn send(settings: &SmtpSettings, password: Option<String>, message: Message) -> Result<(), String> {
+    let host = settings.host.trim();
+    let builder = match settings.security.as_str() {
+        "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host).map_err(|e| e.to_string())?,
+        "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host).map_err(|e| e.to_string())?,
+        "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        other => return Err(format!("Unknown connection security: {}", other)),
+    };
+    let mut builder = builder.port(settings.port).timeout(Some(SEND_TIMEOUT));
+    if !settings.username.trim().is_empty() {
+        builder = builder.credentials(Credentials::new(settings.username.trim().to_string(), password.unwrap_or_default()));
+    }
+    builder.build().send(message).await.map(|_| ()).map_err(|e| format!("Sending failed: {}", e))
+}
+
+/// Records a send attempt against the invoice, whether or not it went through.
+pub fn log_attempt(
+    conn: &Connection,
+    invoice_number: &str,
+    email: &OutgoingEmail,
+    attachment: &str,
+    result: &Result<(), String>,
+) -> Result<EmailLogEntry, String> {
+    let cc = Some(email.cc.trim()).filter(|cc| !cc.is_empty());
+    let (status, error) = match result {
+        Ok(()) => ("sent", None),
+        Err(e) => ("failed", Some(e.as_str())),
+    };
+    conn.execute(
+        "INSERT INTO invoice_emails(invoice_number, recipients, cc, subject, attachment, status, error) VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![invoice_number, email.to.trim(), cc, email.subject.trim(), attachment, status, error],
+    )
+    .map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+    history(conn, invoice_number)?.into_iter().find(|entry| entry.id == id).ok_or_else(|| "Send was not logged".to_string())
+}
+
+/// Emails sent for an invoice, newest first.
+pub fn history(conn: &Connection, invoice_number: &str) -> Result<Vec<EmailLogEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, invoice_number, recipients, cc, subject, attachment, status, error, sent_at
+             FROM invoice_emails WHERE invoice_number = ?1 ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([invoice_number], |row| {
+            Ok(EmailLogEntry {
+                id: row.get(0)?,
+                invoice_number: row.get(1)?,
+                recipients: row.get(2)?,
+                cc: row.get(3)?,
+                subject: row.get(4)?,
+                attachment: row.get(5)?,
+                status: row.get(6)?,
+                error: row.get(7)?,
+                sent_at: row.get(8)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// The SMTP password, read from the vault right before sending. Not needed without a username.
+fn smtp_password(app_handle: &tauri::AppHandle, vault: &Vault, settings: &SmtpSettings) -> Result<Option<String>, String> {
+    if settings.username.trim().is_empty() {
+        return Ok(None);
+    }
+    if !vault::exists(&device::config_dir(app_handle)?) {
+        return Err("Set up the vault and save the SMTP password in it first.".to_string());
+    }
+    let password = vault.get(vault::SMTP_PASSWORD)?.ok_or("No SMTP password has been saved.")?;
+    String::from_utf8(password).map(Some).map_err(|e| e.to_string())
+}
+
+/// Stores the SMTP password in the vault; an empty password removes it.
+#[tauri::command]
+pub async fn save_smtp_password(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>, password: String) -> Result<(), String> {
+    if !vault::exists(&device::config_dir(&app_handle)?) {
+        return Err("Set up the vault before storing the SMTP password in it.".to_string());
+    }
+    if password.is_empty() {
+        vault.remove(vault::SMTP_PASSWORD)
+    } else {
+        vault.put(vault::SMTP_PASSWORD, password.into_bytes())
+    }
+}
+
+/// Sends a short message with the saved settings, to check them.
+#[tauri::command]
+pub async fn send_test_email(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>, to: String) -> Result<(), String> {
+    let settings = load_settings(&db::open(&app_handle)?)?;
+    let password = smtp_password(&app_handle, &vault, &settings)?;
+    let email = OutgoingEmail {
+        to,
+        cc: String::new(),
+        subject: "Test email from Apex Solar Invoice Generator".to_string(),
+        body: format!("The mail server {}:{} is set up correctly.", settings.host.trim(), settings.port),
+    };
+    let message = build_message(&settings, &email, None)?;
+    send(&settings, password, message).await?;
+    log::info!("Sent a test email through {}:{}", settings.host.trim(), settings.port);
+    Ok(())
+}
+
+/// Recipients, subject and body an invoice would be sent with, for the user to review.
+#[tauri::command]
+pub async fn preview_invoice_email(app_handle: tauri::AppHandle, invoice_number: String) -> Result<OutgoingEmail, String> {
+    let conn = db::open(&app_handle)?;
+    let settings = load_settings(&conn)?;
+    default_email(&conn, &settings, &invoice_number)
+}
+
+/// Emails a generated invoice PDF and logs the attempt against the invoice. A failed send is
+/// logged too and then reported as an error.
+#[tauri::command]
+pub async fn email_invoice(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>, request: EmailRequest) -> Result<EmailLogEntry, String> {
+    let conn = db::open(&app_handle)?;
+    let settings = load_settings(&conn)?;
+    let email = match request.email {
+        Some(email) => email,
+        None => default_email(&conn, &settings, &request.invoice_number)?,
+    };
+    let pdf = fs::read(bundle::generated_file(&app_handle, &request.pdf_file)?).map_err(|e| e.to_string())?;
+    let password = smtp_password(&app_handle, &vault, &settings)?;
+    let message = build_message(&settings, &email, Some((&request.pdf_file, pdf)))?;
+    drop(conn);
+
+    let result = send(&settings, password, message).await;
+    let entry = log_attempt(&db::open(&app_handle)?, &request.invoice_number, &email, &request.pdf_file, &result)?;
+    result?;
+    log::info!("Emailed invoice {} to {}", request.invoice_number, email.to);
+    Ok(entry)
+}
+
+#[tauri::command]
+pub async fn get_invoice_emails(app_handle: tauri::AppHandle, invoice_number: String) -> Result<Vec<EmailLogEntry>, String> {
+    history(&db::open(&app_handle)?, &invoice_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_invoice;
+
+    fn settings() -> SmtpSettings {
+        SmtpSettings {
+            host: "localhost".to_string(),
+            from_address: "billing@apexsolar.in".to_string(),
+            from_name: "Apex Solar".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn email(to: &str, cc: &str) -> OutgoingEmail {
+        OutgoingEmail { to: to.to_string(), cc: cc.to_string(), subject: " Invoice 001 ".to_string(), body: "Please find it attached.".to_string() }
+    }
+
+    #[test]
+    fn amounts_are_grouped_the_indian_way() {
+        assert_eq!(indian_amount(123456.5), "1,23,456.50");
+        assert_eq!(indian_amount(12345678.9), "1,23,45,678.90");
+        assert_eq!(indian_amount(1000.0), "1,000.00");
+        assert_eq!(indian_amount(999.999), "1,000.00");
+        assert_eq!(indian_amount(42.0), "42.00");
+        assert_eq!(indian_amount(0.0), "0.00");
+        assert_eq!(indian_amount(-123456.5), "-1,23,456.50");
+        assert_eq!(indian_amount(-5.0), "-5.00");
+        assert_eq!(indian_amount(-0.001), "0.00");
+    }
+
+    #[test]
+    fn templates_are_filled_from_the_invoice() {
+        let invoice = sample_invoice("001", "c1");
+        let template = "{invoiceNumber}/{financialYear} of {invoiceDate} to {customerName}: Rs. {grandTotal} from {senderName} {unknown}";
+        assert_eq!(
+            render(template, &invoice, &settings()),
+            "001/24-25 of 03-07-2024 to Acme Pvt Ltd: Rs. 2,52,000.00 from Apex Solar {unknown}"
+        );
+        let defaults = SmtpSettings::default();
+        assert_eq!(render(&defaults.subject_template, &invoice, &defaults), "Invoice 001 dated 03-07-2024");
+    }
+
+    #[test]
+    fn recipients_and_copies_are_parsed_from_lists() {
+        let message = build_message(&settings(), &email("accounts@acme.in; Ravi <ravi@acme.in>, ", "books@apexsolar.in"), None).unwrap();
+        let envelope: Vec<String> = message.envelope().to().iter().map(|a| a.to_string()).collect();
+        assert_eq!(envelope, vec!["accounts@acme.in", "ravi@acme.in", "books@apexsolar.in"]);
+        let raw = String::from_utf8(message.formatted()).unwrap();
+        assert!(raw.contains("From: \"Apex Solar\" <billing@apexsolar.in>"));
+        assert!(raw.contains("Cc: books@apexsolar.in"));
+        assert!(raw.contains("Subject: Invoice 001\r\n"));
+
+        let with_pdf = build_message(&settings(), &email("accounts@acme.in", ""), Some(("Invoice_001.pdf", b"%PDF-1.7".to_vec()))).unwrap();
+        let raw = String::from_utf8(with_pdf.formatted()).unwrap();
+        assert!(raw.contains("Content-Type: application/pdf"));
+        assert!(raw.contains("Invoice_001.pdf"));
+        assert!(!raw.contains("Cc:"));
+    }
+
+    #[test]
+    fn bad_or_missing_addresses_are_refused() {
+        let error = build_message(&settings(), &email(" , ;", "books@apexsolar.in"), None).unwrap_err();
+        assert_eq!(error, "Add at least one recipient. The customer has no email address on file.");
+        let error = build_message(&settings(), &email("accounts@acme.in, not-an-address", ""), None).unwrap_err();
+        assert_eq!(error, "not-an-address is not a valid email address");
+        let error = build_message(&settings(), &email("accounts@acme.in", "books@"), None).unwrap_err();
+        assert_eq!(error, "books@ is not a valid email address");
+        let sender = SmtpSettings { from_address: "billing".to_string(), ..settings() };
+        assert!(build_message(&sender, &email("accounts@acme.in", ""), None).unwrap_err().contains("not a valid sender address"));
+    }
+
+    /// Sends through a local catcher such as Mailpit or MailHog:
+    /// `APEX_SMTP_TEST_SERVER=localhost:1025 cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore = "needs an SMTP catcher in APEX_SMTP_TEST_SERVER"]
+    async fn sends_through_a_plain_smtp_server() {
+        let server = std::env::var("APEX_SMTP_TEST_SERVER").expect("APEX_SMTP_TEST_SERVER is not set");
+        let (host, port) = server.rsplit_once(':').expect("APEX_SMTP_TEST_SERVER is host:port");
+        let settings = SmtpSettings { host: host.to_string(), port: port.parse().unwrap(), security: "none".to_string(), ..settings() };
+        let message = build_message(&settings, &email("accounts@acme.in", "books@apexsolar.in"), Some(("Invoice_001.pdf", b"%PDF-1.7".to_vec()))).unwrap();
+        send(&settings, None, message).await.unwrap();
+    }
+}
@@ -0,0 +1,427 @@
+use crate::db;
+use crate::models::Invoice;
+use crate::spreadsheet::{self, date_cell, Cell};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A column that can be exported: the key the frontend selects it by and its header text.
+type Column = (&'static str, &'static str);
+
+const INVOICE_COLUMNS: &[Column] = &[
+    ("invoiceNumber", "Invoice No."),
+    ("invoiceDate", "Invoice Date"),
+    ("financialYear", "Financial Year"),
+    ("status", "Status"),
+    ("customerName", "Customer"),
+    ("customerGstin", "Customer GSTIN"),
+    ("customerState", "Customer State"),
+    ("workOrderReference", "Work Order Ref."),
+    ("workOrderDate", "Work Order Date"),
+    ("taxableValue", "Taxable Value"),
+    ("cgstRate", "CGST %"),
+    ("cgstAmount", "CGST"),
+    ("sgstRate", "SGST %"),
+    ("sgstAmount", "SGST"),
+    ("totalTax", "Total Tax"),
+    ("grandTotal", "Invoice Total"),
+    ("revision", "Revision"),
+    ("cancelledAt", "Cancelled On"),
+    ("cancellationReason", "Cancellation Reason"),
+];
+
+const ITEM_COLUMNS: &[Column] = &[
+    ("invoiceNumber", "Invoice No."),
+    ("invoiceDate", "Invoice Date"),
+    ("financialYear", "Financial Year"),
+    ("status", "Status"),
+    ("customerName", "Customer"),
+    ("customerGstin", "Customer GSTIN"),
+    ("serialNumber", "Sl. No."),
+    ("description", "Description"),
+    ("hsnSacCode", "HSN/SAC"),
+    ("quantity", "Quantity"),
+    ("unit", "Unit"),
+    ("rate", "Rate"),
+    ("amount", "Taxable Value"),
+    ("cgstRate", "CGST %"),
+    ("cgstAmount", "CGST"),
+    ("sgstRate", "SGST %"),
+    ("sgstAmount", "SGST"),
+];
+
+const CUSTOMER_COLUMNS: &[Column] = &[
+    ("companyName", "Company Name"),
+    ("gstNumber", "GSTIN"),
+    ("panNumber", "PAN"),
+    ("addressLine1", "Address Line 1"),
+    ("addressLine2", "Address Line 2"),
+    ("addressLine3", "Address Line 3"),
+    ("city", "City"),
+    ("state", "State"),
+    ("pincode", "Pincode"),
+    ("createdAt", "Added On"),
+];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    pub dataset: String,     // "invoices" | "items" | "customers"
+    pub format: String,      // "csv" | "xlsx"
+    pub date_format: String, // "indian" | "iso"
+    /// Inclusive `YYYY-MM-DD` bounds on the invoice date. Customers are not filtered by date.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Column keys in the order to write them; all columns when left out.
+    pub columns: Option<Vec<String>>,
+    #[serde(default)]
+    pub include_cancelled: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportColumn {
+    pub key: String,
+    pub header: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportReport {
+    pub path: String,
+    pub rows: usize,
+}
+
+fn dataset_columns(dataset: &str) -> Result<&'static [Column], String> {
+    match dataset {
+        "invoices" => Ok(INVOICE_COLUMNS),
+        "items" => Ok(ITEM_COLUMNS),
+        "customers" => Ok(CUSTOMER_COLUMNS),
+        other => Err(format!("Unknown export: {}", other)),
+    }
+}
+
+/// An issued invoice as stored, with the columns that are not part of its JSON.
+pub struct StoredInvoice {
+    pub invoice: Invoice,
+    pub status: String,
+    pub cancelled_at: Option<String>,
+    pub cancellation_reason: Option<String>,
+}
+
+/// Which issued invoices to load. Every field left out matches all of them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceFilter {
+    /// Inclusive `YYYY-MM-DD` bounds on the invoice date.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub customer_id: Option<String>,
+    pub financial_year: Option<String>,
+    #[serde(default)]
+    pub include_cancelled: bool,
+}
+
+pub fn load_invoices(conn: &Connection, filter: &InvoiceFilter) -> Result<Vec<StoredInvoice>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT json_data, status, revision, cancelled_at, cancellation_reason FROM invoices
+             WHERE status IN ('GENERATED', 'CANCELLED') AND json_data IS NOT NULL
+               AND (?1 IS NULL OR invoice_date >= ?1) AND (?2 IS NULL OR invoice_date <= ?2)
+               AND (?3 IS NULL OR customer_id = ?3) AND (?4 IS NULL OR financial_year = ?4)
+               AND (?5 OR status != 'CANCELLED')
+             ORDER BY invoice_date, invoice_number",
+        )
+        .map_err(|e| e.to_string())?;
+    let params = params![filter.from, filter.to, filter.customer_id, filter.financial_year, filter.include_cancelled];
+    let rows = stmt
+        .query_map(params, |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut invoices = Vec::new();
+    for row in rows {
+        let (json_data, status, revision, cancelled_at, cancellation_reason) = row.map_err(|e| e.to_string())?;
+        let mut invoice: Invoice = serde_json::from_str(&json_data).map_err(|e| e.to_string())?;
+        invoice.revision = revision;
+        invoices.push(StoredInvoice { invoice, status, cancelled_at, cancellation_reason });
+    }
+    Ok(invoices)
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn invoice_row(stored: &StoredInvoice) -> Vec<Cell> {
+    let invoice = &stored.invoice;
+    vec![
+        Cell::from(invoice.invoice_number.as_str()),
+        date_cell(&invoice.invoice_date),
+        Cell::from(invoice.financial_year.as_str()),
+        Cell::from(stored.status.as_str()),
+        Cell::from(invoice.customer.company_name.as_str()),
+        Cell::from(invoice.customer.gst_number.clone()),
+        Cell::from(invoice.customer.state.clone()),
+        Cell::from(invoice.work_order_reference.as_str()),
+        invoice.work_order_date.as_deref().map(date_cell).unwrap_or(Cell::Empty),
+        Cell::from(invoice.total_basic_amount),
+        Cell::from(invoice.cgst_percentage),
+        Cell::from(invoice.cgst_amount),
+        Cell::from(invoice.sgst_percentage),
+        Cell::from(invoice.sgst_amount),
+        Cell::from(round2(invoice.cgst_amount + invoice.sgst_amount)),
+        Cell::from(invoice.grand_total),
+        Cell::from(invoice.revision),
+        stored.cancelled_at.as_deref().map(date_cell).unwrap_or(Cell::Empty),
+        Cell::from(stored.cancellation_reason.clone()),
+    ]
+}
+
+fn item_rows(stored: &StoredInvoice) -> Vec<Vec<Cell>> {
+    let invoice = &stored.invoice;
+    invoice
+        .line_items
+        .iter()
+        .map(|item| {
+            vec![
+                Cell::from(invoice.invoice_number.as_str()),
+                date_cell(&invoice.invoice_date),
+                Cell::from(invoice.financial_year.as_str()),
+                Cell::from(stored.status.as_str()),
+                Cell::from(invoice.customer.company_name.as_str()),
+                Cell::from(invoice.customer.gst_number.clone()),
+                Cell::from(item.serial_number),
+                Cell::from(item.description.as_str()),
+                Cell::from(item.hsn_sac_code.as_str()),
+                Cell::from(item.quantity),
+                Cell::from(item.unit.as_str()),
+                Cell::from(item.rate),
+                Cell::from(item.amount),
+                Cell::from(invoice.cgst_percentage),
+                Cell::from(round2(item.amount * invoice.cgst_percentage / 100.0)),
+                Cell::from(invoice.sgst_percentage),
+                Cell::from(round2(item.amount * invoice.sgst_percentage / 100.0)),
+            ]
+        })
+        .collect()
+}
+
+fn customer_rows(conn: &Connection) -> Result<Vec<Vec<Cell>>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT company_name, gst_number, pan_number, address_line1, address_line2, address_line3, city, state, pincode,
+                    date(created_at)
+             FROM customers WHERE deleted_at IS NULL ORDER BY company_name COLLATE NOCASE",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            let mut cells = Vec::new();
+            for i in 0..9 {
+                cells.push(Cell::from(row.get::<_, Option<String>>(i)?));
+            }
+            cells.push(row.get::<_, Option<String>>(9)?.as_deref().map(date_cell).unwrap_or(Cell::Empty));
+            Ok(cells)
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Writes the chosen dataset to `target` and returns how many rows were written.
+pub fn export(conn: &Connection, options: &ExportOptions, target: &Path) -> Result<usize, String> {
+    let columns = dataset_columns(&options.dataset)?;
+    let selected: Vec<usize> = match &options.columns {
+        Some(keys) if !keys.is_empty() => keys
+            .iter()
+            .map(|key| columns.iter().position(|(k, _)| k == key).ok_or_else(|| format!("Unknown column: {}", key)))
+            .collect::<Result<_, _>>()?,
+        _ => (0..columns.len()).collect(),
+    };
+
+    let filter = InvoiceFilter {
+        from: options.from.clone(),
+        to: options.to.clone(),
+        include_cancelled: options.include_cancelled,
+        ..Default::default()
+    };
+    let rows = match options.dataset.as_str() {
+        "invoices" => load_invoices(conn, &filter)?.iter().map(invoice_row).collect(),
+        "items" => load_invoices(conn, &filter)?.iter().flat_map(item_rows).collect(),
+        _ => customer_rows(conn)?,
+    };
+    let rows: Vec<Vec<Cell>> =
+        rows.into_iter().map(|row| selected.iter().map(|&i| row[i].clone()).collect()).collect();
+    let header: Vec<&str> = selected.iter().map(|&i| columns[i].1).collect();
+
+    match options.format.as_str() {
+        "csv" => spreadsheet::write_csv(target, &header, &rows, &options.date_format)?,
+        "xlsx" => {
+            let sheet = match options.dataset.as_str() {
+                "invoices" => "Invoices",
+                "items" => "Line Items",
+                _ => "Customers",
+            };
+            spreadsheet::write_xlsx(target, sheet, &header, &rows, &options.date_format)?
+        }
+        other => return Err(format!("Unknown export format: {}", other)),
+    }
+    Ok(rows.len())
+}
+
+/// The columns a dataset can be exported with, in their default order.
+#[tauri::command]
+pub async fn get_export_columns(dataset: String) -> Result<Vec<ExportColumn>, String> {
+    Ok(dataset_columns(&dataset)?
+        .iter()
+        .map(|(key, header)| ExportColumn { key: key.to_string(), header: header.to_string() })
+        .collect())
+}
+
+/// Exports invoices, line items or customers to a CSV or XLSX file picked by the user.
+#[tauri::command]
+pub async fn export_spreadsheet(app_handle: tauri::AppHandle, options: ExportOptions, target_path: String) -> Result<ExportReport, String> {
+    let conn = db::open(&app_handle)?;
+    let rows = export(&conn, &options, Path::new(&target_path))?;
+    log::info!("Exported {} {} row(s) to {}", rows, options.dataset, target_path);
+    Ok(ExportReport { path: target_path, rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoices;
+    use crate::models::LineItem;
+    use crate::test_support::{insert_customer, migrated_db, sample_invoice};
+    use std::io::Read;
+
+    /// 001 on 1 April, 002 (two items) on 3 July, 003 on 31 July and cancelled, 004 on 1 August.
+    fn invoices_db() -> Connection {
+        let mut conn = migrated_db();
+        insert_customer(&conn, "c1", "Acme Pvt Ltd");
+        for (number, date) in [("001", "2024-04-01"), ("002", "2024-07-03"), ("003", "2024-07-31"), ("004", "2024-08-01")] {
+            let mut invoice = sample_invoice(number, "c1");
+            invoice.invoice_date = date.to_string();
+            if number == "002" {
+                invoice.line_items.push(LineItem {
+                    id: "002-2".to_string(),
+                    serial_number: 2,
+                    description: "Installation".to_string(),
+                    hsn_sac_code: "9954".to_string(),
+                    rate: 10000.0,
+                    quantity: 1.0,
+                    unit: "Job".to_string(),
+                    amount: 10000.0,
+                });
+            }
+            invoices::issue(&mut conn, invoice).unwrap();
+        }
+        invoices::cancel(&conn, "003", "Raised twice", Some("2024-08-02")).unwrap();
+        conn
+    }
+
+    fn options(dataset: &str, format: &str, columns: &[&str]) -> ExportOptions {
+        ExportOptions {
+            dataset: dataset.to_string(),
+            format: format.to_string(),
+            date_format: "indian".to_string(),
+            from: None,
+            to: None,
+            columns: Some(columns.iter().map(|c| c.to_string()).collect()),
+            include_cancelled: false,
+        }
+    }
+
+    fn export_csv(conn: &Connection, options: &ExportOptions) -> Vec<Vec<String>> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        let rows = export(conn, options, &path).unwrap();
+        let table = spreadsheet::read_table(&path).unwrap();
+        assert_eq!(table.iter().filter(|row| !row.is_empty()).count(), rows + 1);
+        table.into_iter().filter(|row| !row.is_empty()).collect()
+    }
+
+    #[test]
+    fn the_date_range_is_inclusive_and_cancelled_invoices_are_opt_in() {
+        let conn = invoices_db();
+        let mut options = options("invoices", "csv", &["invoiceNumber", "status"]);
+        options.from = Some("2024-04-01".to_string());
+        options.to = Some("2024-07-31".to_string());
+
+        let numbers = |table: Vec<Vec<String>>| table.into_iter().skip(1).map(|row| row.join(" ")).collect::<Vec<_>>();
+        assert_eq!(numbers(export_csv(&conn, &options)), vec!["001 GENERATED", "002 GENERATED"]);
+        options.include_cancelled = true;
+        assert_eq!(numbers(export_csv(&conn, &options)), vec!["001 GENERATED", "002 GENERATED", "003 CANCELLED"]);
+        options.from = None;
+        options.to = Some("2024-04-01".to_string());
+        assert_eq!(numbers(export_csv(&conn, &options)), vec!["001 GENERATED"]);
+    }
+
+    #[test]
+    fn columns_are_written_as_chosen() {
+        let conn = invoices_db();
+        let chosen = options("invoices", "csv", &["grandTotal", "invoiceNumber", "customerGstin"]);
+        let table = export_csv(&conn, &chosen);
+        assert_eq!(table[0], vec!["Invoice Total", "Invoice No.", "Customer GSTIN"]);
+        assert_eq!(table[1], vec!["252000", "001", "19AAACA1234A1Z5"]);
+
+        let mut all = chosen;
+        all.columns = None;
+        assert_eq!(export_csv(&conn, &all)[0].len(), INVOICE_COLUMNS.len());
+
+        let mut unknown = options("invoices", "csv", &["invoiceNumber", "gstin"]);
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(export(&conn, &unknown, &dir.path().join("export.csv")).unwrap_err(), "Unknown column: gstin");
+        unknown.dataset = "payments".to_string();
+        assert_eq!(export(&conn, &unknown, &dir.path().join("export.csv")).unwrap_err(), "Unknown export: payments");
+    }
+
+    #[test]
+    fn dates_follow_the_chosen_format() {
+        let conn = invoices_db();
+        let mut options = options("invoices", "csv", &["invoiceNumber", "invoiceDate", "cancelledAt"]);
+        options.include_cancelled = true;
+        let table = export_csv(&conn, &options);
+        assert_eq!(table[2], vec!["002", "03-07-2024", ""]);
+        assert_eq!(table[3], vec!["003", "31-07-2024", "02-08-2024"]);
+        options.date_format = "iso".to_string();
+        assert_eq!(export_csv(&conn, &options)[2], vec!["002", "2024-07-03", ""]);
+
+        // XLSX keeps real dates and only changes how they are shown
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.xlsx");
+        for (date_format, shown_as) in [("indian", "dd-mm-yyyy"), ("iso", "yyyy-mm-dd")] {
+            options.format = "xlsx".to_string();
+            options.date_format = date_format.to_string();
+            export(&conn, &options, &path).unwrap();
+            assert_eq!(spreadsheet::read_xlsx(&path).unwrap()[2], vec!["002", "45476"]);
+            let mut styles = String::new();
+            let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+            archive.by_name("xl/styles.xml").unwrap().read_to_string(&mut styles).unwrap();
+            assert!(styles.contains(&format!(r#"formatCode="{}""#, shown_as)), "{}", styles);
+        }
+    }
+
+    #[test]
+    fn line_items_are_one_row_each_with_their_invoice() {
+        let conn = invoices_db();
+        let mut options = options("items", "csv", &["invoiceNumber", "invoiceDate", "customerName", "serialNumber", "hsnSacCode", "amount", "cgstAmount"]);
+        options.from = Some("2024-07-03".to_string());
+        options.to = Some("2024-07-03".to_string());
+        let table = export_csv(&conn, &options);
+        assert_eq!(
+            table[1..],
+            vec![
+                vec!["002", "03-07-2024", "Acme Pvt Ltd", "1", "8541", "225000", "13500"],
+                vec!["002", "03-07-2024", "Acme Pvt Ltd", "2", "9954", "10000", "600"],
+            ]
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{backup, bundle, changeset, crypto};
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+/// Emitted when files were queued for the frontend; it collects them with `take_file_open_requests`.
+pub const FILE_OPEN_EVENT: &str = "file-open-requested";
+const MAIN_WINDOW: &str = "main";
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OpenKind {
+    /// A snapshot or bundle; offered through the restore preview.
+    Backup,
+    /// Row changes exported by another installation; merged after a preview.
+    Changeset,
+    /// A PDF this app exported; its invoice is looked up in the history.
+    Invoice,
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenRequest {
+    pub path: String,
+    pub kind: OpenKind,
+    pub invoice_number: Option<String>,
+    pub financial_year: Option<String>,
+}
+
+/// Files handed to this or a second instance that the frontend has not picked up yet.
+#[derive(Default)]
+pub struct PendingOpens(Mutex<Vec<OpenRequest>>);
+
+impl PendingOpens {
+    fn push(&self, requests: Vec<OpenRequest>) {
+        self.0.lock().unwrap().extend(requests);
+    }
+
+    fn take(&self) -> Vec<OpenRequest> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+fn has_sqlite_header(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    fs::File::open(path).and_then(|mut f| f.read_exact(&mut header)).is_ok() && header == SQLITE_MAGIC
+}
+
+pub fn classify(path: &Path) -> OpenRequest {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    // Matches the names pdfGenerator gives exported invoices
+    let invoice = Regex::new(r"^Invoice_AS_(\d{2,4}-\d{2,4})_(.+?)(?:_Rev\d+)?(?:_CANCELLED)?\.pdf$").unwrap();
+    let mut request = OpenRequest {
+        path: path.to_string_lossy().into_owned(),
+        kind: OpenKind::Unsupported,
+        invoice_number: None,
+        financial_year: None,
+    };
+    if backup::is_backup_file_name(&name) || bundle::read_manifest(path).is_ok() || crypto::is_encrypted(path) || has_sqlite_header(path) {
+        request.kind = OpenKind::Backup;
+    } else if name.to_lowercase().ends_with(".json") && changeset::is_changeset_file(path) {
+        request.kind = OpenKind::Changeset;
+    } else if let Some(caps) = invoice.captures(&name) {
+        request.kind = OpenKind::Invoice;
+        request.financial_year = Some(caps[1].to_string());
+        request.invoice_number = Some(caps[2].to_string());
+    }
+    request
+}
+
+/// The existing files among a launch's arguments. The first argument is the executable, flags
+/// are skipped, relative paths are taken from the launching process's directory and `file://`
+/// URLs (as some desktops pass them) are decoded.
+pub fn requests_from_args(args: &[String], cwd: &Path) -> Vec<OpenRequest> {
+    args.iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| match arg.strip_prefix("file://") {
+            Some(url) => PathBuf::from(percent_decode_str(url).decode_utf8_lossy().into_owned()),
+            None => PathBuf::from(arg),
+        })
+        .map(|path| if path.is_absolute() { path } else { cwd.join(path) })
+        .filter(|path| path.is_file())
+        .map(|path| classify(&path))
+        .collect()
+}
+
+fn queue(app_handle: &tauri::AppHandle, requests: Vec<OpenRequest>) {
+    if requests.is_empty() {
+        return;
+    }
+    app_handle.state::<PendingOpens>().push(requests);
+    let _ = app_handle.emit_all(FILE_OPEN_EVENT, ());
+}
+
+/// Files the app itself was started with.
+pub fn queue_launch_args(app_handle: &tauri::AppHandle) {
+    let args: Vec<String> = std::env::args().collect();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    queue(app_handle, requests_from_args(&args, &cwd));
+}
+
+/// Called in the running instance when the app is launched again: brings its window forward
+/// and takes over the files the second launch was given, which then exits.
+pub fn on_second_instance(app_handle: &tauri::AppHandle, args: Vec<String>, cwd: String) {
+    if let Some(window) = app_handle.get_window(MAIN_WINDOW) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    queue(app_handle, requests_from_args(&args, Path::new(&cwd)));
+}
+
+#[tauri::command]
+pub async fn take_file_open_requests(pending: tauri::State<'_, PendingOpens>) -> Result<Vec<OpenRequest>, String> {
+    Ok(pending.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::RetentionPolicy;
+    use crate::device::Device;
+    use crate::test_support::{migrated_db, migrated_db_at};
+
+    fn kind(path: &Path) -> OpenKind {
+        classify(path).kind
+    }
+
+    #[test]
+    fn backups_are_recognised_by_name_or_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("invoices.db");
+        drop(migrated_db_at(&db_path));
+        assert_eq!(kind(&db_path), OpenKind::Backup);
+        // A snapshot name is enough, even before the file is read
+        assert_eq!(kind(&dir.path().join("invoices-20240703-101500-1a2b3c4d.zip.enc")), OpenKind::Backup);
+
+        // Renamed copies still open as backups by their content
+        let copied_db = dir.path().join("office copy.sqlite");
+        fs::copy(&db_path, &copied_db).unwrap();
+        assert_eq!(kind(&copied_db), OpenKind::Backup);
+
+        let generated = dir.path().join("generated");
+        fs::create_dir_all(&generated).unwrap();
+        let device = Device { id: "1a2b3c4d5e6f7a8b".to_string(), name: "Office".to_string() };
+        let policy = RetentionPolicy { daily: 0, weekly: 0, monthly: 0 };
+        for passphrase in [None, Some("backup passphrase")] {
+            let target = dir.path().join("backups");
+            let result = bundle::create_bundle(&db_path, &generated, &[], &target, &policy, passphrase, &device).unwrap();
+            let bundle = PathBuf::from(&result.path);
+            assert_eq!(kind(&bundle), OpenKind::Backup, "{}", result.path);
+            let renamed = dir.path().join(format!("from the office {}", passphrase.is_some()));
+            fs::rename(&bundle, &renamed).unwrap();
+            assert_eq!(kind(&renamed), OpenKind::Backup, "{}", result.path);
+        }
+    }
+
+    #[test]
+    fn exported_invoices_are_matched_with_their_number() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, number) in [
+            ("Invoice_AS_2024-25_001.pdf", "001"),
+            ("Invoice_AS_2024-25_001_Rev2.pdf", "001"),
+            ("Invoice_AS_2024-25_001_CANCELLED.pdf", "001"),
+            ("Invoice_AS_2024-25_001_Rev2_CANCELLED.pdf", "001"),
+            ("Invoice_AS_2024-2025_APX-17_Rev10.pdf", "APX-17"),
+        ] {
+            let path = dir.path().join(name);
+            fs::write(&path, b"%PDF-1.4").unwrap();
+            let request = classify(&path);
+            assert_eq!(request.kind, OpenKind::Invoice, "{}", name);
+            assert_eq!(request.invoice_number.as_deref(), Some(number), "{}", name);
+            assert!(request.financial_year.as_deref().is_some_and(|fy| fy.starts_with("2024-")), "{}", name);
+        }
+    }
+
+    #[test]
+    fn changesets_are_recognised_and_anything_else_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let device = Device { id: "1a2b3c4d5e6f7a8b".to_string(), name: "Office".to_string() };
+        let changeset = changeset::export(&migrated_db(), &device, 0).unwrap();
+        let changeset_path = dir.path().join("changes.json");
+        fs::write(&changeset_path, serde_json::to_string(&changeset).unwrap()).unwrap();
+        assert_eq!(kind(&changeset_path), OpenKind::Changeset);
+
+        for (name, content) in [
+            ("settings.json", &b"{\"format\": \"something else\"}"[..]),
+            ("Invoice_001.pdf", b"%PDF-1.4"),
+            ("Invoice_AS_2024-25_001.txt", b"notes"),
+            ("quote.pdf", b"%PDF-1.4"),
+            ("photo.zip", b"PK\x03\x04"),
+            ("invoices.db.bak", b"not a database"),
+        ] {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            let request = classify(&path);
+            assert_eq!(request.kind, OpenKind::Unsupported, "{}", name);
+            assert_eq!(request.invoice_number, None);
+        }
+    }
+
+    #[test]
+    fn a_second_launch_passes_on_only_the_files_it_was_given() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("My Invoices");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("Invoice_AS_2024-25_001.pdf"), b"%PDF-1.4").unwrap();
+        fs::write(folder.join("notes.txt"), b"notes").unwrap();
+        let url = format!("file://{}", folder.join("Invoice_AS_2024-25_001.pdf").to_string_lossy().replace(' ', "%20"));
+
+        let args: Vec<String> = ["apex-invoice", "--minimized", &url, "notes.txt", "missing.pdf", "."]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let requests = requests_from_args(&args, &folder);
+        let kinds: Vec<(String, OpenKind)> = requests
+            .iter()
+            .map(|r| (Path::new(&r.path).file_name().unwrap().to_string_lossy().into_owned(), r.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![("Invoice_AS_2024-25_001.pdf".to_string(), OpenKind::Invoice), ("notes.txt".to_string(), OpenKind::Unsupported)]
+        );
+        assert!(requests.iter().all(|r| Path::new(&r.path).is_absolute()));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::conflict::{self, SyncCheck};
+use crate::vault::Vault;
+use crate::{backup, crypto, db, device};
+use notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Manager;
+
+/// Emitted with a [`BackupFolderChange`] when a backup appears in the backup folder.
+pub const BACKUP_FOLDER_EVENT: &str = "backup-folder-changed";
+/// Sync clients often write a file in several steps; wait for the folder to settle before looking.
+const SETTLE_DELAY: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupFolderChange {
+    pub file: String,
+    pub check: SyncCheck,
+}
+
+/// The watcher on the configured backup folder, if there is one.
+#[derive(Default)]
+pub struct FolderWatch(Mutex<Option<Debouncer<RecommendedWatcher>>>);
+
+impl FolderWatch {
+    /// Dropping the previous watcher stops it.
+    fn set(&self, watcher: Option<Debouncer<RecommendedWatcher>>) {
+        *self.0.lock().unwrap() = watcher;
+    }
+}
+
+/// The newest backup among the changed paths, leaving out deleted files, sync clients' temp
+/// files and our own `.partial` files.
+pub fn newest_backup(paths: &[PathBuf]) -> Option<PathBuf> {
+    let names = paths
+        .iter()
+        .filter(|path| path.is_file())
+        .filter_map(|path| path.file_name()?.to_str());
+    let newest = backup::latest_backup_name(names)?;
+    paths.iter().find(|path| path.file_name().and_then(|n| n.to_str()) == Some(newest)).cloned()
+}
+
+/// Calls `on_backup` with the newest backup each time backups in `dir` have been written and
+/// the folder has been quiet for `delay`. Watching stops when the debouncer is dropped.
+pub fn watch<F>(dir: &Path, delay: Duration, on_backup: F) -> Result<Debouncer<RecommendedWatcher>, String>
+where
+    F: Fn(PathBuf) + Send + 'static,
+{
+    let mut debouncer = new_debouncer(delay, move |result: DebounceEventResult| match result {
+        Ok(events) => {
+            let paths: Vec<PathBuf> = events.into_iter().map(|event| event.path).collect();
+            if let Some(path) = newest_backup(&paths) {
+                on_backup(path);
+            }
+        }
+        Err(e) => log::warn!("Watching the backup folder failed: {}", e),
+    })
+    .map_err(|e| e.to_string())?;
+    debouncer.watcher().watch(dir, RecursiveMode::NonRecursive).map_err(|e| e.to_string())?;
+    Ok(debouncer)
+}
+
+fn check_folder(app_handle: &tauri::AppHandle, dir: &Path) -> Result<SyncCheck, String> {
+    let config_dir = device::config_dir(app_handle)?;
+    let passphrase = crypto::passphrase_if_unlocked(&config_dir, &app_handle.state::<Vault>())?;
+    conflict::check(&db::db_path(app_handle)?, dir, &config_dir, passphrase.as_deref())
+}
+
+/// Watches the backup folder from the settings, replacing the watcher on the previous one, so a
+/// newer backup synced in from another device is offered right away. Stops watching when no
+/// folder is set.
+#[tauri::command]
+pub async fn watch_backup_folder(app_handle: tauri::AppHandle, folder_watch: tauri::State<'_, FolderWatch>) -> Result<(), String> {
+    let dir = backup::configured_dir(&db::open(&app_handle)?)?;
+    folder_watch.set(None);
+    let Some(dir) = dir else {
+        return Ok(());
+    };
+    let handle = app_handle.clone();
+    let watched = dir.clone();
+    let watcher = watch(&dir, SETTLE_DELAY, move |file| match check_folder(&handle, &watched) {
+        Ok(check) => {
+            let change = BackupFolderChange { file: file.to_string_lossy().into_owned(), check };
+            let _ = handle.emit_all(BACKUP_FOLDER_EVENT, change);
+        }
+        Err(e) => log::warn!("Checking {} failed: {}", file.display(), e),
+    })?;
+    folder_watch.set(Some(watcher));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+
+    #[test]
+    fn only_backups_that_still_exist_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let older = dir.path().join("invoices-20240702-101500-1a2b3c4d.zip");
+        let newer = dir.path().join("invoices-20240703-101500-9f8e7d6c.zip.enc");
+        let partial = dir.path().join("invoices-20240704-101500-1a2b3c4d.zip.partial");
+        let removed = dir.path().join("invoices-20240705-101500-1a2b3c4d.zip");
+        for path in [&older, &newer, &partial] {
+            fs::write(path, b"backup").unwrap();
+        }
+        assert_eq!(newest_backup(&[older.clone(), newer.clone(), partial.clone(), removed]), Some(newer));
+        assert_eq!(newest_backup(&[partial.clone(), older.clone()]), Some(older));
+        assert_eq!(newest_backup(&[partial]), None);
+    }
+
+    #[test]
+    fn a_backup_written_into_the_folder_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let (tx, rx) = mpsc::channel();
+        let _watcher = watch(dir.path(), Duration::from_millis(100), move |path| {
+            let _ = tx.send(path);
+        }).unwrap();
+
+        fs::write(dir.path().join("notes.txt"), b"not a backup").unwrap();
+        let backup = dir.path().join("invoices-20240703-101500-9f8e7d6c.zip");
+        fs::write(&backup, b"backup").unwrap();
+        let reported = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(reported.file_name(), backup.file_name());
+    }
+}
@@ -0,0 +1,953 @@
+use crate::conflict::{self, SyncCheck, SYNC_CONFLICT};
+use crate::vault::{self, Vault};
+use crate::{backup, crypto, db, device};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::Manager;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// File in the app config dir holding the chosen folder, and the OAuth tokens until a vault is set up.
+const CONFIG_FILE: &str = "gdrive.json";
+/// Only grants access to files and folders created by this app, not the rest of the user's Drive.
+const SCOPE: &str = "https://www.googleapis.com/auth/drive.file";
+const FOLDER_MIME_TYPE: &str = "application/vnd.google-apps.folder";
+const DEFAULT_FOLDER_NAME: &str = "Apex Invoice Backups";
+const FILE_FIELDS: &str = "id,name,mimeType,size,modifiedTime,md5Checksum";
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+/// Refresh the access token this many seconds before Google says it expires.
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// Where the OAuth and Drive requests go. Everything sits under one base URL in [`Endpoints::at`],
+/// so the client can be pointed at a local mock server.
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    pub auth: String,
+    pub token: String,
+    pub revoke: String,
+    pub api: String,
+    pub upload: String,
+}
+
+impl Endpoints {
+    pub fn google() -> Self {
+        Endpoints {
+            auth: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+            token: "https://oauth2.googleapis.com/token".to_string(),
+            revoke: "https://oauth2.googleapis.com/revoke".to_string(),
+            api: "https://www.googleapis.com/drive/v3".to_string(),
+            upload: "https://www.googleapis.com/upload/drive/v3".to_string(),
+        }
+    }
+
+    pub fn at(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        Endpoints {
+            auth: format!("{}/o/oauth2/v2/auth", base),
+            token: format!("{}/token", base),
+            revoke: format!("{}/revoke", base),
+            api: format!("{}/drive/v3", base),
+            upload: format!("{}/upload/drive/v3", base),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OAuthClient {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+}
+
+impl OAuthClient {
+    /// The desktop OAuth client, supplied at build time. Google does not treat an installed
+    /// app's secret as confidential; PKCE is what protects the authorization code.
+    pub fn from_build_env() -> Option<Self> {
+        Some(OAuthClient {
+            client_id: option_env!("APEX_GOOGLE_CLIENT_ID")?.to_string(),
+            client_secret: option_env!("APEX_GOOGLE_CLIENT_SECRET").map(str::to_string),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Unix timestamp in seconds.
+    pub expires_at: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+    error: String,
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorBody {
+    error: ApiErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorDetail {
+    message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriveFile {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub mime_type: String,
+    /// Drive reports sizes as strings; folders have none.
+    #[serde(default, deserialize_with = "size_from_string")]
+    pub size: Option<u64>,
+    pub modified_time: Option<String>,
+    pub md5_checksum: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileList {
+    #[serde(default)]
+    files: Vec<DriveFile>,
+    next_page_token: Option<String>,
+}
+
+fn size_from_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Text(String),
+        Number(u64),
+    }
+    Ok(match Option::<Size>::deserialize(deserializer)? {
+        Some(Size::Text(text)) => text.parse().ok(),
+        Some(Size::Number(n)) => Some(n),
+        None => None,
+    })
+}
+
+fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    ChaCha20Rng::from_entropy().fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Proof Key for Code Exchange (RFC 7636): the challenge goes into the browser URL,
+/// the verifier only ever travels with the token request.
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn generate() -> Self {
+        // 32 random bytes encode to 43 characters, the minimum verifier length
+        let verifier = random_token(32);
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+        Pkce { verifier, challenge }
+    }
+}
+
+pub fn authorization_url(
+    endpoints: &Endpoints,
+    oauth: &OAuthClient,
+    redirect_uri: &str,
+    pkce: &Pkce,
+    state: &str,
+) -> Result<String, String> {
+    let url = Url::parse_with_params(
+        &endpoints.auth,
+        &[
+            ("client_id", oauth.client_id.as_str()),
+            ("redirect_uri", redirect_uri),
+            ("response_type", "code"),
+            ("scope", SCOPE),
+            ("code_challenge", pkce.challenge.as_str()),
+            ("code_challenge_method", "S256"),
+            ("state", state),
+            ("access_type", "offline"),
+            // Without it Google only returns a refresh token the first time the app is authorized
+            ("prompt", "consent"),
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(url.into())
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Serves the loopback redirect until the browser comes back with a code (or an error).
+async fn wait_for_redirect(listener: &TcpListener, state: &str) -> Result<String, String> {
+    loop {
+        let (mut stream, _) = listener.accept().await.map_err(|e| e.to_string())?;
+        let mut buf = vec![0u8; 8192];
+        let mut len = 0;
+        while len < buf.len() {
+            let n = stream.read(&mut buf[len..]).await.map_err(|e| e.to_string())?;
+            if n == 0 {
+                break;
+            }
+            len += n;
+            if buf[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+                break;
+            }
+        }
+
+        let request = String::from_utf8_lossy(&buf[..len]);
+        let target = request.lines().next().and_then(|line| line.split_whitespace().nth(1)).unwrap_or("/");
+        let url = match Url::parse("http://127.0.0.1").and_then(|base| base.join(target)) {
+            Ok(url) => url,
+            Err(_) => {
+                respond(&mut stream, "400 Bad Request", "").await;
+                continue;
+            }
+        };
+        let param = |key: &str| url.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.into_owned());
+        let (code, error) = (param("code"), param("error"));
+        if code.is_none() && error.is_none() {
+            // Browsers also ask for /favicon.ico
+            respond(&mut stream, "404 Not Found", "").await;
+            continue;
+        }
+
+        let result = if param("state").as_deref() != Some(state) {
+            Err("The sign-in response did not match the request. Please try again.".to_string())
+        } else if let Some(error) = error {
+            Err(match error.as_str() {
+                "access_denied" => "Google Drive access was not granted.".to_string(),
+                _ => format!("Google sign-in failed: {}", error),
+            })
+        } else {
+            code.ok_or_else(|| "Google sign-in returned no code".to_string())
+        };
+        let page = if result.is_ok() {
+            "<h3>Signed in to Google Drive.</h3><p>You can close this window and return to the app.</p>"
+        } else {
+            "<h3>Google sign-in failed.</h3><p>You can close this window and return to the app.</p>"
+        };
+        respond(&mut stream, "200 OK", page).await;
+        return result;
+    }
+}
+
+async fn token_request(
+    http: &Client,
+    endpoints: &Endpoints,
+    oauth: &OAuthClient,
+    params: &[(&str, &str)],
+) -> Result<TokenResponse, String> {
+    let mut form = vec![("client_id", oauth.client_id.as_str())];
+    if let Some(secret) = &oauth.client_secret {
+        form.push(("client_secret", secret.as_str()));
+    }
+    form.extend_from_slice(params);
+
+    let response = http.post(&endpoints.token).form(&form).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(match response.json::<OAuthError>().await {
+            Ok(e) if e.error == "invalid_grant" => {
+                "Google Drive access has expired or was revoked. Please reconnect your account.".to_string()
+            }
+            Ok(e) => format!("Google sign-in failed: {}", e.error_description.unwrap_or(e.error)),
+            Err(_) => format!("Google sign-in failed ({})", status),
+        });
+    }
+    response.json().await.map_err(|e| e.to_string())
+}
+
+/// Runs the installed-app authorization flow: listens on a random loopback port, lets
+/// `open_browser` show Google's consent page and exchanges the returned code for tokens.
+pub async fn authorize<F>(http: &Client, endpoints: &Endpoints, oauth: &OAuthClient, open_browser: F) -> Result<Tokens, String>
+where
+    F: FnOnce(&str) -> Result<(), String>,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let redirect_uri = format!("http://127.0.0.1:{}", port);
+    let pkce = Pkce::generate();
+    let state = random_token(16);
+
+    open_browser(&authorization_url(endpoints, oauth, &redirect_uri, &pkce, &state)?)?;
+    let code = tokio::time::timeout(AUTHORIZATION_TIMEOUT, wait_for_redirect(&listener, &state))
+        .await
+        .map_err(|_| "Timed out waiting for Google sign-in.".to_string())??;
+
+    let response = token_request(
+        http,
+        endpoints,
+        oauth,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", &code),
+            ("code_verifier", &pkce.verifier),
+            ("redirect_uri", &redirect_uri),
+        ],
+    )
+    .await?;
+    Ok(Tokens {
+        access_token: response.access_token,
+        refresh_token: response.refresh_token.ok_or("Google did not return a refresh token")?,
+        expires_at: now() + response.expires_in,
+    })
+}
+
+/// Best effort: the tokens are forgotten locally either way.
+pub async fn revoke(http: &Client, endpoints: &Endpoints, tokens: &Tokens) {
+    let _ = http.post(&endpoints.revoke).form(&[("token", tokens.refresh_token.as_str())]).send().await;
+}
+
+/// Drive file IDs are URL-safe; anything else must not be pasted into a request path.
+fn check_file_id(id: &str) -> Result<(), String> {
+    if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        Ok(())
+    } else {
+        Err("Invalid Google Drive file ID".to_string())
+    }
+}
+
+/// Quotes a value for a Drive search query.
+fn query_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+pub struct DriveClient {
+    http: Client,
+    endpoints: Endpoints,
+    oauth: OAuthClient,
+    tokens: Tokens,
+}
+
+impl DriveClient {
+    pub fn new(http: Client, endpoints: Endpoints, oauth: OAuthClient, tokens: Tokens) -> Self {
+        DriveClient { http, endpoints, oauth, tokens }
+    }
+
+    /// The current tokens, which change whenever the access token is refreshed.
+    pub fn tokens(&self) -> &Tokens {
+        &self.tokens
+    }
+
+    pub async fn refresh(&mut self) -> Result<(), String> {
+        let response = token_request(
+            &self.http,
+            &self.endpoints,
+            &self.oauth,
+            &[("grant_type", "refresh_token"), ("refresh_token", &self.tokens.refresh_token)],
+        )
+        .await?;
+        self.tokens.access_token = response.access_token;
+        self.tokens.expires_at = now() + response.expires_in;
+        // Google may rotate the refresh token
+        if let Some(refresh_token) = response.refresh_token {
+            self.tokens.refresh_token = refresh_token;
+        }
+        Ok(())
+    }
+
+    /// Sends an authorized request, refreshing the access token first when it is about to expire
+    /// and once more if Drive still answers 401.
+    async fn send<F>(&mut self, build: F) -> Result<Response, String>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        if self.tokens.expires_at - EXPIRY_MARGIN_SECS <= now() {
+            self.refresh().await?;
+        }
+        let mut response = build(&self.http).bearer_auth(&self.tokens.access_token).send().await.map_err(|e| e.to_string())?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            self.refresh().await?;
+            response = build(&self.http).bearer_auth(&self.tokens.access_token).send().await.map_err(|e| e.to_string())?;
+        }
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let detail = match response.json::<ApiErrorBody>().await {
+            Ok(body) => body.error.message,
+            Err(_) => status.canonical_reason().unwrap_or("Unknown error").to_string(),
+        };
+        Err(format!("Google Drive request failed ({}): {}", status.as_u16(), detail))
+    }
+
+    async fn query(&mut self, q: &str) -> Result<Vec<DriveFile>, String> {
+        let url = format!("{}/files", self.endpoints.api);
+        let fields = format!("nextPageToken,files({})", FILE_FIELDS);
+        let mut files = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut params = vec![
+                ("q", q.to_string()),
+                ("fields", fields.clone()),
+                ("orderBy", "modifiedTime desc".to_string()),
+                ("pageSize", "100".to_string()),
+                ("spaces", "drive".to_string()),
+            ];
+            if let Some(token) = &page_token {
+                params.push(("pageToken", token.clone()));
+            }
+            let page: FileList = self
+                .send(|http| http.get(&url).query(&params))
+                .await?
+                .json()
+                .await
+                .map_err(|e| e.to_string())?;
+            files.extend(page.files);
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => return Ok(files),
+            }
+        }
+    }
+
+    /// Files (not sub-folders) directly inside a folder, newest first.
+    pub async fn list(&mut self, folder_id: &str) -> Result<Vec<DriveFile>, String> {
+        self.query(&format!(
+            "{} in parents and trashed = false and mimeType != {}",
+            query_literal(folder_id),
+            query_literal(FOLDER_MIME_TYPE)
+        ))
+        .await
+    }
+
+    /// Reuses a folder of that name created by this app, or creates one in My Drive.
+    pub async fn find_or_create_folder(&mut self, name: &str) -> Result<DriveFile, String> {
+        let existing = self
+            .query(&format!(
+                "name = {} and mimeType = {} and trashed = false",
+                query_literal(name),
+                query_literal(FOLDER_MIME_TYPE)
+            ))
+            .await?;
+        if let Some(folder) = existing.into_iter().next() {
+            return Ok(folder);
+        }
+
+        let url = format!("{}/files", self.endpoints.api);
+        let metadata = serde_json::json!({ "name": name, "mimeType": FOLDER_MIME_TYPE });
+        self.send(|http| http.post(&url).query(&[("fields", FILE_FIELDS)]).json(&metadata))
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Uploads a file into a folder with a resumable upload session.
+    pub async fn upload(&mut self, folder_id: &str, path: &Path) -> Result<DriveFile, String> {
+        check_file_id(folder_id)?;
+        let name = path.file_name().ok_or("Invalid file path")?.to_string_lossy().into_owned();
+        let content = fs::read(path).map_err(|e| e.to_string())?;
+
+        let url = format!("{}/files", self.endpoints.upload);
+        let metadata = serde_json::json!({ "name": name, "parents": [folder_id] });
+        let session = self
+            .send(|http| {
+                http.post(&url)
+                    .query(&[("uploadType", "resumable"), ("fields", FILE_FIELDS)])
+                    .header("X-Upload-Content-Type", "application/octet-stream")
+                    .header("X-Upload-Content-Length", content.len())
+                    .json(&metadata)
+            })
+            .await?;
+        let location = session
+            .headers()
+            .get(LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or("Google Drive did not return an upload session")?
+            .to_string();
+
+        self.send(|http| http.put(&location).header(CONTENT_TYPE, "application/octet-stream").body(content.clone()))
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Downloads a file's content to `target`, returning the number of bytes written.
+    pub async fn download(&mut self, file_id: &str, target: &Path) -> Result<u64, String> {
+        check_file_id(file_id)?;
+        let url = format!("{}/files/{}", self.endpoints.api, file_id);
+        let mut response = self.send(|http| http.get(&url).query(&[("alt", "media")])).await?;
+
+        let mut file = fs::File::create(target).map_err(|e| e.to_string())?;
+        let mut written = 0u64;
+        let result: Result<(), String> = async {
+            while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+                file.write_all(&chunk).map_err(|e| e.to_string())?;
+                written += chunk.len() as u64;
+            }
+            file.sync_all().map_err(|e| e.to_string())
+        }
+        .await;
+        if let Err(e) = result {
+            let _ = fs::remove_file(target);
+            return Err(e);
+        }
+        Ok(written)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DriveConfig {
+    /// Only used while there is no vault; see [`vault::GDRIVE_TOKENS`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tokens: Option<Tokens>,
+    /// The tokens are held in the vault.
+    #[serde(default)]
+    tokens_in_vault: bool,
+    folder: Option<DriveFile>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriveStatus {
+    /// Whether this build has OAuth client credentials at all.
+    configured: bool,
+    connected: bool,
+    folder: Option<DriveFile>,
+}
+
+fn http_client() -> Result<Client, String> {
+    Client::builder()
+        .user_agent(concat!("ApexInvoice/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// `APEX_GDRIVE_BASE_URL` points the app at a mock Drive server instead of Google.
+fn endpoints() -> Endpoints {
+    match std::env::var("APEX_GDRIVE_BASE_URL") {
+        Ok(base) if !base.is_empty() => Endpoints::at(&base),
+        _ => Endpoints::google(),
+    }
+}
+
+fn oauth_client() -> Result<OAuthClient, String> {
+    OAuthClient::from_build_env().ok_or_else(|| "Google Drive is not configured in this build.".to_string())
+}
+
+fn config_path(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Failed to resolve app config dir")?;
+    Ok(config_dir.join(CONFIG_FILE))
+}
+
+fn load_config(app_handle: &tauri::AppHandle) -> Result<DriveConfig, String> {
+    let path = config_path(app_handle)?;
+    if !path.exists() {
+        return Ok(DriveConfig::default());
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+fn save_config(app_handle: &tauri::AppHandle, config: &DriveConfig) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    // May hold a refresh token, so keep it private like the backup passphrase
+    crypto::write_private_file(&config_path(app_handle)?, content.as_bytes())
+}
+
+fn status(config: &DriveConfig) -> DriveStatus {
+    DriveStatus {
+        configured: OAuthClient::from_build_env().is_some(),
+        connected: config.tokens.is_some() || config.tokens_in_vault,
+        folder: config.folder.clone(),
+    }
+}
+
+/// The stored tokens. They are read from the vault only here, right before a Drive request.
+fn load_tokens(config: &DriveConfig, vault: &Vault) -> Result<Option<Tokens>, String> {
+    if config.tokens_in_vault {
+        return vault.get_json(vault::GDRIVE_TOKENS);
+    }
+    Ok(config.tokens.clone())
+}
+
+fn connected_client(app_handle: &tauri::AppHandle, vault: &Vault) -> Result<(DriveClient, DriveConfig), String> {
+    let config = load_config(app_handle)?;
+    let tokens = load_tokens(&config, vault)?.ok_or("Google Drive is not connected.")?;
+    Ok((DriveClient::new(http_client()?, endpoints(), oauth_client()?, tokens), config))
+}
+
+/// Writes back tokens that were refreshed during a request, into the vault once there is one.
+fn store_tokens(
+    app_handle: &tauri::AppHandle,
+    vault: &Vault,
+    mut config: DriveConfig,
+    client: &DriveClient,
+) -> Result<DriveConfig, String> {
+    if vault::exists(&device::config_dir(app_handle)?) {
+        vault.put_json(vault::GDRIVE_TOKENS, client.tokens())?;
+        config.tokens = None;
+        config.tokens_in_vault = true;
+    } else {
+        config.tokens = Some(client.tokens().clone());
+        config.tokens_in_vault = false;
+    }
+    save_config(app_handle, &config)?;
+    Ok(config)
+}
+
+/// Moves tokens kept in `gdrive.json` into the vault, which must be unlocked.
+pub fn migrate_tokens(app_handle: &tauri::AppHandle, vault: &Vault) -> Result<bool, String> {
+    let mut config = load_config(app_handle)?;
+    let Some(tokens) = config.tokens.take() else {
+        return Ok(false);
+    };
+    vault.put_json(vault::GDRIVE_TOKENS, &tokens)?;
+    config.tokens_in_vault = true;
+    save_config(app_handle, &config)?;
+    Ok(true)
+}
+
+fn chosen_folder(config: &DriveConfig) -> Result<String, String> {
+    config.folder.as_ref().map(|f| f.id.clone()).ok_or_else(|| "Choose a Google Drive folder first.".to_string())
+}
+
+#[tauri::command]
+pub async fn gdrive_status(app_handle: tauri::AppHandle) -> Result<DriveStatus, String> {
+    Ok(status(&load_config(&app_handle)?))
+}
+
+#[tauri::command]
+pub async fn gdrive_connect(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>) -> Result<DriveStatus, String> {
+    if vault::exists(&device::config_dir(&app_handle)?) && !vault.is_unlocked() {
+        // Ask for the vault password before the browser round trip, not after it
+        return Err(vault::VAULT_LOCKED.to_string());
+    }
+    let http = http_client()?;
+    let oauth = oauth_client()?;
+    let tokens = authorize(&http, &endpoints(), &oauth, |url| {
+        tauri::api::shell::open(&app_handle.shell_scope(), url, None).map_err(|e| e.to_string())
+    })
+    .await?;
+
+    let mut client = DriveClient::new(http, endpoints(), oauth, tokens);
+    let mut config = load_config(&app_handle)?;
+    if config.folder.is_none() {
+        config.folder = Some(client.find_or_create_folder(DEFAULT_FOLDER_NAME).await?);
+    }
+    let config = store_tokens(&app_handle, &vault, config, &client)?;
+    Ok(status(&config))
+}
+
+#[tauri::command]
+pub async fn gdrive_disconnect(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>) -> Result<DriveStatus, String> {
+    let config = load_config(&app_handle)?;
+    if let Some(tokens) = load_tokens(&config, &vault)? {
+        revoke(&http_client()?, &endpoints(), &tokens).await;
+    }
+    if config.tokens_in_vault {
+        vault.remove(vault::GDRIVE_TOKENS)?;
+    }
+    let path = config_path(&app_handle)?;
+    if path.exists() {
+        fs::remove_file(path).map_err(|e| e.to_string())?;
+    }
+    Ok(status(&DriveConfig::default()))
+}
+
+#[tauri::command]
+pub async fn gdrive_set_folder(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    name: String,
+) -> Result<DriveStatus, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Folder name cannot be empty".to_string());
+    }
+    let (mut client, config) = connected_client(&app_handle, &vault)?;
+    let folder = client.find_or_create_folder(name).await;
+    let mut config = store_tokens(&app_handle, &vault, config, &client)?;
+    config.folder = Some(folder?);
+    save_config(&app_handle, &config)?;
+    Ok(status(&config))
+}
+
+/// Backups in the chosen folder, newest first.
+#[tauri::command]
+pub async fn gdrive_list_backups(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+) -> Result<Vec<DriveFile>, String> {
+    let (mut client, config) = connected_client(&app_handle, &vault)?;
+    let folder_id = chosen_folder(&config)?;
+    let files = client.list(&folder_id).await;
+    store_tokens(&app_handle, &vault, config, &client)?;
+
+    let mut backups: Vec<DriveFile> = files?.into_iter().filter(|f| backup::is_backup_file_name(&f.name)).collect();
+    backups.sort_by_key(|f| std::cmp::Reverse(backup::parse_snapshot_name(&f.name)));
+    Ok(backups)
+}
+
+fn download_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let cache_dir = app_handle.path_resolver().app_cache_dir().ok_or("Failed to resolve app cache dir")?;
+    Ok(cache_dir.join("downloads"))
+}
+
+/// Compares this device's data with the newest backup in the chosen folder, downloading it
+/// unless this device made it. `None` when there is nothing to compare with.
+async fn remote_check(
+    app_handle: &tauri::AppHandle,
+    vault: &Vault,
+    client: &mut DriveClient,
+    folder_id: &str,
+) -> Result<Option<SyncCheck>, String> {
+    let config_dir = device::config_dir(app_handle)?;
+    let device = device::load_or_create(&config_dir)?;
+    let files = client.list(folder_id).await?;
+    let Some(name) = conflict::newest_foreign_backup(files.iter().map(|f| f.name.as_str()), &device.id) else {
+        return Ok(None);
+    };
+    let file = files.iter().find(|f| f.name == name).ok_or("Google Drive listing changed")?;
+    // Backup names are unique, so a copy downloaded before is the same file
+    let download_dir = download_dir(app_handle)?;
+    let local = download_dir.join(name);
+    if !local.exists() {
+        fs::create_dir_all(&download_dir).map_err(|e| e.to_string())?;
+        client.download(&file.id, &local).await?;
+    }
+    let passphrase = crypto::passphrase_if_unlocked(&config_dir, vault)?;
+    let origin = conflict::origin(&local, passphrase.as_deref());
+    conflict::check_against(&db::db_path(app_handle)?, &config_dir, origin).map(Some)
+}
+
+/// How this device's data compares with the newest backup in Drive, for the conflict dialog.
+#[tauri::command]
+pub async fn gdrive_check_sync_status(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+) -> Result<Option<SyncCheck>, String> {
+    let (mut client, config) = connected_client(&app_handle, &vault)?;
+    let folder_id = chosen_folder(&config)?;
+    let check = remote_check(&app_handle, &vault, &mut client, &folder_id).await;
+    store_tokens(&app_handle, &vault, config, &client)?;
+    check
+}
+
+/// Uploads a backup from the backup folder. When the newest backup in Drive holds changes from
+/// another device that this device has not seen, fails with [`SYNC_CONFLICT`] unless `force` is set.
+#[tauri::command]
+pub async fn gdrive_upload_backup(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    path: String,
+    force: Option<bool>,
+) -> Result<DriveFile, String> {
+    let local = backup::configured_backup(&app_handle, &path)?;
+    let (mut client, config) = connected_client(&app_handle, &vault)?;
+    let folder_id = chosen_folder(&config)?;
+    let uploaded = async {
+        if !force.unwrap_or(false) {
+            let check = remote_check(&app_handle, &vault, &mut client, &folder_id).await?;
+            if check.is_some_and(|check| check.status.is_conflict()) {
+                return Err(SYNC_CONFLICT.to_string());
+            }
+        }
+        client.upload(&folder_id, &local).await
+    }
+    .await;
+    // The backup is in Drive either way; the next request can refresh the token again
+    if let Err(e) = store_tokens(&app_handle, &vault, config, &client) {
+        log::warn!("Saving the refreshed Google Drive tokens failed: {}", e);
+    }
+    uploaded
+}
+
+/// Downloads a backup into the app cache and returns the local path, ready for a restore preview.
+#[tauri::command]
+pub async fn gdrive_download_backup(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    file_id: String,
+    file_name: String,
+) -> Result<String, String> {
+    // The name ends up in a local path, so only accept our own backup names
+    if !backup::is_backup_file_name(&file_name) {
+        return Err("Not an invoice backup file".to_string());
+    }
+    let download_dir = download_dir(&app_handle)?;
+    fs::create_dir_all(&download_dir).map_err(|e| e.to_string())?;
+    let target = download_dir.join(&file_name);
+
+    let (mut client, config) = connected_client(&app_handle, &vault)?;
+    let downloaded = client.download(&file_id, &target).await;
+    store_tokens(&app_handle, &vault, config, &client)?;
+    downloaded?;
+    Ok(target.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn oauth() -> OAuthClient {
+        OAuthClient { client_id: "client-1".to_string(), client_secret: None }
+    }
+
+    fn tokens(access_token: &str) -> Tokens {
+        Tokens { access_token: access_token.to_string(), refresh_token: "refresh-1".to_string(), expires_at: now() + 3600 }
+    }
+
+    fn drive_file(id: &str, name: &str) -> serde_json::Value {
+        serde_json::json!({ "id": id, "name": name, "mimeType": "application/octet-stream", "size": "6" })
+    }
+
+    /// Plays the browser: follows the consent page's redirect back to the loopback listener.
+    fn redirect_back(auth_url: &str, code: &str) {
+        let url = Url::parse(auth_url).unwrap();
+        let param = |key: &str| url.query_pairs().find(|(k, _)| k == key).unwrap().1.into_owned();
+        let redirect = Url::parse(&param("redirect_uri")).unwrap();
+        let target = format!("/?code={}&state={}", code, param("state"));
+        let address = format!("{}:{}", redirect.host_str().unwrap(), redirect.port().unwrap());
+        std::thread::spawn(move || {
+            use std::io::Read;
+            let mut stream = std::net::TcpStream::connect(address).unwrap();
+            stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).as_bytes()).unwrap();
+            let _ = stream.read_to_end(&mut Vec::new());
+        });
+    }
+
+    #[tokio::test]
+    async fn authorization_exchanges_the_code_with_the_pkce_verifier() {
+        let server = MockServer::start(|_| {
+            MockResponse::json(200, serde_json::json!({ "access_token": "access-1", "expires_in": 3599, "refresh_token": "refresh-1" }))
+        });
+        let endpoints = Endpoints::at(&server.url);
+        let mut auth_url = String::new();
+        let tokens = authorize(&Client::new(), &endpoints, &oauth(), |url| {
+            auth_url = url.to_string();
+            redirect_back(url, "code-1");
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!((tokens.access_token.as_str(), tokens.refresh_token.as_str()), ("access-1", "refresh-1"));
+        assert!(tokens.expires_at > now() + 3500);
+
+        let auth_url = Url::parse(&auth_url).unwrap();
+        let param = |key: &str| auth_url.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.into_owned());
+        assert_eq!(param("code_challenge_method").as_deref(), Some("S256"));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let token_request = &requests[0];
+        assert_eq!((token_request.method.as_str(), token_request.path()), ("POST", "/token"));
+        assert_eq!(token_request.form("grant_type").as_deref(), Some("authorization_code"));
+        assert_eq!(token_request.form("code").as_deref(), Some("code-1"));
+        assert_eq!(token_request.form("redirect_uri"), param("redirect_uri"));
+        let verifier = token_request.form("code_verifier").unwrap();
+        assert_eq!(Some(URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))), param("code_challenge"));
+    }
+
+    #[tokio::test]
+    async fn a_401_refreshes_the_token_and_retries() {
+        let server = MockServer::start(|request| match request.path() {
+            "/token" => MockResponse::json(200, serde_json::json!({ "access_token": "fresh", "expires_in": 3599 })),
+            _ if request.header("Authorization") == Some("Bearer fresh") => {
+                MockResponse::json(200, serde_json::json!({ "files": [drive_file("f1", "invoices-20240703-101500.zip")] }))
+            }
+            _ => MockResponse::json(401, serde_json::json!({ "error": { "message": "Invalid Credentials" } })),
+        });
+        let mut client = DriveClient::new(Client::new(), Endpoints::at(&server.url), oauth(), tokens("stale"));
+
+        let files = client.list("folder-1").await.unwrap();
+        assert_eq!(files[0].size, Some(6));
+        assert_eq!(client.tokens().access_token, "fresh");
+        assert_eq!(client.tokens().refresh_token, "refresh-1", "kept when Google does not rotate it");
+        let requests = server.requests();
+        let paths: Vec<_> = requests.iter().map(|r| r.path()).collect();
+        assert_eq!(paths, ["/drive/v3/files", "/token", "/drive/v3/files"]);
+        assert_eq!(requests[1].form("grant_type").as_deref(), Some("refresh_token"));
+        assert_eq!(requests[1].form("refresh_token").as_deref(), Some("refresh-1"));
+    }
+
+    #[tokio::test]
+    async fn a_second_401_is_reported() {
+        let server = MockServer::start(|request| match request.path() {
+            "/token" => MockResponse::json(200, serde_json::json!({ "access_token": "fresh", "expires_in": 3599 })),
+            _ => MockResponse::json(401, serde_json::json!({ "error": { "message": "Invalid Credentials" } })),
+        });
+        let mut client = DriveClient::new(Client::new(), Endpoints::at(&server.url), oauth(), tokens("stale"));
+        assert_eq!(client.list("folder-1").await.unwrap_err(), "Google Drive request failed (401): Invalid Credentials");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn upload_goes_through_a_resumable_session() {
+        let session_opened = AtomicBool::new(false);
+        let server = MockServer::start(move |request| match (request.method.as_str(), request.path()) {
+            ("POST", "/upload/drive/v3/files") => {
+                session_opened.store(true, Ordering::SeqCst);
+                let session = format!("http://{}/upload/drive/v3/files?upload_id=session-1", request.header("Host").unwrap());
+                MockResponse::new(200, "").header("Location", &session)
+            }
+            ("PUT", "/upload/drive/v3/files") if session_opened.load(Ordering::SeqCst) => {
+                MockResponse::json(200, drive_file("f1", "invoices-20240703-101500.zip"))
+            }
+            _ => MockResponse::new(404, ""),
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("invoices-20240703-101500.zip");
+        fs::write(&local, b"backup").unwrap();
+        let mut client = DriveClient::new(Client::new(), Endpoints::at(&server.url), oauth(), tokens("access-1"));
+        let uploaded = client.upload("folder-1", &local).await.unwrap();
+        assert_eq!(uploaded.id, "f1");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let (start, content) = (&requests[0], &requests[1]);
+        assert_eq!(start.query("uploadType").as_deref(), Some("resumable"));
+        assert_eq!(start.header("X-Upload-Content-Length"), Some("6"));
+        assert_eq!(start.header("Authorization"), Some("Bearer access-1"));
+        let metadata: serde_json::Value = serde_json::from_slice(&start.body).unwrap();
+        assert_eq!(metadata, serde_json::json!({ "name": "invoices-20240703-101500.zip", "parents": ["folder-1"] }));
+        assert_eq!(content.query("upload_id").as_deref(), Some("session-1"));
+        assert_eq!(content.body, b"backup");
+    }
+
+    #[tokio::test]
+    async fn upload_refuses_a_folder_id_that_is_not_a_drive_id() {
+        let mut client = DriveClient::new(Client::new(), Endpoints::at("http://127.0.0.1:9"), oauth(), tokens("access-1"));
+        let err = client.upload("../files", Path::new("invoices-20240703-101500.zip")).await.unwrap_err();
+        assert_eq!(err, "Invalid Google Drive file ID");
+    }
+}
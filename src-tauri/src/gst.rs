@@ -0,0 +1,60 @@
+/// GST state codes, the first two digits of a GSTIN, with the state names Tally uses.
+const STATES: &[(&str, &str)] = &[
+    ("01", "Jammu & Kashmir"),
+    ("02", "Himachal Pradesh"),
+    ("03", "Punjab"),
+    ("04", "Chandigarh"),
+    ("05", "Uttarakhand"),
+    ("06", "Haryana"),
+    ("07", "Delhi"),
+    ("08", "Rajasthan"),
+    ("09", "Uttar Pradesh"),
+    ("10", "Bihar"),
+    ("11", "Sikkim"),
+    ("12", "Arunachal Pradesh"),
+    ("13", "Nagaland"),
+    ("14", "Manipur"),
+    ("15", "Mizoram"),
+    ("16", "Tripura"),
+    ("17", "Meghalaya"),
+    ("18", "Assam"),
+    ("19", "West Bengal"),
+    ("20", "Jharkhand"),
+    ("21", "Odisha"),
+    ("22", "Chhattisgarh"),
+    ("23", "Madhya Pradesh"),
+    ("24", "Gujarat"),
+    ("26", "Dadra & Nagar Haveli and Daman & Diu"),
+    ("27", "Maharashtra"),
+    ("29", "Karnataka"),
+    ("30", "Goa"),
+    ("31", "Lakshadweep"),
+    ("32", "Kerala"),
+    ("33", "Tamil Nadu"),
+    ("34", "Puducherry"),
+    ("35", "Andaman & Nicobar Islands"),
+    ("36", "Telangana"),
+    ("37", "Andhra Pradesh"),
+    ("38", "Ladakh"),
+    ("97", "Other Territory"),
+];
+
+pub fn state_name(code: &str) -> Option<&'static str> {
+    STATES.iter().find(|(c, _)| *c == code).map(|(_, name)| *name)
+}
+
+fn comparable(name: &str) -> String {
+    name.to_lowercase().replace(" and ", " & ").chars().filter(|c| c.is_alphanumeric() || *c == '&').collect()
+}
+
+/// Looks up the code of a state written out by name, e.g. "west bengal" or "Tamil Nadu".
+pub fn state_code(name: &str) -> Option<&'static str> {
+    let name = comparable(name);
+    STATES.iter().find(|(_, n)| comparable(n) == name).map(|(code, _)| *code)
+}
+
+/// State code of a GSTIN, if it starts with a known one.
+pub fn gstin_state(gstin: &str) -> Option<&'static str> {
+    let prefix = gstin.get(..2)?;
+    STATES.iter().find(|(code, _)| *code == prefix).map(|(code, _)| *code)
+}
@@ -0,0 +1,838 @@
+use crate::export::StoredInvoice;
+use crate::pdf_sign::{self, SignatureBlock, Signer};
+use crate::vault::{self, BankDetails, Vault};
+use crate::{bundle, db, device};
+use base64::Engine;
+use chrono::NaiveDate;
+use image::{DynamicImage, RgbImage};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, Stream};
+use printpdf::path::PaintMode;
+use printpdf::{
+    BuiltinFont, Color, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference,
+    PdfLayerReference, Point, Rect, Rgb, TextMatrix,
+};
+use rusqlite::{Connection, OptionalExtension};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+
+// Invoices rendered here follow the layout of generateInvoicePDF in src/services/pdfGenerator.ts,
+// which the app uses for single invoices. Positions are in mm from the top left, as there.
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 10.0;
+const PT_PER_MM: f32 = 72.0 / 25.4;
+/// Tables are centred on the page at this width.
+const TABLE_WIDTH: f32 = 183.0;
+const TABLE_X: f32 = (PAGE_WIDTH - TABLE_WIDTH) / 2.0;
+const ITEM_COLUMNS: [f32; 6] = [16.0, 80.0, 18.0, 23.0, 23.0, 23.0];
+const TAX_COLUMNS: [f32; 3] = [61.0, 61.0, 61.0];
+/// jspdf-autotable's default page margin, where the account details table starts.
+const ACCOUNT_TABLE_X: f32 = 40.0 / PT_PER_MM;
+const ACCOUNT_TABLE_WIDTH: f32 = 90.0;
+const CELL_PADDING: f32 = 2.0;
+const CELL_FONT_SIZE: f32 = 8.5;
+const GRID_LINE_WIDTH: f32 = 0.5;
+/// Space the account details, signature and GST number need below the tables.
+const FOOTER_HEIGHT: f32 = 50.0;
+
+/// The letterhead's address and contact details, shared with the app's own PDFs.
+const COMPANY_JSON: &str = include_str!("../../src/utils/company.json");
+
+const BLACK: (u8, u8, u8) = (0, 0, 0);
+const DARK_GRAY: (u8, u8, u8) = (51, 51, 51);
+const BRAND_BLUE: (u8, u8, u8) = (41, 98, 184);
+const RED: (u8, u8, u8) = (220, 0, 0);
+
+/// Widths of the printable ASCII characters in the standard Helvetica fonts, per 1000 units
+/// of font size, for centring and wrapping text.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667,
+    556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556,
+    556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722,
+    500, 500, 500, 334, 260, 334, 584,
+];
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722,
+    611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556,
+    611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778,
+    556, 556, 500, 389, 280, 389, 584,
+];
+
+#[derive(Debug, Deserialize)]
+struct CompanyDetails {
+    address: CompanyAddress,
+    contact: CompanyContact,
+    tagline: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompanyAddress {
+    full_address: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompanyContact {
+    phone: String,
+    email: String,
+}
+
+fn company_details() -> Result<CompanyDetails, String> {
+    serde_json::from_str(COMPANY_JSON).map_err(|e| format!("Invalid company details: {}", e))
+}
+
+/// What every invoice PDF shares: the company settings, bank details and images.
+pub struct Letterhead {
+    pub company: Value,
+    pub bank: BankDetails,
+    pub logo: Option<DynamicImage>,
+    pub stamp: Option<DynamicImage>,
+    /// Signs each invoice with a visible signature where the stamp would go, instead of the stamp.
+    pub signer: Option<Signer>,
+}
+
+fn setting(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+    conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get::<_, Option<String>>(0))
+        .optional()
+        .map(Option::flatten)
+        .map_err(|e| e.to_string())
+}
+
+/// Decodes an image saved by the settings page as a data URL. Transparent parts are laid on
+/// white, which is what the page behind them is.
+fn data_url_image(data_url: &str) -> Result<DynamicImage, String> {
+    let (_, data) = data_url.split_once(";base64,").ok_or("The image is not a base64 data URL")?;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim()).map_err(|e| e.to_string())?;
+    let image = image::load_from_memory(&bytes).map_err(|e| e.to_string())?.to_rgba8();
+    let flattened = RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    });
+    Ok(DynamicImage::ImageRgb8(flattened))
+}
+
+fn saved_image(conn: &Connection, key: &str) -> Result<Option<DynamicImage>, String> {
+    match setting(conn, key)?.filter(|url| !url.is_empty()) {
+        // A broken image leaves a gap on the invoice rather than stopping it, as in the app
+        Some(url) => Ok(data_url_image(&url).map_err(|e| log::warn!("Skipping the saved {}: {}", key, e)).ok()),
+        None => Ok(None),
+    }
+}
+
+impl Letterhead {
+    pub fn load(conn: &Connection, bank: BankDetails) -> Result<Letterhead, String> {
+        Ok(Letterhead {
+            company: vault::company_settings(conn)?.unwrap_or(Value::Null),
+            bank,
+            logo: saved_image(conn, "company_logo")?,
+            stamp: saved_image(conn, "stamp_signature")?,
+            signer: None,
+        })
+    }
+
+    fn field(&self, name: &str) -> &str {
+        self.company.get(name).and_then(Value::as_str).unwrap_or_default()
+    }
+}
+
+/// The name the app gives an invoice's PDF, e.g. `Invoice_AS_24-25_012_Rev1.pdf`.
+pub fn file_name(stored: &StoredInvoice) -> String {
+    let invoice = &stored.invoice;
+    let revision = if invoice.revision > 0 { format!("_Rev{}", invoice.revision) } else { String::new() };
+    let cancelled = if stored.status == "CANCELLED" { "_CANCELLED" } else { "" };
+    format!("Invoice_AS_{}_{}{}{}.pdf", invoice.financial_year, invoice.invoice_number, revision, cancelled)
+}
+
+/// `YYYY-MM-DD` (or a timestamp starting with it) in the given pattern; anything else as it is.
+fn format_date(value: &str, pattern: &str) -> String {
+    value
+        .get(..10)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .map(|date| date.format(pattern).to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+const ONES: [&str; 10] = ["", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine"];
+const TENS: [&str; 10] = ["", "", "Twenty", "Thirty", "Forty", "Fifty", "Sixty", "Seventy", "Eighty", "Ninety"];
+const TEENS: [&str; 10] =
+    ["Ten", "Eleven", "Twelve", "Thirteen", "Fourteen", "Fifteen", "Sixteen", "Seventeen", "Eighteen", "Nineteen"];
+
+fn two_digits(n: u64) -> String {
+    match n {
+        0 => String::new(),
+        1..=9 => ONES[n as usize].to_string(),
+        10..=19 => TEENS[(n - 10) as usize].to_string(),
+        _ => match n % 10 {
+            0 => TENS[(n / 10) as usize].to_string(),
+            unit => format!("{} {}", TENS[(n / 10) as usize], ONES[unit as usize]),
+        },
+    }
+}
+
+fn below_thousand(n: u64) -> String {
+    let hundreds = if n >= 100 { format!("{} Hundred", ONES[(n / 100) as usize]) } else { String::new() };
+    [hundreds, two_digits(n % 100)].into_iter().filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+/// Same wording as numberToWordsIndian in the frontend, e.g. "One Lakh Twenty Rupees and Five Paise Only".
+pub fn amount_in_words(amount: f64) -> String {
+    let paise_total = (amount * 100.0).round() as u64;
+    let (whole, paise) = (paise_total / 100, paise_total % 100);
+    if whole == 0 && paise == 0 {
+        return "Zero Rupees Only".to_string();
+    }
+    if whole == 0 {
+        return format!("{} Paise Only", two_digits(paise));
+    }
+    let mut parts = Vec::new();
+    let mut rest = whole;
+    for (value, name) in [(100_000_000_000, "Kharab"), (1_000_000_000, "Arab"), (10_000_000, "Crore"), (100_000, "Lakh"), (1000, "Thousand")] {
+        if rest >= value {
+            parts.push(format!("{} {}", below_thousand(rest / value), name));
+            rest %= value;
+        }
+    }
+    if rest > 0 {
+        parts.push(below_thousand(rest));
+    }
+    let mut words = format!("{} Rupees", parts.join(" "));
+    if paise > 0 {
+        words.push_str(&format!(" and {} Paise", two_digits(paise)));
+    }
+    words + " Only"
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+    Italic,
+    TimesBold,
+}
+
+/// Font size in points, font and colour of a piece of text.
+#[derive(Clone, Copy)]
+struct Style(f32, Font, (u8, u8, u8));
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+fn text_width(text: &str, font: Font, size: f32) -> f32 {
+    let widths = if font == Font::Regular || font == Font::Italic { &HELVETICA_WIDTHS } else { &HELVETICA_BOLD_WIDTHS };
+    let units: u32 = text
+        .chars()
+        .map(|c| match c as u32 {
+            code @ 32..=126 => widths[(code - 32) as usize] as u32,
+            _ => 556,
+        })
+        .sum();
+    units as f32 / 1000.0 * size / PT_PER_MM
+}
+
+/// Breaks text into lines no wider than `width`, at spaces where possible.
+fn wrap(text: &str, font: Font, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if text_width(&candidate, font, size) <= width || line.is_empty() && text_width(word, font, size) <= width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // A word longer than the line is split wherever it runs out of room
+            for c in word.chars() {
+                if !line.is_empty() && text_width(&format!("{}{}", line, c), font, size) > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+struct TableCell {
+    text: String,
+    font: Font,
+    align: Align,
+}
+
+fn cell(text: impl Into<String>, font: Font, align: Align) -> TableCell {
+    TableCell { text: text.into(), font, align }
+}
+
+struct Fonts {
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    italic: IndirectFontRef,
+    times_bold: IndirectFontRef,
+}
+
+struct Writer {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    fonts: Fonts,
+}
+
+fn color((r, g, b): (u8, u8, u8)) -> Color {
+    Color::Rgb(Rgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, None))
+}
+
+impl Writer {
+    fn new(title: &str) -> Result<Writer, String> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Invoice");
+        let font = |f: BuiltinFont| doc.add_builtin_font(f).map_err(|e| e.to_string());
+        let fonts = Fonts {
+            regular: font(BuiltinFont::Helvetica)?,
+            bold: font(BuiltinFont::HelveticaBold)?,
+            italic: font(BuiltinFont::HelveticaOblique)?,
+            times_bold: font(BuiltinFont::TimesBold)?,
+        };
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Writer { doc, layer, fonts })
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Invoice");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+    }
+
+    /// Starts a new page when `height` more does not fit below `y`, and returns where to continue.
+    fn reserve(&mut self, y: f32, height: f32) -> f32 {
+        if y + height > PAGE_HEIGHT - MARGIN {
+            self.new_page();
+            MARGIN
+        } else {
+            y
+        }
+    }
+
+    fn font_ref(&self, font: Font) -> &IndirectFontRef {
+        match font {
+            Font::Regular => &self.fonts.regular,
+            Font::Bold => &self.fonts.bold,
+            Font::Italic => &self.fonts.italic,
+            Font::TimesBold => &self.fonts.times_bold,
+        }
+    }
+
+    fn text(&self, text: &str, x: f32, y: f32, Style(size, font, rgb): Style, align: Align) {
+        let x = match align {
+            Align::Left => x,
+            Align::Center => x - text_width(text, font, size) / 2.0,
+            Align::Right => x - text_width(text, font, size),
+        };
+        self.layer.set_fill_color(color(rgb));
+        self.layer.use_text(text, size, Mm(x), Mm(PAGE_HEIGHT - y), self.font_ref(font));
+    }
+
+    /// Text centred on (`x`, `y`) and turned `angle` degrees anticlockwise.
+    fn rotated_text(&self, text: &str, size: f32, x: f32, y: f32, angle: f32, rgb: (u8, u8, u8)) {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let half = text_width(text, Font::Bold, size) / 2.0;
+        let start_x = (x - half * cos) * PT_PER_MM;
+        let start_y = (PAGE_HEIGHT - y - half * sin) * PT_PER_MM;
+        self.layer.set_fill_color(color(rgb));
+        self.layer.begin_text_section();
+        self.layer.set_font(&self.fonts.bold, size);
+        self.layer.set_text_matrix(TextMatrix::Raw([cos, sin, -sin, cos, start_x, start_y]));
+        self.layer.write_text(text, &self.fonts.bold);
+        self.layer.end_text_section();
+    }
+
+    fn line(&self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32, rgb: (u8, u8, u8)) {
+        self.layer.set_outline_color(color(rgb));
+        self.layer.set_outline_thickness(width * PT_PER_MM);
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(x1), Mm(PAGE_HEIGHT - y1)), false),
+                (Point::new(Mm(x2), Mm(PAGE_HEIGHT - y2)), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    fn image(&self, image: &DynamicImage, x: f32, y: f32, width: f32, height: f32) {
+        // At 72 dpi one pixel is one point, so the scale is the wanted size in points per pixel
+        let transform = ImageTransform {
+            translate_x: Some(Mm(x)),
+            translate_y: Some(Mm(PAGE_HEIGHT - y - height)),
+            scale_x: Some(width * PT_PER_MM / image.width() as f32),
+            scale_y: Some(height * PT_PER_MM / image.height() as f32),
+            dpi: Some(72.0),
+            ..Default::default()
+        };
+        Image::from_dynamic_image(image).add_to_layer(self.layer.clone(), transform);
+    }
+
+    fn row_height(cells: &[TableCell], widths: &[f32]) -> f32 {
+        let lines = cells
+            .iter()
+            .zip(widths)
+            .map(|(c, w)| wrap(&c.text, c.font, CELL_FONT_SIZE, w - 2.0 * CELL_PADDING).len())
+            .max()
+            .unwrap_or(1);
+        lines as f32 * line_height(CELL_FONT_SIZE) + 2.0 * CELL_PADDING
+    }
+
+    /// Draws one grid row at `y` and returns where the next one starts. Header rows are
+    /// centred vertically, body rows start at the top.
+    fn row(&self, cells: &[TableCell], widths: &[f32], x: f32, y: f32, header: bool) -> f32 {
+        let height = Self::row_height(cells, widths);
+        let mut left = x;
+        self.layer.set_outline_color(color(BLACK));
+        self.layer.set_outline_thickness(GRID_LINE_WIDTH * PT_PER_MM);
+        for (c, &width) in cells.iter().zip(widths) {
+            self.layer.add_rect(
+                Rect::new(Mm(left), Mm(PAGE_HEIGHT - y - height), Mm(left + width), Mm(PAGE_HEIGHT - y))
+                    .with_mode(PaintMode::Stroke),
+            );
+            let lines = wrap(&c.text, c.font, CELL_FONT_SIZE, width - 2.0 * CELL_PADDING);
+            let block = lines.len() as f32 * line_height(CELL_FONT_SIZE);
+            let top = if header { y + (height - block) / 2.0 } else { y + CELL_PADDING };
+            let anchor = match c.align {
+                Align::Left => left + CELL_PADDING,
+                Align::Center => left + width / 2.0,
+                Align::Right => left + width - CELL_PADDING,
+            };
+            for (i, text) in lines.iter().enumerate() {
+                let baseline = top + (i as f32 + 0.8) * line_height(CELL_FONT_SIZE);
+                self.text(text, anchor, baseline, Style(CELL_FONT_SIZE, c.font, BLACK), c.align);
+            }
+            left += width;
+        }
+        y + height
+    }
+}
+
+fn line_height(size: f32) -> f32 {
+    size * 1.15 / PT_PER_MM
+}
+
+fn item_header() -> Vec<TableCell> {
+    ["SL.\nNO", "DESCRIPTION", "HSN/SAC", "Rate\n(In Rs.)", "Qty.\n(in Kwp)", "Amount\n(In Rs.)"]
+        .into_iter()
+        .map(|text| cell(text, Font::Bold, Align::Center))
+        .collect()
+}
+
+fn letterhead(w: &Writer, letterhead: &Letterhead, details: &CompanyDetails) {
+    let top = 8.0;
+    if let Some(logo) = &letterhead.logo {
+        w.image(logo, MARGIN, top, 40.0, 35.0);
+    }
+    // "APEX SOLAR" with larger initials
+    for (text, size, x) in [("A", 36.0, 55.0), ("PEX", 30.0, 64.0), ("S", 36.0, 92.0), ("OLAR", 30.0, 99.0)] {
+        w.text(text, x, top + 12.0, Style(size, Font::TimesBold, BRAND_BLUE), Align::Left);
+    }
+    w.text(&details.tagline, 55.0, top + 21.0, Style(12.0, Font::Italic, (0, 128, 0)), Align::Left);
+    w.text("Solar Power Plant Installation", PAGE_WIDTH - 75.0, top + 10.0, Style(12.0, Font::Bold, DARK_GRAY), Align::Left);
+    w.text("and Commissioning", PAGE_WIDTH - 75.0, top + 18.0, Style(12.0, Font::Bold, DARK_GRAY), Align::Left);
+
+    w.text(&details.address.full_address, MARGIN, top + 40.0, Style(9.0, Font::Regular, DARK_GRAY), Align::Left);
+    w.text("Ph : ", MARGIN, top + 44.0, Style(9.0, Font::Bold, BLACK), Align::Left);
+    w.text(&details.contact.phone, MARGIN + 12.0, top + 44.0, Style(9.0, Font::Regular, DARK_GRAY), Align::Left);
+    w.text("E-mail : ", MARGIN + 50.0, top + 44.0, Style(9.0, Font::Bold, BLACK), Align::Left);
+    w.text(&details.contact.email, MARGIN + 66.0, top + 44.0, Style(9.0, Font::Regular, DARK_GRAY), Align::Left);
+
+    w.line(MARGIN, top + 49.0, PAGE_WIDTH - MARGIN, top + 49.0, 2.0, (139, 69, 19));
+    w.line(MARGIN, top + 50.5, PAGE_WIDTH - MARGIN, top + 50.5, 0.5, BRAND_BLUE);
+}
+
+/// Renders an issued or cancelled invoice to PDF bytes, signed when the letterhead has a signer.
+pub fn render(stored: &StoredInvoice, company: &Letterhead) -> Result<Vec<u8>, String> {
+    let invoice = &stored.invoice;
+    let customer = &invoice.customer;
+    let mut w = Writer::new(&format!("Invoice AS/{}/{}", invoice.financial_year, invoice.invoice_number))?;
+    letterhead(&w, company, &company_details()?);
+
+    let mut y = 66.0;
+    w.text("Tax Invoice", PAGE_WIDTH / 2.0, y, Style(22.0, Font::Bold, BLACK), Align::Center);
+    w.line(PAGE_WIDTH / 2.0 - 25.0, y + 2.0, PAGE_WIDTH / 2.0 + 25.0, y + 2.0, 0.8, BLACK);
+    if invoice.revision > 0 {
+        let marker = format!("REVISED (Rev. {})", invoice.revision);
+        w.text(&marker, PAGE_WIDTH - MARGIN, y, Style(11.0, Font::Bold, (200, 0, 0)), Align::Right);
+    }
+    y += 10.0;
+
+    let right_x = PAGE_WIDTH - MARGIN - 70.0;
+    let name_y = y;
+    w.text(&customer.company_name.to_uppercase(), MARGIN, y, Style(11.0, Font::Bold, DARK_GRAY), Align::Left);
+    w.text("Date:- ", right_x, y, Style(9.0, Font::Bold, BLACK), Align::Left);
+    w.text(&format_date(&invoice.invoice_date, "%d-%m-%Y"), right_x + 12.0, y, Style(9.0, Font::Regular, DARK_GRAY), Align::Left);
+    w.text("Tax Invoice Ref. No: - ", right_x, y + 5.0, Style(9.0, Font::Bold, BLACK), Align::Left);
+    let reference = format!("AS/{}/{}", invoice.financial_year, invoice.invoice_number);
+    w.text(&reference, right_x + 35.0, y + 5.0, Style(9.0, Font::Regular, DARK_GRAY), Align::Left);
+    y += 6.0;
+
+    let place = format!(
+        "{} {} {}",
+        customer.city.as_deref().unwrap_or_default(),
+        customer.state.as_deref().unwrap_or_default(),
+        customer.pincode.as_deref().unwrap_or_default()
+    );
+    let address = [
+        Some(customer.address_line1.as_str()),
+        customer.address_line2.as_deref(),
+        customer.address_line3.as_deref(),
+        Some(place.trim()),
+    ];
+    for line in address.into_iter().flatten().filter(|l| !l.trim().is_empty()) {
+        for part in wrap(line, Font::Regular, 9.0, 100.0) {
+            w.text(&part, MARGIN, y, Style(9.0, Font::Regular, DARK_GRAY), Align::Left);
+            y += 4.0;
+        }
+    }
+    if let Some(gstin) = customer.gst_number.as_deref().filter(|s| !s.is_empty()) {
+        w.text("G.S.T. No: ", MARGIN, y, Style(9.0, Font::Bold, BLACK), Align::Left);
+        w.text(gstin, MARGIN + 18.0, y, Style(9.0, Font::Regular, DARK_GRAY), Align::Left);
+        y += 5.0;
+    }
+    if let Some(pan) = customer.pan_number.as_deref().filter(|s| !s.is_empty()) {
+        w.text("PAN NO: ", MARGIN, y, Style(9.0, Font::Bold, BLACK), Align::Left);
+        w.text(pan, MARGIN + 18.0, y, Style(9.0, Font::Regular, DARK_GRAY), Align::Left);
+    }
+    y = y.max(name_y + 10.0) + 10.0;
+
+    let work_order = match invoice.work_order_date.as_deref().filter(|d| !d.is_empty()) {
+        Some(date) => format!("{} Dated- {}", invoice.work_order_reference, format_date(date, "%d-%m-%Y")),
+        None => invoice.work_order_reference.clone(),
+    };
+    let work_order = format!("Ref. Work Order No: - {}", work_order);
+    w.text(&work_order, PAGE_WIDTH / 2.0, y, Style(10.0, Font::Bold, BLACK), Align::Center);
+    y += 10.0;
+
+    // Line items, with the header repeated on every page the table runs onto
+    let header = item_header();
+    y = w.reserve(y, Writer::row_height(&header, &ITEM_COLUMNS) * 2.0);
+    y = w.row(&header, &ITEM_COLUMNS, TABLE_X, y, true);
+    for item in &invoice.line_items {
+        let cells = vec![
+            cell(item.serial_number.to_string(), Font::Regular, Align::Center),
+            cell(item.description.as_str(), Font::Regular, Align::Left),
+            cell(item.hsn_sac_code.as_str(), Font::Regular, Align::Center),
+            cell(format!("{:.2}", item.rate), Font::Regular, Align::Center),
+            cell(format!("{:.2}\n({})", item.quantity, item.unit), Font::Regular, Align::Center),
+            cell(format!("{:.2}", item.amount), Font::Regular, Align::Center),
+        ];
+        let next = w.reserve(y, Writer::row_height(&cells, &ITEM_COLUMNS));
+        if next < y {
+            y = w.row(&header, &ITEM_COLUMNS, TABLE_X, next, true);
+        }
+        y = w.row(&cells, &ITEM_COLUMNS, TABLE_X, y, false);
+    }
+
+    let summary = [
+        ("Total Basic".to_string(), invoice.total_basic_amount),
+        (format!("C.G.S.T. ({}%)", invoice.cgst_percentage), invoice.cgst_amount),
+        (format!("S.G.S.T. ({}%)", invoice.sgst_percentage), invoice.sgst_amount),
+        ("Grand Total".to_string(), invoice.grand_total),
+    ];
+    for (label, amount) in summary {
+        let mut cells: Vec<TableCell> = (0..4).map(|_| cell("", Font::Regular, Align::Left)).collect();
+        cells.push(cell(label, Font::Bold, Align::Center));
+        cells.push(cell(format!("{:.2}", amount), Font::Bold, Align::Center));
+        y = w.reserve(y, Writer::row_height(&cells, &ITEM_COLUMNS));
+        y = w.row(&cells, &ITEM_COLUMNS, TABLE_X, y, false);
+    }
+
+    y += 3.0;
+    for line in wrap(&format!("In words:- {}", invoice.amount_in_words), Font::Bold, 9.0, PAGE_WIDTH - 2.0 * MARGIN) {
+        y = w.reserve(y, 4.0);
+        w.text(&line, MARGIN, y, Style(9.0, Font::Bold, DARK_GRAY), Align::Left);
+        y += 4.0;
+    }
+    y += 3.0;
+
+    let total_tax = invoice.cgst_amount + invoice.sgst_amount;
+    let tax_header = vec![
+        cell(format!("C.G.S.T. ({}%)", invoice.cgst_percentage), Font::Bold, Align::Center),
+        cell(format!("S.G.S.T. ({}%)", invoice.sgst_percentage), Font::Bold, Align::Center),
+        cell("Total", Font::Bold, Align::Center),
+    ];
+    let tax_values = vec![
+        cell(format!("{:.2}", invoice.cgst_amount), Font::Regular, Align::Center),
+        cell(format!("{:.2}", invoice.sgst_amount), Font::Regular, Align::Center),
+        cell(format!("{:.2}", total_tax), Font::Regular, Align::Center),
+    ];
+    y = w.reserve(y, 2.0 * Writer::row_height(&tax_values, &TAX_COLUMNS));
+    y = w.row(&tax_header, &TAX_COLUMNS, TABLE_X, y, true);
+    y = w.row(&tax_values, &TAX_COLUMNS, TABLE_X, y, false) + 3.0;
+
+    let tax_words = format!("Total Tax amount in words: - {}", amount_in_words((total_tax * 100.0).round() / 100.0));
+    for line in wrap(&tax_words, Font::Bold, 9.0, PAGE_WIDTH - 2.0 * MARGIN) {
+        y = w.reserve(y, 4.0);
+        w.text(&line, MARGIN, y, Style(9.0, Font::Bold, DARK_GRAY), Align::Left);
+        y += 4.0;
+    }
+    y += 4.0;
+
+    // Account details on the left, signature on the right
+    y = w.reserve(y, FOOTER_HEIGHT);
+    let signature_y = y;
+    let ifsc = if company.bank.ifsc_code.is_empty() { company.field("ifscCode") } else { &company.bank.ifsc_code };
+    let account = if company.bank.account_number.is_empty() { company.field("accountNumber") } else { &company.bank.account_number };
+    let width = [ACCOUNT_TABLE_WIDTH];
+    y = w.row(&[cell("Account Details", Font::Bold, Align::Center)], &width, ACCOUNT_TABLE_X, y, true);
+    for text in [
+        format!("Name- {}", company.field("accountName")),
+        format!("Bank Name- {}", company.field("bankName")),
+        format!("IFS CODE- {}", ifsc),
+        format!("A/C NO.- {}", account),
+    ] {
+        y = w.row(&[cell(text, Font::Regular, Align::Left)], &width, ACCOUNT_TABLE_X, y, false);
+    }
+
+    let signature_x = right_x + 22.5;
+    w.text("PROPRIETOR", signature_x, signature_y, Style(8.5, Font::Bold, BLACK), Align::Center);
+    let proprietor = match company.field("proprietorName") {
+        "" => "(AUTHORIZED SIGNATORY)".to_string(),
+        name => format!("({})", name.to_uppercase()),
+    };
+    w.text(&proprietor, signature_x, signature_y + 4.0, Style(7.5, Font::Regular, BLACK), Align::Center);
+    let signature_block = SignatureBlock { x: right_x + 2.0, y: signature_y + 6.0, width: 55.0, height: 22.0 };
+    if company.signer.is_some() {
+        w.text("APEX SOLAR", signature_x, signature_y + 32.0, Style(7.5, Font::Bold, BLACK), Align::Center);
+    } else if let Some(stamp) = &company.stamp {
+        w.image(stamp, signature_block.x, signature_block.y, signature_block.width, signature_block.height);
+        w.text("APEX SOLAR", signature_x, signature_y + 32.0, Style(7.5, Font::Bold, BLACK), Align::Center);
+    }
+
+    y = y.max(signature_y + 35.0) + 5.0;
+    let gst = format!("GST No: {}", company.field("gstNumber"));
+    w.text(&gst, PAGE_WIDTH / 2.0, y + 3.0, Style(10.0, Font::Bold, BLACK), Align::Center);
+
+    if stored.status == "CANCELLED" {
+        w.rotated_text("CANCELLED", 80.0, PAGE_WIDTH / 2.0, PAGE_HEIGHT / 2.0, 35.0, RED);
+        let note = format!(
+            "Cancelled on {}: {}",
+            stored.cancelled_at.as_deref().map(|d| format_date(d, "%d/%m/%Y")).unwrap_or_default(),
+            stored.cancellation_reason.as_deref().unwrap_or_default()
+        );
+        w.text(&note, PAGE_WIDTH / 2.0, PAGE_HEIGHT - 8.0, Style(10.0, Font::Bold, RED), Align::Center);
+    }
+
+    let pdf = w.doc.save_to_bytes().map_err(|e| e.to_string())?;
+    match &company.signer {
+        Some(signer) => pdf_sign::sign(&pdf, signer, signature_block),
+        None => Ok(pdf),
+    }
+}
+
+/// A cell of the account details table that the app's jsPDF layout leaves blank, in mm from
+/// the top left of the first page.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CellBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankCells {
+    pub ifsc_code: CellBox,
+    pub account_number: CellBox,
+}
+
+/// The first page's resources as a dictionary of its own, with fonts that are references or
+/// inherited from the page tree resolved.
+fn own_resources(doc: &Document, page: lopdf::ObjectId) -> Dictionary {
+    let (inline, inherited) = doc.get_page_resources(page);
+    let mut resources = inline
+        .cloned()
+        .or_else(|| inherited.first().and_then(|id| doc.get_dictionary(*id).ok()).cloned())
+        .unwrap_or_default();
+    let fonts = match resources.get(b"Font") {
+        Ok(Object::Reference(id)) => doc.get_dictionary(*id).cloned().unwrap_or_default(),
+        Ok(Object::Dictionary(fonts)) => fonts.clone(),
+        _ => Dictionary::new(),
+    };
+    resources.set("Font", fonts);
+    resources
+}
+
+/// Writes the IFSC and account number into the blank cells of an invoice PDF made by
+/// generateInvoicePDF, in the table's font. The existing page content is left as it is.
+pub fn fill_bank_details(pdf: &[u8], bank: &BankDetails, cells: BankCells) -> Result<Vec<u8>, String> {
+    if pdf_sign::is_signed(pdf) {
+        return Err("The PDF is already signed.".to_string());
+    }
+    let mut doc = Document::load_mem(pdf).map_err(|e| e.to_string())?;
+    let page = *doc.get_pages().values().next().ok_or("The PDF has no pages")?;
+    let page_height = pdf_sign::page_height(&doc, page);
+
+    let font = doc.add_object(dictionary! {
+        "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica", "Encoding" => "WinAnsiEncoding",
+    });
+    let mut resources = own_resources(&doc, page);
+    if let Ok(fonts) = resources.get_mut(b"Font").and_then(Object::as_dict_mut) {
+        fonts.set("FBank", font);
+    }
+
+    // Centred vertically like the other rows: half the cap height of Helvetica below the middle
+    let baseline = |cell: CellBox| page_height - (cell.y + cell.height / 2.0) * PT_PER_MM - CELL_FONT_SIZE * 0.36;
+    let mut operations = Vec::new();
+    for (text, cell) in [
+        (format!("IFS CODE- {}", bank.ifsc_code), cells.ifsc_code),
+        (format!("A/C NO.- {}", bank.account_number), cells.account_number),
+    ] {
+        // Clipped to the cell so a long value cannot run into the next column
+        let (left, bottom) = (cell.x * PT_PER_MM, page_height - (cell.y + cell.height) * PT_PER_MM);
+        let (width, height) = (cell.width * PT_PER_MM, cell.height * PT_PER_MM);
+        operations.extend([
+            Operation::new("q", vec![]),
+            Operation::new("re", vec![left.into(), bottom.into(), width.into(), height.into()]),
+            Operation::new("W", vec![]),
+            Operation::new("n", vec![]),
+            Operation::new("BT", vec![]),
+            Operation::new("rg", vec![0.into(), 0.into(), 0.into()]),
+            Operation::new("Tf", vec![Object::Name(b"FBank".to_vec()), CELL_FONT_SIZE.into()]),
+            Operation::new("Tm", vec![1.into(), 0.into(), 0.into(), 1.into(), (left + CELL_PADDING * PT_PER_MM).into(), baseline(cell).into()]),
+            Operation::new("Tj", vec![Object::string_literal(pdf_sign::latin1(&text))]),
+            Operation::new("ET", vec![]),
+            Operation::new("Q", vec![]),
+        ]);
+    }
+    let text = Content { operations }.encode().map_err(|e| e.to_string())?;
+
+    // The page's own content runs in a saved graphics state so whatever it leaves set cannot move the text
+    let mut contents: Vec<Object> = vec![doc.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec())).into()];
+    contents.extend(doc.get_page_contents(page).into_iter().map(Object::from));
+    let mut closing = b"Q\n".to_vec();
+    closing.extend(text);
+    contents.push(doc.add_object(Stream::new(Dictionary::new(), closing)).into());
+
+    let page_dict = doc.get_dictionary_mut(page).map_err(|e| e.to_string())?;
+    page_dict.set("Resources", resources);
+    page_dict.set("Contents", contents);
+    let mut out = Vec::new();
+    doc.save_to(&mut out).map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+/// Fills the bank details into an invoice PDF in the app's `generated` folder. They go from the
+/// vault straight into the file, so the webview never holds the full account number.
+#[tauri::command]
+pub async fn add_bank_details_to_pdf(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    file_name: String,
+    cells: BankCells,
+) -> Result<(), String> {
+    let path = bundle::generated_file(&app_handle, &file_name)?;
+    let bank = vault::invoice_bank_details(&device::config_dir(&app_handle)?, &vault, &db::open(&app_handle)?)?;
+    let pdf = fs::read(&path).map_err(|e| e.to_string())?;
+    fs::write(&path, fill_bank_details(&pdf, &bank, cells)?).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_invoice;
+
+    fn letterhead(bank: BankDetails) -> Letterhead {
+        let company = serde_json::json!({ "accountName": "Apex Solar", "bankName": "State Bank of India", "gstNumber": "19ABCDE1234F1Z5" });
+        Letterhead { company, bank, logo: None, stamp: None, signer: None }
+    }
+
+    fn stored(invoice_number: &str) -> StoredInvoice {
+        StoredInvoice { invoice: sample_invoice(invoice_number, "c1"), status: "GENERATED".to_string(), cancelled_at: None, cancellation_reason: None }
+    }
+
+    fn page_text(pdf: &[u8]) -> String {
+        Document::load_mem(pdf).unwrap().extract_text(&[1]).unwrap()
+    }
+
+    #[test]
+    fn amounts_are_written_out_in_indian_words() {
+        assert_eq!(amount_in_words(252000.0), "Two Lakh Fifty Two Thousand Rupees Only");
+        assert_eq!(amount_in_words(12345678.05), "One Crore Twenty Three Lakh Forty Five Thousand Six Hundred Seventy Eight Rupees and Five Paise Only");
+        assert_eq!(amount_in_words(1011.5), "One Thousand Eleven Rupees and Fifty Paise Only");
+        assert_eq!(amount_in_words(0.75), "Seventy Five Paise Only");
+        assert_eq!(amount_in_words(0.0), "Zero Rupees Only");
+    }
+
+    #[test]
+    fn file_names_carry_the_revision_and_cancellation() {
+        let mut stored = stored("012");
+        assert_eq!(file_name(&stored), "Invoice_AS_24-25_012.pdf");
+        stored.invoice.revision = 2;
+        assert_eq!(file_name(&stored), "Invoice_AS_24-25_012_Rev2.pdf");
+        stored.status = "CANCELLED".to_string();
+        assert_eq!(file_name(&stored), "Invoice_AS_24-25_012_Rev2_CANCELLED.pdf");
+        stored.invoice.revision = 0;
+        assert_eq!(file_name(&stored), "Invoice_AS_24-25_012_CANCELLED.pdf");
+    }
+
+    #[test]
+    fn invoices_render_with_the_shared_letterhead_and_totals_in_words() {
+        let bank = BankDetails { account_number: "123456782674".to_string(), ifsc_code: "SBIN0001234".to_string() };
+        let text = page_text(&render(&stored("001"), &letterhead(bank.clone())).unwrap());
+        let details = company_details().unwrap();
+        for expected in [details.address.full_address.as_str(), &details.contact.phone, &details.contact.email, &details.tagline] {
+            assert!(text.contains(expected), "{} missing from\n{}", expected, text);
+        }
+        assert!(text.contains("In words:- Two Lakh Fifty Two Thousand Rupees Only"));
+        assert!(text.contains("Total Tax amount in words: - Twenty Seven Thousand Rupees Only"));
+        assert!(text.contains("IFS CODE- SBIN0001234"));
+        assert!(!text.contains("CANCELLED"));
+
+        let mut cancelled = stored("002");
+        cancelled.status = "CANCELLED".to_string();
+        cancelled.cancelled_at = Some("2024-07-10T09:30:00Z".to_string());
+        cancelled.cancellation_reason = Some("Raised twice".to_string());
+        let text = page_text(&render(&cancelled, &letterhead(bank)).unwrap());
+        assert!(text.contains("CANCELLED"));
+        assert!(text.contains("Cancelled on 10/07/2024: Raised twice"));
+    }
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle.as_bytes())
+    }
+
+    #[test]
+    fn bank_details_are_written_into_the_blank_cells() {
+        let pdf = render(&stored("001"), &letterhead(BankDetails::default())).unwrap();
+        let original = Document::load_mem(&pdf).unwrap();
+        let fonts_before = own_resources(&original, original.page_iter().next().unwrap()).get(b"Font").unwrap().as_dict().unwrap().len();
+        assert!(fonts_before > 0);
+        let bank = BankDetails { account_number: "123456782674".to_string(), ifsc_code: "SBIN0001234".to_string() };
+        let cell = |y| CellBox { x: 14.1, y, width: 90.0, height: 7.5 };
+        let filled = fill_bank_details(&pdf, &bank, BankCells { ifsc_code: cell(180.0), account_number: cell(187.5) }).unwrap();
+
+        assert!(contains(&filled, "(IFS CODE- SBIN0001234)"));
+        assert!(contains(&filled, "(A/C NO.- 123456782674)"));
+        let doc = Document::load_mem(&filled).unwrap();
+        let page = doc.page_iter().next().unwrap();
+        let fonts = doc.get_page_fonts(page);
+        assert!(fonts.contains_key(&b"FBank"[..]));
+        assert_eq!(fonts.len(), fonts_before + 1);
+        assert!(!contains(&pdf, "SBIN0001234"));
+    }
+
+    #[test]
+    fn signed_pdfs_are_left_alone() {
+        let bank = BankDetails { account_number: "123456782674".to_string(), ifsc_code: "SBIN0001234".to_string() };
+        let cell = CellBox { x: 14.1, y: 180.0, width: 90.0, height: 7.5 };
+        let signed = b"%PDF-1.7\n1 0 obj << /Type /Sig /ByteRange [0 10 20 30] >> endobj".to_vec();
+        let error = fill_bank_details(&signed, &bank, BankCells { ifsc_code: cell, account_number: cell }).unwrap_err();
+        assert_eq!(error, "The PDF is already signed.");
+    }
+}
@@ -0,0 +1,319 @@
+use crate::db;
+use crate::models::Invoice;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceRevision {
+    pub invoice_number: String,
+    pub revision: i64,
+    pub reason: Option<String>,
+    pub grand_total: Option<f64>,
+    pub created_at: Option<String>,
+    pub invoice: Invoice,
+}
+
+/// Returns `(locked, revision)` for an invoice, or `None` if the number is unused.
+fn lock_state(conn: &Connection, invoice_number: &str) -> Result<Option<(bool, i64)>, String> {
+    conn.query_row(
+        "SELECT locked, revision FROM invoices WHERE invoice_number = ?1",
+        params![invoice_number],
+        |row| Ok((row.get::<_, i64>(0)? == 1, row.get::<_, i64>(1)?)),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn status(conn: &Connection, invoice_number: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT status FROM invoices WHERE invoice_number = ?1",
+        params![invoice_number],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .optional()
+    .map(Option::flatten)
+    .map_err(|e| e.to_string())
+}
+
+fn snapshot(invoice: &Invoice) -> Result<String, String> {
+    let mut invoice = invoice.clone();
+    invoice.id = Some(invoice.invoice_number.clone());
+    serde_json::to_string(&invoice).map_err(|e| e.to_string())
+}
+
+fn replace_items(tx: &Transaction, invoice: &Invoice) -> Result<(), String> {
+    tx.execute("DELETE FROM invoice_items WHERE invoice_number = ?1", params![invoice.invoice_number])
+        .map_err(|e| e.to_string())?;
+    for item in &invoice.line_items {
+        tx.execute(
+            "INSERT INTO invoice_items(id, invoice_number, serial_number, description, hsn_sac_code, rate, quantity, unit, amount)
+             VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![item.id, invoice.invoice_number, item.serial_number, item.description, item.hsn_sac_code, item.rate, item.quantity, item.unit, item.amount],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn record_revision(tx: &Transaction, invoice: &Invoice, reason: Option<&str>) -> Result<(), String> {
+    tx.execute(
+        "INSERT INTO invoice_revisions(invoice_number, revision, reason, json_data, grand_total) VALUES(?1, ?2, ?3, ?4, ?5)",
+        params![invoice.invoice_number, invoice.revision, reason, snapshot(invoice)?, invoice.grand_total],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Writes a new invoice and locks it. Fails if the number has already been issued.
+pub fn issue(conn: &mut Connection, mut invoice: Invoice) -> Result<Invoice, String> {
+    if let Some((true, _)) = lock_state(conn, &invoice.invoice_number)? {
+        return Err(format!(
+            "Invoice {} has already been issued. File an amendment to change it.",
+            invoice.invoice_number
+        ));
+    }
+    invoice.revision = 0;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO invoices(invoice_number, financial_year, customer_id, invoice_date, grand_total, status, work_order_reference, work_order_date, json_data, revision, locked)
+         VALUES(?1, ?2, ?3, ?4, ?5, 'GENERATED', ?6, ?7, ?8, 0, 0)
+         ON CONFLICT(invoice_number) DO UPDATE SET
+         financial_year = excluded.financial_year, customer_id = excluded.customer_id, invoice_date = excluded.invoice_date,
+         grand_total = excluded.grand_total, status = excluded.status, work_order_reference = excluded.work_order_reference,
+         work_order_date = excluded.work_order_date, json_data = excluded.json_data",
+        params![
+            invoice.invoice_number,
+            invoice.financial_year,
+            invoice.customer.id,
+            invoice.invoice_date,
+            invoice.grand_total,
+            invoice.work_order_reference,
+            invoice.work_order_date.clone().unwrap_or_default(),
+            snapshot(&invoice)?,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    replace_items(&tx, &invoice)?;
+    record_revision(&tx, &invoice, None)?;
+    tx.execute("UPDATE invoices SET locked = 1 WHERE invoice_number = ?1", params![invoice.invoice_number])
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(invoice)
+}
+
+/// Replaces an issued invoice with a new revision, keeping every earlier one in `invoice_revisions`.
+pub fn amend(conn: &mut Connection, invoice: Invoice, reason: &str) -> Result<Invoice, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let invoice = amend_in(&tx, invoice, reason)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(invoice)
+}
+
+/// [`amend`] inside a transaction the caller owns, for changes that span several invoices.
+pub fn amend_in(tx: &Transaction, mut invoice: Invoice, reason: &str) -> Result<Invoice, String> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err("A reason is required to amend an issued invoice".to_string());
+    }
+    if status(tx, &invoice.invoice_number)?.as_deref() == Some("CANCELLED") {
+        return Err(format!("Invoice {} has been cancelled and cannot be amended", invoice.invoice_number));
+    }
+    let current_revision = match lock_state(tx, &invoice.invoice_number)? {
+        Some((true, revision)) => revision,
+        Some((false, _)) => return Err(format!("Invoice {} has not been issued yet", invoice.invoice_number)),
+        None => return Err(format!("Invoice {} not found", invoice.invoice_number)),
+    };
+    invoice.revision = current_revision + 1;
+
+    // The revision row has to exist before the header may move to it
+    record_revision(tx, &invoice, Some(reason))?;
+    tx.execute(
+        "UPDATE invoices SET locked = 0, revision = ?2, financial_year = ?3, customer_id = ?4, invoice_date = ?5,
+         grand_total = ?6, work_order_reference = ?7, work_order_date = ?8, json_data = ?9
+         WHERE invoice_number = ?1",
+        params![
+            invoice.invoice_number,
+            invoice.revision,
+            invoice.financial_year,
+            invoice.customer.id,
+            invoice.invoice_date,
+            invoice.grand_total,
+            invoice.work_order_reference,
+            invoice.work_order_date.clone().unwrap_or_default(),
+            snapshot(&invoice)?,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    replace_items(tx, &invoice)?;
+    tx.execute("UPDATE invoices SET locked = 1 WHERE invoice_number = ?1", params![invoice.invoice_number])
+        .map_err(|e| e.to_string())?;
+    Ok(invoice)
+}
+
+/// Cancels an issued invoice. The row and its number are kept; only the status changes.
+/// `cancelled_on` defaults to today's local date.
+pub fn cancel(conn: &Connection, invoice_number: &str, reason: &str, cancelled_on: Option<&str>) -> Result<(), String> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err("A reason is required to cancel an invoice".to_string());
+    }
+    match status(conn, invoice_number)?.as_deref() {
+        Some("CANCELLED") => return Err(format!("Invoice {} is already cancelled", invoice_number)),
+        Some("GENERATED") => {}
+        Some(_) => return Err(format!("Invoice {} has not been issued yet", invoice_number)),
+        None => return Err(format!("Invoice {} not found", invoice_number)),
+    }
+    conn.execute(
+        "UPDATE invoices SET status = 'CANCELLED', cancellation_reason = ?2,
+         cancelled_at = COALESCE(?3, date('now', 'localtime'))
+         WHERE invoice_number = ?1",
+        params![invoice_number, reason, cancelled_on.filter(|d| !d.trim().is_empty())],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Lists every recorded revision of an invoice, oldest (the original issue) first.
+pub fn revisions(conn: &Connection, invoice_number: &str) -> Result<Vec<InvoiceRevision>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT invoice_number, revision, reason, grand_total, created_at, json_data
+             FROM invoice_revisions WHERE invoice_number = ?1 ORDER BY revision",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![invoice_number], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<f64>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut revisions = Vec::new();
+    for row in rows {
+        let (invoice_number, revision, reason, grand_total, created_at, json_data) = row.map_err(|e| e.to_string())?;
+        let mut invoice: Invoice = serde_json::from_str(&json_data).map_err(|e| e.to_string())?;
+        invoice.revision = revision;
+        revisions.push(InvoiceRevision { invoice_number, revision, reason, grand_total, created_at, invoice });
+    }
+    Ok(revisions)
+}
+
+#[tauri::command]
+pub async fn issue_invoice(app_handle: tauri::AppHandle, invoice: Invoice) -> Result<Invoice, String> {
+    let mut conn = db::open(&app_handle)?;
+    issue(&mut conn, invoice)
+}
+
+#[tauri::command]
+pub async fn amend_invoice(app_handle: tauri::AppHandle, invoice: Invoice, reason: String) -> Result<Invoice, String> {
+    let mut conn = db::open(&app_handle)?;
+    amend(&mut conn, invoice, &reason)
+}
+
+#[tauri::command]
+pub async fn cancel_invoice(app_handle: tauri::AppHandle, invoice_number: String, reason: String, cancelled_on: Option<String>) -> Result<(), String> {
+    let conn = db::open(&app_handle)?;
+    cancel(&conn, &invoice_number, &reason, cancelled_on.as_deref())
+}
+
+#[tauri::command]
+pub async fn get_invoice_revisions(app_handle: tauri::AppHandle, invoice_number: String) -> Result<Vec<InvoiceRevision>, String> {
+    let conn = db::open(&app_handle)?;
+    revisions(&conn, &invoice_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_customer, migrated_db, sample_invoice};
+
+    fn issued() -> Connection {
+        let mut conn = migrated_db();
+        insert_customer(&conn, "c1", "Acme Pvt Ltd");
+        issue(&mut conn, sample_invoice("001", "c1")).unwrap();
+        conn
+    }
+
+    fn assert_locked(result: rusqlite::Result<usize>) {
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("locked") || error.contains("cannot be deleted"), "{}", error);
+    }
+
+    #[test]
+    fn issued_invoice_refuses_update_and_delete() {
+        let conn = issued();
+        assert_locked(conn.execute("UPDATE invoices SET grand_total = 1 WHERE invoice_number = '001'", []));
+        assert_locked(conn.execute("UPDATE invoices SET locked = 0 WHERE invoice_number = '001'", []));
+        assert_locked(conn.execute("DELETE FROM invoices WHERE invoice_number = '001'", []));
+        let total: f64 = conn.query_row("SELECT grand_total FROM invoices WHERE invoice_number = '001'", [], |r| r.get(0)).unwrap();
+        assert_eq!(total, 252000.0);
+    }
+
+    #[test]
+    fn issued_invoice_items_refuse_changes() {
+        let conn = issued();
+        assert_locked(conn.execute("UPDATE invoice_items SET amount = 1 WHERE invoice_number = '001'", []));
+        assert_locked(conn.execute("DELETE FROM invoice_items WHERE invoice_number = '001'", []));
+        assert_locked(conn.execute(
+            "INSERT INTO invoice_items(id, invoice_number, serial_number, description, rate, quantity, amount)
+             VALUES ('extra', '001', 2, 'Extra', 1, 1, 1)",
+            [],
+        ));
+    }
+
+    #[test]
+    fn revision_cannot_be_bumped_without_a_record() {
+        let conn = issued();
+        let error = conn.execute("UPDATE invoices SET revision = 1, grand_total = 1 WHERE invoice_number = '001'", []).unwrap_err();
+        assert!(error.to_string().contains("must be recorded"), "{}", error);
+        let error = conn.execute("DELETE FROM invoice_revisions", []).unwrap_err();
+        assert!(error.to_string().contains("cannot be deleted"), "{}", error);
+    }
+
+    #[test]
+    fn issuing_twice_is_refused() {
+        let mut conn = issued();
+        let error = issue(&mut conn, sample_invoice("001", "c1")).unwrap_err();
+        assert!(error.contains("already been issued"), "{}", error);
+    }
+
+    #[test]
+    fn amend_records_a_revision_and_relocks() {
+        let mut conn = issued();
+        let mut changed = sample_invoice("001", "c1");
+        changed.line_items[0].quantity = 6.0;
+        changed.grand_total = 302400.0;
+        assert!(amend(&mut conn, changed.clone(), "  ").unwrap_err().contains("reason is required"));
+
+        let amended = amend(&mut conn, changed, "Quantity corrected").unwrap();
+        assert_eq!(amended.revision, 1);
+        let history = revisions(&conn, "001").unwrap();
+        assert_eq!(history.iter().map(|r| r.revision).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(history[0].reason, None);
+        assert_eq!(history[0].grand_total, Some(252000.0));
+        assert_eq!(history[1].reason.as_deref(), Some("Quantity corrected"));
+        assert_eq!(history[1].invoice.line_items[0].quantity, 6.0);
+
+        let (locked, revision) = lock_state(&conn, "001").unwrap().unwrap();
+        assert!(locked);
+        assert_eq!(revision, 1);
+        assert_locked(conn.execute("UPDATE invoices SET grand_total = 1 WHERE invoice_number = '001'", []));
+    }
+
+    #[test]
+    fn cancelled_invoice_cannot_be_amended() {
+        let mut conn = issued();
+        cancel(&conn, "001", "Duplicate", Some("2024-07-04")).unwrap();
+        assert_eq!(status(&conn, "001").unwrap().as_deref(), Some("CANCELLED"));
+        let error = amend(&mut conn, sample_invoice("001", "c1"), "Late change").unwrap_err();
+        assert!(error.contains("cancelled"), "{}", error);
+    }
+}
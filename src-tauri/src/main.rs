@@ -4,8 +4,44 @@
     windows_subsystem = "windows"
 )]
 
+mod backup;
+mod bundle;
+mod changeset;
+mod conflict;
+mod crypto;
+mod customer_import;
+mod db;
+mod device;
+mod diagnostics;
+mod drive_link;
+mod email;
+mod export;
+mod file_open;
+mod folder_watch;
+mod gdrive;
+mod gst;
+mod invoice_pdf;
+mod invoices;
+mod migrations;
+mod models;
+mod pdf_batch;
+mod pdf_sign;
+mod printing;
+mod restore;
+mod selective_restore;
+mod spreadsheet;
+mod sync;
+mod tally;
+mod tally_import;
+#[cfg(test)]
+mod test_support;
+mod trash;
+mod updater;
+mod vault;
+
 use std::fs;
 use std::path::{Path, PathBuf};
+use tauri_plugin_log::{LogTarget, RotationStrategy};
 
 /// Validates that a path is within the allowed AppData scope to prevent path traversal.
 fn ensure_path_in_scope(app_handle: &tauri::AppHandle, path: &str) -> Result<PathBuf, String> {
@@ -34,36 +70,50 @@ fn ensure_path_in_scope(app_handle: &tauri::AppHandle, path: &str) -> Result<Pat
 
 #[tauri::command]
 async fn export_database(app_handle: tauri::AppHandle, target_path: String) -> Result<(), String> {
-    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Failed to resolve app config dir")?;
-    let data_dir = app_handle.path_resolver().app_data_dir().ok_or("Failed to resolve app data dir")?;
-    
-    let db_path_config = config_dir.join("invoices.db");
-    let db_path_data = data_dir.join("invoices.db");
-
-    let db_path = if db_path_config.exists() {
-        db_path_config
-    } else if db_path_data.exists() {
-        db_path_data
-    } else {
+    let db_path = db::db_path(&app_handle)?;
+    if !db_path.exists() {
         return Err("Database file not found".to_string());
-    };
+    }
 
     // Note: We don't use ensure_path_in_scope here because target_path comes from a system dialog (Safe)
-    fs::copy(&db_path, &target_path).map_err(|e| e.to_string())?;
-    Ok(())
+    // A plain fs::copy can catch the file mid-write while the sql plugin holds it open
+    backup::snapshot_database(&db_path, Path::new(&target_path))
 }
 
 #[tauri::command]
-async fn import_database(app_handle: tauri::AppHandle, source_path: String) -> Result<(), String> {
-    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Failed to resolve app config dir")?;
-    let db_path = config_dir.join("invoices.db");
-    
-    if !std::path::Path::new(&source_path).exists() {
+async fn import_database(
+    app_handle: tauri::AppHandle,
+    source_path: String,
+    passphrase: Option<String>,
+) -> Result<restore::RestoreReport, String> {
+    // Same resolution as export_database, so a restore replaces the file the app actually uses
+    let db_path = db::db_path(&app_handle)?;
+
+    let source = Path::new(&source_path);
+    if !source.exists() {
         return Err("Source file not found".to_string());
     }
 
-    fs::copy(&source_path, &db_path).map_err(|e| e.to_string())?;
-    Ok(())
+    let generated_dir = bundle::generated_dir(&app_handle)?;
+    let report = restore::restore(source, passphrase.as_deref(), &db_path, &generated_dir, &device::config_dir(&app_handle)?)
+        .map_err(|e| {
+            log::error!("Restoring {} failed: {}", source_path, e);
+            e
+        })?;
+    log::info!("Restored {}", source_path);
+    // The restored data is the new common base for conflict checks. The restore itself already
+    // succeeded; without a base the next backup just asks before overwriting.
+    if let Ok(hash) = db::open_path(&db_path).and_then(|conn| conflict::data_hash(&conn)) {
+        let _ = conflict::record_sync(&device::config_dir(&app_handle)?, &hash, Some(source));
+    }
+    Ok(report)
+}
+
+/// Relaunches the app, used after restoring a backup older than the current schema
+/// since the sql plugin only runs migrations at startup.
+#[tauri::command]
+fn restart_app(app_handle: tauri::AppHandle) {
+    app_handle.restart();
 }
 
 #[tauri::command]
@@ -81,186 +131,123 @@ async fn save_file_content(app_handle: tauri::AppHandle, path: String, content:
     Ok(())
 }
 
-#[tauri::command]
-async fn download_gdrive_file(app_handle: tauri::AppHandle, url: String, output_path: String) -> Result<(), String> {
-    let safe_output_path = std::path::PathBuf::from(&output_path);
-    
-    let client = reqwest::Client::builder()
-        .cookie_store(true)
-        .user_agent("Mozilla/5.0")
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let extract_id = |s: &str| -> Option<String> {
-        if let Some(caps) = regex::Regex::new(r"/folders/([a-zA-Z0-9_-]+)").unwrap().captures(s) {
-            return Some(caps.get(1).unwrap().as_str().to_string());
-        }
-        if let Some(caps) = regex::Regex::new(r"/d/([a-zA-Z0-9_-]+)").unwrap().captures(s) {
-            return Some(caps.get(1).unwrap().as_str().to_string());
-        }
-        if let Some(caps) = regex::Regex::new(r"id=([a-zA-Z0-9_-]+)").unwrap().captures(s) {
-            return Some(caps.get(1).unwrap().as_str().to_string());
-        }
-        // Direct ID validation
-        if regex::Regex::new(r"^[a-zA-Z0-9_-]{10,50}$").unwrap().is_match(s) {
-            return Some(s.to_string());
-        }
-        None
-    };
-
-    let initial_id = extract_id(&url).ok_or("Invalid Google Drive URL or ID")?;
-    let is_folder = url.contains("/folders/");
-
-    let mut final_file_id = None;
-
-    if is_folder {
-        let folder_url = format!("https://drive.google.com/embeddedfolderview?id={}#list", initial_id);
-        if let Ok(resp) = client.get(&folder_url).send().await {
-             if let Ok(text) = resp.text().await {
-                 // Try JSON pattern first
-                 let re_json = regex::Regex::new(r#"\["([a-zA-Z0-9_-]+)","invoices\.db""#).unwrap();
-                 if let Some(caps) = re_json.captures(&text) {
-                     final_file_id = Some(caps.get(1).unwrap().as_str().to_string());
-                 } else {
-                     // Try HTML pattern (Robust)
-                      let re_html = regex::Regex::new(r#"(?s)/d/([a-zA-Z0-9_-]+)/view[^>]*>.*?invoices\.db"#).unwrap();
-                     if let Some(caps) = re_html.captures(&text) {
-                         final_file_id = Some(caps.get(1).unwrap().as_str().to_string());
-                     }
-                 }
-             }
-        }
-    } else {
-        final_file_id = Some(initial_id.clone());
-    }
-
-    let file_id_to_try = if let Some(fid) = final_file_id.clone() { fid } else { initial_id.clone() };
-    let try_direct_file = !is_folder || final_file_id.is_some();
-
-    if try_direct_file {
-        let download_url = format!("https://drive.google.com/uc?id={}&export=download", file_id_to_try);
-        if let Ok(response) = client.get(&download_url).send().await {
-             let headers = response.headers().clone();
-             let is_html = headers.get("content-type").map(|v| v.to_str().unwrap_or("")).unwrap_or("").contains("text/html");
-             if is_html {
-                 let text = response.text().await.unwrap_or_default();
-                 if text.contains("confirm=") {
-                     let re = regex::Regex::new(r"confirm=([a-zA-Z0-9_-]+)").unwrap();
-                     if let Some(caps) = re.captures(&text) {
-                         let code = caps.get(1).unwrap().as_str();
-                         let confirm_url = format!("{}&confirm={}", download_url, code);
-                         if let Ok(resp2) = client.get(&confirm_url).send().await {
-                              let bytes = resp2.bytes().await.map_err(|e| e.to_string())?;
-                              fs::write(&safe_output_path, bytes).map_err(|e| e.to_string())?;
-                              return Ok(());
-                         }
-                     }
-                 }
-             } else {
-                  let bytes = response.bytes().await.map_err(|e| e.to_string())?;
-                  fs::write(&safe_output_path, bytes).map_err(|e| e.to_string())?;
-                  return Ok(());
-             }
-        }
-    }
-
-    // Fallback: Zip Download
-    if is_folder {
-        let download_url = format!("https://drive.google.com/uc?id={}&export=download", initial_id);
-        if let Ok(mut response) = client.get(&download_url).send().await {
-             let is_html = response.headers().get("content-type").map(|v| v.to_str().unwrap_or("")).unwrap_or("").contains("text/html");
-             if is_html {
-                 let text = response.text().await.unwrap_or_default();
-                 if text.contains("confirm=") {
-                     let re = regex::Regex::new(r"confirm=([a-zA-Z0-9_-]+)").unwrap();
-                     if let Some(caps) = re.captures(&text) {
-                         let code = caps.get(1).unwrap().as_str();
-                         let confirm_url = format!("{}&confirm={}", download_url, code);
-                         if let Ok(resp2) = client.get(&confirm_url).send().await {
-                              response = resp2;
-                         } else {
-                              return Err("Zip Confirm Request failed".to_string());
-                         }
-                     } else {
-                        return Err("Folder Zip failed: Virus scan warning found but no confirm code.".to_string());
-                     }
-                 } else {
-                      return Err("Folder Zip failed: Link returned HTML (Likely Access Denied or Empty Folder).".to_string());
-                 }
-             }
-             
-             let zip_path = safe_output_path.with_extension("zip");
-             {
-                 let bytes = response.bytes().await.map_err(|e| e.to_string())?;
-                 fs::write(&zip_path, bytes).map_err(|e| e.to_string())?;
-             }
-
-             let file = std::fs::File::open(&zip_path).map_err(|e| e.to_string())?;
-             let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Invalid Zip: {}", e))?;
-             
-             let mut found = false;
-             for i in 0..archive.len() {
-                 let mut file = archive.by_index(i).unwrap();
-                 if file.name().ends_with("invoices.db") {
-                     let mut outfile = std::fs::File::create(&safe_output_path).map_err(|e| e.to_string())?;
-                     std::io::copy(&mut file, &mut outfile).map_err(|e| e.to_string())?;
-                     found = true;
-                     break;
-                 }
-             }
-             let _ = std::fs::remove_file(zip_path); 
-             
-             if found {
-                 return Ok(());
-             }
-             return Err("Fallback: Zip downloaded but 'invoices.db' not found.".to_string());
-        }
-    }
-
-    // FINAL FALLBACK: gdown (Python utility)
-    println!("Rust download failed, attempting gdown fallback for ID: {}", initial_id);
-    let mut args = vec![initial_id.as_str(), "-O"];
-    let output_str = safe_output_path.to_string_lossy();
-    args.push(&output_str);
-    
-    if is_folder {
-        args.push("--folder");
-    }
-
-    // Try python3 -m gdown
-    if let Ok(status) = std::process::Command::new("python3")
-        .args(&["-m", "gdown"])
-        .args(&args)
-        .status() {
-        if status.success() {
-            return Ok(());
-        }
-    }
-
-    // Try direct gdown command
-    if let Ok(status) = std::process::Command::new("gdown")
-        .args(&args)
-        .status() {
-        if status.success() {
-            return Ok(());
-        }
-    }
-
-    Err("All download methods failed. Please check your internet connection or the GDrive link.".to_string())
-}
-
 fn main() {
     tauri::Builder::default()
-        .plugin(tauri_plugin_sql::Builder::default().build())
-        .invoke_handler(tauri::generate_handler![export_database, import_database, save_file_content, download_gdrive_file])
+        // Registered first so a second launch hands over and exits before anything opens the database
+        .plugin(tauri_plugin_single_instance::init(file_open::on_second_instance))
+        .plugin(
+            tauri_plugin_log::Builder::default()
+                .targets([LogTarget::LogDir, LogTarget::Stdout])
+                .log_name(diagnostics::LOG_NAME)
+                .rotation_strategy(RotationStrategy::KeepAll)
+                .max_file_size(diagnostics::MAX_LOG_FILE_SIZE)
+                .level(log::LevelFilter::Info)
+                .build(),
+        )
+        .plugin(
+            tauri_plugin_sql::Builder::default()
+                .add_migrations(migrations::DB_URL, migrations::migrations())
+                .build(),
+        )
+        .manage(drive_link::Downloads::default())
+        .manage(file_open::PendingOpens::default())
+        .manage(vault::Vault::default())
+        .manage(pdf_batch::Jobs::default())
+        .manage(folder_watch::FolderWatch::default())
+        .invoke_handler(tauri::generate_handler![
+            export_database,
+            import_database,
+            restart_app,
+            save_file_content,
+            drive_link::download_gdrive_file,
+            drive_link::cancel_gdrive_download,
+            invoices::issue_invoice,
+            invoices::amend_invoice,
+            invoices::cancel_invoice,
+            invoices::get_invoice_revisions,
+            trash::trash_customer_record,
+            trash::trash_draft_invoice,
+            trash::list_trash,
+            trash::restore_from_trash,
+            trash::purge_trash,
+            backup::create_backup_snapshot,
+            bundle::create_backup_bundle,
+            bundle::verify_backup_bundle,
+            crypto::set_backup_passphrase,
+            crypto::is_backup_encryption_enabled,
+            crypto::is_backup_encrypted,
+            restore::preview_restore,
+            selective_restore::list_backup_records,
+            selective_restore::restore_backup_records,
+            gdrive::gdrive_status,
+            gdrive::gdrive_connect,
+            gdrive::gdrive_disconnect,
+            gdrive::gdrive_set_folder,
+            gdrive::gdrive_list_backups,
+            gdrive::gdrive_upload_backup,
+            gdrive::gdrive_check_sync_status,
+            gdrive::gdrive_download_backup,
+            sync::get_sync_provider,
+            sync::set_sync_provider,
+            sync::test_sync_provider,
+            sync::sync_push_backup,
+            sync::sync_check_status,
+            sync::sync_list_backups,
+            sync::sync_pull_backup,
+            sync::sync_delete_backup,
+            device::get_device_info,
+            device::set_device_name,
+            conflict::check_sync_status,
+            folder_watch::watch_backup_folder,
+            changeset::export_changeset,
+            changeset::preview_changeset,
+            changeset::import_changeset,
+            updater::check_for_update,
+            updater::download_update,
+            updater::install_update,
+            file_open::take_file_open_requests,
+            diagnostics::create_diagnostics_bundle,
+            vault::vault_status,
+            vault::vault_unlock,
+            vault::vault_lock,
+            vault::get_masked_bank_details,
+            vault::save_bank_details,
+            export::get_export_columns,
+            export::export_spreadsheet,
+            customer_import::read_customer_import_file,
+            customer_import::import_customers,
+            tally::export_tally_xml,
+            tally_import::import_tally_masters,
+            email::save_smtp_password,
+            email::send_test_email,
+            email::preview_invoice_email,
+            email::email_invoice,
+            email::get_invoice_emails,
+            pdf_batch::export_invoice_pdfs,
+            pdf_batch::cancel_invoice_pdfs,
+            invoice_pdf::add_bank_details_to_pdf,
+            pdf_sign::save_signing_password,
+            pdf_sign::get_signing_certificate,
+            pdf_sign::sign_invoice_pdf,
+            pdf_sign::verify_pdf_signature,
+            printing::get_printers,
+            printing::print_invoice_pdf
+        ])
         .setup(|app| {
             let app_handle = app.handle();
+            log::info!("Starting version {}", app_handle.package_info().version);
+            if let Some(log_dir) = app_handle.path_resolver().app_log_dir() {
+                if let Err(e) = diagnostics::prune_logs(&log_dir) {
+                    log::warn!("Failed to prune old logs: {}", e);
+                }
+            }
             // Create generated invoices directory
             let app_data_dir = app_handle.path_resolver().app_data_dir().unwrap();
             let generated_dir = app_data_dir.join("generated");
             if !generated_dir.exists() {
                 std::fs::create_dir_all(generated_dir).unwrap();
             }
+            file_open::queue_launch_args(&app_handle);
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -0,0 +1,433 @@
+use tauri_plugin_sql::{Migration, MigrationKind};
+
+/// Connection string the frontend passes to `Database.load`.
+pub const DB_URL: &str = "sqlite:invoices.db";
+
+/// Schema version a database has after all migrations below are applied.
+pub fn latest_version() -> i64 {
+    migrations().iter().map(|m| m.version).max().unwrap_or(0)
+}
+
+/// Schema migrations, applied by the sql plugin when the frontend loads the database.
+/// The applied versions are tracked in `_sqlx_migrations`.
+pub fn migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            version: 1,
+            description: "baseline_schema",
+            // Same tables `db.ts` has always created, so existing databases pass through unchanged
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS settings(
+                    key TEXT PRIMARY KEY,
+                    value TEXT
+                );
+                CREATE TABLE IF NOT EXISTS customers(
+                    id TEXT PRIMARY KEY,
+                    company_name TEXT,
+                    gst_number TEXT,
+                    pan_number TEXT,
+                    address_line1 TEXT,
+                    address_line2 TEXT,
+                    address_line3 TEXT,
+                    city TEXT,
+                    state TEXT,
+                    pincode TEXT,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );
+                CREATE TABLE IF NOT EXISTS invoices(
+                    invoice_number TEXT PRIMARY KEY,
+                    financial_year TEXT,
+                    customer_id TEXT,
+                    invoice_date TEXT,
+                    grand_total REAL,
+                    status TEXT,
+                    json_data TEXT,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    work_order_reference TEXT,
+                    work_order_date TEXT,
+                    FOREIGN KEY(customer_id) REFERENCES customers(id)
+                );
+                CREATE TABLE IF NOT EXISTS invoice_items(
+                    id TEXT PRIMARY KEY,
+                    invoice_number TEXT,
+                    serial_number INTEGER,
+                    description TEXT,
+                    hsn_sac_code TEXT,
+                    rate REAL,
+                    quantity REAL,
+                    unit TEXT,
+                    amount REAL,
+                    FOREIGN KEY(invoice_number) REFERENCES invoices(invoice_number) ON DELETE CASCADE
+                );
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 2,
+            description: "issued_invoice_lock",
+            // Issued invoices are locked. The only way to change one is to record a new row in
+            // invoice_revisions first and bump invoices.revision to match (see invoices.rs).
+            sql: r#"
+                ALTER TABLE invoices ADD COLUMN revision INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE invoices ADD COLUMN locked INTEGER NOT NULL DEFAULT 0;
+
+                CREATE TABLE IF NOT EXISTS invoice_revisions(
+                    invoice_number TEXT NOT NULL,
+                    revision INTEGER NOT NULL,
+                    reason TEXT, -- NULL for the original issue
+                    json_data TEXT NOT NULL,
+                    grand_total REAL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    PRIMARY KEY(invoice_number, revision)
+                );
+
+                -- Everything generated so far becomes revision 0 of a locked invoice
+                INSERT OR IGNORE INTO invoice_revisions(invoice_number, revision, reason, json_data, grand_total, created_at)
+                    SELECT invoice_number, 0, NULL, json_data, grand_total, created_at
+                    FROM invoices WHERE status = 'GENERATED' AND json_data IS NOT NULL;
+                -- Line items still only in json_data are copied out first: db.ts moves them into
+                -- invoice_items after the migrations have run, and the lock would refuse them then
+                INSERT OR IGNORE INTO invoice_items(id, invoice_number, serial_number, description, hsn_sac_code, rate, quantity, unit, amount)
+                    SELECT json_extract(item.value, '$.id'), invoices.invoice_number, json_extract(item.value, '$.serialNumber'),
+                        json_extract(item.value, '$.description'), json_extract(item.value, '$.hsnSacCode'), json_extract(item.value, '$.rate'),
+                        json_extract(item.value, '$.quantity'), json_extract(item.value, '$.unit'), json_extract(item.value, '$.amount')
+                    FROM invoices, json_each(invoices.json_data, '$.lineItems') AS item
+                    WHERE json_valid(invoices.json_data) AND json_type(invoices.json_data, '$.lineItems') = 'array'
+                        AND json_extract(item.value, '$.id') IS NOT NULL;
+                UPDATE invoices SET locked = 1 WHERE status = 'GENERATED';
+
+                CREATE TRIGGER IF NOT EXISTS invoices_locked_update
+                BEFORE UPDATE ON invoices
+                WHEN OLD.locked = 1 AND NEW.revision = OLD.revision
+                BEGIN
+                    SELECT RAISE(ABORT, 'Invoice has been issued and is locked. File an amendment instead.');
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoices_revision_recorded
+                BEFORE UPDATE OF revision ON invoices
+                WHEN NEW.revision <> OLD.revision AND NOT EXISTS(
+                    SELECT 1 FROM invoice_revisions WHERE invoice_number = NEW.invoice_number AND revision = NEW.revision
+                )
+                BEGIN
+                    SELECT RAISE(ABORT, 'Invoice revision must be recorded before it is applied.');
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_items_locked_insert
+                BEFORE INSERT ON invoice_items
+                WHEN (SELECT locked FROM invoices WHERE invoice_number = NEW.invoice_number) = 1
+                BEGIN
+                    SELECT RAISE(ABORT, 'Invoice has been issued and is locked. File an amendment instead.');
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_items_locked_update
+                BEFORE UPDATE ON invoice_items
+                WHEN (SELECT locked FROM invoices WHERE invoice_number = OLD.invoice_number) = 1
+                BEGIN
+                    SELECT RAISE(ABORT, 'Invoice has been issued and is locked. File an amendment instead.');
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_items_locked_delete
+                BEFORE DELETE ON invoice_items
+                WHEN (SELECT locked FROM invoices WHERE invoice_number = OLD.invoice_number) = 1
+                BEGIN
+                    SELECT RAISE(ABORT, 'Invoice has been issued and is locked. File an amendment instead.');
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_revisions_append_only_update
+                BEFORE UPDATE ON invoice_revisions
+                BEGIN
+                    SELECT RAISE(ABORT, 'Invoice revisions cannot be modified.');
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_revisions_append_only_delete
+                BEFORE DELETE ON invoice_revisions
+                BEGIN
+                    SELECT RAISE(ABORT, 'Invoice revisions cannot be deleted.');
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 3,
+            description: "cancellation_and_trash",
+            // Issued invoices are never deleted, only cancelled, so their numbers stay consumed.
+            // Customers and drafts go to a recycle bin first and are purged by trash.rs.
+            sql: r#"
+                ALTER TABLE invoices ADD COLUMN cancelled_at TEXT;
+                ALTER TABLE invoices ADD COLUMN cancellation_reason TEXT;
+                ALTER TABLE customers ADD COLUMN deleted_at DATETIME;
+
+                CREATE TABLE IF NOT EXISTS deleted_drafts(
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    json_data TEXT NOT NULL,
+                    deleted_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );
+
+                -- Same lock as before, except that a GENERATED invoice may move to CANCELLED
+                -- as long as nothing else about it changes
+                DROP TRIGGER IF EXISTS invoices_locked_update;
+                CREATE TRIGGER invoices_locked_update
+                BEFORE UPDATE ON invoices
+                WHEN OLD.locked = 1 AND NEW.revision = OLD.revision AND NOT (
+                    OLD.status = 'GENERATED' AND NEW.status = 'CANCELLED' AND NEW.locked = 1
+                    AND NEW.cancelled_at IS NOT NULL AND NEW.cancellation_reason IS NOT NULL
+                    AND NEW.financial_year IS OLD.financial_year AND NEW.customer_id IS OLD.customer_id
+                    AND NEW.invoice_date IS OLD.invoice_date AND NEW.grand_total IS OLD.grand_total
+                    AND NEW.json_data IS OLD.json_data AND NEW.work_order_reference IS OLD.work_order_reference
+                    AND NEW.work_order_date IS OLD.work_order_date
+                )
+                BEGIN
+                    SELECT RAISE(ABORT, 'Invoice has been issued and is locked. File an amendment instead.');
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoices_locked_delete
+                BEFORE DELETE ON invoices
+                WHEN OLD.locked = 1
+                BEGIN
+                    SELECT RAISE(ABORT, 'Issued invoices cannot be deleted. Cancel the invoice instead.');
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS customers_trash_before_delete
+                BEFORE DELETE ON customers
+                WHEN OLD.deleted_at IS NULL
+                BEGIN
+                    SELECT RAISE(ABORT, 'Customers must be moved to the trash before they can be purged.');
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 4,
+            description: "row_versions",
+            // Every insert, update and delete of a synced row stamps it with the next value of a
+            // per-database clock, so changeset.rs can export what changed since a given version
+            // and tell local edits apart from rows it imported. Deleted rows keep a tombstone.
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS sync_clock(
+                    id INTEGER PRIMARY KEY CHECK (id = 1),
+                    version INTEGER NOT NULL,
+                    exported_version INTEGER NOT NULL DEFAULT 0
+                );
+                INSERT OR IGNORE INTO sync_clock(id, version) VALUES (1, 1);
+
+                CREATE TABLE IF NOT EXISTS row_versions(
+                    table_name TEXT NOT NULL,
+                    row_key TEXT NOT NULL,
+                    version INTEGER NOT NULL,
+                    deleted INTEGER NOT NULL DEFAULT 0,
+                    -- Set when the row was last written by a changeset import; NULL for local edits
+                    origin_device TEXT,
+                    origin_version INTEGER,
+                    PRIMARY KEY(table_name, row_key)
+                );
+                CREATE INDEX IF NOT EXISTS row_versions_version ON row_versions(version);
+
+                -- Other installations this one has imported changesets from
+                CREATE TABLE IF NOT EXISTS sync_peers(
+                    device_id TEXT PRIMARY KEY,
+                    device_name TEXT,
+                    last_version INTEGER NOT NULL DEFAULT 0, -- highest of the peer's versions imported
+                    local_version INTEGER NOT NULL DEFAULT 0, -- this database's clock right after that import
+                    synced_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );
+
+                -- Rows that exist already all start at version 1
+                INSERT OR IGNORE INTO row_versions(table_name, row_key, version) SELECT 'customers', id, 1 FROM customers;
+                INSERT OR IGNORE INTO row_versions(table_name, row_key, version) SELECT 'invoices', invoice_number, 1 FROM invoices;
+                INSERT OR IGNORE INTO row_versions(table_name, row_key, version) SELECT 'invoice_items', id, 1 FROM invoice_items;
+                INSERT OR IGNORE INTO row_versions(table_name, row_key, version) SELECT 'invoice_revisions', invoice_number || '/' || revision, 1 FROM invoice_revisions;
+
+                CREATE TRIGGER IF NOT EXISTS customers_version_insert
+                AFTER INSERT ON customers
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'customers', NEW.id, version, 0 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS customers_version_update
+                AFTER UPDATE ON customers
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'customers', OLD.id, version, 1 FROM sync_clock WHERE OLD.id IS NOT NEW.id;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'customers', NEW.id, version, 0 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS customers_version_delete
+                AFTER DELETE ON customers
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'customers', OLD.id, version, 1 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoices_version_insert
+                AFTER INSERT ON invoices
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoices', NEW.invoice_number, version, 0 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoices_version_update
+                AFTER UPDATE ON invoices
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoices', OLD.invoice_number, version, 1 FROM sync_clock WHERE OLD.invoice_number IS NOT NEW.invoice_number;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoices', NEW.invoice_number, version, 0 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoices_version_delete
+                AFTER DELETE ON invoices
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoices', OLD.invoice_number, version, 1 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_items_version_insert
+                AFTER INSERT ON invoice_items
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoice_items', NEW.id, version, 0 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_items_version_update
+                AFTER UPDATE ON invoice_items
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoice_items', OLD.id, version, 1 FROM sync_clock WHERE OLD.id IS NOT NEW.id;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoice_items', NEW.id, version, 0 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_items_version_delete
+                AFTER DELETE ON invoice_items
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoice_items', OLD.id, version, 1 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_revisions_version_insert
+                AFTER INSERT ON invoice_revisions
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoice_revisions', NEW.invoice_number || '/' || NEW.revision, version, 0 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_revisions_version_update
+                AFTER UPDATE ON invoice_revisions
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoice_revisions', OLD.invoice_number || '/' || OLD.revision, version, 1 FROM sync_clock WHERE OLD.invoice_number || '/' || OLD.revision IS NOT NEW.invoice_number || '/' || NEW.revision;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoice_revisions', NEW.invoice_number || '/' || NEW.revision, version, 0 FROM sync_clock;
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS invoice_revisions_version_delete
+                AFTER DELETE ON invoice_revisions
+                BEGIN
+                    UPDATE sync_clock SET version = version + 1;
+                    INSERT OR REPLACE INTO row_versions(table_name, row_key, version, deleted)
+                        SELECT 'invoice_revisions', OLD.invoice_number || '/' || OLD.revision, version, 1 FROM sync_clock;
+                END;
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "catalogue_items",
+            // Goods and services that can be picked into an invoice line, filled by the Tally
+            // masters import (tally_import.rs). Not part of changeset sync; re-import on each device.
+            sql: r#"
+                CREATE TABLE IF NOT EXISTS catalogue_items(
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                    description TEXT,
+                    hsn_sac_code TEXT,
+                    unit TEXT,
+                    rate REAL,
+                    gst_rate REAL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );
+            "#,
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "invoice_emails",
+            // `email` may hold several addresses separated by commas. Every send attempt made by
+            // email.rs is logged, including failed ones.
+            sql: r#"
+                ALTER TABLE customers ADD COLUMN email TEXT;
+
+                CREATE TABLE IF NOT EXISTS invoice_emails(
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    invoice_number TEXT NOT NULL,
+                    recipients TEXT NOT NULL,
+                    cc TEXT,
+                    subject TEXT NOT NULL,
+                    attachment TEXT NOT NULL,
+                    status TEXT NOT NULL, -- 'sent' | 'failed'
+                    error TEXT,
+                    sent_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );
+                CREATE INDEX IF NOT EXISTS invoice_emails_invoice ON invoice_emails(invoice_number);
+            "#,
+            kind: MigrationKind::Up,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::{params, Connection};
+
+    #[test]
+    fn legacy_line_items_are_moved_out_of_json_before_invoices_lock() {
+        // A database from before the migrations: invoices written, the relational step not yet run
+        let conn = Connection::open_in_memory().unwrap();
+        let all = migrations();
+        conn.execute_batch(all[0].sql).unwrap();
+        let items = serde_json::json!({ "lineItems": [
+            { "id": "001-1", "serialNumber": 1, "description": "Solar panels", "hsnSacCode": "8541", "rate": 45000.0, "quantity": 5.0, "unit": "Kwp", "amount": 225000.0 },
+            { "id": "001-2", "serialNumber": 2, "description": "Installation", "hsnSacCode": "9954", "rate": 10000.0, "quantity": 1.0, "unit": "Job", "amount": 10000.0 },
+        ]});
+        conn.execute(
+            "INSERT INTO invoices(invoice_number, status, json_data) VALUES ('001', 'GENERATED', ?1), ('002', 'GENERATED', 'not json'), ('003', 'DRAFT', '{}')",
+            params![items.to_string()],
+        )
+        .unwrap();
+        // One item already moved by a relational step that stopped half way
+        conn.execute("INSERT INTO invoice_items(id, invoice_number, serial_number, description) VALUES ('001-1', '001', 1, 'Solar panels')", [])
+            .unwrap();
+
+        conn.execute_batch(all[1].sql).unwrap();
+
+        let moved: Vec<(String, i64, String, f64)> = conn
+            .prepare("SELECT id, serial_number, hsn_sac_code, amount FROM invoice_items WHERE id = '001-2'")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(moved, vec![("001-2".to_string(), 2, "9954".to_string(), 10000.0)]);
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM invoice_items", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+        let locked: i64 = conn.query_row("SELECT SUM(locked) FROM invoices", [], |row| row.get(0)).unwrap();
+        assert_eq!(locked, 2);
+        // What db.ts would try next is now refused, and no longer needed
+        assert!(conn.execute("INSERT INTO invoice_items(id, invoice_number) VALUES ('001-3', '001')", []).is_err());
+    }
+}
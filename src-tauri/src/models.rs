@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+// Mirrors of the frontend types in `src/types/invoice.ts`. They are (de)serialized in
+// camelCase so they round-trip through `json_data` unchanged.
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineItem {
+    pub id: String,
+    pub serial_number: i64,
+    pub description: String,
+    #[serde(default)]
+    pub hsn_sac_code: String,
+    pub rate: f64,
+    pub quantity: f64,
+    #[serde(default)]
+    pub unit: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Customer {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub company_name: String,
+    #[serde(default)]
+    pub address_line1: String,
+    #[serde(default)]
+    pub address_line2: Option<String>,
+    #[serde(default)]
+    pub address_line3: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub pincode: Option<String>,
+    #[serde(default)]
+    pub gst_number: Option<String>,
+    #[serde(default)]
+    pub pan_number: Option<String>,
+    /// One or more addresses, separated by commas.
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogueItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub hsn_sac_code: Option<String>,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub rate: Option<f64>,
+    /// Total GST in percent, e.g. 12 for 6% CGST + 6% SGST.
+    #[serde(default)]
+    pub gst_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Invoice {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub invoice_number: String,
+    pub financial_year: String,
+    pub invoice_date: String,
+    #[serde(default)]
+    pub work_order_reference: String,
+    #[serde(default)]
+    pub work_order_date: Option<String>,
+    pub customer: Customer,
+    pub line_items: Vec<LineItem>,
+    pub total_basic_amount: f64,
+    pub cgst_percentage: f64,
+    pub cgst_amount: f64,
+    pub sgst_percentage: f64,
+    pub sgst_amount: f64,
+    pub grand_total: f64,
+    #[serde(default)]
+    pub amount_in_words: String,
+    #[serde(default)]
+    pub revision: i64,
+}
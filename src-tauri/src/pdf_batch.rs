@@ -0,0 +1,250 @@
+use crate::export::{self, InvoiceFilter, StoredInvoice};
+use crate::invoice_pdf::{self, Letterhead};
+use crate::pdf_sign;
+use crate::spreadsheet::{self, date_cell, Cell};
+use crate::vault::{self, Vault};
+use crate::{db, device};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+/// Emitted with a [`BatchProgress`] after each invoice is rendered.
+pub const PROGRESS_EVENT: &str = "pdf-batch-progress";
+/// Lists every PDF in the zip with the invoice it holds.
+const INDEX_FILE: &str = "index.csv";
+const INDEX_HEADER: &[&str] = &[
+    "File",
+    "Invoice No.",
+    "Invoice Date",
+    "Financial Year",
+    "Status",
+    "Revision",
+    "Customer",
+    "Customer GSTIN",
+    "Taxable Value",
+    "CGST",
+    "SGST",
+    "Grand Total",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchProgress {
+    pub job_id: String,
+    pub done: usize,
+    pub total: usize,
+    pub invoice_number: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub path: String,
+    pub invoices: usize,
+}
+
+/// Cancellation flags of the batch exports in progress, by job ID.
+#[derive(Default)]
+pub struct Jobs(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+impl Jobs {
+    fn register(&self, id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.0.lock().unwrap().insert(id.to_string(), flag.clone());
+        flag
+    }
+
+    fn finish(&self, id: &str) {
+        self.0.lock().unwrap().remove(id);
+    }
+
+    fn cancel(&self, id: &str) -> bool {
+        match self.0.lock().unwrap().get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn index_row(file: &str, stored: &StoredInvoice) -> Vec<Cell> {
+    let invoice = &stored.invoice;
+    vec![
+        Cell::from(file),
+        Cell::from(invoice.invoice_number.as_str()),
+        date_cell(&invoice.invoice_date),
+        Cell::from(invoice.financial_year.as_str()),
+        Cell::from(stored.status.as_str()),
+        Cell::from(invoice.revision),
+        Cell::from(invoice.customer.company_name.as_str()),
+        Cell::from(invoice.customer.gst_number.clone()),
+        Cell::from(invoice.total_basic_amount),
+        Cell::from(invoice.cgst_amount),
+        Cell::from(invoice.sgst_amount),
+        Cell::from(invoice.grand_total),
+    ]
+}
+
+fn write_zip(
+    target: &Path,
+    invoices: &[StoredInvoice],
+    letterhead: &Letterhead,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(usize, &str),
+) -> Result<(), String> {
+    let file = fs::File::create(target).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut index = Vec::new();
+    for (i, stored) in invoices.iter().enumerate() {
+        if cancel.load(Ordering::SeqCst) {
+            return Err("The export was cancelled.".to_string());
+        }
+        let name = invoice_pdf::file_name(stored);
+        let pdf = invoice_pdf::render(stored, letterhead)
+            .map_err(|e| format!("Invoice {}: {}", stored.invoice.invoice_number, e))?;
+        zip.start_file(name.as_str(), options).map_err(|e| e.to_string())?;
+        zip.write_all(&pdf).map_err(|e| e.to_string())?;
+        index.push(index_row(&name, stored));
+        on_progress(i + 1, &stored.invoice.invoice_number);
+    }
+    zip.start_file(INDEX_FILE, options).map_err(|e| e.to_string())?;
+    zip.write_all(spreadsheet::csv_text(INDEX_HEADER, &index, "indian")?.as_bytes()).map_err(|e| e.to_string())?;
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Renders every invoice matching `filter` into a zip at `target`, with an index of them.
+/// The zip is built next to the target and only moved there once complete, so a failed or
+/// cancelled export leaves nothing behind. Returns how many invoices went in.
+pub fn export_pdfs(
+    conn: &rusqlite::Connection,
+    filter: &InvoiceFilter,
+    letterhead: &Letterhead,
+    target: &Path,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(usize, usize, &str),
+) -> Result<usize, String> {
+    let invoices = export::load_invoices(conn, filter)?;
+    if invoices.is_empty() {
+        return Err("No issued invoices match the filter.".to_string());
+    }
+    let total = invoices.len();
+    let partial = PathBuf::from(format!("{}.part", target.display()));
+    let result = write_zip(&partial, &invoices, letterhead, cancel, |done, number| on_progress(done, total, number))
+        .and_then(|_| fs::rename(&partial, target).map_err(|e| e.to_string()));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result.map(|_| total)
+}
+
+/// Exports the PDFs of the invoices matching `filter` as a zip, emitting [`PROGRESS_EVENT`]s
+/// tagged with `job_id`.
+#[tauri::command]
+pub async fn export_invoice_pdfs(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    jobs: tauri::State<'_, Jobs>,
+    filter: InvoiceFilter,
+    target_path: String,
+    job_id: String,
+) -> Result<BatchReport, String> {
+    let conn = db::open(&app_handle)?;
+    let config_dir = device::config_dir(&app_handle)?;
+    let bank = vault::invoice_bank_details(&config_dir, &vault, &conn)?;
+    let mut letterhead = Letterhead::load(&conn, bank)?;
+    letterhead.signer = pdf_sign::signer(&conn, &vault, &config_dir)?;
+    let cancel = jobs.register(&job_id);
+
+    let handle = app_handle.clone();
+    let id = job_id.clone();
+    let target = PathBuf::from(&target_path);
+    // Rendering is CPU bound, so it runs off the async workers
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        export_pdfs(&conn, &filter, &letterhead, &target, &cancel, |done, total, number| {
+            let progress = BatchProgress { job_id: id.clone(), done, total, invoice_number: number.to_string() };
+            let _ = handle.emit_all(PROGRESS_EVENT, progress);
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|result| result);
+    jobs.finish(&job_id);
+
+    let invoices = result?;
+    log::info!("Exported {} invoice PDF(s) to {}", invoices, target_path);
+    Ok(BatchReport { path: target_path, invoices })
+}
+
+/// Stops a running export after the invoice it is on.
+#[tauri::command]
+pub async fn cancel_invoice_pdfs(jobs: tauri::State<'_, Jobs>, job_id: String) -> Result<bool, String> {
+    Ok(jobs.cancel(&job_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_invoice;
+    use std::io::Read;
+
+    fn stored(invoice_number: &str, revision: i64, status: &str) -> StoredInvoice {
+        let mut invoice = sample_invoice(invoice_number, "c1");
+        invoice.revision = revision;
+        StoredInvoice { invoice, status: status.to_string(), cancelled_at: None, cancellation_reason: None }
+    }
+
+    #[test]
+    fn the_zip_holds_each_pdf_and_an_index_of_them() {
+        let letterhead = Letterhead {
+            company: serde_json::json!({ "accountName": "Apex Solar", "gstNumber": "19ABCDE1234F1Z5" }),
+            bank: vault::BankDetails::default(),
+            logo: None,
+            stamp: None,
+            signer: None,
+        };
+        let invoices = [stored("001", 0, "GENERATED"), stored("002", 1, "GENERATED"), stored("003", 0, "CANCELLED")];
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("invoices.zip");
+        let mut progress = Vec::new();
+        write_zip(&target, &invoices, &letterhead, &AtomicBool::new(false), |done, number| progress.push((done, number.to_string())))
+            .unwrap();
+        assert_eq!(progress.last(), Some(&(3, "003".to_string())));
+
+        let mut zip = zip::ZipArchive::new(fs::File::open(&target).unwrap()).unwrap();
+        let names: Vec<String> = zip.file_names().map(str::to_string).collect();
+        for name in ["Invoice_AS_24-25_001.pdf", "Invoice_AS_24-25_002_Rev1.pdf", "Invoice_AS_24-25_003_CANCELLED.pdf", INDEX_FILE] {
+            assert!(names.iter().any(|n| n == name), "{} missing from {:?}", name, names);
+        }
+        let mut index = String::new();
+        zip.by_name(INDEX_FILE).unwrap().read_to_string(&mut index).unwrap();
+        let rows = spreadsheet::parse_csv(&index);
+        assert_eq!(rows[0], INDEX_HEADER);
+        assert_eq!(
+            rows[2],
+            [
+                "Invoice_AS_24-25_002_Rev1.pdf", "002", "03-07-2024", "24-25", "GENERATED", "1", "Acme Pvt Ltd", "19AAACA1234A1Z5",
+                "225000", "13500", "13500", "252000",
+            ]
+        );
+        assert_eq!(rows[3][0], "Invoice_AS_24-25_003_CANCELLED.pdf");
+        assert_eq!(rows[3][4], "CANCELLED");
+    }
+
+    #[test]
+    fn a_cancelled_export_stops_before_writing() {
+        let letterhead = Letterhead { company: serde_json::json!({}), bank: vault::BankDetails::default(), logo: None, stamp: None, signer: None };
+        let dir = tempfile::tempdir().unwrap();
+        let error = write_zip(&dir.path().join("invoices.zip"), &[stored("001", 0, "GENERATED")], &letterhead, &AtomicBool::new(true), |_, _| {})
+            .unwrap_err();
+        assert_eq!(error, "The export was cancelled.");
+    }
+}
@@ -0,0 +1,758 @@
+use crate::vault::{self, Vault};
+use crate::{bundle, db, device};
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+use cms::builder::{create_signing_time_attribute, SignedDataBuilder, SignerInfoBuilder};
+use cms::cert::{CertificateChoices, IssuerAndSerialNumber};
+use cms::content_info::ContentInfo;
+use cms::signed_data::{EncapsulatedContentInfo, SignedData, SignerIdentifier};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream, StringFormat};
+use p12_keystore::KeyStore;
+use regex::bytes::Regex;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::fs;
+use std::path::Path;
+use x509_cert::attr::Attribute;
+use x509_cert::der::asn1::{Any, ObjectIdentifier, OctetString, SetOfVec};
+use x509_cert::der::oid::db::{rfc4519, rfc5911, rfc5912};
+use x509_cert::der::oid::AssociatedOid;
+use x509_cert::der::{Decode, Encode, SliceReader};
+use x509_cert::name::Name;
+use x509_cert::spki::AlgorithmIdentifierOwned;
+use x509_cert::Certificate;
+
+/// Setting holding the signing settings as JSON. The certificate password is kept in the vault.
+pub const SIGNING_SETTINGS: &str = "signing_settings";
+/// Bytes set aside in the PDF for the CMS signature, enough for a DSC with its CA chain.
+const SIGNATURE_SIZE: usize = 16384;
+/// Written in place of each ByteRange offset until the signature's position is known; wide
+/// enough that the real offsets fit in its place.
+const OFFSET_PLACEHOLDER: i64 = 9_999_999_999;
+const PT_PER_MM: f32 = 72.0 / 25.4;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SigningSettings {
+    /// Sign invoice PDFs as they are generated, in place of the stamp image.
+    pub enabled: bool,
+    /// The PKCS#12 (.pfx / .p12) file the DSC was exported as.
+    pub certificate_path: String,
+    pub reason: String,
+    pub location: String,
+}
+
+/// Where the visible signature goes on the last page, in mm from the top left.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureBlock {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateInfo {
+    pub name: String,
+    pub subject: String,
+    pub issuer: String,
+    pub serial_number: String,
+    pub valid_from: String,
+    pub valid_to: String,
+    pub expired: bool,
+}
+
+/// What was found checking one signature in a PDF.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureReport {
+    pub signer: String,
+    pub issuer: String,
+    pub signed_at: Option<String>,
+    /// `signed_at` comes from the signature's signed attributes. Otherwise it is the `/M` entry
+    /// beside the signature, which is not covered by it. Either way it is the signer's own clock.
+    pub signed_time_in_signature: bool,
+    pub reason: Option<String>,
+    pub location: Option<String>,
+    /// Nothing was added to the file after this signature.
+    pub covers_whole_file: bool,
+    /// The signed bytes match the signature.
+    pub intact: bool,
+    /// The signing certificate was within its validity period at `signed_at`.
+    pub certificate_valid: bool,
+    pub problem: Option<String>,
+}
+
+/// The private key and certificates of a DSC, with what goes into the signature dictionary.
+pub struct Signer {
+    key: SigningKey<Sha256>,
+    /// The signing certificate first, then its issuers.
+    chain: Vec<Certificate>,
+    reason: String,
+    location: String,
+}
+
+pub fn load_settings(conn: &Connection) -> Result<SigningSettings, String> {
+    let value: Option<Option<String>> = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [SIGNING_SETTINGS], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match value.flatten() {
+        Some(json) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => Ok(SigningSettings::default()),
+    }
+}
+
+/// The common name in a certificate name, or the whole name when it has none.
+fn common_name(name: &Name) -> String {
+    name.0
+        .iter()
+        .flat_map(|rdn| rdn.0.iter())
+        .find(|atv| atv.oid == rfc4519::CN)
+        .and_then(|atv| atv.to_string().strip_prefix("CN=").map(str::to_string))
+        .unwrap_or_else(|| name.to_string())
+}
+
+fn certificate_time(time: &x509_cert::time::Time) -> DateTime<Utc> {
+    DateTime::from_timestamp(time.to_unix_duration().as_secs() as i64, 0).unwrap_or_default()
+}
+
+impl Signer {
+    /// Opens a PKCS#12 file's first key and its certificate chain. Only RSA keys are supported,
+    /// which is what Class 3 DSCs are issued with.
+    pub fn from_pkcs12(data: &[u8], password: &str, settings: &SigningSettings) -> Result<Signer, String> {
+        let store = KeyStore::from_pkcs12(data, password)
+            .map_err(|e| format!("Could not open the certificate. Check the password. ({})", e))?;
+        let (_, key_chain) = store.private_key_chain().ok_or("The certificate file holds no private key")?;
+        let key = RsaPrivateKey::from_pkcs8_der(key_chain.key())
+            .map_err(|_| "Only certificates with an RSA key are supported".to_string())?;
+        let chain = key_chain
+            .chain()
+            .iter()
+            .map(|cert| Certificate::from_der(cert.as_der()).map_err(|e| e.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if chain.is_empty() {
+            return Err("The certificate file holds no certificate for its key".to_string());
+        }
+        Ok(Signer {
+            key: SigningKey::new(key),
+            chain,
+            reason: settings.reason.trim().to_string(),
+            location: settings.location.trim().to_string(),
+        })
+    }
+
+    fn certificate(&self) -> &Certificate {
+        &self.chain[0]
+    }
+
+    pub fn name(&self) -> String {
+        common_name(&self.certificate().tbs_certificate.subject)
+    }
+
+    pub fn info(&self) -> CertificateInfo {
+        let tbs = &self.certificate().tbs_certificate;
+        let valid_from = certificate_time(&tbs.validity.not_before);
+        let valid_to = certificate_time(&tbs.validity.not_after);
+        CertificateInfo {
+            name: self.name(),
+            subject: tbs.subject.to_string(),
+            issuer: tbs.issuer.to_string(),
+            serial_number: tbs.serial_number.to_string(),
+            valid_from: valid_from.to_rfc3339(),
+            valid_to: valid_to.to_rfc3339(),
+            expired: valid_to < Utc::now(),
+        }
+    }
+}
+
+/// The signer set up under Settings > Digital Signature, or `None` while signing is turned off.
+pub fn signer(conn: &Connection, vault: &Vault, config_dir: &Path) -> Result<Option<Signer>, String> {
+    let settings = load_settings(conn)?;
+    if !settings.enabled {
+        return Ok(None);
+    }
+    configured_signer(&settings, vault, config_dir).map(Some)
+}
+
+fn configured_signer(settings: &SigningSettings, vault: &Vault, config_dir: &Path) -> Result<Signer, String> {
+    if settings.certificate_path.trim().is_empty() {
+        return Err("Choose the certificate file under Settings > Digital Signature first.".to_string());
+    }
+    if !vault::exists(config_dir) {
+        return Err("Set up the vault and save the certificate password in it first.".to_string());
+    }
+    let password = vault.get(vault::SIGNING_PASSWORD)?.ok_or("No certificate password has been saved.")?;
+    let password = String::from_utf8(password).map_err(|e| e.to_string())?;
+    let data = fs::read(settings.certificate_path.trim())
+        .map_err(|e| format!("Could not read the certificate file {}: {}", settings.certificate_path.trim(), e))?;
+    Signer::from_pkcs12(&data, &password, settings)
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack.get(from..)?.windows(needle.len()).position(|w| w == needle).map(|i| i + from)
+}
+
+/// Text for the standard fonts, which only cover Latin-1.
+pub fn latin1(text: &str) -> Vec<u8> {
+    text.chars().map(|c| if (c as u32) < 256 { c as u8 } else { b'?' }).collect()
+}
+
+/// A date as PDF dates are written, e.g. `D:20240415103000+05'30'`.
+fn pdf_date(time: &DateTime<Local>) -> String {
+    let offset = time.offset().local_minus_utc();
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs() / 60;
+    format!("D:{}{}{:02}'{:02}'", time.format("%Y%m%d%H%M%S"), sign, offset / 60, offset % 60)
+}
+
+fn parse_pdf_date(text: &str) -> Option<DateTime<FixedOffset>> {
+    let digits = text.strip_prefix("D:").unwrap_or(text);
+    let local = chrono::NaiveDateTime::parse_from_str(digits.get(..14)?, "%Y%m%d%H%M%S").ok()?;
+    let zone = digits.get(14..).unwrap_or("").replace('\'', "");
+    let offset = match zone.split_at(zone.len().min(1)) {
+        ("+" | "-", hhmm) if hhmm.len() >= 4 => {
+            let seconds = hhmm[..2].parse::<i32>().ok()? * 3600 + hhmm[2..4].parse::<i32>().ok()? * 60;
+            FixedOffset::east_opt(if zone.starts_with('-') { -seconds } else { seconds })?
+        }
+        _ => FixedOffset::east_opt(0)?,
+    };
+    offset.from_local_datetime(&local).single()
+}
+
+/// Height of a page in points, from its own or an inherited MediaBox; A4 when there is none.
+pub fn page_height(doc: &Document, page: lopdf::ObjectId) -> f32 {
+    let mut node = doc.get_dictionary(page).ok();
+    while let Some(dict) = node {
+        if let Ok(media_box) = dict.get(b"MediaBox").and_then(|b| doc.dereference(b)).and_then(|(_, b)| b.as_array()) {
+            let values: Vec<f32> = media_box.iter().filter_map(|v| v.as_float().ok()).collect();
+            if values.len() == 4 {
+                return values[3] - values[1];
+            }
+        }
+        node = dict.get(b"Parent").and_then(Object::as_reference).and_then(|id| doc.get_dictionary(id)).ok();
+    }
+    297.0 * PT_PER_MM
+}
+
+/// The box drawn where the signature is: who signed, when, why and where.
+fn appearance(signer: &Signer, signed_at: &DateTime<Local>, width: f32, height: f32) -> Result<Vec<u8>, String> {
+    let name = signer.name();
+    // Long names are shrunk to fit, at about 0.6 em per character
+    let name_size = (9.0f32).min((width - 8.0) / (name.chars().count().max(1) as f32 * 0.6));
+    let mut lines = vec![
+        ("F1", 7.0, "Digitally signed by".to_string()),
+        ("F2", name_size, name),
+        ("F1", 7.0, format!("Date: {}", signed_at.format("%d-%m-%Y %H:%M:%S %:z"))),
+    ];
+    if !signer.reason.is_empty() {
+        lines.push(("F1", 7.0, format!("Reason: {}", signer.reason)));
+    }
+    if !signer.location.is_empty() {
+        lines.push(("F1", 7.0, format!("Location: {}", signer.location)));
+    }
+
+    let mut operations = vec![
+        Operation::new("q", vec![]),
+        Operation::new("RG", vec![0.16.into(), 0.38.into(), 0.72.into()]),
+        Operation::new("w", vec![0.75.into()]),
+        Operation::new("re", vec![0.5.into(), 0.5.into(), (width - 1.0).into(), (height - 1.0).into()]),
+        Operation::new("S", vec![]),
+        Operation::new("Q", vec![]),
+        Operation::new("BT", vec![]),
+        Operation::new("rg", vec![0.into(), 0.into(), 0.into()]),
+    ];
+    let mut y = height - 4.0;
+    for (font, size, text) in lines {
+        y -= size + 2.0;
+        operations.push(Operation::new("Tf", vec![Object::Name(font.into()), size.into()]));
+        operations.push(Operation::new("Tm", vec![1.into(), 0.into(), 0.into(), 1.into(), 4.into(), y.into()]));
+        operations.push(Operation::new("Tj", vec![Object::string_literal(latin1(&text))]));
+    }
+    operations.push(Operation::new("ET", vec![]));
+    Content { operations }.encode().map_err(|e| e.to_string())
+}
+
+/// The CMS SignedData over `data`, detached, as PAdES expects: the content is not included and
+/// the signing certificate is bound to the signature with a signing-certificate-v2 attribute.
+fn cms_signature(signer: &Signer, data: &[&[u8]]) -> Result<Vec<u8>, String> {
+    let mut hasher = Sha256::new();
+    for part in data {
+        hasher.update(part);
+    }
+    let digest = hasher.finalize();
+    let certificate = signer.certificate();
+    let certificate_hash = Sha256::digest(certificate.to_der().map_err(|e| e.to_string())?);
+    // SigningCertificateV2 { certs: [ESSCertIDv2 { certHash }] }, SHA-256 being the default hash
+    let signing_certificate = vec![vec![vec![OctetString::new(certificate_hash.to_vec()).map_err(|e| e.to_string())?]]];
+    let signing_certificate = Attribute {
+        oid: rfc5911::ID_AA_SIGNING_CERTIFICATE_V_2,
+        values: SetOfVec::try_from(vec![Any::encode_from(&signing_certificate).map_err(|e| e.to_string())?])
+            .map_err(|e| e.to_string())?,
+    };
+
+    let sha256 = AlgorithmIdentifierOwned { oid: rfc5912::ID_SHA_256, parameters: None };
+    let content = EncapsulatedContentInfo { econtent_type: rfc5911::ID_DATA, econtent: None };
+    let sid = SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+        issuer: certificate.tbs_certificate.issuer.clone(),
+        serial_number: certificate.tbs_certificate.serial_number.clone(),
+    });
+    let mut signer_info = SignerInfoBuilder::new(&signer.key, sid, sha256.clone(), &content, Some(&digest))
+        .map_err(|e| e.to_string())?;
+    signer_info.add_signed_attribute(signing_certificate).map_err(|e| e.to_string())?;
+    signer_info.add_signed_attribute(create_signing_time_attribute().map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+
+    let mut builder = SignedDataBuilder::new(&content);
+    builder.add_digest_algorithm(sha256).map_err(|e| e.to_string())?;
+    for cert in &signer.chain {
+        builder.add_certificate(CertificateChoices::Certificate(cert.clone())).map_err(|e| e.to_string())?;
+    }
+    builder
+        .add_signer_info::<_, rsa::pkcs1v15::Signature>(signer_info)
+        .map_err(|e| e.to_string())?
+        .build()
+        .map_err(|e| e.to_string())?
+        .to_der()
+        .map_err(|e| e.to_string())
+}
+
+/// Whether a PDF carries a signature, which any later change to the file would break.
+pub fn is_signed(pdf: &[u8]) -> bool {
+    find(pdf, b"/ByteRange", 0).is_some()
+}
+
+/// Signs a PDF with a visible signature on its last page. The document is written out again with
+/// a signature field, and the signature covers every byte of it except the signature itself.
+pub fn sign(pdf: &[u8], signer: &Signer, block: SignatureBlock) -> Result<Vec<u8>, String> {
+    if is_signed(pdf) {
+        return Err("The PDF is already signed.".to_string());
+    }
+    let mut doc = Document::load_mem(pdf).map_err(|e| e.to_string())?;
+    if doc.catalog().map_err(|e| e.to_string())?.has(b"AcroForm") {
+        return Err("PDFs with form fields cannot be signed.".to_string());
+    }
+    let page = *doc.get_pages().values().last().ok_or("The PDF has no pages")?;
+    let (width, height) = (block.width * PT_PER_MM, block.height * PT_PER_MM);
+    let left = block.x * PT_PER_MM;
+    let top = page_height(&doc, page) - block.y * PT_PER_MM;
+    let signed_at = Local::now();
+
+    let regular = doc.add_object(dictionary! {
+        "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica", "Encoding" => "WinAnsiEncoding",
+    });
+    let bold = doc.add_object(dictionary! {
+        "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica-Bold", "Encoding" => "WinAnsiEncoding",
+    });
+    let form = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Form",
+        "BBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+        "Resources" => dictionary! { "Font" => dictionary! { "F1" => regular, "F2" => bold } },
+    };
+    let appearance = doc.add_object(Stream::new(form, appearance(signer, &signed_at, width, height)?));
+
+    let mut signature = dictionary! {
+        "Type" => "Sig",
+        "Filter" => "Adobe.PPKLite",
+        "SubFilter" => "ETSI.CAdES.detached",
+        "Name" => Object::string_literal(latin1(&signer.name())),
+        "M" => Object::string_literal(pdf_date(&signed_at)),
+    };
+    if !signer.reason.is_empty() {
+        signature.set("Reason", Object::string_literal(latin1(&signer.reason)));
+    }
+    if !signer.location.is_empty() {
+        signature.set("Location", Object::string_literal(latin1(&signer.location)));
+    }
+    // Placeholders patched once the document is written; /Contents must come after /ByteRange
+    signature.set("ByteRange", vec![0.into(), OFFSET_PLACEHOLDER.into(), OFFSET_PLACEHOLDER.into(), OFFSET_PLACEHOLDER.into()]);
+    signature.set("Contents", Object::String(vec![0; SIGNATURE_SIZE], StringFormat::Hexadecimal));
+    let signature = doc.add_object(signature);
+
+    let field = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "FT" => "Sig",
+        "T" => Object::string_literal("Signature1"),
+        "V" => signature,
+        "F" => 132, // Print | Locked
+        "Rect" => vec![left.into(), (top - height).into(), (left + width).into(), top.into()],
+        "P" => page,
+        "AP" => dictionary! { "N" => appearance },
+    });
+    let annots = doc.get_dictionary(page).map_err(|e| e.to_string())?.get(b"Annots").ok().cloned();
+    match annots {
+        Some(Object::Reference(id)) => doc
+            .get_object_mut(id)
+            .and_then(Object::as_array_mut)
+            .map_err(|e| e.to_string())?
+            .push(field.into()),
+        Some(Object::Array(mut list)) => {
+            list.push(field.into());
+            doc.get_dictionary_mut(page).map_err(|e| e.to_string())?.set("Annots", list);
+        }
+        _ => doc.get_dictionary_mut(page).map_err(|e| e.to_string())?.set("Annots", vec![field.into()]),
+    }
+    // SignaturesExist | AppendOnly
+    let form = dictionary! { "Fields" => vec![field.into()], "SigFlags" => 3 };
+    doc.catalog_mut().map_err(|e| e.to_string())?.set("AcroForm", form);
+
+    let mut out = Vec::new();
+    doc.save_to(&mut out).map_err(|e| e.to_string())?;
+
+    let byte_range_at = find(&out, b"/ByteRange", 0).ok_or("The signature dictionary was not written")?;
+    let range_start = byte_range_at + "/ByteRange".len();
+    let range_end = find(&out, b"]", range_start).ok_or("The signature dictionary was not written")? + 1;
+    let contents_start = find(&out, b"/Contents<", range_end).ok_or("The signature dictionary was not written")? + "/Contents".len();
+    let contents_end = contents_start + 2 * SIGNATURE_SIZE + 2;
+    if out.get(contents_end - 1) != Some(&b'>') {
+        return Err("The signature placeholder was not written as expected".to_string());
+    }
+
+    let byte_range = format!("[0 {} {} {}", contents_start, contents_end, out.len() - contents_end);
+    let placeholder_width = range_end - range_start - 1;
+    out.splice(range_start..range_end, format!("{:<width$}]", byte_range, width = placeholder_width).into_bytes());
+
+    let der = cms_signature(signer, &[&out[..contents_start], &out[contents_end..]])?;
+    let hex = hex::encode_upper(der);
+    if hex.len() > 2 * SIGNATURE_SIZE {
+        return Err("The signature is too large for the space set aside for it".to_string());
+    }
+    out[contents_start + 1..contents_start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+    Ok(out)
+}
+
+/// A string value of the signature dictionary holding the ByteRange at `at`.
+fn dictionary_string(pdf: &[u8], at: usize, key: &str) -> Option<String> {
+    let start = pdf[..at].windows(3).rposition(|w| w == b"obj").unwrap_or(0);
+    let end = find(pdf, b"endobj", at).unwrap_or(pdf.len());
+    let pattern = Regex::new(&format!(r"/{}\s*\(((?:[^()\\]|\\.)*)\)", key)).ok()?;
+    let value = pattern.captures(&pdf[start..end])?.get(1)?.as_bytes();
+    let mut text = String::new();
+    let mut escaped = false;
+    for &byte in value {
+        if escaped || byte != b'\\' {
+            text.push(byte as char);
+            escaped = false;
+        } else {
+            escaped = true;
+        }
+    }
+    Some(text)
+}
+
+fn hash<D: Digest + AssociatedOid>(parts: &[&[u8]]) -> (Vec<u8>, Pkcs1v15Sign) {
+    let mut hasher = D::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    (hasher.finalize().to_vec(), Pkcs1v15Sign::new::<D>())
+}
+
+/// The digest of `parts` with a signature's digest algorithm, and the RSA scheme it goes with.
+fn digest(algorithm: ObjectIdentifier, parts: &[&[u8]]) -> Result<(Vec<u8>, Pkcs1v15Sign), String> {
+    match algorithm {
+        oid if oid == rfc5912::ID_SHA_256 => Ok(hash::<Sha256>(parts)),
+        oid if oid == rfc5912::ID_SHA_384 => Ok(hash::<Sha384>(parts)),
+        oid if oid == rfc5912::ID_SHA_512 => Ok(hash::<Sha512>(parts)),
+        oid => Err(format!("The digest algorithm {} is not supported", oid)),
+    }
+}
+
+fn check_signature(pdf: &[u8], range: [usize; 4]) -> Result<SignatureReport, String> {
+    let [a, b, c, d] = range;
+    if a + b > c || c.checked_add(d).filter(|&end| end <= pdf.len()).is_none() {
+        return Err("The signature's byte range lies outside the file".to_string());
+    }
+    let hex_text: Vec<u8> = pdf[a + b..c].iter().copied().filter(|byte| byte.is_ascii_hexdigit()).collect();
+    let der = hex::decode(&hex_text).map_err(|e| e.to_string())?;
+    // The hex string is padded with zeros past the end of the signature
+    let mut reader = SliceReader::new(&der).map_err(|e| e.to_string())?;
+    let content_info = ContentInfo::decode(&mut reader).map_err(|e| format!("The signature cannot be read: {}", e))?;
+    let signed_data: SignedData = content_info.content.decode_as().map_err(|e| e.to_string())?;
+    let signer_info = signed_data.signer_infos.0.get(0).ok_or("The signature has no signer")?;
+
+    let certificates: Vec<&Certificate> = signed_data
+        .certificates
+        .as_ref()
+        .map(|set| {
+            set.0
+                .iter()
+                .filter_map(|choice| match choice {
+                    CertificateChoices::Certificate(cert) => Some(cert),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let certificate = match &signer_info.sid {
+        SignerIdentifier::IssuerAndSerialNumber(id) => certificates.iter().find(|cert| {
+            cert.tbs_certificate.issuer == id.issuer && cert.tbs_certificate.serial_number == id.serial_number
+        }),
+        SignerIdentifier::SubjectKeyIdentifier(_) => certificates.first(),
+    }
+    .ok_or("The signing certificate is not included in the signature")?;
+    let tbs = &certificate.tbs_certificate;
+
+    // The signing-time attribute is covered by the signature; /M beside it is not
+    let signed_time = signer_info.signed_attrs.as_ref().and_then(|attributes| {
+        let attribute = attributes.iter().find(|attr| attr.oid == rfc5911::ID_SIGNING_TIME)?;
+        x509_cert::time::Time::from_der(&attribute.values.get(0)?.to_der().ok()?).ok()
+    });
+    let claimed_at = dictionary_string(pdf, c, "M");
+    let signing_time = match &signed_time {
+        Some(time) => Some(certificate_time(time)),
+        None => claimed_at.as_deref().and_then(parse_pdf_date).map(|t| t.with_timezone(&Utc)),
+    };
+    let mut report = SignatureReport {
+        signer: common_name(&tbs.subject),
+        issuer: common_name(&tbs.issuer),
+        signed_at: signing_time.map(|t| t.to_rfc3339()).or(claimed_at),
+        signed_time_in_signature: signed_time.is_some(),
+        reason: dictionary_string(pdf, c, "Reason"),
+        location: dictionary_string(pdf, c, "Location"),
+        covers_whole_file: a == 0 && c + d == pdf.len(),
+        intact: false,
+        certificate_valid: false,
+        problem: None,
+    };
+
+    let signing_time = signing_time.unwrap_or_else(Utc::now);
+    report.certificate_valid = certificate_time(&tbs.validity.not_before) <= signing_time
+        && signing_time <= certificate_time(&tbs.validity.not_after);
+
+    let signed = [&pdf[a..a + b], &pdf[c..c + d]];
+    let (content_digest, scheme) = match digest(signer_info.digest_alg.oid, &signed) {
+        Ok(result) => result,
+        Err(e) => {
+            report.problem = Some(e);
+            return Ok(report);
+        }
+    };
+    // With signed attributes the signature is over them, and they hold the content's digest
+    let signed_digest = match &signer_info.signed_attrs {
+        Some(attributes) => {
+            let message_digest = attributes
+                .iter()
+                .find(|attr| attr.oid == rfc5911::ID_MESSAGE_DIGEST)
+                .and_then(|attr| attr.values.get(0))
+                .and_then(|value| value.decode_as::<OctetString>().ok());
+            if message_digest.as_ref().map(OctetString::as_bytes) != Some(content_digest.as_slice()) {
+                report.problem = Some("The document was changed after it was signed.".to_string());
+                return Ok(report);
+            }
+            digest(signer_info.digest_alg.oid, &[&attributes.to_der().map_err(|e| e.to_string())?])?.0
+        }
+        None => content_digest,
+    };
+    let public_key = RsaPublicKey::from_public_key_der(&tbs.subject_public_key_info.to_der().map_err(|e| e.to_string())?)
+        .map_err(|_| "Only signatures made with an RSA key can be checked".to_string())?;
+    report.intact = public_key.verify(scheme, &signed_digest, signer_info.signature.as_bytes()).is_ok();
+
+    report.problem = if !report.intact {
+        Some("The signature does not match the document.".to_string())
+    } else if !report.covers_whole_file {
+        Some("The document was changed after it was signed.".to_string())
+    } else if !report.certificate_valid {
+        Some("The certificate was not valid when the document was signed.".to_string())
+    } else {
+        None
+    };
+    Ok(report)
+}
+
+/// Checks every signature in a PDF. Whether the issuing CA is trusted is not checked; that
+/// takes the CA's root certificate, which a PDF reader such as Adobe Reader has.
+pub fn verify(pdf: &[u8]) -> Result<Vec<SignatureReport>, String> {
+    let pattern = Regex::new(r"/ByteRange\s*\[\s*(\d+)\s+(\d+)\s+(\d+)\s+(\d+)\s*\]").map_err(|e| e.to_string())?;
+    pattern
+        .captures_iter(pdf)
+        .map(|captures| {
+            let mut range = [0usize; 4];
+            for (i, value) in range.iter_mut().enumerate() {
+                *value = std::str::from_utf8(&captures[i + 1]).ok().and_then(|v| v.parse().ok()).ok_or("Invalid byte range")?;
+            }
+            check_signature(pdf, range)
+        })
+        .collect()
+}
+
+/// Stores the certificate password in the vault; an empty password removes it.
+#[tauri::command]
+pub async fn save_signing_password(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>, password: String) -> Result<(), String> {
+    if !vault::exists(&device::config_dir(&app_handle)?) {
+        return Err("Set up the vault before storing the certificate password in it.".to_string());
+    }
+    if password.is_empty() {
+        vault.remove(vault::SIGNING_PASSWORD)
+    } else {
+        vault.put(vault::SIGNING_PASSWORD, password.into_bytes())
+    }
+}
+
+/// Opens the configured certificate with the saved password, to check both.
+#[tauri::command]
+pub async fn get_signing_certificate(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>) -> Result<CertificateInfo, String> {
+    let settings = load_settings(&db::open(&app_handle)?)?;
+    let signer = configured_signer(&settings, &vault, &device::config_dir(&app_handle)?)?;
+    Ok(signer.info())
+}
+
+/// Signs a PDF in the app's `generated` folder in place.
+#[tauri::command]
+pub async fn sign_invoice_pdf(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    file_name: String,
+    block: SignatureBlock,
+) -> Result<(), String> {
+    let settings = load_settings(&db::open(&app_handle)?)?;
+    let signer = configured_signer(&settings, &vault, &device::config_dir(&app_handle)?)?;
+    let path = bundle::generated_file(&app_handle, &file_name)?;
+    let pdf = fs::read(&path).map_err(|e| e.to_string())?;
+    let signed = tauri::async_runtime::spawn_blocking(move || sign(&pdf, &signer, block))
+        .await
+        .map_err(|e| e.to_string())??;
+    fs::write(&path, signed).map_err(|e| e.to_string())?;
+    log::info!("Signed {}", file_name);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn verify_pdf_signature(path: String) -> Result<Vec<SignatureReport>, String> {
+    let pdf = fs::read(&path).map_err(|e| e.to_string())?;
+    verify(&pdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p12_keystore::{KeyStoreEntry, PrivateKeyChain};
+    use rsa::pkcs8::EncodePrivateKey;
+    use std::str::FromStr;
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    use x509_cert::builder::{Builder, CertificateBuilder, Profile};
+    use x509_cert::serial_number::SerialNumber;
+    use x509_cert::spki::SubjectPublicKeyInfoOwned;
+    use x509_cert::time::Validity;
+
+    const BLOCK: SignatureBlock = SignatureBlock { x: 120.0, y: 240.0, width: 70.0, height: 25.0 };
+
+    fn key() -> &'static RsaPrivateKey {
+        static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+        KEY.get_or_init(|| RsaPrivateKey::new(&mut rand_core::OsRng, 1024).unwrap())
+    }
+
+    fn certificate(serial: u32) -> Certificate {
+        let signing_key = SigningKey::<Sha256>::new(key().clone());
+        let public_key = SubjectPublicKeyInfoOwned::from_key(key().to_public_key()).unwrap();
+        let validity = Validity::from_now(Duration::from_secs(365 * 24 * 60 * 60)).unwrap();
+        let subject = Name::from_str(&format!("CN=Test Signer {},O=Apex Solar,C=IN", serial)).unwrap();
+        CertificateBuilder::new(Profile::Root, SerialNumber::from(serial), validity, subject, public_key, &signing_key)
+            .unwrap()
+            .build::<rsa::pkcs1v15::Signature>()
+            .unwrap()
+    }
+
+    /// A PKCS#12 file holding the test key and a self-signed certificate for it.
+    fn pkcs12(password: &str) -> Vec<u8> {
+        let certificate = p12_keystore::Certificate::from_der(&certificate(1).to_der().unwrap()).unwrap();
+        let key = key().to_pkcs8_der().unwrap();
+        let mut store = KeyStore::new();
+        store.add_entry("signer", KeyStoreEntry::PrivateKeyChain(PrivateKeyChain::new(key.as_bytes(), [1], [certificate])));
+        store.writer(password).write().unwrap()
+    }
+
+    fn signer() -> Signer {
+        let settings = SigningSettings { reason: "Invoice".to_string(), location: "Kolkata".to_string(), ..Default::default() };
+        Signer::from_pkcs12(&pkcs12("secret"), "secret", &settings).unwrap()
+    }
+
+    fn blank_pdf() -> Vec<u8> {
+        let mut doc = Document::with_version("1.7");
+        let pages = doc.new_object_id();
+        let content = doc.add_object(Stream::new(dictionary! {}, b"BT /F1 12 Tf 72 720 Td (Invoice) Tj ET".to_vec()));
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Contents" => content,
+        });
+        doc.objects.insert(pages, Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => vec![page.into()], "Count" => 1 }));
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages });
+        doc.trailer.set("Root", catalog);
+        let mut out = Vec::new();
+        doc.save_to(&mut out).unwrap();
+        out
+    }
+
+    fn byte_range(pdf: &[u8]) -> [usize; 4] {
+        let captures = Regex::new(r"/ByteRange\s*\[(\d+) (\d+) (\d+) (\d+)").unwrap().captures(pdf).unwrap();
+        [1, 2, 3, 4].map(|i| std::str::from_utf8(&captures[i]).unwrap().parse().unwrap())
+    }
+
+    #[test]
+    fn a_signed_pdf_verifies_over_the_whole_file() {
+        let signed = sign(&blank_pdf(), &signer(), BLOCK).unwrap();
+        assert!(is_signed(&signed));
+        let reports = verify(&signed).unwrap();
+        assert_eq!(reports.len(), 1);
+        let report = &reports[0];
+        assert!(report.intact && report.covers_whole_file, "{:?}", report);
+        assert!(report.certificate_valid);
+        assert!(report.signed_time_in_signature);
+        assert_eq!(report.problem, None);
+        assert_eq!(report.signer, "Test Signer 1");
+        assert_eq!(report.reason.as_deref(), Some("Invoice"));
+        assert_eq!(report.location.as_deref(), Some("Kolkata"));
+        let signed_at = DateTime::parse_from_rfc3339(report.signed_at.as_deref().unwrap()).unwrap();
+        assert!((Utc::now() - signed_at.with_timezone(&Utc)).num_minutes().abs() < 5);
+
+        assert_eq!(sign(&signed, &signer(), BLOCK).unwrap_err(), "The PDF is already signed.");
+    }
+
+    #[test]
+    fn a_changed_byte_inside_the_signed_range_is_reported() {
+        let mut signed = sign(&blank_pdf(), &signer(), BLOCK).unwrap();
+        let [a, b, _, _] = byte_range(&signed);
+        let at = a + signed[a..a + b].windows(12).position(|w| w == b"(Invoice) Tj").unwrap();
+        signed[at + 1] = b'X';
+
+        let report = verify(&signed).unwrap().remove(0);
+        assert!(!report.intact);
+        assert_eq!(report.problem.as_deref(), Some("The document was changed after it was signed."));
+    }
+
+    #[test]
+    fn bytes_appended_after_signing_are_not_covered() {
+        let mut signed = sign(&blank_pdf(), &signer(), BLOCK).unwrap();
+        signed.extend_from_slice(b"\n% added later\n");
+
+        let report = verify(&signed).unwrap().remove(0);
+        assert!(report.intact);
+        assert!(!report.covers_whole_file);
+        assert_eq!(report.problem.as_deref(), Some("The document was changed after it was signed."));
+    }
+
+    #[test]
+    fn a_wrong_password_is_reported_clearly() {
+        let error = Signer::from_pkcs12(&pkcs12("secret"), "guess", &SigningSettings::default()).err().unwrap();
+        assert!(error.starts_with("Could not open the certificate. Check the password."), "{}", error);
+    }
+
+    #[test]
+    fn a_chain_too_large_for_the_placeholder_is_refused() {
+        // Every certificate in the chain goes into the signature
+        let mut signer = signer();
+        signer.chain.extend((2..=60).map(certificate));
+        let error = sign(&blank_pdf(), &signer, BLOCK).unwrap_err();
+        assert_eq!(error, "The signature is too large for the space set aside for it");
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{bundle, db, vault};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::process::Command;
+
+// Printing goes through the CUPS command line tools on Linux and macOS. On Windows, PowerShell
+// lists the printers from WMI and prints with the "printto" verb of the app registered for PDFs.
+
+/// Field of the company settings holding the print options remembered for the company.
+pub const PRINT_DEFAULTS: &str = "printing";
+const MAX_COPIES: u32 = 99;
+
+/// Every printer as JSON objects with `Name` and `Default`. WMI works on every Windows edition,
+/// unlike Get-Printer, which needs the PrintManagement module.
+const WINDOWS_LIST_PRINTERS: &str =
+    "Get-CimInstance -ClassName Win32_Printer | Select-Object Name, Default | ConvertTo-Json -Compress";
+/// Prints the file with the PDF app's "printto" verb, or "print" for the default printer. The
+/// file and printer come in through the environment so neither needs quoting.
+const WINDOWS_PRINT: &str = "$ErrorActionPreference = 'Stop'; \
+    if ($env:APEX_PRINTER) { Start-Process -FilePath $env:APEX_PRINT_FILE -Verb PrintTo -ArgumentList ('\"' + $env:APEX_PRINTER + '\"') -WindowStyle Hidden } \
+    else { Start-Process -FilePath $env:APEX_PRINT_FILE -Verb Print -WindowStyle Hidden }";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Printer {
+    pub name: String,
+    /// The system's default destination.
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrintOptions {
+    /// Empty for the system default printer.
+    pub printer: String,
+    pub copies: u32,
+    pub duplex: String,     // "none" | "long-edge" | "short-edge"
+    pub paper_size: String, // "A4" | "Letter" | "Legal"
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions { printer: String::new(), copies: 1, duplex: "none".to_string(), paper_size: "A4".to_string() }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrintJob {
+    /// Empty when the job went to the system default printer.
+    pub printer: String,
+    pub job_id: Option<String>,
+}
+
+/// Runs a command and returns what it printed. `missing` is the error when it is not installed.
+fn output(command: &mut Command, missing: &str) -> Result<String, String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command.output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => missing.to_string(),
+        _ => e.to_string(),
+    })?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if output.status.success() {
+        return Ok(stdout);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(if stderr.is_empty() { format!("{} failed with {}", program, output.status) } else { stderr })
+}
+
+/// Runs a CUPS tool.
+fn run(program: &str, args: &[OsString]) -> Result<String, String> {
+    output(Command::new(program).args(args), &format!("{} was not found. Install CUPS to print from the app.", program))
+}
+
+/// Runs a PowerShell script without a console window, with `env` added to its environment.
+fn powershell(script: &str, env: &[(&str, &OsStr)]) -> Result<String, String> {
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", script]).envs(env.iter().copied());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    output(&mut command, "PowerShell was not found, so the app cannot reach the printers.")
+}
+
+/// Printer names from `lpstat -e`, one per line.
+pub fn parse_printers(output: &str) -> Vec<String> {
+    output.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect()
+}
+
+/// The destination named by `lpstat -d`, e.g. `system default destination: Office_Laser`.
+pub fn parse_default(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.split_once("default destination:"))
+        .map(|(_, name)| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// The job from `lp`'s `request id is Office_Laser-42 (1 file(s))`.
+pub fn parse_job_id(output: &str) -> Option<String> {
+    let rest = output.split_once("request id is ")?.1;
+    rest.split_whitespace().next().map(str::to_string)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct WmiPrinter {
+    name: String,
+    default: Option<bool>,
+}
+
+/// Printers from [`WINDOWS_LIST_PRINTERS`]. ConvertTo-Json writes a single printer as a lone
+/// object and none at all as nothing.
+pub fn parse_windows_printers(output: &str) -> Result<Vec<Printer>, String> {
+    let output = output.trim();
+    let printers: Vec<WmiPrinter> = if output.is_empty() {
+        Vec::new()
+    } else if output.starts_with('[') {
+        serde_json::from_str(output).map_err(|e| format!("Unexpected printer list: {}", e))?
+    } else {
+        vec![serde_json::from_str(output).map_err(|e| format!("Unexpected printer list: {}", e))?]
+    };
+    Ok(printers.into_iter().map(|printer| Printer { name: printer.name, is_default: printer.default == Some(true) }).collect())
+}
+
+pub fn list_printers() -> Result<Vec<Printer>, String> {
+    if cfg!(windows) {
+        return parse_windows_printers(&powershell(WINDOWS_LIST_PRINTERS, &[])?);
+    }
+    let names = match run("lpstat", &["-e".into()]) {
+        Ok(output) => parse_printers(&output),
+        // Older CUPS prints this, with an error status, rather than an empty list
+        Err(e) if e.contains("No destinations added") => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let default = run("lpstat", &["-d".into()]).ok().and_then(|output| parse_default(&output));
+    Ok(names.into_iter().map(|name| Printer { is_default: default.as_deref() == Some(name.as_str()), name }).collect())
+}
+
+/// Checks the copies, duplex and paper size, and returns the CUPS `sides` value for the duplex.
+fn check_options(options: &PrintOptions) -> Result<&'static str, String> {
+    if options.copies == 0 || options.copies > MAX_COPIES {
+        return Err(format!("Copies must be between 1 and {}.", MAX_COPIES));
+    }
+    let sides = match options.duplex.as_str() {
+        "none" => "one-sided",
+        "long-edge" => "two-sided-long-edge",
+        "short-edge" => "two-sided-short-edge",
+        other => return Err(format!("Unknown duplex setting: {}", other)),
+    };
+    if !["A4", "Letter", "Legal"].contains(&options.paper_size.as_str()) {
+        return Err(format!("Unknown paper size: {}", options.paper_size));
+    }
+    Ok(sides)
+}
+
+/// Arguments for `lp` printing `path` with these options. The invoice is scaled to fit other
+/// paper sizes than A4.
+pub fn lp_args(path: &Path, title: &str, options: &PrintOptions) -> Result<Vec<OsString>, String> {
+    let sides = check_options(options)?;
+    let mut args: Vec<OsString> = Vec::new();
+    if !options.printer.is_empty() {
+        args.extend(["-d".into(), options.printer.clone().into()]);
+    }
+    args.extend([
+        "-n".into(),
+        options.copies.to_string().into(),
+        "-o".into(),
+        format!("sides={}", sides).into(),
+        "-o".into(),
+        format!("media={}", options.paper_size).into(),
+        "-o".into(),
+        "fit-to-page".into(),
+        "-t".into(),
+        title.into(),
+        "--".into(),
+        path.as_os_str().to_owned(),
+    ]);
+    Ok(args)
+}
+
+/// Sends a PDF to the printer and returns the CUPS job. Windows has no job to report.
+pub fn print_pdf(path: &Path, title: &str, options: &PrintOptions) -> Result<PrintJob, String> {
+    if cfg!(windows) {
+        return print_with_shell(path, options);
+    }
+    let output = run("lp", &lp_args(path, title, options)?)?;
+    Ok(PrintJob { printer: options.printer.clone(), job_id: parse_job_id(&output) })
+}
+
+/// Prints once per copy through the PDF app's print verb, which takes no other options:
+/// two-sided printing and the paper size follow the printer's own settings.
+fn print_with_shell(path: &Path, options: &PrintOptions) -> Result<PrintJob, String> {
+    check_options(options)?;
+    if options.duplex != "none" || options.paper_size != "A4" {
+        log::warn!("Windows prints with the printer's own duplex and paper settings; ignoring {} on {}", options.duplex, options.paper_size);
+    }
+    let env = [("APEX_PRINT_FILE", path.as_os_str()), ("APEX_PRINTER", OsStr::new(&options.printer))];
+    for _ in 0..options.copies {
+        powershell(WINDOWS_PRINT, &env).map_err(|e| format!("Printing failed: {}. Check that a PDF app is set up to print.", e))?;
+    }
+    Ok(PrintJob { printer: options.printer.clone(), job_id: None })
+}
+
+/// The print options remembered in the company settings.
+pub fn saved_options(conn: &Connection) -> Result<PrintOptions, String> {
+    let saved = vault::company_settings(conn)?.and_then(|settings| settings.get(PRINT_DEFAULTS).cloned());
+    match saved {
+        Some(Value::Null) | None => Ok(PrintOptions::default()),
+        Some(value) => serde_json::from_value(value).map_err(|e| e.to_string()),
+    }
+}
+
+/// `list_printers` off the async runtime, since it waits on lpstat or PowerShell.
+async fn installed_printers() -> Result<Vec<Printer>, String> {
+    tauri::async_runtime::spawn_blocking(list_printers).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_printers() -> Result<Vec<Printer>, String> {
+    installed_printers().await
+}
+
+/// Prints a PDF in the app's `generated` folder. Without options, the company's remembered
+/// ones are used; a remembered printer that is no longer installed, or that cannot be looked up,
+/// gives way to the system default.
+#[tauri::command]
+pub async fn print_invoice_pdf(app_handle: tauri::AppHandle, file_name: String, options: Option<PrintOptions>) -> Result<PrintJob, String> {
+    let path = bundle::generated_file(&app_handle, &file_name)?;
+    let options = match options {
+        Some(options) => options,
+        None => {
+            let mut saved = saved_options(&db::open(&app_handle)?)?;
+            if !saved.printer.is_empty() {
+                match installed_printers().await {
+                    Ok(printers) if printers.iter().any(|printer| printer.name == saved.printer) => {}
+                    Ok(_) => {
+                        log::warn!("The remembered printer {} is not installed; using the system default", saved.printer);
+                        saved.printer.clear();
+                    }
+                    Err(e) => {
+                        log::warn!("Could not list the printers ({}); using the system default instead of {}", e, saved.printer);
+                        saved.printer.clear();
+                    }
+                }
+            }
+            saved
+        }
+    };
+    let job = tauri::async_runtime::spawn_blocking(move || print_pdf(&path, &file_name, &options))
+        .await
+        .map_err(|e| e.to_string())??;
+    let printer = if job.printer.is_empty() { "the default printer" } else { &job.printer };
+    log::info!("Printed {} on {}", job.job_id.as_deref().unwrap_or("a job"), printer);
+    Ok(job)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cups_output_is_parsed() {
+        assert_eq!(parse_printers("Office_Laser\n  Label_Printer \n\n"), vec!["Office_Laser", "Label_Printer"]);
+        assert_eq!(parse_default("system default destination: Office_Laser\n").as_deref(), Some("Office_Laser"));
+        assert_eq!(parse_default("no system default destination\n"), None);
+        assert_eq!(parse_job_id("request id is Office_Laser-42 (1 file(s))\n").as_deref(), Some("Office_Laser-42"));
+    }
+
+    #[test]
+    fn wmi_printers_are_parsed_however_many_there_are() {
+        let printers = parse_windows_printers(r#"[{"Name":"Microsoft Print to PDF","Default":false},{"Name":"HP LaserJet","Default":true}]"#).unwrap();
+        let listed: Vec<(&str, bool)> = printers.iter().map(|p| (p.name.as_str(), p.is_default)).collect();
+        assert_eq!(listed, vec![("Microsoft Print to PDF", false), ("HP LaserJet", true)]);
+
+        let single = parse_windows_printers("{\"Name\":\"HP LaserJet\",\"Default\":null}\r\n").unwrap();
+        assert_eq!((single[0].name.as_str(), single[0].is_default), ("HP LaserJet", false));
+        assert!(parse_windows_printers("  \r\n").unwrap().is_empty());
+        assert!(parse_windows_printers("Get-CimInstance : Access denied").is_err());
+    }
+
+    #[test]
+    fn lp_gets_the_chosen_options() {
+        let options = PrintOptions { printer: "Office_Laser".to_string(), copies: 2, duplex: "long-edge".to_string(), paper_size: "Legal".to_string() };
+        let args = lp_args(Path::new("/tmp/Invoice_AS_24-25_001.pdf"), "Invoice_AS_24-25_001.pdf", &options).unwrap();
+        let args: Vec<String> = args.iter().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(
+            args,
+            [
+                "-d", "Office_Laser", "-n", "2", "-o", "sides=two-sided-long-edge", "-o", "media=Legal", "-o", "fit-to-page", "-t",
+                "Invoice_AS_24-25_001.pdf", "--", "/tmp/Invoice_AS_24-25_001.pdf",
+            ]
+        );
+        let default_printer = lp_args(Path::new("a.pdf"), "a.pdf", &PrintOptions::default()).unwrap();
+        assert_eq!(default_printer[0], "-n");
+
+        for (bad, error) in [
+            (PrintOptions { copies: 0, ..PrintOptions::default() }, "Copies must be between 1 and 99."),
+            (PrintOptions { duplex: "both".to_string(), ..PrintOptions::default() }, "Unknown duplex setting: both"),
+            (PrintOptions { paper_size: "A3".to_string(), ..PrintOptions::default() }, "Unknown paper size: A3"),
+        ] {
+            assert_eq!(check_options(&bad).unwrap_err(), error);
+        }
+    }
+}
@@ -0,0 +1,565 @@
+use crate::bundle::{self, BundleManifest};
+use crate::{backup, crypto, db, migrations, vault};
+use chrono::Local;
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Folders next to the live database: the working area for restores, with a folder of its own
+/// for each call, and the copies of the database taken right before each restore.
+const STAGING_DIR: &str = "restore-staging";
+const ROLLBACK_DIR: &str = "restore-points";
+const ROLLBACK_POINTS_KEPT: usize = 5;
+/// How long to wait for the app's other connections to release the database before giving up.
+const SWAP_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUIRED_TABLES: [&str; 4] = ["settings", "customers", "invoices", "invoice_items"];
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffCounts {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub unchanged: usize,
+}
+
+/// What a restore would do, computed without touching the live database.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestorePreview {
+    pub source_kind: String, // "database" | "bundle"
+    pub encrypted: bool,
+    pub schema_version: i64,
+    pub current_schema_version: i64,
+    /// From the bundle manifest; plain database files carry no metadata.
+    pub created_at: Option<String>,
+    pub app_version: Option<String>,
+    pub invoices: DiffCounts,
+    pub customers: DiffCounts,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub preview: RestorePreview,
+    /// Copy of the database as it was before the restore.
+    pub rollback_point: Option<String>,
+    pub restored_pdfs: usize,
+    /// The backup's vault was put in place because this computer had none.
+    pub restored_vault: bool,
+    /// The backup predates the latest migration; the sql plugin only migrates on startup.
+    pub restart_required: bool,
+}
+
+/// A candidate database unpacked into the staging folder. The folder is removed on drop.
+pub(crate) struct Staged {
+    dir: PathBuf,
+    pub(crate) database: PathBuf,
+    generated: Option<PathBuf>,
+    vault: Option<PathBuf>,
+    manifest: Option<BundleManifest>,
+    encrypted: bool,
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A staging folder no other call is using: previews, restores and record listings can run at
+/// the same time.
+fn staging_dir(app_dir: &Path) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let name = format!("{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+    app_dir.join(STAGING_DIR).join(name)
+}
+
+/// Unpacks a backup (plain database, bundle or encrypted bundle) into `staging_dir`.
+/// Bundles are decrypted and verified in full here, before anything else happens.
+fn stage(source: &Path, passphrase: Option<&str>, staging_dir: &Path) -> Result<Staged, String> {
+    // Only left over from a run that crashed with the same process ID
+    if staging_dir.exists() {
+        fs::remove_dir_all(staging_dir).map_err(|e| e.to_string())?;
+    }
+    fs::create_dir_all(staging_dir).map_err(|e| e.to_string())?;
+    let mut staged = Staged {
+        dir: staging_dir.to_path_buf(),
+        database: staging_dir.join(db::DB_FILE_NAME),
+        generated: None,
+        vault: None,
+        manifest: None,
+        encrypted: false,
+    };
+
+    let mut source = source.to_path_buf();
+    if crypto::is_encrypted(&source) {
+        let passphrase = passphrase.ok_or(crypto::PASSPHRASE_REQUIRED)?;
+        // Plaintext stays in the app's own folder, never in the synced backup folder
+        let decrypted = staging_dir.join("backup.zip");
+        crypto::decrypt_file(&source, &decrypted, passphrase)?;
+        source = decrypted;
+        staged.encrypted = true;
+    }
+
+    if bundle::is_bundle(&source) {
+        let manifest = bundle::verify_bundle(&source)?;
+        bundle::extract_entry(&source, bundle::DATABASE_PATH, &staged.database)?;
+        let generated = staging_dir.join("generated");
+        bundle::extract_generated(&source, &manifest, &generated)?;
+        staged.generated = Some(generated);
+        let vault_dir = staging_dir.join("vault");
+        if bundle::extract_vault(&source, &manifest, &vault_dir)? > 0 {
+            staged.vault = Some(vault_dir);
+        }
+        staged.manifest = Some(manifest);
+    } else {
+        fs::copy(&source, &staged.database).map_err(|e| e.to_string())?;
+    }
+    Ok(staged)
+}
+
+/// Opens a candidate read-only and checks it is an intact invoice database this app can use.
+/// Returns its schema version.
+fn validate(path: &Path) -> Result<i64, String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Not a readable database: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
+        .map_err(|e| format!("Not a valid database: {}", e))?;
+    let problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("The backup failed the integrity check: {}", e))?;
+    if problems != ["ok"] {
+        let shown: Vec<_> = problems.into_iter().take(5).collect();
+        return Err(format!("The backup failed the integrity check: {}", shown.join("; ")));
+    }
+
+    for table in REQUIRED_TABLES {
+        if !has_table(&conn, table)? {
+            return Err(format!("The backup is not an invoice database (no {} table)", table));
+        }
+    }
+
+    let version = db::schema_version(&conn)?;
+    if version > migrations::latest_version() {
+        return Err(format!(
+            "This backup uses database schema v{}, newer than this app supports (v{}). Please update first.",
+            version,
+            migrations::latest_version()
+        ));
+    }
+    Ok(version)
+}
+
+fn has_table(conn: &Connection, table: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        [table],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn columns(conn: &Connection, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table)).map_err(|e| e.to_string())?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(names)
+}
+
+/// Fingerprints every row of `table` by key, over the given columns only.
+fn fingerprints(conn: &Connection, table: &str, key: &str, columns: &[String]) -> Result<HashMap<String, String>, String> {
+    let selected: Vec<&str> = std::iter::once(key).chain(columns.iter().map(String::as_str)).collect();
+    let sql = format!("SELECT {} FROM {}", selected.join(", "), table);
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    let mut result = HashMap::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let id: String = row.get(0).map_err(|e| e.to_string())?;
+        let mut fingerprint = String::new();
+        for i in 1..=columns.len() {
+            let value = match row.get_ref(i).map_err(|e| e.to_string())? {
+                ValueRef::Null => "\u{0}".to_string(),
+                ValueRef::Integer(v) => v.to_string(),
+                ValueRef::Real(v) => v.to_string(),
+                ValueRef::Text(v) | ValueRef::Blob(v) => String::from_utf8_lossy(v).to_string(),
+            };
+            fingerprint.push_str(&value);
+            fingerprint.push('\u{1}');
+        }
+        result.insert(id, fingerprint);
+    }
+    Ok(result)
+}
+
+/// Compares one table between the live database and a candidate. Only columns both schemas
+/// have are compared, so restoring an older backup doesn't mark every row as changed.
+fn diff_table(current: Option<&Connection>, candidate: &Connection, table: &str, key: &str) -> Result<DiffCounts, String> {
+    let (current_rows, candidate_rows) = match current {
+        Some(current) => {
+            let current_columns: HashSet<String> = columns(current, table)?.into_iter().collect();
+            let shared: Vec<String> = columns(candidate, table)?
+                .into_iter()
+                .filter(|c| current_columns.contains(c) && c != key && c != "created_at")
+                .collect();
+            (fingerprints(current, table, key, &shared)?, fingerprints(candidate, table, key, &shared)?)
+        }
+        None => (HashMap::new(), fingerprints(candidate, table, key, &[])?),
+    };
+
+    let mut counts = DiffCounts::default();
+    for (id, fingerprint) in &candidate_rows {
+        match current_rows.get(id) {
+            None => counts.added += 1,
+            Some(existing) if existing == fingerprint => counts.unchanged += 1,
+            Some(_) => counts.changed += 1,
+        }
+    }
+    counts.removed = current_rows.keys().filter(|id| !candidate_rows.contains_key(*id)).count();
+    Ok(counts)
+}
+
+fn build_preview(staged: &Staged, db_path: &Path) -> Result<RestorePreview, String> {
+    let schema_version = validate(&staged.database)?;
+    let candidate = Connection::open_with_flags(&staged.database, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    let current = if db_path.exists() { Some(db::open_path(db_path)?) } else { None };
+    let current_schema_version = match &current {
+        Some(conn) => db::schema_version(conn)?,
+        None => 0,
+    };
+
+    Ok(RestorePreview {
+        source_kind: if staged.manifest.is_some() { "bundle" } else { "database" }.to_string(),
+        encrypted: staged.encrypted,
+        schema_version,
+        current_schema_version,
+        created_at: staged.manifest.as_ref().map(|m| m.created_at.clone()),
+        app_version: staged.manifest.as_ref().map(|m| m.app_version.clone()),
+        invoices: diff_table(current.as_ref(), &candidate, "invoices", "invoice_number")?,
+        customers: diff_table(current.as_ref(), &candidate, "customers", "id")?,
+    })
+}
+
+/// Copies `source` over the live database through the backup API in a single step, so the
+/// swap happens in one write transaction and connections the sql plugin holds stay valid.
+fn swap_in(source: &Path, db_path: &Path) -> Result<(), String> {
+    let src = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(|e| e.to_string())?;
+    let mut dst = db::open_path(db_path)?;
+    let backup = Backup::new(&src, &mut dst).map_err(|e| e.to_string())?;
+    // run_to_completion only copies in batches, which would expose a half-swapped database
+    let deadline = Instant::now() + SWAP_TIMEOUT;
+    loop {
+        match backup.step(-1).map_err(|e| e.to_string())? {
+            StepResult::Done => return Ok(()),
+            StepResult::Busy | StepResult::Locked if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(50))
+            }
+            StepResult::Busy | StepResult::Locked => {
+                return Err("The database is busy. Close other windows and try again.".to_string())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Renames, falling back to copy and delete when the folders are on different volumes.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| e.to_string())?;
+    fs::remove_file(from).map_err(|e| e.to_string())
+}
+
+/// Puts restored PDFs in place after the database swap. The files they replace are set aside
+/// first, so a restore that fails afterwards can put the previous PDFs back with the database.
+struct PdfSwap {
+    set_aside_dir: PathBuf,
+    written: Vec<PathBuf>,
+    /// Where each replaced file was set aside, and where it came from.
+    replaced: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PdfSwap {
+    fn new(set_aside_dir: PathBuf) -> Self {
+        PdfSwap { set_aside_dir, written: Vec::new(), replaced: Vec::new() }
+    }
+
+    fn apply(&mut self, from: &Path, to: &Path) -> Result<usize, String> {
+        fs::create_dir_all(to).map_err(|e| e.to_string())?;
+        fs::create_dir_all(&self.set_aside_dir).map_err(|e| e.to_string())?;
+        for entry in fs::read_dir(from).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+            let target = to.join(name);
+            if target.exists() {
+                let aside = self.set_aside_dir.join(name);
+                move_file(&target, &aside)?;
+                self.replaced.push((aside, target.clone()));
+            }
+            self.written.push(target.clone());
+            fs::copy(&path, &target).map_err(|e| e.to_string())?;
+        }
+        Ok(self.written.len())
+    }
+
+    /// Removes the restored PDFs and puts back the ones they replaced.
+    fn undo(&self) {
+        for path in &self.written {
+            let _ = fs::remove_file(path);
+        }
+        for (aside, target) in &self.replaced {
+            if let Err(e) = move_file(aside, target) {
+                log::warn!("Could not put {} back after a failed restore: {}", target.display(), e);
+            }
+        }
+    }
+}
+
+/// Puts a backed-up vault in place when this computer has none, so bank details and Drive
+/// tokens come back with the data. An existing vault is never replaced.
+fn restore_vault(from: &Path, vault_dir: &Path) -> Result<bool, String> {
+    let files = vault::files(from);
+    if vault::exists(vault_dir) || files.len() < 2 {
+        return Ok(false);
+    }
+    fs::create_dir_all(vault_dir).map_err(|e| e.to_string())?;
+    for file in files {
+        let name = file.file_name().ok_or("Invalid vault file")?;
+        crypto::write_private_file(&vault_dir.join(name), &fs::read(&file).map_err(|e| e.to_string())?)?;
+    }
+    Ok(true)
+}
+
+/// Deletes all but the newest rollback points.
+fn prune_rollback_points(dir: &Path) -> Result<(), String> {
+    for (_, path) in backup::list_snapshots(dir)?.into_iter().skip(ROLLBACK_POINTS_KEPT) {
+        fs::remove_file(path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn app_dir(db_path: &Path) -> Result<&Path, String> {
+    db_path.parent().ok_or_else(|| "Invalid database path".to_string())
+}
+
+/// Unpacks and validates a backup for reading individual records out of it (see selective_restore.rs).
+pub(crate) fn open_backup(source: &Path, passphrase: Option<&str>, db_path: &Path) -> Result<Staged, String> {
+    let staged = stage(source, passphrase, &staging_dir(app_dir(db_path)?))?;
+    validate(&staged.database)?;
+    Ok(staged)
+}
+
+/// Dry run: unpacks and validates a backup and reports how it differs from the live database.
+pub fn preview(source: &Path, passphrase: Option<&str>, db_path: &Path) -> Result<RestorePreview, String> {
+    let staged = stage(source, passphrase, &staging_dir(app_dir(db_path)?))?;
+    build_preview(&staged, db_path)
+}
+
+/// Restores a backup over the live database. The current database is saved as a rollback point
+/// first; if the swap or anything after it fails, the rollback point and the previous PDFs are
+/// put back.
+pub fn restore(
+    source: &Path,
+    passphrase: Option<&str>,
+    db_path: &Path,
+    generated_dir: &Path,
+    vault_dir: &Path,
+) -> Result<RestoreReport, String> {
+    let app_dir = app_dir(db_path)?;
+    let staged = stage(source, passphrase, &staging_dir(app_dir))?;
+    let preview = build_preview(&staged, db_path)?;
+
+    let rollback_point = if db_path.exists() {
+        let dir = app_dir.join(ROLLBACK_DIR);
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let path = dir.join(backup::snapshot_file_name(Local::now().naive_local(), None, backup::SNAPSHOT_SUFFIX));
+        backup::snapshot_database(db_path, &path)?;
+        Some(path)
+    } else {
+        None
+    };
+
+    let mut pdfs = PdfSwap::new(staged.dir.join("replaced"));
+    let applied = swap_in(&staged.database, db_path)
+        .and_then(|_| validate(db_path).map(|_| ()))
+        .and_then(|_| match &staged.generated {
+            Some(generated) => pdfs.apply(generated, generated_dir),
+            None => Ok(0),
+        });
+
+    let restored_pdfs = match applied {
+        Ok(count) => count,
+        Err(e) => {
+            pdfs.undo();
+            let Some(rollback_point) = &rollback_point else {
+                return Err(format!("Restore failed: {}", e));
+            };
+            return match swap_in(rollback_point, db_path) {
+                Ok(()) => Err(format!("Restore failed and your previous data was put back: {}", e)),
+                Err(rollback_error) => Err(format!(
+                    "Restore failed ({}) and the automatic rollback failed too ({}). Your previous database is saved at {}",
+                    e,
+                    rollback_error,
+                    rollback_point.display()
+                )),
+            };
+        }
+    };
+
+    // The data is restored at this point; a vault that cannot be copied is not worth undoing that
+    let restored_vault = match &staged.vault {
+        Some(dir) => restore_vault(dir, vault_dir).unwrap_or_else(|e| {
+            log::warn!("Could not restore the vault from the backup: {}", e);
+            false
+        }),
+        None => false,
+    };
+
+    prune_rollback_points(&app_dir.join(ROLLBACK_DIR))?;
+    Ok(RestoreReport {
+        restart_required: preview.schema_version < migrations::latest_version(),
+        preview,
+        rollback_point: rollback_point.map(|p| p.to_string_lossy().to_string()),
+        restored_pdfs,
+        restored_vault,
+    })
+}
+
+#[tauri::command]
+pub async fn preview_restore(app_handle: tauri::AppHandle, source_path: String, passphrase: Option<String>) -> Result<RestorePreview, String> {
+    let source = Path::new(&source_path);
+    if !source.exists() {
+        return Err("Source file not found".to_string());
+    }
+    preview(source, passphrase.as_deref(), &db::db_path(&app_handle)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::RetentionPolicy;
+    use crate::device::Device;
+    use crate::test_support::{insert_customer, migrated_db_at};
+
+    struct Fixture {
+        dir: tempfile::TempDir,
+        bundle: PathBuf,
+        db_path: PathBuf,
+        generated: PathBuf,
+    }
+
+    /// A backup holding customer "backed-up" and Invoice_001.pdf, and a live install holding
+    /// customer "live" and an older Invoice_001.pdf.
+    fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let backed_up = dir.path().join("source");
+        fs::create_dir_all(backed_up.join("generated")).unwrap();
+        insert_customer(&migrated_db_at(&backed_up.join("invoices.db")), "backed-up", "Backed Up Ltd");
+        fs::write(backed_up.join("generated").join("Invoice_001.pdf"), b"restored").unwrap();
+        let device = Device { id: "1a2b3c4d5e6f7a8b".to_string(), name: "Office".to_string() };
+        let policy = RetentionPolicy { daily: 0, weekly: 0, monthly: 0 };
+        let result = bundle::create_bundle(
+            &backed_up.join("invoices.db"),
+            &backed_up.join("generated"),
+            &[],
+            &dir.path().join("backups"),
+            &policy,
+            None,
+            &device,
+        )
+        .unwrap();
+
+        let live = dir.path().join("live");
+        let generated = live.join("generated");
+        fs::create_dir_all(&generated).unwrap();
+        let db_path = live.join("invoices.db");
+        insert_customer(&migrated_db_at(&db_path), "live", "Live Ltd");
+        fs::write(generated.join("Invoice_001.pdf"), b"previous").unwrap();
+        Fixture { bundle: PathBuf::from(result.path), db_path, generated, dir }
+    }
+
+    fn customer_ids(db_path: &Path) -> Vec<String> {
+        let conn = db::open_path(db_path).unwrap();
+        let mut stmt = conn.prepare("SELECT id FROM customers ORDER BY id").unwrap();
+        let ids = stmt.query_map([], |row| row.get(0)).unwrap().collect::<Result<Vec<String>, _>>().unwrap();
+        ids
+    }
+
+    #[test]
+    fn restore_swaps_database_and_pdfs() {
+        let f = fixture();
+        let report = restore(&f.bundle, None, &f.db_path, &f.generated, &f.dir.path().join("config")).unwrap();
+        assert_eq!(report.restored_pdfs, 1);
+        assert_eq!(report.preview.customers.added, 1);
+        assert_eq!(report.preview.customers.removed, 1);
+        assert!(report.rollback_point.is_some_and(|p| Path::new(&p).exists()));
+        assert_eq!(customer_ids(&f.db_path), ["backed-up"]);
+        assert_eq!(fs::read(f.generated.join("Invoice_001.pdf")).unwrap(), b"restored");
+        // Nothing is left behind in the staging area
+        let staging = f.db_path.parent().unwrap().join(STAGING_DIR);
+        assert_eq!(fs::read_dir(staging).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn concurrent_calls_stage_separately() {
+        let f = fixture();
+        let first = open_backup(&f.bundle, None, &f.db_path).unwrap();
+        let second = open_backup(&f.bundle, None, &f.db_path).unwrap();
+        assert_ne!(first.dir, second.dir);
+        // A preview running meanwhile does not disturb either
+        preview(&f.bundle, None, &f.db_path).unwrap();
+        drop(first);
+        assert!(second.database.exists());
+        assert_eq!(customer_ids(&second.database), ["backed-up"]);
+    }
+
+    #[test]
+    fn failed_pdf_swap_puts_previous_pdfs_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to, aside) = (dir.path().join("from"), dir.path().join("to"), dir.path().join("aside"));
+        for folder in [&from, &to] {
+            fs::create_dir_all(folder).unwrap();
+        }
+        for name in ["a.pdf", "b.pdf"] {
+            fs::write(from.join(name), b"restored").unwrap();
+            fs::write(to.join(name), b"previous").unwrap();
+        }
+        // b.pdf cannot be set aside, so the swap fails whichever file comes first
+        fs::create_dir_all(aside.join("b.pdf").join("blocked")).unwrap();
+
+        let mut swap = PdfSwap::new(aside);
+        assert!(swap.apply(&from, &to).is_err());
+        swap.undo();
+        for name in ["a.pdf", "b.pdf"] {
+            assert_eq!(fs::read(to.join(name)).unwrap(), b"previous", "{}", name);
+        }
+    }
+
+    #[test]
+    fn undo_removes_new_pdfs() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::create_dir_all(&from).unwrap();
+        fs::write(from.join("new.pdf"), b"restored").unwrap();
+        let mut swap = PdfSwap::new(dir.path().join("aside"));
+        assert_eq!(swap.apply(&from, &to).unwrap(), 1);
+        swap.undo();
+        assert!(!to.join("new.pdf").exists());
+    }
+}
@@ -0,0 +1,641 @@
+use crate::models::Invoice;
+use crate::{db, invoices, restore};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Schema name the backup is attached under.
+const BACKUP_SCHEMA: &str = "backup";
+const RESTORE_REASON: &str = "Restored from backup";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInvoice {
+    pub invoice_number: String,
+    pub invoice_date: Option<String>,
+    pub customer_name: Option<String>,
+    pub grand_total: Option<f64>,
+    pub status: Option<String>,
+    pub conflict: String, // "none" | "identical" | "different"
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupCustomer {
+    pub id: String,
+    pub company_name: Option<String>,
+    pub gst_number: Option<String>,
+    pub conflict: String, // "none" | "identical" | "different"
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupRecords {
+    pub invoices: Vec<BackupInvoice>,
+    pub customers: Vec<BackupCustomer>,
+}
+
+/// An invoice to copy out of the backup. `on_conflict` is required when the live database
+/// already has a different invoice under the same number: "skip", "replace" or "renumber"
+/// (with `new_number`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceChoice {
+    pub invoice_number: String,
+    pub on_conflict: Option<String>,
+    pub new_number: Option<String>,
+}
+
+/// A customer to copy out of the backup. `on_conflict` is "skip" or "replace".
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomerChoice {
+    pub id: String,
+    pub on_conflict: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordSelection {
+    #[serde(default)]
+    pub invoices: Vec<InvoiceChoice>,
+    #[serde(default)]
+    pub customers: Vec<CustomerChoice>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectiveRestoreReport {
+    pub invoices_restored: usize,
+    pub invoices_replaced: usize,
+    pub invoices_renumbered: usize,
+    pub invoices_skipped: usize,
+    /// Includes customers copied because a restored invoice refers to them.
+    pub customers_restored: usize,
+    pub customers_replaced: usize,
+    pub customers_skipped: usize,
+}
+
+/// Attaches a database file read-only under the `backup` schema.
+pub fn attach(conn: &Connection, path: &Path) -> Result<(), String> {
+    // URI form so the backup can be opened read-only; escape the characters URIs reserve
+    let escaped = path.to_string_lossy().replace('%', "%25").replace('?', "%3f").replace('#', "%23");
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {}", BACKUP_SCHEMA),
+        params![format!("file:{}?mode=ro", escaped)],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn table_columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA {}.table_info({})", schema, table))
+        .map_err(|e| e.to_string())?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(names)
+}
+
+/// Columns a table has in both the live database and the backup, which may be on an older schema.
+fn shared_columns(conn: &Connection, table: &str) -> Result<Vec<String>, String> {
+    let backup: HashSet<String> = table_columns(conn, BACKUP_SCHEMA, table)?.into_iter().collect();
+    Ok(table_columns(conn, "main", table)?
+        .into_iter()
+        .filter(|c| backup.contains(c))
+        .collect())
+}
+
+// Baseline columns every schema version has, so these comparisons work against old backups too
+const INVOICE_COMPARE: &str = "l.json_data IS b.json_data AND l.status IS b.status AND l.grand_total IS b.grand_total";
+const CUSTOMER_COMPARE: &str = "l.company_name IS b.company_name AND l.gst_number IS b.gst_number AND l.pan_number IS b.pan_number
+     AND l.address_line1 IS b.address_line1 AND l.address_line2 IS b.address_line2 AND l.address_line3 IS b.address_line3
+     AND l.city IS b.city AND l.state IS b.state AND l.pincode IS b.pincode";
+
+fn conflict_sql(table: &str, key: &str, compare: &str) -> String {
+    format!(
+        "CASE WHEN NOT EXISTS(SELECT 1 FROM main.{table} l WHERE l.{key} = b.{key}) THEN 'none'
+              WHEN EXISTS(SELECT 1 FROM main.{table} l WHERE l.{key} = b.{key} AND {compare}) THEN 'identical'
+              ELSE 'different' END",
+        table = table,
+        key = key,
+        compare = compare
+    )
+}
+
+/// Lists the invoices and customers in an attached backup, with how each compares to the live row.
+pub fn list(conn: &Connection) -> Result<BackupRecords, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT b.invoice_number, b.invoice_date, c.company_name, b.grand_total, b.status, {}
+             FROM backup.invoices b LEFT JOIN backup.customers c ON c.id = b.customer_id
+             ORDER BY b.invoice_date DESC, b.invoice_number DESC",
+            conflict_sql("invoices", "invoice_number", INVOICE_COMPARE)
+        ))
+        .map_err(|e| e.to_string())?;
+    let invoices = stmt
+        .query_map([], |row| {
+            Ok(BackupInvoice {
+                invoice_number: row.get(0)?,
+                invoice_date: row.get(1)?,
+                customer_name: row.get(2)?,
+                grand_total: row.get(3)?,
+                status: row.get(4)?,
+                conflict: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT b.id, b.company_name, b.gst_number, {} FROM backup.customers b ORDER BY b.company_name",
+            conflict_sql("customers", "id", CUSTOMER_COMPARE)
+        ))
+        .map_err(|e| e.to_string())?;
+    let customers = stmt
+        .query_map([], |row| {
+            Ok(BackupCustomer {
+                id: row.get(0)?,
+                company_name: row.get(1)?,
+                gst_number: row.get(2)?,
+                conflict: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(BackupRecords { invoices, customers })
+}
+
+fn conflict(tx: &Transaction, table: &str, key: &str, compare: &str, id: &str) -> Result<Option<String>, String> {
+    tx.query_row(
+        &format!("SELECT {} FROM backup.{} b WHERE b.{} = ?1", conflict_sql(table, key, compare), table, key),
+        params![id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn copy_customer(tx: &Transaction, id: &str) -> Result<(), String> {
+    let columns = shared_columns(tx, "customers")?.join(", ");
+    tx.execute(
+        &format!("INSERT INTO main.customers({0}) SELECT {0} FROM backup.customers WHERE id = ?1", columns),
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn replace_customer(tx: &Transaction, id: &str) -> Result<(), String> {
+    let columns: Vec<String> = shared_columns(tx, "customers")?
+        .into_iter()
+        .filter(|c| c != "id" && c != "created_at")
+        .collect();
+    tx.execute(
+        &format!(
+            "UPDATE main.customers SET ({0}) = (SELECT {0} FROM backup.customers WHERE id = ?1) WHERE id = ?1",
+            columns.join(", ")
+        ),
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Copies the backup customer an invoice refers to when the live database doesn't have it,
+/// so the invoice's foreign key holds. Returns whether a customer was copied.
+fn ensure_customer(tx: &Transaction, invoice_number: &str) -> Result<bool, String> {
+    let missing: Option<String> = tx
+        .query_row(
+            "SELECT b.customer_id FROM backup.invoices b
+             WHERE b.invoice_number = ?1 AND b.customer_id IS NOT NULL
+             AND NOT EXISTS(SELECT 1 FROM main.customers c WHERE c.id = b.customer_id)
+             AND EXISTS(SELECT 1 FROM backup.customers c WHERE c.id = b.customer_id)",
+            params![invoice_number],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match missing {
+        Some(id) => copy_customer(tx, &id).map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// Copies an invoice with its items and revision history from the backup, as `target` (its own
+/// number unless renumbering), and locks it again if it had been issued.
+fn copy_invoice(tx: &Transaction, invoice_number: &str, target: &str) -> Result<(), String> {
+    // json_data carries the number too; only rewritten when renumbering
+    let json = "CASE WHEN ?1 = ?2 THEN json_data ELSE json_set(json_data, '$.invoiceNumber', ?2, '$.id', ?2) END";
+
+    let columns = shared_columns(tx, "invoices")?;
+    let values: Vec<String> = columns
+        .iter()
+        .map(|c| match c.as_str() {
+            "invoice_number" => "?2".to_string(),
+            "json_data" => json.to_string(),
+            // Items can only be written while unlocked
+            "locked" => "0".to_string(),
+            _ => c.clone(),
+        })
+        .collect();
+    tx.execute(
+        &format!(
+            "INSERT INTO main.invoices({}) SELECT {} FROM backup.invoices WHERE invoice_number = ?1",
+            columns.join(", "),
+            values.join(", ")
+        ),
+        params![invoice_number, target],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let columns = shared_columns(tx, "invoice_items")?;
+    let values: Vec<String> = columns
+        .iter()
+        .map(|c| match c.as_str() {
+            "invoice_number" => "?2".to_string(),
+            // Item ids are primary keys; keep them unique when the same invoice is restored twice
+            "id" => "CASE WHEN ?1 = ?2 THEN id ELSE ?2 || '/' || id END".to_string(),
+            _ => c.clone(),
+        })
+        .collect();
+    tx.execute(
+        &format!(
+            "INSERT INTO main.invoice_items({}) SELECT {} FROM backup.invoice_items WHERE invoice_number = ?1",
+            columns.join(", "),
+            values.join(", ")
+        ),
+        params![invoice_number, target],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let backup_has_revisions: bool = tx
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM backup.sqlite_master WHERE type = 'table' AND name = 'invoice_revisions')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if backup_has_revisions {
+        tx.execute(
+            &format!(
+                "INSERT INTO main.invoice_revisions(invoice_number, revision, reason, json_data, grand_total, created_at)
+                 SELECT ?2, revision, reason, {}, grand_total, created_at FROM backup.invoice_revisions WHERE invoice_number = ?1",
+                json
+            ),
+            params![invoice_number, target],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    // Backups from before invoices were locked have no history; record what they have as the
+    // current revision, the same way the lock migration backfilled existing invoices
+    tx.execute(
+        "INSERT OR IGNORE INTO main.invoice_revisions(invoice_number, revision, reason, json_data, grand_total, created_at)
+         SELECT invoice_number, revision, NULL, json_data, grand_total, created_at FROM main.invoices
+         WHERE invoice_number = ?1 AND status IN ('GENERATED', 'CANCELLED') AND json_data IS NOT NULL",
+        params![target],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE main.invoices SET locked = 1 WHERE invoice_number = ?1 AND status IN ('GENERATED', 'CANCELLED')",
+        params![target],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Overwrites a live invoice with the backup's version. Issued invoices go through an amendment,
+/// so the live history is kept and the restored version becomes a new revision.
+fn replace_invoice(tx: &Transaction, invoice_number: &str) -> Result<(), String> {
+    let locked: bool = tx
+        .query_row("SELECT locked = 1 FROM main.invoices WHERE invoice_number = ?1", params![invoice_number], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if !locked {
+        tx.execute("DELETE FROM main.invoice_items WHERE invoice_number = ?1", params![invoice_number])
+            .map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM main.invoices WHERE invoice_number = ?1", params![invoice_number])
+            .map_err(|e| e.to_string())?;
+        return copy_invoice(tx, invoice_number, invoice_number);
+    }
+
+    let json_data: String = tx
+        .query_row("SELECT json_data FROM backup.invoices WHERE invoice_number = ?1", params![invoice_number], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let mut invoice: Invoice = serde_json::from_str(&json_data)
+        .map_err(|e| format!("Invoice {} in the backup is unreadable: {}", invoice_number, e))?;
+    invoice.invoice_number = invoice_number.to_string();
+    invoices::amend_in(tx, invoice, RESTORE_REASON)?;
+    Ok(())
+}
+
+fn invoice_exists(tx: &Transaction, invoice_number: &str) -> Result<bool, String> {
+    tx.query_row(
+        "SELECT EXISTS(SELECT 1 FROM main.invoices WHERE invoice_number = ?1)",
+        params![invoice_number],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Copies the chosen records from an attached backup in one transaction. Any unresolved
+/// conflict or failure leaves the live database untouched.
+pub fn restore(conn: &mut Connection, selection: &RecordSelection) -> Result<SelectiveRestoreReport, String> {
+    let mut report = SelectiveRestoreReport::default();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    for choice in &selection.customers {
+        match conflict(&tx, "customers", "id", CUSTOMER_COMPARE, &choice.id)?.as_deref() {
+            None => return Err(format!("Customer {} is not in the backup", choice.id)),
+            Some("none") => {
+                copy_customer(&tx, &choice.id)?;
+                report.customers_restored += 1;
+            }
+            Some("identical") => report.customers_skipped += 1,
+            _ => match choice.on_conflict.as_deref() {
+                Some("replace") => {
+                    replace_customer(&tx, &choice.id)?;
+                    report.customers_replaced += 1;
+                }
+                Some("skip") => report.customers_skipped += 1,
+                _ => {
+                    return Err(format!(
+                        "Customer {} already exists with different details. Choose whether to skip or replace it.",
+                        choice.id
+                    ))
+                }
+            },
+        }
+    }
+
+    for choice in &selection.invoices {
+        let number = choice.invoice_number.as_str();
+        match conflict(&tx, "invoices", "invoice_number", INVOICE_COMPARE, number)?.as_deref() {
+            None => return Err(format!("Invoice {} is not in the backup", number)),
+            Some("none") => {
+                report.customers_restored += ensure_customer(&tx, number)? as usize;
+                copy_invoice(&tx, number, number)?;
+                report.invoices_restored += 1;
+            }
+            Some("identical") => report.invoices_skipped += 1,
+            _ => match choice.on_conflict.as_deref() {
+                Some("skip") => report.invoices_skipped += 1,
+                Some("replace") => {
+                    report.customers_restored += ensure_customer(&tx, number)? as usize;
+                    replace_invoice(&tx, number)?;
+                    report.invoices_replaced += 1;
+                }
+                Some("renumber") => {
+                    let target = choice.new_number.as_deref().map(str::trim).unwrap_or_default();
+                    if target.is_empty() {
+                        return Err(format!("Enter a new number to restore invoice {} under", number));
+                    }
+                    if invoice_exists(&tx, target)? {
+                        return Err(format!("Invoice number {} is already in use", target));
+                    }
+                    report.customers_restored += ensure_customer(&tx, number)? as usize;
+                    copy_invoice(&tx, number, target)?;
+                    report.invoices_renumbered += 1;
+                }
+                _ => {
+                    return Err(format!(
+                        "Invoice {} already exists with different contents. Choose whether to skip it, replace it or restore it under a new number.",
+                        number
+                    ))
+                }
+            },
+        }
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn list_backup_records(
+    app_handle: tauri::AppHandle,
+    source_path: String,
+    passphrase: Option<String>,
+) -> Result<BackupRecords, String> {
+    let db_path = db::db_path(&app_handle)?;
+    let staged = restore::open_backup(Path::new(&source_path), passphrase.as_deref(), &db_path)?;
+    let conn = db::open(&app_handle)?;
+    attach(&conn, &staged.database)?;
+    list(&conn)
+}
+
+#[tauri::command]
+pub async fn restore_backup_records(
+    app_handle: tauri::AppHandle,
+    source_path: String,
+    passphrase: Option<String>,
+    selection: RecordSelection,
+) -> Result<SelectiveRestoreReport, String> {
+    let db_path = db::db_path(&app_handle)?;
+    let staged = restore::open_backup(Path::new(&source_path), passphrase.as_deref(), &db_path)?;
+    let mut conn = db::open(&app_handle)?;
+    attach(&conn, &staged.database)?;
+    restore(&mut conn, &selection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::migrations;
+    use crate::test_support::{insert_customer, migrated_db, migrated_db_at, sample_invoice};
+    use std::path::PathBuf;
+
+    /// A backup file in `dir` on the current schema, filled by `fill`.
+    fn backup(dir: &Path, fill: impl FnOnce(&mut Connection)) -> PathBuf {
+        let path = dir.join("backup.db");
+        fill(&mut migrated_db_at(&path));
+        path
+    }
+
+    fn live_with(path: &Path) -> Connection {
+        let conn = migrated_db();
+        attach(&conn, path).unwrap();
+        conn
+    }
+
+    fn choose(invoice_number: &str, on_conflict: Option<&str>, new_number: Option<&str>) -> RecordSelection {
+        RecordSelection {
+            invoices: vec![InvoiceChoice {
+                invoice_number: invoice_number.to_string(),
+                on_conflict: on_conflict.map(str::to_string),
+                new_number: new_number.map(str::to_string),
+            }],
+            customers: vec![],
+        }
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn an_invoice_is_copied_with_the_customer_it_refers_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = backup(dir.path(), |conn| {
+            insert_customer(conn, "c1", "Acme Pvt Ltd");
+            invoices::issue(conn, sample_invoice("001", "c1")).unwrap();
+        });
+        let mut live = live_with(&path);
+        assert_eq!(list(&live).unwrap().invoices[0].conflict, "none");
+
+        let report = restore(&mut live, &choose("001", None, None)).unwrap();
+        assert_eq!((report.invoices_restored, report.customers_restored), (1, 1));
+        assert_eq!(count(&live, "SELECT COUNT(*) FROM main.customers WHERE id = 'c1'"), 1);
+        assert_eq!(count(&live, "SELECT locked FROM main.invoices WHERE invoice_number = '001'"), 1);
+        assert_eq!(count(&live, "SELECT COUNT(*) FROM main.invoice_items WHERE invoice_number = '001'"), 1);
+        assert_eq!(invoices::revisions(&live, "001").unwrap().len(), 1);
+        assert_eq!(list(&live).unwrap().invoices[0].conflict, "identical");
+    }
+
+    #[test]
+    fn an_invoice_restored_under_a_new_number_is_renumbered_throughout() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = backup(dir.path(), |conn| {
+            insert_customer(conn, "c1", "Acme Pvt Ltd");
+            let issued = invoices::issue(conn, sample_invoice("001", "c1")).unwrap();
+            let mut amended = issued.clone();
+            amended.work_order_reference = "WO/78".to_string();
+            invoices::amend(conn, amended, "Work order corrected").unwrap();
+        });
+        let mut live = live_with(&path);
+        insert_customer(&live, "c1", "Acme Pvt Ltd");
+        let mut other = sample_invoice("001", "c1");
+        other.invoice_date = "2024-08-01".to_string();
+        invoices::issue(&mut live, other).unwrap();
+
+        let error = restore(&mut live, &choose("001", Some("renumber"), Some("001"))).unwrap_err();
+        assert_eq!(error, "Invoice number 001 is already in use");
+        let report = restore(&mut live, &choose("001", Some("renumber"), Some(" 101 "))).unwrap();
+        assert_eq!((report.invoices_renumbered, report.customers_restored), (1, 0));
+
+        let (json, locked, revision): (String, i64, i64) = live
+            .query_row("SELECT json_data, locked, revision FROM main.invoices WHERE invoice_number = '101'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        let invoice: Invoice = serde_json::from_str(&json).unwrap();
+        assert_eq!((invoice.invoice_number.as_str(), invoice.id.as_deref()), ("101", Some("101")));
+        assert_eq!((locked, revision), (1, 1));
+        let item: String = live.query_row("SELECT id FROM main.invoice_items WHERE invoice_number = '101'", [], |row| row.get(0)).unwrap();
+        assert_eq!(item, "101/001-1");
+
+        let revisions = invoices::revisions(&live, "101").unwrap();
+        let reasons: Vec<Option<&str>> = revisions.iter().map(|r| r.reason.as_deref()).collect();
+        assert_eq!(reasons, vec![None, Some("Work order corrected")]);
+        assert!(revisions.iter().all(|r| r.invoice.invoice_number == "101"));
+        // Locked again: the items can no longer be changed directly
+        assert!(live.execute("DELETE FROM main.invoice_items WHERE invoice_number = '101'", []).is_err());
+        // The live invoice under the old number is untouched
+        let date: String = live.query_row("SELECT invoice_date FROM main.invoices WHERE invoice_number = '001'", [], |row| row.get(0)).unwrap();
+        assert_eq!(date, "2024-08-01");
+    }
+
+    #[test]
+    fn a_locked_invoice_is_replaced_through_an_amendment() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = backup(dir.path(), |conn| {
+            insert_customer(conn, "c1", "Acme Pvt Ltd");
+            let mut invoice = sample_invoice("001", "c1");
+            invoice.line_items[0].description = "Rooftop solar plant, 5 kWp".to_string();
+            invoices::issue(conn, invoice).unwrap();
+        });
+        let mut live = live_with(&path);
+        insert_customer(&live, "c1", "Acme Pvt Ltd");
+        invoices::issue(&mut live, sample_invoice("001", "c1")).unwrap();
+        assert_eq!(list(&live).unwrap().invoices[0].conflict, "different");
+
+        let report = restore(&mut live, &choose("001", Some("replace"), None)).unwrap();
+        assert_eq!(report.invoices_replaced, 1);
+
+        let revisions = invoices::revisions(&live, "001").unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!((revisions[1].revision, revisions[1].reason.as_deref()), (1, Some(RESTORE_REASON)));
+        assert_eq!(revisions[0].invoice.line_items[0].description, "Rooftop solar power plant");
+        let description: String = live
+            .query_row("SELECT description FROM main.invoice_items WHERE invoice_number = '001'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(description, "Rooftop solar plant, 5 kWp");
+        assert_eq!(count(&live, "SELECT locked FROM main.invoices WHERE invoice_number = '001'"), 1);
+    }
+
+    #[test]
+    fn an_unresolved_conflict_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = backup(dir.path(), |conn| {
+            insert_customer(conn, "c1", "Acme Pvt Ltd");
+            insert_customer(conn, "c2", "Sunrise Traders");
+            invoices::issue(conn, sample_invoice("001", "c1")).unwrap();
+            invoices::issue(conn, sample_invoice("002", "c1")).unwrap();
+        });
+        let mut live = live_with(&path);
+        insert_customer(&live, "c1", "Acme Pvt Ltd");
+        let mut other = sample_invoice("002", "c1");
+        other.grand_total = 250000.0;
+        invoices::issue(&mut live, other).unwrap();
+
+        let selection = RecordSelection {
+            invoices: vec![
+                InvoiceChoice { invoice_number: "001".to_string(), on_conflict: None, new_number: None },
+                InvoiceChoice { invoice_number: "002".to_string(), on_conflict: None, new_number: None },
+            ],
+            customers: vec![CustomerChoice { id: "c2".to_string(), on_conflict: None }],
+        };
+        let error = restore(&mut live, &selection).unwrap_err();
+        assert!(error.starts_with("Invoice 002 already exists with different contents."), "{}", error);
+        assert_eq!(count(&live, "SELECT COUNT(*) FROM main.customers"), 1);
+        assert_eq!(count(&live, "SELECT COUNT(*) FROM main.invoices"), 1);
+        assert_eq!(count(&live, "SELECT COUNT(*) FROM main.invoice_revisions"), 1);
+    }
+
+    #[test]
+    fn a_backup_on_an_older_schema_is_restored_and_locked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.db");
+        {
+            // Only the baseline tables: no revision, lock or trash columns
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(migrations()[0].sql).unwrap();
+            insert_customer(&conn, "c1", "Acme Pvt Ltd");
+            let invoice = sample_invoice("001", "c1");
+            conn.execute(
+                "INSERT INTO invoices(invoice_number, financial_year, customer_id, invoice_date, grand_total, status, json_data)
+                 VALUES ('001', '24-25', 'c1', '2024-07-03', 252000, 'GENERATED', ?1)",
+                params![serde_json::to_string(&invoice).unwrap()],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO invoice_items(id, invoice_number, serial_number, description, amount) VALUES ('001-1', '001', 1, 'Solar panels', 225000)",
+                [],
+            )
+            .unwrap();
+        }
+        let mut live = live_with(&path);
+        assert!(!shared_columns(&live, "invoices").unwrap().contains(&"locked".to_string()));
+        let listed = list(&live).unwrap();
+        assert_eq!((listed.invoices[0].conflict.as_str(), listed.customers[0].conflict.as_str()), ("none", "none"));
+
+        let report = restore(&mut live, &choose("001", None, None)).unwrap();
+        assert_eq!((report.invoices_restored, report.customers_restored), (1, 1));
+        let (locked, revision): (i64, i64) = live
+            .query_row("SELECT locked, revision FROM main.invoices WHERE invoice_number = '001'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((locked, revision), (1, 0));
+        let revisions = invoices::revisions(&live, "001").unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].reason, None);
+        assert_eq!(count(&live, "SELECT COUNT(*) FROM main.invoice_items WHERE invoice_number = '001'"), 1);
+        assert_eq!(count(&live, "SELECT COUNT(*) FROM main.customers WHERE id = 'c1' AND deleted_at IS NULL"), 1);
+    }
+}
@@ -0,0 +1,431 @@
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// A value in an exported sheet. Dates and numbers keep their type so XLSX cells sort and sum
+/// properly; CSV writes them as text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    Empty,
+}
+
+impl From<String> for Cell {
+    fn from(value: String) -> Self {
+        if value.is_empty() {
+            Cell::Empty
+        } else {
+            Cell::Text(value)
+        }
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(value: &str) -> Self {
+        Cell::from(value.to_string())
+    }
+}
+
+impl From<Option<String>> for Cell {
+    fn from(value: Option<String>) -> Self {
+        value.map(Cell::from).unwrap_or(Cell::Empty)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(value: f64) -> Self {
+        Cell::Number(value)
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(value: i64) -> Self {
+        Cell::Number(value as f64)
+    }
+}
+
+/// Parses a stored `YYYY-MM-DD` date, keeping anything else as text rather than dropping it.
+pub fn date_cell(value: &str) -> Cell {
+    match NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+        Ok(date) => Cell::Date(date),
+        Err(_) => Cell::from(value),
+    }
+}
+
+/// `"indian"` writes dates as DD-MM-YYYY, the way they are printed on invoices; `"iso"` as YYYY-MM-DD.
+fn date_pattern(date_format: &str) -> Result<(&'static str, &'static str), String> {
+    // (chrono pattern for CSV, Excel number format for XLSX)
+    match date_format {
+        "indian" => Ok(("%d-%m-%Y", "dd-mm-yyyy")),
+        "iso" => Ok(("%Y-%m-%d", "yyyy-mm-dd")),
+        other => Err(format!("Unknown date format: {}", other)),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    // Spreadsheet apps run text starting with these as a formula
+    let value = if value.starts_with(['=', '+', '-', '@']) && value.parse::<f64>().is_err() {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Writes a CSV file with a header row. It starts with a UTF-8 byte order mark so Excel does
+/// not mangle non-ASCII names.
+pub fn write_csv(path: &Path, header: &[&str], rows: &[Vec<Cell>], date_format: &str) -> Result<(), String> {
+    fs::write(path, csv_text(header, rows, date_format)?).map_err(|e| e.to_string())
+}
+
+/// The contents [`write_csv`] writes, for CSV files that go somewhere other than the disk.
+pub fn csv_text(header: &[&str], rows: &[Vec<Cell>], date_format: &str) -> Result<String, String> {
+    let (pattern, _) = date_pattern(date_format)?;
+    let mut out = String::from("\u{feff}");
+    let header: Vec<String> = header.iter().map(|h| csv_field(h)).collect();
+    out.push_str(&header.join(","));
+    out.push_str("\r\n");
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|cell| match cell {
+                Cell::Text(s) => csv_field(s),
+                Cell::Number(n) => n.to_string(),
+                Cell::Date(d) => d.format(pattern).to_string(),
+                Cell::Empty => String::new(),
+            })
+            .collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    Ok(out)
+}
+
+pub fn xml_escape(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Spreadsheet column letters: 0 is `A`, 26 is `AA`.
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// Days since Excel's epoch, which counts the non-existent 29 February 1900.
+fn excel_serial(date: NaiveDate) -> i64 {
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap_or_default();
+    (date - epoch).num_days()
+}
+
+// Style 1 is the bold header, style 2 a date
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><numFmts count="1"><numFmt numFmtId="164" formatCode="{date}"/></numFmts><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="3"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/><xf numFmtId="164" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/></cellXfs></styleSheet>"#;
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/></Types>"#;
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#;
+
+fn sheet_xml(header: &[&str], rows: &[Vec<Cell>]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews><sheetData>"#,
+    );
+    let header_cells: Vec<Cell> = header.iter().map(|h| Cell::from(*h)).collect();
+    for (r, row) in std::iter::once(&header_cells).chain(rows).enumerate() {
+        xml.push_str(&format!(r#"<row r="{}">"#, r + 1));
+        for (c, cell) in row.iter().enumerate() {
+            let reference = format!("{}{}", column_name(c), r + 1);
+            match cell {
+                Cell::Text(s) => {
+                    let style = if r == 0 { r#" s="1""# } else { "" };
+                    xml.push_str(&format!(
+                        r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                        reference,
+                        style,
+                        xml_escape(s)
+                    ));
+                }
+                Cell::Number(n) if n.is_finite() => xml.push_str(&format!(r#"<c r="{}"><v>{}</v></c>"#, reference, n)),
+                Cell::Date(d) => xml.push_str(&format!(r#"<c r="{}" s="2"><v>{}</v></c>"#, reference, excel_serial(*d))),
+                Cell::Number(_) | Cell::Empty => {}
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+/// Writes a single-sheet XLSX workbook with a bold, frozen header row.
+pub fn write_xlsx(path: &Path, sheet_name: &str, header: &[&str], rows: &[Vec<Cell>], date_format: &str) -> Result<(), String> {
+    let (_, excel_pattern) = date_pattern(date_format)?;
+    let workbook = format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+        xml_escape(sheet_name)
+    );
+    let parts = [
+        ("[Content_Types].xml", CONTENT_TYPES.to_string()),
+        ("_rels/.rels", ROOT_RELS.to_string()),
+        ("xl/workbook.xml", workbook),
+        ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS.to_string()),
+        ("xl/styles.xml", STYLES.replace("{date}", excel_pattern)),
+        ("xl/worksheets/sheet1.xml", sheet_xml(header, rows)),
+    ];
+
+    let file = fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, content) in parts {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(content.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Splits CSV text into rows of fields, honouring quoted fields with embedded commas, quotes
+/// and line breaks. Blank lines are kept so row numbers match the file.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+fn zip_entry(archive: &mut zip::ZipArchive<fs::File>, name: &str) -> Result<Option<String>, String> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut content = String::new();
+    entry.read_to_string(&mut content).map_err(|e| e.to_string())?;
+    Ok(Some(content))
+}
+
+// The size of an Excel worksheet: columns run to XFD
+const MAX_COLUMNS: usize = 16_384;
+const MAX_ROWS: usize = 1_048_576;
+// Rows are padded out to their last cell, so a few stray cells far to the right could otherwise
+// ask for millions of empty ones
+const MAX_CELLS: usize = 2_000_000;
+
+/// Index of the column in a cell reference like `AB12`, or `None` when it has no column letters.
+fn column_index(reference: &str) -> Result<Option<usize>, String> {
+    let letters = reference.bytes().take_while(u8::is_ascii_alphabetic).count();
+    if letters == 0 {
+        return Ok(None);
+    }
+    let index = reference.as_bytes()[..letters.min(4)]
+        .iter()
+        .fold(0usize, |acc, b| acc * 26 + (b.to_ascii_uppercase() - b'A' + 1) as usize);
+    if letters > 3 || index > MAX_COLUMNS {
+        return Err(format!("Cell {} is beyond the last column of a worksheet (XFD).", reference));
+    }
+    Ok(Some(index - 1))
+}
+
+fn row_number(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(number) if (1..=MAX_ROWS).contains(&number) => Ok(number),
+        _ => Err(format!("Row {} is outside a worksheet, which has rows 1 to {}.", value, MAX_ROWS)),
+    }
+}
+
+/// Text of every `<t>` under a node, which is how rich text runs are split up.
+fn node_text(node: roxmltree::Node) -> String {
+    node.descendants().filter(|n| n.tag_name().name() == "t").filter_map(|n| n.text()).collect()
+}
+
+/// Reads the first worksheet of an XLSX workbook as text, one entry per sheet row. Numbers keep the digits Excel
+/// stored, so a pincode typed as a number comes back as `700152`.
+pub fn read_xlsx(path: &Path) -> Result<Vec<Vec<String>>, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|_| "Not a valid XLSX file".to_string())?;
+
+    let workbook = zip_entry(&mut archive, "xl/workbook.xml")?.ok_or("Not a valid XLSX file: no workbook")?;
+    let rels = zip_entry(&mut archive, "xl/_rels/workbook.xml.rels")?.unwrap_or_default();
+    let sheet_path = {
+        let workbook = roxmltree::Document::parse(&workbook).map_err(|e| format!("Invalid XLSX workbook: {}", e))?;
+        let relation = workbook
+            .descendants()
+            .find(|n| n.tag_name().name() == "sheet")
+            .and_then(|sheet| sheet.attributes().find(|a| a.name() == "id").map(|a| a.value().to_string()));
+        let target = roxmltree::Document::parse(&rels).ok().and_then(|rels| {
+            rels.descendants()
+                .find(|n| n.tag_name().name() == "Relationship" && n.attribute("Id") == relation.as_deref())
+                .and_then(|n| n.attribute("Target").map(str::to_string))
+        });
+        match target {
+            Some(target) if target.starts_with('/') => target.trim_start_matches('/').to_string(),
+            Some(target) => format!("xl/{}", target),
+            None => "xl/worksheets/sheet1.xml".to_string(),
+        }
+    };
+
+    let shared: Vec<String> = match zip_entry(&mut archive, "xl/sharedStrings.xml")? {
+        Some(xml) => {
+            let doc = roxmltree::Document::parse(&xml).map_err(|e| format!("Invalid XLSX strings: {}", e))?;
+            doc.root_element().children().filter(|n| n.tag_name().name() == "si").map(node_text).collect()
+        }
+        None => Vec::new(),
+    };
+
+    let sheet = zip_entry(&mut archive, &sheet_path)?.ok_or("Not a valid XLSX file: no worksheet")?;
+    let doc = roxmltree::Document::parse(&sheet).map_err(|e| format!("Invalid XLSX worksheet: {}", e))?;
+    let mut rows = Vec::new();
+    let mut cell_count = 0;
+    for row in doc.descendants().filter(|n| n.tag_name().name() == "row") {
+        // Excel leaves out empty rows; pad them back so row numbers match the sheet
+        if let Some(number) = row.attribute("r").map(row_number).transpose()? {
+            while rows.len() + 1 < number {
+                rows.push(Vec::new());
+            }
+        }
+        let mut cells: HashMap<usize, String> = HashMap::new();
+        for (position, cell) in row.children().filter(|n| n.tag_name().name() == "c").enumerate() {
+            let index = match cell.attribute("r") {
+                Some(reference) => column_index(reference)?.unwrap_or(position),
+                None => position,
+            };
+            let value = cell.children().find(|n| n.tag_name().name() == "v").and_then(|v| v.text()).unwrap_or_default();
+            let text = match cell.attribute("t") {
+                Some("s") => value.parse::<usize>().ok().and_then(|i| shared.get(i).cloned()).unwrap_or_default(),
+                Some("inlineStr") => cell.children().find(|n| n.tag_name().name() == "is").map(node_text).unwrap_or_default(),
+                Some("b") => if value == "1" { "TRUE" } else { "FALSE" }.to_string(),
+                _ => value.to_string(),
+            };
+            cells.insert(index, text);
+        }
+        let width = cells.keys().max().map_or(0, |max| max + 1);
+        cell_count += width;
+        if cell_count > MAX_CELLS {
+            return Err("The worksheet is too large to import. Remove unused rows and columns, or save it as CSV.".to_string());
+        }
+        rows.push((0..width).map(|i| cells.remove(&i).unwrap_or_default()).collect());
+    }
+    Ok(rows)
+}
+
+/// Reads a `.csv` or `.xlsx` file into rows of text, the first row being the header.
+pub fn read_table(path: &Path) -> Result<Vec<Vec<String>>, String> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    match extension.as_str() {
+        "csv" | "txt" => {
+            let bytes = fs::read(path).map_err(|e| e.to_string())?;
+            Ok(parse_csv(&String::from_utf8_lossy(&bytes)))
+        }
+        "xlsx" => read_xlsx(path),
+        "xls" => Err("Old .xls workbooks are not supported. Save the file as .xlsx or CSV first.".to_string()),
+        _ => Err("Choose a .csv or .xlsx file.".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workbook_with_sheet(path: &Path, sheet_data: &str) {
+        let workbook = r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+        let sheet = format!(r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>{}</sheetData></worksheet>"#, sheet_data);
+        let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, content) in [("xl/workbook.xml", workbook), ("xl/_rels/workbook.xml.rels", WORKBOOK_RELS), ("xl/worksheets/sheet1.xml", &sheet)] {
+            zip.start_file(name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn column_references_stop_at_xfd() {
+        assert_eq!(column_index("A1"), Ok(Some(0)));
+        assert_eq!(column_index("ab12"), Ok(Some(27)));
+        assert_eq!(column_index("XFD1048576"), Ok(Some(16_383)));
+        assert_eq!(column_index("12"), Ok(None));
+        assert!(column_index("XFE1").is_err());
+        assert!(column_index("AAAA1").is_err());
+        assert!(column_index(&format!("{}1", "Z".repeat(40))).is_err());
+    }
+
+    #[test]
+    fn sheets_read_back_with_gaps_filled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("customers.xlsx");
+        let rows = vec![vec![Cell::from("Acme"), Cell::Empty, Cell::from(700152i64)]];
+        write_xlsx(&path, "Customers", &["Name", "City", "Pincode"], &rows, "indian").unwrap();
+        assert_eq!(read_xlsx(&path).unwrap(), vec![vec!["Name", "City", "Pincode"], vec!["Acme", "", "700152"]]);
+
+        workbook_with_sheet(&path, r#"<row r="1"><c r="A1" t="inlineStr"><is><t>Name</t></is></c></row><row r="3"><c r="C3"><v>5</v></c></row>"#);
+        assert_eq!(read_xlsx(&path).unwrap(), vec![vec!["Name"], vec![], vec!["", "", "5"]]);
+    }
+
+    #[test]
+    fn references_outside_a_worksheet_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.xlsx");
+        for (sheet_data, error) in [
+            (r#"<row r="1"><c r="ZZZZZZZZZZZZZZ1"><v>1</v></c></row>"#, "beyond the last column"),
+            (r#"<row r="1"><c r="XFE1"><v>1</v></c></row>"#, "beyond the last column"),
+            (r#"<row r="1048577"><c r="A1048577"><v>1</v></c></row>"#, "outside a worksheet"),
+            (r#"<row r="99999999999999999999999"><c><v>1</v></c></row>"#, "outside a worksheet"),
+            (&r#"<row><c r="XFD1"><v>1</v></c></row>"#.repeat(200), "too large"),
+        ] {
+            workbook_with_sheet(&path, sheet_data);
+            let message = read_xlsx(&path).unwrap_err();
+            assert!(message.contains(error), "{}: {}", sheet_data, message);
+        }
+    }
+}
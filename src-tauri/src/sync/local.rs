@@ -0,0 +1,76 @@
+use super::{check_name, RemoteFile, SyncProvider};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// A folder on this machine, typically one kept in sync by a desktop client (OneDrive, Dropbox)
+/// or a mounted network share.
+pub struct LocalFolder {
+    dir: PathBuf,
+}
+
+impl LocalFolder {
+    pub fn new(dir: PathBuf) -> Self {
+        LocalFolder { dir }
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, String> {
+        check_name(name)?;
+        Ok(self.dir.join(name))
+    }
+}
+
+#[async_trait]
+impl SyncProvider for LocalFolder {
+    async fn push(&self, local: &Path, name: &str) -> Result<(), String> {
+        let target = self.path(name)?;
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        // Copy next to the target and rename, so a sync client never uploads a half-written file
+        let partial = self.dir.join(format!("{}.partial", name));
+        if let Err(e) = fs::copy(local, &partial) {
+            let _ = fs::remove_file(&partial);
+            return Err(e.to_string());
+        }
+        fs::rename(&partial, target).map_err(|e| e.to_string())
+    }
+
+    async fn pull(&self, name: &str, local: &Path) -> Result<(), String> {
+        let source = self.path(name)?;
+        if let Err(e) = fs::copy(source, local) {
+            let _ = fs::remove_file(local);
+            return Err(e.to_string());
+        }
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<RemoteFile>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.to_string()),
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| e.to_string())?;
+            let metadata = entry.metadata().map_err(|e| e.to_string())?;
+            if !metadata.is_file() {
+                continue;
+            }
+            files.push(RemoteFile {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                size: Some(metadata.len()),
+                modified: metadata.modified().ok().map(|t| DateTime::<Utc>::from(t).to_rfc3339()),
+            });
+        }
+        Ok(files)
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), String> {
+        match fs::remove_file(self.path(name)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
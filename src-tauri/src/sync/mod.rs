@@ -0,0 +1,568 @@
+mod local;
+mod s3;
+mod webdav;
+
+pub use local::LocalFolder;
+pub use s3::S3Storage;
+pub use webdav::WebDav;
+
+use crate::backup::{self, RetentionPolicy};
+use crate::conflict::{self, SyncCheck, SYNC_CONFLICT};
+use crate::vault::{self, Vault};
+use crate::{crypto, db, device};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File in the app config dir holding the provider settings. The password or secret key is only
+/// kept in it while there is no vault; see [`vault::WEBDAV_PASSWORD`] and [`vault::S3_SECRET_KEY`].
+const CONFIG_FILE: &str = "sync.json";
+const SECRET_KEYS: [&str; 2] = [vault::WEBDAV_PASSWORD, vault::S3_SECRET_KEY];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteFile {
+    pub name: String,
+    pub size: Option<u64>,
+    /// RFC 3339 timestamp when the provider reports one.
+    pub modified: Option<String>,
+}
+
+/// A remote backup target: a flat folder of files addressed by name. The backup code does not
+/// care whether a bundle ends up in a local folder, on a WebDAV server or in an S3 bucket.
+#[async_trait]
+pub trait SyncProvider: Send + Sync {
+    /// Uploads `local` as `name`, replacing any remote file of that name.
+    async fn push(&self, local: &Path, name: &str) -> Result<(), String>;
+    /// Downloads `name` to `local`. A partial file is removed on failure.
+    async fn pull(&self, name: &str, local: &Path) -> Result<(), String>;
+    async fn list(&self) -> Result<Vec<RemoteFile>, String>;
+    /// Deleting a file that is already gone is not an error.
+    async fn delete(&self, name: &str) -> Result<(), String>;
+}
+
+/// Remote names become URL segments and file names, so only plain names are allowed.
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(format!("Invalid remote file name: {}", name));
+    }
+    Ok(())
+}
+
+/// Streams a successful HTTP response body into `target`, removing it if the transfer fails.
+pub(crate) async fn save_response(mut response: reqwest::Response, target: &Path) -> Result<(), String> {
+    let mut file = fs::File::create(target).map_err(|e| e.to_string())?;
+    let result: Result<(), String> = async {
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            file.write_all(&chunk).map_err(|e| e.to_string())?;
+        }
+        file.sync_all().map_err(|e| e.to_string())
+    }
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_file(target);
+    }
+    result
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalConfig {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavConfig {
+    /// Folder URL, e.g. `https://cloud.example.com/remote.php/dav/files/me/Backups/` on Nextcloud.
+    pub url: String,
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3Config {
+    /// e.g. `https://s3.ap-south-1.amazonaws.com` or `http://localhost:9000` for MinIO.
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Key prefix the backups live under, e.g. `apex/`.
+    #[serde(default)]
+    pub prefix: String,
+    pub access_key_id: String,
+    #[serde(default)]
+    pub secret_access_key: String,
+    /// `endpoint/bucket/key` instead of `bucket.endpoint/key`; MinIO needs this.
+    #[serde(default)]
+    pub path_style: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ProviderConfig {
+    Local(LocalConfig),
+    Webdav(WebDavConfig),
+    S3(S3Config),
+}
+
+impl ProviderConfig {
+    pub fn provider(&self) -> Result<Box<dyn SyncProvider>, String> {
+        Ok(match self {
+            ProviderConfig::Local(c) => Box::new(LocalFolder::new(PathBuf::from(&c.path))),
+            ProviderConfig::Webdav(c) => Box::new(WebDav::new(c)?),
+            ProviderConfig::S3(c) => Box::new(S3Storage::new(c)?),
+        })
+    }
+
+    /// The provider's secret and the vault key it is stored under.
+    fn secret_mut(&mut self) -> Option<(&'static str, &mut String)> {
+        match self {
+            ProviderConfig::Local(_) => None,
+            ProviderConfig::Webdav(c) => Some((vault::WEBDAV_PASSWORD, &mut c.password)),
+            ProviderConfig::S3(c) => Some((vault::S3_SECRET_KEY, &mut c.secret_access_key)),
+        }
+    }
+
+    /// The config as shown to the frontend: secrets are never sent back.
+    fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        if let Some((_, secret)) = config.secret_mut() {
+            secret.clear();
+        }
+        config
+    }
+
+    /// An empty secret coming from the frontend means "keep the stored one".
+    fn with_stored_secrets(mut self, stored: Option<&ProviderConfig>) -> Self {
+        match (&mut self, stored) {
+            (ProviderConfig::Webdav(c), Some(ProviderConfig::Webdav(s))) if c.password.is_empty() => {
+                c.password = s.password.clone();
+            }
+            (ProviderConfig::S3(c), Some(ProviderConfig::S3(s))) if c.secret_access_key.is_empty() => {
+                c.secret_access_key = s.secret_access_key.clone();
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPushReport {
+    pub name: String,
+    /// Remote backups deleted by the retention policy.
+    pub pruned: Vec<String>,
+}
+
+/// Uploads a backup and applies the same retention policy as the local backup folder, to this
+/// device's backups only.
+pub async fn push_backup(
+    provider: &dyn SyncProvider,
+    local: &Path,
+    policy: &RetentionPolicy,
+    device_id: &str,
+) -> Result<SyncPushReport, String> {
+    let name = local.file_name().ok_or("Invalid backup path")?.to_string_lossy().into_owned();
+    if !backup::is_backup_file_name(&name) {
+        return Err("Not an invoice backup file".to_string());
+    }
+    provider.push(local, &name).await?;
+
+    let mut remote: Vec<_> = provider
+        .list()
+        .await?
+        .into_iter()
+        .filter(|f| backup::is_own_snapshot(&f.name, device_id))
+        .filter_map(|f| backup::parse_snapshot_name(&f.name).map(|time| (time, PathBuf::from(f.name))))
+        .collect();
+    remote.sort_by_key(|s| std::cmp::Reverse(s.0));
+    let keep = backup::snapshots_to_keep(&remote, policy);
+
+    let mut pruned = Vec::new();
+    for (_, path) in remote.iter().filter(|(_, p)| !keep.contains(p)) {
+        let old = path.to_string_lossy();
+        provider.delete(&old).await?;
+        pruned.push(old.into_owned());
+    }
+    Ok(SyncPushReport { name, pruned })
+}
+
+/// Compares this device's data with the newest backup at the remote, downloading it into
+/// `download_dir` unless this device made it. `None` when there is nothing to compare with.
+pub async fn remote_check(
+    provider: &dyn SyncProvider,
+    db_path: &Path,
+    config_dir: &Path,
+    download_dir: &Path,
+    passphrase: Option<&str>,
+) -> Result<Option<SyncCheck>, String> {
+    let device = device::load_or_create(config_dir)?;
+    let files = provider.list().await?;
+    let Some(name) = conflict::newest_foreign_backup(files.iter().map(|f| f.name.as_str()), &device.id) else {
+        return Ok(None);
+    };
+    // Backup names are unique, so a copy downloaded before is the same file
+    let local = download_dir.join(name);
+    if !local.exists() {
+        fs::create_dir_all(download_dir).map_err(|e| e.to_string())?;
+        provider.pull(name, &local).await?;
+    }
+    conflict::check_against(db_path, config_dir, conflict::origin(&local, passphrase)).map(Some)
+}
+
+fn download_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let cache_dir = app_handle.path_resolver().app_cache_dir().ok_or("Failed to resolve app cache dir")?;
+    Ok(cache_dir.join("downloads"))
+}
+
+async fn configured_remote_check(app_handle: &tauri::AppHandle, vault: &Vault, provider: &dyn SyncProvider) -> Result<Option<SyncCheck>, String> {
+    let config_dir = device::config_dir(app_handle)?;
+    let passphrase = crypto::passphrase_if_unlocked(&config_dir, vault)?;
+    remote_check(provider, &db::db_path(app_handle)?, &config_dir, &download_dir(app_handle)?, passphrase.as_deref()).await
+}
+
+fn read_config(config_dir: &Path) -> Result<Option<ProviderConfig>, String> {
+    let path = config_dir.join(CONFIG_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map(Some).map_err(|e| e.to_string())
+}
+
+/// Fills in a secret left out of the config from the vault, right before connecting.
+fn with_vault_secret(mut config: ProviderConfig, config_dir: &Path, vault: &Vault) -> Result<ProviderConfig, String> {
+    if vault::exists(config_dir) {
+        if let Some((key, secret)) = config.secret_mut() {
+            if secret.is_empty() {
+                if let Some(stored) = vault.get(key)? {
+                    *secret = String::from_utf8(stored).map_err(|e| e.to_string())?;
+                }
+            }
+        }
+    }
+    Ok(config)
+}
+
+/// Writes the provider settings, with the secret going into the vault once there is one.
+fn save_config(config_dir: &Path, vault: &Vault, mut config: ProviderConfig) -> Result<(), String> {
+    if vault::exists(config_dir) {
+        let in_use = match config.secret_mut() {
+            Some((key, secret)) => {
+                if !secret.is_empty() {
+                    vault.put(key, std::mem::take(secret).into_bytes())?;
+                }
+                Some(key)
+            }
+            None => None,
+        };
+        // A secret of a provider no longer in use should not linger
+        if vault.is_unlocked() {
+            for key in SECRET_KEYS.into_iter().filter(|key| Some(*key) != in_use) {
+                vault.remove(key)?;
+            }
+        }
+    }
+    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    crypto::write_private_file(&config_dir.join(CONFIG_FILE), content.as_bytes())
+}
+
+fn remove_config(config_dir: &Path, vault: &Vault) -> Result<(), String> {
+    let path = config_dir.join(CONFIG_FILE);
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| e.to_string())?;
+    }
+    if vault::exists(config_dir) && vault.is_unlocked() {
+        for key in SECRET_KEYS {
+            vault.remove(key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Moves a password or secret key kept in `sync.json` into the vault, which must be unlocked.
+pub fn migrate_secrets(config_dir: &Path, vault: &Vault) -> Result<bool, String> {
+    let Some(mut config) = read_config(config_dir)? else {
+        return Ok(false);
+    };
+    let has_secret = config.secret_mut().is_some_and(|(_, secret)| !secret.is_empty());
+    if !has_secret {
+        return Ok(false);
+    }
+    save_config(config_dir, vault, config)?;
+    Ok(true)
+}
+
+/// The settings to connect with: an empty secret from the frontend keeps the stored one.
+fn resolve_config(config_dir: &Path, vault: &Vault, config: ProviderConfig) -> Result<ProviderConfig, String> {
+    let config = config.with_stored_secrets(read_config(config_dir)?.as_ref());
+    with_vault_secret(config, config_dir, vault)
+}
+
+fn configured_provider(app_handle: &tauri::AppHandle, vault: &Vault) -> Result<Box<dyn SyncProvider>, String> {
+    let config_dir = device::config_dir(app_handle)?;
+    let config = read_config(&config_dir)?.ok_or("No sync provider is configured.")?;
+    with_vault_secret(config, &config_dir, vault)?.provider()
+}
+
+#[tauri::command]
+pub async fn get_sync_provider(app_handle: tauri::AppHandle) -> Result<Option<ProviderConfig>, String> {
+    Ok(read_config(&device::config_dir(&app_handle)?)?.map(|c| c.redacted()))
+}
+
+/// Saves the provider settings, or removes them when `config` is `None`.
+#[tauri::command]
+pub async fn set_sync_provider(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    config: Option<ProviderConfig>,
+) -> Result<(), String> {
+    let config_dir = device::config_dir(&app_handle)?;
+    match config {
+        Some(config) => {
+            let config = resolve_config(&config_dir, &vault, config)?;
+            config.provider()?;
+            save_config(&config_dir, &vault, config)
+        }
+        None => remove_config(&config_dir, &vault),
+    }
+}
+
+/// Connects with unsaved settings and returns how many backups are already there.
+#[tauri::command]
+pub async fn test_sync_provider(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    config: ProviderConfig,
+) -> Result<usize, String> {
+    let config = resolve_config(&device::config_dir(&app_handle)?, &vault, config)?;
+    let files = config.provider()?.list().await?;
+    Ok(files.iter().filter(|f| backup::is_backup_file_name(&f.name)).count())
+}
+
+/// Uploads a backup from the backup folder. When the newest backup at the remote holds changes
+/// from another device that this device has not seen, fails with [`SYNC_CONFLICT`] unless
+/// `force` is set.
+#[tauri::command]
+pub async fn sync_push_backup(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    path: String,
+    retention: Option<RetentionPolicy>,
+    force: Option<bool>,
+) -> Result<SyncPushReport, String> {
+    let local = backup::configured_backup(&app_handle, &path)?;
+    let provider = configured_provider(&app_handle, &vault)?;
+    if !force.unwrap_or(false) {
+        let check = configured_remote_check(&app_handle, &vault, provider.as_ref()).await?;
+        if check.is_some_and(|check| check.status.is_conflict()) {
+            return Err(SYNC_CONFLICT.to_string());
+        }
+    }
+    let device = device::this_device(&app_handle)?;
+    push_backup(provider.as_ref(), &local, &retention.unwrap_or_default(), &device.id)
+        .await
+        .map_err(|e| {
+            log::warn!("Pushing {} to the sync provider failed: {}", path, e);
+            e
+        })
+}
+
+/// How this device's data compares with the newest backup at the remote, for the conflict dialog.
+#[tauri::command]
+pub async fn sync_check_status(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>) -> Result<Option<SyncCheck>, String> {
+    let provider = configured_provider(&app_handle, &vault)?;
+    configured_remote_check(&app_handle, &vault, provider.as_ref()).await
+}
+
+/// Backups at the remote, newest first.
+#[tauri::command]
+pub async fn sync_list_backups(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>) -> Result<Vec<RemoteFile>, String> {
+    let mut files: Vec<RemoteFile> = configured_provider(&app_handle, &vault)?
+        .list()
+        .await?
+        .into_iter()
+        .filter(|f| backup::is_backup_file_name(&f.name))
+        .collect();
+    files.sort_by_key(|f| std::cmp::Reverse(backup::parse_snapshot_name(&f.name)));
+    Ok(files)
+}
+
+/// Downloads a remote backup into the app cache and returns the local path.
+#[tauri::command]
+pub async fn sync_pull_backup(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>, name: String) -> Result<String, String> {
+    if !backup::is_backup_file_name(&name) {
+        return Err("Not an invoice backup file".to_string());
+    }
+    let download_dir = download_dir(&app_handle)?;
+    fs::create_dir_all(&download_dir).map_err(|e| e.to_string())?;
+    let target = download_dir.join(&name);
+    configured_provider(&app_handle, &vault)?.pull(&name, &target).await?;
+    Ok(target.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+pub async fn sync_delete_backup(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>, name: String) -> Result<(), String> {
+    if !backup::is_backup_file_name(&name) {
+        return Err("Not an invoice backup file".to_string());
+    }
+    configured_provider(&app_handle, &vault)?.delete(&name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundle;
+    use crate::conflict::SyncStatus;
+    use crate::device::Device;
+    use crate::test_support::{insert_customer, migrated_db_at};
+
+    fn webdav(password: &str) -> ProviderConfig {
+        ProviderConfig::Webdav(WebDavConfig {
+            url: "https://dav.example.com/Backups/".to_string(),
+            username: "me".to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    fn s3(secret_access_key: &str) -> ProviderConfig {
+        ProviderConfig::S3(S3Config {
+            endpoint: "http://localhost:9000".to_string(),
+            region: "us-east-1".to_string(),
+            bucket: "backups".to_string(),
+            prefix: String::new(),
+            access_key_id: "minio".to_string(),
+            secret_access_key: secret_access_key.to_string(),
+            path_style: true,
+        })
+    }
+
+    fn secret(mut config: ProviderConfig) -> String {
+        config.secret_mut().map(|(_, secret)| secret.clone()).unwrap_or_default()
+    }
+
+    fn stored_file(config_dir: &Path) -> String {
+        fs::read_to_string(config_dir.join(CONFIG_FILE)).unwrap()
+    }
+
+    #[test]
+    fn secrets_stay_in_the_file_only_until_there_is_a_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = Vault::default();
+        save_config(dir.path(), &vault, webdav("hunter22")).unwrap();
+        assert!(stored_file(dir.path()).contains("hunter22"));
+
+        vault.unlock(dir.path(), "vault password").unwrap();
+        assert!(migrate_secrets(dir.path(), &vault).unwrap());
+        assert!(!stored_file(dir.path()).contains("hunter22"));
+        assert!(!migrate_secrets(dir.path(), &vault).unwrap());
+        let stored = read_config(dir.path()).unwrap().unwrap();
+        assert_eq!(secret(with_vault_secret(stored, dir.path(), &vault).unwrap()), "hunter22");
+
+        // A blank secret from the frontend keeps the stored one
+        assert_eq!(secret(resolve_config(dir.path(), &vault, webdav("")).unwrap()), "hunter22");
+        vault.lock();
+        assert_eq!(resolve_config(dir.path(), &vault, webdav("")).unwrap_err(), vault::VAULT_LOCKED);
+    }
+
+    #[test]
+    fn switching_provider_drops_the_old_secret() {
+        let dir = tempfile::tempdir().unwrap();
+        let vault = Vault::default();
+        vault.unlock(dir.path(), "vault password").unwrap();
+        save_config(dir.path(), &vault, webdav("hunter22")).unwrap();
+        save_config(dir.path(), &vault, s3("s3cret-key")).unwrap();
+        assert!(!stored_file(dir.path()).contains("s3cret-key"));
+        assert_eq!(vault.get(vault::WEBDAV_PASSWORD).unwrap(), None);
+        assert_eq!(vault.get(vault::S3_SECRET_KEY).unwrap().as_deref(), Some(&b"s3cret-key"[..]));
+        // Going back does not pick up the password saved before
+        assert_eq!(secret(resolve_config(dir.path(), &vault, webdav("")).unwrap()), "");
+
+        remove_config(dir.path(), &vault).unwrap();
+        assert!(read_config(dir.path()).unwrap().is_none());
+        assert_eq!(vault.get(vault::S3_SECRET_KEY).unwrap(), None);
+    }
+
+    #[test]
+    fn the_frontend_never_gets_the_secret() {
+        assert_eq!(secret(s3("s3cret-key").redacted()), "");
+        assert_eq!(secret(webdav("hunter22").redacted()), "");
+    }
+
+    async fn push_and_prune(provider: &dyn SyncProvider) {
+        let dir = tempfile::tempdir().unwrap();
+        let device_id = "1a2b3c4d5e6f7a8b";
+        let older = dir.path().join("invoices-20240702-101500-1a2b3c4d.zip");
+        let newer = dir.path().join("invoices-20240703-101500-1a2b3c4d.zip");
+        fs::write(&older, b"older backup").unwrap();
+        fs::write(&newer, b"newer backup").unwrap();
+        let policy = RetentionPolicy { daily: 1, weekly: 0, monthly: 0 };
+
+        push_backup(provider, &older, &policy, device_id).await.unwrap();
+        let report = push_backup(provider, &newer, &policy, device_id).await.unwrap();
+        assert_eq!(report.pruned, ["invoices-20240702-101500-1a2b3c4d.zip"]);
+        let names: Vec<_> = provider.list().await.unwrap().into_iter().map(|f| f.name).collect();
+        assert!(names.contains(&report.name) && !names.contains(&report.pruned[0]));
+
+        let pulled = dir.path().join("pulled.zip");
+        provider.pull(&report.name, &pulled).await.unwrap();
+        assert_eq!(fs::read(&pulled).unwrap(), b"newer backup");
+        provider.delete(&report.name).await.unwrap();
+        provider.delete(&report.name).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn push_prunes_a_local_folder() {
+        let remote = tempfile::tempdir().unwrap();
+        push_and_prune(&LocalFolder::new(remote.path().to_path_buf())).await;
+    }
+
+    #[tokio::test]
+    async fn another_devices_newer_backup_at_the_remote_is_a_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote_dir = dir.path().join("remote");
+        let laptop_db = dir.path().join("laptop.db");
+        insert_customer(&migrated_db_at(&laptop_db), "c1", "Acme Pvt Ltd");
+        let generated = dir.path().join("generated");
+        fs::create_dir_all(&generated).unwrap();
+        let laptop = Device { id: "9f8e7d6c5b4a3f2e".to_string(), name: "Laptop".to_string() };
+        let policy = RetentionPolicy { daily: 0, weekly: 0, monthly: 0 };
+        bundle::create_bundle(&laptop_db, &generated, &[], &remote_dir, &policy, None, &laptop).unwrap();
+
+        let db_path = dir.path().join("invoices.db");
+        let local_hash = conflict::data_hash(&migrated_db_at(&db_path)).unwrap();
+        let config_dir = dir.path().join("config");
+        conflict::record_sync(&config_dir, &local_hash, None).unwrap();
+        let downloads = dir.path().join("downloads");
+        let provider = LocalFolder::new(remote_dir.clone());
+
+        let check = remote_check(&provider, &db_path, &config_dir, &downloads, None).await.unwrap().unwrap();
+        assert_eq!(check.status, SyncStatus::RemoteAhead);
+        let remote = check.remote.unwrap();
+        assert_eq!(remote.device_name, "Laptop");
+        assert!(Path::new(&remote.path).starts_with(&downloads));
+
+        // A newer backup from this device already carries everything it has seen
+        let device = device::load_or_create(&config_dir).unwrap();
+        let later = chrono::NaiveDate::from_ymd_opt(2099, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let own = backup::snapshot_file_name(later, Some(&device.id), backup::BUNDLE_SUFFIX);
+        fs::write(remote_dir.join(own), b"own backup").unwrap();
+        assert!(remote_check(&provider, &db_path, &config_dir, &downloads, None).await.unwrap().is_none());
+    }
+
+    /// Runs against a real server, e.g. MinIO or a Nextcloud folder, with the provider settings
+    /// as saved in sync.json:
+    /// `APEX_SYNC_TEST_PROVIDER='{"kind":"s3","endpoint":"http://localhost:9000",...}' cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore = "needs a WebDAV or S3 server in APEX_SYNC_TEST_PROVIDER"]
+    async fn push_prunes_a_remote_provider() {
+        let config = std::env::var("APEX_SYNC_TEST_PROVIDER").expect("APEX_SYNC_TEST_PROVIDER is not set");
+        let config: ProviderConfig = serde_json::from_str(&config).unwrap();
+        push_and_prune(config.provider().unwrap().as_ref()).await;
+    }
+}
@@ -0,0 +1,228 @@
+use super::{check_name, save_response, RemoteFile, SyncProvider, WebDavConfig};
+use async_trait::async_trait;
+use chrono::DateTime;
+use percent_encoding::percent_decode_str;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode, Url};
+use std::fs;
+use std::path::Path;
+
+const DAV_NS: &str = "DAV:";
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getcontentlength/><d:getlastmodified/></d:prop></d:propfind>"#;
+
+/// A WebDAV folder such as a Nextcloud or ownCloud directory, with HTTP basic auth.
+pub struct WebDav {
+    http: Client,
+    base: Url,
+    username: String,
+    password: String,
+}
+
+impl WebDav {
+    pub fn new(config: &WebDavConfig) -> Result<Self, String> {
+        let mut base = Url::parse(config.url.trim()).map_err(|e| format!("Invalid WebDAV URL: {}", e))?;
+        if base.scheme() != "http" && base.scheme() != "https" {
+            return Err("The WebDAV URL must start with http:// or https://".to_string());
+        }
+        // Names are resolved relative to the folder, which needs the trailing slash
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        Ok(WebDav {
+            http: Client::new(),
+            base,
+            username: config.username.clone(),
+            password: config.password.clone(),
+        })
+    }
+
+    fn url(&self, name: &str) -> Result<Url, String> {
+        check_name(name)?;
+        let mut url = self.base.clone();
+        url.path_segments_mut().map_err(|_| "Invalid WebDAV URL")?.pop_if_empty().push(name);
+        Ok(url)
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        self.http.request(method, url).basic_auth(&self.username, Some(&self.password))
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, String> {
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        match response.status() {
+            StatusCode::UNAUTHORIZED => Err("The WebDAV server rejected the username or password.".to_string()),
+            _ => Ok(response),
+        }
+    }
+
+    fn failed(action: &str, status: StatusCode) -> String {
+        format!("WebDAV {} failed ({})", action, status)
+    }
+
+    async fn put(&self, name: &str, content: &[u8]) -> Result<Response, String> {
+        self.send(self.request(Method::PUT, self.url(name)?).body(content.to_vec())).await
+    }
+}
+
+/// The last path segment of a `href`, decoded.
+fn href_name(href: &str) -> Option<String> {
+    let path = Url::parse(href).map(|u| u.path().to_string()).unwrap_or_else(|_| href.to_string());
+    let segment = path.trim_end_matches('/').rsplit('/').next()?;
+    Some(percent_decode_str(segment).decode_utf8_lossy().into_owned())
+}
+
+/// Parses a PROPFIND multistatus into the files it lists, skipping collections.
+fn parse_multistatus(xml: &str) -> Result<Vec<RemoteFile>, String> {
+    let document = roxmltree::Document::parse(xml).map_err(|e| format!("Invalid WebDAV response: {}", e))?;
+    let dav = |node: &roxmltree::Node, name: &str| node.tag_name().namespace() == Some(DAV_NS) && node.tag_name().name() == name;
+
+    let mut files = Vec::new();
+    for response in document.descendants().filter(|n| dav(n, "response")) {
+        let href = response.children().find(|n| dav(n, "href")).and_then(|n| n.text()).unwrap_or_default();
+        let prop = |name: &str| response.descendants().find(|n| dav(n, name));
+        let is_collection = prop("resourcetype").is_some_and(|n| n.children().any(|c| dav(&c, "collection")));
+        if is_collection || href.ends_with('/') {
+            continue;
+        }
+        let Some(name) = href_name(href) else { continue };
+        files.push(RemoteFile {
+            name,
+            size: prop("getcontentlength").and_then(|n| n.text()).and_then(|t| t.trim().parse().ok()),
+            modified: prop("getlastmodified")
+                .and_then(|n| n.text())
+                .and_then(|t| DateTime::parse_from_rfc2822(t.trim()).ok())
+                .map(|t| t.to_rfc3339()),
+        });
+    }
+    Ok(files)
+}
+
+#[async_trait]
+impl SyncProvider for WebDav {
+    async fn push(&self, local: &Path, name: &str) -> Result<(), String> {
+        let content = fs::read(local).map_err(|e| e.to_string())?;
+        let mut response = self.put(name, &content).await?;
+        // The folder does not exist yet: create it and try once more
+        if matches!(response.status(), StatusCode::CONFLICT | StatusCode::NOT_FOUND) {
+            let method = Method::from_bytes(b"MKCOL").map_err(|e| e.to_string())?;
+            let created = self.send(self.request(method, self.base.clone())).await?;
+            if !created.status().is_success() {
+                return Err(Self::failed("folder creation", created.status()));
+            }
+            response = self.put(name, &content).await?;
+        }
+        if !response.status().is_success() {
+            return Err(Self::failed("upload", response.status()));
+        }
+        Ok(())
+    }
+
+    async fn pull(&self, name: &str, local: &Path) -> Result<(), String> {
+        let response = self.send(self.request(Method::GET, self.url(name)?)).await?;
+        if !response.status().is_success() {
+            return Err(Self::failed("download", response.status()));
+        }
+        save_response(response, local).await
+    }
+
+    async fn list(&self) -> Result<Vec<RemoteFile>, String> {
+        let method = Method::from_bytes(b"PROPFIND").map_err(|e| e.to_string())?;
+        let request = self
+            .request(method, self.base.clone())
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(PROPFIND_BODY);
+        let response = self.send(request).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(Vec::new()),
+            StatusCode::MULTI_STATUS => parse_multistatus(&response.text().await.map_err(|e| e.to_string())?),
+            status => Err(Self::failed("listing", status)),
+        }
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), String> {
+        let response = self.send(self.request(Method::DELETE, self.url(name)?)).await?;
+        match response.status() {
+            status if status.is_success() || status == StatusCode::NOT_FOUND => Ok(()),
+            status => Err(Self::failed("delete", status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_nextcloud_listing() {
+        // PROPFIND Depth: 1 on a Nextcloud folder; properties a collection lacks come back in a 404 propstat
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:s="http://sabredav.org/ns" xmlns:oc="http://owncloud.org/ns" xmlns:nc="http://nextcloud.org/ns"><d:response><d:href>/remote.php/dav/files/me/Backups/</d:href><d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype><d:getlastmodified>Wed, 03 Jul 2024 10:15:02 GMT</d:getlastmodified></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat><d:propstat><d:prop><d:getcontentlength/></d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat></d:response><d:response><d:href>/remote.php/dav/files/me/Backups/invoices-20240703-101500-1a2b3c4d.zip</d:href><d:propstat><d:prop><d:resourcetype/><d:getcontentlength>48213</d:getcontentlength><d:getlastmodified>Wed, 03 Jul 2024 10:15:02 GMT</d:getlastmodified></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response><d:response><d:href>/remote.php/dav/files/me/Backups/Old%20backups/</d:href><d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype><d:getlastmodified>Mon, 01 Jul 2024 08:00:00 GMT</d:getlastmodified></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response></d:multistatus>"#;
+        let files = parse_multistatus(xml).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, "invoices-20240703-101500-1a2b3c4d.zip");
+        assert_eq!(files[0].size, Some(48213));
+        assert_eq!(files[0].modified.as_deref(), Some("2024-07-03T10:15:02+00:00"));
+    }
+
+    #[test]
+    fn parses_an_apache_mod_dav_listing() {
+        // mod_dav declares the DAV: namespace again under other prefixes and pretty-prints the XML
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:multistatus xmlns:D="DAV:" xmlns:ns0="DAV:">
+<D:response xmlns:lp1="DAV:" xmlns:lp2="http://apache.org/dav/props/">
+<D:href>/dav/backups/</D:href>
+<D:propstat>
+<D:prop>
+<lp1:resourcetype><D:collection/></lp1:resourcetype>
+<lp1:getlastmodified>Wed, 03 Jul 2024 10:15:02 GMT</lp1:getlastmodified>
+</D:prop>
+<D:status>HTTP/1.1 200 OK</D:status>
+</D:propstat>
+</D:response>
+<D:response xmlns:lp1="DAV:" xmlns:lp2="http://apache.org/dav/props/">
+<D:href>/dav/backups/invoices-20240703-101500.zip.enc</D:href>
+<D:propstat>
+<D:prop>
+<lp1:resourcetype/>
+<lp1:getcontentlength>
+1024
+</lp1:getcontentlength>
+<lp1:getlastmodified>Wed, 03 Jul 2024 10:15:02 GMT</lp1:getlastmodified>
+</D:prop>
+<D:status>HTTP/1.1 200 OK</D:status>
+</D:propstat>
+</D:response>
+<D:response xmlns:lp1="DAV:" xmlns:lp2="http://apache.org/dav/props/">
+<D:href>/dav/backups/notes%20for%20accounts.txt</D:href>
+<D:propstat>
+<D:prop>
+<lp1:resourcetype/>
+<lp1:getcontentlength>12</lp1:getcontentlength>
+</D:prop>
+<D:status>HTTP/1.1 200 OK</D:status>
+</D:propstat>
+</D:response>
+</D:multistatus>"#;
+        let files = parse_multistatus(xml).unwrap();
+        let names: Vec<_> = files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["invoices-20240703-101500.zip.enc", "notes for accounts.txt"]);
+        assert_eq!(files[0].size, Some(1024));
+        assert_eq!(files[1].modified, None);
+    }
+
+    #[test]
+    fn accepts_absolute_hrefs_and_rejects_broken_xml() {
+        let xml = r#"<multistatus xmlns="DAV:"><response><href>https://dav.example.com/Backups/invoices.db</href><propstat><prop><resourcetype/><getcontentlength>4096</getcontentlength></prop></propstat></response></multistatus>"#;
+        let files = parse_multistatus(xml).unwrap();
+        assert_eq!((files[0].name.as_str(), files[0].size), ("invoices.db", Some(4096)));
+        assert!(parse_multistatus("<d:multistatus xmlns:d=\"DAV:\">").unwrap_err().starts_with("Invalid WebDAV response"));
+    }
+
+    #[test]
+    fn names_resolve_inside_the_folder() {
+        let dav = WebDav::new(&WebDavConfig { url: "https://dav.example.com/Backups".to_string(), username: "me".to_string(), password: String::new() }).unwrap();
+        assert_eq!(dav.url("invoices 1.zip").unwrap().as_str(), "https://dav.example.com/Backups/invoices%201.zip");
+        assert!(dav.url("..").is_err());
+    }
+}
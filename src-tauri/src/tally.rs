@@ -0,0 +1,421 @@
+use crate::models::{Customer, Invoice};
+use crate::spreadsheet::xml_escape;
+use crate::{db, gst, vault};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Names of the ledgers and voucher type in the Tally company. The sales and duty ledgers
+/// must already exist there; party ledgers are created by the import.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TallyLedgers {
+    pub sales: String,
+    pub cgst: String,
+    pub sgst: String,
+    pub round_off: String,
+    pub party_group: String,
+    pub voucher_type: String,
+}
+
+impl Default for TallyLedgers {
+    fn default() -> Self {
+        TallyLedgers {
+            sales: "Sales".to_string(),
+            cgst: "CGST".to_string(),
+            sgst: "SGST".to_string(),
+            round_off: "Round Off".to_string(),
+            party_group: "Sundry Debtors".to_string(),
+            voucher_type: "Sales".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TallyExportOptions {
+    /// Inclusive `YYYY-MM-DD` bounds on the invoice date.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Tally company to import into; whichever company is open in Tally when empty.
+    #[serde(default)]
+    pub company_name: String,
+    #[serde(default)]
+    pub ledgers: TallyLedgers,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TallyExportReport {
+    pub path: String,
+    pub ledgers: usize,
+    pub vouchers: usize,
+    /// Invoices charging CGST + SGST to a customer in another state. They are booked as
+    /// invoiced, so the place of supply should be checked before filing.
+    pub place_of_supply_mismatches: Vec<String>,
+}
+
+fn amount(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Tally wants dates as `YYYYMMDD`.
+fn tally_date(date: &str) -> String {
+    date.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+fn tag(name: &str, value: &str) -> String {
+    format!("<{0}>{1}</{0}>", name, xml_escape(value))
+}
+
+/// State code of a customer: from the GSTIN, else from the state name.
+fn customer_state(customer: &Customer) -> Option<&'static str> {
+    customer
+        .gst_number
+        .as_deref()
+        .and_then(gst::gstin_state)
+        .or_else(|| customer.state.as_deref().and_then(gst::state_code))
+}
+
+/// Issued invoices in the range, oldest first. Cancelled invoices are left out.
+fn load_invoices(conn: &Connection, from: Option<&str>, to: Option<&str>) -> Result<Vec<Invoice>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT json_data FROM invoices
+             WHERE status = 'GENERATED' AND json_data IS NOT NULL
+               AND (?1 IS NULL OR invoice_date >= ?1) AND (?2 IS NULL OR invoice_date <= ?2)
+             ORDER BY invoice_date, invoice_number",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![from, to], |row| row.get::<_, String>(0)).map_err(|e| e.to_string())?;
+    let mut invoices = Vec::new();
+    for row in rows {
+        invoices.push(serde_json::from_str::<Invoice>(&row.map_err(|e| e.to_string())?).map_err(|e| e.to_string())?);
+    }
+    Ok(invoices)
+}
+
+fn party_key(customer: &Customer) -> (String, String) {
+    (customer.company_name.trim().to_string(), customer.gst_number.clone().unwrap_or_default())
+}
+
+/// Ledger names per customer. Tally needs unique names, so customers sharing a name with
+/// another GSTIN (branches in other states) get their GSTIN appended.
+fn party_names(invoices: &[Invoice]) -> HashMap<(String, String), String> {
+    let mut by_name: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for invoice in invoices {
+        let (name, gstin) = party_key(&invoice.customer);
+        let gstins = by_name.entry(name.to_lowercase()).or_default();
+        if !gstins.contains(&gstin) {
+            gstins.push(gstin);
+        }
+    }
+    invoices
+        .iter()
+        .map(|invoice| {
+            let (name, gstin) = party_key(&invoice.customer);
+            let shared = by_name.get(&name.to_lowercase()).is_some_and(|g| g.len() > 1);
+            let ledger = if shared && !gstin.is_empty() { format!("{} ({})", name, gstin) } else { name.clone() };
+            ((name, gstin), ledger)
+        })
+        .collect()
+}
+
+fn ledger_xml(name: &str, customer: &Customer, group: &str) -> String {
+    let mut xml = format!(r#"<LEDGER NAME="{}" ACTION="Create">"#, xml_escape(name));
+    xml.push_str(&format!("<NAME.LIST>{}</NAME.LIST>", tag("NAME", name)));
+    xml.push_str(&tag("PARENT", group));
+    xml.push_str(&tag("ISBILLWISEON", "Yes"));
+    let city_line = match (&customer.city, &customer.pincode) {
+        (Some(city), Some(pin)) => Some(format!("{} - {}", city, pin)),
+        (Some(city), None) => Some(city.clone()),
+        _ => None,
+    };
+    let address: Vec<String> = [Some(customer.address_line1.clone()), customer.address_line2.clone(), customer.address_line3.clone(), city_line]
+        .into_iter()
+        .flatten()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if !address.is_empty() {
+        xml.push_str(r#"<ADDRESS.LIST TYPE="String">"#);
+        for line in address {
+            xml.push_str(&tag("ADDRESS", &line));
+        }
+        xml.push_str("</ADDRESS.LIST>");
+    }
+    xml.push_str(&tag("COUNTRYNAME", "India"));
+    if let Some(state) = customer_state(customer).and_then(gst::state_name) {
+        xml.push_str(&tag("LEDSTATENAME", state));
+    }
+    if let Some(pincode) = &customer.pincode {
+        xml.push_str(&tag("PINCODE", pincode));
+    }
+    match &customer.gst_number {
+        Some(gstin) if !gstin.is_empty() => {
+            xml.push_str(&tag("GSTREGISTRATIONTYPE", "Regular"));
+            xml.push_str(&tag("PARTYGSTIN", gstin));
+        }
+        _ => xml.push_str(&tag("GSTREGISTRATIONTYPE", "Unregistered/Consumer")),
+    }
+    if let Some(pan) = customer.pan_number.as_deref().filter(|p| !p.is_empty()) {
+        xml.push_str(&tag("INCOMETAXNUMBER", pan));
+    }
+    xml.push_str("</LEDGER>");
+    xml
+}
+
+/// One ledger line of a voucher. Tally signs debits negative and credits positive.
+fn entry(ledger: &str, value: f64, extra: &str) -> String {
+    let debit = value < 0.0;
+    format!(
+        "<ALLLEDGERENTRIES.LIST>{}{}{}{}</ALLLEDGERENTRIES.LIST>",
+        tag("LEDGERNAME", ledger),
+        tag("ISDEEMEDPOSITIVE", if debit { "Yes" } else { "No" }),
+        tag("AMOUNT", &amount(value)),
+        extra
+    )
+}
+
+fn rate_details(duty_head: &str, rate: f64) -> String {
+    format!("<RATEDETAILS.LIST>{}{}</RATEDETAILS.LIST>", tag("GSTRATEDUTYHEAD", duty_head), tag("GSTRATE", &amount(rate)))
+}
+
+/// A sales voucher: the party is debited with the invoice total; sales (one line per HSN/SAC
+/// code), the duties exactly as invoiced and any round-off are credited.
+fn voucher_xml(invoice: &Invoice, party: &str, ledgers: &TallyLedgers) -> String {
+    let customer = &invoice.customer;
+    let mut xml = format!(
+        r#"<VOUCHER VCHTYPE="{}" ACTION="Create" OBJVIEW="Accounting Voucher View">"#,
+        xml_escape(&ledgers.voucher_type)
+    );
+    xml.push_str(&tag("DATE", &tally_date(&invoice.invoice_date)));
+    xml.push_str(&tag("EFFECTIVEDATE", &tally_date(&invoice.invoice_date)));
+    xml.push_str(&tag("VOUCHERTYPENAME", &ledgers.voucher_type));
+    xml.push_str(&tag("VOUCHERNUMBER", &invoice.invoice_number));
+    if !invoice.work_order_reference.is_empty() {
+        xml.push_str(&tag("REFERENCE", &invoice.work_order_reference));
+    }
+    xml.push_str(&tag("PARTYLEDGERNAME", party));
+    xml.push_str(&tag("PARTYNAME", party));
+    if let Some(gstin) = customer.gst_number.as_deref().filter(|g| !g.is_empty()) {
+        xml.push_str(&tag("PARTYGSTIN", gstin));
+    }
+    if let Some(state) = customer_state(customer).and_then(gst::state_name) {
+        xml.push_str(&tag("STATENAME", state));
+        xml.push_str(&tag("PLACEOFSUPPLY", state));
+    }
+    xml.push_str(&tag("PERSISTEDVIEW", "Accounting Voucher View"));
+    xml.push_str(&tag("NARRATION", &format!("Invoice {} ({})", invoice.invoice_number, invoice.financial_year)));
+
+    let bill = format!(
+        "<BILLALLOCATIONS.LIST>{}{}{}</BILLALLOCATIONS.LIST>",
+        tag("NAME", &invoice.invoice_number),
+        tag("BILLTYPE", "New Ref"),
+        tag("AMOUNT", &amount(-invoice.grand_total))
+    );
+    xml.push_str(&entry(party, -invoice.grand_total, &bill));
+
+    let mut by_hsn: BTreeMap<String, f64> = BTreeMap::new();
+    for item in &invoice.line_items {
+        *by_hsn.entry(item.hsn_sac_code.trim().to_string()).or_default() += item.amount;
+    }
+    for (hsn, value) in &by_hsn {
+        let mut extra = String::new();
+        if !hsn.is_empty() {
+            extra.push_str(&tag("GSTHSNNAME", hsn));
+            extra.push_str(&tag("GSTOVRDNTYPEOFSUPPLY", if hsn.starts_with("99") { "Services" } else { "Goods" }));
+        }
+        extra.push_str(&tag("GSTOVRDNTAXABILITY", "Taxable"));
+        extra.push_str(&rate_details("CGST", invoice.cgst_percentage));
+        extra.push_str(&rate_details("SGST/UTGST", invoice.sgst_percentage));
+        xml.push_str(&entry(&ledgers.sales, round2(*value), &extra));
+    }
+
+    if invoice.cgst_amount != 0.0 {
+        xml.push_str(&entry(&ledgers.cgst, round2(invoice.cgst_amount), ""));
+    }
+    if invoice.sgst_amount != 0.0 {
+        xml.push_str(&entry(&ledgers.sgst, round2(invoice.sgst_amount), ""));
+    }
+    // Whatever makes the credits add up to the rounded invoice total
+    let tax = round2(invoice.cgst_amount) + round2(invoice.sgst_amount);
+    let credited: f64 = by_hsn.values().map(|v| round2(*v)).sum::<f64>() + tax;
+    let round_off = round2(invoice.grand_total - credited);
+    if round_off != 0.0 {
+        xml.push_str(&entry(&ledgers.round_off, round_off, ""));
+    }
+    xml.push_str("</VOUCHER>");
+    xml
+}
+
+fn envelope(report: &str, company: &str, messages: &[String]) -> String {
+    let company = if company.trim().is_empty() {
+        String::new()
+    } else {
+        format!("<STATICVARIABLES>{}</STATICVARIABLES>", tag("SVCURRENTCOMPANY", company.trim()))
+    };
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ENVELOPE><HEADER><TALLYREQUEST>Import Data</TALLYREQUEST></HEADER><BODY><IMPORTDATA><REQUESTDESC><REPORTNAME>{}</REPORTNAME>{}</REQUESTDESC><REQUESTDATA>"#,
+        report, company
+    );
+    for message in messages {
+        xml.push_str(&format!("\n<TALLYMESSAGE xmlns:UDF=\"TallyUDF\">{}</TALLYMESSAGE>", message));
+    }
+    xml.push_str("\n</REQUESTDATA></IMPORTDATA></BODY></ENVELOPE>\n");
+    xml
+}
+
+/// Writes the party ledgers and sales vouchers for the period as one Tally import file. The
+/// tax is booked under the heads on the invoice; invoices whose customer is in another state
+/// than the company's GSTIN are only reported, since an export is no place to change the tax.
+pub fn export(conn: &Connection, options: &TallyExportOptions, target: &Path) -> Result<TallyExportReport, String> {
+    let invoices = load_invoices(conn, options.from.as_deref(), options.to.as_deref())?;
+    let company_state = vault::company_settings(conn)?
+        .and_then(|settings| settings.get("gstNumber").and_then(Value::as_str).map(str::to_string))
+        .and_then(|gstin| gst::gstin_state(gstin.trim()));
+
+    let names = party_names(&invoices);
+    // The latest invoice has the most recent address for each party
+    let mut parties: BTreeMap<String, &Customer> = BTreeMap::new();
+    for invoice in &invoices {
+        parties.insert(names[&party_key(&invoice.customer)].clone(), &invoice.customer);
+    }
+    let mut messages: Vec<String> =
+        parties.iter().map(|(name, customer)| ledger_xml(name, customer, &options.ledgers.party_group)).collect();
+
+    let mut place_of_supply_mismatches = Vec::new();
+    for invoice in &invoices {
+        let charges_local_tax = invoice.cgst_amount != 0.0 || invoice.sgst_amount != 0.0;
+        if let (Some(ours), Some(theirs)) = (company_state, customer_state(&invoice.customer)) {
+            if ours != theirs && charges_local_tax {
+                place_of_supply_mismatches.push(invoice.invoice_number.clone());
+            }
+        }
+        let party = &names[&party_key(&invoice.customer)];
+        messages.push(voucher_xml(invoice, party, &options.ledgers));
+    }
+
+    fs::write(target, envelope("Vouchers", &options.company_name, &messages)).map_err(|e| e.to_string())?;
+    Ok(TallyExportReport {
+        path: target.to_string_lossy().into_owned(),
+        ledgers: parties.len(),
+        vouchers: invoices.len(),
+        place_of_supply_mismatches,
+    })
+}
+
+/// Exports the period's invoices as Tally XML, ready for Import > Transactions in Tally Prime.
+#[tauri::command]
+pub async fn export_tally_xml(app_handle: tauri::AppHandle, options: TallyExportOptions, target_path: String) -> Result<TallyExportReport, String> {
+    let conn = db::open(&app_handle)?;
+    let report = export(&conn, &options, Path::new(&target_path))?;
+    log::info!("Exported {} voucher(s) to Tally XML at {}", report.vouchers, target_path);
+    if !report.place_of_supply_mismatches.is_empty() {
+        log::warn!("Invoices with CGST + SGST for customers in another state: {}", report.place_of_supply_mismatches.join(", "));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoices;
+    use crate::test_support::{insert_customer, migrated_db, sample_invoice};
+
+    #[test]
+    fn tax_is_booked_as_invoiced_and_other_states_are_reported() {
+        let mut conn = migrated_db();
+        conn.execute(
+            "INSERT INTO settings(key, value) VALUES ('company_settings', ?1)",
+            [serde_json::json!({ "gstNumber": "19ABCDE1234F1Z5" }).to_string()],
+        )
+        .unwrap();
+        insert_customer(&conn, "c1", "Acme Pvt Ltd");
+        insert_customer(&conn, "c2", "Deccan Traders");
+        invoices::issue(&mut conn, sample_invoice("001", "c1")).unwrap();
+        let mut other_state = sample_invoice("002", "c2");
+        other_state.customer.company_name = "Deccan Traders".to_string();
+        other_state.customer.gst_number = Some("27AAACD1234A1Z5".to_string());
+        invoices::issue(&mut conn, other_state).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("tally.xml");
+        let options = TallyExportOptions { from: None, to: None, company_name: String::new(), ledgers: TallyLedgers::default() };
+        let report = export(&conn, &options, &target).unwrap();
+        assert_eq!((report.vouchers, report.ledgers), (2, 2));
+        assert_eq!(report.place_of_supply_mismatches, vec!["002"]);
+
+        let xml = fs::read_to_string(&target).unwrap();
+        assert!(!xml.contains("IGST"));
+        for ledger in ["CGST", "SGST"] {
+            let booked = format!("<LEDGERNAME>{}</LEDGERNAME><ISDEEMEDPOSITIVE>No</ISDEEMEDPOSITIVE><AMOUNT>13500.00</AMOUNT>", ledger);
+            assert_eq!(xml.matches(&booked).count(), 2);
+        }
+        assert!(xml.contains("<PLACEOFSUPPLY>Maharashtra</PLACEOFSUPPLY>"));
+    }
+
+    /// The voucher's ledger lines as (ledger, amount in paise); bill allocations are left out.
+    fn ledger_entries(xml: &str) -> Vec<(String, i64)> {
+        xml.split("<ALLLEDGERENTRIES.LIST>")
+            .skip(1)
+            .map(|entry| {
+                let between = |open: &str, close: &str| entry.split(open).nth(1).unwrap().split(close).next().unwrap().to_string();
+                let amount: f64 = between("<AMOUNT>", "</AMOUNT>").parse().unwrap();
+                (between("<LEDGERNAME>", "</LEDGERNAME>"), (amount * 100.0).round() as i64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_rounded_total_is_balanced_with_a_round_off_entry() {
+        let mut conn = migrated_db();
+        insert_customer(&conn, "c1", "Acme Pvt Ltd");
+        // 10000.40 + 5000.35 taxable, 900.05 CGST and SGST: 16800.85 rounded to the rupee both ways
+        for (number, grand_total, round_off) in [("001", 16801.0, 15), ("002", 16800.0, -85)] {
+            let mut invoice = sample_invoice(number, "c1");
+            let mut service = invoice.line_items[0].clone();
+            invoice.line_items[0].amount = 10000.40;
+            service.id = format!("{}-2", number);
+            service.serial_number = 2;
+            service.hsn_sac_code = "995442".to_string();
+            service.amount = 5000.35;
+            invoice.line_items.push(service);
+            invoice.total_basic_amount = 15000.75;
+            invoice.cgst_amount = 900.05;
+            invoice.sgst_amount = 900.05;
+            invoice.grand_total = grand_total;
+            invoices::issue(&mut conn, invoice).unwrap();
+
+            let dir = tempfile::tempdir().unwrap();
+            let target = dir.path().join("tally.xml");
+            let options = TallyExportOptions {
+                from: Some("2024-07-03".to_string()),
+                to: Some("2024-07-03".to_string()),
+                company_name: String::new(),
+                ledgers: TallyLedgers::default(),
+            };
+            export(&conn, &options, &target).unwrap();
+            let xml = fs::read_to_string(&target).unwrap();
+            let voucher = xml.split("<VOUCHERNUMBER>").find(|v| v.starts_with(&format!("{}<", number))).unwrap();
+            let entries = ledger_entries(voucher);
+
+            let ledgers = TallyLedgers::default();
+            let amount_of = |ledger: &str| entries.iter().filter(|(name, _)| name == ledger).map(|(_, paise)| *paise).collect::<Vec<_>>();
+            assert_eq!(amount_of(&ledgers.sales), vec![1000040, 500035], "{}", voucher);
+            assert_eq!(amount_of(&ledgers.round_off), vec![round_off], "{}", voucher);
+            assert_eq!(amount_of("Acme Pvt Ltd"), vec![-(grand_total * 100.0) as i64]);
+            assert_eq!(entries.iter().map(|(_, paise)| paise).sum::<i64>(), 0, "{}", voucher);
+            if round_off < 0 {
+                assert!(voucher.contains(&format!("<LEDGERNAME>{}</LEDGERNAME><ISDEEMEDPOSITIVE>Yes</ISDEEMEDPOSITIVE>", ledgers.round_off)));
+            }
+        }
+    }
+}
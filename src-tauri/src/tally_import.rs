@@ -0,0 +1,519 @@
+use crate::customer_import::{self, compact_upper, ImportReport};
+use crate::models::{CatalogueItem, Customer};
+use crate::{db, gst};
+use regex::{Captures, Regex};
+use roxmltree::{Document, Node};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemRow {
+    /// Line of the stock item in the file.
+    pub line: usize,
+    pub item: CatalogueItem,
+    pub action: String, // "create" | "update" | "skip" | "invalid"
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+    /// Catalogue item with the same name, if there is one.
+    pub duplicate_of: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TallyMastersReport {
+    /// Party ledgers, planned exactly like a customer spreadsheet import.
+    pub customers: ImportReport,
+    pub items: Vec<ItemRow>,
+    pub items_created: usize,
+    pub items_updated: usize,
+    pub items_skipped: usize,
+    pub items_invalid: usize,
+    /// Ledgers outside the party group (bank, expense, duty ledgers and so on).
+    pub ignored_ledgers: usize,
+    /// False for a dry run.
+    pub committed: bool,
+}
+
+/// Tally exports masters as UTF-16 unless told otherwise. UTF-8 is accepted with or without a
+/// BOM, and anything else is read as Latin-1, which is what Tally's "ASCII" option writes.
+fn decode(bytes: &[u8]) -> Result<String, String> {
+    let utf16 = |bytes: &[u8], big_endian: bool| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| if big_endian { u16::from_be_bytes([pair[0], pair[1]]) } else { u16::from_le_bytes([pair[0], pair[1]]) })
+            .collect();
+        String::from_utf16(&units).map_err(|_| "The file is not valid UTF-16 text".to_string())
+    };
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, false),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, true),
+        [b'<', 0, ..] => utf16(bytes, false),
+        [0, b'<', ..] => utf16(bytes, true),
+        _ => {
+            let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+            Ok(String::from_utf8(bytes.to_vec()).unwrap_or_else(|_| bytes.iter().map(|&b| b as char).collect()))
+        }
+    }
+}
+
+/// Reads an export file as text, whatever encoding Tally wrote it in.
+pub fn read_file(path: &Path) -> Result<String, String> {
+    decode(&fs::read(path).map_err(|e| e.to_string())?)
+}
+
+/// Tally writes control characters that XML 1.0 forbids, e.g. `&#4; Any` for the "any state"
+/// entry of a GST rate. They carry no meaning here, so they are dropped before parsing.
+fn strip_control_characters(xml: &str) -> Result<String, String> {
+    let reference = Regex::new(r"&#(x[0-9A-Fa-f]+|[0-9]+);").map_err(|e| e.to_string())?;
+    let is_forbidden = |code: u32| code < 0x20 && !matches!(code, 0x09 | 0x0A | 0x0D);
+    let xml: String = xml.chars().filter(|&c| !is_forbidden(c as u32)).collect();
+    Ok(reference
+        .replace_all(&xml, |caps: &Captures| {
+            let code = match caps[1].strip_prefix('x') {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => caps[1].parse().ok(),
+            };
+            if code.is_some_and(is_forbidden) {
+                String::new()
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .into_owned())
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn text(node: Node, name: &str) -> Option<String> {
+    child(node, name).and_then(|n| n.text()).map(str::trim).filter(|t| !t.is_empty()).map(str::to_string)
+}
+
+/// Tally keeps histories (GST details, addresses, price lists) as repeated lists, each with
+/// the date it applies from. The latest one is current.
+fn latest<'a, 'i>(node: Node<'a, 'i>, list: &str, date_tag: &str) -> Option<Node<'a, 'i>> {
+    node.children().filter(|n| n.has_tag_name(list)).max_by_key(|n| text(*n, date_tag).unwrap_or_default())
+}
+
+fn master_name(node: Node) -> Option<String> {
+    node.attribute("NAME")
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| {
+            node.descendants()
+                .find(|n| n.has_tag_name("NAME.LIST"))
+                .and_then(|list| text(list, "NAME"))
+        })
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    value.trim().replace(',', "").parse().ok()
+}
+
+/// Whether `group` is `target` or one of its sub-groups, following the GROUP masters in the
+/// file. Ledgers of a sub-group such as "Debtors - Kolkata" count as parties too.
+fn is_under(group: &str, target: &str, parents: &HashMap<String, String>) -> bool {
+    let target = target.trim().to_lowercase();
+    let mut current = group.trim().to_lowercase();
+    // Bounded in case a hand-edited file has a cycle
+    for _ in 0..32 {
+        if current == target {
+            return true;
+        }
+        match parents.get(&current) {
+            Some(parent) => current = parent.clone(),
+            None => return false,
+        }
+    }
+    false
+}
+
+fn ledger_customer(ledger: Node, name: &str) -> Customer {
+    // Tally Prime 3 moved the address into dated mailing details; older releases keep it on the ledger
+    let mailing = latest(ledger, "LEDMAILINGDETAILS.LIST", "APPLICABLEFROM");
+    let field = |tag: &str, mailing_tag: &str| text(ledger, tag).or_else(|| mailing.and_then(|m| text(m, mailing_tag)));
+
+    let mut lines: Vec<String> = child(ledger, "ADDRESS.LIST")
+        .or_else(|| mailing.and_then(|m| child(m, "ADDRESS.LIST")))
+        .map(|list| {
+            list.children()
+                .filter(|n| n.has_tag_name("ADDRESS"))
+                .filter_map(|n| n.text())
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let pincode = field("PINCODE", "PINCODE").map(|p| p.chars().filter(|c| !c.is_whitespace()).collect::<String>());
+
+    // The export in tally.rs ends the address with "City - Pincode"; take the city back from it
+    let mut city = None;
+    if let (Some(pin), Some(last)) = (&pincode, lines.last()) {
+        if let Some(rest) = last.strip_suffix(pin.as_str()) {
+            let rest = rest.trim_end().trim_end_matches('-').trim();
+            if !rest.is_empty() && !rest.contains(',') {
+                city = Some(rest.to_string());
+                lines.pop();
+            }
+        }
+    }
+
+    let state = field("LEDSTATENAME", "STATE")
+        .or_else(|| text(ledger, "STATENAME"))
+        .map(|state| gst::state_code(&state).and_then(gst::state_name).map(str::to_string).unwrap_or(state));
+    let gst_number = compact_upper(
+        text(ledger, "PARTYGSTIN").or_else(|| latest(ledger, "LEDGSTREGDETAILS.LIST", "APPLICABLEFROM").and_then(|d| text(d, "GSTIN"))),
+    );
+    // The printed name, when set, is the one invoices should carry. The export appends the
+    // GSTIN to ledger names that would otherwise clash, so strip that again.
+    let mut company_name = child(ledger, "MAILINGNAME.LIST")
+        .and_then(|l| text(l, "MAILINGNAME"))
+        .or_else(|| mailing.and_then(|m| text(m, "MAILINGNAME")))
+        .unwrap_or_else(|| name.to_string());
+    if let Some(gstin) = &gst_number {
+        if let Some(stripped) = company_name.strip_suffix(&format!(" ({})", gstin)) {
+            company_name = stripped.to_string();
+        }
+    }
+
+    let mut lines = lines.into_iter();
+    let address_line1 = lines.next().unwrap_or_default();
+    let address_line2 = lines.next();
+    let address_line3 = Some(lines.collect::<Vec<_>>().join(", ")).filter(|rest| !rest.is_empty());
+    Customer {
+        id: None,
+        company_name,
+        address_line1,
+        address_line2,
+        address_line3,
+        city,
+        state,
+        pincode,
+        gst_number,
+        pan_number: compact_upper(text(ledger, "INCOMETAXNUMBER")),
+        email: field("EMAIL", "EMAIL"),
+    }
+}
+
+fn stock_item(node: Node, name: &str) -> CatalogueItem {
+    let gst_details = latest(node, "GSTDETAILS.LIST", "APPLICABLEFROM");
+    let hsn_sac_code = latest(node, "HSNDETAILS.LIST", "APPLICABLEFROM")
+        .and_then(|h| text(h, "HSNCODE"))
+        .or_else(|| gst_details.and_then(|g| text(g, "HSNCODE")));
+    // The integrated rate is the total GST; CGST and SGST are half of it each
+    let gst_rate = gst_details.and_then(|g| {
+        g.descendants()
+            .filter(|n| n.has_tag_name("RATEDETAILS.LIST"))
+            .find(|r| matches!(text(*r, "GSTRATEDUTYHEAD").as_deref(), Some("IGST" | "Integrated Tax")))
+            .and_then(|r| text(r, "GSTRATE"))
+            .and_then(|rate| parse_number(&rate))
+    });
+    // Prices are written as "25000.00/Nos"
+    let rate = latest(node, "STANDARDPRICELIST.LIST", "DATE")
+        .and_then(|p| text(p, "RATE"))
+        .and_then(|rate| rate.split('/').next().and_then(parse_number));
+    CatalogueItem {
+        id: None,
+        name: name.to_string(),
+        description: text(node, "DESCRIPTION"),
+        hsn_sac_code,
+        unit: text(node, "BASEUNITS"),
+        rate,
+        gst_rate,
+    }
+}
+
+fn check_item(item: &CatalogueItem) -> Vec<String> {
+    let mut warnings = Vec::new();
+    match &item.hsn_sac_code {
+        None => warnings.push("No HSN/SAC code".to_string()),
+        Some(code) if !(code.chars().all(|c| c.is_ascii_digit()) && matches!(code.len(), 4 | 6 | 8)) => {
+            warnings.push(format!("HSN/SAC code {} should be 4, 6 or 8 digits", code))
+        }
+        _ => {}
+    }
+    if item.rate.is_none() {
+        warnings.push("No standard selling price; enter the rate on each invoice".to_string());
+    }
+    warnings
+}
+
+fn existing_items(conn: &Connection) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn.prepare("SELECT id, name FROM catalogue_items").map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(1)?.to_lowercase(), row.get::<_, String>(0)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Works out what importing a Tally masters export would do without writing anything.
+/// Ledgers under `party_group` become customers; stock items become catalogue items.
+pub fn plan(conn: &Connection, xml: &str, party_group: &str, on_duplicate: &str) -> Result<TallyMastersReport, String> {
+    let xml = strip_control_characters(xml)?;
+    let doc = Document::parse(&xml).map_err(|e| format!("The file is not valid XML: {}", e))?;
+    if !doc.root_element().has_tag_name("ENVELOPE") {
+        return Err("This is not a Tally export; it has no ENVELOPE".to_string());
+    }
+    let line = |node: Node| doc.text_pos_at(node.range().start).row as usize;
+
+    let parents: HashMap<String, String> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("GROUP"))
+        .filter_map(|group| Some((master_name(group)?.to_lowercase(), text(group, "PARENT")?.to_lowercase())))
+        .collect();
+    let ledgers: Vec<Node> = doc.descendants().filter(|n| n.has_tag_name("LEDGER")).collect();
+    let stock_items: Vec<Node> = doc.descendants().filter(|n| n.has_tag_name("STOCKITEM")).collect();
+    if ledgers.is_empty() && stock_items.is_empty() {
+        return Err("The file has no ledgers or stock items. In Tally, use Export > Masters with the XML format.".to_string());
+    }
+
+    let mut customers = Vec::new();
+    let mut ignored_ledgers = 0;
+    for ledger in ledgers {
+        let name = master_name(ledger).unwrap_or_default();
+        match text(ledger, "PARENT") {
+            Some(parent) if is_under(&parent, party_group, &parents) => customers.push((line(ledger), ledger_customer(ledger, &name))),
+            _ => ignored_ledgers += 1,
+        }
+    }
+    let mut report = TallyMastersReport {
+        customers: customer_import::plan_customers(conn, customers, on_duplicate)?,
+        ignored_ledgers,
+        ..Default::default()
+    };
+
+    let existing = existing_items(conn)?;
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for node in stock_items {
+        let item = stock_item(node, &master_name(node).unwrap_or_default());
+        let line = line(node);
+        let mut errors = Vec::new();
+        let mut warnings = check_item(&item);
+        let key = item.name.to_lowercase();
+        let duplicate_of = existing.get(&key).cloned();
+        if item.name.is_empty() {
+            errors.push("Stock item has no name".to_string());
+        }
+        let action = if !errors.is_empty() {
+            "invalid"
+        } else if let Some(earlier) = seen.get(&key) {
+            warnings.push(format!("Same item as line {}", earlier));
+            "skip"
+        } else if duplicate_of.is_some() {
+            on_duplicate
+        } else {
+            "create"
+        };
+        if action != "invalid" {
+            seen.entry(key).or_insert(line);
+        }
+        match action {
+            "create" => report.items_created += 1,
+            "update" => report.items_updated += 1,
+            "skip" => report.items_skipped += 1,
+            _ => report.items_invalid += 1,
+        }
+        report.items.push(ItemRow { line, item, action: action.to_string(), errors, warnings, duplicate_of });
+    }
+    Ok(report)
+}
+
+/// Applies a plan in one transaction, customers and items together.
+pub fn commit(conn: &mut Connection, report: &mut TallyMastersReport) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    customer_import::write_rows(&tx, &mut report.customers)?;
+    for row in report.items.iter_mut() {
+        let item = &mut row.item;
+        match row.action.as_str() {
+            "create" => {
+                let id = customer_import::new_id();
+                tx.execute(
+                    "INSERT INTO catalogue_items(id, name, description, hsn_sac_code, unit, rate, gst_rate)
+                     VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![id, item.name, item.description, item.hsn_sac_code, item.unit, item.rate, item.gst_rate],
+                )
+                .map_err(|e| format!("Line {}: {}", row.line, e))?;
+                item.id = Some(id);
+            }
+            "update" => {
+                let id = row.duplicate_of.clone().ok_or("Update without a matching catalogue item")?;
+                tx.execute(
+                    "UPDATE catalogue_items SET
+                        name = ?2, description = COALESCE(?3, description), hsn_sac_code = COALESCE(?4, hsn_sac_code),
+                        unit = COALESCE(?5, unit), rate = COALESCE(?6, rate), gst_rate = COALESCE(?7, gst_rate),
+                        updated_at = CURRENT_TIMESTAMP
+                     WHERE id = ?1",
+                    params![id, item.name, item.description, item.hsn_sac_code, item.unit, item.rate, item.gst_rate],
+                )
+                .map_err(|e| format!("Line {}: {}", row.line, e))?;
+                item.id = Some(id);
+            }
+            _ => {}
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    report.customers.committed = true;
+    report.committed = true;
+    Ok(())
+}
+
+/// Reads a Tally masters export and plans the import of its party ledgers and stock items.
+/// With `dry_run` nothing is written; otherwise the plan is applied in a single transaction.
+#[tauri::command]
+pub async fn import_tally_masters(
+    app_handle: tauri::AppHandle,
+    path: String,
+    party_group: String,
+    on_duplicate: String,
+    dry_run: bool,
+) -> Result<TallyMastersReport, String> {
+    let xml = read_file(Path::new(&path))?;
+    let mut conn = db::open(&app_handle)?;
+    let mut report = plan(&conn, &xml, &party_group, &on_duplicate)?;
+    if !dry_run {
+        commit(&mut conn, &mut report)?;
+        log::info!(
+            "Imported Tally masters from {}: {} customer(s) and {} item(s) created, {} and {} updated",
+            path,
+            report.customers.created,
+            report.items_created,
+            report.customers.updated,
+            report.items_updated
+        );
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_customer, migrated_db};
+
+    /// A masters export as Tally Prime writes it: groups nested under Sundry Debtors, dated GST
+    /// and mailing details listed newest first, and a raw and an escaped control character.
+    const MASTERS: &str = "<ENVELOPE>
+ <HEADER><TALLYREQUEST>Import Data</TALLYREQUEST></HEADER>
+ <BODY><IMPORTDATA><REQUESTDESC><REPORTNAME>All Masters</REPORTNAME></REQUESTDESC><REQUESTDATA>
+  <TALLYMESSAGE><GROUP NAME=\"Debtors - Kolkata\"><PARENT>Sundry Debtors</PARENT></GROUP></TALLYMESSAGE>
+  <TALLYMESSAGE><GROUP NAME=\"Rooftop Clients\"><PARENT>Debtors - Kolkata</PARENT></GROUP></TALLYMESSAGE>
+  <TALLYMESSAGE><LEDGER NAME=\"Acme Pvt Ltd\"><PARENT>Rooftop Clients</PARENT>
+   <LEDGSTREGDETAILS.LIST><APPLICABLEFROM>20230401</APPLICABLEFROM><GSTIN>27AAACA1234A1ZK</GSTIN></LEDGSTREGDETAILS.LIST>
+   <LEDGSTREGDETAILS.LIST><APPLICABLEFROM>20170701</APPLICABLEFROM><GSTIN>19AAACA1234A1ZH</GSTIN></LEDGSTREGDETAILS.LIST>
+   <LEDMAILINGDETAILS.LIST><APPLICABLEFROM>20230401</APPLICABLEFROM><MAILINGNAME>Acme Private Limited</MAILINGNAME>
+    <ADDRESS.LIST><ADDRESS>4 Marine Drive</ADDRESS><ADDRESS>Mumbai - 400002</ADDRESS></ADDRESS.LIST><STATE>Maharashtra</STATE><PINCODE>400002</PINCODE></LEDMAILINGDETAILS.LIST>
+   <LEDMAILINGDETAILS.LIST><APPLICABLEFROM>20170701</APPLICABLEFROM>
+    <ADDRESS.LIST><ADDRESS>12 Park Street</ADDRESS><ADDRESS>Kolkata - 700016</ADDRESS></ADDRESS.LIST><STATE>West Bengal</STATE><PINCODE>700016</PINCODE></LEDMAILINGDETAILS.LIST>
+  </LEDGER></TALLYMESSAGE>
+  <TALLYMESSAGE><LEDGER NAME=\"Sunrise Traders\"><PARENT>Sundry Debtors</PARENT><LEDSTATENAME>West Bengal</LEDSTATENAME></LEDGER></TALLYMESSAGE>
+  <TALLYMESSAGE><LEDGER NAME=\"HDFC Bank\"><PARENT>Bank Accounts</PARENT></LEDGER></TALLYMESSAGE>
+  <TALLYMESSAGE><LEDGER NAME=\"Output CGST\"><PARENT>Duties &amp; Taxes</PARENT></LEDGER></TALLYMESSAGE>
+  <TALLYMESSAGE><STOCKITEM NAME=\"Solar Panel 540W\"><BASEUNITS>Nos</BASEUNITS>
+   <GSTDETAILS.LIST><APPLICABLEFROM>20231001</APPLICABLEFROM><HSNCODE>85414300</HSNCODE>
+    <STATEWISEDETAILS.LIST><STATENAME>&#4; Any</STATENAME>
+     <RATEDETAILS.LIST><GSTRATEDUTYHEAD>CGST</GSTRATEDUTYHEAD><GSTRATE>6</GSTRATE></RATEDETAILS.LIST>
+     <RATEDETAILS.LIST><GSTRATEDUTYHEAD>IGST</GSTRATEDUTYHEAD><GSTRATE>12</GSTRATE></RATEDETAILS.LIST></STATEWISEDETAILS.LIST></GSTDETAILS.LIST>
+   <GSTDETAILS.LIST><APPLICABLEFROM>20170701</APPLICABLEFROM><HSNCODE>8541</HSNCODE>
+    <STATEWISEDETAILS.LIST><STATENAME>\u{4} Any</STATENAME>
+     <RATEDETAILS.LIST><GSTRATEDUTYHEAD>IGST</GSTRATEDUTYHEAD><GSTRATE>18</GSTRATE></RATEDETAILS.LIST></STATEWISEDETAILS.LIST></GSTDETAILS.LIST>
+   <STANDARDPRICELIST.LIST><DATE>20240401</DATE><RATE>25,000.00/Nos</RATE></STANDARDPRICELIST.LIST>
+   <STANDARDPRICELIST.LIST><DATE>20230401</DATE><RATE>28,000.00/Nos</RATE></STANDARDPRICELIST.LIST>
+  </STOCKITEM></TALLYMESSAGE>
+  <TALLYMESSAGE><STOCKITEM NAME=\"Installation\"><BASEUNITS>Job</BASEUNITS></STOCKITEM></TALLYMESSAGE>
+ </REQUESTDATA></IMPORTDATA></BODY>
+</ENVELOPE>";
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn parties_are_found_through_sub_groups() {
+        let parents: HashMap<String, String> = [("debtors - kolkata", "sundry debtors"), ("rooftop clients", "debtors - kolkata"), ("a", "b"), ("b", "a")]
+            .into_iter()
+            .map(|(group, parent)| (group.to_string(), parent.to_string()))
+            .collect();
+        assert!(is_under("Rooftop Clients", "Sundry Debtors", &parents));
+        assert!(is_under(" SUNDRY DEBTORS ", "Sundry Debtors", &parents));
+        assert!(!is_under("Bank Accounts", "Sundry Debtors", &parents));
+        assert!(!is_under("a", "Sundry Debtors", &parents));
+
+        let report = plan(&migrated_db(), MASTERS, "Sundry Debtors", "skip").unwrap();
+        let names: Vec<&str> = report.customers.rows.iter().map(|row| row.customer.company_name.as_str()).collect();
+        assert_eq!(names, vec!["Acme Private Limited", "Sunrise Traders"]);
+        assert_eq!(report.ignored_ledgers, 2);
+    }
+
+    #[test]
+    fn the_latest_dated_details_win() {
+        let report = plan(&migrated_db(), MASTERS, "Sundry Debtors", "skip").unwrap();
+        let acme = &report.customers.rows[0].customer;
+        assert_eq!(acme.gst_number.as_deref(), Some("27AAACA1234A1ZK"));
+        assert_eq!(acme.address_line1, "4 Marine Drive");
+        assert_eq!((acme.city.as_deref(), acme.pincode.as_deref()), (Some("Mumbai"), Some("400002")));
+        assert_eq!(acme.state.as_deref(), Some("Maharashtra"));
+
+        let panel = &report.items[0].item;
+        assert_eq!(panel.hsn_sac_code.as_deref(), Some("85414300"));
+        assert_eq!((panel.gst_rate, panel.rate), (Some(12.0), Some(25000.0)));
+        assert_eq!(report.items[1].warnings, vec!["No HSN/SAC code", "No standard selling price; enter the rate on each invoice"]);
+    }
+
+    #[test]
+    fn utf16_exports_and_control_characters_are_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Master.xml");
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(MASTERS.encode_utf16().flat_map(u16::to_le_bytes));
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(read_file(&path).unwrap(), MASTERS);
+
+        let big_endian: Vec<u8> = "<ENVELOPE/>".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(decode(&big_endian).unwrap(), "<ENVELOPE/>");
+        assert_eq!(decode(b"\xEF\xBB\xBF<A>Caf\xC3\xA9</A>").unwrap(), "<A>Café</A>");
+        assert_eq!(decode(b"<A>Caf\xE9</A>").unwrap(), "<A>Café</A>");
+
+        assert_eq!(strip_control_characters("<S>&#4; Any\u{4}&#x1F;&#10;&amp;</S>").unwrap(), "<S> Any&#10;&amp;</S>");
+        assert!(Document::parse(MASTERS).is_err());
+        assert!(plan(&migrated_db(), &read_file(&path).unwrap(), "Sundry Debtors", "skip").is_ok());
+    }
+
+    #[test]
+    fn existing_customers_and_items_are_skipped_or_updated() {
+        let mut conn = migrated_db();
+        insert_customer(&conn, "c1", "Acme Pvt Ltd");
+        conn.execute("UPDATE customers SET gst_number = '27AAACA1234A1ZK' WHERE id = 'c1'", []).unwrap();
+        conn.execute("INSERT INTO catalogue_items(id, name, rate) VALUES ('i1', 'solar panel 540w', 28000)", []).unwrap();
+
+        let report = plan(&conn, MASTERS, "Sundry Debtors", "skip").unwrap();
+        let customers: Vec<&str> = report.customers.rows.iter().map(|row| row.action.as_str()).collect();
+        let items: Vec<&str> = report.items.iter().map(|row| row.action.as_str()).collect();
+        assert_eq!((customers, items), (vec!["skip", "create"], vec!["skip", "create"]));
+        assert_eq!(report.items[0].duplicate_of.as_deref(), Some("i1"));
+
+        let mut report = plan(&conn, MASTERS, "Sundry Debtors", "update").unwrap();
+        assert_eq!((report.customers.updated, report.customers.created, report.items_updated, report.items_created), (1, 1, 1, 1));
+        commit(&mut conn, &mut report).unwrap();
+        let (name, city): (String, String) =
+            conn.query_row("SELECT company_name, city FROM customers WHERE id = 'c1'", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert_eq!((name.as_str(), city.as_str()), ("Acme Private Limited", "Mumbai"));
+        let (rate, hsn): (f64, String) =
+            conn.query_row("SELECT rate, hsn_sac_code FROM catalogue_items WHERE id = 'i1'", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert_eq!((rate, hsn.as_str()), (25000.0, "85414300"));
+        assert_eq!((count(&conn, "customers"), count(&conn, "catalogue_items")), (2, 2));
+    }
+
+    #[test]
+    fn a_dry_run_writes_nothing() {
+        let conn = migrated_db();
+        let report = plan(&conn, MASTERS, "Sundry Debtors", "update").unwrap();
+        assert_eq!((report.customers.created, report.items_created), (2, 2));
+        assert!(!report.committed && !report.customers.committed);
+        assert_eq!((count(&conn, "customers"), count(&conn, "catalogue_items")), (0, 0));
+    }
+}
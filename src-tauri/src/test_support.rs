@@ -0,0 +1,211 @@
+use crate::migrations;
+use crate::models::{Customer, Invoice, LineItem};
+use reqwest::Url;
+use rusqlite::{params, Connection};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Shared fixtures for the unit tests.
+
+/// An in-memory database with every migration applied and recorded the way the sql plugin does.
+pub fn migrated_db() -> Connection {
+    migrate(Connection::open_in_memory().unwrap())
+}
+
+/// A database file at `path`, migrated like [`migrated_db`].
+pub fn migrated_db_at(path: &Path) -> Connection {
+    migrate(Connection::open(path).unwrap())
+}
+
+fn migrate(conn: Connection) -> Connection {
+    conn.pragma_update(None, "foreign_keys", "ON").unwrap();
+    conn.execute_batch(
+        "CREATE TABLE _sqlx_migrations(version INTEGER PRIMARY KEY, description TEXT, success INTEGER NOT NULL)",
+    )
+    .unwrap();
+    for migration in migrations::migrations() {
+        conn.execute_batch(migration.sql).unwrap();
+        conn.execute(
+            "INSERT INTO _sqlx_migrations(version, description, success) VALUES (?1, ?2, 1)",
+            params![migration.version, migration.description],
+        )
+        .unwrap();
+    }
+    conn
+}
+
+pub fn insert_customer(conn: &Connection, id: &str, name: &str) {
+    conn.execute(
+        "INSERT INTO customers(id, company_name, address_line1, city, state) VALUES (?1, ?2, '12 Park Street', 'Kolkata', 'West Bengal')",
+        params![id, name],
+    )
+    .unwrap();
+}
+
+/// A one-line invoice for 5 kWp at 45,000 with 6% CGST and 6% SGST.
+pub fn sample_invoice(invoice_number: &str, customer_id: &str) -> Invoice {
+    Invoice {
+        invoice_number: invoice_number.to_string(),
+        financial_year: "24-25".to_string(),
+        invoice_date: "2024-07-03".to_string(),
+        work_order_reference: "WO/77".to_string(),
+        work_order_date: Some("2024-06-01".to_string()),
+        customer: Customer {
+            id: Some(customer_id.to_string()),
+            company_name: "Acme Pvt Ltd".to_string(),
+            address_line1: "12 Park Street".to_string(),
+            city: Some("Kolkata".to_string()),
+            state: Some("West Bengal".to_string()),
+            gst_number: Some("19AAACA1234A1Z5".to_string()),
+            ..Default::default()
+        },
+        line_items: vec![LineItem {
+            id: format!("{}-1", invoice_number),
+            serial_number: 1,
+            description: "Rooftop solar power plant".to_string(),
+            hsn_sac_code: "8541".to_string(),
+            rate: 45000.0,
+            quantity: 5.0,
+            unit: "Kwp".to_string(),
+            amount: 225000.0,
+        }],
+        total_basic_amount: 225000.0,
+        cgst_percentage: 6.0,
+        cgst_amount: 13500.0,
+        sgst_percentage: 6.0,
+        sgst_amount: 13500.0,
+        grand_total: 252000.0,
+        amount_in_words: "Two Lakh Fifty Two Thousand Rupees Only".to_string(),
+        ..Default::default()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    /// Path and query, as sent.
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    pub fn query(&self, key: &str) -> Option<String> {
+        pair(self.target.split_once('?')?.1, key)
+    }
+
+    /// A field of an `application/x-www-form-urlencoded` body.
+    pub fn form(&self, key: &str) -> Option<String> {
+        pair(&String::from_utf8_lossy(&self.body), key)
+    }
+}
+
+fn pair(encoded: &str, key: &str) -> Option<String> {
+    let url = Url::parse(&format!("http://localhost/?{}", encoded)).ok()?;
+    let value = url.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.into_owned());
+    value
+}
+
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        MockResponse { status, headers: Vec::new(), body: body.into() }
+    }
+
+    pub fn json(status: u16, value: serde_json::Value) -> Self {
+        MockResponse::new(status, value.to_string()).header("Content-Type", "application/json")
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A loopback HTTP server answering each request with `handler` and recording what it was sent.
+/// Every connection carries a single request.
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&MockRequest) -> MockResponse + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let Some(request) = read_request(&mut stream) else { continue };
+                let response = handler(&request);
+                recorded.lock().unwrap().push(request);
+                let _ = write_response(&mut stream, &response);
+            }
+        });
+        MockServer { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> Option<MockRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = (parts.next()?.to_string(), parts.next()?.to_string());
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    let length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(MockRequest { method, target, headers, body })
+}
+
+fn write_response(stream: &mut TcpStream, response: &MockResponse) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n", response.status, response.body.len());
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
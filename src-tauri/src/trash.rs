@@ -0,0 +1,310 @@
+use crate::db;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::Serialize;
+
+/// Settings key the frontend stores the in-progress invoice under.
+const DRAFT_KEY: &str = "draft_invoice";
+/// Settings key for how long trashed records are kept before a purge may remove them.
+const RETENTION_KEY: &str = "trash_retention_days";
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    pub kind: String, // "customer" | "draft"
+    pub id: String,
+    pub label: String,
+    pub deleted_at: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeReport {
+    pub retention_days: u32,
+    pub customers_purged: usize,
+    /// Trashed customers that issued invoices still point to. They stay in the trash.
+    pub customers_kept: usize,
+    pub drafts_purged: usize,
+}
+
+fn draft_label(json_data: &str) -> String {
+    let draft: serde_json::Value = serde_json::from_str(json_data).unwrap_or_default();
+    let number = draft["invoiceNumber"].as_str().filter(|s| !s.is_empty()).unwrap_or("(no number)");
+    let customer = draft["customer"]["companyName"].as_str().filter(|s| !s.is_empty()).unwrap_or("(no customer)");
+    format!("Draft {} - {}", number, customer)
+}
+
+pub fn trash_customer(conn: &Connection, id: &str) -> Result<(), String> {
+    let changed = conn
+        .execute("UPDATE customers SET deleted_at = CURRENT_TIMESTAMP WHERE id = ?1 AND deleted_at IS NULL", params![id])
+        .map_err(|e| e.to_string())?;
+    if changed == 0 {
+        return Err("Customer not found".to_string());
+    }
+    Ok(())
+}
+
+/// Moves the current draft invoice, if any, into the trash.
+pub fn trash_draft(conn: &mut Connection) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    trash_draft_in(&tx)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// [`trash_draft`] inside a transaction the caller owns.
+pub fn trash_draft_in(tx: &Transaction) -> Result<(), String> {
+    let draft: Option<Option<String>> = tx
+        .query_row("SELECT value FROM settings WHERE key = ?1", params![DRAFT_KEY], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    // Drafts are cleared by writing "null" in older builds, nothing worth keeping there
+    if let Some(json_data) = draft.flatten().filter(|v| !v.is_empty() && v != "null") {
+        tx.execute("INSERT INTO deleted_drafts(json_data) VALUES(?1)", params![json_data])
+            .map_err(|e| e.to_string())?;
+    }
+    tx.execute("DELETE FROM settings WHERE key = ?1", params![DRAFT_KEY])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<TrashItem>, String> {
+    let mut items = Vec::new();
+
+    let mut stmt = conn
+        .prepare("SELECT id, company_name, deleted_at FROM customers WHERE deleted_at IS NOT NULL")
+        .map_err(|e| e.to_string())?;
+    let customers = stmt
+        .query_map([], |row| {
+            Ok(TrashItem {
+                kind: "customer".to_string(),
+                id: row.get(0)?,
+                label: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                deleted_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    for customer in customers {
+        items.push(customer.map_err(|e| e.to_string())?);
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT id, json_data, deleted_at FROM deleted_drafts")
+        .map_err(|e| e.to_string())?;
+    let drafts = stmt
+        .query_map([], |row| {
+            Ok(TrashItem {
+                kind: "draft".to_string(),
+                id: row.get::<_, i64>(0)?.to_string(),
+                label: draft_label(&row.get::<_, String>(1)?),
+                deleted_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    for draft in drafts {
+        items.push(draft.map_err(|e| e.to_string())?);
+    }
+
+    items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(items)
+}
+
+/// Restores a trashed record. Restoring a draft swaps it with the current draft, which goes to the trash.
+pub fn restore(conn: &mut Connection, kind: &str, id: &str) -> Result<(), String> {
+    match kind {
+        "customer" => {
+            let changed = conn
+                .execute("UPDATE customers SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL", params![id])
+                .map_err(|e| e.to_string())?;
+            if changed == 0 {
+                return Err("Customer not found in trash".to_string());
+            }
+            Ok(())
+        }
+        "draft" => {
+            let draft_id: i64 = id.parse().map_err(|_| "Invalid draft id".to_string())?;
+            let tx = conn.transaction().map_err(|e| e.to_string())?;
+            let json_data: String = tx
+                .query_row("SELECT json_data FROM deleted_drafts WHERE id = ?1", params![draft_id], |row| row.get(0))
+                .optional()
+                .map_err(|e| e.to_string())?
+                .ok_or("Draft not found in trash")?;
+
+            trash_draft_in(&tx)?;
+            tx.execute("INSERT INTO settings(key, value) VALUES(?1, ?2)", params![DRAFT_KEY, json_data])
+                .map_err(|e| e.to_string())?;
+            tx.execute("DELETE FROM deleted_drafts WHERE id = ?1", params![draft_id])
+                .map_err(|e| e.to_string())?;
+            tx.commit().map_err(|e| e.to_string())
+        }
+        _ => Err(format!("Unknown trash item kind: {}", kind)),
+    }
+}
+
+fn retention_days(conn: &Connection) -> Result<u32, String> {
+    let value: Option<Option<String>> = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", params![RETENTION_KEY], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(value.flatten().and_then(|v| v.trim().parse().ok()).unwrap_or(DEFAULT_RETENTION_DAYS))
+}
+
+/// Permanently removes trashed records older than the retention period. Invoices are never purged;
+/// customers referenced by an invoice are kept so the invoice stays complete.
+pub fn purge(conn: &mut Connection, retention_days_override: Option<u32>) -> Result<PurgeReport, String> {
+    let retention_days = match retention_days_override {
+        Some(days) => days,
+        None => retention_days(conn)?,
+    };
+    let cutoff = format!("-{} days", retention_days);
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let customers_purged = tx
+        .execute(
+            "DELETE FROM customers WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?1)
+             AND NOT EXISTS(SELECT 1 FROM invoices WHERE invoices.customer_id = customers.id)",
+            params![cutoff],
+        )
+        .map_err(|e| e.to_string())?;
+    let customers_kept: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM customers WHERE deleted_at IS NOT NULL AND deleted_at <= datetime('now', ?1)",
+            params![cutoff],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let drafts_purged = tx
+        .execute("DELETE FROM deleted_drafts WHERE deleted_at <= datetime('now', ?1)", params![cutoff])
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(PurgeReport {
+        retention_days,
+        customers_purged,
+        customers_kept: customers_kept as usize,
+        drafts_purged,
+    })
+}
+
+#[tauri::command]
+pub async fn trash_customer_record(app_handle: tauri::AppHandle, id: String) -> Result<(), String> {
+    let conn = db::open(&app_handle)?;
+    trash_customer(&conn, &id)
+}
+
+#[tauri::command]
+pub async fn trash_draft_invoice(app_handle: tauri::AppHandle) -> Result<(), String> {
+    let mut conn = db::open(&app_handle)?;
+    trash_draft(&mut conn)
+}
+
+#[tauri::command]
+pub async fn list_trash(app_handle: tauri::AppHandle) -> Result<Vec<TrashItem>, String> {
+    let conn = db::open(&app_handle)?;
+    list(&conn)
+}
+
+#[tauri::command]
+pub async fn restore_from_trash(app_handle: tauri::AppHandle, kind: String, id: String) -> Result<(), String> {
+    let mut conn = db::open(&app_handle)?;
+    restore(&mut conn, &kind, &id)
+}
+
+#[tauri::command]
+pub async fn purge_trash(app_handle: tauri::AppHandle, retention_days: Option<u32>) -> Result<PurgeReport, String> {
+    let mut conn = db::open(&app_handle)?;
+    purge(&mut conn, retention_days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoices;
+    use crate::test_support::{insert_customer, migrated_db, sample_invoice};
+
+    fn trash_customer_days_ago(conn: &Connection, id: &str, days: u32) {
+        insert_customer(conn, id, id);
+        conn.execute(
+            "UPDATE customers SET deleted_at = datetime('now', ?2) WHERE id = ?1",
+            params![id, format!("-{} days", days)],
+        )
+        .unwrap();
+    }
+
+    fn trash_draft_days_ago(conn: &Connection, number: &str, days: u32) {
+        conn.execute(
+            "INSERT INTO deleted_drafts(json_data, deleted_at) VALUES(?1, datetime('now', ?2))",
+            params![serde_json::json!({ "invoiceNumber": number }).to_string(), format!("-{} days", days)],
+        )
+        .unwrap();
+    }
+
+    fn draft(conn: &Connection) -> Option<String> {
+        conn.query_row("SELECT value FROM settings WHERE key = ?1", params![DRAFT_KEY], |row| row.get(0))
+            .optional()
+            .unwrap()
+    }
+
+    #[test]
+    fn purge_keeps_records_for_the_retention_period() {
+        let mut conn = migrated_db();
+        trash_customer_days_ago(&conn, "old", 31);
+        trash_customer_days_ago(&conn, "recent", 20);
+        trash_draft_days_ago(&conn, "001", 31);
+        trash_draft_days_ago(&conn, "002", 20);
+
+        // 30 days unless the setting says otherwise
+        let report = purge(&mut conn, None).unwrap();
+        assert_eq!(report.retention_days, 30);
+        assert_eq!((report.customers_purged, report.drafts_purged), (1, 1));
+        let left: Vec<String> = list(&conn).unwrap().into_iter().map(|item| item.label).collect();
+        assert_eq!(left.len(), 2);
+        assert!(left.contains(&"recent".to_string()) && left.contains(&"Draft 002 - (no customer)".to_string()));
+
+        conn.execute("INSERT INTO settings(key, value) VALUES(?1, ' 14 ')", params![RETENTION_KEY]).unwrap();
+        let report = purge(&mut conn, None).unwrap();
+        assert_eq!(report.retention_days, 14);
+        assert_eq!((report.customers_purged, report.drafts_purged), (1, 1));
+        assert!(list(&conn).unwrap().is_empty());
+
+        trash_customer_days_ago(&conn, "today", 0);
+        assert_eq!(purge(&mut conn, Some(1)).unwrap().customers_purged, 0);
+        assert_eq!(purge(&mut conn, Some(0)).unwrap().customers_purged, 1);
+    }
+
+    #[test]
+    fn customers_that_invoices_refer_to_stay_in_the_trash() {
+        let mut conn = migrated_db();
+        insert_customer(&conn, "c1", "Acme Pvt Ltd");
+        invoices::issue(&mut conn, sample_invoice("001", "c1")).unwrap();
+        trash_customer(&conn, "c1").unwrap();
+        assert_eq!(trash_customer(&conn, "c1").unwrap_err(), "Customer not found");
+        conn.execute("UPDATE customers SET deleted_at = datetime('now', '-90 days') WHERE id = 'c1'", []).unwrap();
+
+        let report = purge(&mut conn, None).unwrap();
+        assert_eq!((report.customers_purged, report.customers_kept), (0, 1));
+        assert_eq!(list(&conn).unwrap()[0].id, "c1");
+        // Still there for the invoice, and can be brought back
+        restore(&mut conn, "customer", "c1").unwrap();
+        assert!(list(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn restoring_a_draft_swaps_it_with_the_current_one() {
+        let mut conn = migrated_db();
+        conn.execute("INSERT INTO settings(key, value) VALUES(?1, '{\"invoiceNumber\":\"001\"}')", params![DRAFT_KEY]).unwrap();
+        trash_draft(&mut conn).unwrap();
+        assert_eq!(draft(&conn), None);
+        conn.execute("INSERT INTO settings(key, value) VALUES(?1, '{\"invoiceNumber\":\"002\"}')", params![DRAFT_KEY]).unwrap();
+
+        let trashed = list(&conn).unwrap().remove(0);
+        assert_eq!(trashed.label, "Draft 001 - (no customer)");
+        restore(&mut conn, "draft", &trashed.id).unwrap();
+
+        assert_eq!(draft(&conn).as_deref(), Some("{\"invoiceNumber\":\"001\"}"));
+        let labels: Vec<String> = list(&conn).unwrap().into_iter().map(|item| item.label).collect();
+        assert_eq!(labels, vec!["Draft 002 - (no customer)"]);
+        assert_eq!(restore(&mut conn, "draft", &trashed.id).unwrap_err(), "Draft not found in trash");
+        // The failed restore left the current draft where it was
+        assert_eq!(draft(&conn).as_deref(), Some("{\"invoiceNumber\":\"001\"}"));
+    }
+}
@@ -0,0 +1,471 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+/// Emitted with an [`UpdateProgress`] while an installer is being downloaded.
+pub const PROGRESS_EVENT: &str = "update-download-progress";
+/// Published with every release next to the installers, along with `latest.json.sig`.
+const MANIFEST_URL: &str = "https://github.com/ArindamTripathi619/InvoiceGeneratorDesktop/releases/latest/download/latest.json";
+const SIGNATURE_SUFFIX: &str = ".sig";
+const STAGING_DIR: &str = "updates";
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What a release offers. Only trusted once `latest.json.sig` verifies against the built-in key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseManifest {
+    pub version: String,
+    #[serde(default)]
+    pub notes: String,
+    pub pub_date: Option<String>,
+    /// Installers keyed by `<os>-<arch>`, e.g. `windows-x86_64` or `linux-x86_64`.
+    pub platforms: HashMap<String, ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseAsset {
+    pub url: String,
+    /// File name the installer is staged under; its extension decides how it is launched.
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub latest_version: String,
+    pub has_update: bool,
+    pub notes: String,
+    pub pub_date: Option<String>,
+    /// The installer for this platform; `None` when the release has none.
+    pub asset: Option<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedUpdate {
+    pub version: String,
+    pub path: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProgress {
+    pub downloaded: u64,
+    pub total: u64,
+}
+
+/// The key releases are signed with, supplied at build time as base64 of the raw 32 bytes.
+pub fn public_key_from_build_env() -> Option<String> {
+    option_env!("APEX_UPDATE_PUBLIC_KEY").map(str::to_string)
+}
+
+pub fn platform_key() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Compares dotted versions. A pre-release (`1.2.0-beta`) sorts before its release, and
+/// pre-releases of one version by their dot-separated parts, numbers numerically (`beta.2` < `beta.10`).
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |v: &str| -> (Vec<u64>, Option<String>) {
+        let v = v.trim().trim_start_matches('v');
+        let (numbers, pre) = match v.split_once('-') {
+            Some((numbers, pre)) => (numbers, Some(pre.to_string())),
+            None => (v, None),
+        };
+        (numbers.split('.').map(|n| n.parse().unwrap_or(0)).collect(), pre)
+    };
+    let ((mut na, pre_a), (mut nb, pre_b)) = (parse(a), parse(b));
+    let length = na.len().max(nb.len());
+    na.resize(length, 0);
+    nb.resize(length, 0);
+    na.cmp(&nb).then_with(|| match (pre_a, pre_b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(pre_a), Some(pre_b)) => compare_pre_releases(&pre_a, &pre_b),
+    })
+}
+
+fn compare_pre_releases(a: &str, b: &str) -> Ordering {
+    let (mut parts_a, mut parts_b) = (a.split('.'), b.split('.'));
+    loop {
+        let ordering = match (parts_a.next(), parts_b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Checks `signature` (base64) over the exact manifest bytes, then parses them.
+pub fn verify_manifest(manifest: &[u8], signature: &str, public_key: &str) -> Result<ReleaseManifest, String> {
+    let key_bytes: [u8; 32] = STANDARD
+        .decode(public_key.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("The update signing key is invalid")?;
+    let key = VerifyingKey::from_bytes(&key_bytes).map_err(|_| "The update signing key is invalid".to_string())?;
+    let signature = STANDARD
+        .decode(signature.trim())
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or("The update signature is malformed")?;
+    key.verify_strict(manifest, &signature)
+        .map_err(|_| "The update manifest is not signed by the publisher. The update was not applied.".to_string())?;
+    serde_json::from_slice(manifest).map_err(|e| format!("Invalid update manifest: {}", e))
+}
+
+async fn fetch(http: &Client, url: &str) -> Result<Vec<u8>, String> {
+    let response = http.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("{} responded with {}", url, response.status()));
+    }
+    Ok(response.bytes().await.map_err(|e| e.to_string())?.to_vec())
+}
+
+/// Fetches the manifest and its signature, and reports whether it offers a newer version.
+pub async fn check(http: &Client, manifest_url: &str, public_key: &str, current_version: &str) -> Result<UpdateCheck, String> {
+    let manifest = fetch(http, manifest_url).await?;
+    let signature = fetch(http, &format!("{}{}", manifest_url, SIGNATURE_SUFFIX)).await?;
+    let manifest = verify_manifest(&manifest, &String::from_utf8_lossy(&signature), public_key)?;
+    Ok(UpdateCheck {
+        current_version: current_version.to_string(),
+        has_update: compare_versions(current_version, &manifest.version) == Ordering::Less,
+        asset: manifest.platforms.get(&platform_key()).cloned(),
+        latest_version: manifest.version,
+        notes: manifest.notes,
+        pub_date: manifest.pub_date,
+    })
+}
+
+/// Staged installers are run from this name, so a path outside the manifest can never be launched.
+fn check_asset_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid installer name in the update manifest: {}", name))
+    }
+}
+
+fn file_sha256(path: &Path) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    let mut file = fs::File::open(path).map_err(|e| e.to_string())?;
+    std::io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Downloads the installer into `staging_dir`, hashing it on the way. Only a file whose size and
+/// SHA-256 match the signed manifest is kept; anything previously staged is cleared first.
+pub async fn stage<F: FnMut(UpdateProgress)>(
+    http: &Client,
+    asset: &ReleaseAsset,
+    version: &str,
+    staging_dir: &Path,
+    mut on_progress: F,
+) -> Result<StagedUpdate, String> {
+    check_asset_name(&asset.name)?;
+    if staging_dir.exists() {
+        fs::remove_dir_all(staging_dir).map_err(|e| e.to_string())?;
+    }
+    fs::create_dir_all(staging_dir).map_err(|e| e.to_string())?;
+    let target = staging_dir.join(&asset.name);
+    let partial = staging_dir.join(format!("{}.partial", asset.name));
+
+    let mut response = http.get(&asset.url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("Downloading the update failed: the server responded with {}", response.status()));
+    }
+    let mut file = fs::File::create(&partial).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut progress = UpdateProgress { downloaded: 0, total: asset.size };
+    let mut last_report = Instant::now();
+    on_progress(progress);
+    let result: Result<(), String> = async {
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            progress.downloaded += chunk.len() as u64;
+            if progress.downloaded > asset.size {
+                return Err("The update is larger than the manifest says".to_string());
+            }
+            hasher.update(&chunk);
+            file.write_all(&chunk).map_err(|e| e.to_string())?;
+            if last_report.elapsed() >= PROGRESS_INTERVAL {
+                on_progress(progress);
+                last_report = Instant::now();
+            }
+        }
+        file.sync_all().map_err(|e| e.to_string())?;
+        on_progress(progress);
+        if progress.downloaded != asset.size {
+            return Err(format!("The update download is incomplete ({} of {} bytes)", progress.downloaded, asset.size));
+        }
+        let sha256 = hex::encode(hasher.finalize_reset());
+        if !sha256.eq_ignore_ascii_case(asset.sha256.trim()) {
+            return Err("The downloaded update does not match its published checksum. It was discarded.".to_string());
+        }
+        Ok(())
+    }
+    .await;
+    drop(file);
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+
+    fs::rename(&partial, &target).map_err(|e| e.to_string())?;
+    Ok(StagedUpdate {
+        version: version.to_string(),
+        path: target.to_string_lossy().into_owned(),
+        sha256: asset.sha256.trim().to_ascii_lowercase(),
+    })
+}
+
+/// Re-checks a staged installer right before it is run, in case it changed on disk since.
+pub fn verify_staged(staging_dir: &Path, staged: &StagedUpdate) -> Result<PathBuf, String> {
+    let path = PathBuf::from(&staged.path);
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    check_asset_name(&name)?;
+    if path.parent() != Some(staging_dir) || !path.is_file() {
+        return Err("The staged update is missing. Download it again.".to_string());
+    }
+    if file_sha256(&path)? != staged.sha256 {
+        let _ = fs::remove_file(&path);
+        return Err("The staged update was modified after it was downloaded. Download it again.".to_string());
+    }
+    Ok(path)
+}
+
+/// How a staged installer is applied once the app is about to close.
+enum Launch {
+    /// An installer that replaces the app; this process exits so it can.
+    Installer,
+    /// The running AppImage was swapped for the new one; restarting runs it.
+    Restart,
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).map_err(|e| e.to_string())
+}
+
+fn launch(installer: &Path) -> Result<Launch, String> {
+    let name = installer.to_string_lossy().to_lowercase();
+    let spawned = if name.ends_with(".msi") {
+        std::process::Command::new("msiexec").arg("/i").arg(installer).spawn()
+    } else if name.ends_with(".exe") {
+        std::process::Command::new(installer).spawn()
+    } else if name.ends_with(".appimage") {
+        #[cfg(unix)]
+        {
+            make_executable(installer)?;
+            // Tauri AppImages know where they run from; swap the file in place so shortcuts keep working
+            if let Some(current) = std::env::var_os("APPIMAGE").map(PathBuf::from) {
+                let swap = current.with_extension("update");
+                fs::copy(installer, &swap).map_err(|e| e.to_string())?;
+                make_executable(&swap)?;
+                fs::rename(&swap, &current).map_err(|e| e.to_string())?;
+                return Ok(Launch::Restart);
+            }
+        }
+        std::process::Command::new(installer).spawn()
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open").arg(installer).spawn()
+    } else {
+        std::process::Command::new("xdg-open").arg(installer).spawn()
+    };
+    spawned.map_err(|e| format!("Could not start the installer: {}", e))?;
+    Ok(Launch::Installer)
+}
+
+fn http_client() -> Result<Client, String> {
+    Client::builder()
+        .user_agent(concat!("ApexInvoice/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// `APEX_UPDATE_MANIFEST_URL` points the updater at a locally served manifest.
+fn manifest_url() -> String {
+    match std::env::var("APEX_UPDATE_MANIFEST_URL") {
+        Ok(url) if !url.is_empty() => url,
+        _ => MANIFEST_URL.to_string(),
+    }
+}
+
+fn public_key() -> Result<String, String> {
+    public_key_from_build_env().ok_or_else(|| "Updates are not configured in this build.".to_string())
+}
+
+fn staging_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let cache_dir = app_handle.path_resolver().app_cache_dir().ok_or("Failed to resolve app cache dir")?;
+    Ok(cache_dir.join(STAGING_DIR))
+}
+
+#[tauri::command]
+pub async fn check_for_update() -> Result<UpdateCheck, String> {
+    check(&http_client()?, &manifest_url(), &public_key()?, env!("CARGO_PKG_VERSION")).await
+}
+
+/// Verifies the manifest again and downloads this platform's installer, emitting [`PROGRESS_EVENT`]s.
+#[tauri::command]
+pub async fn download_update(app_handle: tauri::AppHandle) -> Result<StagedUpdate, String> {
+    let http = http_client()?;
+    let update = check(&http, &manifest_url(), &public_key()?, env!("CARGO_PKG_VERSION")).await?;
+    if !update.has_update {
+        return Err(format!("Version {} is already the latest", update.current_version));
+    }
+    let asset = update
+        .asset
+        .ok_or_else(|| format!("Version {} has no installer for this system ({})", update.latest_version, platform_key()))?;
+    stage(&http, &asset, &update.latest_version, &staging_dir(&app_handle)?, |progress| {
+        let _ = app_handle.emit_all(PROGRESS_EVENT, progress);
+    })
+    .await
+}
+
+/// Runs a staged installer and closes the app so it can replace it.
+#[tauri::command]
+pub async fn install_update(app_handle: tauri::AppHandle, staged: StagedUpdate) -> Result<(), String> {
+    let installer = verify_staged(&staging_dir(&app_handle)?, &staged)?;
+    log::info!("Installing update {} from {}", staged.version, installer.display());
+    match launch(&installer)? {
+        Launch::Installer => app_handle.exit(0),
+        Launch::Restart => app_handle.restart(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockResponse, MockServer};
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const PUBLISHER: [u8; 32] = [7; 32];
+
+    fn manifest() -> Vec<u8> {
+        serde_json::json!({
+            "version": "1.2.0",
+            "notes": "Faster PDF export",
+            "pubDate": "2024-07-03T10:15:00Z",
+            "platforms": {
+                "linux-x86_64": {
+                    "url": "https://example.com/apex-invoice_1.2.0_amd64.AppImage",
+                    "name": "apex-invoice_1.2.0_amd64.AppImage",
+                    "size": 13,
+                    "sha256": hex::encode(Sha256::digest(b"new installer")),
+                }
+            }
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    fn sign(key: &[u8; 32], bytes: &[u8]) -> String {
+        STANDARD.encode(SigningKey::from_bytes(key).sign(bytes).to_bytes())
+    }
+
+    fn public_key(key: &[u8; 32]) -> String {
+        STANDARD.encode(SigningKey::from_bytes(key).verifying_key().to_bytes())
+    }
+
+    #[test]
+    fn only_the_publishers_signature_over_the_exact_bytes_verifies() {
+        let manifest = manifest();
+        let signature = sign(&PUBLISHER, &manifest);
+        let verified = verify_manifest(&manifest, &signature, &public_key(&PUBLISHER)).unwrap();
+        assert_eq!(verified.version, "1.2.0");
+        assert_eq!(verified.platforms["linux-x86_64"].size, 13);
+
+        let error = verify_manifest(&manifest, &signature, &public_key(&[9; 32])).unwrap_err();
+        assert!(error.contains("not signed by the publisher"), "{}", error);
+        let tampered = String::from_utf8(manifest.clone()).unwrap().replace("1.2.0", "9.9.9").into_bytes();
+        let error = verify_manifest(&tampered, &signature, &public_key(&PUBLISHER)).unwrap_err();
+        assert!(error.contains("not signed by the publisher"), "{}", error);
+
+        assert_eq!(verify_manifest(&manifest, "not base64!", &public_key(&PUBLISHER)).unwrap_err(), "The update signature is malformed");
+        assert_eq!(verify_manifest(&manifest, &signature, "c2hvcnQ=").unwrap_err(), "The update signing key is invalid");
+    }
+
+    #[test]
+    fn versions_compare_numerically_with_pre_releases_first() {
+        assert_eq!(compare_versions("1.2.0-beta", "1.2.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.0-alpha", "1.2.0-beta"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.0-beta.2", "1.2.0-beta.10"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.0-beta", "1.2.0-beta.1"), Ordering::Less);
+        assert_eq!(compare_versions("1.2.0-rc.1", "1.1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("v1.2.0", "1.2.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.2", "1.2.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.2", "1.2.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("1.10.0", "1.9.9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn installer_names_cannot_leave_the_staging_folder() {
+        assert!(check_asset_name("apex-invoice_1.2.0_amd64.AppImage").is_ok());
+        assert!(check_asset_name("Apex Invoice_1.2.0_x64-setup.exe").is_err());
+        for name in ["", ".hidden", "../apex.exe", "updates/apex.exe", "..\\apex.exe", "C:apex.exe"] {
+            assert!(check_asset_name(name).is_err(), "{}", name);
+        }
+    }
+
+    async fn stage_served(body: &'static [u8], asset: ReleaseAsset, staging_dir: &Path) -> Result<StagedUpdate, String> {
+        let server = MockServer::start(move |_| MockResponse::new(200, body));
+        let asset = ReleaseAsset { url: format!("{}/{}", server.url, asset.name), ..asset };
+        stage(&Client::new(), &asset, "1.2.0", staging_dir, |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn only_a_download_matching_the_manifest_is_staged() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging_dir = dir.path().join(STAGING_DIR);
+        let asset = ReleaseAsset {
+            url: String::new(),
+            name: "apex-invoice_1.2.0_amd64.AppImage".to_string(),
+            size: 13,
+            sha256: hex::encode(Sha256::digest(b"new installer")),
+        };
+
+        let oversize = stage_served(b"new installer, and more", asset.clone(), &staging_dir).await.unwrap_err();
+        assert!(oversize.contains("larger than the manifest"), "{}", oversize);
+        let short = stage_served(b"new", asset.clone(), &staging_dir).await.unwrap_err();
+        assert!(short.contains("incomplete (3 of 13 bytes)"), "{}", short);
+        let tampered = stage_served(b"old installer", asset.clone(), &staging_dir).await.unwrap_err();
+        assert!(tampered.contains("does not match its published checksum"), "{}", tampered);
+        assert_eq!(fs::read_dir(&staging_dir).unwrap().count(), 0);
+
+        let staged = stage_served(b"new installer", asset.clone(), &staging_dir).await.unwrap();
+        assert_eq!(fs::read(&staged.path).unwrap(), b"new installer");
+        assert_eq!(verify_staged(&staging_dir, &staged).unwrap(), staging_dir.join(&asset.name));
+        fs::write(&staged.path, b"changed afterwards").unwrap();
+        assert!(verify_staged(&staging_dir, &staged).unwrap_err().contains("modified after it was downloaded"));
+    }
+}
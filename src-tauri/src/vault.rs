@@ -0,0 +1,381 @@
+use crate::{crypto, db, device, gdrive, sync};
+use rusqlite::{Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri_plugin_stronghold::kdf::KeyDerivation;
+use tauri_plugin_stronghold::stronghold::Stronghold;
+
+/// Encrypted Stronghold snapshot in the app config dir.
+pub const SNAPSHOT_FILE: &str = "vault.stronghold";
+/// Random salt the password is stretched with; useless without the password.
+pub const SALT_FILE: &str = "vault.salt";
+/// Returned when the vault exists but has not been unlocked this session; the frontend asks
+/// for the password and retries.
+pub const VAULT_LOCKED: &str = "VAULT_LOCKED";
+/// Store keys inside the vault.
+pub const BANK_DETAILS: &str = "bank_details";
+pub const GDRIVE_TOKENS: &str = "gdrive_tokens";
+pub const SMTP_PASSWORD: &str = "smtp_password";
+pub const SIGNING_PASSWORD: &str = "signing_password";
+pub const BACKUP_PASSPHRASE: &str = "backup_passphrase";
+pub const WEBDAV_PASSWORD: &str = "webdav_password";
+pub const S3_SECRET_KEY: &str = "s3_secret_access_key";
+const CLIENT: &[u8] = b"apex-invoice";
+const MIN_PASSWORD_LENGTH: usize = 8;
+/// Setting holding the company details as JSON; the secret fields used to live in it.
+const COMPANY_SETTINGS: &str = "company_settings";
+
+/// The bank fields printed on invoices that should not sit in the database in plain text.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankDetails {
+    pub account_number: String,
+    pub ifsc_code: String,
+}
+
+/// Fields left out are kept as they are.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BankDetailsUpdate {
+    pub account_number: Option<String>,
+    pub ifsc_code: Option<String>,
+}
+
+/// What the settings page shows for bank details held in the vault.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaskedBankDetails {
+    pub account_number: String,
+    pub ifsc_code: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultStatus {
+    pub exists: bool,
+    pub unlocked: bool,
+}
+
+/// The unlocked Stronghold, held in memory until the app closes or the vault is locked again.
+/// Only the plugin's snapshot and key derivation are used, not its JS API. Secrets are read on
+/// this side only: commands use them or return them masked, and the bank details go straight
+/// into the invoice PDFs.
+#[derive(Default)]
+pub struct Vault(Mutex<Option<Stronghold>>);
+
+pub fn exists(config_dir: &Path) -> bool {
+    config_dir.join(SNAPSHOT_FILE).is_file()
+}
+
+/// The vault's files that exist, for backups.
+pub fn files(config_dir: &Path) -> Vec<PathBuf> {
+    [SNAPSHOT_FILE, SALT_FILE].iter().map(|name| config_dir.join(name)).filter(|path| path.is_file()).collect()
+}
+
+impl Vault {
+    /// Opens the vault in `config_dir`, creating it with this password when there is none yet.
+    pub fn unlock(&self, config_dir: &Path, password: &str) -> Result<(), String> {
+        let snapshot = config_dir.join(SNAPSHOT_FILE);
+        let creating = !snapshot.is_file();
+        if creating && password.chars().count() < MIN_PASSWORD_LENGTH {
+            return Err(format!("The vault password must be at least {} characters.", MIN_PASSWORD_LENGTH));
+        }
+        if creating && config_dir.join(SALT_FILE).exists() {
+            // Left over from a vault that was deleted; a fresh vault gets a fresh salt
+            fs::remove_file(config_dir.join(SALT_FILE)).map_err(|e| e.to_string())?;
+        }
+        fs::create_dir_all(config_dir).map_err(|e| e.to_string())?;
+
+        let hash = KeyDerivation::argon2(password, &config_dir.join(SALT_FILE));
+        let stronghold = Stronghold::new(&snapshot, hash).map_err(|e| {
+            if creating {
+                e.to_string()
+            } else {
+                "The vault password is incorrect.".to_string()
+            }
+        })?;
+        if creating {
+            stronghold.create_client(CLIENT).map_err(|e| e.to_string())?;
+            stronghold.save().map_err(|e| e.to_string())?;
+        } else {
+            stronghold.load_client(CLIENT).map_err(|e| e.to_string())?;
+        }
+        *self.0.lock().unwrap() = Some(stronghold);
+        Ok(())
+    }
+
+    pub fn lock(&self) {
+        self.0.lock().unwrap().take();
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.0.lock().unwrap().is_some()
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let guard = self.0.lock().unwrap();
+        let stronghold = guard.as_ref().ok_or(VAULT_LOCKED)?;
+        let client = stronghold.get_client(CLIENT).map_err(|e| e.to_string())?;
+        client.store().get(key.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    /// Stores a value and writes the snapshot straight away.
+    pub fn put(&self, key: &str, value: Vec<u8>) -> Result<(), String> {
+        let guard = self.0.lock().unwrap();
+        let stronghold = guard.as_ref().ok_or(VAULT_LOCKED)?;
+        let client = stronghold.get_client(CLIENT).map_err(|e| e.to_string())?;
+        client.store().insert(key.as_bytes().to_vec(), value, None).map_err(|e| e.to_string())?;
+        stronghold.save().map_err(|e| e.to_string())
+    }
+
+    pub fn remove(&self, key: &str) -> Result<(), String> {
+        let guard = self.0.lock().unwrap();
+        let stronghold = guard.as_ref().ok_or(VAULT_LOCKED)?;
+        let client = stronghold.get_client(CLIENT).map_err(|e| e.to_string())?;
+        client.store().delete(key.as_bytes()).map_err(|e| e.to_string())?;
+        stronghold.save().map_err(|e| e.to_string())
+    }
+
+    pub fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        match self.get(key)? {
+            Some(bytes) => serde_json::from_slice(&bytes).map(Some).map_err(|e| e.to_string()),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put_json<T: Serialize>(&self, key: &str, value: &T) -> Result<(), String> {
+        self.put(key, serde_json::to_vec(value).map_err(|e| e.to_string())?)
+    }
+}
+
+/// The company details the settings page saves, if they have been saved yet.
+pub fn company_settings(conn: &Connection) -> Result<Option<Value>, String> {
+    let value: Option<Option<String>> = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [COMPANY_SETTINGS], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    match value.flatten() {
+        Some(json) => serde_json::from_str(&json).map(Some).map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+fn string_field(settings: &Value, field: &str) -> String {
+    settings.get(field).and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+/// Bank details still kept in the company settings, from before there was a vault.
+pub fn plain_bank_details(conn: &Connection) -> Result<BankDetails, String> {
+    let settings = company_settings(conn)?.unwrap_or(Value::Null);
+    Ok(BankDetails { account_number: string_field(&settings, "accountNumber"), ifsc_code: string_field(&settings, "ifscCode") })
+}
+
+/// Moves bank details out of the company settings into the vault. Details already in the
+/// vault win over what is left in the settings.
+pub fn migrate_bank_details(conn: &Connection, vault: &Vault) -> Result<bool, String> {
+    let Some(mut settings) = company_settings(conn)? else {
+        return Ok(false);
+    };
+    let plain = plain_bank_details(conn)?;
+    if plain == BankDetails::default() {
+        return Ok(false);
+    }
+    if vault.get_json::<BankDetails>(BANK_DETAILS)?.is_none() {
+        vault.put_json(BANK_DETAILS, &plain)?;
+    }
+    if let Some(object) = settings.as_object_mut() {
+        object.insert("accountNumber".to_string(), Value::String(String::new()));
+        object.insert("ifscCode".to_string(), Value::String(String::new()));
+    }
+    let json = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+    conn.execute("UPDATE settings SET value = ?1 WHERE key = ?2", rusqlite::params![json, COMPANY_SETTINGS])
+        .map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Keeps the first and last few characters, e.g. `SBIN•••••••` and `•••••••2674`.
+fn mask(value: &str, keep_start: usize, keep_end: usize) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= keep_start + keep_end {
+        return "•".repeat(chars.len());
+    }
+    let start: String = chars[..keep_start].iter().collect();
+    let end: String = chars[chars.len() - keep_end..].iter().collect();
+    format!("{}{}{}", start, "•".repeat(chars.len() - keep_start - keep_end), end)
+}
+
+fn status(config_dir: &Path, vault: &Vault) -> VaultStatus {
+    VaultStatus { exists: exists(config_dir), unlocked: vault.is_unlocked() }
+}
+
+#[tauri::command]
+pub async fn vault_status(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>) -> Result<VaultStatus, String> {
+    Ok(status(&device::config_dir(&app_handle)?, &vault))
+}
+
+/// Unlocks the vault, creating it on first use. Secrets still stored in plain text are moved
+/// into it right away.
+#[tauri::command]
+pub async fn vault_unlock(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    password: String,
+) -> Result<VaultStatus, String> {
+    let config_dir = device::config_dir(&app_handle)?;
+    vault.unlock(&config_dir, &password)?;
+
+    if let Ok(conn) = db::open(&app_handle) {
+        if migrate_bank_details(&conn, &vault)? {
+            log::info!("Moved bank details into the vault");
+        }
+    }
+    if gdrive::migrate_tokens(&app_handle, &vault)? {
+        log::info!("Moved Google Drive tokens into the vault");
+    }
+    if crypto::migrate_passphrase(&config_dir, &vault)? {
+        log::info!("Moved the backup passphrase into the vault");
+    }
+    if sync::migrate_secrets(&config_dir, &vault)? {
+        log::info!("Moved the sync provider credentials into the vault");
+    }
+    Ok(status(&config_dir, &vault))
+}
+
+#[tauri::command]
+pub async fn vault_lock(app_handle: tauri::AppHandle, vault: tauri::State<'_, Vault>) -> Result<VaultStatus, String> {
+    vault.lock();
+    Ok(status(&device::config_dir(&app_handle)?, &vault))
+}
+
+/// Masked bank details for the settings page, `None` when none are stored in the vault.
+#[tauri::command]
+pub async fn get_masked_bank_details(vault: tauri::State<'_, Vault>) -> Result<Option<MaskedBankDetails>, String> {
+    Ok(vault.get_json::<BankDetails>(BANK_DETAILS)?.map(|details| MaskedBankDetails {
+        account_number: mask(&details.account_number, 0, 4),
+        ifsc_code: mask(&details.ifsc_code, 4, 0),
+    }))
+}
+
+#[tauri::command]
+pub async fn save_bank_details(
+    app_handle: tauri::AppHandle,
+    vault: tauri::State<'_, Vault>,
+    details: BankDetailsUpdate,
+) -> Result<(), String> {
+    if !exists(&device::config_dir(&app_handle)?) {
+        return Err("Set up the vault before storing bank details in it.".to_string());
+    }
+    let mut stored = vault.get_json::<BankDetails>(BANK_DETAILS)?.unwrap_or_default();
+    if let Some(account_number) = details.account_number {
+        stored.account_number = account_number.trim().to_string();
+    }
+    if let Some(ifsc_code) = details.ifsc_code {
+        stored.ifsc_code = ifsc_code.trim().to_uppercase();
+    }
+    vault.put_json(BANK_DETAILS, &stored)
+}
+
+/// The full bank details, read only to print them on an invoice. Installs without a vault
+/// still have them in the company settings.
+pub fn invoice_bank_details(config_dir: &Path, vault: &Vault, conn: &Connection) -> Result<BankDetails, String> {
+    if exists(config_dir) {
+        if let Some(details) = vault.get_json(BANK_DETAILS)? {
+            return Ok(details);
+        }
+    }
+    plain_bank_details(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_db;
+
+    fn save_company(conn: &Connection, settings: Value) {
+        conn.execute(
+            "INSERT OR REPLACE INTO settings(key, value) VALUES (?1, ?2)",
+            rusqlite::params![COMPANY_SETTINGS, settings.to_string()],
+        )
+        .unwrap();
+    }
+
+    fn stored_company(conn: &Connection) -> String {
+        conn.query_row("SELECT value FROM settings WHERE key = ?1", [COMPANY_SETTINGS], |row| row.get(0)).unwrap()
+    }
+
+    fn bank(account_number: &str, ifsc_code: &str) -> BankDetails {
+        BankDetails { account_number: account_number.to_string(), ifsc_code: ifsc_code.to_string() }
+    }
+
+    #[test]
+    fn bank_details_move_out_of_the_company_settings_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = migrated_db();
+        let vault = Vault::default();
+        vault.unlock(dir.path(), "vault password").unwrap();
+        assert!(!migrate_bank_details(&conn, &vault).unwrap());
+
+        save_company(&conn, serde_json::json!({ "companyName": "Apex Solar", "accountNumber": "123456782674", "ifscCode": "SBIN0001234" }));
+        assert!(migrate_bank_details(&conn, &vault).unwrap());
+        assert_eq!(vault.get_json::<BankDetails>(BANK_DETAILS).unwrap(), Some(bank("123456782674", "SBIN0001234")));
+        let stored = stored_company(&conn);
+        assert!(!stored.contains("123456782674") && !stored.contains("SBIN0001234"), "{}", stored);
+        assert_eq!(plain_bank_details(&conn).unwrap(), BankDetails::default());
+        assert_eq!(company_settings(&conn).unwrap().unwrap()["companyName"], "Apex Solar");
+
+        // Nothing left to move the second time
+        assert!(!migrate_bank_details(&conn, &vault).unwrap());
+        // An older build writing them back does not overwrite what the vault holds
+        save_company(&conn, serde_json::json!({ "companyName": "Apex Solar", "accountNumber": "999", "ifscCode": "HDFC0000001" }));
+        assert!(migrate_bank_details(&conn, &vault).unwrap());
+        assert_eq!(vault.get_json::<BankDetails>(BANK_DETAILS).unwrap(), Some(bank("123456782674", "SBIN0001234")));
+        assert!(!stored_company(&conn).contains("HDFC0000001"));
+    }
+
+    #[test]
+    fn a_locked_vault_is_reported_and_nothing_is_moved() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = migrated_db();
+        let vault = Vault::default();
+        vault.unlock(dir.path(), "vault password").unwrap();
+        vault.lock();
+        save_company(&conn, serde_json::json!({ "accountNumber": "123456782674", "ifscCode": "SBIN0001234" }));
+
+        assert_eq!(migrate_bank_details(&conn, &vault).unwrap_err(), VAULT_LOCKED);
+        assert!(stored_company(&conn).contains("123456782674"));
+        assert_eq!(vault.get(BANK_DETAILS).unwrap_err(), VAULT_LOCKED);
+        assert_eq!(vault.put(BANK_DETAILS, Vec::new()).unwrap_err(), VAULT_LOCKED);
+        assert_eq!(invoice_bank_details(dir.path(), &vault, &conn).unwrap_err(), VAULT_LOCKED);
+
+        assert_eq!(Vault::default().unlock(dir.path(), "wrong password").unwrap_err(), "The vault password is incorrect.");
+    }
+
+    #[test]
+    fn masks_keep_only_the_ends() {
+        assert_eq!(mask("123456782674", 0, 4), "••••••••2674");
+        assert_eq!(mask("SBIN0001234", 4, 0), "SBIN•••••••");
+        // Too short to show anything
+        assert_eq!(mask("2674", 0, 4), "••••");
+        assert_eq!(mask("SBI", 4, 0), "•••");
+        assert_eq!(mask("", 0, 4), "");
+    }
+
+    #[test]
+    fn invoices_get_the_bank_details_from_wherever_they_are() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = migrated_db();
+        let vault = Vault::default();
+        save_company(&conn, serde_json::json!({ "accountNumber": "123456782674", "ifscCode": "SBIN0001234" }));
+        // No vault set up yet
+        assert_eq!(invoice_bank_details(dir.path(), &vault, &conn).unwrap(), bank("123456782674", "SBIN0001234"));
+
+        vault.unlock(dir.path(), "vault password").unwrap();
+        assert_eq!(invoice_bank_details(dir.path(), &vault, &conn).unwrap(), bank("123456782674", "SBIN0001234"));
+        vault.put_json(BANK_DETAILS, &bank("000111222333", "HDFC0000001")).unwrap();
+        assert_eq!(invoice_bank_details(dir.path(), &vault, &conn).unwrap(), bank("000111222333", "HDFC0000001"));
+    }
+}
@@ -6,11 +6,15 @@ import InvoiceHistory from './components/InvoiceHistory';
 import CustomerManagement from './components/CustomerManagement';
 import SplashScreen from './components/SplashScreen';
 import { UpdateTab } from './components/UpdateTab';
+import SyncConflictDialog from './components/SyncConflictDialog';
+import VaultUnlockDialog from './components/VaultUnlockDialog';
 import { ThemeProvider, useTheme } from './contexts/ThemeContext';
 import packageJson from '../package.json';
 import { COMPANY_DETAILS, FOOTER_DETAILS } from './utils/constants';
 import { dbService } from './services/db';
 import { updateService } from './services/updateService';
+import { fileOpenService, FileOpenRequest } from './services/fileOpen';
+import { importChangesetFile } from './services/changesetImport';
 import { message } from '@tauri-apps/api/dialog';
 
 type TabType = 'create' | 'history' | 'customers' | 'settings' | 'updates';
@@ -18,6 +22,10 @@ type TabType = 'create' | 'history' | 'customers' | 'settings' | 'updates';
 function AppContent() {
   const [activeTab, setActiveTab] = useState<TabType>('create');
   const [showSplash, setShowSplash] = useState(true);
+  // Backup chosen in the sync conflict dialog, restored through the Settings restore flow
+  const [pendingRestore, setPendingRestore] = useState<string | null>(null);
+  // Invoice number of a PDF opened with the app, shown in the history tab
+  const [historySearch, setHistorySearch] = useState('');
   const { theme, toggleTheme } = useTheme();
 
   useEffect(() => {
@@ -55,6 +63,44 @@ function AppContent() {
     return () => clearTimeout(timer);
   }, []);
 
+  // Files double-clicked or dropped on the app icon, including ones a second launch handed over
+  useEffect(() => {
+    const handleRequest = async (request: FileOpenRequest) => {
+      switch (request.kind) {
+        case 'backup':
+          setPendingRestore(request.path);
+          setActiveTab('settings');
+          break;
+        case 'changeset':
+          await importChangesetFile(request.path, 'local');
+          break;
+        case 'invoice':
+          setHistorySearch(request.invoiceNumber || '');
+          setActiveTab('history');
+          break;
+        default:
+          await message(`This file can't be opened here:\n${request.path}`, { title: 'Unsupported File', type: 'warning' });
+      }
+    };
+
+    let active = true;
+    let unlisten: (() => void) | undefined;
+    fileOpenService
+      .onRequests(async (requests) => {
+        for (const request of requests) {
+          await handleRequest(request);
+        }
+      })
+      .then((fn) => {
+        if (active) unlisten = fn;
+        else fn();
+      });
+    return () => {
+      active = false;
+      unlisten?.();
+    };
+  }, []);
+
   // Check if splash has been shown in this session
   useEffect(() => {
     const splashShown = sessionStorage.getItem('splashShown');
@@ -73,6 +119,11 @@ function AppContent() {
     return <SplashScreen onComplete={handleSplashComplete} theme={theme} />;
   }
 
+  const handleUseRemote = (path: string) => {
+    setPendingRestore(path);
+    setActiveTab('settings');
+  };
+
   const tabs = [
     { id: 'create' as TabType, label: 'Create Invoice', icon: FileText },
     { id: 'history' as TabType, label: 'Invoice History', icon: History },
@@ -153,12 +204,15 @@ function AppContent() {
 
       <main className="py-2 sm:py-4 md:py-8">
         {activeTab === 'create' && <InvoiceForm />}
-        {activeTab === 'history' && <InvoiceHistory />}
+        {activeTab === 'history' && <InvoiceHistory initialSearch={historySearch} />}
         {activeTab === 'customers' && <CustomerManagement />}
-        {activeTab === 'settings' && <Settings />}
+        {activeTab === 'settings' && <Settings restorePath={pendingRestore} onRestoreStarted={() => setPendingRestore(null)} />}
         {activeTab === 'updates' && <UpdateTab />}
       </main>
 
+      <SyncConflictDialog onUseRemote={handleUseRemote} />
+      <VaultUnlockDialog />
+
       <footer className="bg-white dark:bg-gray-800 border-t border-gray-200 dark:border-gray-700 mt-4 sm:mt-8 md:mt-12 transition-colors duration-200">
         <div className="max-w-7xl mx-auto px-2 sm:px-4 md:px-6 py-3 sm:py-4 md:py-6">
           <div className="text-center text-[10px] sm:text-xs md:text-sm text-gray-600 dark:text-gray-400 transition-colors duration-200">
@@ -0,0 +1,106 @@
+import { useState } from 'react';
+import { GitMerge, Loader2, Upload, Download } from 'lucide-react';
+import { open, message } from '@tauri-apps/api/dialog';
+import { backupService, ChangesetPreference } from '../services/backup';
+import { importChangesetFile } from '../services/changesetImport';
+
+// Exchanges row-level changes with another installation, so invoices raised on two computers
+// can be merged instead of one backup replacing the other
+export default function ChangesetSync() {
+  const [fullExport, setFullExport] = useState(false);
+  const [prefer, setPrefer] = useState<ChangesetPreference>('local');
+  const [isExporting, setIsExporting] = useState(false);
+  const [isImporting, setIsImporting] = useState(false);
+
+  const handleExport = async () => {
+    let targetDir: string | null = backupService.getBackupPath() || null;
+    if (!targetDir) {
+      const selected = await open({ directory: true, multiple: false, title: 'Select Folder for the Changes File' });
+      if (!selected || typeof selected !== 'string') return;
+      targetDir = selected;
+    }
+
+    setIsExporting(true);
+    try {
+      const result = await backupService.exportChangeset(targetDir, fullExport);
+      if (!result.path) {
+        await message('Nothing changed since the last export.', { title: 'No Changes', type: 'info' });
+      } else {
+        await message(`Exported ${result.changes} change(s) to:\n${result.path}\n\nImport this file on the other computer.`, {
+          title: 'Changes Exported',
+          type: 'info'
+        });
+      }
+    } catch (e: any) {
+      await message(`Export failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
+  const handleImport = async () => {
+    const selected = await open({
+      directory: false,
+      multiple: false,
+      filters: [{ name: 'Invoice Changes', extensions: ['json'] }],
+      title: 'Select Changes File From Another Computer'
+    });
+    if (!selected || typeof selected !== 'string') return;
+
+    setIsImporting(true);
+    try {
+      await importChangesetFile(selected, prefer);
+    } finally {
+      setIsImporting(false);
+    }
+  };
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-1 flex items-center gap-2 transition-colors duration-200">
+        <GitMerge size={22} /> Merge With Another Computer
+      </h2>
+      <p className="text-sm text-gray-600 dark:text-gray-300 mb-4 transition-colors duration-200">
+        Export the invoices and customers changed here since the last export, and import the other computer's file to combine both.
+        Issued invoices are never overwritten by a different copy.
+      </p>
+
+      <div className="flex flex-wrap items-center gap-4 mb-4">
+        <button
+          onClick={handleExport}
+          disabled={isExporting}
+          className="flex items-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50"
+        >
+          {isExporting ? <Loader2 size={16} className="animate-spin" /> : <Upload size={16} />}
+          Export Changes
+        </button>
+        <label className="flex items-center gap-2 text-sm text-gray-700 dark:text-gray-300">
+          <input type="checkbox" checked={fullExport} onChange={(e) => setFullExport(e.target.checked)} />
+          Include everything, not just recent changes
+        </label>
+      </div>
+
+      <div className="flex flex-wrap items-center gap-4">
+        <button
+          onClick={handleImport}
+          disabled={isImporting}
+          className="flex items-center gap-2 px-4 py-2 bg-gray-600 dark:bg-gray-500 text-white rounded-lg hover:bg-gray-700 disabled:opacity-50"
+        >
+          {isImporting ? <Loader2 size={16} className="animate-spin" /> : <Download size={16} />}
+          Import Changes
+        </button>
+        <label className="flex items-center gap-2 text-sm text-gray-700 dark:text-gray-300">
+          When both changed a record:
+          <select
+            value={prefer}
+            onChange={(e) => setPrefer(e.target.value as ChangesetPreference)}
+            className="px-2 py-1 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 text-gray-800 dark:text-gray-200 rounded-lg"
+          >
+            <option value="local">Keep this computer's version</option>
+            <option value="remote">Take the other computer's version</option>
+          </select>
+        </label>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,220 @@
+import { useState } from 'react';
+import { FileUp, Loader2, X, CheckCircle2 } from 'lucide-react';
+import { open, message } from '@tauri-apps/api/dialog';
+import {
+  customerImportService,
+  ColumnMapping,
+  CUSTOMER_FIELDS,
+  CustomerField,
+  DuplicateHandling,
+  ImportFile,
+  ImportReport,
+} from '../services/customerImport';
+
+interface CustomerImportProps {
+  onClose: () => void;
+  onImported: () => void;
+}
+
+const ACTION_STYLES: Record<string, string> = {
+  create: 'bg-green-100 text-green-800 dark:bg-green-900 dark:text-green-200',
+  update: 'bg-blue-100 text-blue-800 dark:bg-blue-900 dark:text-blue-200',
+  skip: 'bg-gray-200 text-gray-700 dark:bg-gray-600 dark:text-gray-200',
+  invalid: 'bg-red-100 text-red-800 dark:bg-red-900 dark:text-red-200',
+};
+
+const selectClass = 'w-full px-3 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg text-sm';
+
+// Bulk import: pick a CSV/XLSX file, map its columns, check the dry-run report, then save
+export default function CustomerImport({ onClose, onImported }: CustomerImportProps) {
+  const [path, setPath] = useState<string | null>(null);
+  const [file, setFile] = useState<ImportFile | null>(null);
+  const [mapping, setMapping] = useState<ColumnMapping>({});
+  const [onDuplicate, setOnDuplicate] = useState<DuplicateHandling>('skip');
+  const [report, setReport] = useState<ImportReport | null>(null);
+  const [isBusy, setIsBusy] = useState(false);
+
+  const handleChooseFile = async () => {
+    const selected = await open({
+      directory: false,
+      multiple: false,
+      filters: [{ name: 'Spreadsheet', extensions: ['csv', 'xlsx'] }],
+      title: 'Select Customer List'
+    });
+    if (!selected || typeof selected !== 'string') return;
+
+    setIsBusy(true);
+    try {
+      const loaded = await customerImportService.readFile(selected);
+      setPath(selected);
+      setFile(loaded);
+      setMapping(loaded.suggestedMapping);
+      setReport(null);
+    } catch (e: any) {
+      await message(`Could not read the file: ${e.message || e}`, { title: 'Import', type: 'error' });
+    } finally {
+      setIsBusy(false);
+    }
+  };
+
+  const setColumn = (field: CustomerField, value: string) => {
+    setMapping({ ...mapping, [field]: value === '' ? null : Number(value) });
+    setReport(null);
+  };
+
+  const handleCheck = async () => {
+    if (!path) return;
+    setIsBusy(true);
+    try {
+      setReport(await customerImportService.dryRun(path, mapping, onDuplicate));
+    } catch (e: any) {
+      await message(`${e.message || e}`, { title: 'Import', type: 'error' });
+    } finally {
+      setIsBusy(false);
+    }
+  };
+
+  const handleImport = async () => {
+    if (!path) return;
+    setIsBusy(true);
+    try {
+      const result = await customerImportService.commit(path, mapping, onDuplicate);
+      setReport(result);
+      onImported();
+      await message(`${result.created} customer(s) added and ${result.updated} updated.`, { title: 'Import Complete', type: 'info' });
+    } catch (e: any) {
+      await message(`Import failed, nothing was saved: ${e.message || e}`, { title: 'Import', type: 'error' });
+    } finally {
+      setIsBusy(false);
+    }
+  };
+
+  const toSave = report ? report.created + report.updated : 0;
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg mb-8 border-2 border-blue-200 dark:border-blue-600 transition-colors duration-200 shrink-0 overflow-y-auto max-h-[70vh]">
+      <div className="flex justify-between items-center mb-4">
+        <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100">Import Customers</h2>
+        <button onClick={onClose} className="text-gray-500 dark:text-gray-400 hover:text-gray-700 dark:hover:text-gray-300">
+          <X size={24} />
+        </button>
+      </div>
+
+      <div className="flex items-center gap-3 mb-4">
+        <button
+          onClick={handleChooseFile}
+          disabled={isBusy}
+          className="flex items-center gap-2 px-4 py-2 bg-blue-600 dark:bg-blue-500 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50"
+        >
+          {isBusy && !file ? <Loader2 size={16} className="animate-spin" /> : <FileUp size={16} />}
+          Choose CSV or Excel File
+        </button>
+        {file && <span className="text-sm text-gray-600 dark:text-gray-300 truncate">{path} ({file.totalRows} rows)</span>}
+      </div>
+
+      {file && (
+        <>
+          <h3 className="font-medium text-gray-800 dark:text-gray-100 mb-2">Columns</h3>
+          <div className="grid grid-cols-1 md:grid-cols-3 gap-3 mb-4">
+            {CUSTOMER_FIELDS.map(({ field, label }) => (
+              <div key={field}>
+                <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">
+                  {label}{field === 'companyName' && <span className="text-red-500"> *</span>}
+                </label>
+                <select value={mapping[field] ?? ''} onChange={(e) => setColumn(field, e.target.value)} className={selectClass}>
+                  <option value="">(not imported)</option>
+                  {file.headers.map((header, i) => <option key={i} value={i}>{header || `Column ${i + 1}`}</option>)}
+                </select>
+              </div>
+            ))}
+          </div>
+
+          <div className="overflow-x-auto mb-4">
+            <table className="text-xs text-gray-700 dark:text-gray-300 border-collapse">
+              <thead>
+                <tr>{file.headers.map((h, i) => <th key={i} className="border border-gray-300 dark:border-gray-600 px-2 py-1 text-left">{h}</th>)}</tr>
+              </thead>
+              <tbody>
+                {file.sampleRows.map((row, r) => (
+                  <tr key={r}>{file.headers.map((_, i) => <td key={i} className="border border-gray-300 dark:border-gray-600 px-2 py-1">{row[i]}</td>)}</tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+
+          <div className="flex flex-wrap items-center gap-4 mb-4">
+            <label className="text-sm text-gray-700 dark:text-gray-300">
+              Customers that already exist (same GSTIN or name):
+              <select
+                value={onDuplicate}
+                onChange={(e) => { setOnDuplicate(e.target.value as DuplicateHandling); setReport(null); }}
+                className="ml-2 px-2 py-1 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 rounded-lg"
+              >
+                <option value="skip">Leave unchanged</option>
+                <option value="update">Update with the file's values</option>
+              </select>
+            </label>
+            <button
+              onClick={handleCheck}
+              disabled={isBusy || mapping.companyName == null}
+              className="px-4 py-2 bg-gray-600 dark:bg-gray-500 text-white rounded-lg hover:bg-gray-700 disabled:opacity-50"
+            >
+              Check File
+            </button>
+          </div>
+        </>
+      )}
+
+      {report && (
+        <>
+          <p className="text-sm text-gray-700 dark:text-gray-300 mb-2">
+            {report.created} new, {report.updated} to update, {report.skipped} skipped, {report.invalid} with errors.
+            {report.invalid > 0 && !report.committed && ' Rows with errors are left out.'}
+          </p>
+          <div className="overflow-x-auto mb-4">
+            <table className="w-full text-xs text-gray-700 dark:text-gray-300">
+              <thead>
+                <tr className="text-left">
+                  <th className="px-2 py-1">Row</th>
+                  <th className="px-2 py-1">Customer</th>
+                  <th className="px-2 py-1">GSTIN</th>
+                  <th className="px-2 py-1">Result</th>
+                  <th className="px-2 py-1">Details</th>
+                </tr>
+              </thead>
+              <tbody>
+                {report.rows.map((row) => (
+                  <tr key={row.line} className="border-t border-gray-200 dark:border-gray-600 align-top">
+                    <td className="px-2 py-1">{row.line}</td>
+                    <td className="px-2 py-1">{row.customer.companyName}</td>
+                    <td className="px-2 py-1">{row.customer.gstNumber}</td>
+                    <td className="px-2 py-1">
+                      <span className={`px-2 py-0.5 rounded ${ACTION_STYLES[row.action]}`}>{row.action}</span>
+                    </td>
+                    <td className="px-2 py-1">
+                      {row.duplicateOf && <div>Matches "{row.duplicateOf.companyName}" by {row.duplicateOf.matchedBy === 'gstin' ? 'GSTIN' : 'name'}</div>}
+                      {row.errors.map((e, i) => <div key={`e${i}`} className="text-red-600 dark:text-red-400">{e}</div>)}
+                      {row.warnings.map((w, i) => <div key={`w${i}`} className="text-amber-600 dark:text-amber-400">{w}</div>)}
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+          {report.committed ? (
+            <p className="flex items-center gap-2 text-green-700 dark:text-green-400"><CheckCircle2 size={18} /> Import saved.</p>
+          ) : (
+            <button
+              onClick={handleImport}
+              disabled={isBusy || toSave === 0}
+              className="flex items-center gap-2 px-6 py-3 bg-green-600 dark:bg-green-500 text-white rounded-lg hover:bg-green-700 disabled:opacity-50"
+            >
+              {isBusy ? <Loader2 size={18} className="animate-spin" /> : <FileUp size={18} />}
+              Import {toSave} Customer(s)
+            </button>
+          )}
+        </>
+      )}
+    </div>
+  );
+}
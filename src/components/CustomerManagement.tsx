@@ -1,8 +1,9 @@
 import { useState, useEffect } from 'react';
-import { Users, Plus, Trash2, CreditCard as Edit2, X, Save, Loader2 } from 'lucide-react';
+import { Users, Plus, Trash2, CreditCard as Edit2, X, Save, Loader2, FileUp } from 'lucide-react';
 import { ask, message } from '@tauri-apps/api/dialog';
 import { Customer } from '../types/invoice';
 import { customerService } from '../services/customerService';
+import CustomerImport from './CustomerImport';
 
 export default function CustomerManagement() {
   const [customers, setCustomers] = useState<Customer[]>([]);
@@ -10,6 +11,7 @@ export default function CustomerManagement() {
   const [editingCustomerId, setEditingCustomerId] = useState<string | null>(null);
   const [isLoading, setIsLoading] = useState<boolean>(true);
   const [isSaving, setIsSaving] = useState<boolean>(false);
+  const [isImporting, setIsImporting] = useState<boolean>(false);
 
   const [formData, setFormData] = useState<Omit<Customer, 'id'>>({
     companyName: '',
@@ -21,6 +23,7 @@ export default function CustomerManagement() {
     pincode: '',
     gstNumber: '',
     panNumber: '',
+    email: '',
   });
 
   useEffect(() => {
@@ -50,6 +53,7 @@ export default function CustomerManagement() {
       pincode: '',
       gstNumber: '',
       panNumber: '',
+      email: '',
     });
     setIsAddingCustomer(false);
     setEditingCustomerId(null);
@@ -72,6 +76,15 @@ export default function CustomerManagement() {
       return;
     }
 
+    const badEmail = (formData.email || '').split(/[,;]/).map(e => e.trim()).find(e => e && !/^[^\s@]+@[^\s@]+\.[^\s@]+$/.test(e));
+    if (badEmail) {
+      await message(`"${badEmail}" is not a valid email address`, {
+        title: 'Validation Error',
+        type: 'error'
+      });
+      return;
+    }
+
     setIsSaving(true);
     try {
       const customerToSave: Customer = editingCustomerId
@@ -112,6 +125,7 @@ export default function CustomerManagement() {
       pincode: customer.pincode || '',
       gstNumber: customer.gstNumber || '',
       panNumber: customer.panNumber || '',
+      email: customer.email || '',
     });
     setEditingCustomerId(customer.id || null);
     setIsAddingCustomer(true);
@@ -119,7 +133,7 @@ export default function CustomerManagement() {
 
   const handleDeleteCustomer = async (id: string, companyName: string) => {
     const confirmed = await ask(
-      `Are you sure you want to delete customer "${companyName}"?\n\nThis action cannot be undone.`,
+      `Move customer "${companyName}" to the recycle bin?\n\nIt can be restored from Settings until the trash is purged.`,
       {
         title: 'Confirm Deletion',
         type: 'warning'
@@ -130,7 +144,7 @@ export default function CustomerManagement() {
       try {
         await customerService.deleteCustomer(id);
         await loadCustomers();
-        await message(`Customer "${companyName}" has been deleted successfully.`, {
+        await message(`Customer "${companyName}" has been moved to the recycle bin.`, {
           title: 'Deleted',
           type: 'info'
         });
@@ -152,18 +166,29 @@ export default function CustomerManagement() {
             <h1 className="text-3xl font-bold text-gray-800 dark:text-gray-100 mb-2 transition-colors duration-200">Customer Management</h1>
             <p className="text-gray-600 dark:text-gray-400 transition-colors duration-200">Manage customer templates for quick invoice creation</p>
           </div>
-          {!isAddingCustomer && (
-            <button
-              onClick={() => setIsAddingCustomer(true)}
-              className="flex items-center gap-2 px-6 py-3 bg-blue-600 dark:bg-blue-500 text-white rounded-lg hover:bg-blue-700 dark:hover:bg-blue-600 transition-all duration-200"
-            >
-              <Plus size={20} />
-              Add Customer
-            </button>
+          {!isAddingCustomer && !isImporting && (
+            <div className="flex gap-3">
+              <button
+                onClick={() => setIsImporting(true)}
+                className="flex items-center gap-2 px-6 py-3 border border-blue-600 dark:border-blue-400 text-blue-600 dark:text-blue-400 rounded-lg hover:bg-blue-50 dark:hover:bg-gray-700 transition-all duration-200"
+              >
+                <FileUp size={20} />
+                Import
+              </button>
+              <button
+                onClick={() => setIsAddingCustomer(true)}
+                className="flex items-center gap-2 px-6 py-3 bg-blue-600 dark:bg-blue-500 text-white rounded-lg hover:bg-blue-700 dark:hover:bg-blue-600 transition-all duration-200"
+              >
+                <Plus size={20} />
+                Add Customer
+              </button>
+            </div>
           )}
         </div>
       </div>
 
+      {isImporting && <CustomerImport onClose={() => setIsImporting(false)} onImported={loadCustomers} />}
+
       {isAddingCustomer && (
         <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg mb-8 border-2 border-blue-200 dark:border-blue-600 transition-colors duration-200 shrink-0">
           <div className="flex justify-between items-center mb-4">
@@ -209,6 +234,19 @@ export default function CustomerManagement() {
               />
             </div>
 
+            <div>
+              <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2">
+                Email for Invoices
+              </label>
+              <input
+                type="text"
+                value={formData.email}
+                onChange={(e) => setFormData({ ...formData, email: e.target.value })}
+                placeholder="accounts@customer.com, purchase@customer.com"
+                className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 transition-colors duration-200"
+              />
+            </div>
+
             <div className="flex gap-3 justify-end">
               <button onClick={resetForm} className="px-6 py-2 bg-gray-300 dark:bg-gray-600 rounded-lg">Cancel</button>
               <button
@@ -280,6 +318,11 @@ export default function CustomerManagement() {
                         <span className="font-medium text-gray-700 dark:text-gray-300">PAN:</span> {customer.panNumber}
                       </p>
                     )}
+                    {customer.email && (
+                      <p className="line-clamp-1">
+                        <span className="font-medium text-gray-700 dark:text-gray-300">Email:</span> {customer.email}
+                      </p>
+                    )}
                   </div>
                 </div>
               </div>
@@ -0,0 +1,146 @@
+import { useEffect, useState } from 'react';
+import { FileSpreadsheet, Loader2 } from 'lucide-react';
+import { save, message } from '@tauri-apps/api/dialog';
+import { dataExportService, ExportColumn, ExportDataset, ExportDateFormat, ExportFormat } from '../services/dataExport';
+
+const DATASETS: { value: ExportDataset; label: string; fileName: string }[] = [
+  { value: 'invoices', label: 'Invoices (one row per invoice)', fileName: 'invoices' },
+  { value: 'items', label: 'Line items (one row per item)', fileName: 'invoice-items' },
+  { value: 'customers', label: 'Customers', fileName: 'customers' },
+];
+
+const inputClass = 'w-full px-3 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 dark:focus:ring-blue-400 focus:border-transparent transition-colors duration-200';
+
+// Spreadsheet export for the accountant: invoices, line items or customers as CSV or Excel
+export default function DataExport() {
+  const [dataset, setDataset] = useState<ExportDataset>('invoices');
+  const [format, setFormat] = useState<ExportFormat>('xlsx');
+  const [dateFormat, setDateFormat] = useState<ExportDateFormat>('indian');
+  const [from, setFrom] = useState('');
+  const [to, setTo] = useState('');
+  const [includeCancelled, setIncludeCancelled] = useState(false);
+  const [columns, setColumns] = useState<ExportColumn[]>([]);
+  const [selected, setSelected] = useState<Set<string>>(new Set());
+  const [isExporting, setIsExporting] = useState(false);
+
+  useEffect(() => {
+    dataExportService.getColumns(dataset)
+      .then((cols) => {
+        setColumns(cols);
+        setSelected(new Set(cols.map((c) => c.key)));
+      })
+      .catch((e) => console.error('Failed to load export columns:', e));
+  }, [dataset]);
+
+  const toggleColumn = (key: string) => {
+    const next = new Set(selected);
+    if (next.has(key)) next.delete(key);
+    else next.add(key);
+    setSelected(next);
+  };
+
+  const handleExport = async () => {
+    if (selected.size === 0) {
+      await message('Select at least one column to export.', { title: 'Export', type: 'warning' });
+      return;
+    }
+    const name = DATASETS.find((d) => d.value === dataset)!.fileName;
+    const target = await save({
+      title: 'Export Spreadsheet',
+      defaultPath: `${name}.${format}`,
+      filters: [format === 'xlsx' ? { name: 'Excel Workbook', extensions: ['xlsx'] } : { name: 'CSV', extensions: ['csv'] }],
+    });
+    if (!target) return;
+
+    setIsExporting(true);
+    try {
+      const report = await dataExportService.exportTo(target, {
+        dataset,
+        format,
+        dateFormat,
+        from: from || undefined,
+        to: to || undefined,
+        // Keep the default column order whatever order the boxes were ticked in
+        columns: columns.filter((c) => selected.has(c.key)).map((c) => c.key),
+        includeCancelled,
+      });
+      await message(`Exported ${report.rows} row(s) to:\n${report.path}`, { title: 'Export Complete', type: 'info' });
+    } catch (e: any) {
+      await message(`Export failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-1 flex items-center gap-2 transition-colors duration-200">
+        <FileSpreadsheet size={22} /> Export to Spreadsheet
+      </h2>
+      <p className="text-sm text-gray-600 dark:text-gray-300 mb-4 transition-colors duration-200">
+        Issued invoices, their line items or the customer list as a CSV or Excel file. Drafts are never exported.
+      </p>
+
+      <div className="grid grid-cols-1 md:grid-cols-3 gap-4 mb-4">
+        <div>
+          <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">Data</label>
+          <select value={dataset} onChange={(e) => setDataset(e.target.value as ExportDataset)} className={inputClass}>
+            {DATASETS.map((d) => <option key={d.value} value={d.value}>{d.label}</option>)}
+          </select>
+        </div>
+        <div>
+          <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">Format</label>
+          <select value={format} onChange={(e) => setFormat(e.target.value as ExportFormat)} className={inputClass}>
+            <option value="xlsx">Excel (.xlsx)</option>
+            <option value="csv">CSV</option>
+          </select>
+        </div>
+        <div>
+          <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">Dates</label>
+          <select value={dateFormat} onChange={(e) => setDateFormat(e.target.value as ExportDateFormat)} className={inputClass}>
+            <option value="indian">DD-MM-YYYY</option>
+            <option value="iso">YYYY-MM-DD</option>
+          </select>
+        </div>
+      </div>
+
+      {dataset !== 'customers' && (
+        <div className="grid grid-cols-1 md:grid-cols-3 gap-4 mb-4 items-end">
+          <div>
+            <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">From</label>
+            <input type="date" value={from} onChange={(e) => setFrom(e.target.value)} className={inputClass} />
+          </div>
+          <div>
+            <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">To</label>
+            <input type="date" value={to} onChange={(e) => setTo(e.target.value)} className={inputClass} />
+          </div>
+          <label className="flex items-center gap-2 text-sm text-gray-700 dark:text-gray-300 pb-2">
+            <input type="checkbox" checked={includeCancelled} onChange={(e) => setIncludeCancelled(e.target.checked)} />
+            Include cancelled invoices
+          </label>
+        </div>
+      )}
+
+      <div className="mb-4">
+        <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2">Columns</label>
+        <div className="grid grid-cols-2 md:grid-cols-3 gap-1">
+          {columns.map((c) => (
+            <label key={c.key} className="flex items-center gap-2 text-sm text-gray-700 dark:text-gray-300">
+              <input type="checkbox" checked={selected.has(c.key)} onChange={() => toggleColumn(c.key)} />
+              {c.header}
+            </label>
+          ))}
+        </div>
+      </div>
+
+      <button
+        onClick={handleExport}
+        disabled={isExporting}
+        className="flex items-center gap-2 px-4 py-2 bg-green-600 dark:bg-green-500 text-white rounded-lg hover:bg-green-700 disabled:opacity-50"
+      >
+        {isExporting ? <Loader2 size={16} className="animate-spin" /> : <FileSpreadsheet size={16} />}
+        Export
+      </button>
+    </div>
+  );
+}
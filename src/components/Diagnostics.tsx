@@ -0,0 +1,47 @@
+import { useState } from 'react';
+import { LifeBuoy, Loader2, FileArchive } from 'lucide-react';
+import { open, message } from '@tauri-apps/api/dialog';
+import { createDiagnosticsBundle } from '../services/logger';
+
+// Packs what support needs to look into a problem into one zip the user can send
+export default function Diagnostics() {
+  const [isCreating, setIsCreating] = useState(false);
+
+  const handleCreate = async () => {
+    const selected = await open({ directory: true, multiple: false, title: 'Select Folder for the Diagnostics File' });
+    if (!selected || typeof selected !== 'string') return;
+
+    setIsCreating(true);
+    try {
+      const path = await createDiagnosticsBundle(selected);
+      await message(`Diagnostics saved to:\n${path}\n\nSend this file to support. Passwords, tokens, bank details and tax numbers are left out.`, {
+        title: 'Diagnostics Created',
+        type: 'info'
+      });
+    } catch (e: any) {
+      console.error('Failed to create diagnostics bundle:', e);
+      await message(`Could not create the diagnostics file: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setIsCreating(false);
+    }
+  };
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-1 flex items-center gap-2 transition-colors duration-200">
+        <LifeBuoy size={22} /> Diagnostics
+      </h2>
+      <p className="text-sm text-gray-600 dark:text-gray-300 mb-4 transition-colors duration-200">
+        Collects the app logs, version, database statistics and settings into a zip file for support. No invoices or customer records are included.
+      </p>
+      <button
+        onClick={handleCreate}
+        disabled={isCreating}
+        className="flex items-center gap-2 px-4 py-2 bg-gray-600 dark:bg-gray-500 text-white rounded-lg hover:bg-gray-700 disabled:opacity-50"
+      >
+        {isCreating ? <Loader2 size={16} className="animate-spin" /> : <FileArchive size={16} />}
+        Create Diagnostics File
+      </button>
+    </div>
+  );
+}
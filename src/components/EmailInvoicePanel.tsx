@@ -0,0 +1,115 @@
+import { useEffect, useState } from 'react';
+import { Mail, Loader2, Send, CheckCircle2, XCircle } from 'lucide-react';
+import { message } from '@tauri-apps/api/dialog';
+import { Invoice } from '../types/invoice';
+import { dbService } from '../services/db';
+import { emailService, EmailLogEntry, OutgoingEmail } from '../services/email';
+import { generateInvoicePDF } from '../services/pdfGenerator';
+
+interface EmailInvoicePanelProps {
+  invoice: Invoice;
+  onClose: () => void;
+}
+
+const inputClass = 'w-full px-3 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 rounded-lg text-sm';
+
+// Sends the invoice PDF to the customer, with the recipients and text filled in from the settings
+export default function EmailInvoicePanel({ invoice, onClose }: EmailInvoicePanelProps) {
+  const [email, setEmail] = useState<OutgoingEmail | null>(null);
+  const [history, setHistory] = useState<EmailLogEntry[]>([]);
+  const [isSending, setIsSending] = useState(false);
+  const [loadError, setLoadError] = useState<string | null>(null);
+
+  useEffect(() => {
+    emailService.preview(invoice.invoiceNumber)
+      .then(setEmail)
+      .catch((e) => setLoadError(`${e.message || e}`));
+    emailService.getHistory(invoice.invoiceNumber)
+      .then(setHistory)
+      .catch((e) => console.error('Failed to load email history:', e));
+  }, [invoice.invoiceNumber]);
+
+  const handleSend = async () => {
+    if (!email) return;
+    setIsSending(true);
+    try {
+      const companySettings = await dbService.getCompanySettings();
+      const stampSignature = await dbService.getStampSignature();
+      const companyLogo = await dbService.getCompanyLogo();
+      const pdfFile = await generateInvoicePDF(invoice, companySettings, stampSignature || undefined, companyLogo || undefined, false);
+      const sent = await emailService.sendInvoice(invoice.invoiceNumber, pdfFile, email);
+      setHistory([sent, ...history]);
+      await message(`Invoice sent to ${sent.recipients}.`, { title: 'Email Sent', type: 'info' });
+      onClose();
+    } catch (e: any) {
+      // Failed attempts are logged too
+      emailService.getHistory(invoice.invoiceNumber).then(setHistory).catch(() => undefined);
+      await message(`${e.message || e}`, { title: 'Email Failed', type: 'error' });
+    } finally {
+      setIsSending(false);
+    }
+  };
+
+  return (
+    <div className="mt-4 p-4 rounded-lg border border-blue-200 dark:border-blue-800 bg-blue-50 dark:bg-blue-900/20 space-y-3">
+      <h4 className="font-medium text-gray-800 dark:text-gray-100 flex items-center gap-2"><Mail size={18} /> Email Invoice</h4>
+      {loadError ? (
+        <p className="text-sm text-red-700 dark:text-red-300">{loadError}</p>
+      ) : !email ? (
+        <Loader2 size={20} className="animate-spin text-blue-600" />
+      ) : (
+        <>
+          <div className="grid grid-cols-1 md:grid-cols-2 gap-3">
+            <div>
+              <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">To</label>
+              <input type="text" value={email.to} onChange={(e) => setEmail({ ...email, to: e.target.value })} placeholder="customer@example.com" className={inputClass} />
+            </div>
+            <div>
+              <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">CC</label>
+              <input type="text" value={email.cc} onChange={(e) => setEmail({ ...email, cc: e.target.value })} className={inputClass} />
+            </div>
+          </div>
+          <div>
+            <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Subject</label>
+            <input type="text" value={email.subject} onChange={(e) => setEmail({ ...email, subject: e.target.value })} className={inputClass} />
+          </div>
+          <div>
+            <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Message</label>
+            <textarea value={email.body} onChange={(e) => setEmail({ ...email, body: e.target.value })} rows={6} className={inputClass} />
+          </div>
+        </>
+      )}
+
+      <div className="flex gap-2">
+        <button
+          onClick={handleSend}
+          disabled={!email || isSending || !email.to.trim()}
+          className="flex items-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50"
+        >
+          {isSending ? <Loader2 size={16} className="animate-spin" /> : <Send size={16} />}
+          Send with PDF
+        </button>
+        <button onClick={onClose} className="px-4 py-2 text-gray-600 dark:text-gray-300 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg">
+          Close
+        </button>
+      </div>
+
+      {history.length > 0 && (
+        <div className="text-xs text-gray-600 dark:text-gray-300 space-y-1">
+          <p className="font-medium">Sent before</p>
+          {history.map((entry) => (
+            <p key={entry.id} className="flex items-start gap-1">
+              {entry.status === 'sent'
+                ? <CheckCircle2 size={14} className="text-green-600 shrink-0" />
+                : <XCircle size={14} className="text-red-600 shrink-0" />}
+              <span>
+                {entry.sentAt ? new Date(`${entry.sentAt.replace(' ', 'T')}Z`).toLocaleString('en-GB') : ''} to {entry.recipients}
+                {entry.cc ? ` (cc ${entry.cc})` : ''}{entry.error ? `: ${entry.error}` : ''}
+              </span>
+            </p>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,152 @@
+import { useEffect, useState } from 'react';
+import { Mail, Loader2, Save, Send } from 'lucide-react';
+import { message } from '@tauri-apps/api/dialog';
+import { emailService, SmtpSettings, SmtpSecurity, DEFAULT_SMTP_SETTINGS, TEMPLATE_PLACEHOLDERS } from '../services/email';
+
+const DEFAULT_PORTS: Record<SmtpSecurity, number> = { starttls: 587, tls: 465, none: 25 };
+
+const inputClass = 'w-full px-3 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 dark:focus:ring-blue-400 focus:border-transparent transition-colors duration-200';
+
+// SMTP server and the templates used when emailing invoices
+export default function EmailSettings() {
+  const [settings, setSettings] = useState<SmtpSettings>(DEFAULT_SMTP_SETTINGS);
+  const [password, setPassword] = useState('');
+  const [testAddress, setTestAddress] = useState('');
+  const [isSaving, setIsSaving] = useState(false);
+  const [isTesting, setIsTesting] = useState(false);
+
+  useEffect(() => {
+    emailService.getSettings().then(setSettings).catch((e) => console.error('Failed to load SMTP settings:', e));
+  }, []);
+
+  const update = <K extends keyof SmtpSettings>(key: K, value: SmtpSettings[K]) => setSettings({ ...settings, [key]: value });
+
+  const changeSecurity = (security: SmtpSecurity) => {
+    // Follow the port along unless it was set to something non-standard
+    const port = Object.values(DEFAULT_PORTS).includes(settings.port) ? DEFAULT_PORTS[security] : settings.port;
+    setSettings({ ...settings, security, port });
+  };
+
+  const save = async () => {
+    await emailService.saveSettings(settings);
+    // A blank field keeps the stored password
+    if (password) {
+      await emailService.savePassword(password);
+      setPassword('');
+    }
+  };
+
+  const handleSave = async () => {
+    setIsSaving(true);
+    try {
+      await save();
+      await message('Email settings saved.', { title: 'Email', type: 'info' });
+    } catch (e: any) {
+      await message(`Could not save email settings: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  const handleTest = async () => {
+    const to = testAddress.trim() || settings.fromAddress;
+    setIsTesting(true);
+    try {
+      await save();
+      await emailService.sendTest(to);
+      await message(`Test email sent to ${to}.`, { title: 'Email', type: 'info' });
+    } catch (e: any) {
+      await message(`${e.message || e}`, { title: 'Test Email Failed', type: 'error' });
+    } finally {
+      setIsTesting(false);
+    }
+  };
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-1 flex items-center gap-2 transition-colors duration-200">
+        <Mail size={22} /> Email
+      </h2>
+      <p className="text-sm text-gray-600 dark:text-gray-300 mb-4 transition-colors duration-200">
+        The mail server used to send invoice PDFs to customers. The password is kept in the vault.
+      </p>
+
+      <div className="grid grid-cols-1 md:grid-cols-3 gap-3 mb-4">
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">SMTP server</label>
+          <input type="text" value={settings.host} onChange={(e) => update('host', e.target.value.trim())} placeholder="smtp.gmail.com" className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Security</label>
+          <select value={settings.security} onChange={(e) => changeSecurity(e.target.value as SmtpSecurity)} className={inputClass}>
+            <option value="starttls">STARTTLS</option>
+            <option value="tls">SSL/TLS</option>
+            <option value="none">None (local testing only)</option>
+          </select>
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Port</label>
+          <input type="number" value={settings.port} onChange={(e) => update('port', parseInt(e.target.value) || 0)} className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Username</label>
+          <input type="text" value={settings.username} onChange={(e) => update('username', e.target.value)} autoComplete="off" className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Password</label>
+          <input type="password" value={password} onChange={(e) => setPassword(e.target.value)} placeholder="Unchanged" autoComplete="new-password" className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Always CC</label>
+          <input type="text" value={settings.cc} onChange={(e) => update('cc', e.target.value)} placeholder="accounts@example.com" className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">From address</label>
+          <input type="email" value={settings.fromAddress} onChange={(e) => update('fromAddress', e.target.value.trim())} className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">From name</label>
+          <input type="text" value={settings.fromName} onChange={(e) => update('fromName', e.target.value)} placeholder="Company name" className={inputClass} />
+        </div>
+      </div>
+
+      <div className="space-y-3 mb-4">
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Subject</label>
+          <input type="text" value={settings.subjectTemplate} onChange={(e) => update('subjectTemplate', e.target.value)} className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Message</label>
+          <textarea value={settings.bodyTemplate} onChange={(e) => update('bodyTemplate', e.target.value)} rows={6} className={inputClass} />
+        </div>
+        <p className="text-xs text-gray-500 dark:text-gray-400">Placeholders: {TEMPLATE_PLACEHOLDERS.join(' ')}</p>
+      </div>
+
+      <div className="flex flex-wrap gap-2 items-center">
+        <button
+          onClick={handleSave}
+          disabled={isSaving}
+          className="flex items-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50"
+        >
+          {isSaving ? <Loader2 size={18} className="animate-spin" /> : <Save size={18} />}
+          Save
+        </button>
+        <input
+          type="email"
+          value={testAddress}
+          onChange={(e) => setTestAddress(e.target.value)}
+          placeholder={settings.fromAddress || 'Test recipient'}
+          className="px-3 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg"
+        />
+        <button
+          onClick={handleTest}
+          disabled={isTesting || !settings.host || !settings.fromAddress}
+          className="flex items-center gap-2 px-4 py-2 bg-gray-600 text-white rounded-lg hover:bg-gray-700 disabled:opacity-50"
+        >
+          {isTesting ? <Loader2 size={18} className="animate-spin" /> : <Send size={18} />}
+          Send Test Email
+        </button>
+      </div>
+    </div>
+  );
+}
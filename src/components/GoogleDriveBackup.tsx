@@ -0,0 +1,236 @@
+import { useState, useEffect } from 'react';
+import { Cloud, Loader2, LogOut, RefreshCw, UploadCloud, DownloadCloud, Folder } from 'lucide-react';
+import { message, ask } from '@tauri-apps/api/dialog';
+import { backupService, DriveFile, DriveStatus, SyncConflictError } from '../services/backup';
+
+interface GoogleDriveBackupProps {
+  // Hands a downloaded backup to the regular restore flow (preview, passphrase, confirm)
+  onRestore: (path: string) => Promise<void>;
+}
+
+const formatSize = (size?: number) => {
+  if (size === undefined) return '';
+  if (size < 1024 * 1024) return `${(size / 1024).toFixed(0)} KB`;
+  return `${(size / (1024 * 1024)).toFixed(1)} MB`;
+};
+
+export default function GoogleDriveBackup({ onRestore }: GoogleDriveBackupProps) {
+  const [status, setStatus] = useState<DriveStatus | null>(null);
+  const [folderName, setFolderName] = useState('');
+  const [uploadEnabled, setUploadEnabled] = useState(backupService.isDriveUploadEnabled());
+  const [backups, setBackups] = useState<DriveFile[]>([]);
+  // 'connect', 'folder', 'list', 'upload' or the id of the file being downloaded
+  const [busy, setBusy] = useState<string | null>(null);
+
+  const applyStatus = (next: DriveStatus) => {
+    setStatus(next);
+    setFolderName(next.folder?.name || '');
+  };
+
+  const refreshBackups = async () => {
+    setBusy('list');
+    try {
+      setBackups(await backupService.listDriveBackups());
+    } catch (e: any) {
+      await message(`Could not list Google Drive backups: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  useEffect(() => {
+    backupService.getDriveStatus()
+      .then((loaded) => {
+        applyStatus(loaded);
+        if (loaded.connected && loaded.folder) {
+          backupService.listDriveBackups().then(setBackups).catch((e) => console.error('Failed to list Drive backups', e));
+        }
+      })
+      .catch((e) => console.error('Failed to load Google Drive status', e));
+  }, []);
+
+  const handleConnect = async () => {
+    setBusy('connect');
+    try {
+      const next = await backupService.connectDrive();
+      applyStatus(next);
+      setBackups(await backupService.listDriveBackups());
+    } catch (e: any) {
+      await message(`Google Drive sign-in failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleDisconnect = async () => {
+    const confirmed = await ask('Disconnect Google Drive? Backups already in Drive are kept.', { title: 'Disconnect', type: 'warning' });
+    if (!confirmed) return;
+    try {
+      applyStatus(await backupService.disconnectDrive());
+      setUploadEnabled(false);
+      setBackups([]);
+    } catch (e: any) {
+      await message(`Failed to disconnect: ${e.message || e}`, { title: 'Error', type: 'error' });
+    }
+  };
+
+  const handleSetFolder = async () => {
+    setBusy('folder');
+    try {
+      applyStatus(await backupService.setDriveFolder(folderName));
+      setBackups(await backupService.listDriveBackups());
+    } catch (e: any) {
+      await message(`Failed to set folder: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleToggleUpload = async () => {
+    const next = !uploadEnabled;
+    setUploadEnabled(next);
+    await backupService.setDriveUpload(next);
+  };
+
+  const handleUploadNow = async () => {
+    setBusy('upload');
+    try {
+      const bundlePath = await backupService.performBackup();
+      // performBackup already uploads when automatic upload is on
+      if (!backupService.isDriveUploadEnabled()) {
+        await backupService.uploadToDrive(bundlePath);
+      }
+      setBackups(await backupService.listDriveBackups());
+      await message('Backup uploaded to Google Drive.', { title: 'Success', type: 'info' });
+    } catch (e: any) {
+      if (e instanceof SyncConflictError) return;
+      await message(`Upload failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleRestore = async (file: DriveFile) => {
+    setBusy(file.id);
+    try {
+      const localPath = await backupService.downloadFromDrive(file);
+      await onRestore(localPath);
+    } catch (e: any) {
+      await message(`Download failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  if (!status) return null;
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <div className="flex items-center justify-between mb-4">
+        <div>
+          <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 flex items-center gap-2 transition-colors duration-200">
+            <Cloud size={24} /> Google Drive
+          </h2>
+          <p className="text-sm text-gray-600 dark:text-gray-300 transition-colors duration-200">
+            Upload backups to your Google Drive and restore them on any computer.
+          </p>
+        </div>
+        {status.connected ? (
+          <button
+            onClick={handleDisconnect}
+            className="flex items-center gap-2 px-4 py-2 text-gray-700 dark:text-gray-300 bg-gray-100 dark:bg-gray-600 rounded-lg hover:bg-gray-200 dark:hover:bg-gray-500"
+          >
+            <LogOut size={18} /> Disconnect
+          </button>
+        ) : (
+          <button
+            onClick={handleConnect}
+            disabled={!status.configured || busy === 'connect'}
+            className="flex items-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50 disabled:cursor-not-allowed"
+          >
+            {busy === 'connect' ? <Loader2 size={18} className="animate-spin" /> : <Cloud size={18} />}
+            {busy === 'connect' ? 'Waiting for browser...' : 'Connect Google Drive'}
+          </button>
+        )}
+      </div>
+
+      {!status.configured && (
+        <p className="text-sm text-yellow-700 dark:text-yellow-300">Google Drive is not available in this build.</p>
+      )}
+
+      {status.connected && (
+        <div className="space-y-4">
+          <div className="flex gap-2">
+            <div className="relative flex-1">
+              <Folder size={16} className="absolute left-3 top-1/2 -translate-y-1/2 text-gray-400" />
+              <input
+                type="text"
+                value={folderName}
+                onChange={(e) => setFolderName(e.target.value)}
+                placeholder="Drive folder name"
+                className="w-full pl-9 pr-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 rounded-lg text-sm"
+              />
+            </div>
+            <button
+              onClick={handleSetFolder}
+              disabled={busy === 'folder' || !folderName.trim() || folderName === status.folder?.name}
+              className="px-4 py-2 bg-blue-100 dark:bg-blue-900 text-blue-700 dark:text-blue-300 rounded-lg hover:bg-blue-200 dark:hover:bg-blue-800 disabled:opacity-50 text-sm font-medium"
+            >
+              {busy === 'folder' ? <Loader2 size={16} className="animate-spin" /> : 'Use Folder'}
+            </button>
+          </div>
+
+          <div className="flex items-center justify-between">
+            <label className="flex items-center gap-2 text-sm text-gray-700 dark:text-gray-300 cursor-pointer">
+              <input type="checkbox" checked={uploadEnabled} onChange={handleToggleUpload} />
+              Upload every backup to Google Drive
+            </label>
+            <button
+              onClick={handleUploadNow}
+              disabled={busy === 'upload' || !backupService.getBackupPath()}
+              title={backupService.getBackupPath() ? undefined : 'Select a backup folder first'}
+              className="flex items-center gap-2 px-4 py-2 bg-green-600 text-white rounded-lg hover:bg-green-700 disabled:opacity-50 text-sm"
+            >
+              {busy === 'upload' ? <Loader2 size={16} className="animate-spin" /> : <UploadCloud size={16} />}
+              Back Up Now
+            </button>
+          </div>
+
+          <div>
+            <div className="flex items-center justify-between mb-2">
+              <h3 className="font-medium text-gray-800 dark:text-gray-200">Backups in Drive</h3>
+              <button
+                onClick={refreshBackups}
+                disabled={busy === 'list'}
+                className="p-1 text-gray-500 hover:text-gray-700 dark:hover:text-gray-300 disabled:opacity-50"
+                title="Refresh"
+              >
+                <RefreshCw size={16} className={busy === 'list' ? 'animate-spin' : ''} />
+              </button>
+            </div>
+            {backups.length === 0 ? (
+              <p className="text-sm text-gray-500">No backups in this folder yet.</p>
+            ) : (
+              <div className="space-y-2 max-h-64 overflow-y-auto">
+                {backups.map((file) => (
+                  <div key={file.id} className="flex items-center gap-3 p-3 bg-white dark:bg-gray-800 rounded-lg border border-gray-200 dark:border-gray-600">
+                    <span className="text-sm font-medium text-gray-800 dark:text-gray-100">{file.name}</span>
+                    <span className="text-xs text-gray-500">{formatSize(file.size)}</span>
+                    <button
+                      onClick={() => handleRestore(file)}
+                      disabled={busy !== null}
+                      className="ml-auto flex items-center gap-1 px-3 py-1 text-sm bg-red-600 text-white rounded-lg hover:bg-red-700 disabled:opacity-50"
+                    >
+                      {busy === file.id ? <Loader2 size={14} className="animate-spin" /> : <DownloadCloud size={14} />}
+                      Restore
+                    </button>
+                  </div>
+                ))}
+              </div>
+            )}
+          </div>
+        </div>
+      )}
+    </div>
+  );
+}
@@ -1,6 +1,6 @@
 import React, { useEffect } from 'react';
 import { Download, RotateCcw } from 'lucide-react';
-import { message } from '@tauri-apps/api/dialog';
+import { ask, message } from '@tauri-apps/api/dialog';
 import { generateInvoicePDF } from '../services/pdfGenerator';
 import { dbService } from '../services/db';
 import { invoiceService } from '../services/invoiceService';
@@ -15,6 +15,7 @@ export const InvoiceForm: React.FC = () => {
   const { state, actions } = useInvoiceForm();
   const {
     customers,
+    catalogueItems,
     selectedCustomerId,
     invoiceNumber,
     financialYear,
@@ -26,6 +27,8 @@ export const InvoiceForm: React.FC = () => {
     cgstPercentage,
     sgstPercentage,
     isGenerating,
+    isIssued,
+    amendmentReason,
     totals,
   } = state;
 
@@ -37,11 +40,14 @@ export const InvoiceForm: React.FC = () => {
     setCgstPercentage,
     setSgstPercentage,
     setIsGenerating,
+    setAmendmentReason,
+    refreshIssuedState,
     handleCustomerSelect,
     updateCustomerField,
     addLineItem,
     removeLineItem,
     updateLineItem,
+    applyCatalogueItem,
     handleAutoSave,
     handleReset,
     validateForm,
@@ -93,14 +99,37 @@ export const InvoiceForm: React.FC = () => {
         workOrderDate,
       };
 
+      let saved;
+      if (isIssued) {
+        if (!amendmentReason.trim()) {
+          await message('This invoice has already been issued. Please enter a reason for the amendment.', {
+            title: 'Amendment Reason Required',
+            type: 'error',
+          });
+          return;
+        }
+        const confirmed = await ask(
+          `Invoice AS/${financialYear}/${invoiceNumber} has already been issued.\n\nSave these changes as a revision? The original will be kept in the invoice history.`,
+          { title: 'Amend Invoice', type: 'warning' }
+        );
+        if (!confirmed) return;
+        saved = await invoiceService.amendInvoice(invoice as any, amendmentReason);
+      } else {
+        saved = await invoiceService.saveInvoice(invoice as any);
+      }
+
       await generateInvoicePDF(
-        invoice as any,
+        saved,
         companySettings,
         stampSignature || undefined,
         companyLogo || undefined
       );
-      await invoiceService.saveInvoice(invoice as any);
-      await message('Invoice generated and saved successfully!', 'Success');
+      setAmendmentReason('');
+      await refreshIssuedState();
+      await message(
+        saved.revision ? `Invoice revised (Rev. ${saved.revision}) and saved successfully!` : 'Invoice generated and saved successfully!',
+        'Success'
+      );
     } catch (error) {
       console.error('PDF Generation Error:', error);
       await message('Failed to generate PDF. Please check console for details.', {
@@ -157,6 +186,8 @@ export const InvoiceForm: React.FC = () => {
           onAdd={addLineItem}
           onRemove={removeLineItem}
           onUpdate={updateLineItem}
+          catalogueItems={catalogueItems}
+          onPickCatalogueItem={applyCatalogueItem}
         />
 
         <TaxSummary
@@ -167,6 +198,21 @@ export const InvoiceForm: React.FC = () => {
           totals={totals}
         />
 
+        {isIssued && (
+          <div className="p-4 rounded-lg border border-amber-300 dark:border-amber-600 bg-amber-50 dark:bg-amber-900/20">
+            <p className="text-sm font-semibold text-amber-800 dark:text-amber-300 mb-2">
+              Invoice AS/{financialYear}/{invoiceNumber} has already been issued. Saving will create a revision.
+            </p>
+            <input
+              type="text"
+              value={amendmentReason}
+              onChange={(e) => setAmendmentReason(e.target.value)}
+              placeholder="Reason for amendment (required)"
+              className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+            />
+          </div>
+        )}
+
         <div className="flex justify-end pt-4 pb-12">
           <button
             onClick={handleGeneratePDF}
@@ -175,7 +221,7 @@ export const InvoiceForm: React.FC = () => {
               }`}
           >
             <Download size={24} />
-            {isGenerating ? 'Generating PDF...' : 'Generate & Save Invoice'}
+            {isGenerating ? 'Generating PDF...' : isIssued ? 'Save Revision & Generate PDF' : 'Generate & Save Invoice'}
           </button>
         </div>
       </div>
@@ -1,23 +1,39 @@
 import { useState, useEffect } from 'react';
-import { FileText, Download, Trash2, Search, Loader2 } from 'lucide-react';
+import { FileText, Download, Ban, Search, Loader2, History, Mail, Printer } from 'lucide-react';
 import { ask, message } from '@tauri-apps/api/dialog';
 import { Invoice } from '../types/invoice';
 import { dbService } from '../services/db';
 import { invoiceService } from '../services/invoiceService';
 import { generateInvoicePDF } from '../services/pdfGenerator';
+import EmailInvoicePanel from './EmailInvoicePanel';
+import PrintInvoicePanel from './PrintInvoicePanel';
 
-export default function InvoiceHistory() {
+interface InvoiceHistoryProps {
+  // Pre-fills the search, e.g. with the number of an invoice PDF opened with the app
+  initialSearch?: string;
+}
+
+export default function InvoiceHistory({ initialSearch = '' }: InvoiceHistoryProps) {
   const [invoices, setInvoices] = useState<Invoice[]>([]);
   const [filteredInvoices, setFilteredInvoices] = useState<Invoice[]>([]);
-  const [searchTerm, setSearchTerm] = useState<string>('');
+  const [searchTerm, setSearchTerm] = useState<string>(initialSearch);
   const [isLoading, setIsLoading] = useState<boolean>(true);
   const [generatingPdfId, setGeneratingPdfId] = useState<string | null>(null);
   const [visibleCount, setVisibleCount] = useState<number>(10);
+  const [cancellingId, setCancellingId] = useState<string | null>(null);
+  const [cancelReason, setCancelReason] = useState<string>('');
+  const [cancelDate, setCancelDate] = useState<string>('');
+  const [emailingId, setEmailingId] = useState<string | null>(null);
+  const [printingId, setPrintingId] = useState<string | null>(null);
 
   useEffect(() => {
     loadInvoices();
   }, []);
 
+  useEffect(() => {
+    setSearchTerm(initialSearch);
+  }, [initialSearch]);
+
   useEffect(() => {
     if (searchTerm.trim() === '') {
       setFilteredInvoices(invoices);
@@ -68,27 +84,69 @@ export default function InvoiceHistory() {
     }
   };
 
-  const handleDeleteInvoice = async (id: string, invoiceNumber: string, financialYear: string) => {
-    const formattedInvoiceNumber = `AS/${financialYear}/${invoiceNumber}`;
+  // Renders the invoice exactly as it was first issued, before any amendments
+  const handleOriginalPDF = async (invoice: Invoice) => {
+    if (!invoice.id) return;
+    setGeneratingPdfId(`${invoice.id}-original`);
+    try {
+      const revisions = await invoiceService.getInvoiceRevisions(invoice.invoiceNumber);
+      if (revisions.length === 0) {
+        throw new Error('No recorded revisions');
+      }
+      const companySettings = await dbService.getCompanySettings();
+      const stampSignature = await dbService.getStampSignature();
+      const companyLogo = await dbService.getCompanyLogo();
+      await generateInvoicePDF(revisions[0].invoice, companySettings, stampSignature || undefined, companyLogo || undefined);
+      await message('Original invoice PDF has been generated and saved successfully!', {
+        title: 'Success',
+        type: 'info'
+      });
+    } catch (error) {
+      console.error('Error generating original PDF:', error);
+      await message('Failed to generate the original PDF.', {
+        title: 'Error',
+        type: 'error'
+      });
+    } finally {
+      setGeneratingPdfId(null);
+    }
+  };
+
+  const openCancelPanel = (id: string) => {
+    setCancellingId(id);
+    setCancelReason('');
+    setCancelDate(new Date().toISOString().split('T')[0]);
+  };
+
+  const handleCancelInvoice = async (invoice: Invoice) => {
+    const formattedInvoiceNumber = `AS/${invoice.financialYear}/${invoice.invoiceNumber}`;
+    if (!cancelReason.trim()) {
+      await message('Please enter a reason for the cancellation.', {
+        title: 'Reason Required',
+        type: 'error'
+      });
+      return;
+    }
     const confirmed = await ask(
-      `Are you sure you want to delete invoice ${formattedInvoiceNumber}?\n\nThis action cannot be undone.`,
+      `Are you sure you want to cancel invoice ${formattedInvoiceNumber}?\n\nThe invoice number stays used and the invoice will be marked CANCELLED.`,
       {
-        title: 'Confirm Deletion',
+        title: 'Confirm Cancellation',
         type: 'warning'
       }
     );
 
     if (confirmed) {
       try {
-        await invoiceService.deleteInvoice(id);
+        await invoiceService.cancelInvoice(invoice.invoiceNumber, cancelReason, cancelDate);
+        setCancellingId(null);
         await loadInvoices();
-        await message(`Invoice ${formattedInvoiceNumber} has been deleted successfully.`, {
-          title: 'Deleted',
+        await message(`Invoice ${formattedInvoiceNumber} has been cancelled.`, {
+          title: 'Cancelled',
           type: 'info'
         });
       } catch (error) {
-        console.error('Error deleting invoice:', error);
-        await message('Failed to delete invoice. Please try again.', {
+        console.error('Error cancelling invoice:', error);
+        await message(`Failed to cancel invoice: ${error}`, {
           title: 'Error',
           type: 'error'
         });
@@ -145,6 +203,16 @@ export default function InvoiceHistory() {
                     <h3 className="text-lg font-semibold text-gray-800 dark:text-gray-100 transition-colors duration-200">
                       AS/{invoice.financialYear}/{invoice.invoiceNumber}
                     </h3>
+                    {!!invoice.revision && (
+                      <span className="px-2 py-0.5 text-xs font-semibold rounded-full bg-amber-100 dark:bg-amber-900/40 text-amber-800 dark:text-amber-300">
+                        Revised (Rev. {invoice.revision})
+                      </span>
+                    )}
+                    {invoice.status === 'CANCELLED' && (
+                      <span className="px-2 py-0.5 text-xs font-semibold rounded-full bg-red-100 dark:bg-red-900/40 text-red-800 dark:text-red-300">
+                        Cancelled
+                      </span>
+                    )}
                   </div>
 
                   <div className="grid grid-cols-1 md:grid-cols-2 gap-4 mt-4">
@@ -202,15 +270,82 @@ export default function InvoiceHistory() {
                       </>
                     )}
                   </button>
+                  {!!invoice.revision && (
+                    <button
+                      onClick={() => handleOriginalPDF(invoice)}
+                      disabled={generatingPdfId === `${invoice.id}-original`}
+                      className="flex items-center gap-2 px-4 py-2 bg-gray-600 dark:bg-gray-500 text-white rounded-lg hover:bg-gray-700 dark:hover:bg-gray-600 transition-all duration-200 disabled:opacity-50 disabled:cursor-not-allowed"
+                      title="Download the originally issued PDF"
+                    >
+                      <History size={18} />
+                      Original
+                    </button>
+                  )}
                   <button
-                    onClick={() => invoice.id && handleDeleteInvoice(invoice.id, invoice.invoiceNumber, invoice.financialYear)}
-                    className="flex items-center gap-2 px-4 py-2 bg-red-600 dark:bg-red-500 text-white rounded-lg hover:bg-red-700 dark:hover:bg-red-600 transition-all duration-200 transition-colors"
-                    title="Delete Invoice"
+                    onClick={() => setEmailingId(emailingId === invoice.id ? null : invoice.id || null)}
+                    className="flex items-center gap-2 px-4 py-2 bg-blue-600 dark:bg-blue-500 text-white rounded-lg hover:bg-blue-700 dark:hover:bg-blue-600 transition-all duration-200"
+                    title="Email PDF to the customer"
                   >
-                    <Trash2 size={18} />
+                    <Mail size={18} />
                   </button>
+                  <button
+                    onClick={() => setPrintingId(printingId === invoice.id ? null : invoice.id || null)}
+                    className="flex items-center gap-2 px-4 py-2 bg-gray-600 dark:bg-gray-500 text-white rounded-lg hover:bg-gray-700 dark:hover:bg-gray-600 transition-all duration-200"
+                    title="Print on a printer"
+                  >
+                    <Printer size={18} />
+                  </button>
+                  {invoice.status !== 'CANCELLED' && (
+                    <button
+                      onClick={() => invoice.id && openCancelPanel(invoice.id)}
+                      className="flex items-center gap-2 px-4 py-2 bg-red-600 dark:bg-red-500 text-white rounded-lg hover:bg-red-700 dark:hover:bg-red-600 transition-all duration-200 transition-colors"
+                      title="Cancel Invoice"
+                    >
+                      <Ban size={18} />
+                    </button>
+                  )}
                 </div>
               </div>
+
+              {invoice.status === 'CANCELLED' && (
+                <p className="mt-4 text-sm text-red-700 dark:text-red-300">
+                  Cancelled on {invoice.cancelledAt ? new Date(invoice.cancelledAt).toLocaleDateString('en-GB') : '-'}: {invoice.cancellationReason}
+                </p>
+              )}
+
+              {emailingId === invoice.id && <EmailInvoicePanel invoice={invoice} onClose={() => setEmailingId(null)} />}
+
+              {printingId === invoice.id && <PrintInvoicePanel invoice={invoice} onClose={() => setPrintingId(null)} />}
+
+              {cancellingId === invoice.id && (
+                <div className="mt-4 p-4 rounded-lg border border-red-200 dark:border-red-800 bg-red-50 dark:bg-red-900/20 flex flex-wrap gap-2 items-center">
+                  <input
+                    type="text"
+                    value={cancelReason}
+                    onChange={(e) => setCancelReason(e.target.value)}
+                    placeholder="Reason for cancellation (required)"
+                    className="flex-1 min-w-[200px] px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 rounded-lg"
+                  />
+                  <input
+                    type="date"
+                    value={cancelDate}
+                    onChange={(e) => setCancelDate(e.target.value)}
+                    className="px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 rounded-lg"
+                  />
+                  <button
+                    onClick={() => handleCancelInvoice(invoice)}
+                    className="px-4 py-2 bg-red-600 text-white rounded-lg hover:bg-red-700"
+                  >
+                    Cancel Invoice
+                  </button>
+                  <button
+                    onClick={() => setCancellingId(null)}
+                    className="px-4 py-2 text-gray-600 dark:text-gray-300 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg"
+                  >
+                    Keep
+                  </button>
+                </div>
+              )}
             </div>
           ))}
 
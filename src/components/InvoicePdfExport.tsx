@@ -0,0 +1,127 @@
+import { useEffect, useRef, useState } from 'react';
+import { FileArchive, Loader2 } from 'lucide-react';
+import { save, message } from '@tauri-apps/api/dialog';
+import { Customer } from '../types/invoice';
+import { customerService } from '../services/customerService';
+import { invoicePdfExportService, InvoicePdfProgress } from '../services/invoicePdfs';
+
+const inputClass = 'w-full px-3 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 dark:focus:ring-blue-400 focus:border-transparent transition-colors duration-200';
+
+// All invoices of a period as PDFs in one zip, e.g. a quarter for the CA
+export default function InvoicePdfExport() {
+  const [from, setFrom] = useState('');
+  const [to, setTo] = useState('');
+  const [financialYear, setFinancialYear] = useState('');
+  const [customerId, setCustomerId] = useState('');
+  const [includeCancelled, setIncludeCancelled] = useState(false);
+  const [customers, setCustomers] = useState<Customer[]>([]);
+  const [progress, setProgress] = useState<InvoicePdfProgress | null>(null);
+  const [isExporting, setIsExporting] = useState(false);
+  const cancelled = useRef(false);
+
+  useEffect(() => {
+    customerService.getAllCustomers().then(setCustomers).catch((e) => console.error('Failed to load customers:', e));
+  }, []);
+
+  const handleExport = async () => {
+    const period = financialYear ? `-FY${financialYear}` : `${from ? `-${from}` : ''}${to ? `-to-${to}` : ''}`;
+    const target = await save({
+      title: 'Export Invoice PDFs',
+      defaultPath: `invoices${period}.zip`,
+      filters: [{ name: 'Zip Archive', extensions: ['zip'] }],
+    });
+    if (!target) return;
+
+    cancelled.current = false;
+    setIsExporting(true);
+    try {
+      const report = await invoicePdfExportService.exportZip(target, {
+        from: from || undefined,
+        to: to || undefined,
+        customerId: customerId || undefined,
+        financialYear: financialYear.trim() || undefined,
+        includeCancelled,
+      }, setProgress);
+      await message(`Exported ${report.invoices} invoice PDF(s) to:\n${report.path}`, { title: 'Export Complete', type: 'info' });
+    } catch (e: any) {
+      if (!cancelled.current) {
+        await message(`Export failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+      }
+    } finally {
+      setIsExporting(false);
+      setProgress(null);
+    }
+  };
+
+  const handleCancel = async () => {
+    cancelled.current = true;
+    await invoicePdfExportService.cancel();
+  };
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-1 flex items-center gap-2 transition-colors duration-200">
+        <FileArchive size={22} /> Export Invoice PDFs
+      </h2>
+      <p className="text-sm text-gray-600 dark:text-gray-300 mb-4 transition-colors duration-200">
+        Regenerates the PDF of every issued invoice that matches, and saves them in one zip file with an index.csv listing them.
+      </p>
+
+      <div className="grid grid-cols-1 md:grid-cols-3 gap-4 mb-4">
+        <div>
+          <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">From</label>
+          <input type="date" value={from} onChange={(e) => setFrom(e.target.value)} className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">To</label>
+          <input type="date" value={to} onChange={(e) => setTo(e.target.value)} className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">Financial year</label>
+          <input type="text" value={financialYear} onChange={(e) => setFinancialYear(e.target.value)} placeholder="All, or e.g. 24-25" className={inputClass} />
+        </div>
+        <div className="md:col-span-2">
+          <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">Customer</label>
+          <select value={customerId} onChange={(e) => setCustomerId(e.target.value)} className={inputClass}>
+            <option value="">All customers</option>
+            {customers.map((c) => <option key={c.id} value={c.id}>{c.companyName}</option>)}
+          </select>
+        </div>
+        <label className="flex items-center gap-2 text-sm text-gray-700 dark:text-gray-300 self-end pb-2">
+          <input type="checkbox" checked={includeCancelled} onChange={(e) => setIncludeCancelled(e.target.checked)} />
+          Include cancelled invoices
+        </label>
+      </div>
+
+      {progress && (
+        <div className="mb-4">
+          <div className="w-full h-2 bg-gray-200 dark:bg-gray-600 rounded">
+            <div className="h-2 bg-green-600 rounded transition-all" style={{ width: `${Math.floor((progress.done / progress.total) * 100)}%` }} />
+          </div>
+          <p className="text-xs text-gray-600 dark:text-gray-300 mt-1">
+            {progress.done} of {progress.total}: invoice {progress.invoiceNumber}
+          </p>
+        </div>
+      )}
+
+      <div className="flex gap-2">
+        <button
+          onClick={handleExport}
+          disabled={isExporting}
+          className="flex items-center gap-2 px-4 py-2 bg-green-600 dark:bg-green-500 text-white rounded-lg hover:bg-green-700 disabled:opacity-50"
+        >
+          {isExporting ? <Loader2 size={16} className="animate-spin" /> : <FileArchive size={16} />}
+          Export Zip
+        </button>
+        {isExporting && (
+          <button
+            onClick={handleCancel}
+            className="px-4 py-2 text-red-700 dark:text-red-300 bg-white dark:bg-gray-800 border border-red-200 dark:border-red-700 rounded-lg hover:bg-red-100 dark:hover:bg-red-900/30"
+          >
+            Cancel
+          </button>
+        )}
+      </div>
+    </div>
+  );
+}
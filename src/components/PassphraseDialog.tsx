@@ -0,0 +1,102 @@
+import { useState, useEffect } from 'react';
+import { Lock, Loader2 } from 'lucide-react';
+
+interface PassphraseDialogProps {
+  isOpen: boolean;
+  title: string;
+  description: string;
+  // Ask for the passphrase twice (when setting one, not when unlocking a backup)
+  confirm?: boolean;
+  error?: string;
+  isBusy?: boolean;
+  onSubmit: (passphrase: string) => void;
+  onCancel: () => void;
+}
+
+const MIN_PASSPHRASE_LENGTH = 8;
+
+export default function PassphraseDialog({ isOpen, title, description, confirm, error, isBusy, onSubmit, onCancel }: PassphraseDialogProps) {
+  const [passphrase, setPassphrase] = useState('');
+  const [repeat, setRepeat] = useState('');
+  const [localError, setLocalError] = useState('');
+
+  useEffect(() => {
+    if (isOpen) {
+      setPassphrase('');
+      setRepeat('');
+      setLocalError('');
+    }
+  }, [isOpen]);
+
+  if (!isOpen) return null;
+
+  const handleSubmit = (e: React.FormEvent) => {
+    e.preventDefault();
+    if (confirm) {
+      if (passphrase.length < MIN_PASSPHRASE_LENGTH) {
+        setLocalError(`Use at least ${MIN_PASSPHRASE_LENGTH} characters.`);
+        return;
+      }
+      if (passphrase !== repeat) {
+        setLocalError('The passphrases do not match.');
+        return;
+      }
+    }
+    setLocalError('');
+    onSubmit(passphrase);
+  };
+
+  const shownError = localError || error;
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50">
+      <form
+        onSubmit={handleSubmit}
+        className="w-full max-w-md p-6 bg-white dark:bg-gray-800 rounded-lg shadow-lg space-y-4"
+      >
+        <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 flex items-center gap-2">
+          <Lock size={22} /> {title}
+        </h2>
+        <p className="text-sm text-gray-600 dark:text-gray-300">{description}</p>
+
+        <input
+          type="password"
+          autoFocus
+          placeholder="Passphrase"
+          value={passphrase}
+          onChange={(e) => setPassphrase(e.target.value)}
+          className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg"
+        />
+        {confirm && (
+          <input
+            type="password"
+            placeholder="Repeat passphrase"
+            value={repeat}
+            onChange={(e) => setRepeat(e.target.value)}
+            className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg"
+          />
+        )}
+        {shownError && <p className="text-sm text-red-600 dark:text-red-400">{shownError}</p>}
+
+        <div className="flex justify-end gap-2 pt-2">
+          <button
+            type="button"
+            onClick={onCancel}
+            disabled={isBusy}
+            className="px-4 py-2 text-gray-700 dark:text-gray-300 bg-gray-100 dark:bg-gray-700 rounded-lg hover:bg-gray-200 dark:hover:bg-gray-600 disabled:opacity-50"
+          >
+            Cancel
+          </button>
+          <button
+            type="submit"
+            disabled={isBusy || !passphrase}
+            className="flex items-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50 disabled:cursor-not-allowed"
+          >
+            {isBusy && <Loader2 size={16} className="animate-spin" />}
+            {confirm ? 'Set Passphrase' : 'Unlock'}
+          </button>
+        </div>
+      </form>
+    </div>
+  );
+}
@@ -0,0 +1,121 @@
+import { useEffect, useState } from 'react';
+import { Printer as PrinterIcon, Loader2 } from 'lucide-react';
+import { message } from '@tauri-apps/api/dialog';
+import { Invoice } from '../types/invoice';
+import { dbService } from '../services/db';
+import { printService, Printer, PrintOptions, Duplex, PaperSize, DEFAULT_PRINT_OPTIONS } from '../services/printing';
+import { generateInvoicePDF } from '../services/pdfGenerator';
+
+interface PrintInvoicePanelProps {
+  invoice: Invoice;
+  onClose: () => void;
+}
+
+const inputClass = 'w-full px-3 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 rounded-lg text-sm';
+
+// Sends the invoice PDF straight to a printer, starting from the options remembered for the company
+export default function PrintInvoicePanel({ invoice, onClose }: PrintInvoicePanelProps) {
+  const [printers, setPrinters] = useState<Printer[] | null>(null);
+  const [options, setOptions] = useState<PrintOptions>(DEFAULT_PRINT_OPTIONS);
+  const [remember, setRemember] = useState(false);
+  const [isPrinting, setIsPrinting] = useState(false);
+  const [loadError, setLoadError] = useState<string | null>(null);
+
+  useEffect(() => {
+    Promise.all([printService.getPrinters(), printService.getDefaults()])
+      .then(([installed, defaults]) => {
+        setPrinters(installed);
+        // A remembered printer that has since been removed falls back to the system default
+        const printer = installed.some((p) => p.name === defaults.printer) ? defaults.printer : '';
+        setOptions({ ...defaults, printer });
+      })
+      .catch((e) => setLoadError(`${e.message || e}`));
+  }, []);
+
+  const update = <K extends keyof PrintOptions>(key: K, value: PrintOptions[K]) => setOptions({ ...options, [key]: value });
+
+  const handlePrint = async () => {
+    setIsPrinting(true);
+    try {
+      const companySettings = await dbService.getCompanySettings();
+      const stampSignature = await dbService.getStampSignature();
+      const companyLogo = await dbService.getCompanyLogo();
+      const pdfFile = await generateInvoicePDF(invoice, companySettings, stampSignature || undefined, companyLogo || undefined, false);
+      const job = await printService.printInvoice(pdfFile, options);
+      if (remember) await printService.saveDefaults(options);
+      const copies = options.copies === 1 ? '1 copy' : `${options.copies} copies`;
+      await message(`Sent ${copies} to ${job.printer || 'the default printer'}${job.jobId ? ` (job ${job.jobId})` : ''}.`, { title: 'Printing', type: 'info' });
+      onClose();
+    } catch (e: any) {
+      await message(`${e.message || e}`, { title: 'Printing Failed', type: 'error' });
+    } finally {
+      setIsPrinting(false);
+    }
+  };
+
+  return (
+    <div className="mt-4 p-4 rounded-lg border border-gray-200 dark:border-gray-600 bg-gray-50 dark:bg-gray-700/40 space-y-3">
+      <h4 className="font-medium text-gray-800 dark:text-gray-100 flex items-center gap-2"><PrinterIcon size={18} /> Print Invoice</h4>
+      {loadError ? (
+        <p className="text-sm text-red-700 dark:text-red-300">{loadError}</p>
+      ) : !printers ? (
+        <Loader2 size={20} className="animate-spin text-blue-600" />
+      ) : (
+        <>
+          <div className="grid grid-cols-1 md:grid-cols-4 gap-3">
+            <div className="md:col-span-2">
+              <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Printer</label>
+              <select value={options.printer} onChange={(e) => update('printer', e.target.value)} className={inputClass}>
+                <option value="">System default</option>
+                {printers.map((p) => (
+                  <option key={p.name} value={p.name}>{p.name}{p.isDefault ? ' (default)' : ''}</option>
+                ))}
+              </select>
+            </div>
+            <div>
+              <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Copies</label>
+              <input type="number" min={1} max={99} value={options.copies} onChange={(e) => update('copies', parseInt(e.target.value) || 1)} className={inputClass} />
+            </div>
+            <div>
+              <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Paper</label>
+              <select value={options.paperSize} onChange={(e) => update('paperSize', e.target.value as PaperSize)} className={inputClass}>
+                <option value="A4">A4</option>
+                <option value="Letter">Letter</option>
+                <option value="Legal">Legal</option>
+              </select>
+            </div>
+            <div className="md:col-span-2">
+              <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Two-sided</label>
+              <select value={options.duplex} onChange={(e) => update('duplex', e.target.value as Duplex)} className={inputClass}>
+                <option value="none">Off</option>
+                <option value="long-edge">Flip on long edge</option>
+                <option value="short-edge">Flip on short edge</option>
+              </select>
+            </div>
+          </div>
+          {printers.length === 0 && (
+            <p className="text-xs text-gray-500 dark:text-gray-400">No printers are installed; the job goes to the system default, if there is one.</p>
+          )}
+          <label className="flex items-center gap-2 text-sm text-gray-700 dark:text-gray-200">
+            <input type="checkbox" checked={remember} onChange={(e) => setRemember(e.target.checked)} />
+            Remember these settings for this company
+          </label>
+        </>
+      )}
+
+      <div className="flex gap-2">
+        <button
+          onClick={handlePrint}
+          disabled={!printers || isPrinting}
+          className="flex items-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50"
+        >
+          {isPrinting ? <Loader2 size={16} className="animate-spin" /> : <PrinterIcon size={16} />}
+          Print
+        </button>
+        <button onClick={onClose} className="px-4 py-2 text-gray-600 dark:text-gray-300 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg">
+          Close
+        </button>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,136 @@
+import { useState, useEffect } from 'react';
+import { Trash2, RotateCcw, Loader2, Users, FileText } from 'lucide-react';
+import { ask, message } from '@tauri-apps/api/dialog';
+import { TrashItem } from '../types/invoice';
+import { trashService } from '../services/trashService';
+
+export default function RecycleBin() {
+  const [items, setItems] = useState<TrashItem[]>([]);
+  const [retentionDays, setRetentionDays] = useState<number>(30);
+  const [isLoading, setIsLoading] = useState<boolean>(true);
+  const [isPurging, setIsPurging] = useState<boolean>(false);
+
+  useEffect(() => {
+    loadTrash();
+  }, []);
+
+  const loadTrash = async () => {
+    setIsLoading(true);
+    try {
+      setItems(await trashService.listTrash());
+      setRetentionDays(await trashService.getRetentionDays());
+    } catch (error) {
+      console.error('Error loading recycle bin:', error);
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  const handleRestore = async (item: TrashItem) => {
+    try {
+      await trashService.restore(item);
+      await loadTrash();
+      await message(`"${item.label}" has been restored.`, { title: 'Restored', type: 'info' });
+    } catch (error) {
+      console.error('Error restoring item:', error);
+      await message(`Failed to restore: ${error}`, { title: 'Error', type: 'error' });
+    }
+  };
+
+  const handleRetentionChange = async (days: number) => {
+    setRetentionDays(days);
+    await trashService.setRetentionDays(days);
+  };
+
+  const handlePurge = async () => {
+    const confirmed = await ask(
+      `Permanently remove items that have been in the recycle bin for more than ${retentionDays} day(s)?\n\nThis action cannot be undone.`,
+      { title: 'Purge Recycle Bin', type: 'warning' }
+    );
+    if (!confirmed) return;
+
+    setIsPurging(true);
+    try {
+      const report = await trashService.purge();
+      await loadTrash();
+      const kept = report.customersKept > 0
+        ? `\n${report.customersKept} customer(s) were kept because issued invoices refer to them.`
+        : '';
+      await message(
+        `Purged ${report.customersPurged} customer(s) and ${report.draftsPurged} draft(s).${kept}`,
+        { title: 'Purge Complete', type: 'info' }
+      );
+    } catch (error) {
+      console.error('Error purging recycle bin:', error);
+      await message(`Purge failed: ${error}`, { title: 'Error', type: 'error' });
+    } finally {
+      setIsPurging(false);
+    }
+  };
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <div className="flex items-center justify-between mb-4">
+        <div>
+          <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 transition-colors duration-200">Recycle Bin</h2>
+          <p className="text-sm text-gray-600 dark:text-gray-300 transition-colors duration-200">
+            Deleted customers and cleared drafts. Issued invoices are cancelled, never deleted.
+          </p>
+        </div>
+      </div>
+
+      {isLoading ? (
+        <div className="flex justify-center py-6">
+          <Loader2 size={32} className="text-blue-600 animate-spin" />
+        </div>
+      ) : items.length === 0 ? (
+        <p className="text-sm text-gray-500 dark:text-gray-400 py-4">The recycle bin is empty.</p>
+      ) : (
+        <div className="space-y-2 mb-4">
+          {items.map((item) => (
+            <div
+              key={`${item.kind}-${item.id}`}
+              className="flex items-center justify-between p-3 bg-white dark:bg-gray-800 rounded-lg border border-gray-200 dark:border-gray-600"
+            >
+              <div className="flex items-center gap-3">
+                {item.kind === 'customer' ? <Users size={18} className="text-blue-600" /> : <FileText size={18} className="text-gray-500" />}
+                <div>
+                  <p className="font-medium text-gray-800 dark:text-gray-100">{item.label}</p>
+                  <p className="text-xs text-gray-500">Deleted {new Date(`${item.deletedAt.replace(' ', 'T')}Z`).toLocaleString('en-GB')}</p>
+                </div>
+              </div>
+              <button
+                onClick={() => handleRestore(item)}
+                className="flex items-center gap-2 px-3 py-1.5 text-blue-700 dark:text-blue-300 bg-blue-100 dark:bg-blue-900 rounded-lg hover:bg-blue-200 dark:hover:bg-blue-800 text-sm font-medium"
+              >
+                <RotateCcw size={16} /> Restore
+              </button>
+            </div>
+          ))}
+        </div>
+      )}
+
+      <div className="flex flex-wrap items-center gap-4 pt-4 border-t border-gray-200 dark:border-gray-600">
+        <label className="text-sm text-gray-700 dark:text-gray-300 flex items-center gap-2">
+          Keep deleted items for
+          <input
+            type="number"
+            min={0}
+            value={retentionDays}
+            onChange={(e) => handleRetentionChange(Math.max(0, parseInt(e.target.value, 10) || 0))}
+            className="w-20 px-2 py-1 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 rounded-lg"
+          />
+          days
+        </label>
+        <button
+          onClick={handlePurge}
+          disabled={isPurging || items.length === 0}
+          className="flex items-center gap-2 px-4 py-2 bg-red-600 text-white rounded-lg hover:bg-red-700 disabled:opacity-50 disabled:cursor-not-allowed ml-auto"
+        >
+          {isPurging ? <Loader2 size={18} className="animate-spin" /> : <Trash2 size={18} />}
+          Purge Old Items
+        </button>
+      </div>
+    </div>
+  );
+}
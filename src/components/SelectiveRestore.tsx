@@ -0,0 +1,277 @@
+import { useState } from 'react';
+import { FileSearch, Loader2, RotateCcw, Users, FileText } from 'lucide-react';
+import { open, message } from '@tauri-apps/api/dialog';
+import {
+  backupService,
+  BackupRecords,
+  PassphraseRequiredError,
+  InvoiceConflictResolution,
+  CustomerConflictResolution,
+  RecordConflict,
+} from '../services/backup';
+import PassphraseDialog from './PassphraseDialog';
+
+interface InvoiceChoice {
+  selected: boolean;
+  onConflict?: InvoiceConflictResolution;
+  newNumber: string;
+}
+
+interface CustomerChoice {
+  selected: boolean;
+  onConflict?: CustomerConflictResolution;
+}
+
+const conflictLabel: Record<RecordConflict, string> = {
+  none: 'Not in current data',
+  identical: 'Already up to date',
+  different: 'Differs from current data',
+};
+
+const conflictClass: Record<RecordConflict, string> = {
+  none: 'text-green-700 bg-green-100 dark:text-green-300 dark:bg-green-900',
+  identical: 'text-gray-600 bg-gray-100 dark:text-gray-300 dark:bg-gray-700',
+  different: 'text-yellow-700 bg-yellow-100 dark:text-yellow-300 dark:bg-yellow-900',
+};
+
+export default function SelectiveRestore() {
+  const [sourcePath, setSourcePath] = useState<string | null>(null);
+  const [passphrase, setPassphrase] = useState<string | undefined>(undefined);
+  const [records, setRecords] = useState<BackupRecords | null>(null);
+  const [invoiceChoices, setInvoiceChoices] = useState<Record<string, InvoiceChoice>>({});
+  const [customerChoices, setCustomerChoices] = useState<Record<string, CustomerChoice>>({});
+  const [isLoading, setIsLoading] = useState(false);
+  const [isRestoring, setIsRestoring] = useState(false);
+  const [unlockPath, setUnlockPath] = useState<string | null>(null);
+  const [passphraseError, setPassphraseError] = useState('');
+
+  const loadRecords = async (path: string, pass?: string) => {
+    const loaded = await backupService.listBackupRecords(path, pass);
+    setSourcePath(path);
+    setPassphrase(pass);
+    setRecords(loaded);
+    setInvoiceChoices({});
+    setCustomerChoices({});
+  };
+
+  const handleChooseBackup = async () => {
+    const selected = await open({
+      directory: false,
+      multiple: false,
+      filters: [{ name: 'Invoice Backup', extensions: ['zip', 'enc', 'db'] }],
+      title: 'Select Backup to Recover Records From'
+    });
+    if (!selected || typeof selected !== 'string') return;
+
+    setIsLoading(true);
+    try {
+      await loadRecords(selected);
+    } catch (e: any) {
+      if (e instanceof PassphraseRequiredError) {
+        setPassphraseError('');
+        setUnlockPath(e.path);
+        return;
+      }
+      await message(`Could not read backup: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  const handleUnlock = async (pass: string) => {
+    if (!unlockPath) return;
+    setIsLoading(true);
+    setPassphraseError('');
+    try {
+      await loadRecords(unlockPath, pass);
+      setUnlockPath(null);
+    } catch (e: any) {
+      setPassphraseError(`${e.message || e}`);
+    } finally {
+      setIsLoading(false);
+    }
+  };
+
+  const updateInvoice = (invoiceNumber: string, change: Partial<InvoiceChoice>) => {
+    setInvoiceChoices((prev) => ({
+      ...prev,
+      [invoiceNumber]: { selected: false, newNumber: '', ...prev[invoiceNumber], ...change },
+    }));
+  };
+
+  const updateCustomer = (id: string, change: Partial<CustomerChoice>) => {
+    setCustomerChoices((prev) => ({ ...prev, [id]: { selected: false, ...prev[id], ...change } }));
+  };
+
+  const selectedInvoices = Object.entries(invoiceChoices).filter(([, c]) => c.selected);
+  const selectedCustomers = Object.entries(customerChoices).filter(([, c]) => c.selected);
+
+  const handleRestore = async () => {
+    if (!sourcePath) return;
+    setIsRestoring(true);
+    try {
+      const report = await backupService.restoreBackupRecords(sourcePath, {
+        invoices: selectedInvoices.map(([invoiceNumber, c]) => ({
+          invoiceNumber,
+          onConflict: c.onConflict,
+          newNumber: c.onConflict === 'renumber' ? c.newNumber : undefined,
+        })),
+        customers: selectedCustomers.map(([id, c]) => ({ id, onConflict: c.onConflict })),
+      }, passphrase);
+
+      await message(
+        `Invoices: ${report.invoicesRestored} restored, ${report.invoicesReplaced} replaced, ${report.invoicesRenumbered} restored under a new number, ${report.invoicesSkipped} skipped.\n` +
+        `Customers: ${report.customersRestored} restored, ${report.customersReplaced} replaced, ${report.customersSkipped} skipped.`,
+        { title: 'Records Recovered', type: 'info' }
+      );
+      await loadRecords(sourcePath, passphrase);
+    } catch (e: any) {
+      // Nothing is written when any record fails, so the selection can be fixed and retried
+      await message(`Recovery failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setIsRestoring(false);
+    }
+  };
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <div className="flex items-center justify-between mb-4">
+        <div>
+          <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 transition-colors duration-200">Recover Individual Records</h2>
+          <p className="text-sm text-gray-600 dark:text-gray-300 transition-colors duration-200">
+            Copy selected invoices or customers out of a backup without replacing everything else.
+          </p>
+        </div>
+        <button
+          onClick={handleChooseBackup}
+          disabled={isLoading}
+          className="flex items-center gap-2 px-4 py-2 bg-blue-100 dark:bg-blue-900 text-blue-700 dark:text-blue-300 rounded-lg hover:bg-blue-200 dark:hover:bg-blue-800 font-medium disabled:opacity-50"
+        >
+          {isLoading ? <Loader2 size={18} className="animate-spin" /> : <FileSearch size={18} />}
+          Open Backup
+        </button>
+      </div>
+
+      {records && (
+        <div className="space-y-6">
+          <p className="text-xs text-gray-500 truncate">{sourcePath}</p>
+
+          <div>
+            <h3 className="font-medium text-gray-800 dark:text-gray-200 mb-2 flex items-center gap-2"><FileText size={16} /> Invoices</h3>
+            {records.invoices.length === 0 ? (
+              <p className="text-sm text-gray-500">No invoices in this backup.</p>
+            ) : (
+              <div className="space-y-2 max-h-80 overflow-y-auto">
+                {records.invoices.map((invoice) => {
+                  const choice = invoiceChoices[invoice.invoiceNumber];
+                  return (
+                    <div key={invoice.invoiceNumber} className="p-3 bg-white dark:bg-gray-800 rounded-lg border border-gray-200 dark:border-gray-600">
+                      <label className="flex items-center gap-3 cursor-pointer">
+                        <input
+                          type="checkbox"
+                          checked={choice?.selected || false}
+                          disabled={invoice.conflict === 'identical'}
+                          onChange={(e) => updateInvoice(invoice.invoiceNumber, { selected: e.target.checked })}
+                        />
+                        <span className="font-medium text-gray-800 dark:text-gray-100">{invoice.invoiceNumber}</span>
+                        <span className="text-sm text-gray-500">{invoice.invoiceDate} - {invoice.customerName || 'Unknown customer'}</span>
+                        {invoice.status === 'CANCELLED' && <span className="text-xs text-red-600">Cancelled</span>}
+                        <span className="text-sm text-gray-700 dark:text-gray-300 ml-auto">Rs. {(invoice.grandTotal ?? 0).toFixed(2)}</span>
+                        <span className={`text-xs px-2 py-0.5 rounded-full ${conflictClass[invoice.conflict]}`}>{conflictLabel[invoice.conflict]}</span>
+                      </label>
+                      {choice?.selected && invoice.conflict === 'different' && (
+                        <div className="flex flex-wrap items-center gap-2 mt-2 pl-7 text-sm">
+                          <select
+                            value={choice.onConflict || ''}
+                            onChange={(e) => updateInvoice(invoice.invoiceNumber, { onConflict: (e.target.value || undefined) as InvoiceConflictResolution | undefined })}
+                            className="px-2 py-1 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg"
+                          >
+                            <option value="">Choose how to resolve...</option>
+                            <option value="skip">Keep current invoice</option>
+                            <option value="replace">Replace with backup (recorded as an amendment)</option>
+                            <option value="renumber">Restore as a new invoice number</option>
+                          </select>
+                          {choice.onConflict === 'renumber' && (
+                            <input
+                              type="text"
+                              placeholder="New invoice number"
+                              value={choice.newNumber}
+                              onChange={(e) => updateInvoice(invoice.invoiceNumber, { newNumber: e.target.value })}
+                              className="px-2 py-1 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg"
+                            />
+                          )}
+                        </div>
+                      )}
+                    </div>
+                  );
+                })}
+              </div>
+            )}
+          </div>
+
+          <div>
+            <h3 className="font-medium text-gray-800 dark:text-gray-200 mb-2 flex items-center gap-2"><Users size={16} /> Customers</h3>
+            {records.customers.length === 0 ? (
+              <p className="text-sm text-gray-500">No customers in this backup.</p>
+            ) : (
+              <div className="space-y-2 max-h-64 overflow-y-auto">
+                {records.customers.map((customer) => {
+                  const choice = customerChoices[customer.id];
+                  return (
+                    <div key={customer.id} className="p-3 bg-white dark:bg-gray-800 rounded-lg border border-gray-200 dark:border-gray-600">
+                      <label className="flex items-center gap-3 cursor-pointer">
+                        <input
+                          type="checkbox"
+                          checked={choice?.selected || false}
+                          disabled={customer.conflict === 'identical'}
+                          onChange={(e) => updateCustomer(customer.id, { selected: e.target.checked })}
+                        />
+                        <span className="font-medium text-gray-800 dark:text-gray-100">{customer.companyName}</span>
+                        {customer.gstNumber && <span className="text-sm text-gray-500">GSTIN {customer.gstNumber}</span>}
+                        <span className={`text-xs px-2 py-0.5 rounded-full ml-auto ${conflictClass[customer.conflict]}`}>{conflictLabel[customer.conflict]}</span>
+                      </label>
+                      {choice?.selected && customer.conflict === 'different' && (
+                        <div className="mt-2 pl-7 text-sm">
+                          <select
+                            value={choice.onConflict || ''}
+                            onChange={(e) => updateCustomer(customer.id, { onConflict: (e.target.value || undefined) as CustomerConflictResolution | undefined })}
+                            className="px-2 py-1 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg"
+                          >
+                            <option value="">Choose how to resolve...</option>
+                            <option value="skip">Keep current details</option>
+                            <option value="replace">Replace with backup details</option>
+                          </select>
+                        </div>
+                      )}
+                    </div>
+                  );
+                })}
+              </div>
+            )}
+          </div>
+
+          <div className="flex justify-end pt-4 border-t border-gray-200 dark:border-gray-600">
+            <button
+              onClick={handleRestore}
+              disabled={isRestoring || (selectedInvoices.length === 0 && selectedCustomers.length === 0)}
+              className="flex items-center gap-2 px-6 py-3 bg-indigo-600 dark:bg-indigo-500 text-white rounded-lg hover:bg-indigo-700 disabled:opacity-50 disabled:cursor-not-allowed"
+            >
+              {isRestoring ? <Loader2 size={20} className="animate-spin" /> : <RotateCcw size={20} />}
+              Recover Selected ({selectedInvoices.length + selectedCustomers.length})
+            </button>
+          </div>
+        </div>
+      )}
+
+      <PassphraseDialog
+        isOpen={unlockPath !== null}
+        title="Encrypted Backup"
+        description="This backup is encrypted. Enter the passphrase it was created with to read its records."
+        error={passphraseError}
+        isBusy={isLoading}
+        onSubmit={handleUnlock}
+        onCancel={() => setUnlockPath(null)}
+      />
+    </div>
+  );
+}
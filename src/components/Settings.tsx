@@ -1,11 +1,30 @@
-import { useState, useEffect } from 'react';
-import { Upload, Save, Image as ImageIcon, Loader2, Folder, Check, AlertCircle, HardDrive, FileUp, Settings as SettingsIcon, CloudOff, DownloadCloud } from 'lucide-react';
+import { useState, useEffect, useRef } from 'react';
+import { Upload, Save, Image as ImageIcon, Loader2, Folder, Check, AlertCircle, HardDrive, FileUp, Settings as SettingsIcon, CloudOff, DownloadCloud, Lock } from 'lucide-react';
 import { message, open, ask } from '@tauri-apps/api/dialog';
 import { CompanySettings } from '../types/invoice';
 import { dbService } from '../services/db';
-import { backupService } from '../services/backup';
+import { backupService, RetentionPolicy, PassphraseRequiredError, SyncConflictError, RestorePreview, DiffCounts, CloudDownloadProgress } from '../services/backup';
+import RecycleBin from './RecycleBin';
+import ChangesetSync from './ChangesetSync';
+import SelectiveRestore from './SelectiveRestore';
+import GoogleDriveBackup from './GoogleDriveBackup';
+import SyncProviderSettings from './SyncProviderSettings';
+import PassphraseDialog from './PassphraseDialog';
+import Diagnostics from './Diagnostics';
+import DataExport from './DataExport';
+import InvoicePdfExport from './InvoicePdfExport';
+import TallyIntegration from './TallyIntegration';
+import EmailSettings from './EmailSettings';
+import SigningSettings from './SigningSettings';
+import { vaultService, VaultStatus, BankDetails } from '../services/vault';
+
+interface SettingsProps {
+  // A backup to restore as soon as the page opens, e.g. the other device's copy after a sync conflict
+  restorePath?: string | null;
+  onRestoreStarted?: () => void;
+}
 
-export default function Settings() {
+export default function Settings({ restorePath, onRestoreStarted }: SettingsProps) {
   const [settings, setSettings] = useState<CompanySettings>({
     accountName: 'APEX SOLAR',
     bankName: 'STATE BANK OF INDIA',
@@ -23,11 +42,60 @@ export default function Settings() {
 
   // Backup State
   const [backupPath, setBackupPath] = useState('');
+  const [deviceName, setDeviceName] = useState('');
   const [autoBackup, setAutoBackup] = useState(false);
   const [isBackingUp, setIsBackingUp] = useState(false);
   const [isRestoring, setIsRestoring] = useState(false);
   const [cloudLink, setCloudLink] = useState('');
   const [isCloudRestoring, setIsCloudRestoring] = useState(false);
+  const [cloudProgress, setCloudProgress] = useState<CloudDownloadProgress | null>(null);
+  const [retention, setRetention] = useState<RetentionPolicy>(backupService.getRetention());
+  const [encryptBackups, setEncryptBackups] = useState(false);
+  // 'set' asks for a new backup passphrase; 'unlock' asks for the passphrase of an encrypted backup at `path`
+  const [passphraseDialog, setPassphraseDialog] = useState<{ mode: 'set' | 'unlock'; path?: string } | null>(null);
+  const [passphraseError, setPassphraseError] = useState('');
+  const [isUnlocking, setIsUnlocking] = useState(false);
+
+  // Vault State: once the vault exists, the IFSC code and account number are kept in it
+  const [vaultStatus, setVaultStatus] = useState<VaultStatus | null>(null);
+  const [maskedBankDetails, setMaskedBankDetails] = useState<BankDetails | null>(null);
+  const [vaultDialog, setVaultDialog] = useState<'create' | 'unlock' | null>(null);
+  const [vaultError, setVaultError] = useState('');
+  const [isVaultBusy, setIsVaultBusy] = useState(false);
+
+  const refreshVault = async () => {
+    const status = await vaultService.getStatus();
+    setVaultStatus(status);
+    setMaskedBankDetails(status.unlocked ? await vaultService.getMaskedBankDetails() : null);
+  };
+
+  const handleVaultSubmit = async (password: string) => {
+    setIsVaultBusy(true);
+    setVaultError('');
+    try {
+      await vaultService.unlock(password);
+      setVaultDialog(null);
+      // Unlocking moves any bank details still in the company settings into the vault
+      const loadedSettings = await dbService.getCompanySettings();
+      if (loadedSettings) setSettings(loadedSettings);
+      await refreshVault();
+    } catch (e: any) {
+      setVaultError(`${e.message || e}`);
+    } finally {
+      setIsVaultBusy(false);
+    }
+  };
+
+  const handleLockVault = async () => {
+    setVaultStatus(await vaultService.lock());
+    setMaskedBankDetails(null);
+  };
+
+  const bankPlaceholder = (field: keyof BankDetails) => {
+    if (!vaultStatus?.exists) return undefined;
+    if (!vaultStatus.unlocked) return 'Stored in the vault';
+    return maskedBankDetails?.[field] || 'Not set';
+  };
 
 
   const handleSelectBackupFolder = async () => {
@@ -68,57 +136,195 @@ export default function Settings() {
       await backupService.performBackup();
       await message(`Backup successful!`, { title: 'Success', type: 'info' });
     } catch (e: any) {
+      // The conflict dialog takes over from here
+      if (e instanceof SyncConflictError) return;
       await message(`Backup failed: ${e.message}`, { title: 'Error', type: 'error' });
     } finally {
       setIsBackingUp(false);
     }
   };
 
+  const handleRetentionChange = async (field: keyof RetentionPolicy, value: number) => {
+    const updated = { ...retention, [field]: Math.max(0, value || 0) };
+    setRetention(updated);
+    await backupService.setRetention(updated);
+  };
+
+  const handleDeviceNameSave = async () => {
+    try {
+      setDeviceName((await backupService.setDeviceName(deviceName)).name);
+    } catch (e: any) {
+      await message(`${e.message || e}`, { title: 'Error', type: 'error' });
+      setDeviceName((await backupService.getDeviceInfo()).name);
+    }
+  };
+
+  const handleToggleEncryption = async () => {
+    if (!encryptBackups) {
+      setPassphraseError('');
+      setPassphraseDialog({ mode: 'set' });
+      return;
+    }
+    const confirmed = await ask('Turn off encryption? New backups will be stored unencrypted. Existing encrypted backups still need their passphrase to restore.', {
+      title: 'Backup Encryption',
+      type: 'warning'
+    });
+    if (!confirmed) return;
+    await backupService.setEncryptionPassphrase(null);
+    setEncryptBackups(false);
+  };
+
+  const handlePassphraseSubmit = async (passphrase: string) => {
+    if (!passphraseDialog) return;
+
+    if (passphraseDialog.mode === 'set') {
+      try {
+        await backupService.setEncryptionPassphrase(passphrase);
+        setEncryptBackups(true);
+        setPassphraseDialog(null);
+        await message('Backups will now be encrypted. Keep this passphrase safe: without it, encrypted backups cannot be restored.', { title: 'Encryption Enabled', type: 'info' });
+      } catch (e: any) {
+        setPassphraseError(`${e.message || e}`);
+      }
+      return;
+    }
+
+    const path = passphraseDialog.path!;
+    setIsUnlocking(true);
+    setPassphraseError('');
+    let preview: RestorePreview;
+    try {
+      preview = await backupService.previewRestore(path, passphrase);
+    } catch (e: any) {
+      // Wrong passphrase: keep the dialog open so the user can try again
+      setPassphraseError(`${e.message || e}`);
+      return;
+    } finally {
+      setIsUnlocking(false);
+    }
+    setPassphraseDialog(null);
+    await confirmAndRestore(path, preview, passphrase);
+  };
+
+  const handlePassphraseCancel = () => {
+    setPassphraseDialog(null);
+    endRestore();
+  };
+
+  const endRestore = () => {
+    setIsRestoring(false);
+    setIsCloudRestoring(false);
+  };
+
+  const promptForBackupPassphrase = (path: string) => {
+    setPassphraseError('');
+    setPassphraseDialog({ mode: 'unlock', path });
+  };
+
+  const describeDiff = (label: string, diff: DiffCounts) =>
+    `${label}: ${diff.added} added, ${diff.removed} removed, ${diff.changed} changed, ${diff.unchanged} unchanged`;
+
+  // Shows what the backup would change and restores it if the user agrees
+  const confirmAndRestore = async (path: string, preview: RestorePreview, passphrase?: string) => {
+    const origin = preview.createdAt
+      ? `Backup from ${new Date(preview.createdAt).toLocaleString('en-GB')} (app v${preview.appVersion})`
+      : 'Database file (no backup metadata)';
+    const confirmed = await ask(
+      `${origin}\n\n${describeDiff('Invoices', preview.invoices)}\n${describeDiff('Customers', preview.customers)}\n\n` +
+      'Your current data will be replaced. A copy of it is kept as a rollback point.',
+      { title: 'Confirm Restore', type: 'warning' }
+    );
+    if (!confirmed) {
+      endRestore();
+      return;
+    }
+
+    try {
+      const report = await backupService.restoreFrom(path, passphrase);
+      const pdfs = report.restoredPdfs > 0 ? ` ${report.restoredPdfs} invoice PDF(s) were restored.` : '';
+      const next = report.restartRequired ? 'The app will restart.' : 'The app will reload.';
+      await message(`Restore successful!${pdfs} ${next}`, { title: 'Success', type: 'info' });
+      await backupService.finishRestore(report);
+    } catch (e: any) {
+      await message(`Restore failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+      endRestore();
+    }
+  };
+
+  // Validates the backup and computes the diff without touching the current data
+  const beginRestore = async (path: string) => {
+    try {
+      const preview = await backupService.previewRestore(path);
+      await confirmAndRestore(path, preview);
+    } catch (e: any) {
+      if (e instanceof PassphraseRequiredError) {
+        promptForBackupPassphrase(e.path);
+        return;
+      }
+      await message(`Restore failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+      endRestore();
+    }
+  };
+
   const handleRestoreFile = async () => {
     try {
       const selected = await open({
         directory: false,
         multiple: false,
-        filters: [{ name: 'SQLite Database', extensions: ['db'] }],
-        title: 'Select Database to Restore'
+        filters: [{ name: 'Invoice Backup', extensions: ['zip', 'enc', 'db'] }],
+        title: 'Select Backup to Restore'
       });
 
       if (selected && typeof selected === 'string') {
-        const confirmed = await ask(`Are you sure you want to restore from:\n${selected}\n\nThis will OVERWRITE your current data.`, {
-          title: 'Confirm Restore',
-          type: 'warning'
-        });
-
-        if (confirmed) {
-          setIsRestoring(true);
-          try {
-            await backupService.restoreFrom(selected);
-            await message('Restore successful! The app will reload.', { title: 'Success', type: 'info' });
-            window.location.reload();
-          } catch (e: any) {
-            await message(`Restore failed: ${e.message}`, { title: 'Error', type: 'error' });
-            setIsRestoring(false);
-          }
-        }
+        setIsRestoring(true);
+        await beginRestore(selected);
       }
     } catch (e: any) {
       await message('Failed to select file', { title: 'Error', type: 'error' });
     }
   };
 
+  // Backups downloaded from Google Drive or a sync provider go through the same preview and confirmation
+  const restoreDownloaded = async (path: string) => {
+    setIsCloudRestoring(true);
+    await beginRestore(path);
+  };
+
+  // Effects run twice on mount in development; only start each requested restore once
+  const startedRestore = useRef<string | null>(null);
+  useEffect(() => {
+    if (!restorePath || startedRestore.current === restorePath) return;
+    startedRestore.current = restorePath;
+    onRestoreStarted?.();
+    setIsRestoring(true);
+    beginRestore(restorePath);
+  }, [restorePath]);
+
   const handleCloudRestore = async () => {
     if (!cloudLink) return;
+    setIsCloudRestoring(true);
+    setCloudProgress(null);
+    let downloaded: string;
     try {
-      const confirmed = await ask('This will overwrite your current database. Are you sure?', { title: 'Disaster Recovery', type: 'warning' });
-      if (!confirmed) return;
-
-      setIsCloudRestoring(true);
-      await backupService.restoreFromCloudLink(cloudLink);
-      await message('Restored successfully from Cloud Link! The app will reload.', { title: 'Success', type: 'info' });
-      window.location.reload();
+      downloaded = await backupService.downloadFromCloudLink(cloudLink, setCloudProgress);
     } catch (e: any) {
-      await message(`Cloud Restore failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+      if (e?.kind !== 'cancelled') {
+        const hint = e?.kind === 'network' ? '\n\nThe part already downloaded is kept; Recover again to resume.' : '';
+        await message(`Cloud Restore failed: ${e.message || e}${hint}`, { title: 'Error', type: 'error' });
+      }
       setIsCloudRestoring(false);
+      setCloudProgress(null);
+      return;
+    }
+    setCloudProgress(null);
+    await beginRestore(downloaded);
+  };
+
+  const handleCancelCloudRestore = async () => {
+    try {
+      await backupService.cancelCloudDownload();
+    } catch (e) {
+      console.error('Failed to cancel cloud download', e);
     }
   };
 
@@ -138,6 +344,7 @@ export default function Settings() {
         if (loadedLogo) {
           setLogoPreviewUrl(loadedLogo);
         }
+        await refreshVault();
       } catch (error) {
         console.error('Error loading settings:', error);
       } finally {
@@ -156,8 +363,14 @@ export default function Settings() {
         const auto = await dbService.getSetting('auto_backup_enabled');
         setAutoBackup(auto === 'true');
 
+        const storedRetention = await dbService.getSetting('backup_retention');
+        if (storedRetention) setRetention({ ...backupService.getRetention(), ...JSON.parse(storedRetention) });
+
         const link = await dbService.getSetting('cloud_recovery_link');
         if (link) setCloudLink(link);
+
+        setEncryptBackups(await backupService.isEncryptionEnabled());
+        setDeviceName((await backupService.getDeviceInfo()).name);
       } catch (e) {
         console.error("Failed to load backup settings", e);
       }
@@ -168,7 +381,19 @@ export default function Settings() {
   const handleSaveSettings = async () => {
     setIsSaving(true);
     try {
-      await dbService.saveCompanySettings(settings);
+      if (vaultStatus?.exists) {
+        // Only fields the user typed in replace what the vault holds
+        const update: Partial<BankDetails> = {};
+        if (settings.ifscCode.trim()) update.ifscCode = settings.ifscCode;
+        if (settings.accountNumber.trim()) update.accountNumber = settings.accountNumber;
+        if (Object.keys(update).length > 0) await vaultService.saveBankDetails(update);
+        const cleared = { ...settings, ifscCode: '', accountNumber: '' };
+        await dbService.saveCompanySettings(cleared);
+        setSettings(cleared);
+        await refreshVault();
+      } else {
+        await dbService.saveCompanySettings(settings);
+      }
       await message('Company settings have been saved successfully!', {
         title: 'Success',
         type: 'info'
@@ -287,7 +512,43 @@ export default function Settings() {
       ) : (
         <div className="space-y-8">
           <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
-            <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-4 transition-colors duration-200">Bank Account Details</h2>
+            <div className="flex flex-wrap items-start justify-between gap-3 mb-4">
+              <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 transition-colors duration-200">Bank Account Details</h2>
+              <div className="flex items-center gap-2 text-sm text-gray-600 dark:text-gray-300">
+                <Lock size={16} />
+                {!vaultStatus?.exists ? (
+                  <>
+                    <span>IFSC and account number are stored unencrypted</span>
+                    <button
+                      onClick={() => { setVaultError(''); setVaultDialog('create'); }}
+                      className="px-3 py-1 bg-blue-600 dark:bg-blue-500 text-white rounded-lg hover:bg-blue-700 dark:hover:bg-blue-600 transition-colors duration-200"
+                    >
+                      Set Up Vault
+                    </button>
+                  </>
+                ) : vaultStatus.unlocked ? (
+                  <>
+                    <span>Vault unlocked</span>
+                    <button
+                      onClick={handleLockVault}
+                      className="px-3 py-1 border border-gray-300 dark:border-gray-600 rounded-lg hover:bg-gray-100 dark:hover:bg-gray-600 transition-colors duration-200"
+                    >
+                      Lock
+                    </button>
+                  </>
+                ) : (
+                  <>
+                    <span>Vault locked</span>
+                    <button
+                      onClick={() => { setVaultError(''); setVaultDialog('unlock'); }}
+                      className="px-3 py-1 bg-blue-600 dark:bg-blue-500 text-white rounded-lg hover:bg-blue-700 dark:hover:bg-blue-600 transition-colors duration-200"
+                    >
+                      Unlock
+                    </button>
+                  </>
+                )}
+              </div>
+            </div>
             <div className="space-y-4">
               <div>
                 <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 transition-colors duration-200 mb-2">
@@ -321,6 +582,7 @@ export default function Settings() {
                   <input
                     type="text"
                     value={settings.ifscCode}
+                    placeholder={bankPlaceholder('ifscCode')}
                     onChange={(e) => setSettings({ ...settings, ifscCode: e.target.value })}
                     className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 dark:focus:ring-blue-400 focus:border-transparent transition-colors duration-200"
                   />
@@ -333,6 +595,7 @@ export default function Settings() {
                   <input
                     type="text"
                     value={settings.accountNumber}
+                    placeholder={bankPlaceholder('accountNumber')}
                     onChange={(e) => setSettings({ ...settings, accountNumber: e.target.value })}
                     className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 dark:focus:ring-blue-400 focus:border-transparent transition-colors duration-200"
                   />
@@ -433,6 +696,50 @@ export default function Settings() {
                     <p className="text-xs text-gray-500">Automatically creates a backup/sync copy whenever you save data.</p>
                   </div>
                 </div>
+
+                <div className="flex items-center gap-3 p-4 bg-white dark:bg-gray-800 rounded-lg border border-gray-200 dark:border-gray-600">
+                  <div
+                    onClick={handleToggleEncryption}
+                    className={`w-12 h-6 flex items-center rounded-full p-1 cursor-pointer transition-colors ${encryptBackups ? 'bg-green-500' : 'bg-gray-300 dark:bg-gray-600'}`}
+                  >
+                    <div className={`bg-white w-4 h-4 rounded-full shadow-md transform transition-transform ${encryptBackups ? 'translate-x-6' : 'translate-x-0'}`} />
+                  </div>
+                  <div>
+                    <p className="font-medium text-gray-800 dark:text-gray-200 flex items-center gap-1"><Lock size={14} /> Encrypt Backups</p>
+                    <p className="text-xs text-gray-500">Protects customer GSTINs, PANs and bank details in the backup folder with a passphrase kept in the vault.</p>
+                  </div>
+                </div>
+
+                <div className="p-4 bg-white dark:bg-gray-800 rounded-lg border border-gray-200 dark:border-gray-600">
+                  <p className="font-medium text-gray-800 dark:text-gray-200">Snapshot Retention</p>
+                  <p className="text-xs text-gray-500 mb-3">Each backup is saved as a timestamped snapshot. Keep the latest snapshot of each of the last:</p>
+                  <div className="grid grid-cols-3 gap-4">
+                    {(['daily', 'weekly', 'monthly'] as (keyof RetentionPolicy)[]).map((field) => (
+                      <label key={field} className="text-sm text-gray-700 dark:text-gray-300">
+                        <span className="block mb-1">{field === 'daily' ? 'Days' : field === 'weekly' ? 'Weeks' : 'Months'}</span>
+                        <input
+                          type="number"
+                          min={0}
+                          value={retention[field]}
+                          onChange={(e) => handleRetentionChange(field, parseInt(e.target.value, 10))}
+                          className="w-full px-3 py-1.5 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg"
+                        />
+                      </label>
+                    ))}
+                  </div>
+                </div>
+
+                <div className="p-4 bg-white dark:bg-gray-800 rounded-lg border border-gray-200 dark:border-gray-600">
+                  <label className="font-medium text-gray-800 dark:text-gray-200">This Device's Name</label>
+                  <p className="text-xs text-gray-500 mb-3">Shown on other computers sharing this backup folder when their data conflicts with this one.</p>
+                  <input
+                    type="text"
+                    value={deviceName}
+                    onChange={(e) => setDeviceName(e.target.value)}
+                    onBlur={handleDeviceNameSave}
+                    className="w-full px-3 py-1.5 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg"
+                  />
+                </div>
               </div>
 
               <div className="flex flex-wrap gap-4 pt-4 border-t border-gray-200 dark:border-gray-600">
@@ -457,6 +764,10 @@ export default function Settings() {
             </div>
           </div>
 
+          <GoogleDriveBackup onRestore={restoreDownloaded} />
+
+          <SyncProviderSettings onRestore={restoreDownloaded} />
+
           <div className="bg-red-50 dark:bg-red-900/10 p-6 rounded-lg border border-red-100 dark:border-red-900/30">
             <h2 className="text-xl font-semibold text-red-800 dark:text-red-200 mb-4 flex items-center gap-2">
               <CloudOff size={24} /> Disaster Recovery
@@ -479,14 +790,66 @@ export default function Settings() {
                 className="px-4 py-2 bg-red-600 text-white rounded-lg hover:bg-red-700 disabled:opacity-50 flex items-center gap-2"
               >
                 {isCloudRestoring ? <Loader2 size={16} className="animate-spin" /> : <DownloadCloud size={16} />}
-                Recover
+                {cloudProgress?.total ? `${Math.floor((cloudProgress.downloaded / cloudProgress.total) * 100)}%` : 'Recover'}
               </button>
+              {cloudProgress && (
+                <button
+                  onClick={handleCancelCloudRestore}
+                  className="px-4 py-2 text-red-700 dark:text-red-300 bg-white dark:bg-gray-800 border border-red-200 dark:border-red-700 rounded-lg hover:bg-red-100 dark:hover:bg-red-900/30"
+                >
+                  Cancel
+                </button>
+              )}
             </div>
             <p className="text-xs text-red-500 mt-2">
               * Note: Please ensure you have selected a backup/download folder above first.
             </p>
           </div>
 
+          <DataExport />
+
+          <InvoicePdfExport />
+
+          <TallyIntegration />
+
+          <EmailSettings />
+
+          <SigningSettings />
+
+          <ChangesetSync />
+
+          <SelectiveRestore />
+
+          <RecycleBin />
+
+          <Diagnostics />
+
+          <PassphraseDialog
+            isOpen={passphraseDialog !== null}
+            title={passphraseDialog?.mode === 'set' ? 'Set Backup Passphrase' : 'Encrypted Backup'}
+            description={passphraseDialog?.mode === 'set'
+              ? 'Backups will be encrypted with this passphrase. It cannot be recovered if you forget it.'
+              : 'This backup is encrypted. Enter the passphrase it was created with to restore it.'}
+            confirm={passphraseDialog?.mode === 'set'}
+            error={passphraseError}
+            isBusy={isUnlocking}
+            onSubmit={handlePassphraseSubmit}
+            onCancel={handlePassphraseCancel}
+          />
+
+          <PassphraseDialog
+            isOpen={vaultDialog !== null}
+            title={vaultDialog === 'create' ? 'Set Up Vault' : 'Unlock Vault'}
+            description={vaultDialog === 'create'
+              ? 'Bank details and cloud sign-ins will be encrypted with this password (at least 8 characters). It cannot be recovered if you forget it.'
+              : 'Enter the vault password to view and change the bank details kept in it.'}
+            confirm={vaultDialog === 'create'}
+            error={vaultError}
+            isBusy={isVaultBusy}
+            onSubmit={handleVaultSubmit}
+            onCancel={() => setVaultDialog(null)}
+          />
+
           <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
             <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-4 transition-colors duration-200">Company Logo</h2>
             <p className="text-sm text-gray-600 dark:text-gray-300 mb-4 transition-colors duration-200">
@@ -0,0 +1,190 @@
+import { useEffect, useState } from 'react';
+import { FileSignature, Loader2, Save, ShieldCheck, FolderOpen, BadgeCheck, AlertCircle } from 'lucide-react';
+import { message, open } from '@tauri-apps/api/dialog';
+import { signingService, SigningSettings as Settings, CertificateInfo, SignatureReport, DEFAULT_SIGNING_SETTINGS } from '../services/signing';
+
+const inputClass = 'w-full px-3 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 dark:focus:ring-blue-400 focus:border-transparent transition-colors duration-200';
+
+const formatDate = (value?: string | null) => (value ? new Date(value).toLocaleString('en-GB') : '-');
+
+// DSC used to sign invoice PDFs, and a check of signed PDFs
+export default function SigningSettings() {
+  const [settings, setSettings] = useState<Settings>(DEFAULT_SIGNING_SETTINGS);
+  const [password, setPassword] = useState('');
+  const [certificate, setCertificate] = useState<CertificateInfo | null>(null);
+  const [reports, setReports] = useState<{ file: string; signatures: SignatureReport[] } | null>(null);
+  const [isSaving, setIsSaving] = useState(false);
+  const [isChecking, setIsChecking] = useState(false);
+
+  useEffect(() => {
+    signingService.getSettings().then(setSettings).catch((e) => console.error('Failed to load signing settings:', e));
+  }, []);
+
+  const update = <K extends keyof Settings>(key: K, value: Settings[K]) => setSettings({ ...settings, [key]: value });
+
+  const chooseCertificate = async () => {
+    const selected = await open({
+      multiple: false,
+      title: 'Select the DSC Certificate File',
+      filters: [{ name: 'PKCS#12 Certificate', extensions: ['pfx', 'p12'] }],
+    });
+    if (typeof selected === 'string') {
+      update('certificatePath', selected);
+      setCertificate(null);
+    }
+  };
+
+  const save = async () => {
+    await signingService.saveSettings(settings);
+    // A blank field keeps the stored password
+    if (password) {
+      await signingService.savePassword(password);
+      setPassword('');
+    }
+  };
+
+  const handleSave = async () => {
+    setIsSaving(true);
+    try {
+      await save();
+      // Turning signing on with a certificate that cannot be opened would fail every invoice
+      if (settings.enabled) setCertificate(await signingService.getCertificate());
+      await message('Signing settings saved.', { title: 'Digital Signature', type: 'info' });
+    } catch (e: any) {
+      await message(`${e.message || e}`, { title: 'Digital Signature', type: 'error' });
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  const handleCheck = async () => {
+    setIsChecking(true);
+    try {
+      await save();
+      setCertificate(await signingService.getCertificate());
+    } catch (e: any) {
+      setCertificate(null);
+      await message(`${e.message || e}`, { title: 'Certificate Check Failed', type: 'error' });
+    } finally {
+      setIsChecking(false);
+    }
+  };
+
+  const handleVerify = async () => {
+    const selected = await open({ multiple: false, title: 'Select a Signed PDF', filters: [{ name: 'PDF', extensions: ['pdf'] }] });
+    if (typeof selected !== 'string') return;
+    try {
+      setReports({ file: selected, signatures: await signingService.verify(selected) });
+    } catch (e: any) {
+      setReports(null);
+      await message(`${e.message || e}`, { title: 'Verification Failed', type: 'error' });
+    }
+  };
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-1 flex items-center gap-2 transition-colors duration-200">
+        <FileSignature size={22} /> Digital Signature
+      </h2>
+      <p className="text-sm text-gray-600 dark:text-gray-300 mb-4 transition-colors duration-200">
+        Signs invoice PDFs with a Class 3 DSC exported as a .pfx file. The signature appears where the stamp would be. The certificate password is kept in the vault.
+      </p>
+
+      <label className="flex items-center gap-2 mb-4 text-sm text-gray-700 dark:text-gray-200">
+        <input type="checkbox" checked={settings.enabled} onChange={(e) => update('enabled', e.target.checked)} />
+        Sign invoice PDFs
+      </label>
+
+      <div className="grid grid-cols-1 md:grid-cols-2 gap-3 mb-4">
+        <div className="md:col-span-2">
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Certificate file</label>
+          <div className="flex gap-2">
+            <input type="text" value={settings.certificatePath} readOnly placeholder="No certificate selected" className={inputClass} />
+            <button
+              onClick={chooseCertificate}
+              className="flex items-center gap-2 px-4 py-2 bg-gray-600 text-white rounded-lg hover:bg-gray-700 whitespace-nowrap"
+            >
+              <FolderOpen size={18} /> Browse
+            </button>
+          </div>
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Certificate password</label>
+          <input type="password" value={password} onChange={(e) => setPassword(e.target.value)} placeholder="Unchanged" autoComplete="new-password" className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Reason</label>
+          <input type="text" value={settings.reason} onChange={(e) => update('reason', e.target.value)} className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Location</label>
+          <input type="text" value={settings.location} onChange={(e) => update('location', e.target.value)} placeholder="Kolkata" className={inputClass} />
+        </div>
+      </div>
+
+      {certificate && (
+        <div className="mb-4 p-3 rounded-lg border border-gray-200 dark:border-gray-600 text-sm text-gray-700 dark:text-gray-200">
+          <p className="font-semibold flex items-center gap-2">
+            <BadgeCheck size={16} className={certificate.expired ? 'text-red-500' : 'text-green-600'} /> {certificate.name}
+          </p>
+          <p className="text-xs text-gray-500 dark:text-gray-400">Issued by {certificate.issuer}</p>
+          <p className={`text-xs ${certificate.expired ? 'text-red-500' : 'text-gray-500 dark:text-gray-400'}`}>
+            Valid {formatDate(certificate.validFrom)} to {formatDate(certificate.validTo)}{certificate.expired ? ' (expired)' : ''}
+          </p>
+        </div>
+      )}
+
+      <div className="flex flex-wrap gap-2 items-center mb-2">
+        <button
+          onClick={handleSave}
+          disabled={isSaving}
+          className="flex items-center gap-2 px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50"
+        >
+          {isSaving ? <Loader2 size={18} className="animate-spin" /> : <Save size={18} />}
+          Save
+        </button>
+        <button
+          onClick={handleCheck}
+          disabled={isChecking || !settings.certificatePath}
+          className="flex items-center gap-2 px-4 py-2 bg-gray-600 text-white rounded-lg hover:bg-gray-700 disabled:opacity-50"
+        >
+          {isChecking ? <Loader2 size={18} className="animate-spin" /> : <BadgeCheck size={18} />}
+          Check Certificate
+        </button>
+        <button
+          onClick={handleVerify}
+          className="flex items-center gap-2 px-4 py-2 bg-gray-600 text-white rounded-lg hover:bg-gray-700"
+        >
+          <ShieldCheck size={18} /> Verify a PDF
+        </button>
+      </div>
+
+      {reports && (
+        <div className="mt-3 text-sm text-gray-700 dark:text-gray-200">
+          <p className="text-xs text-gray-500 dark:text-gray-400 mb-2 break-all">{reports.file}</p>
+          {reports.signatures.length === 0 && <p>This PDF is not digitally signed.</p>}
+          {reports.signatures.map((report, i) => (
+            <div key={i} className="p-3 mb-2 rounded-lg border border-gray-200 dark:border-gray-600">
+              <p className={`font-semibold flex items-center gap-2 ${report.problem ? 'text-red-600' : 'text-green-600'}`}>
+                {report.problem ? <AlertCircle size={16} /> : <ShieldCheck size={16} />}
+                {report.problem || 'Signature is valid and the document is unchanged.'}
+              </p>
+              <p>Signed by {report.signer} on {formatDate(report.signedAt)}</p>
+              <p className="text-xs text-gray-500 dark:text-gray-400">
+                {report.signedTimeInSignature
+                  ? "The signing time is from the signer's clock, sealed in the signature."
+                  : "The signing time is only the signer's claim; it is not covered by the signature."}
+              </p>
+              <p className="text-xs text-gray-500 dark:text-gray-400">Issued by {report.issuer}</p>
+              {report.reason && <p className="text-xs text-gray-500 dark:text-gray-400">Reason: {report.reason}</p>}
+              {report.location && <p className="text-xs text-gray-500 dark:text-gray-400">Location: {report.location}</p>}
+            </div>
+          ))}
+          {reports.signatures.length > 0 && (
+            <p className="text-xs text-gray-500 dark:text-gray-400">Whether the issuing CA is trusted is not checked here; Adobe Reader shows that.</p>
+          )}
+        </div>
+      )}
+    </div>
+  );
+}
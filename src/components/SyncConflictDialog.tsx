@@ -0,0 +1,105 @@
+import { useState, useEffect } from 'react';
+import { AlertTriangle, Loader2, Monitor, Laptop } from 'lucide-react';
+import { message } from '@tauri-apps/api/dialog';
+import { backupService, SyncCheck } from '../services/backup';
+
+interface SyncConflictDialogProps {
+  // Hands the other device's backup to the regular restore flow (preview, passphrase, confirm)
+  onUseRemote: (path: string) => void;
+}
+
+const formatTime = (value?: string) => (value ? new Date(value).toLocaleString('en-GB') : 'Unknown');
+
+// Shown when the backup folder holds changes from another device that this device has not seen:
+// at launch, when such a backup syncs into the folder, or when a backup is refused because of them
+export default function SyncConflictDialog({ onUseRemote }: SyncConflictDialogProps) {
+  const [check, setCheck] = useState<SyncCheck | null>(null);
+  const [isBusy, setIsBusy] = useState(false);
+
+  useEffect(() => {
+    const unsubscribe = backupService.onSyncConflict(setCheck);
+    backupService.checkForRemoteBackup();
+    return unsubscribe;
+  }, []);
+
+  if (!check || !check.remote) return null;
+  const remote = check.remote;
+
+  const handleKeepLocal = async () => {
+    setIsBusy(true);
+    try {
+      await backupService.performBackup(true);
+      setCheck(null);
+      await message(`Backed up this device's data. ${remote.deviceName}'s backup is kept in the folder until the retention policy removes it.`, {
+        title: 'Backup Complete',
+        type: 'info'
+      });
+    } catch (e: any) {
+      await message(`Backup failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setIsBusy(false);
+    }
+  };
+
+  const handleUseRemote = () => {
+    setCheck(null);
+    onUseRemote(remote.path);
+  };
+
+  const explanation = check.status === 'diverged'
+    ? `Both this device and ${remote.deviceName} changed the data since they last synced. Choose which copy to keep; the other one's changes will not be merged.`
+    : `${remote.deviceName} saved newer data to the backup folder. Backing up from here would overwrite it.`;
+
+  return (
+    <div className="fixed inset-0 z-50 flex items-center justify-center bg-black/50">
+      <div className="w-full max-w-lg p-6 bg-white dark:bg-gray-800 rounded-lg shadow-lg space-y-4">
+        <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 flex items-center gap-2">
+          <AlertTriangle size={22} className="text-yellow-500" /> Data Changed on Another Device
+        </h2>
+        <p className="text-sm text-gray-600 dark:text-gray-300">{explanation}</p>
+
+        <div className="grid grid-cols-2 gap-3 text-sm">
+          <div className="p-3 rounded-lg border border-gray-200 dark:border-gray-600">
+            <p className="font-medium text-gray-800 dark:text-gray-100 flex items-center gap-1 mb-1">
+              <Monitor size={16} /> {check.device.name} (this device)
+            </p>
+            <p className="text-gray-600 dark:text-gray-400">Last change: {formatTime(check.localLastModified)}</p>
+            <p className="text-gray-600 dark:text-gray-400">Last synced: {formatTime(check.lastSyncedAt)}</p>
+          </div>
+          <div className="p-3 rounded-lg border border-gray-200 dark:border-gray-600">
+            <p className="font-medium text-gray-800 dark:text-gray-100 flex items-center gap-1 mb-1">
+              <Laptop size={16} /> {remote.deviceName}
+            </p>
+            <p className="text-gray-600 dark:text-gray-400">Last change: {formatTime(remote.lastModified)}</p>
+            <p className="text-gray-600 dark:text-gray-400">Backed up: {formatTime(remote.createdAt)}</p>
+          </div>
+        </div>
+
+        <div className="flex flex-wrap justify-end gap-2 pt-2">
+          <button
+            onClick={() => setCheck(null)}
+            disabled={isBusy}
+            className="px-4 py-2 text-gray-700 dark:text-gray-300 bg-gray-100 dark:bg-gray-700 rounded-lg hover:bg-gray-200 dark:hover:bg-gray-600 disabled:opacity-50"
+          >
+            Decide Later
+          </button>
+          <button
+            onClick={handleKeepLocal}
+            disabled={isBusy}
+            className="flex items-center gap-2 px-4 py-2 bg-gray-700 text-white rounded-lg hover:bg-gray-800 disabled:opacity-50"
+          >
+            {isBusy && <Loader2 size={16} className="animate-spin" />}
+            Keep This Device's Data
+          </button>
+          <button
+            onClick={handleUseRemote}
+            disabled={isBusy}
+            className="px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50"
+          >
+            Use {remote.deviceName}'s Data
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
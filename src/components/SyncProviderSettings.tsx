@@ -0,0 +1,301 @@
+import { useState, useEffect } from 'react';
+import { Server, Loader2, Save, Plug, RefreshCw, DownloadCloud, Trash2, Folder } from 'lucide-react';
+import { message, ask, open } from '@tauri-apps/api/dialog';
+import { backupService, RemoteFile, SyncProviderConfig } from '../services/backup';
+
+type ProviderKind = 'none' | SyncProviderConfig['kind'];
+
+interface SyncProviderSettingsProps {
+  // Hands a downloaded backup to the regular restore flow (preview, passphrase, confirm)
+  onRestore: (path: string) => Promise<void>;
+}
+
+const emptyConfig = (kind: SyncProviderConfig['kind']): SyncProviderConfig => {
+  switch (kind) {
+    case 'local':
+      return { kind, path: '' };
+    case 'webdav':
+      return { kind, url: '', username: '', password: '' };
+    case 's3':
+      return { kind, endpoint: '', region: '', bucket: '', prefix: '', accessKeyId: '', secretAccessKey: '', pathStyle: true };
+  }
+};
+
+const inputClass = 'w-full px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 text-gray-900 dark:text-gray-100 rounded-lg text-sm';
+const labelClass = 'block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1';
+
+export default function SyncProviderSettings({ onRestore }: SyncProviderSettingsProps) {
+  const [config, setConfig] = useState<SyncProviderConfig | null>(null);
+  // Whether a secret is already stored, so a blank secret field means "unchanged"
+  const [hasStoredSecret, setHasStoredSecret] = useState(false);
+  const [savedKind, setSavedKind] = useState<ProviderKind>('none');
+  const [backups, setBackups] = useState<RemoteFile[]>([]);
+  // 'test', 'save', 'list' or the name of the backup being downloaded
+  const [busy, setBusy] = useState<string | null>(null);
+
+  const refreshBackups = async () => {
+    setBusy('list');
+    try {
+      setBackups(await backupService.listSyncBackups());
+    } catch (e: any) {
+      await message(`Could not list remote backups: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  useEffect(() => {
+    backupService.getSyncProvider()
+      .then((stored) => {
+        setConfig(stored);
+        setSavedKind(stored ? stored.kind : 'none');
+        setHasStoredSecret(stored !== null && stored.kind !== 'local');
+        if (stored) {
+          backupService.listSyncBackups().then(setBackups).catch((e) => console.error('Failed to list remote backups', e));
+        }
+      })
+      .catch((e) => console.error('Failed to load sync provider', e));
+  }, []);
+
+  const kind: ProviderKind = config ? config.kind : 'none';
+
+  const handleKindChange = (next: ProviderKind) => {
+    setConfig(next === 'none' ? null : emptyConfig(next));
+    setHasStoredSecret(next !== 'none' && next === savedKind && next !== 'local');
+  };
+
+  const update = (change: Partial<SyncProviderConfig>) => {
+    setConfig((prev) => (prev ? ({ ...prev, ...change } as SyncProviderConfig) : prev));
+  };
+
+  const handleBrowse = async () => {
+    const selected = await open({ directory: true, multiple: false, title: 'Select Sync Folder' });
+    if (selected && typeof selected === 'string') update({ path: selected });
+  };
+
+  const handleTest = async () => {
+    if (!config) return;
+    setBusy('test');
+    try {
+      const count = await backupService.testSyncProvider(config);
+      await message(`Connected. ${count} backup(s) found.`, { title: 'Connection OK', type: 'info' });
+    } catch (e: any) {
+      await message(`Connection failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleSave = async () => {
+    if (!config && savedKind !== 'none') {
+      const confirmed = await ask('Stop uploading backups to this provider? Backups already uploaded are kept.', { title: 'Remove Provider', type: 'warning' });
+      if (!confirmed) return;
+    }
+    setBusy('save');
+    try {
+      await backupService.setSyncProvider(config);
+      setSavedKind(kind);
+      setHasStoredSecret(config !== null && config.kind !== 'local');
+      setBackups(config ? await backupService.listSyncBackups() : []);
+      await message(config ? 'Backups will be uploaded here after each backup.' : 'Sync provider removed.', { title: 'Saved', type: 'info' });
+    } catch (e: any) {
+      await message(`Failed to save: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleRestore = async (file: RemoteFile) => {
+    setBusy(file.name);
+    try {
+      const localPath = await backupService.pullFromSyncProvider(file.name);
+      await onRestore(localPath);
+    } catch (e: any) {
+      await message(`Download failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleDelete = async (file: RemoteFile) => {
+    const confirmed = await ask(`Delete ${file.name} from the remote storage?`, { title: 'Delete Backup', type: 'warning' });
+    if (!confirmed) return;
+    try {
+      await backupService.deleteSyncBackup(file.name);
+      setBackups((prev) => prev.filter((b) => b.name !== file.name));
+    } catch (e: any) {
+      await message(`Delete failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    }
+  };
+
+  const secretPlaceholder = hasStoredSecret ? 'Unchanged' : '';
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-1 flex items-center gap-2 transition-colors duration-200">
+        <Server size={24} /> Remote Backup Storage
+      </h2>
+      <p className="text-sm text-gray-600 dark:text-gray-300 mb-4 transition-colors duration-200">
+        Upload every backup to a second location: another folder, a WebDAV server (Nextcloud) or S3-compatible storage (MinIO, AWS).
+      </p>
+
+      <div className="space-y-4">
+        <div>
+          <label className={labelClass}>Provider</label>
+          <select value={kind} onChange={(e) => handleKindChange(e.target.value as ProviderKind)} className={inputClass}>
+            <option value="none">None</option>
+            <option value="local">Folder</option>
+            <option value="webdav">WebDAV (Nextcloud, ownCloud)</option>
+            <option value="s3">S3-compatible (MinIO, AWS S3)</option>
+          </select>
+        </div>
+
+        {config?.kind === 'local' && (
+          <div>
+            <label className={labelClass}>Folder</label>
+            <div className="flex gap-2">
+              <input type="text" value={config.path} onChange={(e) => update({ path: e.target.value })} className={inputClass} />
+              <button onClick={handleBrowse} className="px-3 py-2 bg-blue-100 dark:bg-blue-900 text-blue-700 dark:text-blue-300 rounded-lg hover:bg-blue-200 dark:hover:bg-blue-800">
+                <Folder size={18} />
+              </button>
+            </div>
+          </div>
+        )}
+
+        {config?.kind === 'webdav' && (
+          <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+            <div className="md:col-span-2">
+              <label className={labelClass}>Folder URL</label>
+              <input
+                type="text"
+                placeholder="https://cloud.example.com/remote.php/dav/files/user/Backups/"
+                value={config.url}
+                onChange={(e) => update({ url: e.target.value })}
+                className={inputClass}
+              />
+            </div>
+            <div>
+              <label className={labelClass}>Username</label>
+              <input type="text" value={config.username} onChange={(e) => update({ username: e.target.value })} className={inputClass} />
+            </div>
+            <div>
+              <label className={labelClass}>Password / App Password</label>
+              <input type="password" placeholder={secretPlaceholder} value={config.password} onChange={(e) => update({ password: e.target.value })} className={inputClass} />
+            </div>
+          </div>
+        )}
+
+        {config?.kind === 's3' && (
+          <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+            <div className="md:col-span-2">
+              <label className={labelClass}>Endpoint</label>
+              <input
+                type="text"
+                placeholder="https://s3.ap-south-1.amazonaws.com or http://localhost:9000"
+                value={config.endpoint}
+                onChange={(e) => update({ endpoint: e.target.value })}
+                className={inputClass}
+              />
+            </div>
+            <div>
+              <label className={labelClass}>Region</label>
+              <input type="text" placeholder="us-east-1" value={config.region} onChange={(e) => update({ region: e.target.value })} className={inputClass} />
+            </div>
+            <div>
+              <label className={labelClass}>Bucket</label>
+              <input type="text" value={config.bucket} onChange={(e) => update({ bucket: e.target.value })} className={inputClass} />
+            </div>
+            <div>
+              <label className={labelClass}>Key Prefix</label>
+              <input type="text" placeholder="invoices/" value={config.prefix} onChange={(e) => update({ prefix: e.target.value })} className={inputClass} />
+            </div>
+            <div className="flex items-end">
+              <label className="flex items-center gap-2 text-sm text-gray-700 dark:text-gray-300 cursor-pointer pb-2">
+                <input type="checkbox" checked={config.pathStyle} onChange={(e) => update({ pathStyle: e.target.checked })} />
+                Path-style URLs (needed for MinIO)
+              </label>
+            </div>
+            <div>
+              <label className={labelClass}>Access Key ID</label>
+              <input type="text" value={config.accessKeyId} onChange={(e) => update({ accessKeyId: e.target.value })} className={inputClass} />
+            </div>
+            <div>
+              <label className={labelClass}>Secret Access Key</label>
+              <input
+                type="password"
+                placeholder={secretPlaceholder}
+                value={config.secretAccessKey}
+                onChange={(e) => update({ secretAccessKey: e.target.value })}
+                className={inputClass}
+              />
+            </div>
+          </div>
+        )}
+
+        <div className="flex justify-end gap-2">
+          {config && (
+            <button
+              onClick={handleTest}
+              disabled={busy !== null}
+              className="flex items-center gap-2 px-4 py-2 bg-blue-100 dark:bg-blue-900 text-blue-700 dark:text-blue-300 rounded-lg hover:bg-blue-200 dark:hover:bg-blue-800 disabled:opacity-50 text-sm font-medium"
+            >
+              {busy === 'test' ? <Loader2 size={16} className="animate-spin" /> : <Plug size={16} />}
+              Test Connection
+            </button>
+          )}
+          <button
+            onClick={handleSave}
+            disabled={busy !== null || (kind === 'none' && savedKind === 'none')}
+            className="flex items-center gap-2 px-4 py-2 bg-green-600 text-white rounded-lg hover:bg-green-700 disabled:opacity-50 text-sm"
+          >
+            {busy === 'save' ? <Loader2 size={16} className="animate-spin" /> : <Save size={16} />}
+            Save
+          </button>
+        </div>
+
+        {savedKind !== 'none' && (
+          <div>
+            <div className="flex items-center justify-between mb-2">
+              <h3 className="font-medium text-gray-800 dark:text-gray-200">Remote Backups</h3>
+              <button
+                onClick={refreshBackups}
+                disabled={busy === 'list'}
+                className="p-1 text-gray-500 hover:text-gray-700 dark:hover:text-gray-300 disabled:opacity-50"
+                title="Refresh"
+              >
+                <RefreshCw size={16} className={busy === 'list' ? 'animate-spin' : ''} />
+              </button>
+            </div>
+            {backups.length === 0 ? (
+              <p className="text-sm text-gray-500">No backups uploaded yet.</p>
+            ) : (
+              <div className="space-y-2 max-h-64 overflow-y-auto">
+                {backups.map((file) => (
+                  <div key={file.name} className="flex items-center gap-3 p-3 bg-white dark:bg-gray-800 rounded-lg border border-gray-200 dark:border-gray-600">
+                    <span className="text-sm font-medium text-gray-800 dark:text-gray-100">{file.name}</span>
+                    <button
+                      onClick={() => handleRestore(file)}
+                      disabled={busy !== null}
+                      className="ml-auto flex items-center gap-1 px-3 py-1 text-sm bg-red-600 text-white rounded-lg hover:bg-red-700 disabled:opacity-50"
+                    >
+                      {busy === file.name ? <Loader2 size={14} className="animate-spin" /> : <DownloadCloud size={14} />}
+                      Restore
+                    </button>
+                    <button
+                      onClick={() => handleDelete(file)}
+                      disabled={busy !== null}
+                      className="p-1 text-gray-400 hover:text-red-600 disabled:opacity-50"
+                      title="Delete"
+                    >
+                      <Trash2 size={16} />
+                    </button>
+                  </div>
+                ))}
+              </div>
+            )}
+          </div>
+        )}
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,259 @@
+import { useEffect, useState } from 'react';
+import { BookOpen, Loader2, FileDown, FileUp, CheckCircle2 } from 'lucide-react';
+import { open, save, message } from '@tauri-apps/api/dialog';
+import { tallyService, TallySettings, TallyLedgers, TallyMastersReport, DEFAULT_TALLY_SETTINGS } from '../services/tally';
+import { DuplicateHandling } from '../services/customerImport';
+
+const LEDGER_FIELDS: { key: keyof TallyLedgers; label: string }[] = [
+  { key: 'sales', label: 'Sales ledger' },
+  { key: 'cgst', label: 'CGST ledger' },
+  { key: 'sgst', label: 'SGST ledger' },
+  { key: 'roundOff', label: 'Round-off ledger' },
+  { key: 'partyGroup', label: 'Customer group' },
+  { key: 'voucherType', label: 'Voucher type' },
+];
+
+const ACTION_STYLES: Record<string, string> = {
+  create: 'bg-green-100 text-green-800 dark:bg-green-900 dark:text-green-200',
+  update: 'bg-blue-100 text-blue-800 dark:bg-blue-900 dark:text-blue-200',
+  skip: 'bg-gray-200 text-gray-700 dark:bg-gray-600 dark:text-gray-200',
+  invalid: 'bg-red-100 text-red-800 dark:bg-red-900 dark:text-red-200',
+};
+
+const inputClass = 'w-full px-3 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 dark:focus:ring-blue-400 focus:border-transparent transition-colors duration-200';
+
+// Exchange with Tally Prime: sales vouchers and party ledgers out, customer and item masters in
+export default function TallyIntegration() {
+  const [settings, setSettings] = useState<TallySettings>(DEFAULT_TALLY_SETTINGS);
+  const [from, setFrom] = useState('');
+  const [to, setTo] = useState('');
+  const [isExporting, setIsExporting] = useState(false);
+  const [mastersPath, setMastersPath] = useState<string | null>(null);
+  const [onDuplicate, setOnDuplicate] = useState<DuplicateHandling>('skip');
+  const [mastersReport, setMastersReport] = useState<TallyMastersReport | null>(null);
+  const [isImporting, setIsImporting] = useState(false);
+
+  useEffect(() => {
+    tallyService.getSettings().then(setSettings).catch((e) => console.error('Failed to load Tally settings:', e));
+  }, []);
+
+  const setLedger = (key: keyof TallyLedgers, value: string) =>
+    setSettings({ ...settings, ledgers: { ...settings.ledgers, [key]: value } });
+
+  const handleExport = async () => {
+    const target = await save({
+      title: 'Export for Tally',
+      defaultPath: `tally-sales${from ? `-${from}` : ''}${to ? `-to-${to}` : ''}.xml`,
+      filters: [{ name: 'Tally XML', extensions: ['xml'] }],
+    });
+    if (!target) return;
+
+    setIsExporting(true);
+    try {
+      await tallyService.saveSettings(settings);
+      const report = await tallyService.exportXml(target, settings, from || undefined, to || undefined);
+      const mismatches = report.placeOfSupplyMismatches;
+      const warning = mismatches.length > 0
+        ? `\n\n${mismatches.length} invoice(s) charge CGST + SGST to a customer in another state and are booked as invoiced: ${mismatches.join(', ')}. Check their place of supply before filing.`
+        : '';
+      await message(
+        `Exported ${report.vouchers} sales voucher(s) and ${report.ledgers} customer ledger(s).${warning}\n\nIn Tally Prime use Import > Transactions with:\n${report.path}`,
+        { title: 'Tally Export', type: 'info' }
+      );
+    } catch (e: any) {
+      await message(`Tally export failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    } finally {
+      setIsExporting(false);
+    }
+  };
+
+  const checkMasters = async (path: string, duplicates: DuplicateHandling) => {
+    setIsImporting(true);
+    try {
+      setMastersReport(await tallyService.importMasters(path, settings, duplicates, true));
+    } catch (e: any) {
+      setMastersReport(null);
+      await message(`Could not read the Tally file: ${e.message || e}`, { title: 'Tally Import', type: 'error' });
+    } finally {
+      setIsImporting(false);
+    }
+  };
+
+  const handleChooseMasters = async () => {
+    const selected = await open({
+      directory: false,
+      multiple: false,
+      filters: [{ name: 'Tally XML', extensions: ['xml'] }],
+      title: 'Select Tally Masters Export'
+    });
+    if (!selected || typeof selected !== 'string') return;
+    setMastersPath(selected);
+    await checkMasters(selected, onDuplicate);
+  };
+
+  const handleDuplicateChange = async (value: DuplicateHandling) => {
+    setOnDuplicate(value);
+    if (mastersPath) await checkMasters(mastersPath, value);
+  };
+
+  const handleImportMasters = async () => {
+    if (!mastersPath) return;
+    setIsImporting(true);
+    try {
+      await tallyService.saveSettings(settings);
+      const report = await tallyService.importMasters(mastersPath, settings, onDuplicate, false);
+      setMastersReport(report);
+      await message(
+        `Customers: ${report.customers.created} added, ${report.customers.updated} updated.\nCatalogue items: ${report.itemsCreated} added, ${report.itemsUpdated} updated.`,
+        { title: 'Tally Import Complete', type: 'info' }
+      );
+    } catch (e: any) {
+      await message(`Import failed, nothing was saved: ${e.message || e}`, { title: 'Tally Import', type: 'error' });
+    } finally {
+      setIsImporting(false);
+    }
+  };
+
+  const toImport = mastersReport
+    ? mastersReport.customers.created + mastersReport.customers.updated + mastersReport.itemsCreated + mastersReport.itemsUpdated
+    : 0;
+
+  return (
+    <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg">
+      <h2 className="text-xl font-semibold text-gray-800 dark:text-gray-100 mb-1 flex items-center gap-2 transition-colors duration-200">
+        <BookOpen size={22} /> Tally Prime
+      </h2>
+      <p className="text-sm text-gray-600 dark:text-gray-300 mb-4 transition-colors duration-200">
+        Exports issued invoices as sales vouchers with their customer ledgers. The sales, tax and round-off ledgers must already exist in Tally under the names below.
+      </p>
+
+      <div className="grid grid-cols-1 md:grid-cols-3 gap-3 mb-4">
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">Tally company (optional)</label>
+          <input
+            type="text"
+            value={settings.companyName}
+            onChange={(e) => setSettings({ ...settings, companyName: e.target.value })}
+            placeholder="Company open in Tally"
+            className={inputClass}
+          />
+        </div>
+        {LEDGER_FIELDS.map(({ key, label }) => (
+          <div key={key}>
+            <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">{label}</label>
+            <input type="text" value={settings.ledgers[key]} onChange={(e) => setLedger(key, e.target.value)} className={inputClass} />
+          </div>
+        ))}
+      </div>
+
+      <div className="flex flex-wrap items-end gap-3">
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">From</label>
+          <input type="date" value={from} onChange={(e) => setFrom(e.target.value)} className={inputClass} />
+        </div>
+        <div>
+          <label className="block text-xs font-medium text-gray-600 dark:text-gray-300 mb-1">To</label>
+          <input type="date" value={to} onChange={(e) => setTo(e.target.value)} className={inputClass} />
+        </div>
+        <button
+          onClick={handleExport}
+          disabled={isExporting}
+          className="flex items-center gap-2 px-4 py-2 bg-green-600 dark:bg-green-500 text-white rounded-lg hover:bg-green-700 disabled:opacity-50"
+        >
+          {isExporting ? <Loader2 size={16} className="animate-spin" /> : <FileDown size={16} />}
+          Export Sales for Tally
+        </button>
+      </div>
+
+      <h3 className="font-medium text-gray-800 dark:text-gray-100 mt-6 mb-1">Import Masters</h3>
+      <p className="text-sm text-gray-600 dark:text-gray-300 mb-3">
+        Reads a masters file from Tally (Export &gt; Masters, XML). Ledgers under the customer group become customers and stock items become catalogue items you can pick on an invoice.
+      </p>
+      <div className="flex flex-wrap items-center gap-3 mb-3">
+        <button
+          onClick={handleChooseMasters}
+          disabled={isImporting}
+          className="flex items-center gap-2 px-4 py-2 bg-blue-600 dark:bg-blue-500 text-white rounded-lg hover:bg-blue-700 disabled:opacity-50"
+        >
+          {isImporting && !mastersReport ? <Loader2 size={16} className="animate-spin" /> : <FileUp size={16} />}
+          Choose Tally Masters File
+        </button>
+        <label className="text-sm text-gray-700 dark:text-gray-300">
+          Already here:
+          <select
+            value={onDuplicate}
+            onChange={(e) => handleDuplicateChange(e.target.value as DuplicateHandling)}
+            disabled={isImporting}
+            className="ml-2 px-2 py-1 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 rounded-lg"
+          >
+            <option value="skip">Leave unchanged</option>
+            <option value="update">Update from Tally</option>
+          </select>
+        </label>
+      </div>
+
+      {mastersReport && (
+        <>
+          <p className="text-sm text-gray-700 dark:text-gray-300 mb-2">
+            Customers: {mastersReport.customers.created} new, {mastersReport.customers.updated} to update, {mastersReport.customers.skipped} skipped, {mastersReport.customers.invalid} with errors.
+            {' '}Items: {mastersReport.itemsCreated} new, {mastersReport.itemsUpdated} to update, {mastersReport.itemsSkipped} skipped, {mastersReport.itemsInvalid} with errors.
+            {mastersReport.ignoredLedgers > 0 && ` ${mastersReport.ignoredLedgers} ledger(s) outside "${settings.ledgers.partyGroup}" were ignored.`}
+          </p>
+          <div className="overflow-x-auto max-h-80 overflow-y-auto mb-3">
+            <table className="w-full text-xs text-gray-700 dark:text-gray-300">
+              <thead>
+                <tr className="text-left">
+                  <th className="px-2 py-1">Line</th>
+                  <th className="px-2 py-1">Name</th>
+                  <th className="px-2 py-1">GSTIN / HSN</th>
+                  <th className="px-2 py-1">Result</th>
+                  <th className="px-2 py-1">Details</th>
+                </tr>
+              </thead>
+              <tbody>
+                {mastersReport.customers.rows.map((row) => (
+                  <tr key={`c${row.line}`} className="border-t border-gray-200 dark:border-gray-600 align-top">
+                    <td className="px-2 py-1">{row.line}</td>
+                    <td className="px-2 py-1">{row.customer.companyName}</td>
+                    <td className="px-2 py-1">{row.customer.gstNumber}</td>
+                    <td className="px-2 py-1"><span className={`px-2 py-0.5 rounded ${ACTION_STYLES[row.action]}`}>{row.action}</span></td>
+                    <td className="px-2 py-1">
+                      {row.duplicateOf && <div>Matches "{row.duplicateOf.companyName}" by {row.duplicateOf.matchedBy === 'gstin' ? 'GSTIN' : 'name'}</div>}
+                      {row.errors.map((e, i) => <div key={`e${i}`} className="text-red-600 dark:text-red-400">{e}</div>)}
+                      {row.warnings.map((w, i) => <div key={`w${i}`} className="text-amber-600 dark:text-amber-400">{w}</div>)}
+                    </td>
+                  </tr>
+                ))}
+                {mastersReport.items.map((row) => (
+                  <tr key={`i${row.line}`} className="border-t border-gray-200 dark:border-gray-600 align-top">
+                    <td className="px-2 py-1">{row.line}</td>
+                    <td className="px-2 py-1">{row.item.name}</td>
+                    <td className="px-2 py-1">{row.item.hsnSacCode}</td>
+                    <td className="px-2 py-1"><span className={`px-2 py-0.5 rounded ${ACTION_STYLES[row.action]}`}>{row.action}</span></td>
+                    <td className="px-2 py-1">
+                      {row.duplicateOf && <div>Already in the catalogue</div>}
+                      {row.errors.map((e, i) => <div key={`e${i}`} className="text-red-600 dark:text-red-400">{e}</div>)}
+                      {row.warnings.map((w, i) => <div key={`w${i}`} className="text-amber-600 dark:text-amber-400">{w}</div>)}
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+          {mastersReport.committed ? (
+            <p className="flex items-center gap-2 text-green-700 dark:text-green-400"><CheckCircle2 size={18} /> Import saved.</p>
+          ) : (
+            <button
+              onClick={handleImportMasters}
+              disabled={isImporting || toImport === 0}
+              className="flex items-center gap-2 px-4 py-2 bg-green-600 dark:bg-green-500 text-white rounded-lg hover:bg-green-700 disabled:opacity-50"
+            >
+              {isImporting ? <Loader2 size={16} className="animate-spin" /> : <FileUp size={16} />}
+              Import {toImport} Record(s)
+            </button>
+          )}
+        </>
+      )}
+    </div>
+  );
+}
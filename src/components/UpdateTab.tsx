@@ -1,6 +1,6 @@
 import React, { useState, useEffect } from 'react';
 import { RefreshCw, Download, ExternalLink, CheckCircle, AlertTriangle, Loader2 } from 'lucide-react';
-import { updateService, UpdateInfo } from '../services/updateService';
+import { updateService, UpdateInfo, UpdateProgress } from '../services/updateService';
 import { message } from '@tauri-apps/api/dialog';
 
 export const UpdateTab: React.FC = () => {
@@ -8,6 +8,7 @@ export const UpdateTab: React.FC = () => {
     const [updating, setUpdating] = useState(false);
     const [updateInfo, setUpdateInfo] = useState<UpdateInfo | null>(null);
     const [error, setError] = useState<string | null>(null);
+    const [progress, setProgress] = useState<UpdateProgress | null>(null);
 
     const checkUpdates = async () => {
         setLoading(true);
@@ -31,23 +32,27 @@ export const UpdateTab: React.FC = () => {
 
         try {
             setUpdating(true);
-            await updateService.downloadAndInstall(updateInfo.downloadUrl, updateInfo.assetName);
-            // On Windows, the installer usually takes over.
-            await message('The update has been downloaded and the installer will now start. The application will close to complete the update.',
+            setProgress(null);
+            const staged = await updateService.downloadUpdate(setProgress);
+            await message(`Version ${staged.version} was downloaded and its signature and checksum verified. The installer will now start and the application will close to complete the update.`,
                 { title: 'Update Ready', type: 'info' });
+            await updateService.installUpdate(staged);
         } catch (err: any) {
-            await message(`Update failed: ${err.message}`, { title: 'Error', type: 'error' });
+            await message(`Update failed: ${err.message || err}`, { title: 'Error', type: 'error' });
         } finally {
             setUpdating(false);
+            setProgress(null);
         }
     };
 
+    const percent = progress && progress.total > 0 ? Math.floor((progress.downloaded / progress.total) * 100) : null;
+
     return (
         <div className="max-w-4xl mx-auto p-6 bg-white dark:bg-gray-800 rounded-lg shadow-sm transition-colors duration-200">
             <div className="flex items-center justify-between mb-8">
                 <div>
                     <h1 className="text-3xl font-bold text-gray-800 dark:text-gray-100 mb-2">App Updates</h1>
-                    <p className="text-gray-600 dark:text-gray-400">Handle application updates directly from GitHub</p>
+                    <p className="text-gray-600 dark:text-gray-400">Signed updates, downloaded and verified before they are installed</p>
                 </div>
                 <button
                     onClick={checkUpdates}
@@ -92,15 +97,19 @@ export const UpdateTab: React.FC = () => {
                             <div className="flex flex-col md:flex-row md:items-center justify-between gap-4">
                                 <div className="space-y-1">
                                     <h3 className="text-lg font-bold text-blue-800 dark:text-blue-300">New Version Available!</h3>
-                                    <p className="text-blue-600 dark:text-blue-400">Version {updateInfo.latestVersion} is ready to download.</p>
+                                    <p className="text-blue-600 dark:text-blue-400">
+                                        {updateInfo.asset
+                                            ? `Version ${updateInfo.latestVersion} is ready to download (${(updateInfo.asset.size / (1024 * 1024)).toFixed(1)} MB).`
+                                            : `Version ${updateInfo.latestVersion} has no installer for this system. Download it from the release page.`}
+                                    </p>
                                 </div>
                                 <button
                                     onClick={handleUpdate}
-                                    disabled={updating}
+                                    disabled={updating || !updateInfo.asset}
                                     className="flex items-center justify-center gap-2 px-8 py-3 bg-blue-600 hover:bg-blue-700 text-white font-bold rounded-lg shadow-lg hover:shadow-xl transition-all disabled:opacity-50"
                                 >
                                     {updating ? <Loader2 className="animate-spin" size={20} /> : <Download size={20} />}
-                                    {updating ? 'Processing Update...' : 'Download & Install Now'}
+                                    {updating ? (percent !== null ? `Downloading ${percent}%` : 'Processing Update...') : 'Download & Install Now'}
                                 </button>
                             </div>
                         </div>
@@ -110,7 +119,7 @@ export const UpdateTab: React.FC = () => {
                         <div className="flex items-center justify-between mb-4 border-b border-gray-100 dark:border-gray-700 pb-2">
                             <h3 className="text-xl font-semibold text-gray-800 dark:text-gray-100">Release Notes</h3>
                             <button
-                                onClick={() => updateService.openReleasePage(updateInfo.downloadUrl)}
+                                onClick={() => updateService.openReleasePage()}
                                 className="text-sm text-blue-600 dark:text-blue-400 hover:underline flex items-center gap-1"
                             >
                                 <ExternalLink size={14} />
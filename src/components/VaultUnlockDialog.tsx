@@ -0,0 +1,54 @@
+import { useEffect, useRef, useState } from 'react';
+import PassphraseDialog from './PassphraseDialog';
+import { vaultService } from '../services/vault';
+
+// Asks for the vault password whenever an action needs a secret while the vault is locked,
+// e.g. printing bank details on an invoice or talking to Google Drive
+export default function VaultUnlockDialog() {
+  const [isOpen, setIsOpen] = useState(false);
+  const [isBusy, setIsBusy] = useState(false);
+  const [error, setError] = useState('');
+  const pending = useRef<((unlocked: boolean) => void) | null>(null);
+
+  useEffect(() => {
+    return vaultService.setUnlockHandler(
+      () =>
+        new Promise<boolean>((resolve) => {
+          pending.current?.(false);
+          pending.current = resolve;
+          setError('');
+          setIsOpen(true);
+        })
+    );
+  }, []);
+
+  const finish = (unlocked: boolean) => {
+    setIsOpen(false);
+    pending.current?.(unlocked);
+    pending.current = null;
+  };
+
+  const handleSubmit = async (password: string) => {
+    setIsBusy(true);
+    try {
+      await vaultService.unlock(password);
+      finish(true);
+    } catch (e: any) {
+      setError(e.message || String(e));
+    } finally {
+      setIsBusy(false);
+    }
+  };
+
+  return (
+    <PassphraseDialog
+      isOpen={isOpen}
+      title="Unlock Vault"
+      description="Bank details and cloud sign-ins are kept in an encrypted vault. Enter the vault password to continue."
+      error={error}
+      isBusy={isBusy}
+      onSubmit={handleSubmit}
+      onCancel={() => finish(false)}
+    />
+  );
+}
@@ -1,12 +1,14 @@
 import React from 'react';
 import { Plus, Trash2 } from 'lucide-react';
-import { LineItem } from '../../types/invoice';
+import { LineItem, CatalogueItem } from '../../types/invoice';
 
 interface LineItemsTableProps {
     lineItems: LineItem[];
     onAdd: () => void;
     onRemove: (id: string) => void;
     onUpdate: (id: string, field: keyof LineItem, value: any) => void;
+    catalogueItems?: CatalogueItem[];
+    onPickCatalogueItem?: (id: string, item: CatalogueItem) => void;
 }
 
 export const LineItemsTable: React.FC<LineItemsTableProps> = ({
@@ -14,6 +16,8 @@ export const LineItemsTable: React.FC<LineItemsTableProps> = ({
     onAdd,
     onRemove,
     onUpdate,
+    catalogueItems = [],
+    onPickCatalogueItem,
 }) => {
     return (
         <div className="bg-gray-50 dark:bg-gray-700 p-6 rounded-lg transition-colors duration-200">
@@ -51,6 +55,29 @@ export const LineItemsTable: React.FC<LineItemsTableProps> = ({
                         </div>
 
                         <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+                            {onPickCatalogueItem && catalogueItems.length > 0 && (
+                                <div className="md:col-span-2">
+                                    <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2 transition-colors duration-200">
+                                        Fill from Catalogue
+                                    </label>
+                                    <select
+                                        value=""
+                                        onChange={(e) => {
+                                            const picked = catalogueItems.find(c => c.id === e.target.value);
+                                            if (picked) onPickCatalogueItem(item.id, picked);
+                                        }}
+                                        className="w-full px-4 py-2 border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100 rounded-lg focus:ring-2 focus:ring-blue-500 dark:focus:ring-blue-400 focus:border-transparent transition-colors duration-200"
+                                    >
+                                        <option value="">Choose an item...</option>
+                                        {catalogueItems.map(c => (
+                                            <option key={c.id} value={c.id}>
+                                                {c.name}{c.hsnSacCode ? ` (${c.hsnSacCode})` : ''}
+                                            </option>
+                                        ))}
+                                    </select>
+                                </div>
+                            )}
+
                             <div className="md:col-span-2">
                                 <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-2 transition-colors duration-200">
                                     Description <span className="text-red-500">*</span>
@@ -1,12 +1,18 @@
 import { useState, useEffect, useCallback } from 'react';
-import { Invoice, LineItem, Customer } from '../types/invoice';
+import { Invoice, LineItem, Customer, CatalogueItem } from '../types/invoice';
 import { getCurrentFinancialYear } from '../utils/numberToWords';
 import { customerService } from '../services/customerService';
+import { catalogueService } from '../services/catalogueService';
 import { invoiceService } from '../services/invoiceService';
 
+const KWP_TO_WATT_FACTOR = 1000;
+
+const lineAmount = (item: LineItem) => Math.round(item.rate * item.quantity * KWP_TO_WATT_FACTOR * 100) / 100;
+
 export function useInvoiceForm() {
     const [customers, setCustomers] = useState<Customer[]>([]);
     const [selectedCustomerId, setSelectedCustomerId] = useState<string>('');
+    const [catalogueItems, setCatalogueItems] = useState<CatalogueItem[]>([]);
 
     const [invoiceNumber, setInvoiceNumber] = useState<string>('');
     const [financialYear] = useState<string>(getCurrentFinancialYear());
@@ -43,10 +49,17 @@ export function useInvoiceForm() {
     const [sgstPercentage, setSgstPercentage] = useState<number>(9);
     const [isGenerating, setIsGenerating] = useState<boolean>(false);
 
+    // Set when the entered number belongs to an already issued invoice
+    const [isIssued, setIsIssued] = useState<boolean>(false);
+    const [amendmentReason, setAmendmentReason] = useState<string>('');
+
     const loadData = useCallback(async () => {
         try {
             const customerList = await customerService.getAllCustomers();
             setCustomers(customerList);
+            catalogueService.getAllItems()
+                .then(setCatalogueItems)
+                .catch((error) => console.error('Error loading catalogue:', error));
 
             const draft = await invoiceService.getDraftInvoice();
             if (draft) {
@@ -72,6 +85,22 @@ export function useInvoiceForm() {
         loadData();
     }, [loadData]);
 
+    const refreshIssuedState = useCallback(async () => {
+        if (!invoiceNumber.trim()) {
+            setIsIssued(false);
+            return;
+        }
+        try {
+            setIsIssued(await invoiceService.isIssued(invoiceNumber.trim()));
+        } catch (error) {
+            console.error('Error checking invoice status:', error);
+        }
+    }, [invoiceNumber]);
+
+    useEffect(() => {
+        refreshIssuedState();
+    }, [refreshIssuedState]);
+
     const handleAutoSave = useCallback(async () => {
         const draft: Partial<Invoice> = {
             invoiceNumber,
@@ -137,9 +166,7 @@ export function useInvoiceForm() {
             if (item.id === id) {
                 const updated = { ...item, [field]: value };
                 if (field === 'rate' || field === 'quantity') {
-                    const KWP_TO_WATT_FACTOR = 1000;
-                    const amountPaise = Math.round(updated.rate * updated.quantity * KWP_TO_WATT_FACTOR * 100);
-                    updated.amount = amountPaise / 100;
+                    updated.amount = lineAmount(updated);
                 }
                 return updated;
             }
@@ -148,6 +175,23 @@ export function useInvoiceForm() {
         setLineItems(updatedItems);
     };
 
+    // Fills a line from a catalogue item; fields the item has no value for are kept
+    const applyCatalogueItem = (id: string, catalogueItem: CatalogueItem) => {
+        const updatedItems = lineItems.map(item => {
+            if (item.id !== id) return item;
+            const updated = {
+                ...item,
+                description: catalogueItem.description || catalogueItem.name,
+                hsnSacCode: catalogueItem.hsnSacCode ?? item.hsnSacCode,
+                unit: catalogueItem.unit ?? item.unit,
+                rate: catalogueItem.rate ?? item.rate,
+            };
+            updated.amount = lineAmount(updated);
+            return updated;
+        });
+        setLineItems(updatedItems);
+    };
+
     const calculateTotals = () => {
         const toPaise = (num: number) => Math.round(num * 100);
         const totalBasicPaise = lineItems.reduce((sum, item) => sum + toPaise(item.amount), 0);
@@ -194,6 +238,7 @@ export function useInvoiceForm() {
         ]);
         setCgstPercentage(9);
         setSgstPercentage(9);
+        setAmendmentReason('');
         invoiceService.clearDraftInvoice();
     };
 
@@ -216,6 +261,7 @@ export function useInvoiceForm() {
     return {
         state: {
             customers,
+            catalogueItems,
             selectedCustomerId,
             invoiceNumber,
             financialYear,
@@ -227,6 +273,8 @@ export function useInvoiceForm() {
             cgstPercentage,
             sgstPercentage,
             isGenerating,
+            isIssued,
+            amendmentReason,
             totals: calculateTotals(),
         },
         actions: {
@@ -237,11 +285,14 @@ export function useInvoiceForm() {
             setCgstPercentage,
             setSgstPercentage,
             setIsGenerating,
+            setAmendmentReason,
+            refreshIssuedState,
             handleCustomerSelect,
             updateCustomerField,
             addLineItem,
             removeLineItem,
             updateLineItem,
+            applyCatalogueItem,
             handleAutoSave,
             handleReset,
             validateForm,
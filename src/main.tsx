@@ -2,6 +2,9 @@ import React from 'react'
 import ReactDOM from 'react-dom/client'
 import App from './App.tsx'
 import './index.css'
+import { forwardConsoleToLog } from './services/logger'
+
+forwardConsoleToLog()
 
 ReactDOM.createRoot(document.getElementById('root')!).render(
   <React.StrictMode>
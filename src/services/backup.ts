@@ -1,20 +1,278 @@
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
 import * as path from '@tauri-apps/api/path';
 import { dbService } from './db';
+import { vaultService } from './vault';
 
 const BACKUP_PATH_KEY = 'backup_path';
 const AUTO_BACKUP_KEY = 'auto_backup_enabled';
 const RECOVERY_LINK_KEY = 'cloud_recovery_link';
+const RETENTION_KEY = 'backup_retention';
+const DRIVE_UPLOAD_KEY = 'gdrive_upload_enabled';
+const CLOUD_DOWNLOAD_ID = 'cloud-recovery';
+// Emitted by the backend's backup folder watcher with a BackupFolderChange
+const BACKUP_FOLDER_EVENT = 'backup-folder-changed';
+
+// How many snapshots to keep: newest per day, ISO week and month (all zeros keeps everything)
+export interface RetentionPolicy {
+    daily: number;
+    weekly: number;
+    monthly: number;
+}
+
+export interface BundleEntry {
+    path: string;
+    size: number;
+    sha256: string;
+}
+
+export interface BundleManifest {
+    format: string;
+    formatVersion: number;
+    appVersion: string;
+    schemaVersion: number;
+    createdAt: string;
+    entries: BundleEntry[];
+    // Absent in bundles made before multi-device support
+    deviceId?: string;
+    deviceName?: string;
+    lastModified?: string;
+    dataHash?: string;
+}
+
+export interface BackupBundle {
+    path: string;
+    manifest: BundleManifest;
+    pruned: string[];
+}
+
+export interface DiffCounts {
+    added: number;
+    removed: number;
+    changed: number;
+    unchanged: number;
+}
+
+// Dry-run result of a restore: what the backup is and how it differs from the current data
+export interface RestorePreview {
+    sourceKind: 'database' | 'bundle';
+    encrypted: boolean;
+    schemaVersion: number;
+    currentSchemaVersion: number;
+    createdAt?: string;
+    appVersion?: string;
+    invoices: DiffCounts;
+    customers: DiffCounts;
+}
+
+export interface RestoreReport {
+    preview: RestorePreview;
+    rollbackPoint?: string;
+    restoredPdfs: number;
+    restoredVault: boolean;
+    restartRequired: boolean;
+}
+
+export type RecordConflict = 'none' | 'identical' | 'different';
+
+export interface BackupInvoice {
+    invoiceNumber: string;
+    invoiceDate?: string;
+    customerName?: string;
+    grandTotal?: number;
+    status?: string;
+    conflict: RecordConflict;
+}
+
+export interface BackupCustomer {
+    id: string;
+    companyName?: string;
+    gstNumber?: string;
+    conflict: RecordConflict;
+}
+
+export interface BackupRecords {
+    invoices: BackupInvoice[];
+    customers: BackupCustomer[];
+}
+
+// How to handle a record whose key already exists in the current data with different contents
+export type InvoiceConflictResolution = 'skip' | 'replace' | 'renumber';
+export type CustomerConflictResolution = 'skip' | 'replace';
+
+export interface RecordSelection {
+    invoices: { invoiceNumber: string; onConflict?: InvoiceConflictResolution; newNumber?: string }[];
+    customers: { id: string; onConflict?: CustomerConflictResolution }[];
+}
+
+export interface SelectiveRestoreReport {
+    invoicesRestored: number;
+    invoicesReplaced: number;
+    invoicesRenumbered: number;
+    invoicesSkipped: number;
+    customersRestored: number;
+    customersReplaced: number;
+    customersSkipped: number;
+}
+
+export interface DriveFile {
+    id: string;
+    name: string;
+    mimeType: string;
+    size?: number;
+    modifiedTime?: string;
+}
+
+export interface DriveStatus {
+    // False when the app was built without Google OAuth credentials
+    configured: boolean;
+    connected: boolean;
+    folder?: DriveFile;
+}
+
+export type SyncProviderConfig =
+    | { kind: 'local'; path: string }
+    | { kind: 'webdav'; url: string; username: string; password: string }
+    | {
+        kind: 's3';
+        endpoint: string;
+        region: string;
+        bucket: string;
+        prefix: string;
+        accessKeyId: string;
+        secretAccessKey: string;
+        pathStyle: boolean;
+    };
+
+export interface RemoteFile {
+    name: string;
+    size?: number;
+    modified?: string;
+}
+
+export interface SyncPushReport {
+    name: string;
+    pruned: string[];
+}
+
+export interface DeviceInfo {
+    id: string;
+    name: string;
+}
+
+export interface BackupOrigin {
+    path: string;
+    deviceId: string;
+    deviceName: string;
+    createdAt: string;
+    lastModified?: string;
+    dataHash: string;
+}
+
+// How this device's data relates to the newest backup in the (synced) backup folder
+export type SyncStatus = 'noRemote' | 'inSync' | 'localAhead' | 'remoteAhead' | 'diverged';
+
+export interface SyncCheck {
+    status: SyncStatus;
+    device: DeviceInfo;
+    localHash: string;
+    localLastModified?: string;
+    remote?: BackupOrigin;
+    lastSyncedAt?: string;
+}
+
+// A backup written into the backup folder, with how this device compares to the folder's newest backup
+export interface BackupFolderChange {
+    file: string;
+    check: SyncCheck;
+}
+
+// Thrown when a backup is encrypted and no passphrase was given; the UI prompts and retries with `path`
+export class PassphraseRequiredError extends Error {
+    constructor(public path: string) {
+        super('This backup is encrypted. Please enter its passphrase.');
+        this.name = 'PassphraseRequiredError';
+    }
+}
+
+export interface ChangesetExport {
+    // Absent when nothing changed since the last export
+    path?: string;
+    changes: number;
+    fromVersion: number;
+    toVersion: number;
+}
+
+export interface ChangeConflict {
+    table: string;
+    key: string;
+    reason: string;
+}
+
+export interface ChangesetReport {
+    deviceName: string;
+    inserted: number;
+    updated: number;
+    deleted: number;
+    unchanged: number;
+    alreadyImported: number;
+    conflicts: ChangeConflict[];
+}
+
+// Which side wins for rows changed on both installations since they last exchanged changesets
+export type ChangesetPreference = 'local' | 'remote';
+
+export type CloudDownloadErrorKind =
+    | 'invalidLink'
+    | 'notFound'
+    | 'accessDenied'
+    | 'quotaExceeded'
+    | 'noBackupInFolder'
+    | 'notABackup'
+    | 'network'
+    | 'cancelled'
+    | 'io';
+
+// Rejection value of downloadFromCloudLink; message is ready to show to the user
+export interface CloudDownloadError {
+    kind: CloudDownloadErrorKind;
+    message: string;
+}
+
+export interface CloudDownloadProgress {
+    downloadId: string;
+    downloaded: number;
+    total: number | null;
+    // Bytes kept from an earlier, interrupted download of the same file
+    resumedFrom: number;
+}
+
+// Thrown when a backup would overwrite changes another device made; the UI offers the resolution choices
+export class SyncConflictError extends Error {
+    constructor(public check: SyncCheck) {
+        super(`The backup folder has changes from ${check.remote?.deviceName ?? 'another device'} that are not on this device.`);
+        this.name = 'SyncConflictError';
+    }
+}
+
+export const DEFAULT_RETENTION: RetentionPolicy = { daily: 7, weekly: 4, monthly: 12 };
 
 export class BackupService {
     private static instance: BackupService;
     private backupPath: string = '';
     private autoBackup: boolean = false;
     private recoveryLink: string = '';
+    private retention: RetentionPolicy = DEFAULT_RETENTION;
+    private driveUpload: boolean = false;
+    private syncConfigured: boolean = false;
     private debounceTimer: any = null;
+    private conflictListeners: ((check: SyncCheck) => void)[] = [];
+    // Other devices' backups the user has already been told about
+    private announcedBackups = new Set<string>();
+    private ready: Promise<void>;
 
     private constructor() {
-        this.init();
+        this.ready = this.init();
     }
 
     public static getInstance(): BackupService {
@@ -28,13 +286,52 @@ export class BackupService {
         this.backupPath = await dbService.getSetting(BACKUP_PATH_KEY) || '';
         this.autoBackup = (await dbService.getSetting(AUTO_BACKUP_KEY)) === 'true';
         this.recoveryLink = await dbService.getSetting(RECOVERY_LINK_KEY) || '';
+        const retention = await dbService.getSetting(RETENTION_KEY);
+        if (retention) {
+            this.retention = { ...DEFAULT_RETENTION, ...JSON.parse(retention) };
+        }
+        this.driveUpload = (await dbService.getSetting(DRIVE_UPLOAD_KEY)) === 'true';
+        this.syncConfigured = (await invoke<SyncProviderConfig | null>('get_sync_provider')) !== null;
+        await listen<BackupFolderChange>(BACKUP_FOLDER_EVENT, (event) => this.announceRemoteBackup(event.payload.check));
+        await this.watchBackupFolder();
+    }
+
+    // The backend watches the backup folder so a newer backup synced in from another device is offered right away
+    private async watchBackupFolder() {
+        try {
+            await invoke('watch_backup_folder');
+        } catch (e) {
+            console.error('Could not watch the backup folder', e);
+        }
+    }
+
+    // Tells the conflict listeners about a newer backup from another device, once per backup
+    private announceRemoteBackup(check: SyncCheck | null) {
+        if (!check?.remote || (check.status !== 'remoteAhead' && check.status !== 'diverged')) return;
+        if (this.announcedBackups.has(check.remote.path)) return;
+        this.announcedBackups.add(check.remote.path);
+        this.conflictListeners.forEach((listener) => listener(check));
+    }
+
+    public async checkForRemoteBackup() {
+        try {
+            this.announceRemoteBackup(await this.checkSyncStatus());
+        } catch (e) {
+            console.error('Checking the backup folder failed', e);
+        }
     }
 
     public async setBackupPath(path: string) {
         this.backupPath = path;
         await dbService.saveSetting(BACKUP_PATH_KEY, path);
+        await this.watchBackupFolder();
         // Trigger an immediate backup to verify
-        await this.performBackup();
+        try {
+            await this.performBackup();
+        } catch (e) {
+            // The folder already holds another device's newer data; the conflict dialog lets the user choose
+            if (!(e instanceof SyncConflictError)) throw e;
+        }
     }
 
     public getBackupPath(): string {
@@ -59,80 +356,264 @@ export class BackupService {
         return this.autoBackup;
     }
 
-    public async performBackup(): Promise<string> {
+    public getRetention(): RetentionPolicy {
+        return this.retention;
+    }
+
+    public async setRetention(retention: RetentionPolicy) {
+        this.retention = retention;
+        await dbService.saveSetting(RETENTION_KEY, JSON.stringify(retention));
+    }
+
+    // `force` overwrites even when another device has backed up changes this device has not seen
+    public async performBackup(force = false): Promise<string> {
         if (!this.backupPath) {
             throw new Error('Backup path not configured');
         }
 
-        // Construct target filename: invoices.db (Sync Style)
-        // We assume the user wants the folder to contain the "live" copy.
-        // However, Rust's export_database takes a full path.
-        // We need to decide if we strictly use `invoices.db` or allow versioning.
-        // The user asked for "dir ... synced ... pull". A single file `invoices.db` is best for sync.
-
-        // We need to append filename to path.
-        if (!this.backupPath) throw new Error('No backup path configured');
-        const targetPath = await path.join(this.backupPath, 'invoices.db');
-
+        // Each backup is a single timestamped bundle (database snapshot, logo, stamp,
+        // generated PDFs and settings) with a checksummed manifest, built in Rust.
+        // Older bundles are pruned according to the retention policy.
         try {
-            await invoke('export_database', { targetPath });
+            let bundle: BackupBundle;
+            try {
+                // With encryption on, the passphrase comes from the vault, which may need unlocking
+                bundle = await vaultService.withVault(() => invoke<BackupBundle>('create_backup_bundle', {
+                    targetDir: this.backupPath,
+                    retention: this.retention,
+                    force,
+                }));
+            } catch (e) {
+                if (e !== 'SYNC_CONFLICT') throw e;
+                const check = (await this.checkSyncStatus())!;
+                this.conflictListeners.forEach((listener) => listener(check));
+                throw new SyncConflictError(check);
+            }
+            if (this.syncConfigured) {
+                try {
+                    await this.pushToSyncProvider(bundle.path, false, force);
+                } catch (e) {
+                    console.error('Sync provider upload failed', e);
+                }
+            }
+            if (this.driveUpload) {
+                // The local bundle is already safe; a failed upload must not fail the backup
+                try {
+                    await this.uploadToDrive(bundle.path, false, force);
+                } catch (e) {
+                    console.error('Google Drive upload failed', e);
+                }
+            }
+            return bundle.path;
         } catch (e) {
-            console.error('Database backup failed', e);
-            throw e; // Re-throw if DB backup fails
+            console.error('Backup failed', e);
+            throw e;
         }
+    }
 
-        // Backup Images
-        try {
-            // dynamic import to avoid circular dependency
-            const { dbService } = await import('./db');
+    // Null until a backup folder is chosen
+    public async checkSyncStatus(): Promise<SyncCheck | null> {
+        await this.ready;
+        if (!this.backupPath) return null;
+        return invoke<SyncCheck>('check_sync_status', { targetDir: this.backupPath });
+    }
 
-            const logo = await dbService.getCompanyLogo();
-            if (logo) {
-                await this.saveImage(logo, 'company_logo.png');
-            }
+    // Called whenever a backup is refused because of changes from another device, or a newer backup
+    // from another device appears in the backup folder. Returns an unsubscribe function.
+    public onSyncConflict(listener: (check: SyncCheck) => void): () => void {
+        this.conflictListeners.push(listener);
+        return () => {
+            this.conflictListeners = this.conflictListeners.filter((l) => l !== listener);
+        };
+    }
 
-            const signature = await dbService.getStampSignature();
-            if (signature) {
-                await this.saveImage(signature, 'stamp_signature.png');
-            }
-        } catch (e) {
-            console.error('Failed to backup images:', e);
-            // Don't fail the whole backup if images fail, just log the error
-        }
-        return targetPath; // Return the path to the backed-up database
+    public async getDeviceInfo(): Promise<DeviceInfo> {
+        return invoke<DeviceInfo>('get_device_info');
     }
 
-    private async saveImage(base64Data: string, fileName: string) {
-        try {
-            // Remove data URL prefix if present
-            const base64 = base64Data.includes(',') ? base64Data.split(',')[1] : base64Data;
-
-            // Decode base64
-            const binaryString = atob(base64);
-            const len = binaryString.length;
-            const bytes = new Uint8Array(len);
-            for (let i = 0; i < len; i++) {
-                bytes[i] = binaryString.charCodeAt(i);
-            }
+    public async setDeviceName(name: string): Promise<DeviceInfo> {
+        return invoke<DeviceInfo>('set_device_name', { name });
+    }
 
-            const filePath = await path.join(this.backupPath, fileName);
-            await invoke('save_file_content', { path: filePath, content: Array.from(bytes) });
-        } catch (e) {
-            console.error(`Failed to save image ${fileName}`, e);
+    // Writes the invoices, customers and revisions changed since the last export (or all of them)
+    public async exportChangeset(targetDir: string, full = false): Promise<ChangesetExport> {
+        return invoke<ChangesetExport>('export_changeset', { targetDir, full });
+    }
+
+    // Dry run of importChangeset: reports what would be merged without changing anything
+    public async previewChangeset(changesetPath: string, prefer: ChangesetPreference = 'local'): Promise<ChangesetReport> {
+        return invoke<ChangesetReport>('preview_changeset', { path: changesetPath, prefer });
+    }
+
+    public async importChangeset(changesetPath: string, prefer: ChangesetPreference = 'local'): Promise<ChangesetReport> {
+        const report = await invoke<ChangesetReport>('import_changeset', { path: changesetPath, prefer });
+        this.notifyChange();
+        return report;
+    }
+
+    public async verifyBundle(bundlePath: string): Promise<BundleManifest> {
+        return invoke<BundleManifest>('verify_backup_bundle', { path: bundlePath });
+    }
+
+    public async isEncryptionEnabled(): Promise<boolean> {
+        return invoke<boolean>('is_backup_encryption_enabled');
+    }
+
+    // Passing null turns encryption off for future backups; existing encrypted backups keep their passphrase.
+    // The passphrase is kept in the vault.
+    public async setEncryptionPassphrase(passphrase: string | null) {
+        await vaultService.withVault(() => invoke('set_backup_passphrase', { passphrase }));
+    }
+
+    public async previewRestore(sourcePath: string, passphrase?: string): Promise<RestorePreview> {
+        if (!passphrase && await invoke<boolean>('is_backup_encrypted', { path: sourcePath })) {
+            throw new PassphraseRequiredError(sourcePath);
         }
+        return invoke<RestorePreview>('preview_restore', { sourcePath, passphrase: passphrase || null });
     }
 
-    public async restoreFrom(sourcePath: string): Promise<void> {
-        // This will overwrite current DB.
+    // Validates the backup, saves the current database as a rollback point and swaps the backup in.
+    // On any failure the Rust side puts the previous database back before reporting the error.
+    public async restoreFrom(sourcePath: string, passphrase?: string): Promise<RestoreReport> {
         try {
-            await invoke('import_database', { sourcePath });
+            return await invoke<RestoreReport>('import_database', { sourcePath, passphrase: passphrase || null });
         } catch (e) {
             console.error('Restore failed', e);
             throw e;
         }
     }
 
-    public async restoreFromCloudLink(url?: string): Promise<void> {
+    // Reloads the UI on the restored data. Backups from before the latest schema change need a
+    // full relaunch, because migrations only run when the app starts.
+    public async finishRestore(report: RestoreReport): Promise<void> {
+        if (report.restartRequired) {
+            await invoke('restart_app');
+        } else {
+            window.location.reload();
+        }
+    }
+
+    public async listBackupRecords(sourcePath: string, passphrase?: string): Promise<BackupRecords> {
+        if (!passphrase && await invoke<boolean>('is_backup_encrypted', { path: sourcePath })) {
+            throw new PassphraseRequiredError(sourcePath);
+        }
+        return invoke<BackupRecords>('list_backup_records', { sourcePath, passphrase: passphrase || null });
+    }
+
+    // Copies the chosen invoices (with their items) and customers out of a backup in one transaction
+    public async restoreBackupRecords(sourcePath: string, selection: RecordSelection, passphrase?: string): Promise<SelectiveRestoreReport> {
+        const report = await invoke<SelectiveRestoreReport>('restore_backup_records', {
+            sourcePath,
+            passphrase: passphrase || null,
+            selection,
+        });
+        this.notifyChange();
+        return report;
+    }
+
+    public async getDriveStatus(): Promise<DriveStatus> {
+        return invoke<DriveStatus>('gdrive_status');
+    }
+
+    // Opens Google's consent page in the browser and waits for the loopback redirect
+    public async connectDrive(): Promise<DriveStatus> {
+        return vaultService.withVault(() => invoke<DriveStatus>('gdrive_connect'));
+    }
+
+    public async disconnectDrive(): Promise<DriveStatus> {
+        const status = await vaultService.withVault(() => invoke<DriveStatus>('gdrive_disconnect'));
+        await this.setDriveUpload(false);
+        return status;
+    }
+
+    // Uses the app's folder of that name in Drive, creating it if needed
+    public async setDriveFolder(name: string): Promise<DriveStatus> {
+        return vaultService.withVault(() => invoke<DriveStatus>('gdrive_set_folder', { name }));
+    }
+
+    public isDriveUploadEnabled(): boolean {
+        return this.driveUpload;
+    }
+
+    public async setDriveUpload(enabled: boolean) {
+        this.driveUpload = enabled;
+        await dbService.saveSetting(DRIVE_UPLOAD_KEY, String(enabled));
+    }
+
+    public async listDriveBackups(): Promise<DriveFile[]> {
+        return vaultService.withVault(() => invoke<DriveFile[]>('gdrive_list_backups'));
+    }
+
+    // Automatic backups pass `askToUnlock: false` so a locked vault skips the upload instead of prompting.
+    // `force` uploads even when Drive holds another device's changes that this device has not seen.
+    public async uploadToDrive(bundlePath: string, askToUnlock = true, force = false): Promise<DriveFile> {
+        const upload = () => this.announceRemoteConflict(
+            () => invoke<DriveFile>('gdrive_upload_backup', { path: bundlePath, force }),
+            'gdrive_check_sync_status',
+        );
+        return askToUnlock ? vaultService.withVault(upload) : upload();
+    }
+
+    // Downloads a backup from Drive into the app cache and returns its local path
+    public async downloadFromDrive(file: DriveFile): Promise<string> {
+        return vaultService.withVault(() => invoke<string>('gdrive_download_backup', { fileId: file.id, fileName: file.name }));
+    }
+
+    // Secrets come back blank; saving a blank secret keeps the stored one
+    public async getSyncProvider(): Promise<SyncProviderConfig | null> {
+        return invoke<SyncProviderConfig | null>('get_sync_provider');
+    }
+
+    // The password or secret key goes into the vault once there is one
+    public async setSyncProvider(config: SyncProviderConfig | null) {
+        await vaultService.withVault(() => invoke('set_sync_provider', { config }));
+        this.syncConfigured = config !== null;
+    }
+
+    // Connects with the given settings and returns how many backups are already there
+    public async testSyncProvider(config: SyncProviderConfig): Promise<number> {
+        return vaultService.withVault(() => invoke<number>('test_sync_provider', { config }));
+    }
+
+    // Uploads a bundle and prunes remote backups with the local retention policy.
+    // Automatic backups pass `askToUnlock: false`, and `force` works as for Google Drive.
+    public async pushToSyncProvider(bundlePath: string, askToUnlock = true, force = false): Promise<SyncPushReport> {
+        const push = () => this.announceRemoteConflict(
+            () => invoke<SyncPushReport>('sync_push_backup', { path: bundlePath, retention: this.retention, force }),
+            'sync_check_status',
+        );
+        return askToUnlock ? vaultService.withVault(push) : push();
+    }
+
+    // An upload refused because the remote holds another device's newer changes opens the conflict dialog
+    private async announceRemoteConflict<T>(upload: () => Promise<T>, checkCommand: string): Promise<T> {
+        try {
+            return await upload();
+        } catch (e) {
+            if (e !== 'SYNC_CONFLICT') throw e;
+            const check = await invoke<SyncCheck | null>(checkCommand);
+            if (!check) throw e;
+            this.conflictListeners.forEach((listener) => listener(check));
+            throw new SyncConflictError(check);
+        }
+    }
+
+    public async listSyncBackups(): Promise<RemoteFile[]> {
+        return vaultService.withVault(() => invoke<RemoteFile[]>('sync_list_backups'));
+    }
+
+    // Downloads a remote backup into the app cache and returns its local path
+    public async pullFromSyncProvider(name: string): Promise<string> {
+        return vaultService.withVault(() => invoke<string>('sync_pull_backup', { name }));
+    }
+
+    public async deleteSyncBackup(name: string) {
+        await vaultService.withVault(() => invoke('sync_delete_backup', { name }));
+    }
+
+    // Downloads the latest backup from a Google Drive link into the backup folder and returns its path.
+    // An interrupted download is resumed the next time the same link is recovered.
+    public async downloadFromCloudLink(url?: string, onProgress?: (progress: CloudDownloadProgress) => void): Promise<string> {
         let targetUrl = url;
         if (!targetUrl) {
             targetUrl = this.recoveryLink;
@@ -143,27 +624,32 @@ export class BackupService {
         if (!targetUrl) throw new Error('Please provide a Google Drive Link or configure it first.');
 
         // If backup path is not set, we can't save the file easily.
-        // We could default to AppData but user needs to know where it is?
-        // Actually, we restore immediately.
-        // We can use a temp file in AppData if backupPath is missing?
         // But for "Synced" strategy, backupPath is key.
         if (!this.backupPath) throw new Error('Please select a Backup Folder (Local Sync Folder) first.');
 
         const tempPath = await path.join(this.backupPath, 'restored_invoices.db');
         console.log('Downloading to:', tempPath);
+        const unlisten = await listen<CloudDownloadProgress>('gdrive-download-progress', (event) => {
+            if (event.payload.downloadId === CLOUD_DOWNLOAD_ID) onProgress?.(event.payload);
+        });
 
         try {
             console.log('Invoking download_gdrive_file with URL:', targetUrl);
-            await invoke('download_gdrive_file', { url: targetUrl, outputPath: tempPath });
-            console.log('Download complete. Invoking restoreFrom...');
-            await this.restoreFrom(tempPath);
-            console.log('Restore successfully triggered.');
+            await invoke('download_gdrive_file', { url: targetUrl, outputPath: tempPath, downloadId: CLOUD_DOWNLOAD_ID });
+            return tempPath;
         } catch (e) {
-            console.error('Cloud restore failed in backup service:', e);
+            console.error('Cloud download failed in backup service:', e);
             throw e;
+        } finally {
+            unlisten();
         }
     }
 
+    // Stops a running downloadFromCloudLink, which then rejects with kind 'cancelled' and keeps nothing
+    public async cancelCloudDownload(): Promise<void> {
+        await invoke('cancel_gdrive_download', { downloadId: CLOUD_DOWNLOAD_ID });
+    }
+
     // Hook for DB changes
     public notifyChange() {
         if (!this.autoBackup || !this.backupPath) return;
@@ -0,0 +1,32 @@
+import { dbService } from './db';
+import { CatalogueItem } from '../types/invoice';
+
+// Goods and services that can be picked into an invoice line; filled by the Tally masters import
+export class CatalogueService {
+    private static instance: CatalogueService;
+
+    private constructor() { }
+
+    public static getInstance(): CatalogueService {
+        if (!CatalogueService.instance) {
+            CatalogueService.instance = new CatalogueService();
+        }
+        return CatalogueService.instance;
+    }
+
+    public async getAllItems(): Promise<CatalogueItem[]> {
+        const db = await dbService.getDb();
+        const rows = await db.select<any[]>('SELECT * FROM catalogue_items ORDER BY name COLLATE NOCASE');
+        return rows.map(row => ({
+            id: row.id,
+            name: row.name,
+            description: row.description ?? undefined,
+            hsnSacCode: row.hsn_sac_code ?? undefined,
+            unit: row.unit ?? undefined,
+            rate: row.rate ?? undefined,
+            gstRate: row.gst_rate ?? undefined,
+        }));
+    }
+}
+
+export const catalogueService = CatalogueService.getInstance();
@@ -0,0 +1,41 @@
+import { ask, message } from '@tauri-apps/api/dialog';
+import { backupService, ChangesetPreference, ChangesetReport } from './backup';
+
+const describeReport = (report: ChangesetReport) => {
+    const lines = [
+        `From: ${report.deviceName}`,
+        `New records: ${report.inserted}`,
+        `Updated records: ${report.updated}`,
+        `Deleted records: ${report.deleted}`,
+        `Already up to date: ${report.unchanged + report.alreadyImported}`,
+    ];
+    if (report.conflicts.length > 0) {
+        lines.push('', `${report.conflicts.length} record(s) could not be merged and keep this computer's version:`);
+        report.conflicts.slice(0, 10).forEach((c) => lines.push(`- ${c.table} ${c.key}: ${c.reason}`));
+        if (report.conflicts.length > 10) lines.push(`...and ${report.conflicts.length - 10} more`);
+    }
+    return lines.join('\n');
+};
+
+// Previews a changes file, asks before merging it and reloads once it has been imported.
+// Also used when a changes file is opened with the app.
+export const importChangesetFile = async (path: string, prefer: ChangesetPreference) => {
+    try {
+        const preview = await backupService.previewChangeset(path, prefer);
+        if (preview.inserted + preview.updated + preview.deleted === 0 && preview.conflicts.length === 0) {
+            await message(`${describeReport(preview)}\n\nThere is nothing new to merge.`, { title: 'Already Up to Date', type: 'info' });
+            return;
+        }
+        const confirmed = await ask(`${describeReport(preview)}\n\nMerge these changes into this computer's data?`, {
+            title: 'Merge Changes',
+            type: 'warning'
+        });
+        if (!confirmed) return;
+
+        const report = await backupService.importChangeset(path, prefer);
+        await message(describeReport(report), { title: 'Changes Merged', type: 'info' });
+        window.location.reload();
+    } catch (e: any) {
+        await message(`Import failed: ${e.message || e}`, { title: 'Error', type: 'error' });
+    }
+};
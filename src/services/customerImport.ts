@@ -0,0 +1,68 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { Customer } from '../types/invoice';
+import { backupService } from './backup';
+
+export type CustomerField = 'companyName' | 'gstNumber' | 'panNumber' | 'addressLine1' | 'addressLine2' | 'addressLine3' | 'city' | 'state' | 'pincode' | 'email';
+
+// Column index in the file for each customer field; null leaves the field empty
+export type ColumnMapping = Partial<Record<CustomerField, number | null>>;
+
+export type DuplicateHandling = 'skip' | 'update';
+
+export interface ImportFile {
+    headers: string[];
+    sampleRows: string[][];
+    totalRows: number;
+    suggestedMapping: ColumnMapping;
+}
+
+export interface ImportRow {
+    line: number;
+    customer: Customer;
+    action: 'create' | 'update' | 'skip' | 'invalid';
+    errors: string[];
+    warnings: string[];
+    duplicateOf?: { id: string; companyName: string; matchedBy: 'gstin' | 'name' } | null;
+}
+
+export interface ImportReport {
+    rows: ImportRow[];
+    created: number;
+    updated: number;
+    skipped: number;
+    invalid: number;
+    committed: boolean;
+}
+
+export const CUSTOMER_FIELDS: { field: CustomerField; label: string }[] = [
+    { field: 'companyName', label: 'Company Name' },
+    { field: 'gstNumber', label: 'GSTIN' },
+    { field: 'panNumber', label: 'PAN' },
+    { field: 'addressLine1', label: 'Address Line 1' },
+    { field: 'addressLine2', label: 'Address Line 2' },
+    { field: 'addressLine3', label: 'Address Line 3' },
+    { field: 'city', label: 'City' },
+    { field: 'state', label: 'State' },
+    { field: 'pincode', label: 'Pincode' },
+    { field: 'email', label: 'Email' },
+];
+
+class CustomerImportService {
+    async readFile(path: string): Promise<ImportFile> {
+        return invoke<ImportFile>('read_customer_import_file', { path });
+    }
+
+    // Validates and matches every row against the existing customers without saving anything
+    async dryRun(path: string, mapping: ColumnMapping, onDuplicate: DuplicateHandling): Promise<ImportReport> {
+        return invoke<ImportReport>('import_customers', { path, mapping, onDuplicate, dryRun: true });
+    }
+
+    // Saves all valid rows in one transaction
+    async commit(path: string, mapping: ColumnMapping, onDuplicate: DuplicateHandling): Promise<ImportReport> {
+        const report = await invoke<ImportReport>('import_customers', { path, mapping, onDuplicate, dryRun: false });
+        backupService.notifyChange();
+        return report;
+    }
+}
+
+export const customerImportService = new CustomerImportService();
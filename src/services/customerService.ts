@@ -1,3 +1,4 @@
+import { invoke } from '@tauri-apps/api/tauri';
 import { dbService } from './db';
 import { Customer } from '../types/invoice';
 import { backupService } from './backup';
@@ -16,7 +17,7 @@ export class CustomerService {
 
     public async getAllCustomers(): Promise<Customer[]> {
         const db = await dbService.getDb();
-        const rows = await db.select<any[]>('SELECT * FROM customers ORDER BY created_at DESC');
+        const rows = await db.select<any[]>('SELECT * FROM customers WHERE deleted_at IS NULL ORDER BY created_at DESC');
         return rows.map(row => ({
             id: row.id,
             companyName: row.company_name,
@@ -28,18 +29,19 @@ export class CustomerService {
             city: row.city,
             state: row.state,
             pincode: row.pincode,
+            email: row.email ?? undefined,
         }));
     }
 
     public async upsertCustomer(customer: Customer): Promise<void> {
         const db = await dbService.getDb();
         await db.execute(
-            `INSERT INTO customers(id, company_name, gst_number, pan_number, address_line1, address_line2, address_line3, city, state, pincode)
-             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            `INSERT INTO customers(id, company_name, gst_number, pan_number, address_line1, address_line2, address_line3, city, state, pincode, email)
+             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
              ON CONFLICT(id) DO UPDATE SET
              company_name = excluded.company_name, gst_number = excluded.gst_number, pan_number = excluded.pan_number,
              address_line1 = excluded.address_line1, address_line2 = excluded.address_line2, address_line3 = excluded.address_line3,
-             city = excluded.city, state = excluded.state, pincode = excluded.pincode`,
+             city = excluded.city, state = excluded.state, pincode = excluded.pincode, email = excluded.email`,
             [
                 customer.id,
                 customer.companyName,
@@ -51,14 +53,15 @@ export class CustomerService {
                 customer.city || '',
                 customer.state || '',
                 customer.pincode || '',
+                customer.email?.trim() || null,
             ]
         );
         backupService.notifyChange();
     }
 
+    // Soft delete: the customer moves to the recycle bin until it is restored or purged
     public async deleteCustomer(id: string): Promise<void> {
-        const db = await dbService.getDb();
-        await db.execute('DELETE FROM customers WHERE id = $1', [id]);
+        await invoke('trash_customer_record', { id });
         backupService.notifyChange();
     }
 }
@@ -0,0 +1,42 @@
+import { invoke } from '@tauri-apps/api/tauri';
+
+export type ExportDataset = 'invoices' | 'items' | 'customers';
+export type ExportFormat = 'csv' | 'xlsx';
+export type ExportDateFormat = 'indian' | 'iso';
+
+export interface ExportColumn {
+    key: string;
+    header: string;
+}
+
+export interface ExportOptions {
+    dataset: ExportDataset;
+    format: ExportFormat;
+    dateFormat: ExportDateFormat;
+    // Inclusive YYYY-MM-DD bounds on the invoice date; customers are not filtered by date
+    from?: string;
+    to?: string;
+    // Column keys in output order; all columns when left out
+    columns?: string[];
+    includeCancelled?: boolean;
+}
+
+export interface ExportReport {
+    path: string;
+    rows: number;
+}
+
+class DataExportService {
+    async getColumns(dataset: ExportDataset): Promise<ExportColumn[]> {
+        return invoke<ExportColumn[]>('get_export_columns', { dataset });
+    }
+
+    async exportTo(targetPath: string, options: ExportOptions): Promise<ExportReport> {
+        return invoke<ExportReport>('export_spreadsheet', {
+            options: { ...options, includeCancelled: options.includeCancelled ?? false },
+            targetPath
+        });
+    }
+}
+
+export const dataExportService = new DataExportService();
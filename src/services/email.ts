@@ -0,0 +1,92 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { dbService } from './db';
+import { vaultService } from './vault';
+
+const SMTP_SETTINGS_KEY = 'smtp_settings';
+
+export type SmtpSecurity = 'starttls' | 'tls' | 'none';
+
+export interface SmtpSettings {
+    host: string;
+    port: number;
+    // STARTTLS on 587, implicit TLS on 465, or plain text for a local test server
+    security: SmtpSecurity;
+    username: string;
+    fromAddress: string;
+    fromName: string;
+    // Copied on every invoice email; comma separated
+    cc: string;
+    subjectTemplate: string;
+    bodyTemplate: string;
+}
+
+export interface OutgoingEmail {
+    to: string;
+    cc: string;
+    subject: string;
+    body: string;
+}
+
+export interface EmailLogEntry {
+    id: number;
+    invoiceNumber: string;
+    recipients: string;
+    cc?: string | null;
+    subject: string;
+    attachment: string;
+    status: 'sent' | 'failed';
+    error?: string | null;
+    sentAt?: string | null;
+}
+
+export const TEMPLATE_PLACEHOLDERS = ['{invoiceNumber}', '{invoiceDate}', '{financialYear}', '{customerName}', '{grandTotal}', '{senderName}'];
+
+export const DEFAULT_SMTP_SETTINGS: SmtpSettings = {
+    host: '',
+    port: 587,
+    security: 'starttls',
+    username: '',
+    fromAddress: '',
+    fromName: '',
+    cc: '',
+    subjectTemplate: 'Invoice {invoiceNumber} dated {invoiceDate}',
+    bodyTemplate: 'Dear {customerName},\n\nPlease find attached invoice {invoiceNumber} dated {invoiceDate} for Rs. {grandTotal}.\n\nRegards,\n{senderName}',
+};
+
+class EmailService {
+    async getSettings(): Promise<SmtpSettings> {
+        const stored = await dbService.getSetting(SMTP_SETTINGS_KEY);
+        return stored ? { ...DEFAULT_SMTP_SETTINGS, ...JSON.parse(stored) } : DEFAULT_SMTP_SETTINGS;
+    }
+
+    async saveSettings(settings: SmtpSettings): Promise<void> {
+        await dbService.saveSetting(SMTP_SETTINGS_KEY, JSON.stringify(settings));
+    }
+
+    // Kept in the vault, never in the settings table; an empty password removes it
+    async savePassword(password: string): Promise<void> {
+        await vaultService.withVault(() => invoke('save_smtp_password', { password }));
+    }
+
+    async sendTest(to: string): Promise<void> {
+        await vaultService.withVault(() => invoke('send_test_email', { to }));
+    }
+
+    // Customer addresses, CC and the filled-in templates, for the user to review before sending
+    async preview(invoiceNumber: string): Promise<OutgoingEmail> {
+        return invoke<OutgoingEmail>('preview_invoice_email', { invoiceNumber });
+    }
+
+    // pdfFile is the file name in the generated folder, as returned by generateInvoicePDF
+    async sendInvoice(invoiceNumber: string, pdfFile: string, email: OutgoingEmail): Promise<EmailLogEntry> {
+        return vaultService.withVault(() =>
+            invoke<EmailLogEntry>('email_invoice', { request: { invoiceNumber, pdfFile, email } })
+        );
+    }
+
+    async getHistory(invoiceNumber: string): Promise<EmailLogEntry[]> {
+        return invoke<EmailLogEntry[]>('get_invoice_emails', { invoiceNumber });
+    }
+}
+
+export const emailService = new EmailService();
@@ -0,0 +1,36 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
+
+export type FileOpenKind = 'backup' | 'changeset' | 'invoice' | 'unsupported';
+
+// A file the app was launched with, or that a second launch handed over before exiting
+export interface FileOpenRequest {
+    path: string;
+    kind: FileOpenKind;
+    invoiceNumber: string | null;
+    financialYear: string | null;
+}
+
+class FileOpenService {
+    // Returns the files waiting to be opened and clears them, so each is handled once
+    async takeRequests(): Promise<FileOpenRequest[]> {
+        return await invoke<FileOpenRequest[]>('take_file_open_requests');
+    }
+
+    // Calls back with newly handed-over files, including any queued before the listener existed
+    async onRequests(handler: (requests: FileOpenRequest[]) => void): Promise<UnlistenFn> {
+        const drain = async () => {
+            try {
+                const requests = await this.takeRequests();
+                if (requests.length > 0) handler(requests);
+            } catch (error) {
+                console.error('Failed to read opened files:', error);
+            }
+        };
+        const unlisten = await listen('file-open-requested', drain);
+        await drain();
+        return unlisten;
+    }
+}
+
+export const fileOpenService = new FileOpenService();
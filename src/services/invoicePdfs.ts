@@ -0,0 +1,55 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
+import { vaultService } from './vault';
+
+const JOB_ID = 'invoice-pdf-export';
+
+export interface InvoicePdfFilter {
+    // Inclusive YYYY-MM-DD bounds on the invoice date
+    from?: string;
+    to?: string;
+    customerId?: string;
+    // e.g. '24-25'
+    financialYear?: string;
+    includeCancelled?: boolean;
+}
+
+export interface InvoicePdfProgress {
+    jobId: string;
+    done: number;
+    total: number;
+    invoiceNumber: string;
+}
+
+export interface InvoicePdfReport {
+    path: string;
+    invoices: number;
+}
+
+class InvoicePdfExportService {
+    // Renders the PDF of every matching issued invoice into a zip with an index.csv
+    async exportZip(targetPath: string, filter: InvoicePdfFilter, onProgress?: (progress: InvoicePdfProgress) => void): Promise<InvoicePdfReport> {
+        const unlisten = await listen<InvoicePdfProgress>('pdf-batch-progress', (event) => {
+            if (event.payload.jobId === JOB_ID) onProgress?.(event.payload);
+        });
+        try {
+            // The bank details printed on the invoices come from the vault
+            return await vaultService.withVault(() =>
+                invoke<InvoicePdfReport>('export_invoice_pdfs', {
+                    filter: { ...filter, includeCancelled: filter.includeCancelled ?? false },
+                    targetPath,
+                    jobId: JOB_ID,
+                })
+            );
+        } finally {
+            unlisten();
+        }
+    }
+
+    // Stops a running exportZip after the invoice it is on; no zip is written
+    async cancel(): Promise<void> {
+        await invoke('cancel_invoice_pdfs', { jobId: JOB_ID });
+    }
+}
+
+export const invoicePdfExportService = new InvoicePdfExportService();
@@ -1,5 +1,6 @@
+import { invoke } from '@tauri-apps/api/tauri';
 import { dbService } from './db';
-import { Invoice } from '../types/invoice';
+import { Invoice, InvoiceRevision } from '../types/invoice';
 import { backupService } from './backup';
 
 export class InvoiceService {
@@ -14,43 +15,27 @@ export class InvoiceService {
         return InvoiceService.instance;
     }
 
-    public async saveInvoice(invoice: Invoice): Promise<void> {
-        const db = await dbService.getDb();
-
-        // 1. Save main invoice record
-        await db.execute(
-            `INSERT INTO invoices(invoice_number, financial_year, customer_id, invoice_date, grand_total, status, work_order_reference, work_order_date, json_data)
-             VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)
-             ON CONFLICT(invoice_number) DO UPDATE SET
-             customer_id = excluded.customer_id, invoice_date = excluded.invoice_date, grand_total = excluded.grand_total,
-             status = excluded.status, work_order_reference = excluded.work_order_reference, work_order_date = excluded.work_order_date,
-             json_data = excluded.json_data`,
-            [
-                invoice.invoiceNumber,
-                invoice.financialYear,
-                invoice.customer.id || null,
-                invoice.invoiceDate,
-                invoice.grandTotal,
-                'GENERATED',
-                invoice.workOrderReference || '',
-                invoice.workOrderDate || '',
-                JSON.stringify({ ...invoice, id: invoice.invoiceNumber }) // Keep for backward compatibility/backup
-            ]
-        );
-
-        // 2. Save line items relationally
-        // First clear existing items for this invoice number (transactional-ish)
-        await db.execute('DELETE FROM invoice_items WHERE invoice_number = $1', [invoice.invoiceNumber]);
-
-        for (const item of invoice.lineItems) {
-            await db.execute(
-                `INSERT INTO invoice_items(id, invoice_number, serial_number, description, hsn_sac_code, rate, quantity, unit, amount)
-                 VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)`,
-                [item.id, invoice.invoiceNumber, item.serialNumber, item.description, item.hsnSacCode, item.rate, item.quantity, item.unit, item.amount]
-            );
-        }
+    // Issued invoices are locked in the database, so writes go through the Rust commands
+    public async saveInvoice(invoice: Invoice): Promise<Invoice> {
+        const saved = await invoke<Invoice>('issue_invoice', { invoice });
+        backupService.notifyChange();
+        return saved;
+    }
 
+    public async amendInvoice(invoice: Invoice, reason: string): Promise<Invoice> {
+        const saved = await invoke<Invoice>('amend_invoice', { invoice, reason });
         backupService.notifyChange();
+        return saved;
+    }
+
+    public async isIssued(invoiceNumber: string): Promise<boolean> {
+        const db = await dbService.getDb();
+        const rows = await db.select<any[]>('SELECT locked FROM invoices WHERE invoice_number = $1', [invoiceNumber]);
+        return rows.length > 0 && rows[0].locked === 1;
+    }
+
+    public async getInvoiceRevisions(invoiceNumber: string): Promise<InvoiceRevision[]> {
+        return await invoke<InvoiceRevision[]>('get_invoice_revisions', { invoiceNumber });
     }
 
     public async getAllInvoices(): Promise<Invoice[]> {
@@ -71,6 +56,10 @@ export class InvoiceService {
                     ...fallbackData,
                     invoiceNumber: row.invoice_number,
                     id: row.invoice_number,
+                    revision: row.revision ?? 0,
+                    status: row.status,
+                    cancelledAt: row.cancelled_at || undefined,
+                    cancellationReason: row.cancellation_reason || undefined,
                     lineItems: items.map(p => ({
                         id: p.id,
                         serialNumber: p.serial_number,
@@ -86,6 +75,10 @@ export class InvoiceService {
                 // Fallback to JSON blob if no relational items found (shouldn't happen after migration)
                 invoice = JSON.parse(row.json_data);
                 invoice.id = row.invoice_number;
+                invoice.revision = row.revision ?? 0;
+                invoice.status = row.status;
+                invoice.cancelledAt = row.cancelled_at || undefined;
+                invoice.cancellationReason = row.cancellation_reason || undefined;
             }
             invoices.push(invoice);
         }
@@ -93,9 +86,9 @@ export class InvoiceService {
         return invoices;
     }
 
-    public async deleteInvoice(invoiceNumber: string): Promise<void> {
-        const db = await dbService.getDb();
-        await db.execute('DELETE FROM invoices WHERE invoice_number = $1', [invoiceNumber]);
+    // Issued invoices are never deleted; cancelling keeps the number consumed
+    public async cancelInvoice(invoiceNumber: string, reason: string, cancelledOn?: string): Promise<void> {
+        await invoke('cancel_invoice', { invoiceNumber, reason, cancelledOn: cancelledOn || null });
         backupService.notifyChange();
     }
 
@@ -108,9 +101,10 @@ export class InvoiceService {
         await dbService.saveSetting('draft_invoice', JSON.stringify(invoice));
     }
 
+    // The cleared draft goes to the recycle bin and can be restored from Settings
     public async clearDraftInvoice(): Promise<void> {
-        const db = await dbService.getDb();
-        await db.execute('DELETE FROM settings WHERE key = "draft_invoice"');
+        await invoke('trash_draft_invoice');
+        backupService.notifyChange();
     }
 }
 
@@ -0,0 +1,55 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { error, warn, info, debug } from 'tauri-plugin-log-api';
+
+type ConsoleMethod = 'error' | 'warn' | 'info' | 'log' | 'debug';
+
+const FORWARDERS: Record<ConsoleMethod, (message: string) => Promise<void>> = {
+    error,
+    warn,
+    info,
+    log: info,
+    debug,
+};
+
+const format = (args: unknown[]) =>
+    args
+        .map((arg) => {
+            if (arg instanceof Error) return arg.stack || `${arg.name}: ${arg.message}`;
+            if (typeof arg === 'string') return arg;
+            try {
+                return JSON.stringify(arg);
+            } catch {
+                return String(arg);
+            }
+        })
+        .join(' ');
+
+let forwarding = false;
+
+// Copies console output into the app's log file, so errors seen only in the webview are kept
+// for support. The console itself still prints as before.
+export function forwardConsoleToLog() {
+    if (forwarding) return;
+    forwarding = true;
+    (Object.keys(FORWARDERS) as ConsoleMethod[]).forEach((method) => {
+        const original = console[method].bind(console);
+        console[method] = (...args: unknown[]) => {
+            original(...args);
+            FORWARDERS[method](format(args)).catch(() => {
+                // Logging must never throw into the caller
+            });
+        };
+    });
+
+    window.addEventListener('error', (event) => {
+        error(`Uncaught: ${event.message} at ${event.filename}:${event.lineno}`).catch(() => {});
+    });
+    window.addEventListener('unhandledrejection', (event) => {
+        error(`Unhandled rejection: ${format([event.reason])}`).catch(() => {});
+    });
+}
+
+// Zips logs, versions, database stats and settings (secrets removed) into `targetDir`
+export async function createDiagnosticsBundle(targetDir: string): Promise<string> {
+    return await invoke<string>('create_diagnostics_bundle', { targetDir });
+}
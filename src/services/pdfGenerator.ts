@@ -1,20 +1,26 @@
 import jsPDF from 'jspdf';
 import autoTable from 'jspdf-autotable';
 import { save } from '@tauri-apps/api/dialog';
-import { writeBinaryFile, BaseDirectory } from '@tauri-apps/api/fs';
+import { invoke } from '@tauri-apps/api/tauri';
+import { writeBinaryFile, readBinaryFile, BaseDirectory } from '@tauri-apps/api/fs';
 import { Invoice, CompanySettings } from '../types/invoice';
 import { numberToWordsIndian } from '../utils/numberToWords';
 import { COMPANY_DETAILS } from '../utils/constants';
+import { vaultService } from './vault';
+import { signingService } from './signing';
 
 // Module-level cache for images to avoid redundant processing/fetching
 const imageCache: Record<string, string> = {};
 
+// Writes the PDF to the generated folder and returns its file name there. Unless
+// askWhereToSave is false, the user is also offered to save a copy elsewhere.
 export async function generateInvoicePDF(
   invoice: Invoice,
   companySettings: CompanySettings,
   stampSignatureDataUrl?: string,
-  companyLogoDataUrl?: string
-): Promise<void> {
+  companyLogoDataUrl?: string,
+  askWhereToSave = true
+): Promise<string> {
   // Update cache if new data provided
   if (companyLogoDataUrl) imageCache.logo = companyLogoDataUrl;
   if (stampSignatureDataUrl) imageCache.stamp = stampSignatureDataUrl;
@@ -116,6 +122,14 @@ export async function generateInvoicePDF(
   doc.setLineWidth(0.8);
   doc.line(pageWidth / 2 - 25, currentY + 2, pageWidth / 2 + 25, currentY + 2);
 
+  // Amended invoices carry a revision marker next to the title
+  if (invoice.revision && invoice.revision > 0) {
+    doc.setFontSize(11);
+    doc.setTextColor(200, 0, 0);
+    doc.text(`REVISED (Rev. ${invoice.revision})`, pageWidth - margin, currentY, { align: 'right' });
+    doc.setTextColor(0, 0, 0);
+  }
+
   currentY += 10; // Increased spacing for prominence
 
   doc.setFontSize(9);
@@ -342,13 +356,15 @@ export async function generateInvoicePDF(
 
   currentY += 8; // Reduced spacing
 
-  // Account Details in table format
+  // Account Details in table format; the account number and IFSC live in the vault, so their
+  // cells stay blank here and the backend writes them into the saved file
   const accountDetailsData = [
     [`Name- ${companySettings.accountName}`],
     [`Bank Name- ${companySettings.bankName}`],
-    [`IFS CODE- ${companySettings.ifscCode}`],
-    [`A/C NO.- ${companySettings.accountNumber}`],
+    [''],
+    [''],
   ];
+  const bankCells: Record<string, { x: number; y: number; width: number; height: number }> = {};
 
   const accountTableY = currentY;
 
@@ -373,6 +389,11 @@ export async function generateInvoicePDF(
     columnStyles: {
       0: { cellWidth: 90, halign: 'left' }, // Reduced width
     },
+    didDrawCell: (data) => {
+      if (data.section !== 'body' || data.row.index < 2) return;
+      const { x, y, width, height } = data.cell;
+      bankCells[data.row.index === 2 ? 'ifscCode' : 'accountNumber'] = { x, y, width, height };
+    },
   });
 
   // Align signature section with account details table
@@ -393,10 +414,18 @@ export async function generateInvoicePDF(
 
   doc.setFontSize(8.5);
 
-  if (stamp) {
+  // With a DSC set up, the visible signature goes where the stamp would be
+  const signing = await signingService.getSettings();
+  const signatureBlock = { x: rightColumnX + 2, y: signatureY + 6, width: 55, height: 22 };
+  if (signing.enabled) {
+    doc.setFont('helvetica', 'bold');
+    doc.setFontSize(7.5);
+    doc.text('APEX SOLAR', signatureCenterX, signatureY + 32, { align: 'center' });
+    doc.setFontSize(8.5);
+  } else if (stamp) {
     try {
       // Move only the image right and make it wider, text stays centered on original position
-      doc.addImage(stamp, 'PNG', rightColumnX + 2, signatureY + 6, 55, 22); // Even wider signature
+      doc.addImage(stamp, 'PNG', signatureBlock.x, signatureBlock.y, signatureBlock.width, signatureBlock.height); // Even wider signature
       // APEX SOLAR - center aligned below stamp/signature, stays with original text alignment
       doc.setFont('helvetica', 'bold');
       doc.setFontSize(7.5);
@@ -414,9 +443,24 @@ export async function generateInvoicePDF(
   doc.setTextColor(0, 0, 0); // Black color for GST number
   doc.text(`GST No: ${companySettings.gstNumber}`, pageWidth / 2, currentY + 3, { align: 'center' }); // Moved closer to content
 
+  // Cancelled invoices stay printable but carry a watermark and the cancellation details
+  if (invoice.status === 'CANCELLED') {
+    const pageHeight = doc.internal.pageSize.getHeight();
+    doc.setFont('helvetica', 'bold');
+    doc.setFontSize(80);
+    doc.setTextColor(220, 0, 0);
+    doc.text('CANCELLED', pageWidth / 2, pageHeight / 2, { align: 'center', angle: 35 });
+    doc.setFontSize(10);
+    const cancelledOn = invoice.cancelledAt ? new Date(invoice.cancelledAt).toLocaleDateString('en-GB') : '';
+    doc.text(`Cancelled on ${cancelledOn}: ${invoice.cancellationReason || ''}`, pageWidth / 2, pageHeight - 8, { align: 'center' });
+    doc.setTextColor(0, 0, 0);
+  }
+
   // Convert PDF to Blob and save using Tauri's dialog
   const pdfBlob = doc.output('arraybuffer');
-  const fileName = `Invoice_AS_${invoice.financialYear}_${invoice.invoiceNumber}.pdf`;
+  const revisionSuffix = invoice.revision && invoice.revision > 0 ? `_Rev${invoice.revision}` : '';
+  const cancelledSuffix = invoice.status === 'CANCELLED' ? '_CANCELLED' : '';
+  const fileName = `Invoice_AS_${invoice.financialYear}_${invoice.invoiceNumber}${revisionSuffix}${cancelledSuffix}.pdf`;
 
   try {
     // First, save to generated folder in app data
@@ -424,6 +468,13 @@ export async function generateInvoicePDF(
     await writeBinaryFile(generatedPath, new Uint8Array(pdfBlob), {
       dir: BaseDirectory.AppData
     });
+    await vaultService.withVault(() => invoke('add_bank_details_to_pdf', { fileName, cells: bankCells }));
+    if (signing.enabled) {
+      await signingService.signGenerated(fileName, signatureBlock);
+    }
+    const pdfBytes = await readBinaryFile(generatedPath, { dir: BaseDirectory.AppData });
+
+    if (!askWhereToSave) return fileName;
 
     // Then, ask user where they want to save a copy
     const savePath = await save({
@@ -435,8 +486,9 @@ export async function generateInvoicePDF(
     });
 
     if (savePath) {
-      await writeBinaryFile(savePath, new Uint8Array(pdfBlob));
+      await writeBinaryFile(savePath, pdfBytes);
     }
+    return fileName;
   } catch (error) {
     console.error('Error saving PDF:', error);
     throw error;
@@ -0,0 +1,55 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { dbService } from './db';
+
+export type Duplex = 'none' | 'long-edge' | 'short-edge';
+export type PaperSize = 'A4' | 'Letter' | 'Legal';
+
+export interface Printer {
+    name: string;
+    // The system's default destination
+    isDefault: boolean;
+}
+
+export interface PrintOptions {
+    // Empty for the system default printer
+    printer: string;
+    copies: number;
+    duplex: Duplex;
+    paperSize: PaperSize;
+}
+
+export interface PrintJob {
+    printer: string;
+    jobId?: string | null;
+}
+
+export const DEFAULT_PRINT_OPTIONS: PrintOptions = {
+    printer: '',
+    copies: 1,
+    duplex: 'none',
+    paperSize: 'A4',
+};
+
+class PrintService {
+    async getPrinters(): Promise<Printer[]> {
+        return invoke<Printer[]>('get_printers');
+    }
+
+    // Remembered in the company settings, so each company profile keeps its own printer
+    async getDefaults(): Promise<PrintOptions> {
+        const companySettings = await dbService.getCompanySettings();
+        return { ...DEFAULT_PRINT_OPTIONS, ...(companySettings?.printing || {}) };
+    }
+
+    async saveDefaults(options: PrintOptions): Promise<void> {
+        const companySettings = (await dbService.getCompanySettings()) || {};
+        await dbService.saveCompanySettings({ ...companySettings, printing: options });
+    }
+
+    // fileName is the file name in the generated folder, as returned by generateInvoicePDF
+    async printInvoice(fileName: string, options: PrintOptions): Promise<PrintJob> {
+        return invoke<PrintJob>('print_invoice_pdf', { fileName, options });
+    }
+}
+
+export const printService = new PrintService();
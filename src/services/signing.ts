@@ -0,0 +1,88 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { dbService } from './db';
+import { vaultService } from './vault';
+
+const SIGNING_SETTINGS_KEY = 'signing_settings';
+
+export interface SigningSettings {
+    // Sign invoice PDFs as they are generated, in place of the stamp image
+    enabled: boolean;
+    // The .pfx / .p12 file the DSC was exported as
+    certificatePath: string;
+    reason: string;
+    location: string;
+}
+
+// Where the visible signature goes on the last page, in mm from the top left
+export interface SignatureBlock {
+    x: number;
+    y: number;
+    width: number;
+    height: number;
+}
+
+export interface CertificateInfo {
+    name: string;
+    subject: string;
+    issuer: string;
+    serialNumber: string;
+    validFrom: string;
+    validTo: string;
+    expired: boolean;
+}
+
+export interface SignatureReport {
+    signer: string;
+    issuer: string;
+    signedAt?: string | null;
+    // signedAt is covered by the signature rather than only written beside it
+    signedTimeInSignature: boolean;
+    reason?: string | null;
+    location?: string | null;
+    // Nothing was added to the file after this signature
+    coversWholeFile: boolean;
+    // The signed bytes match the signature
+    intact: boolean;
+    // The certificate was within its validity period at signedAt
+    certificateValid: boolean;
+    problem?: string | null;
+}
+
+export const DEFAULT_SIGNING_SETTINGS: SigningSettings = {
+    enabled: false,
+    certificatePath: '',
+    reason: 'Invoice authentication',
+    location: '',
+};
+
+class SigningService {
+    async getSettings(): Promise<SigningSettings> {
+        const stored = await dbService.getSetting(SIGNING_SETTINGS_KEY);
+        return stored ? { ...DEFAULT_SIGNING_SETTINGS, ...JSON.parse(stored) } : DEFAULT_SIGNING_SETTINGS;
+    }
+
+    async saveSettings(settings: SigningSettings): Promise<void> {
+        await dbService.saveSetting(SIGNING_SETTINGS_KEY, JSON.stringify(settings));
+    }
+
+    // Kept in the vault, never in the settings table; an empty password removes it
+    async savePassword(password: string): Promise<void> {
+        await vaultService.withVault(() => invoke('save_signing_password', { password }));
+    }
+
+    // Opens the certificate with the saved password, to check both
+    async getCertificate(): Promise<CertificateInfo> {
+        return vaultService.withVault(() => invoke<CertificateInfo>('get_signing_certificate'));
+    }
+
+    // fileName is the file name in the generated folder, as written by generateInvoicePDF
+    async signGenerated(fileName: string, block: SignatureBlock): Promise<void> {
+        await vaultService.withVault(() => invoke('sign_invoice_pdf', { fileName, block }));
+    }
+
+    async verify(path: string): Promise<SignatureReport[]> {
+        return invoke<SignatureReport[]>('verify_pdf_signature', { path });
+    }
+}
+
+export const signingService = new SigningService();
@@ -0,0 +1,99 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { dbService } from './db';
+import { backupService } from './backup';
+import { CatalogueItem } from '../types/invoice';
+import { DuplicateHandling, ImportReport } from './customerImport';
+
+const TALLY_SETTINGS_KEY = 'tally_settings';
+
+// Ledger and voucher type names as they are in the Tally company
+export interface TallyLedgers {
+    sales: string;
+    cgst: string;
+    sgst: string;
+    roundOff: string;
+    partyGroup: string;
+    voucherType: string;
+}
+
+export interface TallySettings {
+    // Tally company to import into; empty means whichever company is open in Tally
+    companyName: string;
+    ledgers: TallyLedgers;
+}
+
+export interface TallyExportReport {
+    path: string;
+    ledgers: number;
+    vouchers: number;
+    // Invoices charging CGST + SGST to a customer in another state
+    placeOfSupplyMismatches: string[];
+}
+
+export interface CatalogueItemRow {
+    line: number;
+    item: CatalogueItem;
+    action: 'create' | 'update' | 'skip' | 'invalid';
+    errors: string[];
+    warnings: string[];
+    duplicateOf?: string | null;
+}
+
+export interface TallyMastersReport {
+    customers: ImportReport;
+    items: CatalogueItemRow[];
+    itemsCreated: number;
+    itemsUpdated: number;
+    itemsSkipped: number;
+    itemsInvalid: number;
+    ignoredLedgers: number;
+    committed: boolean;
+}
+
+export const DEFAULT_TALLY_SETTINGS: TallySettings = {
+    companyName: '',
+    ledgers: {
+        sales: 'Sales',
+        cgst: 'CGST',
+        sgst: 'SGST',
+        roundOff: 'Round Off',
+        partyGroup: 'Sundry Debtors',
+        voucherType: 'Sales',
+    },
+};
+
+class TallyService {
+    async getSettings(): Promise<TallySettings> {
+        const stored = await dbService.getSetting(TALLY_SETTINGS_KEY);
+        if (!stored) return DEFAULT_TALLY_SETTINGS;
+        const parsed = JSON.parse(stored);
+        return { ...DEFAULT_TALLY_SETTINGS, ...parsed, ledgers: { ...DEFAULT_TALLY_SETTINGS.ledgers, ...parsed.ledgers } };
+    }
+
+    async saveSettings(settings: TallySettings): Promise<void> {
+        await dbService.saveSetting(TALLY_SETTINGS_KEY, JSON.stringify(settings));
+    }
+
+    // Party ledgers and sales vouchers for issued invoices dated within [from, to]
+    async exportXml(targetPath: string, settings: TallySettings, from?: string, to?: string): Promise<TallyExportReport> {
+        return invoke<TallyExportReport>('export_tally_xml', {
+            options: { from, to, companyName: settings.companyName, ledgers: settings.ledgers },
+            targetPath
+        });
+    }
+
+    // Customers from the ledgers under the party group and catalogue items from stock items,
+    // checked against what is already here. Nothing is saved unless dryRun is false.
+    async importMasters(path: string, settings: TallySettings, onDuplicate: DuplicateHandling, dryRun: boolean): Promise<TallyMastersReport> {
+        const report = await invoke<TallyMastersReport>('import_tally_masters', {
+            path,
+            partyGroup: settings.ledgers.partyGroup,
+            onDuplicate,
+            dryRun
+        });
+        if (!dryRun) backupService.notifyChange();
+        return report;
+    }
+}
+
+export const tallyService = new TallyService();
@@ -0,0 +1,48 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { dbService } from './db';
+import { backupService } from './backup';
+import { TrashItem, PurgeReport } from '../types/invoice';
+
+const RETENTION_KEY = 'trash_retention_days';
+const DEFAULT_RETENTION_DAYS = 30;
+
+export class TrashService {
+    private static instance: TrashService;
+
+    private constructor() { }
+
+    public static getInstance(): TrashService {
+        if (!TrashService.instance) {
+            TrashService.instance = new TrashService();
+        }
+        return TrashService.instance;
+    }
+
+    public async listTrash(): Promise<TrashItem[]> {
+        return await invoke<TrashItem[]>('list_trash');
+    }
+
+    public async restore(item: TrashItem): Promise<void> {
+        await invoke('restore_from_trash', { kind: item.kind, id: item.id });
+        backupService.notifyChange();
+    }
+
+    // Removes trashed records older than the retention period (0 empties the trash)
+    public async purge(retentionDays?: number): Promise<PurgeReport> {
+        const report = await invoke<PurgeReport>('purge_trash', { retentionDays: retentionDays ?? null });
+        backupService.notifyChange();
+        return report;
+    }
+
+    public async getRetentionDays(): Promise<number> {
+        const val = await dbService.getSetting(RETENTION_KEY);
+        const days = val ? parseInt(val, 10) : NaN;
+        return isNaN(days) ? DEFAULT_RETENTION_DAYS : days;
+    }
+
+    public async setRetentionDays(days: number): Promise<void> {
+        await dbService.saveSetting(RETENTION_KEY, String(days));
+    }
+}
+
+export const trashService = TrashService.getInstance();
@@ -1,16 +1,12 @@
-import { getVersion } from '@tauri-apps/api/app';
-import { http, shell, path, fs } from '@tauri-apps/api';
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
+import { shell } from '@tauri-apps/api';
 
-export interface GitHubRelease {
-    tag_name: string;
+export interface ReleaseAsset {
+    url: string;
     name: string;
-    body: string;
-    html_url: string;
-    assets: {
-        name: string;
-        browser_download_url: string;
-        size: number;
-    }[];
+    size: number;
+    sha256: string;
 }
 
 export interface UpdateInfo {
@@ -18,46 +14,46 @@ export interface UpdateInfo {
     latestVersion: string;
     hasUpdate: boolean;
     releaseNotes: string;
-    downloadUrl: string;
-    assetName: string;
+    pubDate: string | null;
+    // The installer for this computer's platform; null when the release has none
+    asset: ReleaseAsset | null;
 }
 
-class UpdateService {
-    private GITHUB_API = 'https://api.github.com/repos/ArindamTripathi619/InvoiceGeneratorDesktop/releases/latest';
-
-    async checkUpdates(): Promise<UpdateInfo> {
-        try {
-            const currentVersion = await getVersion();
-
-            const response = await http.fetch<GitHubRelease>(this.GITHUB_API, {
-                method: 'GET',
-                headers: {
-                    'User-Agent': 'Apex-Solar-Invoice-Generator'
-                }
-            });
-
-            if (!response.ok) {
-                throw new Error('Failed to fetch latest release');
-            }
+// An installer that was downloaded and matched the signed manifest's checksum
+export interface StagedUpdate {
+    version: string;
+    path: string;
+    sha256: string;
+}
 
-            const latestRelease = response.data;
-            const latestVersion = latestRelease.tag_name.replace('v', '');
+export interface UpdateProgress {
+    downloaded: number;
+    total: number;
+}
 
-            // Basic version comparison (v1.0.0 vs 1.1.0)
-            const hasUpdate = this.compareVersions(currentVersion, latestVersion) < 0;
+interface UpdateCheck {
+    currentVersion: string;
+    latestVersion: string;
+    hasUpdate: boolean;
+    notes: string;
+    pubDate: string | null;
+    asset: ReleaseAsset | null;
+}
 
-            // Find the best asset (looking for .msi or .exe for Windows)
-            const asset = latestRelease.assets.find(a =>
-                a.name.endsWith('.msi') || a.name.endsWith('.exe')
-            );
+class UpdateService {
+    private RELEASES_PAGE = 'https://github.com/ArindamTripathi619/InvoiceGeneratorDesktop/releases/latest';
 
+    // Fetches the release manifest; it is rejected unless its signature verifies
+    async checkUpdates(): Promise<UpdateInfo> {
+        try {
+            const check = await invoke<UpdateCheck>('check_for_update');
             return {
-                currentVersion,
-                latestVersion,
-                hasUpdate,
-                releaseNotes: latestRelease.body,
-                downloadUrl: asset?.browser_download_url || latestRelease.html_url,
-                assetName: asset?.name || 'latest-installer.exe'
+                currentVersion: check.currentVersion,
+                latestVersion: check.latestVersion,
+                hasUpdate: check.hasUpdate,
+                releaseNotes: check.notes,
+                pubDate: check.pubDate,
+                asset: check.asset
             };
         } catch (error) {
             console.error('Update check failed:', error);
@@ -65,57 +61,26 @@ class UpdateService {
         }
     }
 
-    private compareVersions(v1: string, v2: string): number {
-        const n1 = v1.split('-')[0].split('.').map(Number);
-        const n2 = v2.split('-')[0].split('.').map(Number);
-        const length = Math.max(n1.length, n2.length);
-
-        for (let i = 0; i < length; i++) {
-            const num1 = n1[i] || 0;
-            const num2 = n2[i] || 0;
-            if (num1 > num2) return 1;
-            if (num1 < num2) return -1;
-        }
-
-        // Handle pre-release tags (v1.0.0-beta < v1.0.0)
-        // Note: Simple logic here: if one has a tag and the other doesn't, 
-        // the one without a tag is newer (e.g. 1.0.0 vs 1.0.0-alpha)
-        if (v1.includes('-') && !v2.includes('-')) return -1;
-        if (!v1.includes('-') && v2.includes('-')) return 1;
-
-        return 0;
-    }
-
-    async downloadAndInstall(url: string, fileName: string): Promise<void> {
+    // Downloads and verifies the installer for this platform. Nothing is run yet.
+    async downloadUpdate(onProgress?: (progress: UpdateProgress) => void): Promise<StagedUpdate> {
+        const unlisten = await listen<UpdateProgress>('update-download-progress', (event) => onProgress?.(event.payload));
         try {
-            const downloadsDir = await path.downloadDir();
-            const filePath = await path.join(downloadsDir, fileName);
-
-            // Fetch the executable file
-            const response = await http.fetch(url, {
-                method: 'GET',
-                responseType: http.ResponseType.Binary
-            });
-
-            if (!response.ok) {
-                throw new Error('Failed to download installer');
-            }
-
-            // Write to local downloads folder
-            await fs.writeBinaryFile(filePath, new Uint8Array(response.data as ArrayBuffer));
-
-            // Execute the installer and exit
-            await shell.open(filePath);
-
-            // We don't relaunch immediately because the installer will usually handle closing/restarting the app
+            return await invoke<StagedUpdate>('download_update');
         } catch (error) {
-            console.error('Download/Install failed:', error);
+            console.error('Update download failed:', error);
             throw error;
+        } finally {
+            unlisten();
         }
     }
 
-    async openReleasePage(url: string) {
-        await shell.open(url);
+    // Starts the staged installer; the app closes (or restarts) so it can be replaced
+    async installUpdate(staged: StagedUpdate): Promise<void> {
+        await invoke('install_update', { staged });
+    }
+
+    async openReleasePage() {
+        await shell.open(this.RELEASES_PAGE);
     }
 }
 
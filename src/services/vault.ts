@@ -0,0 +1,71 @@
+import { invoke } from '@tauri-apps/api/tauri';
+
+export interface VaultStatus {
+    exists: boolean;
+    unlocked: boolean;
+}
+
+// Bank fields kept in the vault instead of the company settings
+export interface BankDetails {
+    accountNumber: string;
+    ifscCode: string;
+}
+
+// Left-out fields keep their stored value
+export type BankDetailsUpdate = Partial<BankDetails>;
+
+const VAULT_LOCKED = 'VAULT_LOCKED';
+
+export class VaultLockedError extends Error {
+    constructor() {
+        super('The vault is locked. Unlock it with its password to continue.');
+        this.name = 'VaultLockedError';
+    }
+}
+
+class VaultService {
+    // Shows the password prompt; resolves to whether the vault was unlocked
+    private unlockHandler: (() => Promise<boolean>) | null = null;
+
+    async getStatus(): Promise<VaultStatus> {
+        return invoke<VaultStatus>('vault_status');
+    }
+
+    // Creates the vault with this password the first time, and moves plain-text secrets into it
+    async unlock(password: string): Promise<VaultStatus> {
+        return invoke<VaultStatus>('vault_unlock', { password });
+    }
+
+    async lock(): Promise<VaultStatus> {
+        return invoke<VaultStatus>('vault_lock');
+    }
+
+    // Masked values for display; null when the vault holds no bank details
+    async getMaskedBankDetails(): Promise<BankDetails | null> {
+        return invoke<BankDetails | null>('get_masked_bank_details');
+    }
+
+    async saveBankDetails(details: BankDetailsUpdate): Promise<void> {
+        await this.withVault(() => invoke('save_bank_details', { details }));
+    }
+
+    setUnlockHandler(handler: () => Promise<boolean>): () => void {
+        this.unlockHandler = handler;
+        return () => {
+            if (this.unlockHandler === handler) this.unlockHandler = null;
+        };
+    }
+
+    // Runs `action`; if the vault is locked, asks for the password and tries once more
+    async withVault<T>(action: () => Promise<T>): Promise<T> {
+        try {
+            return await action();
+        } catch (e) {
+            if (e !== VAULT_LOCKED) throw e;
+            if (!this.unlockHandler || !(await this.unlockHandler())) throw new VaultLockedError();
+            return await action();
+        }
+    }
+}
+
+export const vaultService = new VaultService();
@@ -1,3 +1,5 @@
+import type { PrintOptions } from '../services/printing';
+
 export interface LineItem {
   id: string;
   serialNumber: number;
@@ -20,6 +22,19 @@ export interface Customer {
   pincode?: string;
   gstNumber?: string;
   panNumber?: string;
+  // One or more addresses, comma separated
+  email?: string;
+}
+
+export interface CatalogueItem {
+  id?: string;
+  name: string;
+  description?: string;
+  hsnSacCode?: string;
+  unit?: string;
+  rate?: number;
+  // Total GST in percent
+  gstRate?: number;
 }
 
 export interface Invoice {
@@ -38,6 +53,19 @@ export interface Invoice {
   sgstAmount: number;
   grandTotal: number;
   amountInWords: string;
+  revision?: number; // 0 for the original issue, bumped by every amendment
+  status?: 'DRAFT' | 'GENERATED' | 'CANCELLED';
+  cancelledAt?: string;
+  cancellationReason?: string;
+}
+
+export interface InvoiceRevision {
+  invoiceNumber: string;
+  revision: number;
+  reason: string | null;
+  grandTotal: number | null;
+  createdAt: string | null;
+  invoice: Invoice;
 }
 
 export interface CompanySettings {
@@ -49,4 +77,20 @@ export interface CompanySettings {
   gstNumber: string;
   proprietorName?: string;
   stampSignatureUrl?: string;
+  // Print options remembered for this company; see services/printing
+  printing?: PrintOptions;
+}
+
+export interface TrashItem {
+  kind: 'customer' | 'draft';
+  id: string;
+  label: string;
+  deletedAt: string;
+}
+
+export interface PurgeReport {
+  retentionDays: number;
+  customersPurged: number;
+  customersKept: number;
+  draftsPurged: number;
 }
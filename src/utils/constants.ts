@@ -1,18 +1,7 @@
-export const COMPANY_DETAILS = {
-    name: 'APEX SOLAR',
-    address: {
-        line1: 'Ramkrishna Nagar, Paschimpara',
-        line2: 'P.O.- Panchpota, P.S.- Narendrapur',
-        city: 'Kolkata',
-        pincode: '700 152',
-        fullAddress: 'Ramkrishna Nagar, Paschimpara, P.O.- Panchpota, P.S.- Narendrapur, Kolkata - 700 152',
-    },
-    contact: {
-        phone: '+91-97327 33031',
-        email: 'partha.apexsolar@gmail.com',
-    },
-    tagline: 'for green energy',
-};
+import company from './company.json';
+
+// Also printed by the backend on the invoices it renders, so it lives in a JSON file both can read
+export const COMPANY_DETAILS = company;
 
 export const FOOTER_DETAILS = {
     addressLine: 'Ramkrishna Nagar, Paschimpara, P.O.- Panchpota, P.S.- Sonarpur, Kolkata - 700 152',